@@ -1,5 +1,6 @@
-use serde::Deserialize;
-use std::env;
+use serde::{ Deserialize, Serialize };
+use serde_json::json;
+use std::{ collections::BTreeMap, env };
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -11,6 +12,14 @@ pub struct Config {
     pub jwt: JwtConfig,
     pub security: SecurityConfig,
     pub docs: DocsConfig,
+    pub notifications: NotificationConfig,
+    pub gateway: GatewayConfig,
+    pub prompts: PromptConfig,
+    pub gemini: GeminiConfig,
+    pub response_cache: ResponseCacheConfig,
+    pub latency: LatencyConfig,
+    pub body_limits: BodyLimitConfig,
+    pub branding: BrandingConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -22,7 +31,7 @@ pub struct ServerConfig {
     pub frontend_url: String,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Environment {
     Development,
@@ -33,11 +42,125 @@ pub enum Environment {
 pub struct MongoConfig {
     pub uri: String,
     pub database_name: String,
+    /// White-label tenants served from their own database, picked by the
+    /// request's `Host`. Other hosts use `database_name`.
+    pub tenants: Vec<TenantConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantConfig {
+    /// Lowercase host name without a port.
+    pub host: String,
+    pub database_name: String,
+    /// The tenant's own frontend; the default frontend when unset.
+    pub frontend_url: Option<String>,
+    pub branding: BrandingConfig,
+}
+
+/// Name, colors and contact shown to users: in the frontend (via
+/// `GET /api/branding`), emails and PDFs.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BrandingConfig {
+    pub app_name: String,
+    /// `#RRGGBB`.
+    pub primary_color: String,
+    /// `#RRGGBB`, the end of gradients.
+    pub secondary_color: String,
+    pub logo_url: Option<String>,
+    pub support_email: Option<String>,
+}
+
+impl BrandingConfig {
+    /// Reads `BRANDING_*`, or for a tenant `BRANDING_*_<TENANT>` where
+    /// `<TENANT>` is its database name in upper case (e.g. `ALIMENTIFY_ACME`).
+    /// Unset values come from `fallback`.
+    fn from_env(tenant_database: Option<&str>, fallback: Option<&BrandingConfig>) -> Result<Self, anyhow::Error> {
+        let suffix = tenant_database.map(|db| {
+            db.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect::<String>()
+        });
+        let var = |name: &str| {
+            let key = match &suffix {
+                Some(suffix) => format!("BRANDING_{}_{}", name, suffix),
+                None => format!("BRANDING_{}", name),
+            };
+            env::var(key)
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let branding = BrandingConfig {
+            app_name: var("APP_NAME")
+                .or_else(|| fallback.map(|b| b.app_name.clone()))
+                .unwrap_or_else(|| "Alimentify".to_string()),
+            primary_color: var("PRIMARY_COLOR")
+                .or_else(|| fallback.map(|b| b.primary_color.clone()))
+                .unwrap_or_else(|| "#FAB12F".to_string()),
+            secondary_color: var("SECONDARY_COLOR")
+                .or_else(|| fallback.map(|b| b.secondary_color.clone()))
+                .unwrap_or_else(|| "#FA812F".to_string()),
+            logo_url: var("LOGO_URL").or_else(|| fallback.and_then(|b| b.logo_url.clone())),
+            support_email: var("SUPPORT_EMAIL").or_else(|| fallback.and_then(|b| b.support_email.clone())),
+        };
+
+        for color in [&branding.primary_color, &branding.secondary_color] {
+            if hex_rgb(color).is_none() {
+                anyhow::bail!("Branding colors must be #RRGGBB, got {}", color);
+            }
+        }
+
+        Ok(branding)
+    }
+
+    /// `rgba(...)` of the primary color, for shadows.
+    pub fn primary_rgba(&self, alpha: f64) -> String {
+        let (r, g, b) = hex_rgb(&self.primary_color).unwrap_or((250, 177, 47));
+        format!("rgba({}, {}, {}, {})", r, g, b, alpha)
+    }
+}
+
+fn hex_rgb(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct RedisConfig {
     pub url: String,
+    /// Session checks and writes when Redis is down. Refresh tokens live only
+    /// in Redis, so issuing and redeeming them always needs it.
+    pub session_policy: RedisPolicy,
+    pub response_cache_policy: RedisPolicy,
+}
+
+/// What a Redis-backed feature does while Redis is unreachable.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisPolicy {
+    /// Carry on without Redis: skip the check, write or cached copy.
+    FailOpen,
+    /// Refuse with a 503.
+    FailClosed,
+}
+
+impl RedisPolicy {
+    fn from_env(key: &str, default: RedisPolicy) -> anyhow::Result<Self> {
+        match env::var(key) {
+            Err(_) => Ok(default),
+            Ok(value) =>
+                match value.trim().to_lowercase().as_str() {
+                    "fail_open" | "open" => Ok(RedisPolicy::FailOpen),
+                    "fail_closed" | "closed" => Ok(RedisPolicy::FailClosed),
+                    _ => anyhow::bail!("{} must be fail_open or fail_closed", key),
+                }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -60,7 +183,10 @@ pub struct BrevoConfig {
 #[derive(Debug, Clone, Deserialize)]
 pub struct JwtConfig {
     pub secret: String,
-    pub expiration_hours: i64,
+    /// Lifetime of access tokens (JWTs).
+    pub access_token_minutes: i64,
+    /// Lifetime of refresh tokens, which are stored in Redis and rotated on use.
+    pub refresh_token_days: i64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -70,6 +196,14 @@ pub struct SecurityConfig {
     pub api_key_enabled: bool,
     pub allowed_origins: Vec<String>,
     pub require_email_verification: bool,
+    /// Rejects tokens whose user has no live Redis session, so logout takes
+    /// effect immediately instead of when the token expires.
+    pub require_session: bool,
+    pub admin_emails: Vec<String>,
+    pub researcher_emails: Vec<String>,
+    /// Base64 AES-256 key for the sensitive health profile fields; usually
+    /// injected from a KMS or secret manager.
+    pub health_data_key: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -78,6 +212,94 @@ pub struct DocsConfig {
     pub password: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationConfig {
+    pub push_webhook_url: Option<String>,
+}
+
+/// Inbound message gateway for logging meals by email or WhatsApp.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GatewayConfig {
+    /// Shared secret the provider signs webhook bodies with. The webhook is
+    /// disabled without one.
+    pub inbound_secret: Option<String>,
+    /// Number users send WhatsApp messages to, shown when linking a number.
+    pub whatsapp_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptConfig {
+    pub dir: String,
+    /// Re-read the template files on every render so prompt edits apply without a restart.
+    pub hot_reload: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeminiConfig {
+    /// e.g. `gemini-3-pro-preview`.
+    pub model: String,
+    /// API root the model path is appended to, for proxies and gateways.
+    pub base_url: String,
+    /// `low` or `high` for models that support thinking levels; `None`
+    /// leaves it out of requests, for models that don't.
+    pub thinking_level: Option<String>,
+    /// `None` uses the model's default.
+    pub temperature: Option<f64>,
+    pub max_output_tokens: Option<u32>,
+    /// Per attempt, including reading the response.
+    pub timeout_seconds: u64,
+    /// Retries after a timeout, connection error, 429 or 5xx.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for each one after it.
+    pub retry_base_ms: u64,
+    /// Requests in a row that failed after their retries before the circuit
+    /// breaker opens.
+    pub breaker_threshold: u32,
+    /// How long an open breaker fails calls without trying Gemini.
+    pub breaker_cooldown_seconds: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResponseCacheConfig {
+    /// Cache allowlisted GET responses per user in Redis.
+    pub enabled: bool,
+    pub ttl_seconds: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LatencyConfig {
+    /// Record per-route response times in Redis.
+    pub enabled: bool,
+    /// p95 budget for routes without their own entry in `budgets`.
+    pub default_budget_ms: u64,
+    /// (route, budget) pairs. A route is a path pattern such as
+    /// `/api/meals/:id`, optionally prefixed by its method (`PUT /api/meals/:id`).
+    pub budgets: Vec<(String, u64)>,
+}
+
+/// Request body size limits, in bytes.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BodyLimitConfig {
+    /// Every route without a larger limit of its own.
+    pub default_bytes: usize,
+    /// Routes taking images: food analysis, logging a meal from a photo and
+    /// chat messages.
+    pub upload_bytes: usize,
+}
+
+impl LatencyConfig {
+    /// The budget for `method` on the route pattern `path`.
+    pub fn budget_for(&self, method: &str, path: &str) -> u64 {
+        let with_method = format!("{} {}", method, path);
+        self.budgets
+            .iter()
+            .find(|(route, _)| *route == with_method)
+            .or_else(|| self.budgets.iter().find(|(route, _)| route == path))
+            .map(|(_, budget)| *budget)
+            .unwrap_or(self.default_budget_ms)
+    }
+}
+
 impl Config {
     pub fn from_env() -> Result<Self, anyhow::Error> {
         dotenvy
@@ -99,6 +321,70 @@ impl Config {
             .map(|s| s.trim().to_string())
             .collect();
 
+        let admin_emails: Vec<String> = env
+            ::var("ADMIN_EMAILS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.trim().to_lowercase())
+            .collect();
+
+        let researcher_emails: Vec<String> = env
+            ::var("RESEARCHER_EMAILS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| s.trim().to_lowercase())
+            .collect();
+
+        let branding = BrandingConfig::from_env(None, None)?;
+
+        // host=database[=frontend_url] entries, e.g.
+        // "wellness.acme.com=alimentify_acme=https://wellness.acme.com,fit.globex.com=alimentify_globex"
+        let tenants = env
+            ::var("MONGODB_TENANT_DATABASES")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|entry| {
+                let mut parts = entry.splitn(3, '=').map(str::trim);
+                let (Some(host), Some(database_name)) = (parts.next(), parts.next()) else {
+                    anyhow::bail!("Invalid MONGODB_TENANT_DATABASES entry: {}", entry);
+                };
+                if host.is_empty() || database_name.is_empty() {
+                    anyhow::bail!("Invalid MONGODB_TENANT_DATABASES entry: {}", entry);
+                }
+                Ok(TenantConfig {
+                    host: host.to_lowercase(),
+                    database_name: database_name.to_string(),
+                    branding: BrandingConfig::from_env(Some(database_name), Some(&branding))?,
+                    frontend_url: parts
+                        .next()
+                        .filter(|url| !url.is_empty())
+                        .map(|url| url.trim_end_matches('/').to_string()),
+                })
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+        // route=milliseconds entries, e.g.
+        // "/api/nutrition/analyze=15000,POST /api/chat/sessions/:id/messages=20000"
+        let latency_budgets = env
+            ::var("LATENCY_BUDGETS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|entry| {
+                let Some((route, budget)) = entry.rsplit_once('=') else {
+                    anyhow::bail!("Invalid LATENCY_BUDGETS entry: {}", entry);
+                };
+                let budget = budget
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid LATENCY_BUDGETS entry: {}", entry))?;
+                Ok((route.split_whitespace().collect::<Vec<_>>().join(" "), budget))
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
         let dev_origins = env::var("DEV_FRONTEND_ORIGIN").unwrap_or_default();
         let prod_origins = env::var("PRODUCTION_FRONTEND_ORIGIN").unwrap_or_default();
 
@@ -135,9 +421,12 @@ impl Config {
                 database_name: env
                     ::var("MONGODB_DATABASE")
                     .unwrap_or_else(|_| "alimentify".to_string()),
+                tenants,
             },
             redis: RedisConfig {
                 url: env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+                session_policy: RedisPolicy::from_env("REDIS_SESSION_POLICY", RedisPolicy::FailClosed)?,
+                response_cache_policy: RedisPolicy::from_env("REDIS_CACHE_POLICY", RedisPolicy::FailOpen)?,
             },
             google_oauth: GoogleOAuthConfig {
                 client_id: env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID must be set"),
@@ -161,15 +450,19 @@ impl Config {
                 smtp_user: env::var("BREVO_SMTP_USER").expect("BREVO_SMTP_USER must be set"),
                 smtp_pass: env::var("BREVO_SMTP_PASS").expect("BREVO_SMTP_PASS must be set"),
                 from_email: env::var("BREVO_FROM_EMAIL").expect("BREVO_FROM_EMAIL must be set"),
-                from_name: env::var("BREVO_FROM_NAME").unwrap_or_else(|_| "Alimentify".to_string()),
+                from_name: env::var("BREVO_FROM_NAME").unwrap_or_else(|_| branding.app_name.clone()),
             },
             jwt: JwtConfig {
                 secret: env
                     ::var("JWT_SECRET")
                     .unwrap_or_else(|_| "your-secret-key-change-in-production".to_string()),
-                expiration_hours: env
-                    ::var("JWT_EXPIRATION_HOURS")
-                    .unwrap_or_else(|_| "24".to_string())
+                access_token_minutes: env
+                    ::var("JWT_ACCESS_TOKEN_MINUTES")
+                    .unwrap_or_else(|_| "15".to_string())
+                    .parse()?,
+                refresh_token_days: env
+                    ::var("JWT_REFRESH_TOKEN_DAYS")
+                    .unwrap_or_else(|_| "30".to_string())
                     .parse()?,
             },
             security: SecurityConfig {
@@ -182,16 +475,334 @@ impl Config {
                     .unwrap_or_else(|_| (if is_production { "true" } else { "false" }).to_string())
                     .parse()
                     .unwrap_or(is_production),
+                require_session: env
+                    ::var("REQUIRE_SESSION")
+                    .unwrap_or_else(|_| (if is_production { "true" } else { "false" }).to_string())
+                    .parse()
+                    .unwrap_or(is_production),
+                admin_emails,
+                researcher_emails,
+                health_data_key: env
+                    ::var("HEALTH_DATA_ENCRYPTION_KEY")
+                    .ok()
+                    .filter(|key| !key.trim().is_empty()),
             },
             docs: DocsConfig {
                 username: env::var("DOCS_USERNAME").unwrap_or_else(|_| "admin".to_string()),
                 password: env::var("DOCS_PASSWORD").unwrap_or_else(|_| "changeme".to_string()),
             },
+            notifications: NotificationConfig {
+                push_webhook_url: env
+                    ::var("PUSH_WEBHOOK_URL")
+                    .ok()
+                    .filter(|s| !s.is_empty()),
+            },
+            gateway: GatewayConfig {
+                inbound_secret: env
+                    ::var("GATEWAY_INBOUND_SECRET")
+                    .ok()
+                    .filter(|s| !s.is_empty()),
+                whatsapp_number: env
+                    ::var("GATEWAY_WHATSAPP_NUMBER")
+                    .ok()
+                    .filter(|s| !s.is_empty()),
+            },
+            prompts: PromptConfig {
+                dir: env::var("PROMPTS_DIR").unwrap_or_else(|_| "prompts".to_string()),
+                hot_reload: env
+                    ::var("PROMPTS_HOT_RELOAD")
+                    .unwrap_or_else(|_| (if is_production { "false" } else { "true" }).to_string())
+                    .parse()
+                    .unwrap_or(!is_production),
+            },
+            gemini: GeminiConfig {
+                model: env::var("GEMINI_MODEL").unwrap_or_else(|_| "gemini-3-pro-preview".to_string()),
+                base_url: env
+                    ::var("GEMINI_BASE_URL")
+                    .unwrap_or_else(|_| "https://generativelanguage.googleapis.com/v1beta".to_string()),
+                thinking_level: Some(
+                    env
+                        ::var("GEMINI_THINKING_LEVEL")
+                        .unwrap_or_else(|_| "low".to_string())
+                        .trim()
+                        .to_lowercase()
+                ).filter(|level| !level.is_empty() && level != "none"),
+                temperature: env
+                    ::var("GEMINI_TEMPERATURE")
+                    .ok()
+                    .filter(|s| !s.trim().is_empty())
+                    .map(|s| s.trim().parse())
+                    .transpose()?,
+                max_output_tokens: env
+                    ::var("GEMINI_MAX_OUTPUT_TOKENS")
+                    .ok()
+                    .filter(|s| !s.trim().is_empty())
+                    .map(|s| s.trim().parse())
+                    .transpose()?,
+                timeout_seconds: env
+                    ::var("GEMINI_TIMEOUT_SECONDS")
+                    .unwrap_or_else(|_| "60".to_string())
+                    .parse()?,
+                max_retries: env
+                    ::var("GEMINI_MAX_RETRIES")
+                    .unwrap_or_else(|_| "2".to_string())
+                    .parse()?,
+                retry_base_ms: env
+                    ::var("GEMINI_RETRY_BASE_MS")
+                    .unwrap_or_else(|_| "500".to_string())
+                    .parse()?,
+                breaker_threshold: env
+                    ::var("GEMINI_BREAKER_THRESHOLD")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()?,
+                breaker_cooldown_seconds: env
+                    ::var("GEMINI_BREAKER_COOLDOWN_SECONDS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()?,
+            },
+            response_cache: ResponseCacheConfig {
+                enabled: env
+                    ::var("RESPONSE_CACHE_ENABLED")
+                    .unwrap_or_else(|_| "false".to_string())
+                    .parse()
+                    .unwrap_or(false),
+                ttl_seconds: env
+                    ::var("RESPONSE_CACHE_TTL_SECONDS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()?,
+            },
+            latency: LatencyConfig {
+                enabled: env
+                    ::var("LATENCY_TRACKING_ENABLED")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .unwrap_or(true),
+                default_budget_ms: env
+                    ::var("LATENCY_BUDGET_MS")
+                    .unwrap_or_else(|_| "1000".to_string())
+                    .parse()?,
+                budgets: latency_budgets,
+            },
+            body_limits: BodyLimitConfig {
+                default_bytes: env
+                    ::var("BODY_LIMIT_MB")
+                    .unwrap_or_else(|_| "2".to_string())
+                    .parse::<usize>()? * 1024 * 1024,
+                upload_bytes: env
+                    ::var("UPLOAD_BODY_LIMIT_MB")
+                    .unwrap_or_else(|_| "25".to_string())
+                    .parse::<usize>()? * 1024 * 1024,
+            },
+            branding,
         };
 
+        if config.body_limits.default_bytes == 0 || config.body_limits.upload_bytes == 0 {
+            anyhow::bail!("BODY_LIMIT_MB and UPLOAD_BODY_LIMIT_MB must be greater than 0");
+        }
+
+        if config.gemini.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            anyhow::bail!("GEMINI_TEMPERATURE must be between 0 and 2");
+        }
+
+        // Each tenant's scheduler works on its own database, so two tenants
+        // sharing one would run every job twice.
+        let mut databases = vec![config.mongodb.database_name.as_str()];
+        let mut hosts = Vec::new();
+        for tenant in &config.mongodb.tenants {
+            if databases.contains(&tenant.database_name.as_str()) || hosts.contains(&tenant.host.as_str()) {
+                anyhow::bail!(
+                    "MONGODB_TENANT_DATABASES: each tenant needs its own host and database ({})",
+                    tenant.host
+                );
+            }
+            databases.push(&tenant.database_name);
+            hosts.push(&tenant.host);
+        }
+
         Ok(config)
     }
 
+    /// This configuration as seen by requests to `tenant`'s host: its database,
+    /// branding and frontend, and Google sign-in returning to the same host.
+    pub fn for_tenant(&self, tenant: &TenantConfig) -> Config {
+        let mut config = self.clone();
+        config.mongodb.database_name = tenant.database_name.clone();
+
+        if tenant.branding.app_name != self.branding.app_name {
+            config.brevo.from_name = tenant.branding.app_name.clone();
+        }
+        config.branding = tenant.branding.clone();
+
+        if let Some(frontend_url) = &tenant.frontend_url {
+            config.server.frontend_url = frontend_url.clone();
+            config.security.allowed_origins.retain(|origin| origin != frontend_url);
+            config.security.allowed_origins.insert(0, frontend_url.clone());
+        }
+
+        if let Ok(mut redirect_uri) = reqwest::Url::parse(&config.google_oauth.redirect_uri) {
+            if redirect_uri.set_host(Some(&tenant.host)).is_ok() {
+                config.google_oauth.redirect_uri = redirect_uri.to_string();
+            }
+        }
+
+        config
+    }
+
+    /// The database name when this is a tenant's configuration (see
+    /// [`Config::for_tenant`]), `None` for the default database.
+    pub fn tenant_database(&self) -> Option<&str> {
+        let database_name = self.mongodb.database_name.as_str();
+        self.mongodb.tenants
+            .iter()
+            .any(|tenant| tenant.database_name == database_name)
+            .then_some(database_name)
+    }
+
+    /// Switches that change what an instance does, by name.
+    pub fn features(&self) -> BTreeMap<&'static str, bool> {
+        BTreeMap::from([
+            ("cors", self.security.cors_enabled),
+            ("api_key", self.security.api_key_enabled),
+            ("email_verification", self.security.require_email_verification),
+            ("session_required", self.security.require_session),
+            ("health_data_encryption", self.security.health_data_key.is_some()),
+            ("response_cache", self.response_cache.enabled),
+            ("latency_tracking", self.latency.enabled),
+            ("prompt_hot_reload", self.prompts.hot_reload),
+            ("push_notifications", self.notifications.push_webhook_url.is_some()),
+            ("meal_gateway", self.gateway.inbound_secret.is_some()),
+            ("multi_tenant", !self.mongodb.tenants.is_empty()),
+        ])
+    }
+
+    /// The effective configuration without secrets: keys, passwords and
+    /// credentials in URLs are replaced by whether they are set.
+    pub fn redacted(&self) -> serde_json::Value {
+        json!({
+            "server": {
+                "host": self.server.host,
+                "port": self.server.port,
+                "environment": self.server.environment,
+                "frontend_url": self.server.frontend_url,
+            },
+            "mongodb": {
+                "uri": redact_url(&self.mongodb.uri),
+                "database_name": self.mongodb.database_name,
+                "tenants": self.mongodb.tenants
+                    .iter()
+                    .map(|tenant| json!({
+                        "host": tenant.host,
+                        "database_name": tenant.database_name,
+                        "frontend_url": tenant.frontend_url,
+                        "app_name": tenant.branding.app_name,
+                    }))
+                    .collect::<Vec<_>>(),
+            },
+            "redis": {
+                "url": redact_url(&self.redis.url),
+                "session_policy": self.redis.session_policy,
+                "response_cache_policy": self.redis.response_cache_policy,
+            },
+            "google_oauth": {
+                "client_id": self.google_oauth.client_id,
+                "client_secret": is_set(&self.google_oauth.client_secret),
+                "redirect_uri": self.google_oauth.redirect_uri,
+            },
+            "brevo": {
+                "smtp_host": self.brevo.smtp_host,
+                "smtp_port": self.brevo.smtp_port,
+                "smtp_user": self.brevo.smtp_user,
+                "smtp_pass": is_set(&self.brevo.smtp_pass),
+                "from_email": self.brevo.from_email,
+                "from_name": self.brevo.from_name,
+            },
+            "jwt": {
+                "secret": is_set(&self.jwt.secret),
+                "access_token_minutes": self.jwt.access_token_minutes,
+                "refresh_token_days": self.jwt.refresh_token_days,
+            },
+            "security": {
+                "api_keys": self.security.api_keys.len(),
+                "cors_enabled": self.security.cors_enabled,
+                "api_key_enabled": self.security.api_key_enabled,
+                "allowed_origins": self.security.allowed_origins,
+                "require_email_verification": self.security.require_email_verification,
+                "require_session": self.security.require_session,
+                "admin_emails": self.security.admin_emails.len(),
+                "researcher_emails": self.security.researcher_emails.len(),
+                "health_data_key": self.security.health_data_key.is_some(),
+            },
+            "docs": {
+                "username": self.docs.username,
+                "password": is_set(&self.docs.password),
+            },
+            "notifications": {
+                "push_webhook_url": self.notifications.push_webhook_url.as_deref().map(redact_url),
+            },
+            "gateway": {
+                "inbound_secret": self.gateway.inbound_secret.is_some(),
+                "whatsapp_number": self.gateway.whatsapp_number,
+            },
+            "prompts": {
+                "dir": self.prompts.dir,
+                "hot_reload": self.prompts.hot_reload,
+            },
+            "gemini": {
+                "model": self.gemini.model,
+                "base_url": redact_url(&self.gemini.base_url),
+                "thinking_level": self.gemini.thinking_level,
+                "temperature": self.gemini.temperature,
+                "max_output_tokens": self.gemini.max_output_tokens,
+                "timeout_seconds": self.gemini.timeout_seconds,
+                "max_retries": self.gemini.max_retries,
+                "retry_base_ms": self.gemini.retry_base_ms,
+                "breaker_threshold": self.gemini.breaker_threshold,
+                "breaker_cooldown_seconds": self.gemini.breaker_cooldown_seconds,
+            },
+            "response_cache": {
+                "enabled": self.response_cache.enabled,
+                "ttl_seconds": self.response_cache.ttl_seconds,
+            },
+            "latency": {
+                "enabled": self.latency.enabled,
+                "default_budget_ms": self.latency.default_budget_ms,
+                "budgets": self.latency.budgets
+                    .iter()
+                    .map(|(route, budget)| json!({ "route": route, "budget_ms": budget }))
+                    .collect::<Vec<_>>(),
+            },
+            "body_limits": {
+                "default_bytes": self.body_limits.default_bytes,
+                "upload_bytes": self.body_limits.upload_bytes,
+            },
+            "branding": self.branding,
+        })
+    }
+
+    /// Logs what the instance is running with once at startup, without secrets.
+    pub fn log_startup_banner(&self) {
+        let features = self.features();
+        let names = |enabled: bool| {
+            features
+                .iter()
+                .filter(|(_, on)| **on == enabled)
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        tracing::info!(
+            version = env!("CARGO_PKG_VERSION"),
+            environment = ?self.server.environment,
+            database = %self.mongodb.database_name,
+            tenants = self.mongodb.tenants.len(),
+            gemini_model = %self.gemini.model,
+            "Alimentify API configuration loaded"
+        );
+        tracing::info!("Enabled features: {}", names(true));
+        tracing::info!("Disabled features: {}", names(false));
+    }
+
     #[allow(dead_code)]
     pub fn is_development(&self) -> bool {
         self.server.environment == Environment::Development
@@ -202,3 +813,25 @@ impl Config {
         self.server.environment == Environment::Production
     }
 }
+
+/// `"set"` or `"unset"`, in place of a secret.
+fn is_set(secret: &str) -> &'static str {
+    if secret.is_empty() { "unset" } else { "set" }
+}
+
+/// `url` with any username, password and query string replaced by `***`.
+fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) => {
+            if !parsed.username().is_empty() || parsed.password().is_some() {
+                let _ = parsed.set_username("***");
+                let _ = parsed.set_password(None);
+            }
+            if parsed.query().is_some() {
+                parsed.set_query(Some("***"));
+            }
+            parsed.to_string()
+        }
+        Err(_) => "***".to_string(),
+    }
+}
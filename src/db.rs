@@ -1,9 +1,22 @@
-use mongodb::{ Client, Database, options::{ ClientOptions, ServerApi, ServerApiVersion } };
+use mongodb::{
+    bson::{ doc, Document },
+    error::{ ErrorKind, WriteFailure },
+    options::{ ClientOptions, IndexOptions, ServerApi, ServerApiVersion },
+    Client,
+    Database,
+    IndexModel,
+};
+use futures::stream::StreamExt;
 use redis::aio::ConnectionManager;
 use anyhow::Result;
 use std::sync::Arc;
 
 use crate::config::Config;
+use crate::field_encryption;
+use crate::services::cache_service::CacheService;
+use crate::services::live_update_service::LiveUpdates;
+use crate::services::image_service;
+use crate::services::progress_service;
 use crate::services::gemini_service::GeminiService;
 use crate::services::fdc_service::FdcService;
 use crate::services::ninja_service::NinjaService;
@@ -18,24 +31,174 @@ pub struct AppState {
     pub fdc_service: Arc<FdcService>,
     pub ninja_service: Arc<NinjaService>,
     pub mealdb_service: Arc<MealDbService>,
+    pub cache: Arc<CacheService>,
+    /// Per tenant, since a user ID means nothing in another tenant's database.
+    pub live: Arc<LiveUpdates>,
 }
 
-pub async fn setup_database(config: &Config) -> Result<Database> {
+/// One client, and its connection pool, is shared by the default database and
+/// every tenant database.
+pub async fn connect_mongodb(config: &Config) -> Result<Client> {
     let mut client_options = ClientOptions::parse(&config.mongodb.uri).await?;
 
     let server_api = ServerApi::builder().version(ServerApiVersion::V1).build();
     client_options.server_api = Some(server_api);
 
-    let client = Client::with_options(client_options)?;
-    let database = client.database(&config.mongodb.database_name);
+    Ok(Client::with_options(client_options)?)
+}
+
+/// Opens `database_name` and brings it up to date: migrations and indexes.
+pub async fn setup_database(client: &Client, database_name: &str) -> Result<Database> {
+    let database = client.database(database_name);
 
     database.list_collection_names(None).await?;
 
-    tracing::info!("Connected to MongoDB: {}", config.mongodb.database_name);
+    tracing::info!("Connected to MongoDB: {}", database_name);
+
+    migrate_date_fields(&database).await;
+    ensure_indexes(&database).await;
+    migrate_daily_progress_totals(&database).await;
+    encrypt_health_fields(&database).await;
+    move_inline_chat_images(&database).await;
 
     Ok(database)
 }
 
+/// Collections whose `date` field is range-queried and aggregated on. They
+/// used to store it as an RFC 3339 string.
+const DATE_FIELD_COLLECTIONS: [&str; 4] = ["meal_logs", "weight_logs", "exercise_logs", "daily_progress"];
+
+/// Converts string `date` fields left over from before dates were stored as
+/// BSON dates. Only touches documents that still hold a string, so after the
+/// first run this is a cheap no-op.
+async fn migrate_date_fields(database: &Database) {
+    for collection in DATE_FIELD_COLLECTIONS {
+        let result = database
+            .collection::<Document>(collection)
+            .update_many(
+                doc! { "date": { "$type": "string" } },
+                vec![doc! { "$set": { "date": { "$toDate": "$date" } } }],
+                None
+            ).await;
+
+        match result {
+            Ok(result) if result.modified_count > 0 =>
+                tracing::info!("Converted {} string dates to BSON dates in {}", result.modified_count, collection),
+            Ok(_) => {}
+            Err(e) => tracing::error!("Failed to convert string dates in {}: {}", collection, e),
+        }
+    }
+}
+
+const DAILY_PROGRESS_TOTALS_MIGRATION: &str = "daily_progress_meal_totals";
+
+/// Backfills meal totals into `daily_progress` from `meal_logs`, which used to
+/// be summed on every read. Runs once, recorded in `migrations`; needs the
+/// unique (user_id, date) index for `$merge`, so it runs after `ensure_indexes`.
+async fn migrate_daily_progress_totals(database: &Database) {
+    let migrations = database.collection::<Document>("migrations");
+    match migrations.find_one(doc! { "_id": DAILY_PROGRESS_TOTALS_MIGRATION }, None).await {
+        Ok(Some(_)) => {
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to check migration {}: {}", DAILY_PROGRESS_TOTALS_MIGRATION, e);
+            return;
+        }
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let pipeline = progress_service::rebuild_totals_pipeline(doc! {}, &now);
+
+    if let Err(e) = database.collection::<Document>("meal_logs").aggregate(pipeline, None).await {
+        tracing::error!("Failed to backfill daily progress totals: {}", e);
+        return;
+    }
+
+    // Days with only water logged.
+    if
+        let Err(e) = database
+            .collection::<Document>("daily_progress")
+            .update_many(
+                doc! { "meal_count": { "$exists": false } },
+                doc! { "$set": { "meal_count": 0_i64 } },
+                None
+            ).await
+    {
+        tracing::error!("Failed to backfill daily progress meal counts: {}", e);
+        return;
+    }
+
+    match migrations.insert_one(doc! { "_id": DAILY_PROGRESS_TOTALS_MIGRATION, "applied_at": &now }, None).await {
+        Ok(_) => tracing::info!("Backfilled daily progress totals from meal logs"),
+        Err(e) => tracing::error!("Failed to record migration {}: {}", DAILY_PROGRESS_TOTALS_MIGRATION, e),
+    }
+}
+
+/// (collection, keys, unique) for every index the queries rely on.
+fn index_specs() -> Vec<(&'static str, Document, bool)> {
+    vec![
+        ("meal_logs", doc! { "user_id": 1, "date": -1 }, false),
+        // Meal search; `$text` queries always match on user_id, so it can lead.
+        ("meal_logs", doc! { "user_id": 1, "food_name": "text", "notes": "text" }, false),
+        ("meal_history", doc! { "user_id": 1, "meal_id": 1, "created_at": 1 }, false),
+        ("weight_logs", doc! { "user_id": 1, "date": 1 }, false),
+        ("exercise_logs", doc! { "user_id": 1, "date": 1 }, false),
+        ("chat_messages", doc! { "session_id": 1, "created_at": 1 }, false),
+        ("chat_messages", doc! { "session_id": 1, "_id": -1 }, false),
+        ("chat_sessions", doc! { "user_id": 1, "updated_at": -1 }, false),
+        ("meal_reports", doc! { "user_id": 1, "generated_at": -1 }, false),
+        ("monthly_stats", doc! { "user_id": 1, "month": 1 }, true),
+        ("report_schedules", doc! { "user_id": 1, "frequency": 1 }, false),
+        ("favorite_foods", doc! { "user_id": 1, "times_logged": -1 }, false),
+        ("custom_foods", doc! { "user_id": 1, "name": 1 }, false),
+        ("meal_plans", doc! { "user_id": 1, "created_at": -1 }, false),
+        ("shopping_lists", doc! { "user_id": 1, "created_at": -1 }, false),
+        ("goal_history", doc! { "user_id": 1, "started_at": 1 }, false),
+        ("notification_outbox", doc! { "deliver_after": 1 }, false),
+        ("users", doc! { "google_id": 1 }, true),
+        ("users", doc! { "whatsapp.number": 1 }, false),
+        ("users", doc! { "gmail": 1 }, false),
+        ("api_keys", doc! { "key_hash": 1 }, true),
+        // Upserted per user and day / per user, so uniqueness keeps concurrent
+        // requests from creating duplicates.
+        ("daily_progress", doc! { "user_id": 1, "date": 1 }, true),
+        ("health_survey_drafts", doc! { "user_id": 1 }, true),
+        ("achievements", doc! { "user_id": 1 }, true),
+        // Each milestone is celebrated once; see `event_service`.
+        ("user_milestones", doc! { "user_id": 1, "milestone": 1 }, true),
+    ]
+}
+
+/// Whether a write failed on a unique index.
+pub fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    match error.kind.as_ref() {
+        ErrorKind::Command(e) => e.code == 11000,
+        ErrorKind::Write(WriteFailure::WriteError(e)) => e.code == 11000,
+        _ => false,
+    }
+}
+
+/// Creates the indexes if they don't exist yet. Creating an existing index is a
+/// no-op, so this runs on every startup. Failures are logged rather than fatal:
+/// the app still works without an index, only slower.
+async fn ensure_indexes(database: &Database) {
+    for (collection, keys, unique) in index_specs() {
+        let model = IndexModel::builder()
+            .keys(keys.clone())
+            .options(IndexOptions::builder().unique(unique).build())
+            .build();
+
+        match database.collection::<Document>(collection).create_index(model, None).await {
+            Ok(result) => tracing::debug!("Index {} ready on {}", result.index_name, collection),
+            Err(e) => tracing::error!("Failed to create index {:?} on {}: {}", keys, collection, e),
+        }
+    }
+
+    tracing::info!("MongoDB indexes ensured");
+}
+
 pub async fn setup_redis(config: &Config) -> Result<ConnectionManager> {
     tracing::info!("Attempting to connect to Redis...");
 
@@ -54,3 +217,84 @@ pub async fn setup_redis(config: &Config) -> Result<ConnectionManager> {
 
     Ok(connection)
 }
+
+/// Chat images used to be stored inline as `data:` URLs on the message.
+async fn move_inline_chat_images(database: &Database) {
+    match image_service::migrate_inline_images(database).await {
+        Ok(0) => {}
+        Ok(moved) => tracing::info!("Moved {} inline chat images to GridFS", moved),
+        Err(e) => tracing::error!("Failed to move inline chat images to GridFS: {:?}", e),
+    }
+}
+
+/// Seals sensitive health fields that are still stored in plain text, e.g.
+/// from before a key was configured. Sealed fields are strings, so only
+/// documents with a non-string value are touched.
+async fn encrypt_health_fields(database: &Database) {
+    if !field_encryption::is_enabled() {
+        return;
+    }
+
+    for (collection, prefix) in [("users", "health_profile."), ("health_survey_drafts", "")] {
+        let collection = database.collection::<Document>(collection);
+        let plain: Vec<Document> = field_encryption::SENSITIVE_FIELDS
+            .iter()
+            .map(|field| {
+                doc! { format!("{}{}", prefix, field): { "$exists": true, "$not": { "$type": ["string", "null"] } } }
+            })
+            .collect();
+
+        let mut cursor = match collection.find(doc! { "$or": plain }, None).await {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                tracing::error!("Failed to find unencrypted health fields in {}: {}", collection.name(), e);
+                continue;
+            }
+        };
+
+        let mut migrated = 0;
+        while let Some(document) = cursor.next().await {
+            let document = match document {
+                Ok(document) => document,
+                Err(e) => {
+                    tracing::error!("Failed to read {} while encrypting health fields: {}", collection.name(), e);
+                    break;
+                }
+            };
+            let Some(id) = document.get("_id").cloned() else {
+                continue;
+            };
+            let mut fields = if prefix.is_empty() {
+                document
+            } else {
+                match document.get_document("health_profile") {
+                    Ok(profile) => profile.clone(),
+                    Err(_) => {
+                        continue;
+                    }
+                }
+            };
+            if let Err(e) = field_encryption::encrypt_fields(&mut fields) {
+                tracing::error!("Failed to encrypt health fields: {}", e);
+                return;
+            }
+
+            let mut update = Document::new();
+            for field in field_encryption::SENSITIVE_FIELDS {
+                if let Some(value) = fields.get(field) {
+                    update.insert(format!("{}{}", prefix, field), value.clone());
+                }
+            }
+            match collection.update_one(doc! { "_id": id }, doc! { "$set": update }, None).await {
+                Ok(_) => {
+                    migrated += 1;
+                }
+                Err(e) => tracing::error!("Failed to store encrypted health fields: {}", e),
+            }
+        }
+
+        if migrated > 0 {
+            tracing::info!("Encrypted health fields in {} {} documents", migrated, collection.name());
+        }
+    }
+}
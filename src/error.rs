@@ -1,7 +1,10 @@
-use axum::{ http::StatusCode, response::IntoResponse, Json };
+use axum::{ http::{ header, HeaderName, StatusCode }, response::IntoResponse, Json };
 use serde_json::json;
+use std::collections::BTreeMap;
 use thiserror::Error;
 
+use crate::i18n::{ self, Locale };
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Not found: {0}")] NotFound(String),
@@ -10,29 +13,149 @@ pub enum AppError {
 
     #[error("Internal server error")] InternalError(#[from] anyhow::Error),
 
-    #[allow(dead_code)] #[error("Validation error: {0}")] ValidationError(String),
+    #[error("Validation error: {0}")] ValidationError(String),
+
+    /// Request fields out of range, by field name; see `validation::Checks`.
+    #[error("Validation failed: {0:?}")] InvalidFields(BTreeMap<String, String>),
+
+    /// The entry changed since the client read it (`If-Match` mismatch).
+    #[error("Conflict: {0}")] Conflict(String),
 
     #[error("External API unavailable: {0}")] ExternalApiError(String),
+
+    #[error("{0}")] AiRefusal(AiRefusal),
+}
+
+/// Gemini declined to answer (safety block, recitation, or no candidates).
+#[derive(Error, Debug, Clone)]
+#[error("AI response blocked: {reason}")]
+pub struct AiRefusal {
+    /// Gemini's `finishReason` / `blockReason`, e.g. `SAFETY`.
+    pub reason: String,
+}
+
+/// Gemini kept failing or timing out, or the circuit breaker is open.
+/// Reported as a 503 rather than an internal error.
+#[derive(Error, Debug, Clone)]
+#[error("AI temporarily unavailable: {reason}")]
+pub struct AiUnavailable {
+    pub reason: String,
+}
+
+pub const AI_UNAVAILABLE_MESSAGE: &str = "AI is temporarily unavailable. Please try again in a few minutes.";
+
+/// Gemini answered, but not in the shape the prompt asked for. Reported as a
+/// 503 like [`AiUnavailable`], since retrying usually helps.
+#[derive(Error, Debug, Clone)]
+#[error("Malformed AI response: {reason}")]
+pub struct AiMalformedResponse {
+    pub reason: String,
+}
+
+pub const AI_MALFORMED_MESSAGE: &str = "The AI returned an unreadable answer. Please try again.";
+
+impl AiRefusal {
+    pub fn user_message(&self) -> &'static str {
+        match self.reason.as_str() {
+            "RECITATION" =>
+                "I couldn't put together an original answer for that. Please try rephrasing your request.",
+            _ =>
+                "I can't help with that request. Please try rephrasing it or ask about food, meals, or nutrition.",
+        }
+    }
+}
+
+impl AppError {
+    /// Like the `From<anyhow::Error>` conversion, but keeps AI refusals typed so
+    /// they reach the client as a friendly message instead of a 500.
+    /// Also turns an [`AiUnavailable`] or [`AiMalformedResponse`] into a 503.
+    pub fn from_ai(e: anyhow::Error) -> Self {
+        if e.downcast_ref::<AiUnavailable>().is_some() {
+            return AppError::ExternalApiError(AI_UNAVAILABLE_MESSAGE.to_string());
+        }
+        if e.downcast_ref::<AiMalformedResponse>().is_some() {
+            return AppError::ExternalApiError(AI_MALFORMED_MESSAGE.to_string());
+        }
+        match e.downcast::<AiRefusal>() {
+            Ok(refusal) => AppError::AiRefusal(refusal),
+            Err(e) => AppError::InternalError(e),
+        }
+    }
 }
 
 impl IntoResponse for AppError {
+    /// `error` keeps the English text for existing clients; `code` is stable
+    /// and `message` is in the request's `Accept-Language`.
     fn into_response(self) -> axum::response::Response {
-        let (status, error_message) = match self {
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::ValidationError(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+        let locale = i18n::current();
+
+        if let AppError::AiRefusal(refusal) = &self {
+            let (_, message) = i18n::localize(refusal.user_message(), "ai_refusal", locale);
+            let body = Json(
+                json!({
+                "error": refusal.user_message(),
+                "error_type": "ai_refusal",
+                "code": "ai_refusal",
+                "message": message,
+                "reason": refusal.reason,
+            })
+            );
+            return (StatusCode::UNPROCESSABLE_ENTITY, content_language(locale), body).into_response();
+        }
+
+        if let AppError::InvalidFields(fields) = &self {
+            let (code, message) = i18n::localize("Validation failed", "validation_error", locale);
+            let mut field_codes = BTreeMap::new();
+            let mut field_messages = BTreeMap::new();
+            for (field, english) in fields {
+                let (field_code, field_message) = i18n::localize_field(english, locale);
+                field_codes.insert(field, field_code);
+                field_messages.insert(field, field_message);
+            }
+            let body = Json(
+                json!({
+                "error": "Validation failed",
+                "code": code,
+                "message": message,
+                "fields": field_messages,
+                "field_codes": field_codes,
+            })
+            );
+            return (StatusCode::UNPROCESSABLE_ENTITY, content_language(locale), body).into_response();
+        }
+
+        let (status, fallback_code, error_message) = match self {
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg),
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg),
+            AppError::ValidationError(msg) => (StatusCode::UNPROCESSABLE_ENTITY, "validation_error", msg),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg),
+            // Gemini outages reach here through `?` as well as `from_ai`.
+            AppError::InternalError(e) if e.downcast_ref::<AiUnavailable>().is_some() =>
+                (StatusCode::SERVICE_UNAVAILABLE, "external_api_unavailable", AI_UNAVAILABLE_MESSAGE.to_string()),
             AppError::InternalError(_) =>
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string()),
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Internal server error".to_string()),
             AppError::ExternalApiError(msg) =>
-                (StatusCode::SERVICE_UNAVAILABLE, msg),
+                (StatusCode::SERVICE_UNAVAILABLE, "external_api_unavailable", msg),
+            AppError::AiRefusal(refusal) =>
+                (StatusCode::UNPROCESSABLE_ENTITY, "ai_refusal", refusal.to_string()),
+            AppError::InvalidFields(_) =>
+                (StatusCode::UNPROCESSABLE_ENTITY, "validation_error", "Validation failed".to_string()),
         };
 
+        let (code, message) = i18n::localize(&error_message, fallback_code, locale);
+
         let body = Json(json!({
             "error": error_message,
+            "code": code,
+            "message": message,
         }));
 
-        (status, body).into_response()
+        (status, content_language(locale), body).into_response()
     }
 }
 
+fn content_language(locale: Locale) -> [(HeaderName, &'static str); 1] {
+    [(header::CONTENT_LANGUAGE, locale.tag())]
+}
+
 pub type Result<T> = std::result::Result<T, AppError>;
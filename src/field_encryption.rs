@@ -0,0 +1,168 @@
+//! Application-level encryption of medically sensitive health fields. They
+//! are sealed with AES-256-GCM before they reach MongoDB and opened while
+//! deserializing, so the rest of the code only sees plain values.
+//!
+//! A sealed field is stored as the string `enc:v1:<key id>:<base64>`, where
+//! the base64 holds the nonce followed by the ciphertext of the field's JSON.
+
+use anyhow::{ anyhow, Context, Result };
+use base64::{ engine::general_purpose, Engine as _ };
+use mongodb::bson::{ Bson, Document };
+use ring::{ aead, digest, rand::{ SecureRandom, SystemRandom } };
+use serde::{ de::DeserializeOwned, Deserialize, Deserializer };
+use std::sync::OnceLock;
+
+/// Health fields that are encrypted at rest, in health profiles and survey drafts.
+pub const SENSITIVE_FIELDS: [&str; 4] = ["medical_conditions", "blood_pressure", "fasting_blood_sugar", "allergies"];
+
+const PREFIX: &str = "enc:v1:";
+
+struct FieldKey {
+    key: aead::LessSafeKey,
+    /// First bytes of the key's SHA-256, so data sealed with another key is
+    /// reported as such instead of as corrupt.
+    id: String,
+}
+
+static KEY: OnceLock<Option<FieldKey>> = OnceLock::new();
+
+/// Sets the key from `HEALTH_DATA_ENCRYPTION_KEY` (32 bytes, base64). Called
+/// once at startup, before anything is read from MongoDB. Without a key,
+/// fields are written in plain text and sealed ones can't be read.
+pub fn init(key: Option<&str>) -> Result<()> {
+    let key = match key {
+        Some(encoded) => {
+            let bytes = general_purpose::STANDARD
+                .decode(encoded.trim())
+                .context("HEALTH_DATA_ENCRYPTION_KEY must be base64")?;
+            let unbound = aead::UnboundKey
+                ::new(&aead::AES_256_GCM, &bytes)
+                .map_err(|_| anyhow!("HEALTH_DATA_ENCRYPTION_KEY must be 32 bytes"))?;
+            let fingerprint = digest::digest(&digest::SHA256, &bytes);
+            Some(FieldKey {
+                key: aead::LessSafeKey::new(unbound),
+                id: hex(&fingerprint.as_ref()[..4]),
+            })
+        }
+        None => None,
+    };
+
+    KEY.set(key).map_err(|_| anyhow!("Field encryption is already initialized"))
+}
+
+pub fn is_enabled() -> bool {
+    key().is_some()
+}
+
+fn key() -> Option<&'static FieldKey> {
+    KEY.get().and_then(Option::as_ref)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn seal(plaintext: &[u8]) -> Result<String> {
+    let key = key().ok_or_else(|| anyhow!("No health data encryption key is configured"))?;
+
+    let mut nonce = [0u8; aead::NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("Failed to generate a nonce"))?;
+
+    let mut sealed = plaintext.to_vec();
+    key.key
+        .seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::empty(), &mut sealed)
+        .map_err(|_| anyhow!("Failed to encrypt a health field"))?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&sealed);
+    Ok(format!("{}{}:{}", PREFIX, key.id, general_purpose::STANDARD.encode(payload)))
+}
+
+fn open(envelope: &str) -> Result<Vec<u8>> {
+    let (key_id, payload) = envelope
+        .strip_prefix(PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .ok_or_else(|| anyhow!("Malformed encrypted health field"))?;
+    let key = key().ok_or_else(|| anyhow!("Health field is encrypted but no encryption key is configured"))?;
+    if key_id != key.id {
+        return Err(anyhow!("Health field was encrypted with a different key ({})", key_id));
+    }
+
+    let payload = general_purpose::STANDARD.decode(payload).context("Malformed encrypted health field")?;
+    if payload.len() < aead::NONCE_LEN {
+        return Err(anyhow!("Malformed encrypted health field"));
+    }
+    let (nonce, sealed) = payload.split_at(aead::NONCE_LEN);
+    let nonce = aead::Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Malformed encrypted health field"))?;
+
+    let mut sealed = sealed.to_vec();
+    let plaintext = key.key
+        .open_in_place(nonce, aead::Aad::empty(), &mut sealed)
+        .map_err(|_| anyhow!("Failed to decrypt a health field"))?;
+    Ok(plaintext.to_vec())
+}
+
+/// Seals every sensitive field of `document` that isn't sealed yet. Returns
+/// whether anything changed; without a key the document is left as is.
+pub fn encrypt_fields(document: &mut Document) -> Result<bool> {
+    if !is_enabled() {
+        return Ok(false);
+    }
+
+    let mut changed = false;
+    for field in SENSITIVE_FIELDS {
+        let sealed = match document.get(field) {
+            None | Some(Bson::Null) => continue,
+            Some(Bson::String(s)) if s.starts_with(PREFIX) => continue,
+            Some(value) => seal(&serde_json::to_vec(&value.clone().into_relaxed_extjson())?)?,
+        };
+        document.insert(field, sealed);
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// Opens every sealed sensitive field of a raw `document`, for code that
+/// reads documents without going through the models, like exports.
+pub fn decrypt_fields(document: &mut Document) -> Result<()> {
+    for field in SENSITIVE_FIELDS {
+        let Some(Bson::String(envelope)) = document.get(field) else {
+            continue;
+        };
+        if !envelope.starts_with(PREFIX) {
+            continue;
+        }
+        let value: serde_json::Value = serde_json::from_slice(&open(envelope)?)?;
+        document.insert(field, Bson::try_from(value)?);
+    }
+    Ok(())
+}
+
+/// The health profile as stored in MongoDB, with its sensitive fields sealed.
+pub fn profile_to_bson(profile: &crate::models::HealthProfile) -> Result<Bson> {
+    let mut document = mongodb::bson::to_document(profile)?;
+    encrypt_fields(&mut document)?;
+    Ok(Bson::Document(document))
+}
+
+/// `deserialize_with` for a sensitive field: accepts the plain value, as sent
+/// by clients or stored before encryption was enabled, or a sealed one.
+pub fn deserialize<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+    where D: Deserializer<'de>, T: DeserializeOwned
+{
+    use serde::de::Error;
+
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(serde_json::Value::String(s)) if s.starts_with(PREFIX) => {
+            let plaintext = open(&s).map_err(|e| D::Error::custom(e.to_string()))?;
+            serde_json::from_slice(&plaintext).map(Some).map_err(D::Error::custom)
+        }
+        Some(value) => serde_json::from_value(value).map(Some).map_err(D::Error::custom),
+    }
+}
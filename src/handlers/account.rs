@@ -0,0 +1,459 @@
+use axum::{
+    extract::{ Path, Query, State },
+    http::{ header, StatusCode },
+    response::{ IntoResponse, Json },
+    Extension,
+};
+use chrono::Utc;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    middleware::auth,
+    models::{ AccountMerge, Claims, MergeStatus, User },
+    services::{
+        account_data_service::{ self, DELETION_TOKEN_MINUTES },
+        account_merge_service,
+        retention_service::{ self, ALLOWED_RETENTION_MONTHS },
+    },
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StartMergeRequest {
+    /// Token obtained by signing in to the account that should be merged away.
+    pub secondary_token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AccountMergeDto {
+    pub id: String,
+    pub status: MergeStatus,
+    pub secondary_email: String,
+    pub current_step: Option<String>,
+    pub completed_steps: i32,
+    pub total_steps: i32,
+    pub progress_percent: f64,
+    pub migrated: std::collections::BTreeMap<String, i64>,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AccountMergeResponse {
+    pub success: bool,
+    pub merge: AccountMergeDto,
+}
+
+impl From<AccountMerge> for AccountMergeDto {
+    fn from(merge: AccountMerge) -> Self {
+        let progress_percent = if merge.total_steps > 0 {
+            ((merge.completed_steps as f64) / (merge.total_steps as f64)) * 100.0
+        } else {
+            0.0
+        };
+
+        Self {
+            id: merge.id.map(|id| id.to_hex()).unwrap_or_default(),
+            status: merge.status,
+            secondary_email: merge.secondary_email,
+            current_step: merge.current_step,
+            completed_steps: merge.completed_steps,
+            total_steps: merge.total_steps,
+            progress_percent,
+            migrated: merge.migrated,
+            error: merge.error,
+            created_at: merge.created_at.to_rfc3339(),
+            updated_at: merge.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/account/merge",
+    tag = "account",
+    request_body = StartMergeRequest,
+    responses((status = 202, description = "Merge started; poll it for progress", body = AccountMergeResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn start_account_merge(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<StartMergeRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let primary_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    // The same checks as the primary token: tenant, session and email.
+    let secondary_claims = auth::authenticate(&state, &payload.secondary_token).await.map_err(|response| {
+        match response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN =>
+                AppError::BadRequest("Invalid secondary account token".to_string()),
+            StatusCode::SERVICE_UNAVAILABLE =>
+                AppError::ExternalApiError("Could not check the secondary account's session".to_string()),
+            _ => AppError::InternalError(anyhow::anyhow!("Failed to check the secondary account token")),
+        }
+    })?;
+    let secondary_id = ObjectId::parse_str(&secondary_claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid secondary account token".to_string())
+    )?;
+
+    if primary_id == secondary_id {
+        return Err(AppError::BadRequest("Cannot merge an account into itself".to_string()));
+    }
+
+    let users = state.db.collection::<User>("users");
+    for user_id in [primary_id, secondary_id] {
+        users
+            .find_one(doc! { "_id": user_id }, None).await
+            .map_err(|e| AppError::InternalError(e.into()))?
+            .ok_or_else(|| AppError::NotFound("Account not found".to_string()))?;
+    }
+
+    let merges = state.db.collection::<AccountMerge>("account_merges");
+
+    let in_progress = merges
+        .find_one(
+            doc! {
+                "status": { "$in": [MergeStatus::Pending.as_str(), MergeStatus::Running.as_str()] },
+                "$or": [
+                    { "primary_user_id": { "$in": [primary_id, secondary_id] } },
+                    { "secondary_user_id": { "$in": [primary_id, secondary_id] } },
+                ]
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if in_progress.is_some() {
+        return Err(
+            AppError::BadRequest("A merge involving one of these accounts is already in progress".to_string())
+        );
+    }
+
+    let now = Utc::now();
+    let mut merge = AccountMerge {
+        id: None,
+        primary_user_id: primary_id,
+        secondary_user_id: secondary_id,
+        secondary_email: secondary_claims.email,
+        status: MergeStatus::Pending,
+        current_step: None,
+        completed_steps: 0,
+        total_steps: account_merge_service::TOTAL_STEPS,
+        migrated: Default::default(),
+        error: None,
+        created_at: now,
+        updated_at: now,
+    };
+
+    let result = merges
+        .insert_one(&merge, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let merge_id = result.inserted_id.as_object_id().unwrap();
+    merge.id = Some(merge_id);
+
+    tracing::info!("Queued account merge {} ({} -> {})", merge_id, secondary_id, primary_id);
+
+    tokio::spawn(account_merge_service::run_merge(state.clone(), merge_id));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(AccountMergeResponse {
+            success: true,
+            merge: merge.into(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/account/merge/{id}",
+    tag = "account",
+    params(("id" = String, Path, description = "Merge ID")),
+    responses(
+        (status = 200, description = "Merge progress", body = AccountMergeResponse),
+        (status = 404, description = "Merge not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_account_merge(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(merge_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let merge_oid = ObjectId::parse_str(&merge_id).map_err(|_|
+        AppError::BadRequest("Invalid merge ID".to_string())
+    )?;
+
+    let merge = state.db
+        .collection::<AccountMerge>("account_merges")
+        .find_one(doc! { "_id": merge_oid, "primary_user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Merge not found".to_string()))?;
+
+    Ok(
+        Json(AccountMergeResponse {
+            success: true,
+            merge: merge.into(),
+        })
+    )
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateRetentionRequest {
+    /// `null` keeps chat history indefinitely.
+    pub chat_retention_months: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RetentionResponse {
+    pub success: bool,
+    pub chat_retention_months: Option<i32>,
+    #[schema(value_type = Vec<i32>)]
+    pub allowed_months: [i32; 5],
+    /// Messages deleted right away by the new setting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_messages: Option<u64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/account/retention",
+    tag = "account",
+    responses((status = 200, description = "Chat retention preference", body = RetentionResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_retention(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    Ok(
+        Json(RetentionResponse {
+            success: true,
+            chat_retention_months: user.chat_retention_months,
+            allowed_months: ALLOWED_RETENTION_MONTHS,
+            deleted_messages: None,
+        })
+    )
+}
+
+/// Saves the preference and applies it immediately; the daily job keeps
+/// applying it afterwards.
+#[utoipa::path(
+    put,
+    path = "/api/account/retention",
+    tag = "account",
+    request_body = UpdateRetentionRequest,
+    responses((status = 200, description = "Preference saved and applied", body = RetentionResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn update_retention(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<UpdateRetentionRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if let Some(months) = payload.chat_retention_months {
+        if !ALLOWED_RETENTION_MONTHS.contains(&months) {
+            return Err(
+                AppError::BadRequest(
+                    format!("chat_retention_months must be one of {:?} or null", ALLOWED_RETENTION_MONTHS)
+                )
+            );
+        }
+    }
+
+    state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! { "_id": user_id },
+            doc! {
+                "$set": {
+                    "chat_retention_months": payload.chat_retention_months,
+                    "updated_at": Utc::now(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let deleted_messages = match payload.chat_retention_months {
+        Some(months) =>
+            Some(
+                retention_service
+                    ::purge_user_chat_history(&state, user_id, months).await
+                    .map_err(AppError::InternalError)?
+            ),
+        None => None,
+    };
+
+    tracing::info!("Set chat retention to {:?} months for user {}", payload.chat_retention_months, claims.sub);
+
+    Ok(
+        Json(RetentionResponse {
+            success: true,
+            chat_retention_months: payload.chat_retention_months,
+            allowed_months: ALLOWED_RETENTION_MONTHS,
+            deleted_messages,
+        })
+    )
+}
+
+/// Downloads everything stored for the account as one JSON file.
+#[utoipa::path(
+    get,
+    path = "/api/account/export",
+    tag = "account",
+    responses((status = 200, description = "Everything stored for the account, as a JSON attachment", content_type = "application/json")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn export_account_data(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let archive = account_data_service::export(&state, user_id).await?;
+    let body = serde_json::to_vec_pretty(&archive).map_err(|e| AppError::InternalError(e.into()))?;
+    let filename = format!("account-export-{}.json", Utc::now().format("%Y-%m-%d"));
+
+    tracing::info!("Exported account data for user {} ({} bytes)", claims.sub, body.len());
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    ))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeleteAccountQuery {
+    /// Token from the confirmation email. Without it, the email is sent.
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeletionRequestedResponse {
+    pub success: bool,
+    pub message: String,
+    pub expires_in_minutes: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AccountDeletedResponse {
+    pub success: bool,
+    /// Documents deleted per collection.
+    pub deleted: std::collections::BTreeMap<String, u64>,
+}
+
+/// Deleting takes two calls: the first emails a confirmation token, the
+/// second passes it back as `token` and deletes the account.
+#[utoipa::path(
+    delete,
+    path = "/api/account",
+    tag = "account",
+    params(DeleteAccountQuery),
+    responses(
+        (status = 200, description = "Account deleted", body = AccountDeletedResponse),
+        (status = 202, description = "Confirmation token emailed", body = DeletionRequestedResponse)
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<DeleteAccountQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    match query.token.as_deref() {
+        None => {
+            account_data_service::request_deletion(&state, user_id).await?;
+
+            Ok((
+                StatusCode::ACCEPTED,
+                Json(DeletionRequestedResponse {
+                    success: true,
+                    message: "Check your email to confirm deleting your account".to_string(),
+                    expires_in_minutes: DELETION_TOKEN_MINUTES,
+                }),
+            ).into_response())
+        }
+        Some(token) => {
+            let deleted = account_data_service::confirm_deletion(&state, user_id, token).await?;
+
+            Ok((
+                StatusCode::OK,
+                Json(AccountDeletedResponse {
+                    success: true,
+                    deleted,
+                }),
+            ).into_response())
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AiDataDeletedResponse {
+    pub success: bool,
+    /// Documents deleted or cleared per collection.
+    pub deleted: std::collections::BTreeMap<String, u64>,
+}
+
+/// Deletes the AI-generated data only; meals, reports and the rest of the
+/// account stay.
+#[utoipa::path(
+    delete,
+    path = "/api/users/me/ai-data",
+    tag = "account",
+    responses((status = 200, description = "AI-generated data deleted", body = AiDataDeletedResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_ai_data(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let deleted = account_data_service::delete_ai_data(&state, user_id).await?;
+
+    Ok(
+        Json(AiDataDeletedResponse {
+            success: true,
+            deleted,
+        })
+    )
+}
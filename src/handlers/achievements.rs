@@ -0,0 +1,72 @@
+use axum::{ extract::{ Query, State }, response::{ IntoResponse, Json }, Extension };
+use mongodb::bson::oid::ObjectId;
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Badge, Claims, Streak, UserAchievements },
+    services::{ achievement_service, timezone },
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AchievementsQuery {
+    /// UTC offset such as `+07:00`; see `timezone::parse`.
+    pub tz: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AchievementsDto {
+    #[schema(value_type = Object)]
+    pub logging_streak: Streak,
+    #[schema(value_type = Object)]
+    pub goal_streak: Streak,
+    #[schema(value_type = Vec<Object>)]
+    pub badges: Vec<Badge>,
+    pub updated_at: String,
+}
+
+impl From<UserAchievements> for AchievementsDto {
+    fn from(achievements: UserAchievements) -> Self {
+        AchievementsDto {
+            logging_streak: achievements.logging_streak,
+            goal_streak: achievements.goal_streak,
+            badges: achievements.badges,
+            updated_at: achievements.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AchievementsResponse {
+    pub success: bool,
+    pub achievements: AchievementsDto,
+    /// Badges earned since the last refresh, for a celebration on the client.
+    #[schema(value_type = Vec<Object>)]
+    pub new_badges: Vec<Badge>,
+}
+
+/// The user's logging and goal streaks and badges, brought up to date first.
+#[utoipa::path(
+    get,
+    path = "/api/achievements",
+    tag = "achievements",
+    params(AchievementsQuery),
+    responses((status = 200, description = "Streaks and badges, refreshed", body = AchievementsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_achievements(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<AchievementsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let offset = query.tz.as_deref().map(|tz| timezone::parse(Some(tz))).transpose()?;
+
+    let (achievements, new_badges) = achievement_service::refresh(&state, user_id, offset).await?;
+
+    Ok(Json(AchievementsResponse { success: true, achievements: achievements.into(), new_badges }))
+}
@@ -0,0 +1,332 @@
+use axum::{
+    extract::{ Path, State },
+    http::StatusCode,
+    response::{ IntoResponse, Json },
+    Extension,
+};
+use chrono::{ Duration, NaiveDate, TimeZone, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, ExerciseIntensity, ExerciseLog, HealthProfile, User },
+    services::activity_service::{ self, ActivitySuggestion },
+};
+
+const MAX_SESSION_MINUTES: f64 = 24.0 * 60.0;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogExerciseRequest {
+    pub activity: String,
+    pub duration_minutes: f64,
+    pub intensity: ExerciseIntensity,
+    pub calories_burned: Option<f64>,
+    /// `YYYY-MM-DD`; defaults to now. Past dates are recorded at noon UTC.
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExerciseLogDto {
+    pub id: String,
+    pub activity: String,
+    pub duration_minutes: f64,
+    pub intensity: ExerciseIntensity,
+    pub calories_burned: Option<f64>,
+    pub date: String,
+    pub created_at: String,
+}
+
+impl From<ExerciseLog> for ExerciseLogDto {
+    fn from(log: ExerciseLog) -> Self {
+        Self {
+            id: log.id.map(|id| id.to_hex()).unwrap_or_default(),
+            activity: log.activity,
+            duration_minutes: log.duration_minutes,
+            intensity: log.intensity,
+            calories_burned: log.calories_burned,
+            date: log.date.to_rfc3339(),
+            created_at: log.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExerciseLogResponse {
+    pub success: bool,
+    pub session: ExerciseLogDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExerciseLogsResponse {
+    pub success: bool,
+    pub sessions: Vec<ExerciseLogDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ActivitySuggestionResponse {
+    pub success: bool,
+    #[schema(value_type = Option<Object>)]
+    pub suggestion: Option<ActivitySuggestion>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApplyActivityLevelResponse {
+    pub success: bool,
+    #[schema(value_type = Object)]
+    pub profile: HealthProfile,
+    pub message: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/activity",
+    tag = "activity",
+    request_body = LogExerciseRequest,
+    responses((status = 201, description = "Exercise session logged", body = ExerciseLogResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_exercise(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<LogExerciseRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if payload.activity.trim().is_empty() {
+        return Err(AppError::BadRequest("activity cannot be empty".to_string()));
+    }
+    if payload.duration_minutes <= 0.0 || payload.duration_minutes > MAX_SESSION_MINUTES {
+        return Err(AppError::BadRequest("duration_minutes must be between 0 and 1440".to_string()));
+    }
+
+    let now = Utc::now();
+    let date = match payload.date.as_deref() {
+        Some(date) => {
+            let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid date format. Use YYYY-MM-DD".to_string())
+            )?;
+            if day > now.date_naive() {
+                return Err(AppError::BadRequest("Cannot log exercise in the future".to_string()));
+            }
+            if day == now.date_naive() {
+                now
+            } else {
+                Utc.from_utc_datetime(&day.and_hms_opt(12, 0, 0).unwrap())
+            }
+        }
+        None => now,
+    };
+
+    let mut session = ExerciseLog {
+        id: None,
+        user_id,
+        activity: payload.activity.trim().to_string(),
+        duration_minutes: payload.duration_minutes,
+        intensity: payload.intensity,
+        calories_burned: payload.calories_burned,
+        date,
+        created_at: now,
+    };
+
+    let result = state.db
+        .collection::<ExerciseLog>("exercise_logs")
+        .insert_one(&session, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    session.id = result.inserted_id.as_object_id();
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ExerciseLogResponse {
+            success: true,
+            session: session.into(),
+        }),
+    ))
+}
+
+/// Sessions from the observation window the activity suggestion uses.
+#[utoipa::path(
+    get,
+    path = "/api/activity",
+    tag = "activity",
+    responses((status = 200, description = "Recent exercise sessions", body = ExerciseLogsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_exercise_logs(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let now = Utc::now();
+    let sessions = activity_service::fetch_exercise_logs_between(
+        &state,
+        user_id,
+        now - Duration::days(activity_service::OBSERVATION_DAYS),
+        now
+    ).await?;
+
+    Ok(
+        Json(ExerciseLogsResponse {
+            success: true,
+            sessions: sessions.into_iter().rev().map(Into::into).collect(),
+        })
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/activity/{id}",
+    tag = "activity",
+    params(("id" = String, Path, description = "Exercise session ID")),
+    responses(
+        (status = 200, description = "Exercise session deleted"),
+        (status = 404, description = "Exercise session not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_exercise_log(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let session_oid = ObjectId::parse_str(&session_id).map_err(|_|
+        AppError::BadRequest("Invalid session ID".to_string())
+    )?;
+
+    let result = state.db
+        .collection::<ExerciseLog>("exercise_logs")
+        .delete_one(doc! { "_id": session_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("Exercise session not found".to_string()));
+    }
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Exercise session deleted"
+    })
+        )
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/health/activity-suggestion",
+    tag = "activity",
+    responses((status = 200, description = "Suggested activity level from logged exercise, if it differs", body = ActivitySuggestionResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_activity_suggestion(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user = load_user(&state, &claims).await?;
+    if user.health_profile.is_none() {
+        return Err(
+            AppError::NotFound(
+                "Health profile not found. Please complete the health survey.".to_string()
+            )
+        );
+    }
+
+    let suggestion = activity_service::suggest_activity_level(&state, &user).await?;
+
+    let message = match &suggestion {
+        None => "Log some exercise to get an activity level suggestion".to_string(),
+        Some(s) if s.should_update =>
+            format!(
+                "Your logged activity matches {:?} better than your current {:?} setting",
+                s.suggested_level,
+                s.current_level
+            ),
+        Some(_) => "Your activity level matches your logged exercise".to_string(),
+    };
+
+    Ok(
+        Json(ActivitySuggestionResponse {
+            success: true,
+            suggestion,
+            message,
+        })
+    )
+}
+
+/// Applies the current suggestion to the health profile and recalculates targets.
+#[utoipa::path(
+    post,
+    path = "/api/health/activity-suggestion/apply",
+    tag = "activity",
+    responses((status = 200, description = "Suggestion applied and targets recalculated", body = ApplyActivityLevelResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn apply_activity_suggestion(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user = load_user(&state, &claims).await?;
+    let user_id = user.id.ok_or_else(|| AppError::BadRequest("Invalid user ID".to_string()))?;
+    let profile = user.health_profile
+        .clone()
+        .ok_or_else(||
+            AppError::NotFound("Health profile not found. Please complete the health survey.".to_string())
+        )?;
+
+    let suggestion = activity_service
+        ::suggest_activity_level(&state, &user).await?
+        .ok_or_else(||
+            AppError::BadRequest("Not enough logged exercise to suggest an activity level".to_string())
+        )?;
+
+    if !suggestion.should_update {
+        return Err(AppError::BadRequest("Activity level already matches your logged exercise".to_string()));
+    }
+
+    let profile = activity_service::apply_activity_level(
+        &state,
+        user_id,
+        &profile,
+        suggestion.suggested_level
+    ).await?;
+
+    tracing::info!(
+        "Applied activity level {:?} to profile of user {}",
+        suggestion.suggested_level,
+        claims.sub
+    );
+
+    Ok(
+        Json(ApplyActivityLevelResponse {
+            success: true,
+            profile,
+            message: "Activity level and daily targets updated".to_string(),
+        })
+    )
+}
+
+async fn load_user(state: &AppState, claims: &Claims) -> Result<User, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
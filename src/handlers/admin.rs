@@ -0,0 +1,847 @@
+use axum::{ extract::{ Path, Query, State }, http::StatusCode, response::{ IntoResponse, Json }, Extension };
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+use std::collections::BTreeMap;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{
+        ApiKeyRecord,
+        ApiKeyScope,
+        ChatMessage,
+        Claims,
+        MessageReport,
+        MessageReportReason,
+        MessageReportStatus,
+        RecomputeJob,
+        RecomputeStatus,
+        RecomputeStep,
+        User,
+        UserResponse,
+    },
+    services::{
+        api_key_service,
+        audit_service,
+        auth_service,
+        cache_service::CacheCounters,
+        demo_service,
+        latency_service,
+        recompute_service,
+    },
+    validation::{ Checks, Validate },
+};
+
+/// The latency report covers this many minutes.
+const LATENCY_WINDOW_MINUTES: i64 = 60;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MessageReportsQuery {
+    pub status: Option<MessageReportStatus>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageReportDto {
+    pub id: String,
+    pub message_id: String,
+    pub session_id: String,
+    pub reporter_id: String,
+    pub reason: MessageReportReason,
+    pub details: Option<String>,
+    pub message_excerpt: String,
+    pub status: MessageReportStatus,
+    pub reviewed_by: Option<String>,
+    pub review_note: Option<String>,
+    pub created_at: String,
+    pub reviewed_at: Option<String>,
+}
+
+impl From<MessageReport> for MessageReportDto {
+    fn from(report: MessageReport) -> Self {
+        Self {
+            id: report.id.map(|id| id.to_hex()).unwrap_or_default(),
+            message_id: report.message_id.to_hex(),
+            session_id: report.session_id.to_hex(),
+            reporter_id: report.reporter_id.to_hex(),
+            reason: report.reason,
+            details: report.details,
+            message_excerpt: report.message_excerpt,
+            status: report.status,
+            reviewed_by: report.reviewed_by.map(|id| id.to_hex()),
+            review_note: report.review_note,
+            created_at: report.created_at.to_rfc3339(),
+            reviewed_at: report.reviewed_at.map(|d| d.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageReportsListResponse {
+    pub success: bool,
+    pub reports: Vec<MessageReportDto>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewAction {
+    Uphold,
+    Dismiss,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReviewMessageReportRequest {
+    pub action: ReviewAction,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LatencyQuery {
+    /// List every route, not only those over budget.
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RouteLatencyDto {
+    pub route: String,
+    pub requests: u64,
+    pub p95_ms: u64,
+    pub avg_ms: u64,
+    pub budget_ms: u64,
+    pub over_budget: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LatencyReportResponse {
+    pub success: bool,
+    pub window_minutes: i64,
+    pub breaching_count: usize,
+    pub routes: Vec<RouteLatencyDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CacheStatsResponse {
+    pub success: bool,
+    pub hits: u64,
+    pub misses: u64,
+    /// Share of lookups served from the cache, bypasses excluded.
+    pub hit_rate: f64,
+    #[schema(value_type = BTreeMap<String, Object>)]
+    pub by_source: BTreeMap<&'static str, CacheCounters>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateRecomputeJobRequest {
+    /// Omit to recompute every user.
+    pub user_id: Option<String>,
+    /// Defaults to every step.
+    pub steps: Option<Vec<RecomputeStep>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecomputeJobDto {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub steps: Vec<RecomputeStep>,
+    pub status: RecomputeStatus,
+    pub total_users: i64,
+    pub processed_users: i64,
+    pub updated: BTreeMap<String, i64>,
+    pub failed_users: i64,
+    pub failed_user_ids: Vec<String>,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub completed_at: Option<String>,
+}
+
+impl From<RecomputeJob> for RecomputeJobDto {
+    fn from(job: RecomputeJob) -> Self {
+        Self {
+            id: job.id.map(|id| id.to_hex()).unwrap_or_default(),
+            user_id: job.user_id.map(|id| id.to_hex()),
+            steps: job.steps,
+            status: job.status,
+            total_users: job.total_users,
+            processed_users: job.processed_users,
+            updated: job.updated,
+            failed_users: job.failed_users,
+            failed_user_ids: job.failed_user_ids
+                .iter()
+                .map(|id| id.to_hex())
+                .collect(),
+            error: job.error,
+            created_at: job.created_at.to_rfc3339(),
+            updated_at: job.updated_at.to_rfc3339(),
+            completed_at: job.completed_at.map(|d| d.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecomputeJobResponse {
+    pub success: bool,
+    pub job: RecomputeJobDto,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SeedDemoAccountRequest {
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DemoAccountResponse {
+    pub success: bool,
+    pub user: UserResponse,
+    /// Sign-in for the demo user, who has no Google account to log in with.
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+    #[schema(value_type = BTreeMap<String, usize>)]
+    pub seeded: BTreeMap<&'static str, usize>,
+}
+
+const MAX_API_KEY_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    /// Who or what the key is for, e.g. the integration using it.
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+impl Validate for CreateApiKeyRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut checks = Checks::new();
+        checks.required_text("name", &self.name, MAX_API_KEY_NAME_LENGTH);
+        if self.scopes.is_empty() {
+            checks.add("scopes", "scopes must not be empty");
+        }
+        checks.finish()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyDto {
+    pub id: String,
+    pub name: String,
+    /// Start of the key, e.g. `alk_Ab3xYz`.
+    pub key_prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_by: String,
+    pub created_at: String,
+    pub rotated_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl From<ApiKeyRecord> for ApiKeyDto {
+    fn from(record: ApiKeyRecord) -> Self {
+        Self {
+            id: record.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: record.name,
+            key_prefix: record.key_prefix,
+            scopes: record.scopes,
+            created_by: record.created_by.to_hex(),
+            created_at: record.created_at.to_rfc3339(),
+            rotated_at: record.rotated_at.map(|d| d.to_rfc3339()),
+            revoked_at: record.revoked_at.map(|d| d.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyResponse {
+    pub success: bool,
+    pub api_key: ApiKeyDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IssuedApiKeyResponse {
+    pub success: bool,
+    pub api_key: ApiKeyDto,
+    /// The key to send as `x-api-key`. Shown only in this response.
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeysListResponse {
+    pub success: bool,
+    pub api_keys: Vec<ApiKeyDto>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/cache-stats",
+    tag = "admin",
+    responses((status = 200, description = "Lookup cache hits and misses per source", body = CacheStatsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_cache_stats(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let by_source = state.cache.stats();
+    let hits: u64 = by_source.values().map(|c| c.hits).sum();
+    let misses: u64 = by_source.values().map(|c| c.misses).sum();
+    let lookups = hits + misses;
+
+    Ok(
+        Json(CacheStatsResponse {
+            success: true,
+            hits,
+            misses,
+            hit_rate: if lookups > 0 {
+                ((hits as f64) / (lookups as f64) * 1000.0).round() / 1000.0
+            } else {
+                0.0
+            },
+            by_source,
+        })
+    )
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigResponse {
+    pub success: bool,
+    #[schema(value_type = String)]
+    pub version: &'static str,
+    #[schema(value_type = BTreeMap<String, bool>)]
+    pub features: BTreeMap<&'static str, bool>,
+    #[schema(value_type = Object)]
+    pub config: serde_json::Value,
+}
+
+/// The configuration this instance is running with, for the host the request
+/// came in on. Secrets are left out; see `Config::redacted`.
+#[utoipa::path(
+    get,
+    path = "/api/admin/config",
+    tag = "admin",
+    responses((status = 200, description = "Running configuration, secrets left out", body = ConfigResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_config(State(state): State<AppState>) -> Json<ConfigResponse> {
+    Json(ConfigResponse {
+        success: true,
+        version: env!("CARGO_PKG_VERSION"),
+        features: state.config.features(),
+        config: state.config.redacted(),
+    })
+}
+
+/// Routes whose p95 over the last hour is above their budget, slowest first.
+#[utoipa::path(
+    get,
+    path = "/api/admin/latency",
+    tag = "admin",
+    params(LatencyQuery),
+    responses((status = 200, description = "Per-route latency over the last hour", body = LatencyReportResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_latency_report(
+    State(state): State<AppState>,
+    Query(query): Query<LatencyQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let stats = latency_service
+        ::route_stats(&state.redis, LATENCY_WINDOW_MINUTES).await
+        .map_err(AppError::InternalError)?;
+
+    let routes: Vec<RouteLatencyDto> = stats
+        .into_iter()
+        .map(|stat| {
+            let (method, path) = stat.route.split_once(' ').unwrap_or(("", stat.route.as_str()));
+            let budget_ms = state.config.latency.budget_for(method, path);
+            RouteLatencyDto {
+                over_budget: stat.p95_ms > budget_ms,
+                budget_ms,
+                requests: stat.requests,
+                p95_ms: stat.p95_ms,
+                avg_ms: stat.avg_ms,
+                route: stat.route,
+            }
+        })
+        .collect();
+    let breaching_count = routes
+        .iter()
+        .filter(|r| r.over_budget)
+        .count();
+
+    Ok(
+        Json(LatencyReportResponse {
+            success: true,
+            window_minutes: LATENCY_WINDOW_MINUTES,
+            breaching_count,
+            routes: routes
+                .into_iter()
+                .filter(|r| query.all || r.over_budget)
+                .collect(),
+        })
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/message-reports",
+    tag = "admin",
+    params(MessageReportsQuery),
+    responses((status = 200, description = "Reported chat messages", body = MessageReportsListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_message_reports(
+    State(state): State<AppState>,
+    Query(query): Query<MessageReportsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let status = query.status.unwrap_or(MessageReportStatus::Open);
+
+    let reports: Vec<MessageReport> = state.db
+        .collection::<MessageReport>("message_reports")
+        .find(
+            doc! { "status": status.as_str() },
+            mongodb::options::FindOptions
+                ::builder()
+                .sort(doc! { "_id": 1 })
+                .limit(query.limit.unwrap_or(50).clamp(1, 200))
+                .build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(
+        Json(MessageReportsListResponse {
+            success: true,
+            reports: reports.into_iter().map(Into::into).collect(),
+        })
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/admin/message-reports/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Message report ID")),
+    request_body = ReviewMessageReportRequest,
+    responses(
+        (status = 200, description = "Report reviewed"),
+        (status = 404, description = "Message report not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn review_message_report(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(report_id): Path<String>,
+    Json(payload): Json<ReviewMessageReportRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let admin_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let report_oid = ObjectId::parse_str(&report_id).map_err(|_|
+        AppError::BadRequest("Invalid report ID".to_string())
+    )?;
+
+    let reports = state.db.collection::<MessageReport>("message_reports");
+
+    let report = reports
+        .find_one(doc! { "_id": report_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Report not found".to_string()))?;
+
+    if report.status != MessageReportStatus::Open {
+        return Err(AppError::BadRequest("Report has already been reviewed".to_string()));
+    }
+
+    let status = match payload.action {
+        ReviewAction::Uphold => MessageReportStatus::Upheld,
+        ReviewAction::Dismiss => MessageReportStatus::Dismissed,
+    };
+
+    reports
+        .update_one(
+            doc! { "_id": report_oid },
+            doc! {
+                "$set": {
+                    "status": status.as_str(),
+                    "reviewed_by": admin_id,
+                    "review_note": &payload.note,
+                    "reviewed_at": Utc::now().to_rfc3339(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if status == MessageReportStatus::Dismissed {
+        // Only lift the flag once nobody else has an open or upheld report on the message.
+        let still_reported = reports
+            .count_documents(
+                doc! {
+                    "message_id": report.message_id,
+                    "status": { "$in": [MessageReportStatus::Open.as_str(), MessageReportStatus::Upheld.as_str()] },
+                },
+                None
+            ).await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+
+        if still_reported == 0 {
+            state.db
+                .collection::<ChatMessage>("chat_messages")
+                .update_one(
+                    doc! { "_id": report.message_id },
+                    doc! { "$set": { "flagged": false } },
+                    None
+                ).await
+                .map_err(|e| AppError::InternalError(e.into()))?;
+        }
+    }
+
+    tracing::info!("Admin {} marked message report {} as {}", claims.sub, report_id, status.as_str());
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "status": status,
+    })
+        )
+    )
+}
+
+/// Creates (or recreates) a demo user full of sample data and signs in as it.
+#[utoipa::path(
+    post,
+    path = "/api/admin/demo-account",
+    tag = "admin",
+    request_body = SeedDemoAccountRequest,
+    responses((status = 200, description = "Demo user seeded and signed in", body = DemoAccountResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn seed_demo_account(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<SeedDemoAccountRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let admin_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let email = payload.email
+        .map(|email| email.trim().to_lowercase())
+        .unwrap_or_else(|| demo_service::DEFAULT_DEMO_EMAIL.to_string());
+    if !email.contains('@') {
+        return Err(AppError::BadRequest("email must be an email address".to_string()));
+    }
+    let name = payload.name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Demo User".to_string());
+    let days = payload.days.unwrap_or(demo_service::DEFAULT_DEMO_DAYS);
+    if !(14..=demo_service::MAX_DEMO_DAYS).contains(&days) {
+        return Err(
+            AppError::BadRequest(format!("days must be between 14 and {}", demo_service::MAX_DEMO_DAYS))
+        );
+    }
+
+    let demo = demo_service::seed_demo_account(&state, &email, &name, days).await?;
+
+    let token = auth_service::generate_jwt_token(&demo.user, &state.config)?;
+    let refresh_token = auth_service::issue_refresh_token(&state.redis, &demo.user, &state.config).await?;
+    auth_service::tolerate_outage(&state.config, auth_service::store_session(&state.redis, &demo.user, &token).await)?;
+
+    audit_service::record(
+        &state,
+        admin_id,
+        "demo_account.seeded",
+        demo.user.id,
+        doc! { "email": &email, "days": days }
+    ).await;
+
+    Ok(
+        Json(DemoAccountResponse {
+            success: true,
+            user: UserResponse::from(demo.user),
+            token,
+            refresh_token,
+            expires_in: state.config.jwt.access_token_minutes * 60,
+            seeded: demo.seeded,
+        })
+    )
+}
+
+/// Queues a job rebuilding derived data (daily rollups, monthly stats,
+/// milestones, report statistics) for one user or everyone.
+#[utoipa::path(
+    post,
+    path = "/api/admin/recompute-jobs",
+    tag = "admin",
+    request_body = CreateRecomputeJobRequest,
+    responses((status = 202, description = "Job queued", body = RecomputeJobResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn create_recompute_job(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateRecomputeJobRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let admin_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let user_id = match payload.user_id.as_deref() {
+        Some(user_id) => {
+            let user_id = ObjectId::parse_str(user_id).map_err(|_|
+                AppError::BadRequest("Invalid user_id".to_string())
+            )?;
+            state.db
+                .collection::<User>("users")
+                .find_one(doc! { "_id": user_id }, None).await
+                .map_err(|e| AppError::InternalError(e.into()))?
+                .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+            Some(user_id)
+        }
+        None => None,
+    };
+
+    let mut steps = payload.steps.unwrap_or_else(|| RecomputeStep::ALL.to_vec());
+    if steps.is_empty() {
+        return Err(AppError::BadRequest("steps must not be empty".to_string()));
+    }
+    steps.sort();
+    steps.dedup();
+
+    let now = Utc::now();
+    let mut job = RecomputeJob {
+        id: None,
+        requested_by: admin_id,
+        user_id,
+        steps,
+        status: RecomputeStatus::Pending,
+        total_users: 0,
+        processed_users: 0,
+        last_user_id: None,
+        updated: BTreeMap::new(),
+        failed_user_ids: Vec::new(),
+        failed_users: 0,
+        error: None,
+        created_at: now,
+        updated_at: now,
+        completed_at: None,
+    };
+
+    let result = state.db
+        .collection::<RecomputeJob>("recompute_jobs")
+        .insert_one(&job, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let job_id = result.inserted_id.as_object_id().unwrap();
+    job.id = Some(job_id);
+
+    audit_service::record(
+        &state,
+        admin_id,
+        "recompute_job.requested",
+        Some(job_id),
+        doc! {
+            "user_id": user_id.map(|id| id.to_hex()),
+            "steps": job.steps.iter().map(RecomputeStep::as_str).collect::<Vec<_>>(),
+        }
+    ).await;
+
+    tracing::info!("Admin {} queued recompute job {}", claims.sub, job_id);
+
+    tokio::spawn(recompute_service::run_job(state.clone(), job_id));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(RecomputeJobResponse {
+            success: true,
+            job: job.into(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/recompute-jobs/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Recompute job ID")),
+    responses(
+        (status = 200, description = "Job progress", body = RecomputeJobResponse),
+        (status = 404, description = "Recompute job not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_recompute_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let job_oid = ObjectId::parse_str(&job_id).map_err(|_|
+        AppError::BadRequest("Invalid job ID".to_string())
+    )?;
+
+    let job = state.db
+        .collection::<RecomputeJob>("recompute_jobs")
+        .find_one(doc! { "_id": job_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Recompute job not found".to_string()))?;
+
+    Ok(
+        Json(RecomputeJobResponse {
+            success: true,
+            job: job.into(),
+        })
+    )
+}
+
+/// Issues a managed API key limited to `scopes`. The key is returned once;
+/// only its hash is stored.
+#[utoipa::path(
+    post,
+    path = "/api/admin/api-keys",
+    tag = "admin",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "Key issued", body = IssuedApiKeyResponse),
+        (status = 422, description = "Missing name or scopes")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateApiKeyRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let admin_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    payload.validate()?;
+
+    let mut scopes = payload.scopes;
+    scopes.sort();
+    scopes.dedup();
+
+    let (record, key) = api_key_service::create(&state, payload.name.trim(), scopes, admin_id).await?;
+
+    audit_service::record(
+        &state,
+        admin_id,
+        "api_key.created",
+        record.id,
+        doc! {
+            "name": &record.name,
+            "scopes": record.scopes.iter().map(ApiKeyScope::as_str).collect::<Vec<_>>(),
+        }
+    ).await;
+
+    tracing::info!("Admin {} issued API key {}", claims.sub, record.key_prefix);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(IssuedApiKeyResponse {
+            success: true,
+            api_key: record.into(),
+            key,
+        }),
+    ))
+}
+
+/// Every managed key, revoked ones included, newest first. Keys from
+/// `API_KEYS` aren't listed.
+#[utoipa::path(
+    get,
+    path = "/api/admin/api-keys",
+    tag = "admin",
+    responses((status = 200, description = "Managed API keys", body = ApiKeysListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_api_keys(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let api_keys = api_key_service::list(&state).await?;
+
+    Ok(
+        Json(ApiKeysListResponse {
+            success: true,
+            api_keys: api_keys.into_iter().map(ApiKeyDto::from).collect(),
+        })
+    )
+}
+
+/// Replaces a key with a new one of the same name and scopes. The old key
+/// stops working immediately.
+#[utoipa::path(
+    post,
+    path = "/api/admin/api-keys/{id}/rotate",
+    tag = "admin",
+    params(("id" = String, Path, description = "API key ID")),
+    responses(
+        (status = 200, description = "Key rotated", body = IssuedApiKeyResponse),
+        (status = 404, description = "API key not found or revoked")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn rotate_api_key(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(key_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let admin_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let key_oid = ObjectId::parse_str(&key_id).map_err(|_|
+        AppError::BadRequest("Invalid API key ID".to_string())
+    )?;
+
+    let (record, key) = api_key_service::rotate(&state, key_oid).await?;
+
+    audit_service::record(&state, admin_id, "api_key.rotated", Some(key_oid), doc! { "name": &record.name }).await;
+
+    tracing::info!("Admin {} rotated API key {}", claims.sub, key_id);
+
+    Ok(
+        Json(IssuedApiKeyResponse {
+            success: true,
+            api_key: record.into(),
+            key,
+        })
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/admin/api-keys/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "API key ID")),
+    responses(
+        (status = 200, description = "Key revoked", body = ApiKeyResponse),
+        (status = 404, description = "API key not found or already revoked")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(key_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let admin_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let key_oid = ObjectId::parse_str(&key_id).map_err(|_|
+        AppError::BadRequest("Invalid API key ID".to_string())
+    )?;
+
+    let record = api_key_service::revoke(&state, key_oid).await?;
+
+    audit_service::record(&state, admin_id, "api_key.revoked", Some(key_oid), doc! { "name": &record.name }).await;
+
+    tracing::info!("Admin {} revoked API key {}", claims.sub, key_id);
+
+    Ok(
+        Json(ApiKeyResponse {
+            success: true,
+            api_key: record.into(),
+        })
+    )
+}
@@ -5,41 +5,76 @@
     Extension,
     Json,
 };
-use chrono::Utc;
-use mongodb::bson::doc;
+use chrono::{ Duration, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
 use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
 use serde_json::json;
 
 use crate::{
     db::AppState,
     error::{ AppError, Result },
-    models::{ Claims, User, UserResponse },
+    models::{ Claims, User, UserResponse, UserRole },
     services::{ auth_service, email_service },
+    validation::{ Checks, Validate },
 };
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct GoogleCallbackQuery {
     pub code: String,
-    #[allow(dead_code)]
+    /// The `state` of the consent URL, echoed back by Google. Required.
     pub state: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AuthUrlResponse {
     pub auth_url: String,
+    /// CSRF token carried in `auth_url`. Keep it to check that the callback
+    /// comes back with the same one; the backend checks it as well.
+    pub state: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/auth/google",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Google consent screen URL to send the user to", body = AuthUrlResponse),
+        (status = 503, description = "Redis unavailable")
+    ),
+    security(())
+)]
 pub async fn google_auth_url(State(state): State<AppState>) -> Result<Json<AuthUrlResponse>> {
-    let auth_url = auth_service::generate_google_auth_url(&state.config)?;
-    Ok(Json(AuthUrlResponse { auth_url }))
+    let (auth_url, oauth_state) = auth_service::generate_google_auth_url(&state.redis, &state.config).await?;
+    Ok(Json(AuthUrlResponse { auth_url, state: oauth_state }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/auth/google/callback",
+    tag = "auth",
+    params(GoogleCallbackQuery),
+    responses(
+        (status = 303, description = "Redirect to the frontend with a one-time sign-in code"),
+        (status = 400, description = "Missing, unknown, expired or already used `state`")
+    ),
+    security(())
+)]
 pub async fn google_callback(
     State(state): State<AppState>,
     Query(query): Query<GoogleCallbackQuery>
 ) -> Result<impl IntoResponse> {
     tracing::info!("Google callback received with code");
 
+    // Checked before the code is used, so a forged callback gets nowhere.
+    let oauth_state = query.state
+        .as_deref()
+        .ok_or_else(|| AppError::BadRequest("Invalid or expired OAuth state".to_string()))?;
+    auth_service::consume_oauth_state(&state.redis, oauth_state, &state.config).await.map_err(|e| {
+        tracing::warn!("Rejected Google callback with a bad state: {}", e);
+        e
+    })?;
+
     let google_user = auth_service
         ::exchange_code_for_user(&query.code, &state.config).await
         .map_err(|e| {
@@ -54,9 +89,18 @@ pub async fn google_callback(
     tracing::info!("Searching for existing user with google_id: {}", google_user.id);
 
     let is_new_user: bool;
+    let mut verification_link_renewed = false;
 
     let user = match
-        users_collection.find_one(doc! { "google_id": &google_user.id }, None).await
+        users_collection.find_one(
+            doc! {
+                "$or": [
+                    { "google_id": &google_user.id },
+                    { "linked_google_ids": &google_user.id },
+                ]
+            },
+            None
+        ).await
     {
         Ok(Some(mut user)) => {
             is_new_user = false;
@@ -69,7 +113,7 @@ pub async fn google_callback(
 
             tracing::info!("Updating user profile for user_id: {}", user_id);
 
-            let update_doc = doc! {
+            let mut update_doc = doc! {
                 "$set": {
                     "profile_image": &google_user.picture,
                     "name": &google_user.name,
@@ -77,6 +121,23 @@ pub async fn google_callback(
                 }
             };
 
+            // Signing in again is how an unverified user gets a new link once
+            // theirs expired.
+            let link_expired = user.email_verification_token
+                .as_deref()
+                .and_then(|token| auth_service::verification_token_claims(&state.config, token))
+                .is_none_or(|(_, expires_at)| expires_at < Utc::now());
+            if !user.email_verification_status && link_expired {
+                let token = auth_service::generate_verification_token(&state.config, user_id);
+                if let Ok(set) = update_doc.get_document_mut("$set") {
+                    set.insert("email_verification_token", &token);
+                    set.insert("email_verification_sent_at", Utc::now());
+                }
+                user.email_verification_token = Some(token);
+                user.email_verification_sent_at = Some(Utc::now());
+                verification_link_renewed = true;
+            }
+
             users_collection
                 .update_one(doc! { "_id": user_id }, update_doc, None).await
                 .map_err(|e| {
@@ -91,6 +152,19 @@ pub async fn google_callback(
             user.updated_at = Utc::now();
             user.id = Some(user_id); 
 
+            if verification_link_renewed {
+                if
+                    let Err(e) = email_service::send_verification_email(
+                        &state.config,
+                        &user.gmail,
+                        &user.name,
+                        user.email_verification_token.as_deref().unwrap_or_default()
+                    ).await
+                {
+                    tracing::error!("Failed to send verification email: {}", e);
+                }
+            }
+
             user
         }
         Ok(None) => {
@@ -98,10 +172,11 @@ pub async fn google_callback(
             tracing::info!("No existing user found, creating new user");
             let username = google_user.email.split('@').next().unwrap_or("user").to_string();
 
-            let verification_token = auth_service::generate_verification_token();
+            let new_user_id = ObjectId::new();
+            let verification_token = auth_service::generate_verification_token(&state.config, new_user_id);
 
             let new_user = User {
-                id: None,
+                id: Some(new_user_id),
                 google_id: google_user.id.clone(),
                 profile_image: google_user.picture.clone(),
                 username,
@@ -109,9 +184,16 @@ pub async fn google_callback(
                 gmail: google_user.email.clone(),
                 email_verification_status: false,
                 email_verification_token: Some(verification_token.clone()),
+                email_verification_sent_at: Some(Utc::now()),
                 email_verified_at: None,
                 health_profile: None,
                 has_completed_health_survey: Some(false),
+                linked_google_ids: Vec::new(),
+                role: UserRole::User,
+                chat_retention_months: None,
+                notification_preferences: None,
+                widget_token_id: None,
+                whatsapp: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
             };
@@ -161,24 +243,6 @@ pub async fn google_callback(
         return Err(AppError::InternalError(anyhow::anyhow!("User has no ID")));
     }
 
-    tracing::info!("Generating JWT token for user: {}", user.gmail);
-
-    let token = auth_service::generate_jwt_token(&user, &state.config).map_err(|e| {
-        tracing::error!("Failed to generate JWT token: {}", e);
-        e
-    })?;
-
-    tracing::info!("JWT token generated successfully");
-
-    tracing::info!("Storing session in Redis");
-
-    auth_service::store_session(&state.redis, &user, &token).await.map_err(|e| {
-        tracing::error!("Failed to store session in Redis: {}", e);
-        e
-    })?;
-
-    tracing::info!("Session stored successfully for user: {}", user.gmail);
-
     let frontend_url = if state.config.is_production() {
         state.config.security.allowed_origins
             .first()
@@ -188,25 +252,249 @@ pub async fn google_callback(
         "http://localhost:3000".to_string()
     };
 
-    let redirect_url = if is_new_user {
+    let redirect_url = if is_new_user || verification_link_renewed {
         format!("{}/auth/check-email?email={}", frontend_url, urlencoding::encode(&user.gmail))
     } else {
-        format!("{}/?token={}", frontend_url, token)
+        // Tokens are only handed out by `exchange_auth_code`, over the API,
+        // to keep them out of browser history and server logs.
+        let code = auth_service::issue_auth_code(&state.redis, &user, &state.config).await.map_err(|e| {
+            tracing::error!("Failed to issue sign-in code: {}", e);
+            e
+        })?;
+        format!("{}/?code={}", frontend_url, code)
     };
 
-    tracing::info!("Redirecting user {} to {}", user.gmail, redirect_url);
+    tracing::info!("Redirecting user {} to the frontend", user.gmail);
 
     Ok(Redirect::to(&redirect_url))
 }
 
+/// Ends the session and revokes every refresh token of the user. Access
+/// tokens already issued stop working when `REQUIRE_SESSION` is on, and
+/// otherwise stay valid until they expire.
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    tag = "auth",
+    responses((status = 204, description = "Signed out")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn logout(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>
 ) -> Result<StatusCode> {
-    auth_service::delete_session(&state.redis, &claims.sub).await?;
+    auth_service::tolerate_outage(&state.config, auth_service::delete_session(&state.redis, &claims.sub).await)?;
+    auth_service::tolerate_outage(
+        &state.config,
+        auth_service::revoke_refresh_tokens(&state.redis, &claims.sub).await
+    )?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+impl Validate for ResendVerificationRequest {
+    fn validate(&self) -> Result<()> {
+        let mut checks = Checks::new();
+        checks.required_text("email", &self.email, 254);
+        if !self.email.contains('@') {
+            checks.add("email", "email must be an email address");
+        }
+        checks.finish()
+    }
+}
+
+/// Sends a new verification link to an unverified account, replacing (and so
+/// invalidating) the previous one. Answers 202 whether or not the email
+/// belongs to an unverified account, so it can't be used to look up
+/// accounts; a request within a minute of the last link sends nothing.
+#[utoipa::path(
+    post,
+    path = "/api/auth/resend-verification",
+    tag = "auth",
+    request_body = ResendVerificationRequest,
+    responses(
+        (status = 202, description = "A link was sent if the email belongs to an unverified account"),
+        (status = 422, description = "Not an email address")
+    )
+)]
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<ResendVerificationRequest>
+) -> Result<StatusCode> {
+    payload.validate()?;
+    let email = payload.email.trim().to_lowercase();
+    let users_collection = state.db.collection::<User>("users");
+
+    let user = users_collection
+        .find_one(doc! { "gmail": &email, "email_verification_status": false }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    let Some(user) = user else {
+        return Ok(StatusCode::ACCEPTED);
+    };
+    let user_id = user.id.ok_or_else(|| AppError::InternalError(anyhow::anyhow!("User has no ID")))?;
+
+    let cooldown = Duration::seconds(auth_service::VERIFICATION_RESEND_COOLDOWN_SECONDS);
+    if user.email_verification_sent_at.is_some_and(|sent_at| Utc::now() - sent_at < cooldown) {
+        tracing::info!("Skipping verification resend for user {}: sent too recently", user_id);
+        return Ok(StatusCode::ACCEPTED);
+    }
+
+    let token = auth_service::generate_verification_token(&state.config, user_id);
+    let result = users_collection
+        .update_one(
+            doc! { "_id": user_id, "email_verification_status": false },
+            doc! {
+                "$set": {
+                    "email_verification_token": &token,
+                    "email_verification_sent_at": Utc::now(),
+                    "updated_at": Utc::now(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    if result.matched_count == 0 {
+        return Ok(StatusCode::ACCEPTED);
+    }
+
+    if let Err(e) = email_service::send_verification_email(&state.config, &user.gmail, &user.name, &token).await {
+        tracing::error!("Failed to send verification email: {}", e);
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExchangeAuthCodeRequest {
+    /// The `code` the Google callback redirected to the frontend with.
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExchangeAuthCodeResponse {
+    pub success: bool,
+    pub token: String,
+    pub refresh_token: String,
+    /// Seconds until `token` expires.
+    pub expires_in: i64,
+    pub user: UserResponse,
+}
+
+/// Trades the one-time code from the Google callback for an access token and a
+/// refresh token. Each code works once, for a minute.
+#[utoipa::path(
+    post,
+    path = "/api/auth/exchange",
+    tag = "auth",
+    request_body = ExchangeAuthCodeRequest,
+    responses(
+        (status = 200, description = "Signed in", body = ExchangeAuthCodeResponse),
+        (status = 400, description = "Unknown, used or expired code")
+    )
+)]
+pub async fn exchange_auth_code(
+    State(state): State<AppState>,
+    Json(payload): Json<ExchangeAuthCodeRequest>
+) -> Result<Json<ExchangeAuthCodeResponse>> {
+    let invalid = || AppError::BadRequest("Invalid or expired authorization code".to_string());
+    let user_id = auth_service::consume_auth_code(&state.redis, &payload.code, &state.config).await?;
+
+    let object_id = ObjectId::parse_str(&user_id).map_err(|_| invalid())?;
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": object_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(invalid)?;
+
+    let token = auth_service::generate_jwt_token(&user, &state.config)?;
+    let refresh_token = auth_service::issue_refresh_token(&state.redis, &user, &state.config).await?;
+    auth_service::tolerate_outage(&state.config, auth_service::store_session(&state.redis, &user, &token).await)?;
+
+    tracing::info!("Signed in user {} with a sign-in code", user.gmail);
+
+    Ok(
+        Json(ExchangeAuthCodeResponse {
+            success: true,
+            token,
+            refresh_token,
+            expires_in: state.config.jwt.access_token_minutes * 60,
+            user: UserResponse::from(user),
+        })
+    )
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RefreshTokenResponse {
+    pub success: bool,
+    pub token: String,
+    pub refresh_token: String,
+    /// Seconds until `token` expires.
+    pub expires_in: i64,
+}
+
+/// Trades a refresh token for a new access token and a new refresh token. The
+/// old refresh token stops working.
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    tag = "auth",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "New access and refresh tokens", body = RefreshTokenResponse),
+        (status = 400, description = "Unknown, used or expired refresh token")
+    )
+)]
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshTokenRequest>
+) -> Result<Json<RefreshTokenResponse>> {
+    let user_id = auth_service::consume_refresh_token(
+        &state.redis,
+        &payload.refresh_token,
+        &state.config
+    ).await?;
+
+    let object_id = mongodb::bson::oid::ObjectId
+        ::parse_str(&user_id)
+        .map_err(|_| AppError::BadRequest("Invalid or expired refresh token".to_string()))?;
+
+    // Merged or deleted accounts can't refresh.
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": object_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::BadRequest("Invalid or expired refresh token".to_string()))?;
+
+    let token = auth_service::generate_jwt_token(&user, &state.config)?;
+    let refresh_token = auth_service::issue_refresh_token(&state.redis, &user, &state.config).await?;
+    auth_service::tolerate_outage(&state.config, auth_service::store_session(&state.redis, &user, &token).await)?;
+
+    Ok(
+        Json(RefreshTokenResponse {
+            success: true,
+            token,
+            refresh_token,
+            expires_in: state.config.jwt.access_token_minutes * 60,
+        })
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/me",
+    tag = "auth",
+    responses((status = 200, description = "The signed-in user", body = UserResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_current_user(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>
@@ -225,30 +513,66 @@ pub async fn get_current_user(
     Ok(Json(user.into()))
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct VerifyEmailQuery {
     pub token: String,
 }
 
+/// Verifies the email with the link's token. A forged or replaced token is a
+/// 404 (`invalid_verification_token`), an expired one a 400
+/// (`verification_token_expired`) and one whose email is already verified a
+/// 409 (`verification_token_used`).
+#[utoipa::path(
+    get,
+    path = "/api/auth/verify-email",
+    tag = "auth",
+    params(VerifyEmailQuery),
+    responses(
+        (status = 200, description = "Email verified"),
+        (status = 400, description = "Token expired"),
+        (status = 404, description = "Invalid token"),
+        (status = 409, description = "Email already verified")
+    ),
+    security(())
+)]
 pub async fn verify_email(
     State(state): State<AppState>,
     Query(query): Query<VerifyEmailQuery>
 ) -> Result<Json<serde_json::Value>> {
     let users_collection = state.db.collection::<User>("users");
+    let invalid = || AppError::NotFound("Invalid verification token".to_string());
+    let used = || AppError::Conflict("Email already verified".to_string());
+
+    let (user_id, expires_at) = auth_service
+        ::verification_token_claims(&state.config, &query.token)
+        .ok_or_else(invalid)?;
 
     let mut user = users_collection
-        .find_one(doc! { "email_verification_token": &query.token }, None).await
+        .find_one(doc! { "_id": user_id }, None).await
         .map_err(|e| AppError::InternalError(e.into()))?
-        .ok_or_else(|| AppError::NotFound("Invalid verification token".to_string()))?;
+        .ok_or_else(invalid)?;
+
+    if user.email_verification_status {
+        return Err(used());
+    }
+    if user.email_verification_token.as_deref() != Some(query.token.as_str()) {
+        return Err(invalid());
+    }
+    if expires_at < Utc::now() {
+        return Err(AppError::BadRequest("Verification link has expired".to_string()));
+    }
 
-    users_collection
+    // Only the request that clears the token verifies, so a replayed link
+    // racing the first one gets the same answer as a later replay.
+    let result = users_collection
         .update_one(
-            doc! { "_id": user.id },
+            doc! { "_id": user_id, "email_verification_token": &query.token },
             doc! {
                 "$set": {
                     "email_verification_status": true,
                     "email_verified_at": Utc::now(),
                     "email_verification_token": null,
+                    "email_verification_sent_at": null,
                     "updated_at": Utc::now(),
                 }
             },
@@ -256,27 +580,24 @@ pub async fn verify_email(
         ).await
         .map_err(|e| AppError::InternalError(e.into()))?;
 
+    if result.matched_count == 0 {
+        return Err(used());
+    }
+
     user.email_verification_status = true;
     user.email_verified_at = Some(Utc::now());
     user.email_verification_token = None;
+    user.email_verification_sent_at = None;
 
     let token = auth_service::generate_jwt_token(&user, &state.config)?;
+    let refresh_token = auth_service::issue_refresh_token(&state.redis, &user, &state.config).await?;
 
-    auth_service::store_session(&state.redis, &user, &token).await?;
+    auth_service::tolerate_outage(&state.config, auth_service::store_session(&state.redis, &user, &token).await)?;
 
     Ok(Json(json!({
         "message": "Email verified successfully",
         "token": token,
+        "refresh_token": refresh_token,
         "user": UserResponse::from(user)
     })))
 }
-
-pub async fn debug_config(State(state): State<AppState>) -> Json<serde_json::Value> {
-    Json(
-        json!({
-        "google_client_id": state.config.google_oauth.client_id,
-        "google_redirect_uri": state.config.google_oauth.redirect_uri,
-        "environment": format!("{:?}", state.config.server.environment),
-    })
-    )
-}
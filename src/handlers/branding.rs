@@ -0,0 +1,26 @@
+use axum::{ extract::State, response::Json };
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{ config::BrandingConfig, db::AppState };
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BrandingResponse {
+    pub success: bool,
+    #[schema(value_type = Object)]
+    pub branding: BrandingConfig,
+}
+
+/// Public, so the frontend can theme its sign-in page. Tenants get their own.
+#[utoipa::path(
+    get,
+    path = "/api/branding",
+    tag = "branding",
+    responses((status = 200, description = "Branding of the tenant serving the request", body = BrandingResponse))
+)]
+pub async fn get_branding(State(state): State<AppState>) -> Json<BrandingResponse> {
+    Json(BrandingResponse {
+        success: true,
+        branding: state.config.branding.clone(),
+    })
+}
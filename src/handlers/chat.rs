@@ -1,6 +1,7 @@
-use axum::{ extract::{ Path, State }, response::{ IntoResponse, Json }, Extension };
-use mongodb::bson::{ doc, oid::ObjectId };
+use axum::{ extract::{ Path, Query, State }, http::StatusCode, response::{ IntoResponse, Json }, Extension };
+use mongodb::bson::{ doc, oid::ObjectId, Bson, Document };
 use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
 use chrono::Utc;
 use futures::stream::TryStreamExt;
 use std::sync::Arc;
@@ -8,22 +9,93 @@ use std::sync::Arc;
 use crate::{
     db::AppState,
     error::AppError,
-    models::{ Claims, ChatSession, ChatMessage, MessageRole },
-    services::{ email_service::EmailService, chat_agent_service::ChatAgentService },
+    models::{
+        Claims,
+        ChatSession,
+        ChatMessage,
+        MessageRole,
+        MessageReport,
+        MessageReportReason,
+        MessageReportStatus,
+    },
+    services::{
+        email_service::EmailService,
+        chat_agent_service::ChatAgentService,
+        image_service,
+        quick_action_service::{ self, QuickAction },
+        timezone,
+    },
+    validation::{ Checks, Validate },
 };
 
-#[derive(Debug, Deserialize)]
+/// Page size when `limit` isn't given, and the most one page may hold.
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 100;
+
+/// Cursor pagination: `before` is the `next_cursor` of the previous page.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PageQuery {
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl PageQuery {
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    fn before(&self) -> Result<Option<ObjectId>, AppError> {
+        self.before
+            .as_deref()
+            .map(|before| ObjectId::parse_str(before).map_err(|_| AppError::BadRequest("Invalid cursor".to_string())))
+            .transpose()
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateChatRequest {
     pub initial_message: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+/// Longest title a user can give a session.
+const MAX_TITLE_LENGTH: usize = 100;
+/// Longest message a user can send, in characters.
+const MAX_MESSAGE_LENGTH: usize = 4000;
+
+impl Validate for CreateChatRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        Checks::new()
+            .optional_max_length("initial_message", self.initial_message.as_deref(), MAX_MESSAGE_LENGTH)
+            .finish()
+    }
+}
+
+/// Body of `PATCH /api/chat/sessions/:id`: either a new `title`, or
+/// `regenerate_title` to have the AI title the session again.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateChatSessionRequest {
+    pub title: Option<String>,
+    #[serde(default)]
+    pub regenerate_title: bool,
+}
+
+impl Validate for UpdateChatSessionRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut checks = Checks::new();
+        if let Some(title) = &self.title {
+            checks.required_text("title", title.trim(), MAX_TITLE_LENGTH);
+        }
+        checks.finish()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatSessionResponse {
     pub success: bool,
     pub session: ChatSessionDto,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatSessionDto {
     pub id: String,
     pub title: String,
@@ -32,13 +104,15 @@ pub struct ChatSessionDto {
     pub message_count: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatSessionsListResponse {
     pub success: bool,
     pub sessions: Vec<ChatSessionDto>,
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SendMessageRequest {
     pub message: String,
     #[serde(default)]
@@ -47,15 +121,31 @@ pub struct SendMessageRequest {
     pub mime_type: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+/// The text may be empty when an image is attached.
+impl Validate for SendMessageRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut checks = Checks::new();
+        if self.image_data.is_some() {
+            checks.max_length("message", &self.message, MAX_MESSAGE_LENGTH);
+        } else {
+            checks.required_text("message", &self.message, MAX_MESSAGE_LENGTH);
+        }
+        checks.finish()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SendMessageResponse {
     pub success: bool,
     pub user_message: ChatMessageDto,
     pub assistant_message: ChatMessageDto,
     pub session: ChatSessionDto,
+    /// Gemini's block reason when the assistant reply is a safety fallback.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatMessageDto {
     pub id: String,
     pub role: String,
@@ -66,25 +156,37 @@ pub struct ChatMessageDto {
     pub created_at: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ToolCallDto {
     pub tool_name: String,
+    #[schema(value_type = Object)]
     pub parameters: serde_json::Value,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ToolResultDto {
     pub tool_name: String,
+    #[schema(value_type = Object)]
     pub result: serde_json::Value,
     pub success: bool,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatMessagesResponse {
     pub success: bool,
     pub messages: Vec<ChatMessageDto>,
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/chat/sessions",
+    tag = "chat",
+    request_body = CreateChatRequest,
+    responses((status = 200, description = "Session created", body = ChatSessionResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn create_chat_session(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -94,6 +196,8 @@ pub async fn create_chat_session(
         AppError::BadRequest("Invalid user ID".to_string())
     )?;
 
+    payload.validate()?;
+
     tracing::info!("Creating new chat session for user: {}", claims.sub);
 
     let gemini = state.gemini_service.clone();
@@ -104,7 +208,8 @@ pub async fn create_chat_session(
             state.config.brevo.smtp_user.clone(),
             state.config.brevo.smtp_pass.clone(),
             state.config.brevo.from_email.clone(),
-            state.config.brevo.from_name.clone()
+            state.config.brevo.from_name.clone(),
+            state.config.branding.clone()
         )
     );
     let agent = ChatAgentService::new(gemini.clone(), email_service);
@@ -151,21 +256,55 @@ pub async fn create_chat_session(
     )
 }
 
+/// Most recently active first. `before` is a session ID; the page continues
+/// after that session in the same order.
+#[utoipa::path(
+    get,
+    path = "/api/chat/sessions",
+    tag = "chat",
+    params(PageQuery),
+    responses((status = 200, description = "A page of sessions, most recently active first", body = ChatSessionsListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_chat_sessions(
     State(state): State<AppState>,
-    Extension(claims): Extension<Claims>
+    Extension(claims): Extension<Claims>,
+    Query(page): Query<PageQuery>
 ) -> Result<impl IntoResponse, AppError> {
     let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
         AppError::BadRequest("Invalid user ID".to_string())
     )?;
+    let limit = page.limit();
+
+    let mut filter = doc! { "user_id": user_id };
+    if let Some(before) = page.before()? {
+        let anchor = state.db
+            .collection::<Document>("chat_sessions")
+            .find_one(doc! { "_id": before, "user_id": user_id }, None).await
+            .map_err(|e| AppError::InternalError(e.into()))?
+            .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))?;
+        let updated_at = anchor.get("updated_at").cloned().unwrap_or(Bson::Null);
+
+        let mut after_anchor = vec![
+            doc! { "updated_at": { "$lt": updated_at.clone() } },
+            doc! { "updated_at": updated_at.clone(), "_id": { "$lt": before } }
+        ];
+        // New sessions store `updated_at` as a string until their first
+        // message; descending sorts put strings after dates.
+        if matches!(updated_at, Bson::DateTime(_)) {
+            after_anchor.push(doc! { "updated_at": { "$type": "string" } });
+        }
+        filter.insert("$or", after_anchor);
+    }
 
     let mut cursor = state.db
         .collection::<ChatSession>("chat_sessions")
         .find(
-            doc! { "user_id": user_id },
+            filter,
             mongodb::options::FindOptions
                 ::builder()
-                .sort(doc! { "updated_at": -1 })
+                .sort(doc! { "updated_at": -1, "_id": -1 })
+                .limit(limit + 1)
                 .build()
         ).await
         .map_err(|e| AppError::InternalError(e.into()))?;
@@ -183,14 +322,31 @@ pub async fn get_chat_sessions(
         });
     }
 
+    let has_more = sessions.len() as i64 > limit;
+    sessions.truncate(limit as usize);
+    let next_cursor = if has_more { sessions.last().map(|s| s.id.clone()) } else { None };
+
     Ok(
         Json(ChatSessionsListResponse {
             success: true,
             sessions,
+            has_more,
+            next_cursor,
         })
     )
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/chat/sessions/{id}",
+    tag = "chat",
+    params(("id" = String, Path, description = "Chat session ID")),
+    responses(
+        (status = 200, description = "The session", body = ChatSessionResponse),
+        (status = 404, description = "Chat session not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_chat_session(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -224,6 +380,18 @@ pub async fn get_chat_session(
     )
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/chat/sessions/{id}/messages",
+    tag = "chat",
+    params(("id" = String, Path, description = "Chat session ID")),
+    request_body = SendMessageRequest,
+    responses(
+        (status = 200, description = "The message and the assistant's reply", body = SendMessageResponse),
+        (status = 404, description = "Chat session not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn send_message(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -237,6 +405,7 @@ pub async fn send_message(
     let session_oid = ObjectId::parse_str(&session_id).map_err(|_|
         AppError::BadRequest("Invalid session ID".to_string())
     )?;
+    payload.validate()?;
 
     let session = state.db
         .collection::<ChatSession>("chat_sessions")
@@ -247,7 +416,8 @@ pub async fn send_message(
     tracing::info!("Sending message in session: {}", session_id);
 
     let mut message_content = payload.message.clone();
-    let mut image_data_url: Option<String> = None;
+    let mut image_id: Option<ObjectId> = None;
+    let mut image_prompt_version: Option<String> = None;
     let gemini = state.gemini_service.clone();
 
     if
@@ -258,18 +428,26 @@ pub async fn send_message(
     {
         tracing::info!("Processing image in chat message");
 
-        image_data_url = Some(format!("data:{};base64,{}", mime_type, image_data));
+        if !image_service::is_allowed_type(mime_type) {
+            return Err(
+                AppError::BadRequest(
+                    format!("mime_type must be one of: {}", image_service::ALLOWED_IMAGE_TYPES.join(", "))
+                )
+            );
+        }
 
         use base64::{ engine::general_purpose, Engine as _ };
         let image_bytes = general_purpose::STANDARD
             .decode(image_data)
             .map_err(|e| AppError::BadRequest(format!("Invalid image data: {}", e)))?;
 
-        let analysis = gemini
+        let (analysis, prompt_version) = gemini
             .analyze_food_image(&image_bytes, mime_type).await
-            .map_err(|e| AppError::InternalError(e))?;
+            .map_err(AppError::from_ai)?;
 
-        message_content = format!("{}\n\n[Image Analysis]\n{}", message_content, analysis);
+        image_prompt_version = Some(prompt_version);
+        message_content = format!("{}\n\n[Image Analysis]\n{}", message_content, analysis.to_json());
+        image_id = Some(image_service::store_image(&state.db, &image_bytes, mime_type).await?);
     }
 
     let user_message_time = Utc::now();
@@ -279,9 +457,12 @@ pub async fn send_message(
         user_id,
         role: MessageRole::User,
         content: message_content.clone(),
-        image_url: image_data_url.clone(),
+        image_url: None,
+        image_id,
         tool_calls: None,
         tool_results: None,
+        flagged: false,
+        prompt_version: image_prompt_version,
         created_at: user_message_time,
     };
 
@@ -295,7 +476,7 @@ pub async fn send_message(
     let mut cursor = state.db
         .collection::<ChatMessage>("chat_messages")
         .find(
-            doc! { "session_id": session_oid },
+            doc! { "session_id": session_oid, "flagged": { "$ne": true } },
             mongodb::options::FindOptions
                 ::builder()
                 .sort(doc! { "created_at": 1 })
@@ -316,17 +497,28 @@ pub async fn send_message(
             state.config.brevo.smtp_user.clone(),
             state.config.brevo.smtp_pass.clone(),
             state.config.brevo.from_email.clone(),
-            state.config.brevo.from_name.clone()
+            state.config.brevo.from_name.clone(),
+            state.config.branding.clone()
         )
     );
     let agent = ChatAgentService::new(state.gemini_service.clone(), email_service);
 
-    let (response_text, tool_calls, tool_results) = agent
+    let result = agent
         .process_message(&state, user_id, session_oid, &message_content, history).await
         .map_err(|e| {
             tracing::error!("AI agent processing failed: {}", e);
-            AppError::InternalError(e)
-        })?;
+            AppError::from_ai(e)
+        });
+
+    // A refused reply is still stored so the conversation reads naturally.
+    let (response_text, tool_calls, tool_results, prompt_version, refusal) = match result {
+        Ok((text, calls, results, version)) => (text, calls, results, Some(version), None),
+        Err(AppError::AiRefusal(refusal)) =>
+            (refusal.user_message().to_string(), vec![], vec![], None, Some(refusal.reason)),
+        Err(e) => {
+            return Err(e);
+        }
+    };
 
     let assistant_message = ChatMessage {
         id: None,
@@ -335,6 +527,7 @@ pub async fn send_message(
         role: MessageRole::Assistant,
         content: response_text.clone(),
         image_url: None,
+        image_id: None,
         tool_calls: if tool_calls.is_empty() {
             None
         } else {
@@ -345,6 +538,8 @@ pub async fn send_message(
         } else {
             Some(tool_results.clone())
         },
+        flagged: false,
+        prompt_version,
         created_at: Utc::now(),
     };
 
@@ -431,7 +626,7 @@ pub async fn send_message(
         id: user_message_id.to_hex(),
         role: "user".to_string(),
         content: message_content.clone(),
-        image_url: image_data_url,
+        image_url: image_id.map(|id| image_service::signed_url(&state.config, id)),
         tool_calls: None,
         tool_results: None,
         created_at: user_message_time.to_rfc3339(),
@@ -478,14 +673,35 @@ pub async fn send_message(
             user_message: user_message_dto,
             assistant_message: assistant_message_dto,
             session: updated_session,
+            refusal,
         })
     )
 }
 
+/// A fresh signed URL for the message's image, or the inline `data:` URL of
+/// messages stored before images moved to GridFS.
+fn message_image_url(state: &AppState, message: &ChatMessage) -> Option<String> {
+    match message.image_id {
+        Some(id) => Some(image_service::signed_url(&state.config, id)),
+        None => message.image_url.clone(),
+    }
+}
+
+/// The latest messages, oldest first. `before` is a message ID; the page holds
+/// the messages sent before it.
+#[utoipa::path(
+    get,
+    path = "/api/chat/sessions/{id}/messages",
+    tag = "chat",
+    params(("id" = String, Path, description = "Chat session ID"), PageQuery),
+    responses((status = 200, description = "A page of messages, oldest first", body = ChatMessagesResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_chat_messages(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
-    Path(session_id): Path<String>
+    Path(session_id): Path<String>,
+    Query(page): Query<PageQuery>
 ) -> Result<impl IntoResponse, AppError> {
     let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
         AppError::BadRequest("Invalid user ID".to_string())
@@ -501,24 +717,40 @@ pub async fn get_chat_messages(
         .map_err(|e| AppError::InternalError(e.into()))?
         .ok_or_else(|| AppError::NotFound("Chat session not found".to_string()))?;
 
-    let mut cursor = state.db
+    let limit = page.limit();
+    let mut filter = doc! { "session_id": session_oid };
+    if let Some(before) = page.before()? {
+        filter.insert("_id", doc! { "$lt": before });
+    }
+
+    // Newest first to take the page, then flipped for display.
+    let mut page_messages: Vec<ChatMessage> = state.db
         .collection::<ChatMessage>("chat_messages")
         .find(
-            doc! { "session_id": session_oid },
+            filter,
             mongodb::options::FindOptions
                 ::builder()
-                .sort(doc! { "created_at": 1 })
+                .sort(doc! { "_id": -1 })
+                .limit(limit + 1)
                 .build()
         ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
         .map_err(|e| AppError::InternalError(e.into()))?;
 
+    let has_more = page_messages.len() as i64 > limit;
+    page_messages.truncate(limit as usize);
+    page_messages.reverse();
+    let next_cursor = if has_more { page_messages.first().and_then(|m| m.id).map(|id| id.to_hex()) } else { None };
+
     let mut messages = Vec::new();
-    while let Some(msg) = cursor.try_next().await.map_err(|e| AppError::InternalError(e.into()))? {
+    for msg in page_messages {
+        let image_url = message_image_url(&state, &msg);
         messages.push(ChatMessageDto {
             id: msg.id.unwrap().to_hex(),
             role: format!("{:?}", msg.role).to_lowercase(),
             content: msg.content,
-            image_url: msg.image_url,
+            image_url,
             tool_calls: msg.tool_calls.map(|calls| {
                 calls
                     .iter()
@@ -546,10 +778,123 @@ pub async fn get_chat_messages(
         Json(ChatMessagesResponse {
             success: true,
             messages,
+            has_more,
+            next_cursor,
+        })
+    )
+}
+
+/// Renames a session, or regenerates its title from the first message. Doesn't
+/// change `updated_at`, so the session keeps its place in the list.
+#[utoipa::path(
+    patch,
+    path = "/api/chat/sessions/{id}",
+    tag = "chat",
+    params(("id" = String, Path, description = "Chat session ID")),
+    request_body = UpdateChatSessionRequest,
+    responses(
+        (status = 200, description = "Session renamed", body = ChatSessionResponse),
+        (status = 404, description = "Chat session not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn update_chat_session(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<String>,
+    Json(payload): Json<UpdateChatSessionRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let session_oid = ObjectId::parse_str(&session_id).map_err(|_|
+        AppError::BadRequest("Invalid session ID".to_string())
+    )?;
+    payload.validate()?;
+
+    let sessions = state.db.collection::<ChatSession>("chat_sessions");
+    sessions
+        .find_one(doc! { "_id": session_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Chat session not found".to_string()))?;
+
+    let title = match (payload.title, payload.regenerate_title) {
+        (Some(title), false) => title.trim().to_string(),
+        (None, true) => {
+            let first_message = state.db
+                .collection::<ChatMessage>("chat_messages")
+                .find_one(
+                    doc! {
+                        "session_id": session_oid,
+                        "role": "user",
+                        "flagged": { "$ne": true },
+                    },
+                    mongodb::options::FindOneOptions::builder().sort(doc! { "_id": 1 }).build()
+                ).await
+                .map_err(|e| AppError::InternalError(e.into()))?
+                .ok_or_else(|| AppError::BadRequest("The session has no messages to title it from".to_string()))?;
+
+            let email_service = Arc::new(
+                EmailService::new(
+                    state.config.brevo.smtp_host.clone(),
+                    state.config.brevo.smtp_port,
+                    state.config.brevo.smtp_user.clone(),
+                    state.config.brevo.smtp_pass.clone(),
+                    state.config.brevo.from_email.clone(),
+                    state.config.brevo.from_name.clone(),
+                    state.config.branding.clone()
+                )
+            );
+            let agent = ChatAgentService::new(state.gemini_service.clone(), email_service);
+            agent
+                .generate_chat_title(&first_message.content).await
+                .map_err(|e| AppError::ExternalApiError(format!("Failed to generate title: {}", e)))?
+        }
+        _ => {
+            return Err(AppError::BadRequest("Send either title or regenerate_title: true".to_string()));
+        }
+    };
+
+    let session = sessions
+        .find_one_and_update(
+            doc! { "_id": session_oid, "user_id": user_id },
+            doc! { "$set": { "title": &title } },
+            mongodb::options::FindOneAndUpdateOptions
+                ::builder()
+                .return_document(mongodb::options::ReturnDocument::After)
+                .build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Chat session not found".to_string()))?;
+
+    tracing::info!("Retitled chat session {} for user {}", session_id, claims.sub);
+
+    Ok(
+        Json(ChatSessionResponse {
+            success: true,
+            session: ChatSessionDto {
+                id: session.id.unwrap().to_hex(),
+                title: session.title,
+                created_at: session.created_at.to_rfc3339(),
+                updated_at: session.updated_at.to_rfc3339(),
+                message_count: session.message_count,
+            },
         })
     )
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/chat/sessions/{id}",
+    tag = "chat",
+    params(("id" = String, Path, description = "Chat session ID")),
+    responses(
+        (status = 200, description = "Session and its messages deleted"),
+        (status = 404, description = "Chat session not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn delete_chat_session(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -572,6 +917,8 @@ pub async fn delete_chat_session(
         return Err(AppError::NotFound("Chat session not found".to_string()));
     }
 
+    image_service::delete_message_images(&state.db, doc! { "session_id": session_oid }).await?;
+
     state.db
         .collection::<ChatMessage>("chat_messages")
         .delete_many(doc! { "session_id": session_oid }, None).await
@@ -586,3 +933,145 @@ pub async fn delete_chat_session(
         )
     )
 }
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReportMessageRequest {
+    pub reason: MessageReportReason,
+    pub details: Option<String>,
+}
+
+impl Validate for ReportMessageRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        Checks::new().optional_max_length("details", self.details.as_deref(), 1000).finish()
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/chat/messages/{id}/report",
+    tag = "chat",
+    params(("id" = String, Path, description = "Assistant message ID")),
+    request_body = ReportMessageRequest,
+    responses(
+        (status = 201, description = "Message reported and hidden from the assistant's context"),
+        (status = 404, description = "Message not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn report_message(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(message_id): Path<String>,
+    Json(payload): Json<ReportMessageRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let message_oid = ObjectId::parse_str(&message_id).map_err(|_|
+        AppError::BadRequest("Invalid message ID".to_string())
+    )?;
+    payload.validate()?;
+
+    let message = state.db
+        .collection::<ChatMessage>("chat_messages")
+        .find_one(doc! { "_id": message_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
+
+    if !matches!(message.role, MessageRole::Assistant) {
+        return Err(AppError::BadRequest("Only assistant messages can be reported".to_string()));
+    }
+
+    let reports = state.db.collection::<MessageReport>("message_reports");
+
+    let already_reported = reports
+        .find_one(
+            doc! {
+                "message_id": message_oid,
+                "reporter_id": user_id,
+                "status": MessageReportStatus::Open.as_str(),
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if already_reported.is_some() {
+        return Err(AppError::BadRequest("You have already reported this message".to_string()));
+    }
+
+    let report = MessageReport {
+        id: None,
+        message_id: message_oid,
+        session_id: message.session_id,
+        reporter_id: user_id,
+        reason: payload.reason,
+        details: payload.details,
+        message_excerpt: message.content.chars().take(500).collect(),
+        status: MessageReportStatus::Open,
+        reviewed_by: None,
+        review_note: None,
+        created_at: Utc::now(),
+        reviewed_at: None,
+    };
+
+    let result = reports
+        .insert_one(&report, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    state.db
+        .collection::<ChatMessage>("chat_messages")
+        .update_one(doc! { "_id": message_oid }, doc! { "$set": { "flagged": true } }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    tracing::info!("Message {} reported by user {}", message_id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(
+            serde_json::json!({
+            "success": true,
+            "report_id": result.inserted_id.as_object_id().unwrap().to_hex(),
+            "message": "Thanks for the report. This message has been hidden from the assistant's context while we review it."
+        })
+        ),
+    ))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct QuickActionsQuery {
+    /// UTC offset such as `+07:00`; see `timezone::parse`.
+    pub tz: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuickActionsResponse {
+    pub success: bool,
+    #[schema(value_type = Vec<Object>)]
+    pub suggestions: Vec<QuickAction>,
+}
+
+/// Quick-reply chips for the chat input, from the time of day and what the
+/// user has logged today.
+#[utoipa::path(
+    get,
+    path = "/api/chat/suggestions",
+    tag = "chat",
+    params(QuickActionsQuery),
+    responses((status = 200, description = "Quick-reply chips for the chat input", body = QuickActionsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_quick_actions(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<QuickActionsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let offset = query.tz.as_deref().map(|tz| timezone::parse(Some(tz))).transpose()?;
+
+    let suggestions = quick_action_service::quick_actions(&state, user_id, offset).await?;
+
+    Ok(Json(QuickActionsResponse { success: true, suggestions }))
+}
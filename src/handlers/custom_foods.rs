@@ -0,0 +1,406 @@
+use axum::{ extract::{ Path, Query, State }, http::StatusCode, response::{ IntoResponse, Json }, Extension };
+use chrono::Utc;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, CustomFood, ServingPreset },
+    services::custom_food_service::{ self, MAX_CUSTOM_FOODS, MAX_SERVING_PRESETS },
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CustomFoodRequest {
+    pub name: String,
+    pub brand: Option<String>,
+    pub serving_size: Option<String>,
+    pub serving_weight_g: Option<f64>,
+    /// Named portions such as `{"name": "1 cup", "grams": 140}`.
+    #[serde(default)]
+    pub serving_presets: Vec<ServingPreset>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub fiber_g: Option<f64>,
+    pub sugar_g: Option<f64>,
+    pub sodium_mg: Option<f64>,
+    pub notes: Option<String>,
+}
+
+impl CustomFoodRequest {
+    /// Trimmed name, or an error if any value is unusable.
+    fn validate(&self) -> Result<String, AppError> {
+        let name = self.name.trim().to_string();
+        if name.is_empty() {
+            return Err(AppError::BadRequest("name cannot be empty".to_string()));
+        }
+        if name.chars().count() > 120 {
+            return Err(AppError::BadRequest("name must be at most 120 characters".to_string()));
+        }
+
+        let required = [self.calories, self.protein_g, self.carbs_g, self.fat_g];
+        let optional = [self.serving_weight_g, self.fiber_g, self.sugar_g, self.sodium_mg];
+        if
+            required
+                .iter()
+                .chain(optional.iter().flatten())
+                .any(|v| !v.is_finite() || *v < 0.0)
+        {
+            return Err(AppError::BadRequest("Calories and nutrients must be non-negative numbers".to_string()));
+        }
+
+        if !self.serving_presets.is_empty() {
+            if self.serving_weight_g.is_none_or(|g| g <= 0.0) {
+                return Err(AppError::BadRequest("serving_presets need serving_weight_g".to_string()));
+            }
+            if self.serving_presets.len() > MAX_SERVING_PRESETS {
+                return Err(
+                    AppError::BadRequest(format!("A food can have up to {} serving presets", MAX_SERVING_PRESETS))
+                );
+            }
+            for (i, preset) in self.serving_presets.iter().enumerate() {
+                let preset_name = preset.name.trim();
+                if preset_name.is_empty() || preset_name.chars().count() > 60 {
+                    return Err(AppError::BadRequest("Serving names must be 1 to 60 characters".to_string()));
+                }
+                if !preset.grams.is_finite() || preset.grams <= 0.0 {
+                    return Err(AppError::BadRequest("Serving grams must be positive numbers".to_string()));
+                }
+                if
+                    self.serving_presets[..i]
+                        .iter()
+                        .any(|other| other.name.trim().eq_ignore_ascii_case(preset_name))
+                {
+                    return Err(AppError::BadRequest(format!("Duplicate serving name \"{}\"", preset_name)));
+                }
+            }
+        }
+
+        Ok(name)
+    }
+
+    fn serving_presets(&self) -> Vec<ServingPreset> {
+        self.serving_presets
+            .iter()
+            .map(|preset| ServingPreset {
+                name: preset.name.trim().to_string(),
+                grams: preset.grams,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CustomFoodDto {
+    pub id: String,
+    pub name: String,
+    pub brand: Option<String>,
+    pub serving_size: Option<String>,
+    pub serving_weight_g: Option<f64>,
+    pub serving_presets: Vec<ServingPreset>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub fiber_g: Option<f64>,
+    pub sugar_g: Option<f64>,
+    pub sodium_mg: Option<f64>,
+    pub notes: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<CustomFood> for CustomFoodDto {
+    fn from(food: CustomFood) -> Self {
+        Self {
+            id: food.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: food.name,
+            brand: food.brand,
+            serving_size: food.serving_size,
+            serving_weight_g: food.serving_weight_g,
+            serving_presets: food.serving_presets,
+            calories: food.calories,
+            protein_g: food.protein_g,
+            carbs_g: food.carbs_g,
+            fat_g: food.fat_g,
+            fiber_g: food.fiber_g,
+            sugar_g: food.sugar_g,
+            sodium_mg: food.sodium_mg,
+            notes: food.notes,
+            created_at: food.created_at.to_rfc3339(),
+            updated_at: food.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CustomFoodResponse {
+    pub success: bool,
+    pub food: CustomFoodDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CustomFoodsListResponse {
+    pub success: bool,
+    pub foods: Vec<CustomFoodDto>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CustomFoodsQuery {
+    pub query: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/foods/custom",
+    tag = "custom_foods",
+    request_body = CustomFoodRequest,
+    responses((status = 201, description = "Custom food created", body = CustomFoodResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn create_custom_food(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CustomFoodRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let name = payload.validate()?;
+
+    if custom_food_service::name_taken(&state, user_id, &name, None).await? {
+        return Err(AppError::BadRequest(format!("You already have a custom food named \"{}\"", name)));
+    }
+
+    let count = state.db
+        .collection::<CustomFood>("custom_foods")
+        .count_documents(doc! { "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    if count >= MAX_CUSTOM_FOODS {
+        return Err(AppError::BadRequest(format!("You can save up to {} custom foods", MAX_CUSTOM_FOODS)));
+    }
+
+    let serving_presets = payload.serving_presets();
+    let now = Utc::now();
+    let mut food = CustomFood {
+        id: None,
+        user_id,
+        name,
+        brand: payload.brand,
+        serving_size: payload.serving_size,
+        serving_weight_g: payload.serving_weight_g.filter(|g| *g > 0.0),
+        serving_presets,
+        calories: payload.calories,
+        protein_g: payload.protein_g,
+        carbs_g: payload.carbs_g,
+        fat_g: payload.fat_g,
+        fiber_g: payload.fiber_g,
+        sugar_g: payload.sugar_g,
+        sodium_mg: payload.sodium_mg,
+        notes: payload.notes,
+        created_at: now,
+        updated_at: now,
+    };
+
+    let result = state.db
+        .collection::<CustomFood>("custom_foods")
+        .insert_one(&food, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    food.id = result.inserted_id.as_object_id();
+
+    tracing::info!("Created custom food {:?} for user {}", food.id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CustomFoodResponse {
+            success: true,
+            food: food.into(),
+        }),
+    ))
+}
+
+/// Alphabetical; `?query=` filters by name or brand.
+#[utoipa::path(
+    get,
+    path = "/api/foods/custom",
+    tag = "custom_foods",
+    params(CustomFoodsQuery),
+    responses((status = 200, description = "Custom foods, alphabetical", body = CustomFoodsListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_custom_foods(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<CustomFoodsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let foods = custom_food_service::search(&state, user_id, params.query.as_deref().unwrap_or(""), None).await?;
+
+    Ok(
+        Json(CustomFoodsListResponse {
+            success: true,
+            foods: foods.into_iter().map(Into::into).collect(),
+        })
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/foods/custom/{id}",
+    tag = "custom_foods",
+    params(("id" = String, Path, description = "Custom food ID")),
+    responses(
+        (status = 200, description = "The custom food", body = CustomFoodResponse),
+        (status = 404, description = "Custom food not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_custom_food(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(food_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let food_oid = ObjectId::parse_str(&food_id).map_err(|_|
+        AppError::BadRequest("Invalid custom food ID".to_string())
+    )?;
+
+    let food = custom_food_service::find_custom_food(&state, user_id, food_oid).await?;
+
+    Ok(
+        Json(CustomFoodResponse {
+            success: true,
+            food: food.into(),
+        })
+    )
+}
+
+/// Replaces every field. Meals already logged from the food keep their values.
+#[utoipa::path(
+    put,
+    path = "/api/foods/custom/{id}",
+    tag = "custom_foods",
+    params(("id" = String, Path, description = "Custom food ID")),
+    request_body = CustomFoodRequest,
+    responses(
+        (status = 200, description = "Custom food updated", body = CustomFoodResponse),
+        (status = 404, description = "Custom food not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn update_custom_food(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(food_id): Path<String>,
+    Json(payload): Json<CustomFoodRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let food_oid = ObjectId::parse_str(&food_id).map_err(|_|
+        AppError::BadRequest("Invalid custom food ID".to_string())
+    )?;
+
+    let name = payload.validate()?;
+
+    if custom_food_service::name_taken(&state, user_id, &name, Some(food_oid)).await? {
+        return Err(AppError::BadRequest(format!("You already have a custom food named \"{}\"", name)));
+    }
+
+    let result = state.db
+        .collection::<CustomFood>("custom_foods")
+        .update_one(
+            doc! { "_id": food_oid, "user_id": user_id },
+            doc! {
+                "$set": {
+                    "name": &name,
+                    "brand": &payload.brand,
+                    "serving_size": &payload.serving_size,
+                    "serving_weight_g": payload.serving_weight_g.filter(|g| *g > 0.0),
+                    "serving_presets": mongodb::bson
+                        ::to_bson(&payload.serving_presets())
+                        .map_err(|e| AppError::InternalError(e.into()))?,
+                    "calories": payload.calories,
+                    "protein_g": payload.protein_g,
+                    "carbs_g": payload.carbs_g,
+                    "fat_g": payload.fat_g,
+                    "fiber_g": payload.fiber_g,
+                    "sugar_g": payload.sugar_g,
+                    "sodium_mg": payload.sodium_mg,
+                    "notes": &payload.notes,
+                    "updated_at": Utc::now().to_rfc3339(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound("Custom food not found".to_string()));
+    }
+
+    let food = custom_food_service::find_custom_food(&state, user_id, food_oid).await?;
+
+    Ok(
+        Json(CustomFoodResponse {
+            success: true,
+            food: food.into(),
+        })
+    )
+}
+
+/// Meals already logged from the food are kept.
+#[utoipa::path(
+    delete,
+    path = "/api/foods/custom/{id}",
+    tag = "custom_foods",
+    params(("id" = String, Path, description = "Custom food ID")),
+    responses(
+        (status = 200, description = "Custom food deleted"),
+        (status = 404, description = "Custom food not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_custom_food(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(food_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let food_oid = ObjectId::parse_str(&food_id).map_err(|_|
+        AppError::BadRequest("Invalid custom food ID".to_string())
+    )?;
+
+    let result = state.db
+        .collection::<CustomFood>("custom_foods")
+        .delete_one(doc! { "_id": food_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("Custom food not found".to_string()));
+    }
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Custom food deleted"
+    })
+        )
+    )
+}
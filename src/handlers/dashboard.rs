@@ -1,8 +1,9 @@
 use axum::{ extract::State, http::{ header, HeaderMap, StatusCode }, response::IntoResponse };
-use std::fs;
+use std::{ fs, sync::OnceLock };
 use base64::{ engine::general_purpose, Engine as _ };
+use utoipa::OpenApi;
 
-use crate::{ db::AppState, error::AppError };
+use crate::{ db::AppState, error::AppError, openapi::ApiDoc };
 
 pub async fn serve_dashboard(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
     let html_path = "views/index.html";
@@ -29,49 +30,72 @@ pub async fn serve_docs(
     State(state): State<AppState>,
     headers: HeaderMap
 ) -> Result<axum::response::Response, AppError> {
+    if !docs_authorized(&state, &headers) {
+        return docs_unauthorized();
+    }
+
+    tracing::info!("Docs authentication successful");
+    let html_path = "views/docs.html";
+    let html_content = fs
+        ::read_to_string(html_path)
+        .map_err(|e| {
+            tracing::error!("Failed to read docs HTML: {}", e);
+            AppError::InternalError(anyhow::anyhow!("Failed to load documentation"))
+        })?;
+
+    let response = axum::response::Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(axum::body::Body::from(html_content))
+        .map_err(|e| AppError::InternalError(anyhow::anyhow!("Failed to build response: {}", e)))?;
+
+    Ok(response)
+}
+
+/// The OpenAPI document the Swagger UI at `/docs` renders, behind the same
+/// credentials as the page itself.
+pub async fn serve_openapi(
+    State(state): State<AppState>,
+    headers: HeaderMap
+) -> Result<axum::response::Response, AppError> {
+    if !docs_authorized(&state, &headers) {
+        return docs_unauthorized();
+    }
+
+    static SPEC: OnceLock<String> = OnceLock::new();
+    let spec = SPEC.get_or_init(|| {
+        ApiDoc::openapi().to_json().expect("OpenAPI document serializes")
+    });
+
+    Ok((StatusCode::OK, [(header::CONTENT_TYPE, "application/json")], spec.as_str()).into_response())
+}
+
+fn docs_authorized(state: &AppState, headers: &HeaderMap) -> bool {
     tracing::debug!("Docs auth - Expected username: {}", state.config.docs.username);
     tracing::debug!("Docs auth - Expected password: {}", state.config.docs.password);
-    
+
     if let Some(auth_header) = headers.get(header::AUTHORIZATION) {
         if let Ok(auth_str) = auth_header.to_str() {
             tracing::debug!("Auth header received: {}", auth_str);
-            if auth_str.starts_with("Basic ") {
-                let encoded = &auth_str[6..];
+            if let Some(encoded) = auth_str.strip_prefix("Basic ") {
                 if let Ok(decoded_bytes) = general_purpose::STANDARD.decode(encoded) {
                     if let Ok(decoded_str) = String::from_utf8(decoded_bytes) {
                         let parts: Vec<&str> = decoded_str.splitn(2, ':').collect();
                         tracing::debug!("Decoded credentials - username: {}, password: {}", 
-                            parts.get(0).unwrap_or(&""), parts.get(1).unwrap_or(&""));
-                        if
-                            parts.len() == 2 &&
+                            parts.first().unwrap_or(&""), parts.get(1).unwrap_or(&""));
+                        return parts.len() == 2 &&
                             parts[0] == state.config.docs.username &&
-                            parts[1] == state.config.docs.password
-                        {
-                            tracing::info!("Docs authentication successful");
-                            let html_path = "views/docs.html";
-                            let html_content = fs
-                                ::read_to_string(html_path)
-                                .map_err(|e| {
-                                    tracing::error!("Failed to read docs HTML: {}", e);
-                                    AppError::InternalError(
-                                        anyhow::anyhow!("Failed to load documentation")
-                                    )
-                                })?;
-
-                            let response = axum::response::Response::builder()
-                                .status(StatusCode::OK)
-                                .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
-                                .body(axum::body::Body::from(html_content))
-                                .map_err(|e| AppError::InternalError(anyhow::anyhow!("Failed to build response: {}", e)))?;
-                            
-                            return Ok(response);
-                        }
+                            parts[1] == state.config.docs.password;
                     }
                 }
             }
         }
     }
 
+    false
+}
+
+fn docs_unauthorized() -> Result<axum::response::Response, AppError> {
     let response = axum::response::Response::builder()
         .status(StatusCode::UNAUTHORIZED)
         .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
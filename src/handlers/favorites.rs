@@ -0,0 +1,223 @@
+use axum::{ extract::{ Path, State }, http::StatusCode, response::{ IntoResponse, Json }, Extension };
+use chrono::Utc;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, FavoriteFood, MealSource, MealType },
+    services::favorite_service,
+};
+
+const MAX_FAVORITES: usize = 200;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateFavoriteRequest {
+    pub food_name: String,
+    pub meal_type: MealType,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub source: MealSource,
+    pub portion_weight_g: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FavoriteFoodDto {
+    pub id: String,
+    pub food_name: String,
+    pub meal_type: MealType,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: Option<String>,
+    pub notes: Option<String>,
+    pub source: MealSource,
+    pub portion_weight_g: Option<f64>,
+    pub times_logged: i64,
+    pub last_logged_at: Option<String>,
+    pub created_at: String,
+}
+
+impl From<FavoriteFood> for FavoriteFoodDto {
+    fn from(favorite: FavoriteFood) -> Self {
+        Self {
+            id: favorite.id.map(|id| id.to_hex()).unwrap_or_default(),
+            food_name: favorite.food_name,
+            meal_type: favorite.meal_type,
+            calories: favorite.calories,
+            protein_g: favorite.protein_g,
+            carbs_g: favorite.carbs_g,
+            fat_g: favorite.fat_g,
+            serving_size: favorite.serving_size,
+            notes: favorite.notes,
+            source: favorite.source,
+            portion_weight_g: favorite.portion_weight_g,
+            times_logged: favorite.times_logged,
+            last_logged_at: favorite.last_logged_at.map(|d| d.to_rfc3339()),
+            created_at: favorite.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FavoriteFoodResponse {
+    pub success: bool,
+    pub favorite: FavoriteFoodDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FavoriteFoodsListResponse {
+    pub success: bool,
+    pub favorites: Vec<FavoriteFoodDto>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/foods/favorites",
+    tag = "favorites",
+    request_body = CreateFavoriteRequest,
+    responses((status = 201, description = "Favorite saved", body = FavoriteFoodResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn create_favorite(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateFavoriteRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let food_name = payload.food_name.trim().to_string();
+    if food_name.is_empty() {
+        return Err(AppError::BadRequest("food_name cannot be empty".to_string()));
+    }
+    if [payload.calories, payload.protein_g, payload.carbs_g, payload.fat_g].iter().any(|v| *v < 0.0) {
+        return Err(AppError::BadRequest("Calories and macros cannot be negative".to_string()));
+    }
+    if payload.source.is_verified() {
+        return Err(AppError::BadRequest("source cannot be fdc or barcode".to_string()));
+    }
+
+    // Names are unique per user so the chat assistant can log favorites by name.
+    let existing = favorite_service::list_favorites(&state, user_id).await?;
+    if existing.iter().any(|f| f.food_name.eq_ignore_ascii_case(&food_name)) {
+        return Err(AppError::BadRequest(format!("\"{}\" is already a favorite", food_name)));
+    }
+    if existing.len() >= MAX_FAVORITES {
+        return Err(AppError::BadRequest(format!("You can save up to {} favorite foods", MAX_FAVORITES)));
+    }
+
+    let mut favorite = FavoriteFood {
+        id: None,
+        user_id,
+        food_name,
+        meal_type: payload.meal_type,
+        calories: payload.calories,
+        protein_g: payload.protein_g,
+        carbs_g: payload.carbs_g,
+        fat_g: payload.fat_g,
+        serving_size: payload.serving_size,
+        notes: payload.notes,
+        source: payload.source,
+        portion_weight_g: payload.portion_weight_g.filter(|g| *g > 0.0),
+        times_logged: 0,
+        last_logged_at: None,
+        created_at: Utc::now(),
+    };
+
+    let result = state.db
+        .collection::<FavoriteFood>("favorite_foods")
+        .insert_one(&favorite, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    favorite.id = result.inserted_id.as_object_id();
+
+    tracing::info!("Saved favorite food {:?} for user {}", favorite.id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(FavoriteFoodResponse {
+            success: true,
+            favorite: favorite.into(),
+        }),
+    ))
+}
+
+/// Most logged first.
+#[utoipa::path(
+    get,
+    path = "/api/foods/favorites",
+    tag = "favorites",
+    responses((status = 200, description = "Favorites, most logged first", body = FavoriteFoodsListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_favorites(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let favorites = favorite_service::list_favorites(&state, user_id).await?;
+
+    Ok(
+        Json(FavoriteFoodsListResponse {
+            success: true,
+            favorites: favorites.into_iter().map(Into::into).collect(),
+        })
+    )
+}
+
+/// Meals already logged from the favorite are kept.
+#[utoipa::path(
+    delete,
+    path = "/api/foods/favorites/{id}",
+    tag = "favorites",
+    params(("id" = String, Path, description = "Favorite ID")),
+    responses(
+        (status = 200, description = "Favorite deleted"),
+        (status = 404, description = "Favorite not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_favorite(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(favorite_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let favorite_oid = ObjectId::parse_str(&favorite_id).map_err(|_|
+        AppError::BadRequest("Invalid favorite ID".to_string())
+    )?;
+
+    let result = state.db
+        .collection::<FavoriteFood>("favorite_foods")
+        .delete_one(doc! { "_id": favorite_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("Favorite food not found".to_string()));
+    }
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Favorite food removed"
+    })
+        )
+    )
+}
@@ -1,9 +1,22 @@
-use axum::{ extract::{ Path, Query, State }, http::StatusCode, response::IntoResponse, Json };
+use axum::{ extract::{ Path, Query, State }, http::StatusCode, response::IntoResponse, Extension, Json };
+use mongodb::bson::oid::ObjectId;
 use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
 
-use crate::{ db::AppState, error::AppError };
+use crate::{
+    db::AppState,
+    error::AppError,
+    handlers::custom_foods::CustomFoodDto,
+    models::Claims,
+    services::{
+        cache_service::{ self, CacheBypassQuery },
+        custom_food_service,
+        fdc_service::FoodSearchResult,
+        food_lookup_service,
+    },
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct SearchQuery {
     pub query: String,
     #[serde(rename = "pageNumber")]
@@ -12,6 +25,18 @@ pub struct SearchQuery {
     pub page_size: Option<i32>,
     #[serde(rename = "dataType")]
     pub data_type: Option<String>,
+    #[serde(default)]
+    pub no_cache: bool,
+}
+
+/// FDC results plus the user's matching custom foods. Custom foods are only
+/// included on the first page.
+#[derive(Debug, Serialize)]
+pub struct FoodSearchResponse {
+    #[serde(flatten)]
+    pub fdc: FoodSearchResult,
+    #[serde(rename = "customFoods")]
+    pub custom_foods: Vec<CustomFoodDto>,
 }
 
 #[derive(Debug, Serialize)]
@@ -21,37 +46,85 @@ pub struct ApiResponse<T> {
     pub message: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/food-wiki/search",
+    tag = "food_wiki",
+    params(SearchQuery),
+    responses((status = 200, description = "FoodData Central matches, plus the user's matching custom foods on the first page")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn search_foods(
     State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
     Query(params): Query<SearchQuery>
 ) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
     let data_types = params.data_type.map(|dt| {
         dt.split(',')
             .map(|s| s.trim().to_string())
             .collect::<Vec<String>>()
     });
 
-    let result = state.fdc_service
-        .search_foods(&params.query, params.page_number, params.page_size, data_types).await
-        .map_err(|e| AppError::InternalError(e))?;
+    let cache_key = format!(
+        "{}|{}|{}|{}",
+        params.query,
+        params.page_number.unwrap_or_default(),
+        params.page_size.unwrap_or_default(),
+        data_types.as_ref().map(|types| types.join(",")).unwrap_or_default()
+    );
+    let result = state.cache
+        .get_or_fetch(cache_service::FDC_SEARCH, &cache_key, params.no_cache, || {
+            state.fdc_service.search_foods(&params.query, params.page_number, params.page_size, data_types)
+        }).await
+        .map_err(AppError::InternalError)?;
+
+    // Custom foods are per user, so they are looked up after the shared cache.
+    let custom_foods = if params.page_number.unwrap_or(1) <= 1 {
+        custom_food_service::search(
+            &state,
+            user_id,
+            &params.query,
+            Some(custom_food_service::SEARCH_LIMIT)
+        ).await?
+    } else {
+        Vec::new()
+    };
 
     Ok((
         StatusCode::OK,
         Json(ApiResponse {
             success: true,
-            data: Some(result),
+            data: Some(FoodSearchResponse {
+                fdc: result,
+                custom_foods: custom_foods.into_iter().map(Into::into).collect(),
+            }),
             message: None,
         }),
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/food-wiki/{fdc_id}",
+    tag = "food_wiki",
+    params(("fdc_id" = i32, Path, description = "FoodData Central ID"), CacheBypassQuery),
+    responses((status = 200, description = "The food's nutrients and portions")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_food_details(
     State(state): State<AppState>,
-    Path(fdc_id): Path<i32>
+    Path(fdc_id): Path<i32>,
+    Query(cache): Query<CacheBypassQuery>
 ) -> Result<impl IntoResponse, AppError> {
-    let result = state.fdc_service
-        .get_food_details(fdc_id).await
-        .map_err(|e| AppError::InternalError(e))?;
+    let result = state.cache
+        .get_or_fetch(cache_service::FDC_FOOD, &fdc_id.to_string(), cache.no_cache, || {
+            state.fdc_service.get_food_details(fdc_id)
+        }).await
+        .map_err(AppError::InternalError)?;
 
     Ok((
         StatusCode::OK,
@@ -63,19 +136,86 @@ pub async fn get_food_details(
     ))
 }
 
-#[derive(Debug, Deserialize)]
+/// Query of `GET /api/food-wiki/:fdc_id/scaled`: either `grams`, or a
+/// `portion_id` from the food's portions and optionally how many `servings`.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ScaledQuery {
+    pub grams: Option<f64>,
+    pub portion_id: Option<i32>,
+    pub servings: Option<f64>,
+    #[serde(default)]
+    pub no_cache: bool,
+}
+
+/// The food's calories, macros, fiber, sugar and sodium for a serving, from
+/// the nutrients FDC reports per 100 g and the gram weights of its portions.
+#[utoipa::path(
+    get,
+    path = "/api/food-wiki/{fdc_id}/scaled",
+    tag = "food_wiki",
+    params(("fdc_id" = i32, Path, description = "FoodData Central ID"), ScaledQuery),
+    responses(
+        (status = 200, description = "Calories and nutrients for the serving"),
+        (status = 400, description = "Neither or both of `grams` and `portion_id` sent")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_scaled_food(
+    State(state): State<AppState>,
+    Path(fdc_id): Path<i32>,
+    Query(query): Query<ScaledQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let serving = food_lookup_service::serving(query.grams, query.portion_id, query.servings)?;
+
+    let details = state.cache
+        .get_or_fetch(cache_service::FDC_FOOD, &fdc_id.to_string(), query.no_cache, || {
+            state.fdc_service.get_food_details(fdc_id)
+        }).await
+        .map_err(AppError::InternalError)?;
+
+    let scaled = food_lookup_service::scale_fdc_food(&details, serving)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ApiResponse {
+            success: true,
+            data: Some(scaled),
+            message: None,
+        }),
+    ))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct GetFoodsRequest {
     #[serde(rename = "fdcIds")]
     pub fdc_ids: Vec<i32>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/food-wiki/foods",
+    tag = "food_wiki",
+    params(CacheBypassQuery),
+    request_body = GetFoodsRequest,
+    responses((status = 200, description = "The foods, in request order")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_foods(
     State(state): State<AppState>,
+    Query(cache): Query<CacheBypassQuery>,
     Json(payload): Json<GetFoodsRequest>
 ) -> Result<impl IntoResponse, AppError> {
-    let result = state.fdc_service
-        .get_foods(payload.fdc_ids).await
-        .map_err(|e| AppError::InternalError(e))?;
+    // The response follows the request order, so the key keeps it too.
+    let cache_key = payload.fdc_ids
+        .iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let result = state.cache
+        .get_or_fetch(cache_service::FDC_FOODS, &cache_key, cache.no_cache, || {
+            state.fdc_service.get_foods(payload.fdc_ids)
+        }).await
+        .map_err(AppError::InternalError)?;
 
     Ok((
         StatusCode::OK,
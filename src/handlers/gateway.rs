@@ -0,0 +1,158 @@
+use axum::{
+    body::Bytes,
+    extract::{ Path, State },
+    http::{ HeaderMap, StatusCode },
+    response::{ IntoResponse, Json },
+    Extension,
+};
+use chrono::{ DateTime, Utc };
+use mongodb::bson::oid::ObjectId;
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::Claims,
+    services::gateway_service::{ self, Channel, InboundMessage, InboundOutcome },
+};
+
+const SIGNATURE_HEADER: &str = "x-gateway-signature";
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LinkWhatsAppRequest {
+    pub phone_number: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LinkWhatsAppResponse {
+    pub success: bool,
+    pub phone_number: String,
+    /// Sent from the linked number to `send_to` to finish linking.
+    pub verification_code: String,
+    pub expires_at: DateTime<Utc>,
+    pub send_to: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InboundResponse {
+    pub success: bool,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub outcome: InboundOutcome,
+}
+
+/// Webhook for the email or WhatsApp provider. Authorized by the body's
+/// HMAC signature instead of a user token.
+#[utoipa::path(
+    post,
+    path = "/api/gateway/inbound/{channel}",
+    tag = "gateway",
+    params(
+        ("channel" = String, Path, description = "`email` or `whatsapp`"),
+        ("x-gateway-signature" = String, Header, description = "`sha256=<hex>` HMAC of the body with the gateway secret")
+    ),
+    request_body(content = Object, description = "The provider's inbound message"),
+    responses(
+        (status = 200, description = "Message handled", body = InboundResponse),
+        (status = 400, description = "Missing or invalid signature"),
+        (status = 404, description = "The inbound gateway is not enabled")
+    ),
+    security(())
+)]
+pub async fn receive_inbound(
+    State(state): State<AppState>,
+    Path(channel): Path<Channel>,
+    headers: HeaderMap,
+    body: Bytes
+) -> Result<impl IntoResponse, AppError> {
+    let signature = headers.get(SIGNATURE_HEADER).and_then(|v| v.to_str().ok());
+    gateway_service::verify_signature(&state.config, &body, signature)?;
+
+    let message: InboundMessage = serde_json::from_slice(&body).map_err(|e|
+        AppError::BadRequest(format!("Invalid inbound message: {}", e))
+    )?;
+
+    let outcome = gateway_service::handle_inbound(&state, channel, message).await?;
+
+    Ok(Json(InboundResponse { success: true, outcome }))
+}
+
+/// Starts linking a WhatsApp number; it's verified by messaging the code to
+/// the gateway.
+#[utoipa::path(
+    put,
+    path = "/api/gateway/whatsapp",
+    tag = "gateway",
+    request_body = LinkWhatsAppRequest,
+    responses((status = 200, description = "Linking started; send the code to finish", body = LinkWhatsAppResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn link_whatsapp(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<LinkWhatsAppRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let number = gateway_service::normalize_phone(&payload.phone_number).ok_or_else(|| {
+        AppError::ValidationError("Invalid phone number. Use international format, e.g. +6281234567890".to_string())
+    })?;
+
+    let link = gateway_service::link_whatsapp(&state, user_id, &number).await?;
+    let code = link.verification_code.unwrap_or_default();
+    let send_to = state.config.gateway.whatsapp_number.clone();
+
+    tracing::info!("User {} started linking a WhatsApp number", claims.sub);
+
+    Ok((
+        StatusCode::OK,
+        Json(LinkWhatsAppResponse {
+            success: true,
+            message: match &send_to {
+                Some(gateway) => format!("Send {} from {} to {} on WhatsApp to finish linking", code, link.number, gateway),
+                None => format!("Send {} from {} to the gateway on WhatsApp to finish linking", code, link.number),
+            },
+            phone_number: link.number,
+            verification_code: code,
+            expires_at: link.code_expires_at.unwrap_or_else(Utc::now),
+            send_to,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/gateway/whatsapp",
+    tag = "gateway",
+    responses(
+        (status = 200, description = "Number unlinked"),
+        (status = 404, description = "No number linked")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn unlink_whatsapp(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if !gateway_service::unlink_whatsapp(&state, user_id).await? {
+        return Err(AppError::NotFound("No WhatsApp number linked".to_string()));
+    }
+
+    tracing::info!("User {} unlinked their WhatsApp number", claims.sub);
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "WhatsApp number unlinked"
+    })
+        )
+    )
+}
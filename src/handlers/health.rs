@@ -1,11 +1,35 @@
-use axum::{ extract::State, http::StatusCode, response::IntoResponse, Extension, Json };
-use chrono::Utc;
+use axum::{
+    extract::{ Path, Query, State },
+    http::{ header, HeaderMap, StatusCode },
+    response::{ IntoResponse, Response },
+    Extension,
+    Json,
+};
+use chrono::{ NaiveDate, Utc };
 use mongodb::bson::{ doc, oid::ObjectId };
 use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
 
-use crate::{ db::AppState, error::AppError, models::* };
+use crate::{
+    db::AppState,
+    error::AppError,
+    field_encryption,
+    handlers::weight::{ self, WeightLogDto },
+    models::*,
+    services::{
+        condition_guidance,
+        goal_history_service::{ self, GoalEraProgress },
+        goal_presets::{ self, GoalPreset },
+        health_summary_service::{ self, HealthSummary },
+        pdf_service,
+        report_service,
+        response_cache_service,
+        versioning,
+    },
+    validation::{ Checks, Validate },
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateHealthProfileRequest {
     pub age: i32,
     pub gender: Gender,
@@ -13,6 +37,8 @@ pub struct CreateHealthProfileRequest {
     pub weight_kg: f64,
     pub activity_level: ActivityLevel,
     pub goal: HealthGoal,
+    /// Defaults to the goal's percentage split.
+    pub macro_strategy: Option<MacroStrategy>,
     pub medical_conditions: Option<Vec<String>>,
     pub blood_pressure: Option<BloodPressure>,
     pub fasting_blood_sugar: Option<f64>,
@@ -20,21 +46,226 @@ pub struct CreateHealthProfileRequest {
     pub dietary_preferences: Option<Vec<DietaryPreference>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct HealthProfileResponse {
     pub success: bool,
+    #[schema(value_type = Object)]
     pub profile: HealthProfile,
     pub message: String,
 }
 
+/// Partial survey answers for `PATCH /api/health/survey/draft`. Fields left
+/// out keep their saved value.
+#[derive(Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct HealthSurveyAnswers {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gender: Option<Gender>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height_cm: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight_kg: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity_level: Option<ActivityLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal: Option<HealthGoal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub macro_strategy: Option<MacroStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub medical_conditions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blood_pressure: Option<BloodPressure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fasting_blood_sugar: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allergies: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dietary_preferences: Option<Vec<DietaryPreference>>,
+}
+
+const AGE_RANGE: (i32, i32) = (1, 120);
+const HEIGHT_CM_RANGE: (f64, f64) = (50.0, 300.0);
+const WEIGHT_KG_RANGE: (f64, f64) = (10.0, 500.0);
+
+impl Validate for CreateHealthProfileRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut checks = Checks::new();
+        checks
+            .range("age", self.age, AGE_RANGE.0, AGE_RANGE.1)
+            .range("height_cm", self.height_cm, HEIGHT_CM_RANGE.0, HEIGHT_CM_RANGE.1)
+            .range("weight_kg", self.weight_kg, WEIGHT_KG_RANGE.0, WEIGHT_KG_RANGE.1);
+        check_health_details(
+            &mut checks,
+            self.blood_pressure.as_ref(),
+            self.fasting_blood_sugar,
+            self.medical_conditions.as_deref(),
+            self.allergies.as_deref()
+        );
+        checks.finish()
+    }
+}
+
+/// Only the answers given are checked, so a draft can be partial.
+impl Validate for HealthSurveyAnswers {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut checks = Checks::new();
+        checks
+            .optional_range("age", self.age, AGE_RANGE.0, AGE_RANGE.1)
+            .optional_range("height_cm", self.height_cm, HEIGHT_CM_RANGE.0, HEIGHT_CM_RANGE.1)
+            .optional_range("weight_kg", self.weight_kg, WEIGHT_KG_RANGE.0, WEIGHT_KG_RANGE.1);
+        check_health_details(
+            &mut checks,
+            self.blood_pressure.as_ref(),
+            self.fasting_blood_sugar,
+            self.medical_conditions.as_deref(),
+            self.allergies.as_deref()
+        );
+        checks.finish()
+    }
+}
+
+fn check_health_details(
+    checks: &mut Checks,
+    blood_pressure: Option<&BloodPressure>,
+    fasting_blood_sugar: Option<f64>,
+    medical_conditions: Option<&[String]>,
+    allergies: Option<&[String]>
+) {
+    if let Some(blood_pressure) = blood_pressure {
+        checks
+            .range("blood_pressure.systolic", blood_pressure.systolic, 50, 300)
+            .range("blood_pressure.diastolic", blood_pressure.diastolic, 30, 200);
+        if blood_pressure.diastolic >= blood_pressure.systolic {
+            checks.add("blood_pressure.diastolic", "blood_pressure.diastolic must be below systolic");
+        }
+    }
+    checks
+        .optional_range("fasting_blood_sugar", fasting_blood_sugar, 20.0, 600.0)
+        .text_list("medical_conditions", medical_conditions, 50, 100)
+        .text_list("allergies", allergies, 50, 100);
+}
+
+impl From<HealthSurveyDraft> for HealthSurveyAnswers {
+    fn from(draft: HealthSurveyDraft) -> Self {
+        Self {
+            age: draft.age,
+            gender: draft.gender,
+            height_cm: draft.height_cm,
+            weight_kg: draft.weight_kg,
+            activity_level: draft.activity_level,
+            goal: draft.goal,
+            macro_strategy: draft.macro_strategy,
+            medical_conditions: draft.medical_conditions,
+            blood_pressure: draft.blood_pressure,
+            fasting_blood_sugar: draft.fasting_blood_sugar,
+            allergies: draft.allergies,
+            dietary_preferences: draft.dietary_preferences,
+        }
+    }
+}
+
+impl HealthSurveyAnswers {
+    /// Required answers that are still missing before the survey can be submitted.
+    fn missing_fields(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.age.is_none() {
+            missing.push("age");
+        }
+        if self.gender.is_none() {
+            missing.push("gender");
+        }
+        if self.height_cm.is_none() {
+            missing.push("height_cm");
+        }
+        if self.weight_kg.is_none() {
+            missing.push("weight_kg");
+        }
+        if self.activity_level.is_none() {
+            missing.push("activity_level");
+        }
+        if self.goal.is_none() {
+            missing.push("goal");
+        }
+        missing
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthSurveyDraftResponse {
+    pub success: bool,
+    pub answers: HealthSurveyAnswers,
+    #[schema(value_type = Vec<String>)]
+    pub missing_fields: Vec<&'static str>,
+    pub updated_at: Option<String>,
+}
+
+/// With `If-Match`, only saves if the profile is still at that version and
+/// returns 409 otherwise.
+#[utoipa::path(
+    post,
+    path = "/api/health/profile",
+    tag = "health",
+    params(("If-Match" = Option<String>, Header, description = "Profile version last read, from its `ETag`")),
+    request_body = CreateHealthProfileRequest,
+    responses(
+        (status = 200, description = "Profile saved and targets calculated", body = HealthProfileResponse),
+        (status = 409, description = "The profile changed since the `If-Match` version")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn create_or_update_profile(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Json(payload): Json<CreateHealthProfileRequest>
 ) -> Result<impl IntoResponse, AppError> {
-    let user_id = claims.sub;
+    let expected_version = versioning::expected_version(&headers)?;
+    let profile = save_profile(&state, claims.sub, payload, expected_version).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::ETAG, versioning::etag(profile.version))],
+        Json(HealthProfileResponse {
+            success: true,
+            profile,
+            message: "Health profile created successfully!".to_string(),
+        }),
+    ))
+}
+
+/// Computes the derived values and AI recommendations and stores the profile.
+async fn save_profile(
+    state: &AppState,
+    user_id: String,
+    payload: CreateHealthProfileRequest,
+    expected_version: Option<i64>
+) -> Result<HealthProfile, AppError> {
     tracing::info!("Creating health profile for user: {}", user_id);
 
+    payload.validate()?;
+    let macro_strategy = payload.macro_strategy.unwrap_or_default();
+    macro_strategy.validate().map_err(AppError::BadRequest)?;
+
+    let user_oid = ObjectId::parse_str(&user_id).map_err(|e| {
+        tracing::error!("Invalid user ID: {}", e);
+        AppError::BadRequest("Invalid user ID".to_string())
+    })?;
+
+    let current_profile = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?
+        .health_profile;
+    let current_version = current_profile.as_ref().map_or(0, |p| p.version);
+
+    // Checked before the AI call so a stale edit fails fast; the write below
+    // checks again in case the profile changed meanwhile.
+    if expected_version.is_some_and(|v| v != current_version) {
+        return Err(versioning::conflict("health profile"));
+    }
+
     let bmi = HealthProfile::calculate_bmi(payload.weight_kg, payload.height_cm);
     let bmi_category = HealthProfile::bmi_category(bmi);
 
@@ -49,86 +280,28 @@ pub async fn create_or_update_profile(
 
     let daily_calories = HealthProfile::calculate_daily_calories(tdee, &payload.goal);
 
-    let (protein_g, carbs_g, fat_g) = HealthProfile::calculate_macros(
+    let (protein_g, carbs_g, fat_g) = macro_strategy.calculate(
         daily_calories,
-        &payload.goal
-    );
-
-    let ai_prompt = format!(
-        "I am a {} year old {} with the following health profile:\n\
-        - Height: {:.1} cm\n\
-        - Weight: {:.1} kg\n\
-        - BMI: {:.1} ({})\n\
-        - Activity Level: {:?}\n\
-        - Goal: {:?}\n\
-        - Daily Calorie Target: {:.0} kcal\n\
-        - Macros: {:.0}g protein, {:.0}g carbs, {:.0}g fat\n\
-        {}\n\
-        {}\n\
-        {}\n\n\
-        Please provide:\n\
-        1. Personalized nutrition recommendations\n\
-        2. List of 10-15 recommended foods I should eat regularly\n\
-        3. List of foods I should avoid or limit\n\
-        4. General health tips\n\n\
-        Format the response in clear sections.",
-        payload.age,
-        match payload.gender {
-            Gender::Male => "male",
-            Gender::Female => "female",
-        },
-        payload.height_cm,
         payload.weight_kg,
-        bmi,
-        bmi_category,
-        payload.activity_level,
-        payload.goal,
-        daily_calories,
-        protein_g,
-        carbs_g,
-        fat_g,
-        if let Some(ref conditions) = payload.medical_conditions {
-            format!("- Medical conditions: {}", conditions.join(", "))
-        } else {
-            String::new()
-        },
-        if let Some(ref allergies) = payload.allergies {
-            format!("- Allergies: {}", allergies.join(", "))
-        } else {
-            String::new()
-        },
-        if let Some(ref prefs) = payload.dietary_preferences {
-            format!("- Dietary preferences: {:?}", prefs)
-        } else {
-            String::new()
-        }
+        &payload.goal,
+        None
     );
 
-    tracing::info!("Generating AI recommendations for user: {}", user_id);
-
-    let ai_response = match state.gemini_service.get_text_response(&ai_prompt).await {
-        Ok(response) => {
-            tracing::info!("Successfully generated AI recommendations");
-            response
-        }
-        Err(e) => {
-            tracing::error!("Failed to get AI recommendations: {}", e);
-            "Unable to generate AI recommendations at this time. Please try again later.".to_string()
-        }
-    };
-
-    let recommended_foods = extract_recommended_foods(&ai_response);
-    let foods_to_avoid = extract_foods_to_avoid(&ai_response);
+    let condition_guidance = condition_guidance::build(
+        payload.medical_conditions.as_deref(),
+        carbs_g
+    );
 
     tracing::info!("Creating health profile struct for user: {}", user_id);
 
-    let profile = HealthProfile {
+    let mut profile = HealthProfile {
         age: payload.age,
         gender: payload.gender,
         height_cm: payload.height_cm,
         weight_kg: payload.weight_kg,
         activity_level: payload.activity_level,
         goal: payload.goal,
+        macro_strategy,
         medical_conditions: payload.medical_conditions,
         blood_pressure: payload.blood_pressure,
         fasting_blood_sugar: payload.fasting_blood_sugar,
@@ -142,21 +315,25 @@ pub async fn create_or_update_profile(
         daily_protein_g: protein_g,
         daily_carbs_g: carbs_g,
         daily_fat_g: fat_g,
-        ai_recommendations: Some(ai_response),
-        recommended_foods: Some(recommended_foods),
-        foods_to_avoid: Some(foods_to_avoid),
+        ai_recommendations: None,
+        ai_recommendations_prompt_version: None,
+        recommended_foods: None,
+        foods_to_avoid: None,
+        condition_guidance: Some(condition_guidance),
+        goal_preset: None,
+        // Set on its own, so a new survey keeps it.
+        target_cycle: current_profile.as_ref().map(|p| p.target_cycle.clone()).unwrap_or_default(),
+        version: current_version + 1,
         created_at: Utc::now(),
         updated_at: Utc::now(),
     };
 
-    let user_oid = ObjectId::parse_str(&user_id).map_err(|e| {
-        tracing::error!("Invalid user ID: {}", e);
-        AppError::BadRequest("Invalid user ID".to_string())
-    })?;
+    tracing::info!("Generating AI recommendations for user: {}", user_id);
+    generate_recommendations(state, &mut profile).await?;
 
     tracing::info!("Serializing profile to BSON for user: {}", user_id);
 
-    let profile_bson = mongodb::bson::to_bson(&profile).map_err(|e| {
+    let profile_bson = field_encryption::profile_to_bson(&profile).map_err(|e| {
         tracing::error!("Failed to serialize health profile to BSON: {}", e);
         AppError::InternalError(anyhow::anyhow!("Failed to serialize health profile"))
     })?;
@@ -172,16 +349,232 @@ pub async fn create_or_update_profile(
         }
     };
 
-    state.db
+    let mut filter = doc! { "_id": user_oid };
+    if expected_version.is_some() {
+        filter.insert("health_profile.version", versioning::version_filter(current_version));
+    }
+
+    let result = state.db
         .collection::<User>("users")
-        .update_one(doc! { "_id": user_oid }, update, None).await
+        .update_one(filter, update, None).await
         .map_err(|e| {
             tracing::error!("Database update failed: {}", e);
             AppError::InternalError(e.into())
         })?;
 
+    if result.matched_count == 0 {
+        return Err(versioning::conflict("health profile"));
+    }
+    response_cache_service::invalidate_profile(state, user_oid).await;
+    goal_history_service::record_change(state, user_oid, current_profile.as_ref(), &profile).await;
+
     tracing::info!("Successfully created health profile for user: {}", user_id);
 
+    Ok(profile)
+}
+
+/// Asks Gemini for recommendations for the profile's values and targets and
+/// stores them, with the foods to favour and avoid, on the profile. When
+/// Gemini fails the profile still saves, with a note in place of the advice.
+async fn generate_recommendations(state: &AppState, profile: &mut HealthProfile) -> Result<(), AppError> {
+    let condition_guidance = profile.condition_guidance.clone().unwrap_or_default();
+
+    let ai_prompt = state.gemini_service
+        .prompts()
+        .render("health_recommendations", &[
+            ("age", profile.age.to_string()),
+            (
+                "gender",
+                (match profile.gender {
+                    Gender::Male => "male",
+                    Gender::Female => "female",
+                }).to_string(),
+            ),
+            ("height_cm", format!("{:.1}", profile.height_cm)),
+            ("weight_kg", format!("{:.1}", profile.weight_kg)),
+            ("bmi", format!("{:.1}", profile.bmi)),
+            ("bmi_category", profile.bmi_category.clone()),
+            ("activity_level", format!("{:?}", profile.activity_level)),
+            ("goal", format!("{:?}", profile.goal)),
+            ("daily_calories", format!("{:.0}", profile.daily_calories)),
+            ("protein_g", format!("{:.0}", profile.daily_protein_g)),
+            ("carbs_g", format!("{:.0}", profile.daily_carbs_g)),
+            ("fat_g", format!("{:.0}", profile.daily_fat_g)),
+            (
+                "medical_conditions",
+                if let Some(ref conditions) = profile.medical_conditions {
+                    format!("- Medical conditions: {}", conditions.join(", "))
+                } else {
+                    String::new()
+                },
+            ),
+            (
+                "allergies",
+                if let Some(ref allergies) = profile.allergies {
+                    format!("- Allergies: {}", allergies.join(", "))
+                } else {
+                    String::new()
+                },
+            ),
+            (
+                "dietary_preferences",
+                if let Some(ref prefs) = profile.dietary_preferences {
+                    format!("- Dietary preferences: {:?}", prefs)
+                } else {
+                    String::new()
+                },
+            ),
+            ("condition_guidance", condition_guidance::prompt_block(&condition_guidance)),
+        ])
+        .map_err(AppError::InternalError)?;
+
+    let (ai_response, ai_prompt_version) = match
+        state.gemini_service.get_text_response(&ai_prompt.text).await
+    {
+        Ok(response) => {
+            tracing::info!("Successfully generated AI recommendations");
+            (response, Some(ai_prompt.version))
+        }
+        Err(e) => {
+            tracing::error!("Failed to get AI recommendations: {}", e);
+            (
+                "Unable to generate AI recommendations at this time. Please try again later.".to_string(),
+                None,
+            )
+        }
+    };
+
+    profile.recommended_foods = Some(extract_recommended_foods(&ai_response));
+    profile.foods_to_avoid = Some(extract_foods_to_avoid(&ai_response));
+    profile.ai_recommendations = Some(ai_response);
+    profile.ai_recommendations_prompt_version = ai_prompt_version;
+    Ok(())
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/health/survey/draft",
+    tag = "health",
+    request_body = HealthSurveyAnswers,
+    responses((status = 200, description = "Draft saved", body = HealthSurveyDraftResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn save_survey_draft(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<HealthSurveyAnswers>
+) -> Result<impl IntoResponse, AppError> {
+    let user_oid = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    payload.validate()?;
+
+    let mut fields = mongodb::bson
+        ::to_document(&payload)
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    field_encryption::encrypt_fields(&mut fields).map_err(AppError::InternalError)?;
+    fields.insert("updated_at", Utc::now().to_rfc3339());
+
+    state.db
+        .collection::<HealthSurveyDraft>("health_survey_drafts")
+        .update_one(
+            doc! { "user_id": user_oid },
+            doc! { "$set": fields },
+            mongodb::options::UpdateOptions::builder().upsert(true).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    tracing::info!("Saved health survey draft for user: {}", claims.sub);
+
+    draft_response(&state, user_oid).await.map(Json)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/health/survey/draft",
+    tag = "health",
+    responses((status = 200, description = "Saved answers and those still missing", body = HealthSurveyDraftResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_survey_draft(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_oid = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    draft_response(&state, user_oid).await.map(Json)
+}
+
+/// Turns the saved draft into a profile. The draft is removed once the profile
+/// is stored.
+#[utoipa::path(
+    post,
+    path = "/api/health/survey/submit",
+    tag = "health",
+    responses(
+        (status = 200, description = "Profile created from the draft", body = HealthProfileResponse),
+        (status = 404, description = "No saved draft"),
+        (status = 422, description = "The draft is missing answers")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn submit_survey_draft(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_oid = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let draft = state.db
+        .collection::<HealthSurveyDraft>("health_survey_drafts")
+        .find_one(doc! { "user_id": user_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("No saved health survey draft".to_string()))?;
+
+    let answers = HealthSurveyAnswers::from(draft);
+    let missing = answers.missing_fields();
+
+    let (Some(age), Some(gender), Some(height_cm), Some(weight_kg), Some(activity_level), Some(goal)) = (
+        answers.age,
+        answers.gender,
+        answers.height_cm,
+        answers.weight_kg,
+        answers.activity_level,
+        answers.goal,
+    ) else {
+        return Err(
+            AppError::ValidationError(
+                format!("Health survey is incomplete. Missing: {}", missing.join(", "))
+            )
+        );
+    };
+
+    let payload = CreateHealthProfileRequest {
+        age,
+        gender,
+        height_cm,
+        weight_kg,
+        activity_level,
+        goal,
+        macro_strategy: answers.macro_strategy,
+        medical_conditions: answers.medical_conditions,
+        blood_pressure: answers.blood_pressure,
+        fasting_blood_sugar: answers.fasting_blood_sugar,
+        allergies: answers.allergies,
+        dietary_preferences: answers.dietary_preferences,
+    };
+
+    let profile = save_profile(&state, claims.sub, payload, None).await?;
+
+    state.db
+        .collection::<HealthSurveyDraft>("health_survey_drafts")
+        .delete_one(doc! { "user_id": user_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
     Ok((
         StatusCode::OK,
         Json(HealthProfileResponse {
@@ -192,6 +585,36 @@ pub async fn create_or_update_profile(
     ))
 }
 
+async fn draft_response(
+    state: &AppState,
+    user_oid: ObjectId
+) -> Result<HealthSurveyDraftResponse, AppError> {
+    let draft = state.db
+        .collection::<HealthSurveyDraft>("health_survey_drafts")
+        .find_one(doc! { "user_id": user_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let updated_at = draft.as_ref().map(|d| d.updated_at.to_rfc3339());
+    let answers = draft.map(HealthSurveyAnswers::from).unwrap_or_default();
+
+    Ok(HealthSurveyDraftResponse {
+        success: true,
+        missing_fields: answers.missing_fields(),
+        answers,
+        updated_at,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/health/profile",
+    tag = "health",
+    responses(
+        (status = 200, description = "The health profile, with its version in `ETag`"),
+        (status = 404, description = "No health profile yet")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_profile(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>
@@ -208,7 +631,10 @@ pub async fn get_profile(
         .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
     match user.health_profile {
-        Some(profile) => Ok((StatusCode::OK, Json(profile))),
+        Some(profile) => {
+            let etag = versioning::etag(profile.version);
+            Ok((StatusCode::OK, [(header::ETAG, etag)], Json(profile)))
+        }
         None =>
             Err(
                 AppError::NotFound(
@@ -218,6 +644,553 @@ pub async fn get_profile(
     }
 }
 
+/// Body of `PATCH /api/health/profile`: any survey answers, without the
+/// rest of the survey.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateHealthProfileRequest {
+    #[serde(flatten)]
+    pub answers: HealthSurveyAnswers,
+    /// Ask Gemini for new recommendations. Otherwise the current ones stay.
+    #[serde(default)]
+    pub regenerate_recommendations: bool,
+}
+
+/// Updates some answers of an existing profile, e.g. a new weight or goal,
+/// and recalculates BMI, BMR, TDEE and the calorie and macro targets. A new
+/// goal replaces an applied goal preset; the target cycle is kept.
+#[utoipa::path(
+    patch,
+    path = "/api/health/profile",
+    tag = "health",
+    params(("If-Match" = Option<String>, Header, description = "Profile version last read, from its `ETag`")),
+    request_body = UpdateHealthProfileRequest,
+    responses(
+        (status = 200, description = "Profile updated and targets recalculated", body = HealthProfileResponse),
+        (status = 409, description = "The profile changed since the `If-Match` version")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn update_profile(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateHealthProfileRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_oid = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let expected_version = versioning::expected_version(&headers)?;
+
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let mut profile = user.health_profile.ok_or_else(||
+        AppError::NotFound("Health profile not found. Please complete the health survey.".to_string())
+    )?;
+
+    if expected_version.is_some_and(|v| v != profile.version) {
+        return Err(versioning::conflict("health profile"));
+    }
+
+    let answers = payload.answers;
+    answers.validate()?;
+    if let Some(macro_strategy) = &answers.macro_strategy {
+        macro_strategy.validate().map_err(AppError::BadRequest)?;
+    }
+
+    let previous = profile.clone();
+    if let Some(goal) = answers.goal {
+        profile.goal = goal;
+        profile.goal_preset = None;
+    }
+    profile.age = answers.age.unwrap_or(profile.age);
+    profile.gender = answers.gender.unwrap_or(profile.gender);
+    profile.height_cm = answers.height_cm.unwrap_or(profile.height_cm);
+    profile.weight_kg = answers.weight_kg.unwrap_or(profile.weight_kg);
+    profile.activity_level = answers.activity_level.unwrap_or(profile.activity_level);
+    profile.macro_strategy = answers.macro_strategy.unwrap_or(profile.macro_strategy);
+    if answers.medical_conditions.is_some() {
+        profile.medical_conditions = answers.medical_conditions;
+    }
+    if answers.blood_pressure.is_some() {
+        profile.blood_pressure = answers.blood_pressure;
+    }
+    if answers.fasting_blood_sugar.is_some() {
+        profile.fasting_blood_sugar = answers.fasting_blood_sugar;
+    }
+    if answers.allergies.is_some() {
+        profile.allergies = answers.allergies;
+    }
+    if answers.dietary_preferences.is_some() {
+        profile.dietary_preferences = answers.dietary_preferences;
+    }
+
+    profile.bmi = HealthProfile::calculate_bmi(profile.weight_kg, profile.height_cm);
+    profile.bmi_category = HealthProfile::bmi_category(profile.bmi);
+    profile.bmr = HealthProfile::calculate_bmr(profile.weight_kg, profile.height_cm, profile.age, &profile.gender);
+    profile.tdee = HealthProfile::calculate_tdee(profile.bmr, &profile.activity_level);
+    let (daily_calories, (protein_g, carbs_g, fat_g)) = profile.daily_targets(profile.tdee);
+    profile.daily_calories = daily_calories;
+    profile.daily_protein_g = protein_g;
+    profile.daily_carbs_g = carbs_g;
+    profile.daily_fat_g = fat_g;
+    // Carb limits in the guidance scale with the daily target.
+    profile.condition_guidance = Some(
+        condition_guidance::build(profile.medical_conditions.as_deref(), carbs_g)
+    );
+
+    if payload.regenerate_recommendations {
+        generate_recommendations(&state, &mut profile).await?;
+    }
+
+    profile.version += 1;
+    profile.updated_at = Utc::now();
+
+    let profile_bson = field_encryption::profile_to_bson(&profile).map_err(AppError::InternalError)?;
+
+    let result = state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! {
+                "_id": user_oid,
+                "health_profile.version": versioning::version_filter(profile.version - 1),
+            },
+            doc! {
+                "$set": {
+                    "health_profile": profile_bson,
+                    "updated_at": Utc::now(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.matched_count == 0 {
+        return Err(versioning::conflict("health profile"));
+    }
+    response_cache_service::invalidate_profile(&state, user_oid).await;
+    report_service::invalidate_compliance_score(&state, user_oid).await;
+    goal_history_service::record_change(&state, user_oid, Some(&previous), &profile).await;
+
+    tracing::info!("Updated health profile of user {}", claims.sub);
+
+    Ok((
+        StatusCode::OK,
+        [(header::ETAG, versioning::etag(profile.version))],
+        Json(HealthProfileResponse {
+            success: true,
+            profile,
+            message: "Health profile updated successfully!".to_string(),
+        }),
+    ))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GoalPresetsResponse {
+    pub success: bool,
+    #[schema(value_type = Vec<Object>)]
+    pub presets: &'static [GoalPreset],
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/health/presets",
+    tag = "health",
+    responses((status = 200, description = "Available goal presets", body = GoalPresetsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_goal_presets() -> Json<GoalPresetsResponse> {
+    Json(GoalPresetsResponse {
+        success: true,
+        presets: goal_presets::PRESETS,
+    })
+}
+
+/// Switches the profile to a preset's goal, macro split and food guidance.
+/// Submitting the survey again goes back to the plain goal.
+#[utoipa::path(
+    post,
+    path = "/api/health/presets/{id}/apply",
+    tag = "health",
+    params(("id" = String, Path, description = "Goal preset ID"), ("If-Match" = Option<String>, Header, description = "Profile version last read, from its `ETag`")),
+    responses(
+        (status = 200, description = "Preset applied; the updated profile"),
+        (status = 404, description = "Goal preset or health profile not found"),
+        (status = 409, description = "The profile changed since the `If-Match` version")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn apply_goal_preset(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(preset_id): Path<String>,
+    headers: HeaderMap
+) -> Result<impl IntoResponse, AppError> {
+    let user_oid = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let expected_version = versioning::expected_version(&headers)?;
+
+    let preset = goal_presets
+        ::find(&preset_id)
+        .ok_or_else(|| AppError::NotFound(format!("Goal preset '{}' not found", preset_id)))?;
+
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let profile = user.health_profile.ok_or_else(||
+        AppError::NotFound("Health profile not found. Please complete the health survey.".to_string())
+    )?;
+
+    let profile = goal_presets::apply(&state, user_oid, &profile, preset, expected_version).await?;
+
+    tracing::info!("Applied goal preset {} to profile of user {}", preset.id, claims.sub);
+
+    Ok((StatusCode::OK, [(header::ETAG, versioning::etag(profile.version))], Json(profile)))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GoalEraDto {
+    pub id: String,
+    pub goal: HealthGoal,
+    pub goal_preset_id: Option<String>,
+    pub start_weight_kg: f64,
+    pub target_weight_kg: f64,
+    pub daily_calories: f64,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub current: bool,
+    #[schema(value_type = Object)]
+    pub progress: GoalEraProgress,
+}
+
+impl GoalEraDto {
+    fn new(era: GoalEra, progress: GoalEraProgress) -> Self {
+        GoalEraDto {
+            id: era.id.map(|id| id.to_hex()).unwrap_or_default(),
+            goal: era.goal,
+            goal_preset_id: era.goal_preset_id,
+            start_weight_kg: era.start_weight_kg,
+            target_weight_kg: era.target_weight_kg,
+            daily_calories: era.daily_calories,
+            started_at: era.started_at.to_rfc3339(),
+            current: era.ended_at.is_none(),
+            ended_at: era.ended_at.map(|at| at.to_rfc3339()),
+            progress,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GoalHistoryResponse {
+    pub success: bool,
+    /// Newest first.
+    pub eras: Vec<GoalEraDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GoalEraResponse {
+    pub success: bool,
+    pub era: GoalEraDto,
+    pub weigh_ins: Vec<WeightLogDto>,
+}
+
+async fn find_user(state: &AppState, user_id: ObjectId) -> Result<User, AppError> {
+    state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+/// Every goal the user has had, each with the weight progress made while it
+/// was current.
+#[utoipa::path(
+    get,
+    path = "/api/health/goals",
+    tag = "health",
+    responses((status = 200, description = "Every goal era, newest first", body = GoalHistoryResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_goal_history(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let user = find_user(&state, user_id).await?;
+
+    let mut eras = Vec::new();
+    for era in goal_history_service::eras(&state, user_id, user.health_profile.as_ref()).await?.into_iter().rev() {
+        let progress = goal_history_service::progress(&state, &era).await?;
+        eras.push(GoalEraDto::new(era, progress));
+    }
+
+    Ok(Json(GoalHistoryResponse { success: true, eras }))
+}
+
+/// One goal era with its weigh-ins.
+#[utoipa::path(
+    get,
+    path = "/api/health/goals/{id}",
+    tag = "health",
+    params(("id" = String, Path, description = "Goal era ID")),
+    responses(
+        (status = 200, description = "The goal era with its weigh-ins", body = GoalEraResponse),
+        (status = 404, description = "Goal era not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_goal_era(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let era_id = ObjectId::parse_str(&id).map_err(|_| AppError::BadRequest("Invalid goal ID".to_string()))?;
+    let user = find_user(&state, user_id).await?;
+
+    let era = goal_history_service
+        ::eras(&state, user_id, user.health_profile.as_ref()).await?
+        .into_iter()
+        .find(|era| era.id == Some(era_id))
+        .ok_or_else(|| AppError::NotFound("Goal not found".to_string()))?;
+
+    let weigh_ins = goal_history_service::weigh_ins(&state, &era).await?;
+    let progress = goal_history_service::progress_from(&era, &weigh_ins);
+
+    Ok(
+        Json(GoalEraResponse {
+            success: true,
+            era: GoalEraDto::new(era, progress),
+            weigh_ins: weigh_ins
+                .iter()
+                .map(|log| weight::to_dto(log, &weigh_ins))
+                .collect(),
+        })
+    )
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TargetCycleRequest {
+    /// Weekdays not listed in any entry use the profile's daily targets; an
+    /// empty list turns the cycle off.
+    pub days: Vec<CycleDayTargets>,
+}
+
+fn validate_target_cycle(days: &mut [CycleDayTargets]) -> Result<(), AppError> {
+    let mut seen = Vec::new();
+    for day in days.iter_mut() {
+        day.label = day.label.trim().to_string();
+        if day.label.is_empty() || day.label.chars().count() > 50 {
+            return Err(AppError::BadRequest("Each entry needs a label of at most 50 characters".to_string()));
+        }
+        if day.days_of_week.is_empty() || day.days_of_week.iter().any(|d| !(1..=7).contains(d)) {
+            return Err(
+                AppError::BadRequest("days_of_week must use 1 (Monday) through 7 (Sunday)".to_string())
+            );
+        }
+        day.days_of_week.sort_unstable();
+        day.days_of_week.dedup();
+        if day.days_of_week.iter().any(|d| seen.contains(d)) {
+            return Err(AppError::BadRequest("A weekday can only be in one entry".to_string()));
+        }
+        seen.extend_from_slice(&day.days_of_week);
+
+        if !(800.0..=10000.0).contains(&day.calories) {
+            return Err(AppError::BadRequest("calories must be between 800 and 10000".to_string()));
+        }
+        if [day.protein_g, day.carbs_g, day.fat_g].iter().any(|g| !(0.0..=1000.0).contains(g)) {
+            return Err(AppError::BadRequest("Macro targets must be between 0 and 1000 g".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Sets different targets for some weekdays, e.g. more carbs on training
+/// days. They are used for daily totals, stats, reports and the compliance
+/// score in place of the flat daily targets.
+#[utoipa::path(
+    put,
+    path = "/api/health/target-cycle",
+    tag = "health",
+    params(("If-Match" = Option<String>, Header, description = "Profile version last read, from its `ETag`")),
+    request_body = TargetCycleRequest,
+    responses(
+        (status = 200, description = "Cycle saved; the updated profile"),
+        (status = 409, description = "The profile changed since the `If-Match` version")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn set_target_cycle(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(mut payload): Json<TargetCycleRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_oid = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+    let expected_version = versioning::expected_version(&headers)?;
+
+    validate_target_cycle(&mut payload.days)?;
+
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let mut profile = user.health_profile.ok_or_else(||
+        AppError::NotFound("Health profile not found. Please complete the health survey.".to_string())
+    )?;
+
+    if expected_version.is_some_and(|v| v != profile.version) {
+        return Err(versioning::conflict("health profile"));
+    }
+
+    profile.target_cycle = payload.days;
+    profile.version += 1;
+    profile.updated_at = Utc::now();
+
+    let profile_bson = field_encryption::profile_to_bson(&profile).map_err(AppError::InternalError)?;
+
+    let result = state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! {
+                "_id": user_oid,
+                "health_profile.version": versioning::version_filter(profile.version - 1),
+            },
+            doc! {
+                "$set": {
+                    "health_profile": profile_bson,
+                    "updated_at": Utc::now(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.matched_count == 0 {
+        return Err(versioning::conflict("health profile"));
+    }
+    response_cache_service::invalidate_profile(&state, user_oid).await;
+    report_service::invalidate_compliance_score(&state, user_oid).await;
+
+    tracing::info!("Updated target cycle of user {} ({} entries)", claims.sub, profile.target_cycle.len());
+
+    Ok((StatusCode::OK, [(header::ETAG, versioning::etag(profile.version))], Json(profile)))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct HealthSummaryQuery {
+    /// Days covered, ending `end`. Defaults to 30.
+    pub days: Option<i64>,
+    /// `YYYY-MM-DD`; defaults to today (UTC).
+    pub end: Option<String>,
+    /// `json` (default) or `pdf`.
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthSummaryResponse {
+    pub success: bool,
+    #[schema(value_type = Object)]
+    pub summary: HealthSummary,
+}
+
+/// Profile, vitals, weight trend and average intake in one document for a
+/// medical appointment, as JSON or a printable PDF.
+#[utoipa::path(
+    get,
+    path = "/api/health/summary",
+    tag = "health",
+    params(HealthSummaryQuery),
+    responses(
+        (
+            status = 200,
+            description = "The summary as JSON, or as a PDF with `format=pdf`",
+            content(("application/json" = HealthSummaryResponse), ("application/pdf" = String))
+        )
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_health_summary(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<HealthSummaryQuery>
+) -> Result<Response, AppError> {
+    let user_oid = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let days = query.days.unwrap_or(health_summary_service::DEFAULT_SUMMARY_DAYS);
+    if !(1..=health_summary_service::MAX_SUMMARY_DAYS).contains(&days) {
+        return Err(
+            AppError::BadRequest(
+                format!("days must be between 1 and {}", health_summary_service::MAX_SUMMARY_DAYS)
+            )
+        );
+    }
+
+    let end = match query.end.as_deref() {
+        Some(end) =>
+            NaiveDate::parse_from_str(end, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid end format. Use YYYY-MM-DD".to_string())
+            )?,
+        None => Utc::now().date_naive(),
+    };
+
+    let as_pdf = match query.format.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("json") => false,
+        Some("pdf") => true,
+        Some(_) => {
+            return Err(AppError::BadRequest("format must be json or pdf".to_string()));
+        }
+    };
+
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let summary = health_summary_service::build_summary(&state, &user, end, days).await?;
+
+    tracing::info!(
+        "Built {} health summary for user {} ({} days to {})",
+        if as_pdf { "PDF" } else { "JSON" },
+        claims.sub,
+        days,
+        end
+    );
+
+    if !as_pdf {
+        return Ok(Json(HealthSummaryResponse { success: true, summary }).into_response());
+    }
+
+    let pdf = pdf_service::render_health_summary(&summary, &state.config.branding.app_name);
+    let filename = format!("health-summary-{}-to-{}.pdf", summary.period_start, summary.period_end);
+
+    Ok(
+        (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+            ],
+            pdf,
+        ).into_response()
+    )
+}
+
 fn extract_recommended_foods(ai_response: &str) -> Vec<String> {
     let mut foods = Vec::new();
     for line in ai_response.lines() {
@@ -226,10 +1199,10 @@ fn extract_recommended_foods(ai_response: &str) -> Vec<String> {
             trimmed.starts_with('-') ||
             trimmed.starts_with('•') ||
             (trimmed.len() > 2 &&
-                trimmed.chars().nth(0).unwrap().is_numeric() &&
+                trimmed.chars().next().unwrap().is_numeric() &&
                 trimmed.chars().nth(1) == Some('.'))
         {
-            if let Some(food) = trimmed.split_once(|c: char| (c == '-' || c == '•' || c == '.')) {
+            if let Some(food) = trimmed.split_once(['-', '•', '.']) {
                 let food_name = food.1.trim().to_string();
                 if !food_name.is_empty() && food_name.len() < 100 {
                     foods.push(food_name);
@@ -252,13 +1225,11 @@ fn extract_foods_to_avoid(ai_response: &str) -> Vec<String> {
                 trimmed.starts_with('-') ||
                 trimmed.starts_with('•') ||
                 (trimmed.len() > 2 &&
-                    trimmed.chars().nth(0).unwrap().is_numeric() &&
+                    trimmed.chars().next().unwrap().is_numeric() &&
                     trimmed.chars().nth(1) == Some('.'))
             {
                 if
-                    let Some(food) = trimmed.split_once(
-                        |c: char| (c == '-' || c == '•' || c == '.')
-                    )
+                    let Some(food) = trimmed.split_once(['-', '•', '.'])
                 {
                     let food_name = food.1.trim().to_string();
                     if !food_name.is_empty() && food_name.len() < 100 {
@@ -0,0 +1,57 @@
+use axum::{
+    extract::{ Path, Query, State },
+    http::{ header, StatusCode },
+    response::IntoResponse,
+};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{ db::AppState, error::AppError, services::image_service };
+
+/// Images never change, but the URL is a credential until it expires.
+const IMAGE_CACHE_CONTROL: &str = "private, max-age=3600, immutable";
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SignedImageQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// Public: authorized only by the signature `image_service::signed_url`
+/// puts in the query, so `<img>` tags can load it.
+#[utoipa::path(
+    get,
+    path = "/api/images/{id}",
+    tag = "images",
+    params(("id" = String, Path, description = "Image ID"), SignedImageQuery),
+    responses(
+        (status = 200, description = "The image bytes", content_type = "image/*"),
+        (status = 404, description = "Unknown image, bad signature or expired link")
+    ),
+    security(())
+)]
+pub async fn get_image(
+    State(state): State<AppState>,
+    Path(image_id): Path<String>,
+    Query(query): Query<SignedImageQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let invalid = || AppError::NotFound("Image not found or link expired".to_string());
+
+    let image_oid = ObjectId::parse_str(&image_id).map_err(|_| invalid())?;
+    if !image_service::verify_signature(&state.config, image_oid, query.expires, &query.signature) {
+        return Err(invalid());
+    }
+
+    let image = image_service::load_image(&state.db, image_oid).await?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, image.content_type),
+            (header::CACHE_CONTROL, IMAGE_CACHE_CONTROL.to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        image.bytes,
+    ))
+}
@@ -0,0 +1,163 @@
+use axum::{
+    extract::{ ws::{ CloseFrame, Message, WebSocket, WebSocketUpgrade }, Query, State },
+    http::{ header, HeaderMap, StatusCode },
+    response::{ IntoResponse, Response },
+    Json,
+};
+use chrono::{ NaiveDate, Utc };
+use mongodb::bson::oid::ObjectId;
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    db::AppState,
+    handlers::meals::{ self, DailyTotals },
+    middleware::auth,
+    services::{ progress_service, timezone },
+};
+
+/// Close code sent when the access token the connection was opened with
+/// expires; reconnect with a fresh one.
+const TOKEN_EXPIRED_CLOSE_CODE: u16 = 4001;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LiveQuery {
+    /// Access token, for clients that can't set `Authorization` on the
+    /// handshake, such as browsers.
+    pub token: Option<String>,
+}
+
+/// A message pushed over `/api/ws`, told apart by `type`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveMessage {
+    /// Totals of a UTC day, as in `GET /api/meals/daily`.
+    DailyTotals {
+        date: String,
+        daily_totals: DailyTotals,
+    },
+}
+
+/// Opens a WebSocket that pushes a day's `DailyTotals` whenever a meal or
+/// water entry of that day is logged, edited or deleted, starting with today's.
+/// Takes the access token from `Authorization` or `?token=`.
+#[utoipa::path(
+    get,
+    path = "/api/ws",
+    tag = "live",
+    params(LiveQuery),
+    responses(
+        (status = 101, description = "Switched to WebSocket; messages are `LiveMessage` JSON", body = LiveMessage),
+        (status = 401, description = "Missing, invalid or expired token")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn live_updates(
+    State(state): State<AppState>,
+    Query(query): Query<LiveQuery>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade
+) -> Result<Response, Response> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or(query.token)
+        .ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({
+                    "error": "Missing access token"
+                })),
+            ).into_response()
+        })?;
+
+    let claims = auth::authenticate(&state, &token).await?;
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Invalid user ID in token"
+            })),
+        ).into_response()
+    })?;
+    let expires_in = Duration::from_secs((claims.exp - Utc::now().timestamp()).max(0) as u64);
+
+    Ok(ws.on_upgrade(move |socket| stream_updates(state, user_id, expires_in, socket)))
+}
+
+async fn stream_updates(state: AppState, user_id: ObjectId, expires_in: Duration, mut socket: WebSocket) {
+    let mut changes = state.live.subscribe(user_id);
+    let expiry = tokio::time::sleep(expires_in);
+    tokio::pin!(expiry);
+
+    // Today at the user's offset, which keys their daily totals.
+    let today = match progress_service::user_offset(&state, user_id).await {
+        Ok(offset) => timezone::today(offset),
+        Err(e) => {
+            tracing::error!("Failed to look up the offset of user {}: {}", user_id, e);
+            Utc::now().date_naive()
+        }
+    };
+    let mut open = send_totals(&state, user_id, today, &mut socket).await;
+    while open {
+        tokio::select! {
+            change = changes.recv() => {
+                open = match change {
+                    Ok(day) => send_totals(&state, user_id, day, &mut socket).await,
+                    // Some days were skipped; the client can refetch if it cares.
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!("Live updates for user {} skipped {} changes", user_id, skipped);
+                        true
+                    }
+                    Err(RecvError::Closed) => false,
+                };
+            }
+            message = socket.recv() => {
+                // Pings are answered by axum; nothing else from the client is expected.
+                open = !matches!(message, None | Some(Err(_)) | Some(Ok(Message::Close(_))));
+            }
+            _ = &mut expiry => {
+                let close = CloseFrame {
+                    code: TOKEN_EXPIRED_CLOSE_CODE,
+                    reason: "Token expired".into(),
+                };
+                let _ = socket.send(Message::Close(Some(close))).await;
+                open = false;
+            }
+        }
+    }
+
+    drop(changes);
+    state.live.release(user_id);
+}
+
+/// Sends the day's totals. `false` once the client is gone; failing to
+/// compute the totals is logged and skipped.
+async fn send_totals(state: &AppState, user_id: ObjectId, day: NaiveDate, socket: &mut WebSocket) -> bool {
+    let totals = match progress_service::progress_for_day(state, user_id, day).await {
+        Ok(progress) => meals::calculate_daily_totals(state, user_id, day, progress).await,
+        Err(e) => Err(e),
+    };
+    let daily_totals = match totals {
+        Ok(daily_totals) => daily_totals,
+        Err(e) => {
+            tracing::error!("Failed to compute live totals for user {}: {}", user_id, e);
+            return true;
+        }
+    };
+
+    let message = LiveMessage::DailyTotals {
+        date: day.format("%Y-%m-%d").to_string(),
+        daily_totals,
+    };
+    let Ok(text) = serde_json::to_string(&message) else {
+        return true;
+    };
+
+    socket.send(Message::Text(text)).await.is_ok()
+}
@@ -0,0 +1,268 @@
+use axum::{ extract::{ Path, State }, http::StatusCode, response::{ IntoResponse, Json }, Extension };
+use chrono::{ Duration, NaiveDate, Utc };
+use mongodb::bson::oid::ObjectId;
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, MealPlan, MealPlanDay, MealType, PlannedIngredient, PlannedMeal },
+    services::meal_plan_service,
+};
+
+/// Plans returned by the list endpoint.
+const LIST_LIMIT: i64 = 20;
+/// How far ahead a plan may start.
+const MAX_START_DAYS_AHEAD: i64 = 30;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GenerateMealPlanRequest {
+    /// `YYYY-MM-DD`; defaults to today.
+    pub start_date: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlannedMealDto {
+    pub index: usize,
+    pub meal_type: MealType,
+    pub food_name: String,
+    pub description: Option<String>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: Option<String>,
+    pub ingredients: Vec<PlannedIngredient>,
+    pub eaten: bool,
+    pub meal_log_id: Option<String>,
+    pub eaten_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MealPlanDayDto {
+    pub date: String,
+    pub total_calories: f64,
+    pub meals: Vec<PlannedMealDto>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MealPlanDto {
+    pub id: String,
+    pub start_date: String,
+    pub target_calories: f64,
+    pub days: Vec<MealPlanDayDto>,
+    pub prompt_version: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<MealPlan> for MealPlanDto {
+    fn from(plan: MealPlan) -> Self {
+        Self {
+            id: plan.id.map(|id| id.to_hex()).unwrap_or_default(),
+            start_date: plan.start_date,
+            target_calories: plan.target_calories,
+            days: plan.days.into_iter().map(Into::into).collect(),
+            prompt_version: plan.prompt_version,
+            created_at: plan.created_at.to_rfc3339(),
+            updated_at: plan.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+impl From<MealPlanDay> for MealPlanDayDto {
+    fn from(day: MealPlanDay) -> Self {
+        Self {
+            date: day.date,
+            total_calories: day.meals
+                .iter()
+                .map(|m| m.calories)
+                .sum(),
+            meals: day.meals
+                .into_iter()
+                .enumerate()
+                .map(|(index, meal)| PlannedMealDto::from_planned(index, meal))
+                .collect(),
+        }
+    }
+}
+
+impl PlannedMealDto {
+    fn from_planned(index: usize, meal: PlannedMeal) -> Self {
+        Self {
+            index,
+            meal_type: meal.meal_type,
+            food_name: meal.food_name,
+            description: meal.description,
+            calories: meal.calories,
+            protein_g: meal.protein_g,
+            carbs_g: meal.carbs_g,
+            fat_g: meal.fat_g,
+            serving_size: meal.serving_size,
+            ingredients: meal.ingredients,
+            eaten: meal.eaten_at.is_some(),
+            meal_log_id: meal.meal_log_id.map(|id| id.to_hex()),
+            eaten_at: meal.eaten_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MealPlanResponse {
+    pub success: bool,
+    pub plan: MealPlanDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MealPlansListResponse {
+    pub success: bool,
+    pub plans: Vec<MealPlanDto>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/meal-plans/generate",
+    tag = "meal_plans",
+    request_body = GenerateMealPlanRequest,
+    responses((status = 201, description = "A week of meals fitted to the user's targets", body = MealPlanResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn generate_meal_plan(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<GenerateMealPlanRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let today = Utc::now().date_naive();
+    let start_date = match payload.start_date.as_deref() {
+        Some(date) =>
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid date format. Use YYYY-MM-DD".to_string())
+            )?,
+        None => today,
+    };
+    if start_date < today || start_date > today + Duration::days(MAX_START_DAYS_AHEAD) {
+        return Err(
+            AppError::BadRequest(
+                format!("start_date must be between today and {} days from now", MAX_START_DAYS_AHEAD)
+            )
+        );
+    }
+
+    tracing::info!("Generating meal plan from {} for user {}", start_date, claims.sub);
+
+    let plan = meal_plan_service::generate(&state, user_id, start_date).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealPlanResponse {
+            success: true,
+            plan: plan.into(),
+        }),
+    ))
+}
+
+/// The 20 most recent plans, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/meal-plans",
+    tag = "meal_plans",
+    responses((status = 200, description = "The 20 most recent plans", body = MealPlansListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_meal_plans(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let plans = meal_plan_service::list_plans(&state, user_id, LIST_LIMIT).await?;
+
+    Ok(
+        Json(MealPlansListResponse {
+            success: true,
+            plans: plans.into_iter().map(Into::into).collect(),
+        })
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/meal-plans/{id}",
+    tag = "meal_plans",
+    params(("id" = String, Path, description = "Meal plan ID")),
+    responses(
+        (status = 200, description = "The meal plan", body = MealPlanResponse),
+        (status = 404, description = "Meal plan not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_meal_plan(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(plan_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let plan_oid = ObjectId::parse_str(&plan_id).map_err(|_|
+        AppError::BadRequest("Invalid meal plan ID".to_string())
+    )?;
+
+    let plan = meal_plan_service::find_plan(&state, user_id, plan_oid).await?;
+
+    Ok(
+        Json(MealPlanResponse {
+            success: true,
+            plan: plan.into(),
+        })
+    )
+}
+
+/// Replaces one day's meals with a fresh AI suggestion. Days with eaten meals
+/// can't be regenerated.
+#[utoipa::path(
+    post,
+    path = "/api/meal-plans/{id}/days/{date}/regenerate",
+    tag = "meal_plans",
+    params(
+        ("id" = String, Path, description = "Meal plan ID"),
+        ("date" = String, Path, description = "Day of the plan, `YYYY-MM-DD`")
+    ),
+    responses(
+        (status = 200, description = "Day regenerated", body = MealPlanResponse),
+        (status = 404, description = "Meal plan or day not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn regenerate_meal_plan_day(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((plan_id, date)): Path<(String, String)>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let plan_oid = ObjectId::parse_str(&plan_id).map_err(|_|
+        AppError::BadRequest("Invalid meal plan ID".to_string())
+    )?;
+
+    let plan = meal_plan_service::regenerate_day(&state, user_id, plan_oid, &date).await?;
+
+    tracing::info!("Regenerated {} of meal plan {} for user {}", date, plan_id, claims.sub);
+
+    Ok(
+        Json(MealPlanResponse {
+            success: true,
+            plan: plan.into(),
+        })
+    )
+}
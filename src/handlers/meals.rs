@@ -1,37 +1,295 @@
 use axum::{
     extract::{ Path, Query, State },
-    http::StatusCode,
-    response::IntoResponse,
+    http::{ header, HeaderMap, StatusCode },
+    response::{ IntoResponse, Response },
     Extension,
     Json,
 };
-use chrono::{ DateTime, NaiveDate, Utc, TimeZone };
-use mongodb::bson::{ doc, oid::ObjectId };
+use axum_extra::extract::Multipart;
+use chrono::{ DateTime, FixedOffset, NaiveDate, NaiveTime, Utc, TimeZone };
+use mongodb::{
+    bson::{ doc, oid::ObjectId },
+    options::{ FindOneAndUpdateOptions, FindOptions, ReturnDocument },
+};
 use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
 use futures::TryStreamExt;
 
-use crate::{ db::AppState, error::AppError, models::* };
+use crate::{
+    db::AppState,
+    error::AppError,
+    handlers::nutrition,
+    models::*,
+    services::{
+        cache_service,
+        fdc_service::FoodDetails,
+        food_analysis::{ FoodAnalysis, FoodImageAnalysis },
+        food_lookup_service,
+        food_quality_service::{ self, FoodQuality },
+        custom_food_service,
+        event_service,
+        favorite_service,
+        meal_history_service::{ self, ChangeSource },
+        meal_plan_service,
+        monthly_stats_service,
+        pdf_service,
+        progress_service,
+        recipe_nutrition_service,
+        report_service,
+        timezone,
+        versioning,
+        water_service,
+        weight_service,
+    },
+    validation::{ Checks, Validate },
+};
+
+/// Upper bounds for one meal or item, well above any real portion.
+const MAX_CALORIES: f64 = 10_000.0;
+const MAX_MACRO_G: f64 = 1_000.0;
+const MAX_SODIUM_MG: f64 = 50_000.0;
+const MAX_PORTION_G: f64 = 5_000.0;
+const MAX_SEARCH_QUERY_LENGTH: usize = 100;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LogMealRequest {
+    pub meal_type: MealType,
+    pub food_name: String,
+    /// Required without `items`; with them, the meal's values are their sums.
+    pub calories: Option<f64>,
+    pub protein_g: Option<f64>,
+    pub carbs_g: Option<f64>,
+    pub fat_g: Option<f64>,
+    pub fiber_g: Option<f64>,
+    pub sugar_g: Option<f64>,
+    pub sodium_mg: Option<f64>,
+    /// The foods of a meal made of several, e.g. a plate of rice and chicken.
+    pub items: Option<Vec<MealEntry>>,
+    pub serving_size: Option<String>,
+    pub notes: Option<String>,
+    /// Where the values came from; defaults to `manual`, or on an edit to the
+    /// meal's current source. `fdc` and `barcode` are set by the server only.
+    pub source: Option<MealSource>,
+    /// `estimated_weight_g` from the image analysis the macros came from.
+    pub estimated_weight_g: Option<f64>,
+    /// Weight the user says they actually ate; macros are rescaled from the estimate.
+    pub corrected_weight_g: Option<f64>,
+    /// `YYYY-MM-DD` (UTC) for backfilling a past day; defaults to today.
+    pub date: Option<String>,
+    /// `HH:MM` (UTC). Defaults to now for today, or a typical time for the
+    /// meal type on past days.
+    pub time: Option<String>,
+    /// Hunger before eating, 1 (not hungry) to 5 (starving).
+    pub hunger_level: Option<i32>,
+    pub mood: Option<MealMood>,
+    pub eating_context: Option<EatingContext>,
+}
+
+impl Validate for LogMealRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut checks = Checks::new();
+        checks
+            .required_text("food_name", &self.food_name, 200)
+            .optional_range("calories", self.calories, 0.0, MAX_CALORIES)
+            .optional_range("protein_g", self.protein_g, 0.0, MAX_MACRO_G)
+            .optional_range("carbs_g", self.carbs_g, 0.0, MAX_MACRO_G)
+            .optional_range("fat_g", self.fat_g, 0.0, MAX_MACRO_G)
+            .optional_range("fiber_g", self.fiber_g, 0.0, MAX_MACRO_G)
+            .optional_range("sugar_g", self.sugar_g, 0.0, MAX_MACRO_G)
+            .optional_range("sodium_mg", self.sodium_mg, 0.0, MAX_SODIUM_MG)
+            .optional_range("estimated_weight_g", self.estimated_weight_g, 0.1, MAX_PORTION_G)
+            .optional_range("corrected_weight_g", self.corrected_weight_g, 0.1, MAX_PORTION_G)
+            .optional_range("hunger_level", self.hunger_level, 1, 5)
+            .optional_max_length("serving_size", self.serving_size.as_deref(), 100)
+            .optional_max_length("notes", self.notes.as_deref(), 1000);
+        if self.source.is_some_and(|source| source.is_verified()) {
+            checks.add("source", "source cannot be fdc or barcode; log those with /api/meals/log-fdc or /api/meals/log-barcode");
+        }
+        for (i, item) in self.items.iter().flatten().enumerate() {
+            check_item(&mut checks, &format!("items[{}].", i), item);
+        }
+        checks.finish()
+    }
+}
+
+/// Optional overrides for `POST /api/meals/log-favorite/:id`.
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct LogFavoriteRequest {
+    /// Defaults to the favorite's meal type.
+    pub meal_type: Option<MealType>,
+    /// Multiplies calories and macros; defaults to 1.
+    pub servings: Option<f64>,
+    pub date: Option<String>,
+    pub time: Option<String>,
+}
+
+/// Optional overrides for `POST /api/meals/:id/repeat`.
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct RepeatMealRequest {
+    /// Defaults to the original meal's type.
+    pub meal_type: Option<MealType>,
+    /// Defaults to today.
+    pub date: Option<String>,
+    /// Defaults to the original meal's time of day, or now when that is
+    /// still to come today.
+    pub time: Option<String>,
+}
+
+/// Body of `POST /api/meals/copy-day`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CopyDayRequest {
+    /// Day to copy from, `YYYY-MM-DD`, at the user's `utc_offset_minutes`.
+    pub source_date: String,
+    /// Day to copy to, `YYYY-MM-DD`; defaults to today.
+    pub target_date: Option<String>,
+    /// Copies only meals of these types; all of them when absent.
+    pub meal_types: Option<Vec<MealType>>,
+}
+
+/// Body of `POST /api/meals/log-custom/:id`. Without `serving` or `grams`,
+/// `servings` counts the food's base serving.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogCustomFoodRequest {
+    pub meal_type: MealType,
+    /// Name of one of the food's serving presets, e.g. "1 cup".
+    pub serving: Option<String>,
+    /// How many of the serving; defaults to 1.
+    pub servings: Option<f64>,
+    /// Weight eaten, instead of a serving.
+    pub grams: Option<f64>,
+    pub date: Option<String>,
+    pub time: Option<String>,
+}
+
+/// Body of `POST /api/meals/log-fdc/:fdc_id` and
+/// `POST /api/meals/log-barcode/:barcode`: either `grams`, or a `portion_id`
+/// from the food's portions and optionally how many `servings`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogFdcFoodRequest {
+    pub meal_type: MealType,
+    pub grams: Option<f64>,
+    pub portion_id: Option<i32>,
+    pub servings: Option<f64>,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+    pub time: Option<String>,
+}
+
+impl Validate for LogFdcFoodRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        Checks::new()
+            .optional_range("grams", self.grams, 0.1, MAX_PORTION_G)
+            .optional_range("servings", self.servings, 0.1, 20.0)
+            .optional_max_length("notes", self.notes.as_deref(), 1000)
+            .finish()
+    }
+}
+
+/// Body of `POST /api/recipes/:meal_id/log`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogRecipeRequest {
+    pub meal_type: MealType,
+    /// Share of the whole recipe eaten, e.g. 0.25 for a quarter; defaults to 1.
+    pub serving_fraction: Option<f64>,
+    pub date: Option<String>,
+    pub time: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogPlannedMealRequest {
+    /// `HH:MM` on the plan day; defaults to now for today, otherwise the usual time for the meal type.
+    pub time: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MealLogDto {
+    pub id: String,
+    pub date: String,
     pub meal_type: MealType,
     pub food_name: String,
     pub calories: f64,
     pub protein_g: f64,
     pub carbs_g: f64,
     pub fat_g: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fiber_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sugar_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sodium_mg: Option<f64>,
+    /// Indexed from 0 by `/api/meals/:id/items/:index`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<MealEntry>,
     pub serving_size: Option<String>,
     pub notes: Option<String>,
+    pub source: MealSource,
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nutrition_snapshot: Option<NutritionSnapshot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub portion_weight_g: Option<f64>,
+    pub auto_logged: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurring_meal_id: Option<String>,
+    pub hunger_level: Option<i32>,
+    pub mood: Option<MealMood>,
+    pub eating_context: Option<EatingContext>,
+    /// Send back as `If-Match: "<version>"` when editing.
+    pub version: i64,
+    pub created_at: String,
 }
 
-#[derive(Debug, Serialize)]
+impl From<MealLog> for MealLogDto {
+    fn from(meal: MealLog) -> Self {
+        Self {
+            id: meal.id.map(|id| id.to_hex()).unwrap_or_default(),
+            date: meal.date.to_rfc3339(),
+            meal_type: meal.meal_type,
+            food_name: meal.food_name,
+            calories: meal.calories,
+            protein_g: meal.protein_g,
+            carbs_g: meal.carbs_g,
+            fat_g: meal.fat_g,
+            fiber_g: meal.fiber_g,
+            sugar_g: meal.sugar_g,
+            sodium_mg: meal.sodium_mg,
+            items: meal.items,
+            serving_size: meal.serving_size,
+            notes: meal.notes,
+            source: meal.source,
+            verified: meal.verified,
+            nutrition_snapshot: meal.nutrition_snapshot,
+            portion_weight_g: meal.portion_weight_g,
+            auto_logged: meal.auto_logged,
+            recurring_meal_id: meal.recurring_meal_id.map(|id| id.to_hex()),
+            hunger_level: meal.hunger_level,
+            mood: meal.mood,
+            eating_context: meal.eating_context,
+            version: meal.version,
+            created_at: meal.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct MealLogResponse {
     pub success: bool,
-    pub meal: MealLog,
+    pub meal: MealLogDto,
     pub daily_totals: DailyTotals,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CopyDayResponse {
+    pub success: bool,
+    /// The new meals, in the order eaten.
+    pub meals: Vec<MealLogDto>,
+    /// Meals left out because their time of day hasn't come yet on the target day.
+    pub skipped: usize,
+    /// Totals of the target day.
+    pub daily_totals: DailyTotals,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DailyTotals {
     pub total_calories: f64,
     pub total_protein_g: f64,
@@ -45,13 +303,61 @@ pub struct DailyTotals {
     pub protein_remaining: f64,
     pub carbs_remaining: f64,
     pub fat_remaining: f64,
+    pub water_ml: f64,
+    pub water_target_ml: f64,
+    pub water_remaining_ml: f64,
+    /// Nutrient density score for the day; `null` before the first meal.
+    #[schema(value_type = Option<Object>)]
+    pub food_quality: Option<FoodQuality>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct DateQuery {
-    pub date: Option<String>, }
+    pub date: Option<String>,
+    /// UTC offset the day is counted in, e.g. `+07:00`; defaults to UTC.
+    pub tz: Option<String>,
+}
 
 
+/// Filters of `GET /api/meals/search`; all optional and combined.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SearchMealsQuery {
+    /// Words to find in the food name or notes, e.g. `ramen`. Matches whole
+    /// words, ignoring case.
+    pub q: Option<String>,
+    pub meal_type: Option<MealType>,
+    pub min_calories: Option<f64>,
+    pub max_calories: Option<f64>,
+    /// First and last day to search, `YYYY-MM-DD`.
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    /// UTC offset days are counted in, e.g. `+07:00`; defaults to UTC.
+    pub tz: Option<String>,
+    pub skip: Option<u64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchMealsResponse {
+    pub success: bool,
+    /// Newest first.
+    pub meals: Vec<MealLogDto>,
+    /// Meals matching the filters, across all pages.
+    pub total: u64,
+    pub skip: u64,
+    pub limit: i64,
+    pub has_more: bool,
+}
+
+
+#[utoipa::path(
+    post,
+    path = "/api/meals/log",
+    tag = "meals",
+    request_body = LogMealRequest,
+    responses((status = 201, description = "Meal logged; the day's new totals", body = MealLogResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn log_meal(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -66,55 +372,1023 @@ pub async fn log_meal(
     let now = Utc::now();
     tracing::info!("Current UTC time: {}", now);
 
+    payload.validate()?;
+    let meal_date = meal_timestamp(payload.date.as_deref(), payload.time.as_deref(), &payload.meal_type, now)?;
+
+    let (scale, portion_weight_g) = portion_scale(
+        payload.estimated_weight_g,
+        payload.corrected_weight_g
+    )?;
+    let (items, nutrition) = request_nutrition(&payload, scale)?;
+
     let meal_log = MealLog {
         id: None,
         user_id,
-        date: now,
+        date: meal_date,
         meal_type: payload.meal_type,
         food_name: payload.food_name.clone(),
-        calories: payload.calories,
-        protein_g: payload.protein_g,
-        carbs_g: payload.carbs_g,
-        fat_g: payload.fat_g,
+        calories: nutrition.calories,
+        protein_g: nutrition.protein_g,
+        carbs_g: nutrition.carbs_g,
+        fat_g: nutrition.fat_g,
+        fiber_g: nutrition.fiber_g,
+        sugar_g: nutrition.sugar_g,
+        sodium_mg: nutrition.sodium_mg,
+        items,
         serving_size: payload.serving_size.clone(),
         notes: payload.notes.clone(),
+        source: payload.source.unwrap_or_default(),
+        verified: false,
+        nutrition_snapshot: None,
+        portion_weight_g,
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: payload.hunger_level,
+        mood: payload.mood,
+        eating_context: payload.eating_context,
+        version: 0,
         created_at: now,
     };
 
+    let (saved_meal, daily_totals) = save_meal(&state, meal_log).await?;
+
+    tracing::info!("Meal logged successfully for user: {}", claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealLogResponse {
+            success: true,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+/// Inserts a new meal, updates the day's progress and checks milestones.
+/// Returns the saved meal and the day's totals.
+async fn save_meal(state: &AppState, meal_log: MealLog) -> Result<(MealLog, DailyTotals), AppError> {
+    let user_id = meal_log.user_id;
+
     tracing::info!(
         "Meal log before insert - date: {:?}, food: {}",
         meal_log.date,
         meal_log.food_name
     );
 
-    let result = state.db
-        .collection::<MealLog>("meal_logs")
-        .insert_one(&meal_log, None).await
-        .map_err(|e| {
-            tracing::error!("Failed to insert meal log: {}", e);
-            AppError::InternalError(e.into())
-        })?;
+    let result = state.db
+        .collection::<MealLog>("meal_logs")
+        .insert_one(&meal_log, None).await
+        .map_err(|e| {
+            tracing::error!("Failed to insert meal log: {}", e);
+            AppError::InternalError(e.into())
+        })?;
+
+    let mut saved_meal = meal_log;
+    saved_meal.id = Some(result.inserted_id.as_object_id().unwrap());
+
+    tracing::info!("Meal inserted with ID: {:?}, date: {:?}", saved_meal.id, saved_meal.date);
+
+    report_service::invalidate_compliance_score(state, user_id).await;
+
+    let progress = progress_service::record_meal_added(state, &saved_meal).await?;
+    let daily_totals = calculate_daily_totals(state, user_id, progress.date.date_naive(), Some(progress)).await?;
+
+    let event_state = state.clone();
+    tokio::spawn(async move {
+        event_service::check_logging_milestones(&event_state, user_id).await;
+    });
+
+    Ok((saved_meal, daily_totals))
+}
+
+/// Form fields of `POST /api/meals/log-from-image` besides `image`.
+#[derive(Debug, Default)]
+struct LogFromImageFields {
+    meal_type: Option<MealType>,
+    corrected_weight_g: Option<f64>,
+    notes: Option<String>,
+    date: Option<String>,
+    time: Option<String>,
+}
+
+/// Multipart body of `POST /api/meals/log-from-image`, for the API docs; the
+/// handler reads the parts into `LogFromImageFields`.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct LogFromImageForm {
+    #[schema(value_type = String, format = Binary)]
+    pub image: Vec<u8>,
+    pub meal_type: MealType,
+    /// Weight actually eaten; the estimate's macros are rescaled to it.
+    pub corrected_weight_g: Option<f64>,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+    pub time: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LogFromImageResponse {
+    pub success: bool,
+    #[schema(value_type = Object)]
+    pub analysis: FoodAnalysis,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+    pub meal: MealLogDto,
+    pub daily_totals: DailyTotals,
+}
+
+/// Analyzes a food photo and logs it as an `ai_image` meal in one call. An
+/// image that isn't food is answered with a 422 carrying the rejection, and
+/// nothing is logged.
+#[utoipa::path(
+    post,
+    path = "/api/meals/log-from-image",
+    tag = "meals",
+    request_body(content = LogFromImageForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Photo analyzed and logged", body = LogFromImageResponse),
+        (status = 422, description = "Not food; nothing was logged", body = nutrition::NutritionAnalysisResponse)
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_meal_from_image(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    mut multipart: Multipart
+) -> Result<Response, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let mut image_data: Option<Vec<u8>> = None;
+    let mut mime_type: Option<String> = None;
+    let mut fields = LogFromImageFields::default();
+
+    while
+        let Some(field) = multipart
+            .next_field().await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read multipart field: {}", e)))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+        if name == "image" {
+            mime_type = field.content_type().map(|ct| ct.to_string());
+            let data = field
+                .bytes().await
+                .map_err(|e| AppError::BadRequest(format!("Failed to read image data: {}", e)))?;
+            image_data = Some(data.to_vec());
+            continue;
+        }
+
+        let value = field
+            .text().await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read field {}: {}", name, e)))?;
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            continue;
+        }
+        match name.as_str() {
+            "meal_type" => {
+                fields.meal_type = Some(
+                    serde_json
+                        ::from_value(serde_json::Value::String(value))
+                        .map_err(|_|
+                            AppError::BadRequest(
+                                "meal_type must be breakfast, lunch, dinner or snack".to_string()
+                            )
+                        )?
+                );
+            }
+            "corrected_weight_g" => {
+                fields.corrected_weight_g = Some(
+                    value
+                        .parse()
+                        .map_err(|_| AppError::BadRequest("corrected_weight_g must be a number".to_string()))?
+                );
+            }
+            "notes" => {
+                fields.notes = Some(value);
+            }
+            "date" => {
+                fields.date = Some(value);
+            }
+            "time" => {
+                fields.time = Some(value);
+            }
+            _ => {}
+        }
+    }
+
+    let meal_type = fields.meal_type.ok_or_else(|| AppError::BadRequest("meal_type is required".to_string()))?;
+    let now = Utc::now();
+    let meal_date = meal_timestamp(fields.date.as_deref(), fields.time.as_deref(), &meal_type, now)?;
+    let (image_data, mime_type) = nutrition::validate_image(image_data, mime_type)?;
+
+    let (analysis, prompt_version) = nutrition::analyze_image(&state, &image_data, &mime_type).await?;
+    let analysis = match analysis {
+        FoodImageAnalysis::Food(analysis) => *analysis,
+        rejected @ FoodImageAnalysis::Rejected(_) => {
+            let mut response = nutrition::NutritionAnalysisResponse::new(rejected, prompt_version);
+            response.success = false;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(response)).into_response());
+        }
+    };
+
+    let (scale, portion_weight_g) = portion_scale(analysis.estimated_weight_g, fields.corrected_weight_g)?;
+    let macros = &analysis.macronutrients;
+
+    let meal_log = MealLog {
+        id: None,
+        user_id,
+        date: meal_date,
+        meal_type,
+        food_name: analysis.food_name.clone(),
+        calories: analysis.calories * scale,
+        protein_g: macros.protein_g * scale,
+        carbs_g: macros.carbs_g * scale,
+        fat_g: macros.fat_g * scale,
+        fiber_g: macros.fiber_g.map(|g| g * scale),
+        sugar_g: macros.sugar_g.map(|g| g * scale),
+        sodium_mg: macros.sodium_mg.map(|mg| mg * scale),
+        items: Vec::new(),
+        serving_size: analysis.serving_size.clone(),
+        notes: fields.notes,
+        source: MealSource::AiImage,
+        verified: MealSource::AiImage.is_verified(),
+        nutrition_snapshot: None,
+        portion_weight_g,
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: None,
+        mood: None,
+        eating_context: None,
+        version: 0,
+        created_at: now,
+    };
+
+    let (saved_meal, daily_totals) = save_meal(&state, meal_log).await?;
+
+    tracing::info!("Logged meal {:?} from an image for user {}", saved_meal.id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(LogFromImageResponse {
+            success: true,
+            analysis,
+            prompt_version,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/meals/log-favorite/{id}",
+    tag = "meals",
+    params(("id" = String, Path, description = "Favorite ID")),
+    request_body(content = Option<LogFavoriteRequest>, description = "Optional overrides"),
+    responses((status = 201, description = "Meal logged; the day's new totals", body = MealLogResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_favorite(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(favorite_id): Path<String>,
+    payload: Option<Json<LogFavoriteRequest>>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let favorite_oid = ObjectId::parse_str(&favorite_id).map_err(|_|
+        AppError::BadRequest("Invalid favorite ID".to_string())
+    )?;
+
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let favorite = favorite_service::find_favorite(&state, user_id, favorite_oid).await?;
+
+    let meal_type = payload.meal_type.unwrap_or_else(|| favorite.meal_type.clone());
+    let date = meal_timestamp(payload.date.as_deref(), payload.time.as_deref(), &meal_type, Utc::now())?;
+
+    let (saved_meal, progress) = favorite_service::log_favorite(
+        &state,
+        &favorite,
+        Some(meal_type),
+        date,
+        payload.servings.unwrap_or(1.0)
+    ).await?;
+
+    tracing::info!("Logged favorite {} as meal {:?} for user {}", favorite_id, saved_meal.id, claims.sub);
+
+    let daily_totals = calculate_daily_totals(&state, user_id, progress.date.date_naive(), Some(progress)).await?;
+
+    let event_state = state.clone();
+    tokio::spawn(async move {
+        event_service::check_logging_milestones(&event_state, user_id).await;
+    });
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealLogResponse {
+            success: true,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/meals/log-custom/{id}",
+    tag = "meals",
+    params(("id" = String, Path, description = "Custom food ID")),
+    request_body = LogCustomFoodRequest,
+    responses((status = 201, description = "Meal logged; the day's new totals", body = MealLogResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_custom_food(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(food_id): Path<String>,
+    Json(payload): Json<LogCustomFoodRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let food_oid = ObjectId::parse_str(&food_id).map_err(|_|
+        AppError::BadRequest("Invalid custom food ID".to_string())
+    )?;
+
+    let food = custom_food_service::find_custom_food(&state, user_id, food_oid).await?;
+    let date = meal_timestamp(payload.date.as_deref(), payload.time.as_deref(), &payload.meal_type, Utc::now())?;
+
+    let portion = custom_food_service::Portion {
+        serving: payload.serving,
+        servings: payload.servings,
+        grams: payload.grams,
+    };
+    let (saved_meal, progress) = custom_food_service::log_custom_food(
+        &state,
+        &food,
+        payload.meal_type,
+        date,
+        &portion
+    ).await?;
+
+    tracing::info!("Logged custom food {} as meal {:?} for user {}", food_id, saved_meal.id, claims.sub);
+
+    let daily_totals = calculate_daily_totals(&state, user_id, progress.date.date_naive(), Some(progress)).await?;
+
+    let event_state = state.clone();
+    tokio::spawn(async move {
+        event_service::check_logging_milestones(&event_state, user_id).await;
+    });
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealLogResponse {
+            success: true,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+/// Logs a FoodData Central food, with nutrition the server looks up and
+/// scales itself. The only way, with barcodes, to log a verified meal.
+#[utoipa::path(
+    post,
+    path = "/api/meals/log-fdc/{fdc_id}",
+    tag = "meals",
+    params(("fdc_id" = i32, Path, description = "FoodData Central ID")),
+    request_body = LogFdcFoodRequest,
+    responses(
+        (status = 201, description = "Meal logged; the day's new totals", body = MealLogResponse),
+        (status = 503, description = "FoodData Central is unavailable or doesn't know the food")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_fdc_food(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(fdc_id): Path<i32>,
+    Json(payload): Json<LogFdcFoodRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    payload.validate()?;
+    let (saved_meal, daily_totals) = log_database_food(&state, user_id, fdc_id, MealSource::Fdc, payload).await?;
+
+    tracing::info!("Logged FDC food {} as meal {:?} for user {}", fdc_id, saved_meal.id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealLogResponse {
+            success: true,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+/// Logs the packaged food with this UPC/EAN barcode, found among FoodData
+/// Central's branded foods.
+#[utoipa::path(
+    post,
+    path = "/api/meals/log-barcode/{barcode}",
+    tag = "meals",
+    params(("barcode" = String, Path, description = "UPC or EAN barcode, 8 to 14 digits")),
+    request_body = LogFdcFoodRequest,
+    responses(
+        (status = 201, description = "Meal logged; the day's new totals", body = MealLogResponse),
+        (status = 404, description = "No food with this barcode")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_barcode_food(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(barcode): Path<String>,
+    Json(payload): Json<LogFdcFoodRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if !(8..=14).contains(&barcode.len()) || !barcode.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(AppError::BadRequest("A barcode is 8 to 14 digits".to_string()));
+    }
+    payload.validate()?;
+
+    let fdc_id = food_lookup_service
+        ::find_barcode(&state, &barcode).await?
+        .ok_or_else(|| AppError::NotFound("No food found for this barcode".to_string()))?;
+
+    let (saved_meal, daily_totals) = log_database_food(&state, user_id, fdc_id, MealSource::Barcode, payload).await?;
+
+    tracing::info!("Logged barcode {} as meal {:?} for user {}", barcode, saved_meal.id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealLogResponse {
+            success: true,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+/// Looks up the FDC food, scales it to the serving and saves it as a
+/// verified meal with a snapshot of the values it came from.
+async fn log_database_food(
+    state: &AppState,
+    user_id: ObjectId,
+    fdc_id: i32,
+    source: MealSource,
+    payload: LogFdcFoodRequest
+) -> Result<(MealLog, DailyTotals), AppError> {
+    let serving = food_lookup_service::serving(payload.grams, payload.portion_id, payload.servings)?;
+    let date = meal_timestamp(payload.date.as_deref(), payload.time.as_deref(), &payload.meal_type, Utc::now())?;
+
+    let details = state.cache
+        .get_or_fetch(cache_service::FDC_FOOD, &fdc_id.to_string(), false, || {
+            state.fdc_service.get_food_details(fdc_id)
+        }).await
+        .map_err(|e| AppError::ExternalApiError(format!("Nutrition lookup failed: {}", e)))?;
+
+    let food = food_lookup_service::scale_fdc_food(&details, serving)?;
+    let serving_size = match (&food.portion, food.servings) {
+        (Some(portion), Some(servings)) if servings != 1.0 =>
+            format!("{} x {} ({} g)", servings, portion.description, food.grams),
+        (Some(portion), _) => format!("{} ({} g)", portion.description, food.grams),
+        (None, _) => format!("{} g", food.grams),
+    };
+
+    let meal_log = MealLog {
+        id: None,
+        user_id,
+        date,
+        meal_type: payload.meal_type,
+        food_name: food.description.clone(),
+        calories: food.calories,
+        protein_g: food.protein_g,
+        carbs_g: food.carbs_g,
+        fat_g: food.fat_g,
+        fiber_g: food.fiber_g,
+        sugar_g: food.sugar_g,
+        sodium_mg: food.sodium_mg,
+        items: Vec::new(),
+        serving_size: Some(serving_size),
+        notes: payload.notes,
+        source,
+        verified: true,
+        nutrition_snapshot: Some(nutrition_snapshot(&details)),
+        portion_weight_g: Some(food.grams),
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: None,
+        mood: None,
+        eating_context: None,
+        version: 0,
+        created_at: Utc::now(),
+    };
+
+    save_meal(state, meal_log).await
+}
+
+/// Logs a TheMealDB recipe with nutrition estimated from its ingredients.
+#[utoipa::path(
+    post,
+    path = "/api/recipes/{meal_id}/log",
+    tag = "meals",
+    params(("meal_id" = String, Path, description = "TheMealDB recipe ID")),
+    request_body = LogRecipeRequest,
+    responses((status = 201, description = "Meal logged; the day's new totals", body = MealLogResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_recipe(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(meal_id): Path<String>,
+    Json(payload): Json<LogRecipeRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let date = meal_timestamp(payload.date.as_deref(), payload.time.as_deref(), &payload.meal_type, Utc::now())?;
+
+    let recipe = state.cache
+        .get_or_fetch(cache_service::MEALDB_MEAL, &meal_id, false, || {
+            state.mealdb_service.get_meal_by_id(&meal_id)
+        }).await
+        .map_err(AppError::InternalError)?
+        .ok_or_else(|| AppError::NotFound("Recipe not found".to_string()))?;
+
+    let (saved_meal, progress) = recipe_nutrition_service::log_recipe(
+        &state,
+        user_id,
+        &recipe,
+        payload.meal_type,
+        date,
+        payload.serving_fraction.unwrap_or(1.0)
+    ).await?;
+
+    tracing::info!("Logged recipe {} as meal {:?} for user {}", meal_id, saved_meal.id, claims.sub);
+
+    let daily_totals = calculate_daily_totals(&state, user_id, progress.date.date_naive(), Some(progress)).await?;
+
+    let event_state = state.clone();
+    tokio::spawn(async move {
+        event_service::check_logging_milestones(&event_state, user_id).await;
+    });
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealLogResponse {
+            success: true,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+/// Marks a meal plan entry as eaten by logging it. Future plan days are rejected.
+#[utoipa::path(
+    post,
+    path = "/api/meal-plans/{id}/days/{date}/meals/{index}/eaten",
+    tag = "meals",
+    params(
+        ("id" = String, Path, description = "Meal plan ID"),
+        ("date" = String, Path, description = "Day of the plan, `YYYY-MM-DD`"),
+        ("index" = usize, Path, description = "Meal index within the day")
+    ),
+    request_body = LogPlannedMealRequest,
+    responses((status = 201, description = "Meal logged; the day's new totals", body = MealLogResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_planned_meal(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((plan_id, date, index)): Path<(String, String, usize)>,
+    Json(payload): Json<LogPlannedMealRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let plan_oid = ObjectId::parse_str(&plan_id).map_err(|_|
+        AppError::BadRequest("Invalid meal plan ID".to_string())
+    )?;
+
+    let plan = meal_plan_service::find_plan(&state, user_id, plan_oid).await?;
+    let meal_type = plan.days
+        .iter()
+        .find(|day| day.date == date)
+        .and_then(|day| day.meals.get(index))
+        .map(|meal| meal.meal_type.clone())
+        .ok_or_else(|| AppError::NotFound("Planned meal not found".to_string()))?;
+
+    let eaten_at = meal_timestamp(Some(&date), payload.time.as_deref(), &meal_type, Utc::now())?;
+
+    let (saved_meal, progress) = meal_plan_service::mark_eaten(
+        &state,
+        user_id,
+        plan_oid,
+        &date,
+        index,
+        eaten_at
+    ).await?;
+
+    tracing::info!("Logged planned meal {} on {} of plan {} for user {}", index, date, plan_id, claims.sub);
+
+    let daily_totals = calculate_daily_totals(&state, user_id, progress.date.date_naive(), Some(progress)).await?;
+
+    let event_state = state.clone();
+    tokio::spawn(async move {
+        event_service::check_logging_milestones(&event_state, user_id).await;
+    });
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealLogResponse {
+            success: true,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+/// Logs a meal again, for someone who eats the same thing often. Eating
+/// details such as mood and hunger aren't carried over.
+#[utoipa::path(
+    post,
+    path = "/api/meals/{id}/repeat",
+    tag = "meals",
+    params(("id" = String, Path, description = "ID of the meal to repeat")),
+    request_body = RepeatMealRequest,
+    responses(
+        (status = 201, description = "Meal logged; the day's new totals", body = MealLogResponse),
+        (status = 404, description = "Meal not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn repeat_meal(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(meal_id): Path<String>,
+    payload: Option<Json<RepeatMealRequest>>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let meal_oid = ObjectId::parse_str(&meal_id).map_err(|_|
+        AppError::BadRequest("Invalid meal ID".to_string())
+    )?;
+
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let original = state.db
+        .collection::<MealLog>("meal_logs")
+        .find_one(doc! { "_id": meal_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Meal not found".to_string()))?;
+
+    let meal_type = payload.meal_type.unwrap_or_else(|| original.meal_type.clone());
+    let now = Utc::now();
+    let mut date = meal_timestamp(payload.date.as_deref(), payload.time.as_deref(), &meal_type, now)?;
+    if payload.time.is_none() {
+        let same_time = Utc.from_utc_datetime(&date.date_naive().and_time(original.date.time()));
+        if same_time <= now {
+            date = same_time;
+        }
+    }
+
+    let (saved_meal, daily_totals) = save_meal(&state, repeated_meal(original, meal_type, date)).await?;
+
+    tracing::info!("Repeated meal {} as {:?} for user {}", meal_id, saved_meal.id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(MealLogResponse {
+            success: true,
+            meal: saved_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+/// Logs a day's meals again on another day, each at the same time of day.
+/// On today, meals whose time hasn't come yet are skipped.
+#[utoipa::path(
+    post,
+    path = "/api/meals/copy-day",
+    tag = "meals",
+    request_body = CopyDayRequest,
+    responses(
+        (status = 201, description = "Meals copied; the target day's new totals", body = CopyDayResponse),
+        (status = 400, description = "Invalid dates, or a target day in the future"),
+        (status = 404, description = "No meals to copy")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn copy_day(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CopyDayRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    // Days run at the user's own offset, like their daily totals.
+    let offset = progress_service::user_offset(&state, user_id).await?;
+    let now = Utc::now();
+    let today = timezone::today(offset);
+    let source_day = NaiveDate::parse_from_str(&payload.source_date, "%Y-%m-%d").map_err(|_|
+        AppError::BadRequest("Invalid source_date format. Use YYYY-MM-DD".to_string())
+    )?;
+    let target_day = match payload.target_date.as_deref() {
+        Some(date) =>
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid target_date format. Use YYYY-MM-DD".to_string())
+            )?,
+        None => today,
+    };
+
+    if source_day == target_day {
+        return Err(AppError::BadRequest("source_date and target_date must differ".to_string()));
+    }
+    if target_day > today {
+        return Err(AppError::BadRequest("Cannot log a meal in the future".to_string()));
+    }
+
+    let start_of_day = timezone::start_of_day(source_day, offset);
+    let end_of_day = start_of_day + chrono::Duration::days(1);
+    let start_of_target = timezone::start_of_day(target_day, offset);
+
+    let source_meals: Vec<MealLog> = state.db
+        .collection::<MealLog>("meal_logs")
+        .find(
+            doc! {
+                "user_id": user_id,
+                "date": {
+                    "$gte": mongodb::bson::DateTime::from_chrono(start_of_day),
+                    "$lt": mongodb::bson::DateTime::from_chrono(end_of_day)
+                }
+            },
+            FindOptions::builder().sort(doc! { "date": 1 }).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let source_meals: Vec<MealLog> = source_meals
+        .into_iter()
+        .filter(|meal| match &payload.meal_types {
+            Some(types) => types.contains(&meal.meal_type),
+            None => true,
+        })
+        .collect();
+    if source_meals.is_empty() {
+        return Err(AppError::NotFound("No meals to copy on source_date".to_string()));
+    }
+
+    let mut copies = Vec::with_capacity(source_meals.len());
+    let mut skipped = 0;
+    for meal in source_meals {
+        let date = start_of_target + (meal.date - start_of_day);
+        if date > now {
+            skipped += 1;
+            continue;
+        }
+        let meal_type = meal.meal_type.clone();
+        copies.push(repeated_meal(meal, meal_type, date));
+    }
+
+    // Progress is updated per meal, like any other log, so a failure
+    // part-way leaves the totals matching the meals inserted so far.
+    let mut progress = None;
+    for copy in &mut copies {
+        let result = state.db
+            .collection::<MealLog>("meal_logs")
+            .insert_one(&*copy, None).await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+        copy.id = result.inserted_id.as_object_id();
+        progress = Some(progress_service::record_meal_added(&state, copy).await?);
+    }
+
+    tracing::info!(
+        "Copied {} meals from {} to {} for user {} ({} skipped)",
+        copies.len(),
+        source_day,
+        target_day,
+        claims.sub,
+        skipped
+    );
+
+    if !copies.is_empty() {
+        report_service::invalidate_compliance_score(&state, user_id).await;
+
+        let event_state = state.clone();
+        tokio::spawn(async move {
+            event_service::check_logging_milestones(&event_state, user_id).await;
+        });
+    }
+
+    let progress = match progress {
+        Some(progress) => Some(progress),
+        None => progress_service::progress_for_day(&state, user_id, target_day).await?,
+    };
+    let daily_totals = calculate_daily_totals(&state, user_id, target_day, progress).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CopyDayResponse {
+            success: true,
+            meals: copies.into_iter().map(MealLogDto::from).collect(),
+            skipped,
+            daily_totals,
+        }),
+    ))
+}
+
+/// A new, unsaved meal with `meal`'s food and nutrition, eaten at `date`.
+fn repeated_meal(meal: MealLog, meal_type: MealType, date: DateTime<Utc>) -> MealLog {
+    MealLog {
+        id: None,
+        date,
+        meal_type,
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: None,
+        mood: None,
+        eating_context: None,
+        version: 0,
+        created_at: Utc::now(),
+        ..meal
+    }
+}
+
+/// When the meal was eaten. Rejects times in the future.
+fn meal_timestamp(
+    date: Option<&str>,
+    time: Option<&str>,
+    meal_type: &MealType,
+    now: DateTime<Utc>
+) -> Result<DateTime<Utc>, AppError> {
+    let day = match date {
+        Some(date) =>
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid date format. Use YYYY-MM-DD".to_string())
+            )?,
+        None => now.date_naive(),
+    };
+
+    let time = match time {
+        Some(time) =>
+            Some(
+                NaiveTime::parse_from_str(time, "%H:%M").map_err(|_|
+                    AppError::BadRequest("Invalid time format. Use HH:MM".to_string())
+                )?
+            ),
+        None => None,
+    };
+
+    let timestamp = match time {
+        Some(time) => Utc.from_utc_datetime(&day.and_time(time)),
+        None if day == now.date_naive() => now,
+        None => Utc.from_utc_datetime(&day.and_time(default_meal_time(meal_type))),
+    };
+
+    if timestamp > now {
+        return Err(AppError::BadRequest("Cannot log a meal in the future".to_string()));
+    }
+
+    Ok(timestamp)
+}
+
+fn default_meal_time(meal_type: &MealType) -> NaiveTime {
+    let (hour, minute) = match meal_type {
+        MealType::Breakfast => (8, 0),
+        MealType::Lunch => (12, 30),
+        MealType::Dinner => (19, 0),
+        MealType::Snack => (15, 0),
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// Factor to apply to the submitted macros, and the portion weight to record.
+fn portion_scale(estimated_g: Option<f64>, corrected_g: Option<f64>) -> Result<(f64, Option<f64>), AppError> {
+    for weight in [estimated_g, corrected_g].into_iter().flatten() {
+        if !weight.is_finite() || weight <= 0.0 {
+            return Err(AppError::BadRequest("Portion weights must be greater than 0".to_string()));
+        }
+    }
+
+    match (estimated_g, corrected_g) {
+        (Some(estimated), Some(corrected)) => Ok((corrected / estimated, Some(corrected))),
+        (None, Some(_)) =>
+            Err(
+                AppError::BadRequest(
+                    "estimated_weight_g is required to rescale to corrected_weight_g".to_string()
+                )
+            ),
+        (estimated, None) => Ok((1.0, estimated)),
+    }
+}
 
-    let mut saved_meal = meal_log;
-    saved_meal.id = Some(result.inserted_id.as_object_id().unwrap());
+/// The meal's items, scaled by `scale`, and its nutrition: the items' totals,
+/// or the request's own values for a single food.
+fn request_nutrition(payload: &LogMealRequest, scale: f64) -> Result<(Vec<MealEntry>, ItemTotals), AppError> {
+    if let Some(items) = payload.items.as_ref().filter(|items| !items.is_empty()) {
+        let items: Vec<MealEntry> = items
+            .iter()
+            .cloned()
+            .map(|item| unverified_item(item).scaled(scale))
+            .collect();
+        let totals = ItemTotals::of(&items);
+        return Ok((items, totals));
+    }
 
-    tracing::info!("Meal inserted with ID: {:?}, date: {:?}", saved_meal.id, saved_meal.date);
+    let required = |value: Option<f64>, field: &str| {
+        value
+            .map(|value| value * scale)
+            .ok_or_else(|| AppError::BadRequest(format!("{} is required for a meal without items", field)))
+    };
+    let totals = ItemTotals {
+        calories: required(payload.calories, "calories")?,
+        protein_g: required(payload.protein_g, "protein_g")?,
+        carbs_g: required(payload.carbs_g, "carbs_g")?,
+        fat_g: required(payload.fat_g, "fat_g")?,
+        fiber_g: payload.fiber_g.map(|g| g * scale),
+        sugar_g: payload.sugar_g.map(|g| g * scale),
+        sodium_mg: payload.sodium_mg.map(|mg| mg * scale),
+    };
+    Ok((Vec::new(), totals))
+}
 
-    let daily_totals = calculate_daily_totals(&state, user_id, Utc::now()).await?;
+/// Field names get `prefix`, e.g. `items[0].`.
+fn check_item(checks: &mut Checks, prefix: &str, item: &MealEntry) {
+    let field = |name: &str| format!("{}{}", prefix, name);
+    checks
+        .required_text(&field("name"), &item.name, 200)
+        .optional_max_length(&field("quantity"), item.quantity.as_deref(), 100)
+        .range(&field("calories"), item.calories, 0.0, MAX_CALORIES)
+        .range(&field("protein_g"), item.protein_g, 0.0, MAX_MACRO_G)
+        .range(&field("carbs_g"), item.carbs_g, 0.0, MAX_MACRO_G)
+        .range(&field("fat_g"), item.fat_g, 0.0, MAX_MACRO_G)
+        .optional_range(&field("fiber_g"), item.fiber_g, 0.0, MAX_MACRO_G)
+        .optional_range(&field("sugar_g"), item.sugar_g, 0.0, MAX_MACRO_G)
+        .optional_range(&field("sodium_mg"), item.sodium_mg, 0.0, MAX_SODIUM_MG);
+}
 
-    tracing::info!("Meal logged successfully for user: {}", claims.sub);
+fn validate_item(item: &MealEntry) -> Result<(), AppError> {
+    let mut checks = Checks::new();
+    check_item(&mut checks, "", item);
+    checks.finish()
+}
 
-    Ok((
-        StatusCode::CREATED,
-        Json(MealLogResponse {
-            success: true,
-            meal: saved_meal,
-            daily_totals,
-        }),
-    ))
+/// An item from a request. Its values are the client's, so it can't claim
+/// a database source.
+fn unverified_item(item: MealEntry) -> MealEntry {
+    MealEntry {
+        source: item.source.unverified(),
+        ..item
+    }
 }
 
+/// Whether an edit leaves the meal's values as stored. Item sources aren't
+/// compared, since requests can't send database ones.
+fn same_nutrition(meal: &MealLog, items: &[MealEntry], nutrition: &ItemTotals) -> bool {
+    let stored = ItemTotals {
+        calories: meal.calories,
+        protein_g: meal.protein_g,
+        carbs_g: meal.carbs_g,
+        fat_g: meal.fat_g,
+        fiber_g: meal.fiber_g,
+        sugar_g: meal.sugar_g,
+        sodium_mg: meal.sodium_mg,
+    };
+    let without_source = |item: &MealEntry| MealEntry {
+        source: MealSource::Manual,
+        ..item.clone()
+    };
+
+    stored == *nutrition && meal.items.iter().map(without_source).eq(items.iter().map(without_source))
+}
 
+#[utoipa::path(
+    get,
+    path = "/api/meals/daily",
+    tag = "meals",
+    params(DateQuery),
+    responses((status = 200, description = "The day's meals and totals")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_daily_meals(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -124,18 +1398,16 @@ pub async fn get_daily_meals(
         AppError::BadRequest("Invalid user ID".to_string())
     )?;
 
+    let offset = timezone::parse(query.tz.as_deref())?;
+
     let naive_date = if let Some(date_str) = query.date {
         NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
             .map_err(|_| AppError::BadRequest("Invalid date format. Use YYYY-MM-DD".to_string()))?
     } else {
-        Utc::now().date_naive()
+        timezone::today(offset)
     };
 
-    let start_of_day = naive_date
-        .and_hms_opt(0, 0, 0)
-        .ok_or_else(|| AppError::BadRequest("Invalid date".to_string()))?;
-    let start_of_day = Utc.from_utc_datetime(&start_of_day);
-    
+    let start_of_day = timezone::start_of_day(naive_date, offset);
     let end_of_day = start_of_day + chrono::Duration::days(1);
 
     tracing::info!(
@@ -146,69 +1418,54 @@ pub async fn get_daily_meals(
         end_of_day
     );
 
-    use futures::TryStreamExt;
-    let all_meals_cursor = state.db
+    let meals: Vec<MealLogDto> = state.db
         .collection::<MealLog>("meal_logs")
-        .find(doc! { "user_id": user_id }, None).await
-        .map_err(|e| AppError::InternalError(e.into()))?;
-
-    let all_meals: Vec<MealLog> = all_meals_cursor
+        .find(
+            doc! {
+                "user_id": user_id,
+                "date": {
+                    "$gte": mongodb::bson::DateTime::from_chrono(start_of_day),
+                    "$lt": mongodb::bson::DateTime::from_chrono(end_of_day)
+                }
+            },
+            FindOptions::builder().sort(doc! { "date": 1 }).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .map_ok(MealLogDto::from)
         .try_collect().await
         .map_err(|e| AppError::InternalError(e.into()))?;
 
-    tracing::info!("Total meals in DB for user: {}", all_meals.len());
-    for meal in &all_meals {
-        tracing::info!("  Meal: id={:?}, date={:?}, food={}", meal.id, meal.date, meal.food_name);
-    }
-
-    let start_bson = mongodb::bson::DateTime::from_chrono(start_of_day);
-    let end_bson = mongodb::bson::DateTime::from_chrono(end_of_day);
-
-    let filter =
-        doc! {
-        "user_id": user_id,
-        "date": {
-            "$gte": start_bson,
-            "$lt": end_bson
-        }
-    };
-
-    tracing::info!("Query filter: {:?}", filter);
-    tracing::info!("Looking for meals between {} and {}", start_bson, end_bson);
-
-    let mut cursor = state.db
-        .collection::<MealLog>("meal_logs")
-        .find(filter, None).await
-        .map_err(|e| AppError::InternalError(e.into()))?;
-
-    let mut meals = Vec::new();
-    while cursor.advance().await.map_err(|e| AppError::InternalError(e.into()))? {
-        let meal = cursor.deserialize_current().map_err(|e| {
-            tracing::error!("Failed to deserialize meal: {}", e);
-            AppError::InternalError(e.into())
-        })?;
-        tracing::info!("Found meal: {:?}", meal);
-        meals.push(meal);
-    }
-
-    tracing::info!("Total meals found with date query: {}", meals.len());
-
-    if meals.is_empty() && !all_meals.is_empty() {
-        tracing::warn!("No meals found with date query, filtering manually from all meals");
-        meals = all_meals.into_iter()
-            .filter(|meal| {
-                let meal_date = meal.date;
-                let in_range = meal_date >= start_of_day && meal_date < end_of_day;
-                if in_range {
-                    tracing::info!("Meal {} is in range: {}", meal.food_name, meal_date);
-                }
-                in_range
-            })
-            .collect();
-        tracing::info!("Manually filtered meals: {}", meals.len());
+    tracing::info!("Found {} meals for user {}", meals.len(), claims.sub);
+
+    let mut progress = progress_service::progress_for_day(&state, user_id, naive_date).await?;
+    // Running totals are kept per day at the user's own offset; other offsets
+    // sum the meals above. Water is logged against a date rather than a time,
+    // so it carries over.
+    if offset != progress_service::user_offset(&state, user_id).await? {
+        let now = Utc::now();
+        let mut local = progress.unwrap_or(DailyProgress {
+            id: None,
+            user_id,
+            date: start_of_day,
+            total_calories: 0.0,
+            total_protein_g: 0.0,
+            total_carbs_g: 0.0,
+            total_fat_g: 0.0,
+            meal_count: 0,
+            water_ml: None,
+            weight_kg: None,
+            notes: None,
+            created_at: now,
+            updated_at: now,
+        });
+        local.total_calories = meals.iter().map(|m| m.calories).sum();
+        local.total_protein_g = meals.iter().map(|m| m.protein_g).sum();
+        local.total_carbs_g = meals.iter().map(|m| m.carbs_g).sum();
+        local.total_fat_g = meals.iter().map(|m| m.fat_g).sum();
+        local.meal_count = meals.len() as i64;
+        progress = Some(local);
     }
-
-    let daily_totals = calculate_daily_totals(&state, user_id, start_of_day).await?;
+    let daily_totals = calculate_daily_totals_in(&state, user_id, naive_date, progress, Some(offset)).await?;
 
     Ok(
         Json(
@@ -222,10 +1479,27 @@ pub async fn get_daily_meals(
 }
 
 
+/// With `If-Match`, only applies the edit if the meal is still at that
+/// version and returns 409 otherwise. An edit racing another one gets a 409
+/// either way.
+#[utoipa::path(
+    put,
+    path = "/api/meals/{id}",
+    tag = "meals",
+    params(("id" = String, Path, description = "Meal ID"), ("If-Match" = Option<String>, Header, description = "Meal version last read, from `version` or the `ETag`")),
+    request_body = LogMealRequest,
+    responses(
+        (status = 200, description = "Meal updated; the day's new totals", body = MealLogResponse),
+        (status = 404, description = "Meal not found"),
+        (status = 409, description = "The meal changed since the `If-Match` version, or during the edit")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn update_meal(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
     Path(meal_id): Path<String>,
+    headers: HeaderMap,
     Json(payload): Json<LogMealRequest>
 ) -> Result<impl IntoResponse, AppError> {
     let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
@@ -236,56 +1510,304 @@ pub async fn update_meal(
         AppError::BadRequest("Invalid meal ID".to_string())
     )?;
 
+    let expected_version = versioning::expected_version(&headers)?;
+    payload.validate()?;
+    let (items, nutrition) = request_nutrition(&payload, 1.0)?;
+
     tracing::info!("Updating meal {} for user {}", meal_id, claims.sub);
 
-    let update_doc =
-        doc! {
-        "$set": {
-            "meal_type": mongodb::bson::to_bson(&payload.meal_type).unwrap(),
-            "food_name": &payload.food_name,
-            "calories": payload.calories,
-            "protein_g": payload.protein_g,
-            "carbs_g": payload.carbs_g,
-            "fat_g": payload.fat_g,
-            "serving_size": &payload.serving_size,
-            "notes": &payload.notes,
-        }
+    let meals = state.db.collection::<MealLog>("meal_logs");
+    let previous = meals
+        .find_one(doc! { "_id": meal_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Meal not found".to_string()))?;
+    if expected_version.is_some_and(|version| version != previous.version) {
+        return Err(versioning::conflict("meal"));
+    }
+
+    let mut set = doc! {
+        "meal_type": mongodb::bson::to_bson(&payload.meal_type).unwrap(),
+        "food_name": &payload.food_name,
+        "serving_size": &payload.serving_size,
+        "notes": &payload.notes,
+        "hunger_level": payload.hunger_level,
+        "mood": payload.mood.map(|mood| mood.as_str()),
+        "eating_context": payload.eating_context.map(|context| context.as_str()),
     };
+    let mut update_doc = doc! { "$inc": { "version": 1_i64 } };
+
+    // Unchanged values keep the source they came from. Changed ones are the
+    // user's own, so a verified meal becomes manual.
+    if same_nutrition(&previous, &items, &nutrition) {
+        if let Some(source) = payload.source.filter(|source| *source != previous.source) {
+            set.insert("source", source.as_str());
+            set.insert("verified", false);
+        }
+    } else {
+        let source = payload.source.unwrap_or(if previous.verified { MealSource::Manual } else { previous.source });
+        set.insert("calories", nutrition.calories);
+        set.insert("protein_g", nutrition.protein_g);
+        set.insert("carbs_g", nutrition.carbs_g);
+        set.insert("fat_g", nutrition.fat_g);
+        set.insert("fiber_g", nutrition.fiber_g);
+        set.insert("sugar_g", nutrition.sugar_g);
+        set.insert("sodium_mg", nutrition.sodium_mg);
+        set.insert("source", source.as_str());
+        set.insert("verified", false);
+
+        // Without items the meal is a single food again.
+        if items.is_empty() {
+            update_doc.insert("$unset", doc! { "items": "" });
+        } else {
+            let items_bson = mongodb::bson::to_bson(&items).map_err(|e| AppError::InternalError(e.into()))?;
+            set.insert("items", items_bson);
+        }
+    }
+    update_doc.insert("$set", set);
 
-    let result = state.db
-        .collection::<MealLog>("meal_logs")
-        .update_one(
+    // Written only over the version read above, which the source decision
+    // was made against; a concurrent edit is reported as a conflict.
+    let updated_meal = meals
+        .find_one_and_update(
             doc! {
                 "_id": meal_oid,
-                "user_id": user_id
+                "user_id": user_id,
+                "version": versioning::version_filter(previous.version),
             },
             update_doc,
-            None
+            FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build()
         ).await
-        .map_err(|e| AppError::InternalError(e.into()))?;
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| versioning::conflict("meal"))?;
 
-    if result.matched_count == 0 {
-        return Err(AppError::NotFound("Meal not found".to_string()));
-    }
+    let day = progress_service::meal_day(&state, &previous).await?;
+    let progress = progress_service::apply_meal_delta(
+        &state,
+        user_id,
+        day,
+        progress_service::MealDelta {
+            calories: updated_meal.calories - previous.calories,
+            protein_g: updated_meal.protein_g - previous.protein_g,
+            carbs_g: updated_meal.carbs_g - previous.carbs_g,
+            fat_g: updated_meal.fat_g - previous.fat_g,
+            meal_count: 0,
+        }
+    ).await?;
 
-    let updated_meal = state.db
-        .collection::<MealLog>("meal_logs")
-        .find_one(doc! { "_id": meal_oid }, None).await
+    meal_history_service::record_update(&state, &previous, &updated_meal, ChangeSource::user()).await;
+    report_service::invalidate_compliance_score(&state, user_id).await;
+
+    let daily_totals = calculate_daily_totals(&state, user_id, progress.date.date_naive(), Some(progress)).await?;
+
+    Ok((
+        [(header::ETAG, versioning::etag(updated_meal.version))],
+        Json(MealLogResponse {
+            success: true,
+            meal: updated_meal.into(),
+            daily_totals,
+        }),
+    ))
+}
+
+/// `POST /api/meals/:id/items`. A meal logged as a single food becomes its
+/// first item.
+#[utoipa::path(
+    post,
+    path = "/api/meals/{id}/items",
+    tag = "meals",
+    params(("id" = String, Path, description = "Meal ID"), ("If-Match" = Option<String>, Header, description = "Meal version last read, from `version` or the `ETag`")),
+    request_body = MealEntry,
+    responses(
+        (status = 200, description = "Item added", body = MealLogResponse),
+        (status = 409, description = "The meal changed since the `If-Match` version")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn add_meal_item(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(meal_id): Path<String>,
+    headers: HeaderMap,
+    Json(item): Json<MealEntry>
+) -> Result<Response, AppError> {
+    validate_item(&item)?;
+    let item = unverified_item(item);
+
+    change_meal_items(&state, &claims, &meal_id, &headers, |items| {
+        items.push(item);
+        Ok(())
+    }).await
+}
+
+/// `PUT /api/meals/:id/items/:index`
+#[utoipa::path(
+    put,
+    path = "/api/meals/{id}/items/{index}",
+    tag = "meals",
+    params(("id" = String, Path, description = "Meal ID"), ("index" = usize, Path, description = "Item index"), ("If-Match" = Option<String>, Header, description = "Meal version last read, from `version` or the `ETag`")),
+    request_body = MealEntry,
+    responses(
+        (status = 200, description = "Item replaced", body = MealLogResponse),
+        (status = 404, description = "Meal or item not found"),
+        (status = 409, description = "The meal changed since the `If-Match` version")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn update_meal_item(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((meal_id, index)): Path<(String, usize)>,
+    headers: HeaderMap,
+    Json(item): Json<MealEntry>
+) -> Result<Response, AppError> {
+    validate_item(&item)?;
+    let item = unverified_item(item);
+
+    change_meal_items(&state, &claims, &meal_id, &headers, |items| {
+        let slot = items.get_mut(index).ok_or_else(|| AppError::NotFound("Meal item not found".to_string()))?;
+        *slot = item;
+        Ok(())
+    }).await
+}
+
+/// `DELETE /api/meals/:id/items/:index`
+#[utoipa::path(
+    delete,
+    path = "/api/meals/{id}/items/{index}",
+    tag = "meals",
+    params(("id" = String, Path, description = "Meal ID"), ("index" = usize, Path, description = "Item index"), ("If-Match" = Option<String>, Header, description = "Meal version last read, from `version` or the `ETag`")),
+    responses(
+        (status = 200, description = "Item removed", body = MealLogResponse),
+        (status = 404, description = "Meal or item not found"),
+        (status = 409, description = "The meal changed since the `If-Match` version")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_meal_item(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((meal_id, index)): Path<(String, usize)>,
+    headers: HeaderMap
+) -> Result<Response, AppError> {
+    change_meal_items(&state, &claims, &meal_id, &headers, |items| {
+        if index >= items.len() {
+            return Err(AppError::NotFound("Meal item not found".to_string()));
+        }
+        if items.len() == 1 {
+            return Err(AppError::BadRequest("A meal needs at least one item; delete the meal instead".to_string()));
+        }
+        items.remove(index);
+        Ok(())
+    }).await
+}
+
+/// Applies `change` to the meal's items and sets its nutrition to their
+/// totals. The write only succeeds against the version that was read, so a
+/// concurrent edit is reported as a conflict rather than overwritten.
+async fn change_meal_items(
+    state: &AppState,
+    claims: &Claims,
+    meal_id: &str,
+    headers: &HeaderMap,
+    change: impl FnOnce(&mut Vec<MealEntry>) -> Result<(), AppError>
+) -> Result<Response, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let meal_oid = ObjectId::parse_str(meal_id).map_err(|_|
+        AppError::BadRequest("Invalid meal ID".to_string())
+    )?;
+
+    let expected_version = versioning::expected_version(headers)?;
+    let meals = state.db.collection::<MealLog>("meal_logs");
+
+    let previous = meals
+        .find_one(doc! { "_id": meal_oid, "user_id": user_id }, None).await
         .map_err(|e| AppError::InternalError(e.into()))?
         .ok_or_else(|| AppError::NotFound("Meal not found".to_string()))?;
+    if expected_version.is_some_and(|version| version != previous.version) {
+        return Err(versioning::conflict("meal"));
+    }
 
-    let daily_totals = calculate_daily_totals(&state, user_id, updated_meal.date).await?;
+    let mut items = previous.items_or_single();
+    change(&mut items)?;
+    let totals = ItemTotals::of(&items);
+    let verified = items.iter().all(|item| item.source.is_verified());
+    // Items the user changed no longer match the database the meal came from.
+    let source = if previous.verified && !verified { MealSource::Manual } else { previous.source };
 
-    Ok(
+    tracing::info!("Updating items of meal {} for user {}", meal_id, claims.sub);
+
+    let items_bson = mongodb::bson::to_bson(&items).map_err(|e| AppError::InternalError(e.into()))?;
+    let updated_meal = meals
+        .find_one_and_update(
+            doc! {
+                "_id": meal_oid,
+                "user_id": user_id,
+                "version": versioning::version_filter(previous.version),
+            },
+            doc! {
+                "$set": {
+                    "items": items_bson,
+                    "calories": totals.calories,
+                    "protein_g": totals.protein_g,
+                    "carbs_g": totals.carbs_g,
+                    "fat_g": totals.fat_g,
+                    "fiber_g": totals.fiber_g,
+                    "sugar_g": totals.sugar_g,
+                    "sodium_mg": totals.sodium_mg,
+                    "source": source.as_str(),
+                    "verified": verified,
+                },
+                "$inc": { "version": 1_i64 },
+            },
+            FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| versioning::conflict("meal"))?;
+
+    let day = progress_service::meal_day(state, &previous).await?;
+    let progress = progress_service::apply_meal_delta(
+        state,
+        user_id,
+        day,
+        progress_service::MealDelta {
+            calories: totals.calories - previous.calories,
+            protein_g: totals.protein_g - previous.protein_g,
+            carbs_g: totals.carbs_g - previous.carbs_g,
+            fat_g: totals.fat_g - previous.fat_g,
+            meal_count: 0,
+        }
+    ).await?;
+
+    meal_history_service::record_update(state, &previous, &updated_meal, ChangeSource::user()).await;
+    report_service::invalidate_compliance_score(state, user_id).await;
+
+    let daily_totals = calculate_daily_totals(state, user_id, progress.date.date_naive(), Some(progress)).await?;
+
+    Ok((
+        [(header::ETAG, versioning::etag(updated_meal.version))],
         Json(MealLogResponse {
             success: true,
-            meal: updated_meal,
+            meal: updated_meal.into(),
             daily_totals,
-        })
-    )
+        }),
+    ).into_response())
 }
 
 
+#[utoipa::path(
+    delete,
+    path = "/api/meals/{id}",
+    tag = "meals",
+    params(("id" = String, Path, description = "Meal ID")),
+    responses(
+        (status = 200, description = "Meal deleted; the day's new totals"),
+        (status = 404, description = "Meal not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn delete_meal(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -301,9 +1823,10 @@ pub async fn delete_meal(
 
     tracing::info!("Deleting meal {} for user {}", meal_id, claims.sub);
 
+    // Only the request that actually deleted the meal subtracts it.
     let meal = state.db
         .collection::<MealLog>("meal_logs")
-        .find_one(
+        .find_one_and_delete(
             doc! {
                 "_id": meal_oid,
                 "user_id": user_id
@@ -313,24 +1836,11 @@ pub async fn delete_meal(
         .map_err(|e| AppError::InternalError(e.into()))?
         .ok_or_else(|| AppError::NotFound("Meal not found".to_string()))?;
 
-    let meal_date = meal.date;
+    meal_history_service::record_delete(&state, &meal, ChangeSource::user()).await;
+    report_service::invalidate_compliance_score(&state, user_id).await;
 
-    let result = state.db
-        .collection::<MealLog>("meal_logs")
-        .delete_one(
-            doc! {
-                "_id": meal_oid,
-                "user_id": user_id
-            },
-            None
-        ).await
-        .map_err(|e| AppError::InternalError(e.into()))?;
-
-    if result.deleted_count == 0 {
-        return Err(AppError::NotFound("Meal not found".to_string()));
-    }
-
-    let daily_totals = calculate_daily_totals(&state, user_id, meal_date).await?;
+    let progress = progress_service::record_meal_removed(&state, &meal).await?;
+    let daily_totals = calculate_daily_totals(&state, user_id, progress.date.date_naive(), Some(progress)).await?;
 
     Ok(
         Json(
@@ -343,83 +1853,205 @@ pub async fn delete_meal(
     )
 }
 
-async fn calculate_daily_totals(
-    state: &AppState,
-    user_id: ObjectId,
-    date: DateTime<Utc>
-) -> Result<DailyTotals, AppError> {
-    let start_of_day = date
-        .date_naive()
-        .and_hms_opt(0, 0, 0)
-        .ok_or_else(|| AppError::InternalError(anyhow::anyhow!("Invalid date")))?;
-    let start_of_day = Utc.from_utc_datetime(&start_of_day);
-    let end_of_day = start_of_day + chrono::Duration::days(1);
+/// Finds past meals by words in their name or notes, meal type, calories
+/// and date, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/meals/search",
+    tag = "meals",
+    params(SearchMealsQuery),
+    responses((status = 200, description = "A page of matching meals, newest first", body = SearchMealsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn search_meals(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<SearchMealsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
 
-    let start_bson = mongodb::bson::DateTime::from_chrono(start_of_day);
-    let end_bson = mongodb::bson::DateTime::from_chrono(end_of_day);
+    let skip = query.skip.unwrap_or(0);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
 
-    use futures::TryStreamExt;
-    let all_meals_cursor = state.db
-        .collection::<MealLog>("meal_logs")
-        .find(doc! { "user_id": user_id }, None).await
-        .map_err(|e| AppError::InternalError(e.into()))?;
+    let mut filter = doc! { "user_id": user_id };
 
-    let all_meals: Vec<MealLog> = all_meals_cursor
-        .try_collect().await
-        .map_err(|e| AppError::InternalError(e.into()))?;
+    if let Some(q) = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        if q.chars().count() > MAX_SEARCH_QUERY_LENGTH {
+            return Err(AppError::BadRequest(format!("q must be at most {} characters", MAX_SEARCH_QUERY_LENGTH)));
+        }
+        filter.insert("$text", doc! { "$search": q });
+    }
 
-    tracing::info!("calculate_daily_totals: Total meals in DB for user: {}", all_meals.len());
+    if let Some(meal_type) = &query.meal_type {
+        let meal_type = mongodb::bson::to_bson(meal_type).map_err(|e| AppError::InternalError(e.into()))?;
+        filter.insert("meal_type", meal_type);
+    }
 
-    let mut cursor = state.db
-        .collection::<MealLog>("meal_logs")
+    for calories in [query.min_calories, query.max_calories].into_iter().flatten() {
+        if !calories.is_finite() || calories < 0.0 {
+            return Err(AppError::BadRequest("Calorie filters must be 0 or more".to_string()));
+        }
+    }
+    if let (Some(min), Some(max)) = (query.min_calories, query.max_calories) {
+        if min > max {
+            return Err(AppError::BadRequest("min_calories must not be more than max_calories".to_string()));
+        }
+    }
+    let mut calories = doc! {};
+    if let Some(min) = query.min_calories {
+        calories.insert("$gte", min);
+    }
+    if let Some(max) = query.max_calories {
+        calories.insert("$lte", max);
+    }
+    if !calories.is_empty() {
+        filter.insert("calories", calories);
+    }
+
+    let parse_date = |value: &Option<String>, name: &str| -> Result<Option<NaiveDate>, AppError> {
+        value
+            .as_deref()
+            .map(|v|
+                NaiveDate::parse_from_str(v, "%Y-%m-%d").map_err(|_|
+                    AppError::BadRequest(format!("Invalid {} format. Use YYYY-MM-DD", name))
+                )
+            )
+            .transpose()
+    };
+    let start_date = parse_date(&query.start_date, "start_date")?;
+    let end_date = parse_date(&query.end_date, "end_date")?;
+    if let (Some(start), Some(end)) = (start_date, end_date) {
+        if start > end {
+            return Err(AppError::BadRequest("start_date must not be after end_date".to_string()));
+        }
+    }
+    let offset = timezone::parse(query.tz.as_deref())?;
+    let mut date = doc! {};
+    if let Some(start) = start_date {
+        date.insert("$gte", mongodb::bson::DateTime::from_chrono(timezone::start_of_day(start, offset)));
+    }
+    if let Some(end) = end_date {
+        let next_day = end + chrono::Duration::days(1);
+        date.insert("$lt", mongodb::bson::DateTime::from_chrono(timezone::start_of_day(next_day, offset)));
+    }
+    if !date.is_empty() {
+        filter.insert("date", date);
+    }
+
+    let collection = state.db.collection::<MealLog>("meal_logs");
+
+    let total = collection
+        .count_documents(filter.clone(), None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let meals: Vec<MealLogDto> = collection
         .find(
-            doc! {
-                "user_id": user_id,
-                "date": {
-                    "$gte": start_bson,
-                    "$lt": end_bson
-                }
-            },
-            None
+            filter,
+            FindOptions::builder()
+                .sort(doc! { "date": -1, "_id": -1 })
+                .skip(skip)
+                .limit(limit)
+                .build()
         ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .map_ok(MealLogDto::from)
+        .try_collect().await
         .map_err(|e| AppError::InternalError(e.into()))?;
 
-    let mut meals_in_range = Vec::new();
-    while cursor.advance().await.map_err(|e| AppError::InternalError(e.into()))? {
-        let meal: MealLog = cursor
-            .deserialize_current()
-            .map_err(|e| AppError::InternalError(e.into()))?;
-        meals_in_range.push(meal);
-    }
+    Ok(
+        Json(SearchMealsResponse {
+            success: true,
+            has_more: skip + (meals.len() as u64) < total,
+            meals,
+            total,
+            skip,
+            limit,
+        })
+    )
+}
 
-    tracing::info!("calculate_daily_totals: Found {} meals with date query", meals_in_range.len());
+/// Every recorded update and the deletion of a meal, oldest first. Deleted
+/// meals keep their history, so their values can be recovered.
+#[utoipa::path(
+    get,
+    path = "/api/meals/{id}/history",
+    tag = "meals",
+    params(("id" = String, Path, description = "Meal ID")),
+    responses(
+        (status = 200, description = "The meal's recorded changes, oldest first"),
+        (status = 404, description = "Meal not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_meal_history(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(meal_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
 
-    if meals_in_range.is_empty() && !all_meals.is_empty() {
-        tracing::warn!("calculate_daily_totals: No meals found with date query, filtering manually");
-        meals_in_range = all_meals.into_iter()
-            .filter(|meal| {
-                let meal_date = meal.date;
-                meal_date >= start_of_day && meal_date < end_of_day
-            })
-            .collect();
-        tracing::info!("calculate_daily_totals: Manually filtered {} meals", meals_in_range.len());
-    }
+    let meal_oid = ObjectId::parse_str(&meal_id).map_err(|_|
+        AppError::BadRequest("Invalid meal ID".to_string())
+    )?;
 
-    let mut total_calories = 0.0;
-    let mut total_protein = 0.0;
-    let mut total_carbs = 0.0;
-    let mut total_fat = 0.0;
+    let history = meal_history_service::history(&state, user_id, meal_oid).await?;
 
-    for meal in meals_in_range {
-        tracing::info!("Including meal in totals: {} - {}cal", meal.food_name, meal.calories);
-        total_calories += meal.calories;
-        total_protein += meal.protein_g;
-        total_carbs += meal.carbs_g;
-        total_fat += meal.fat_g;
+    let meal = state.db
+        .collection::<MealLog>("meal_logs")
+        .find_one(doc! { "_id": meal_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    if meal.is_none() && history.is_empty() {
+        return Err(AppError::NotFound("Meal not found".to_string()));
     }
 
-    tracing::info!("calculate_daily_totals: Totals - calories: {}, protein: {}, carbs: {}, fat: {}", 
-        total_calories, total_protein, total_carbs, total_fat);
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "meal_id": meal_id,
+        "deleted": meal.is_none(),
+        "history": history.into_iter().map(MealHistoryEntryDto::from).collect::<Vec<_>>(),
+    })
+        )
+    )
+}
+
+/// Totals come from the day's `DailyProgress` document (`None` for a day with
+/// nothing logged); targets from the health profile, for `date`'s weekday.
+/// Totals for a UTC day.
+pub(crate) async fn calculate_daily_totals(
+    state: &AppState,
+    user_id: ObjectId,
+    date: NaiveDate,
+    progress: Option<DailyProgress>
+) -> Result<DailyTotals, AppError> {
+    calculate_daily_totals_in(state, user_id, date, progress, None).await
+}
+
+/// Like [`calculate_daily_totals`], for a day running midnight to midnight at
+/// `offset`, or at the user's own offset like `daily_progress` without one.
+async fn calculate_daily_totals_in(
+    state: &AppState,
+    user_id: ObjectId,
+    date: NaiveDate,
+    progress: Option<DailyProgress>,
+    offset: Option<FixedOffset>
+) -> Result<DailyTotals, AppError> {
+    let (total_calories, total_protein, total_carbs, total_fat, water_ml) = match &progress {
+        Some(p) =>
+            (
+                p.total_calories,
+                p.total_protein_g,
+                p.total_carbs_g,
+                p.total_fat_g,
+                p.water_ml.unwrap_or(0.0),
+            ),
+        None => (0.0, 0.0, 0.0, 0.0, 0.0),
+    };
 
     let user = state.db
         .collection::<User>("users")
@@ -427,15 +2059,20 @@ async fn calculate_daily_totals(
         .map_err(|e| AppError::InternalError(e.into()))?
         .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
+    let offset = offset.unwrap_or_else(|| timezone::of_user(&user));
+    let water_target_ml = water_service::water_target_ml(&user);
+    let food_quality = food_quality_service::day_quality(
+        state,
+        user_id,
+        user.health_profile.as_ref(),
+        date,
+        offset
+    ).await?;
+
     let (target_calories, target_protein, target_carbs, target_fat) = if
         let Some(profile) = user.health_profile
     {
-        (
-            profile.daily_calories,
-            profile.daily_protein_g,
-            profile.daily_carbs_g,
-            profile.daily_fat_g,
-        )
+        profile.targets_on(date)
     } else {
 
         (2000.0, 150.0, 250.0, 67.0)
@@ -454,16 +2091,22 @@ async fn calculate_daily_totals(
         protein_remaining: target_protein - total_protein,
         carbs_remaining: target_carbs - total_carbs,
         fat_remaining: target_fat - total_fat,
+        water_ml,
+        water_target_ml,
+        water_remaining_ml: (water_target_ml - water_ml).max(0.0),
+        food_quality,
     })
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct PeriodQuery {
     pub start_date: String,
     pub end_date: String,
+    /// UTC offset days are counted in, e.g. `+07:00`; defaults to UTC.
+    pub tz: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PeriodStatsResponse {
     pub success: bool,
     pub period_type: String,
@@ -475,7 +2118,7 @@ pub struct PeriodStatsResponse {
     pub goal_progress: GoalProgress,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DailyDataPoint {
     pub date: String,
     pub calories: f64,
@@ -483,18 +2126,26 @@ pub struct DailyDataPoint {
     pub carbs_g: f64,
     pub fat_g: f64,
     pub meal_count: usize,
+    /// Last weigh-in of the day, if any.
+    pub weight_kg: Option<f64>,
+    /// Water logged for the date.
+    pub water_ml: f64,
+    /// Nutrient density score, 0-100; `null` on days without meals.
+    pub food_quality_score: Option<f64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PeriodAverages {
     pub avg_calories: f64,
     pub avg_protein_g: f64,
     pub avg_carbs_g: f64,
     pub avg_fat_g: f64,
     pub avg_meals_per_day: f64,
+    /// Mean food quality score over the days with meals.
+    pub avg_food_quality_score: Option<f64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PeriodTotals {
     pub total_calories: f64,
     pub total_protein_g: f64,
@@ -504,13 +2155,13 @@ pub struct PeriodTotals {
     pub days_logged: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct WeightGoalInfo {
     pub starting_weight: f64,
     pub goal_type: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GoalProgress {
     pub target_calories: f64,
     pub target_protein_g: f64,
@@ -527,8 +2178,18 @@ pub struct GoalProgress {
     pub weight_goal: Option<WeightGoalInfo>,
     pub current_weight: Option<f64>,
     pub target_weight: Option<f64>,
+    /// Change between the first and last weigh-in in the period.
+    pub weight_change: Option<f64>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/meals/period-stats",
+    tag = "meal_stats",
+    params(PeriodQuery),
+    responses((status = 200, description = "Daily intake, averages and goal progress over the period", body = PeriodStatsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_period_stats(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -548,88 +2209,78 @@ pub async fn get_period_stats(
 
     tracing::info!("Fetching period stats for user {} from {} to {}", claims.sub, start_date, end_date);
 
-    let start_datetime = Utc.from_utc_datetime(&start_date.and_hms_opt(0, 0, 0).unwrap());
-    let end_datetime = Utc.from_utc_datetime(&end_date.and_hms_opt(23, 59, 59).unwrap());
-
-    let start_bson = mongodb::bson::DateTime::from_chrono(start_datetime);
-    let end_bson = mongodb::bson::DateTime::from_chrono(end_datetime);
-
-    let filter = doc! {
-        "user_id": user_id,
-        "date": {
-            "$gte": start_bson,
-            "$lte": end_bson
-        }
-    };
+    let offset = timezone::parse(query.tz.as_deref())?;
+    let start_datetime = timezone::start_of_day(start_date, offset);
+    let end_datetime = timezone::start_of_day(end_date + chrono::Duration::days(1), offset) - chrono::Duration::seconds(1);
 
-    let mut cursor = state.db
-        .collection::<MealLog>("meal_logs")
-        .find(filter, None).await
-        .map_err(|e| {
-            tracing::error!("Failed to query meals for period: {}", e);
-            AppError::InternalError(e.into())
-        })?;
+    let daily_totals = monthly_stats_service::daily_totals(&state, user_id, start_date, end_date, offset).await?;
 
-    let mut all_meals: Vec<MealLog> = Vec::new();
-    while
-        let Some(result) = cursor.try_next().await.map_err(|e| {
-            tracing::error!("Error iterating cursor: {}", e);
-            AppError::InternalError(e.into())
-        })?
-    {
-        all_meals.push(result);
-    }
+    let total_meals: usize = daily_totals
+        .iter()
+        .map(|day| day.totals.meal_count)
+        .sum();
+    tracing::info!("Found {} meals in period", total_meals);
 
-    if all_meals.is_empty() {
-        tracing::warn!("No meals found in period, trying manual filtering");
-        let all_meals_filter = doc! { "user_id": user_id };
-        let mut all_cursor = state.db
-            .collection::<MealLog>("meal_logs")
-            .find(all_meals_filter, None).await
-            .map_err(|e| AppError::InternalError(e.into()))?;
+    use std::collections::HashMap;
+    let daily_map: HashMap<NaiveDate, report_service::MealTotals> = daily_totals
+        .into_iter()
+        .map(|day| (day.date, day.totals))
+        .collect();
 
-        while
-            let Some(result) = all_cursor.try_next().await.map_err(|e| {
-                AppError::InternalError(e.into())
-            })?
-        {
-            let meal_date = result.date;
-            if meal_date >= start_datetime && meal_date <= end_datetime {
-                all_meals.push(result);
-            }
-        }
+    let weigh_ins = weight_service::fetch_weight_logs_between(
+        &state,
+        user_id,
+        start_datetime,
+        end_datetime
+    ).await?;
+    let mut daily_weights: HashMap<String, f64> = HashMap::new();
+    for log in &weigh_ins {
+        daily_weights.insert(log.date.with_timezone(&offset).format("%Y-%m-%d").to_string(), log.weight_kg);
     }
 
-    tracing::info!("Found {} meals in period", all_meals.len());
+    let daily_water = water_service::water_between(&state, user_id, start_date, end_date).await?;
 
-    use std::collections::HashMap;
-    let mut daily_map: HashMap<String, Vec<&MealLog>> = HashMap::new();
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
-    for meal in &all_meals {
-        let date_str = meal.date.format("%Y-%m-%d").to_string();
-        daily_map.entry(date_str).or_insert_with(Vec::new).push(meal);
-    }
+    let daily_quality: HashMap<NaiveDate, f64> = food_quality_service
+        ::daily_quality_totals(
+            &state,
+            user_id,
+            start_datetime,
+            timezone::start_of_day(end_date + chrono::Duration::days(1), offset),
+            offset
+        ).await?
+        .into_iter()
+        .filter_map(|day| {
+            food_quality_service
+                ::score_day(&day.totals, user.health_profile.as_ref(), day.date)
+                .map(|quality| (day.date, quality.score))
+        })
+        .collect();
 
     let mut daily_data: Vec<DailyDataPoint> = Vec::new();
     let mut current_date = start_date;
 
     while current_date <= end_date {
         let date_str = current_date.format("%Y-%m-%d").to_string();
-        let meals_for_day = daily_map.get(&date_str).cloned().unwrap_or_default();
+        let day = daily_map.get(&current_date).cloned().unwrap_or_default();
 
-        let (calories, protein, carbs, fat) = meals_for_day
-            .iter()
-            .fold((0.0, 0.0, 0.0, 0.0), |(c, p, cr, f), meal| {
-                (c + meal.calories, p + meal.protein_g, cr + meal.carbs_g, f + meal.fat_g)
-            });
+        let weight_kg = daily_weights.get(&date_str).copied();
 
         daily_data.push(DailyDataPoint {
             date: date_str,
-            calories,
-            protein_g: protein,
-            carbs_g: carbs,
-            fat_g: fat,
-            meal_count: meals_for_day.len(),
+            calories: day.calories,
+            protein_g: day.protein_g,
+            carbs_g: day.carbs_g,
+            fat_g: day.fat_g,
+            meal_count: day.meal_count,
+            weight_kg,
+            water_ml: daily_water.get(&current_date).copied().unwrap_or(0.0),
+            food_quality_score: daily_quality.get(&current_date).copied(),
         });
 
         current_date = current_date.succ_opt().unwrap();
@@ -643,7 +2294,7 @@ pub async fn get_period_stats(
         total_protein_g: daily_data.iter().map(|d| d.protein_g).sum(),
         total_carbs_g: daily_data.iter().map(|d| d.carbs_g).sum(),
         total_fat_g: daily_data.iter().map(|d| d.fat_g).sum(),
-        total_meals: all_meals.len(),
+        total_meals,
         days_logged: days_with_meals,
     };
 
@@ -654,6 +2305,9 @@ pub async fn get_period_stats(
             avg_carbs_g: totals.total_carbs_g / (days_with_meals as f64),
             avg_fat_g: totals.total_fat_g / (days_with_meals as f64),
             avg_meals_per_day: (totals.total_meals as f64) / (days_with_meals as f64),
+            avg_food_quality_score: (!daily_quality.is_empty()).then(|| {
+                (daily_quality.values().sum::<f64>() / (daily_quality.len() as f64)).round()
+            }),
         }
     } else {
         PeriodAverages {
@@ -662,14 +2316,11 @@ pub async fn get_period_stats(
             avg_carbs_g: 0.0,
             avg_fat_g: 0.0,
             avg_meals_per_day: 0.0,
+            avg_food_quality_score: None,
         }
     };
 
-    let user = state.db
-        .collection::<User>("users")
-        .find_one(doc! { "_id": user_id }, None).await
-        .map_err(|e| AppError::InternalError(e.into()))?
-        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let latest_weight = weight_service::latest_weight_before(&state, user_id, end_datetime).await?;
 
     let (target_calories, target_protein, target_carbs, target_fat, goal_type, estimated_progress, weight_goal, current_weight, target_weight) = if
         let Some(profile) = user.health_profile
@@ -681,8 +2332,15 @@ pub async fn get_period_stats(
             crate::models::HealthGoal::BuildMuscle => "build_muscle".to_string(),
         };
         
+        let targets = profile.average_targets(
+            daily_map
+                .iter()
+                .filter(|(_, day)| day.meal_count > 0)
+                .map(|(date, _)| *date)
+        );
+
         let estimated = if days_with_meals > 7 {
-            let avg_cal_diff = averages.avg_calories - profile.daily_calories;
+            let avg_cal_diff = averages.avg_calories - targets.0;
             let days_elapsed = days_with_meals as f64;
             let calories_per_kg = 7700.0;
             let estimated_weight_change = (avg_cal_diff * days_elapsed) / calories_per_kg;
@@ -692,24 +2350,19 @@ pub async fn get_period_stats(
         };
 
         let weight_goal_data = Some(WeightGoalInfo {
-            starting_weight: profile.weight_kg,
+            starting_weight: weigh_ins.first().map_or(profile.weight_kg, |log| log.weight_kg),
             goal_type: goal.clone(),
         });
 
-        let current_wt = Some(profile.weight_kg);
-        
-        let target_wt = match profile.goal {
-            crate::models::HealthGoal::LoseWeight => Some(profile.weight_kg * 0.9), 
-            crate::models::HealthGoal::GainWeight => Some(profile.weight_kg * 1.1), 
-            crate::models::HealthGoal::BuildMuscle => Some(profile.weight_kg * 1.05), 
-            crate::models::HealthGoal::MaintainWeight => Some(profile.weight_kg),
-        };
+        let current_wt = Some(latest_weight.map_or(profile.weight_kg, |log| log.weight_kg));
+
+        let target_wt = Some(weight_service::target_weight(&profile));
 
         (
-            profile.daily_calories,
-            profile.daily_protein_g,
-            profile.daily_carbs_g,
-            profile.daily_fat_g,
+            targets.0,
+            targets.1,
+            targets.2,
+            targets.3,
             goal,
             estimated,
             weight_goal_data,
@@ -760,6 +2413,7 @@ pub async fn get_period_stats(
         weight_goal,
         current_weight,
         target_weight,
+        weight_change: weight_service::trend(&weigh_ins).map(|t| t.weight_change),
     };
 
     let period_type = if total_days <= 7 {
@@ -783,3 +2437,653 @@ pub async fn get_period_stats(
         })
     )
 }
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MonthlyStatsQuery {
+    pub months: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MonthlyStatsDto {
+    pub month: String,
+    /// False for the current month, which is computed live.
+    pub complete: bool,
+    pub total_days: usize,
+    pub days_logged: usize,
+    pub total_meals: usize,
+    pub avg_calories: f64,
+    pub avg_protein_g: f64,
+    pub avg_carbs_g: f64,
+    pub avg_fat_g: f64,
+    pub target_calories: f64,
+    pub calories_compliance_percent: f64,
+    pub protein_compliance_percent: f64,
+    pub carbs_compliance_percent: f64,
+    pub fat_compliance_percent: f64,
+    pub days_on_target: usize,
+    pub longest_streak: usize,
+    pub computed_at: String,
+}
+
+impl MonthlyStatsDto {
+    fn from_stats(stats: MonthlyStats, complete: bool) -> Self {
+        Self {
+            month: stats.month,
+            complete,
+            total_days: stats.total_days,
+            days_logged: stats.days_logged,
+            total_meals: stats.total_meals,
+            avg_calories: stats.avg_calories,
+            avg_protein_g: stats.avg_protein_g,
+            avg_carbs_g: stats.avg_carbs_g,
+            avg_fat_g: stats.avg_fat_g,
+            target_calories: stats.target_calories,
+            calories_compliance_percent: stats.calories_compliance_percent,
+            protein_compliance_percent: stats.protein_compliance_percent,
+            carbs_compliance_percent: stats.carbs_compliance_percent,
+            fat_compliance_percent: stats.fat_compliance_percent,
+            days_on_target: stats.days_on_target,
+            longest_streak: stats.longest_streak,
+            computed_at: stats.computed_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MonthlyStatsResponse {
+    pub success: bool,
+    pub months: Vec<MonthlyStatsDto>,
+}
+
+/// The current month and the `months - 1` before it, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/meals/monthly-stats",
+    tag = "meal_stats",
+    params(MonthlyStatsQuery),
+    responses((status = 200, description = "Monthly rollups, newest first", body = MonthlyStatsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_monthly_stats(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<MonthlyStatsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let count = query.months.unwrap_or(6);
+    if !(1..=24).contains(&count) {
+        return Err(AppError::BadRequest("months must be between 1 and 24".to_string()));
+    }
+
+    let current = monthly_stats_service::month_start(Utc::now().date_naive());
+    let mut first = current;
+    let mut months = Vec::new();
+    for _ in 0..count {
+        let stats = monthly_stats_service::get_month(&state, user_id, first).await?;
+        months.push(MonthlyStatsDto::from_stats(stats, first != current));
+        first = monthly_stats_service::month_start(first - chrono::Duration::days(1));
+    }
+
+    Ok(
+        Json(MonthlyStatsResponse {
+            success: true,
+            months,
+        })
+    )
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SourceStatsQuery {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SourceBreakdown {
+    pub source: MealSource,
+    pub meal_count: usize,
+    pub total_calories: f64,
+    pub percent_of_meals: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SourceStatsResponse {
+    pub success: bool,
+    pub start_date: String,
+    pub end_date: String,
+    pub total_meals: usize,
+    pub verified_meals: usize,
+    pub verified_percent: f64,
+    pub sources: Vec<SourceBreakdown>,
+}
+
+/// One `$group` output row of [`get_source_stats`]. Meals logged before
+/// sources were tracked have no `source` and count as manual.
+#[derive(Debug, Deserialize)]
+struct SourceGroup {
+    #[serde(rename = "_id")]
+    source: Option<MealSource>,
+    meal_count: usize,
+    total_calories: f64,
+    verified_count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/meals/source-stats",
+    tag = "meal_stats",
+    params(SourceStatsQuery),
+    responses((status = 200, description = "How meals were logged", body = SourceStatsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_source_stats(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<SourceStatsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let end_date = match query.end_date {
+        Some(ref date_str) =>
+            NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid end_date format".to_string())
+            )?,
+        None => Utc::now().date_naive(),
+    };
+    let start_date = match query.start_date {
+        Some(ref date_str) =>
+            NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid start_date format".to_string())
+            )?,
+        None => end_date - chrono::Duration::days(29),
+    };
+
+    if start_date > end_date {
+        return Err(AppError::BadRequest("start_date must not be after end_date".to_string()));
+    }
+
+    let start_datetime = Utc.from_utc_datetime(&start_date.and_hms_opt(0, 0, 0).unwrap());
+    let end_datetime = Utc.from_utc_datetime(&end_date.succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap());
+
+    let by_source: Vec<SourceGroup> = report_service::aggregate_meals(&state, vec![
+        report_service::meal_range_stage(user_id, start_datetime, end_datetime),
+        doc! {
+            "$group": {
+                "_id": "$source",
+                "meal_count": { "$sum": 1 },
+                "total_calories": { "$sum": "$calories" },
+                "verified_count": { "$sum": { "$cond": ["$verified", 1, 0] } },
+            }
+        }
+    ]).await?;
+
+    let total_meals: usize = by_source
+        .iter()
+        .map(|group| group.meal_count)
+        .sum();
+    let verified_meals: usize = by_source
+        .iter()
+        .map(|group| group.verified_count)
+        .sum();
+    let percent = |count: usize| if total_meals > 0 {
+        ((count as f64) / (total_meals as f64)) * 100.0
+    } else {
+        0.0
+    };
+
+    let mut sources: Vec<SourceBreakdown> = by_source
+        .into_iter()
+        .map(|group| SourceBreakdown {
+            source: group.source.unwrap_or_default(),
+            meal_count: group.meal_count,
+            total_calories: group.total_calories,
+            percent_of_meals: percent(group.meal_count),
+        })
+        .collect();
+    sources.sort_by_key(|s| std::cmp::Reverse(s.meal_count));
+
+    Ok(
+        Json(SourceStatsResponse {
+            success: true,
+            start_date: start_date.format("%Y-%m-%d").to_string(),
+            end_date: end_date.format("%Y-%m-%d").to_string(),
+            total_meals,
+            verified_meals,
+            verified_percent: percent(verified_meals),
+            sources,
+        })
+    )
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MoodStatsQuery {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+/// Meals logged with one mood, hunger level or eating context.
+#[derive(Debug, Serialize)]
+pub struct IntakeBreakdown<K> {
+    pub value: K,
+    pub meal_count: usize,
+    pub avg_calories: f64,
+    /// Share of the meals that recorded this field.
+    pub percent_of_meals: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_hunger_level: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MoodComparison {
+    /// Bored, tired, stressed, anxious or sad.
+    pub negative_mood_meals: usize,
+    pub negative_mood_avg_calories: f64,
+    pub other_mood_meals: usize,
+    pub other_mood_avg_calories: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MoodStatsResponse {
+    pub success: bool,
+    pub start_date: String,
+    pub end_date: String,
+    pub total_meals: usize,
+    /// Meals with at least one of hunger level, mood or eating context.
+    pub annotated_meals: usize,
+    #[schema(value_type = Vec<Object>)]
+    pub by_mood: Vec<IntakeBreakdown<MealMood>>,
+    #[schema(value_type = Vec<Object>)]
+    pub by_hunger_level: Vec<IntakeBreakdown<i32>>,
+    #[schema(value_type = Vec<Object>)]
+    pub by_eating_context: Vec<IntakeBreakdown<EatingContext>>,
+    pub mood_comparison: MoodComparison,
+    /// Meals eaten at hunger level 1 or 2.
+    pub low_hunger_meals: usize,
+    /// Pearson correlation between hunger level and a meal's calories; `None`
+    /// with fewer than three rated meals or no variation.
+    pub hunger_calories_correlation: Option<f64>,
+}
+
+/// One `$group` row of a [`get_mood_stats`] facet.
+#[derive(Debug, Deserialize)]
+struct IntakeGroup<K> {
+    #[serde(rename = "_id")]
+    value: K,
+    meal_count: usize,
+    total_calories: f64,
+    #[serde(default)]
+    avg_hunger_level: Option<f64>,
+}
+
+/// Running sums for the hunger/calories correlation. Integer sums from
+/// MongoDB deserialize into `f64` as well.
+#[derive(Debug, Deserialize)]
+struct CorrelationSums {
+    n: f64,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xy: f64,
+    sum_xx: f64,
+    sum_yy: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountsRow {
+    total_meals: usize,
+    annotated_meals: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoodFacets {
+    by_mood: Vec<IntakeGroup<MealMood>>,
+    by_hunger_level: Vec<IntakeGroup<i32>>,
+    by_eating_context: Vec<IntakeGroup<EatingContext>>,
+    correlation: Vec<CorrelationSums>,
+    counts: Vec<CountsRow>,
+}
+
+/// How mood, hunger and eating context relate to what the user eats, over
+/// meals that recorded them. Defaults to the last 30 days.
+#[utoipa::path(
+    get,
+    path = "/api/meals/mood-stats",
+    tag = "meal_stats",
+    params(MoodStatsQuery),
+    responses((status = 200, description = "Intake by mood, hunger and eating context", body = MoodStatsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_mood_stats(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<MoodStatsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let end_date = match query.end_date {
+        Some(ref date_str) =>
+            NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid end_date format".to_string())
+            )?,
+        None => Utc::now().date_naive(),
+    };
+    let start_date = match query.start_date {
+        Some(ref date_str) =>
+            NaiveDate::parse_from_str(date_str, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid start_date format".to_string())
+            )?,
+        None => end_date - chrono::Duration::days(29),
+    };
+
+    if start_date > end_date {
+        return Err(AppError::BadRequest("start_date must not be after end_date".to_string()));
+    }
+
+    let start_datetime = Utc.from_utc_datetime(&start_date.and_hms_opt(0, 0, 0).unwrap());
+    let end_datetime = Utc.from_utc_datetime(&end_date.succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap());
+
+    let group_by = |field: &str| {
+        vec![
+            doc! { "$match": { field: { "$ne": null } } },
+            doc! {
+                "$group": {
+                    "_id": format!("${}", field),
+                    "meal_count": { "$sum": 1 },
+                    "total_calories": { "$sum": "$calories" },
+                    "avg_hunger_level": { "$avg": "$hunger_level" },
+                }
+            }
+        ]
+    };
+
+    let facets: Vec<MoodFacets> = report_service::aggregate_meals(&state, vec![
+        report_service::meal_range_stage(user_id, start_datetime, end_datetime),
+        doc! {
+            "$facet": {
+                "by_mood": group_by("mood"),
+                "by_hunger_level": group_by("hunger_level"),
+                "by_eating_context": group_by("eating_context"),
+                "correlation": [
+                    { "$match": { "hunger_level": { "$ne": null } } },
+                    {
+                        "$group": {
+                            "_id": null,
+                            "n": { "$sum": 1 },
+                            "sum_x": { "$sum": "$hunger_level" },
+                            "sum_y": { "$sum": "$calories" },
+                            "sum_xy": { "$sum": { "$multiply": ["$hunger_level", "$calories"] } },
+                            "sum_xx": { "$sum": { "$multiply": ["$hunger_level", "$hunger_level"] } },
+                            "sum_yy": { "$sum": { "$multiply": ["$calories", "$calories"] } },
+                        }
+                    }
+                ],
+                "counts": [
+                    {
+                        "$group": {
+                            "_id": null,
+                            "total_meals": { "$sum": 1 },
+                            "annotated_meals": {
+                                "$sum": {
+                                    "$cond": [
+                                        {
+                                            "$or": [
+                                                { "$gt": ["$hunger_level", null] },
+                                                { "$gt": ["$mood", null] },
+                                                { "$gt": ["$eating_context", null] },
+                                            ]
+                                        },
+                                        1,
+                                        0,
+                                    ]
+                                }
+                            },
+                        }
+                    }
+                ],
+            }
+        }
+    ]).await?;
+
+    let facets = facets
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::InternalError(anyhow::anyhow!("Mood stats aggregation returned nothing")))?;
+    let (total_meals, annotated_meals) = facets.counts
+        .first()
+        .map(|row| (row.total_meals, row.annotated_meals))
+        .unwrap_or((0, 0));
+
+    let mood_comparison = compare_moods(&facets.by_mood);
+    let low_hunger_meals = facets.by_hunger_level
+        .iter()
+        .filter(|group| group.value <= 2)
+        .map(|group| group.meal_count)
+        .sum();
+    let hunger_calories_correlation = facets.correlation.first().and_then(pearson);
+
+    let mut by_mood = breakdown(facets.by_mood);
+    by_mood.sort_by_key(|b| std::cmp::Reverse(b.meal_count));
+    let mut by_hunger_level = breakdown(facets.by_hunger_level);
+    by_hunger_level.sort_by_key(|b| b.value);
+    let mut by_eating_context = breakdown(facets.by_eating_context);
+    by_eating_context.sort_by_key(|b| std::cmp::Reverse(b.meal_count));
+
+    Ok(
+        Json(MoodStatsResponse {
+            success: true,
+            start_date: start_date.format("%Y-%m-%d").to_string(),
+            end_date: end_date.format("%Y-%m-%d").to_string(),
+            total_meals,
+            annotated_meals,
+            by_mood,
+            by_hunger_level,
+            by_eating_context,
+            mood_comparison,
+            low_hunger_meals,
+            hunger_calories_correlation,
+        })
+    )
+}
+
+fn breakdown<K>(groups: Vec<IntakeGroup<K>>) -> Vec<IntakeBreakdown<K>> {
+    let total: usize = groups
+        .iter()
+        .map(|group| group.meal_count)
+        .sum();
+
+    groups
+        .into_iter()
+        .map(|group| IntakeBreakdown {
+            value: group.value,
+            meal_count: group.meal_count,
+            avg_calories: group.total_calories / (group.meal_count.max(1) as f64),
+            percent_of_meals: if total > 0 {
+                ((group.meal_count as f64) / (total as f64)) * 100.0
+            } else {
+                0.0
+            },
+            avg_hunger_level: group.avg_hunger_level,
+        })
+        .collect()
+}
+
+fn compare_moods(groups: &[IntakeGroup<MealMood>]) -> MoodComparison {
+    let (negative, other): (Vec<&IntakeGroup<MealMood>>, Vec<&IntakeGroup<MealMood>>) = groups
+        .iter()
+        .partition(|group| group.value.is_negative());
+    let summarize = |groups: &[&IntakeGroup<MealMood>]| {
+        let meals: usize = groups
+            .iter()
+            .map(|group| group.meal_count)
+            .sum();
+        let calories: f64 = groups
+            .iter()
+            .map(|group| group.total_calories)
+            .sum();
+        (meals, if meals > 0 { calories / (meals as f64) } else { 0.0 })
+    };
+
+    let (negative_mood_meals, negative_mood_avg_calories) = summarize(&negative);
+    let (other_mood_meals, other_mood_avg_calories) = summarize(&other);
+
+    MoodComparison {
+        negative_mood_meals,
+        negative_mood_avg_calories,
+        other_mood_meals,
+        other_mood_avg_calories,
+    }
+}
+
+fn pearson(sums: &CorrelationSums) -> Option<f64> {
+    if sums.n < 3.0 {
+        return None;
+    }
+    let covariance = sums.n * sums.sum_xy - sums.sum_x * sums.sum_y;
+    let variance_x = sums.n * sums.sum_xx - sums.sum_x * sums.sum_x;
+    let variance_y = sums.n * sums.sum_yy - sums.sum_y * sums.sum_y;
+    if variance_x <= 0.0 || variance_y <= 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+/// The FDC values a meal was logged from, as they were at the time.
+fn nutrition_snapshot(details: &FoodDetails) -> NutritionSnapshot {
+    let nutrients = details.food_nutrients
+        .iter()
+        .filter_map(|n| {
+            n.amount.map(|amount| SnapshotNutrient {
+                number: n.nutrient.number.clone(),
+                name: n.nutrient.name.clone(),
+                unit: n.nutrient.unit_name.clone(),
+                amount,
+            })
+        })
+        .collect();
+
+    NutritionSnapshot {
+        fdc_id: details.fdc_id,
+        description: details.description.clone(),
+        data_type: details.data_type.clone(),
+        brand_name: details.brand_name.clone(),
+        gtin_upc: details.gtin_upc.clone(),
+        nutrients,
+        captured_at: Utc::now(),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComplianceScoreResponse {
+    pub success: bool,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub score: report_service::ComplianceScore,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/meals/compliance-score",
+    tag = "meal_stats",
+    responses((status = 200, description = "How closely recent days met the targets", body = ComplianceScoreResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_compliance_score(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let score = report_service::get_compliance_score(&state, user_id).await?;
+
+    Ok(
+        Json(ComplianceScoreResponse {
+            success: true,
+            score,
+        })
+    )
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DiaryExportQuery {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/meals/export.pdf",
+    tag = "meals",
+    params(DiaryExportQuery),
+    responses((status = 200, description = "The food diary as a PDF, the last 7 days by default", content_type = "application/pdf")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn export_diary_pdf(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<DiaryExportQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let parse_date = |value: &str, field: &str| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_|
+            AppError::BadRequest(format!("Invalid {} format. Use YYYY-MM-DD", field))
+        )
+    };
+
+    let end_date = match query.end.as_deref() {
+        Some(end) => parse_date(end, "end")?,
+        None => Utc::now().date_naive(),
+    };
+    let start_date = match query.start.as_deref() {
+        Some(start) => parse_date(start, "start")?,
+        None => end_date - chrono::Duration::days(6),
+    };
+
+    if start_date > end_date {
+        return Err(AppError::BadRequest("start must be on or before end".to_string()));
+    }
+    if (end_date - start_date).num_days() > 366 {
+        return Err(AppError::BadRequest("Export range cannot exceed one year".to_string()));
+    }
+
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let start_datetime = Utc.from_utc_datetime(&start_date.and_hms_opt(0, 0, 0).unwrap());
+    let end_datetime = Utc.from_utc_datetime(&end_date.and_hms_opt(23, 59, 59).unwrap());
+
+    let meals = report_service::fetch_meals_between(&state, user_id, start_datetime, end_datetime).await?;
+
+    tracing::info!(
+        "Exporting food diary PDF for user {} ({} meals, {} to {})",
+        claims.sub,
+        meals.len(),
+        start_date,
+        end_date
+    );
+
+    let pdf = pdf_service::render_food_diary(&user, &meals, start_date, end_date, &state.config.branding.app_name);
+    let filename = format!("food-diary-{}-to-{}.pdf", start_date, end_date);
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        pdf,
+    ))
+}
@@ -1,4 +1,5 @@
 pub mod status;
+pub mod branding;
 pub mod auth;
 pub mod dashboard;
 pub mod nutrition;
@@ -9,3 +10,21 @@ pub mod health;
 pub mod meals;
 pub mod reports;
 pub mod chat;
+pub mod notifications;
+pub mod account;
+pub mod admin;
+pub mod research;
+pub mod recurring_meals;
+pub mod suggestions;
+pub mod weight;
+pub mod water;
+pub mod activity;
+pub mod favorites;
+pub mod custom_foods;
+pub mod meal_plans;
+pub mod shopping_lists;
+pub mod widget;
+pub mod images;
+pub mod achievements;
+pub mod gateway;
+pub mod live;
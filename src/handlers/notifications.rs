@@ -0,0 +1,428 @@
+use axum::{ extract::{ Path, Query, State }, response::{ IntoResponse, Json }, Extension };
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{
+        Claims,
+        Milestone,
+        Notification,
+        NotificationChannel,
+        NotificationKind,
+        NotificationPreferences,
+        ReminderPreferences,
+        User,
+    },
+    services::{ notification_service, progress_service },
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct NotificationsQuery {
+    #[serde(default)]
+    pub unread_only: bool,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NotificationDto {
+    pub id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub milestone: Option<Milestone>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_id: Option<String>,
+    pub read: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NotificationsListResponse {
+    pub success: bool,
+    pub notifications: Vec<NotificationDto>,
+    pub unread_count: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NotificationPreferencesResponse {
+    pub success: bool,
+    pub preferences: NotificationPreferences,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications",
+    tag = "notifications",
+    params(NotificationsQuery),
+    responses((status = 200, description = "In-app notifications, newest first", body = NotificationsListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_notifications(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<NotificationsQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let mut filter = doc! { "user_id": user_id };
+    if query.unread_only {
+        filter.insert("read", false);
+    }
+
+    let notifications: Vec<Notification> = state.db
+        .collection::<Notification>("notifications")
+        .find(
+            filter,
+            mongodb::options::FindOptions
+                ::builder()
+                .sort(doc! { "_id": -1 })
+                .limit(query.limit.unwrap_or(50).clamp(1, 200))
+                .build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let unread_count = state.db
+        .collection::<Notification>("notifications")
+        .count_documents(doc! { "user_id": user_id, "read": false }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let notifications = notifications
+        .into_iter()
+        .map(|n| NotificationDto {
+            id: n.id.map(|id| id.to_hex()).unwrap_or_default(),
+            kind: n.kind,
+            title: n.title,
+            message: n.message,
+            milestone: n.milestone,
+            report_id: n.report_id.map(|id| id.to_hex()),
+            read: n.read,
+            created_at: n.created_at.to_rfc3339(),
+        })
+        .collect();
+
+    Ok(
+        Json(NotificationsListResponse {
+            success: true,
+            notifications,
+            unread_count,
+        })
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/notifications/{id}/read",
+    tag = "notifications",
+    params(("id" = String, Path, description = "Notification ID")),
+    responses(
+        (status = 200, description = "Notification marked as read"),
+        (status = 404, description = "Notification not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn mark_notification_read(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(notification_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let notification_oid = ObjectId::parse_str(&notification_id).map_err(|_|
+        AppError::BadRequest("Invalid notification ID".to_string())
+    )?;
+
+    let result = state.db
+        .collection::<Notification>("notifications")
+        .update_one(
+            doc! { "_id": notification_oid, "user_id": user_id },
+            doc! { "$set": { "read": true } },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound("Notification not found".to_string()));
+    }
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Notification marked as read"
+    })
+        )
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/notifications/read-all",
+    tag = "notifications",
+    responses((status = 200, description = "Every unread notification marked as read")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn mark_all_notifications_read(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let result = state.db
+        .collection::<Notification>("notifications")
+        .update_many(
+            doc! { "user_id": user_id, "read": false },
+            doc! { "$set": { "read": true } },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "updated": result.modified_count
+    })
+        )
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications/preferences",
+    tag = "notifications",
+    responses((status = 200, description = "Notification preferences", body = NotificationPreferencesResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_notification_preferences(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let preferences = notification_service
+        ::load_preferences(&state, user_id).await
+        .map_err(AppError::InternalError)?;
+
+    Ok(
+        Json(NotificationPreferencesResponse {
+            success: true,
+            preferences,
+        })
+    )
+}
+
+/// Replaces the preferences, except `reminders`, which has its own endpoint.
+/// Already queued notifications keep their times.
+#[utoipa::path(
+    put,
+    path = "/api/notifications/preferences",
+    tag = "notifications",
+    request_body = NotificationPreferences,
+    responses((status = 200, description = "Preferences saved", body = NotificationPreferencesResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn update_notification_preferences(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<NotificationPreferences>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if !(-720..=840).contains(&payload.utc_offset_minutes) {
+        return Err(AppError::BadRequest("utc_offset_minutes must be between -720 and 840".to_string()));
+    }
+    match (&payload.quiet_hours_start, &payload.quiet_hours_end) {
+        (Some(start), Some(end)) => {
+            if notification_service::parse_time(start).is_none() || notification_service::parse_time(end).is_none() {
+                return Err(AppError::BadRequest("Quiet hours must be HH:MM".to_string()));
+            }
+            if start == end {
+                return Err(AppError::BadRequest("Quiet hours must not start and end at the same time".to_string()));
+            }
+        }
+        (None, None) => {}
+        _ => {
+            return Err(
+                AppError::BadRequest("quiet_hours_start and quiet_hours_end must be set together".to_string())
+            );
+        }
+    }
+    if notification_service::parse_time(&payload.digest_time).is_none() {
+        return Err(AppError::BadRequest("digest_time must be HH:MM".to_string()));
+    }
+
+    let previous = notification_service
+        ::load_preferences(&state, user_id).await
+        .map_err(AppError::InternalError)?;
+
+    let mut preferences_doc = mongodb::bson
+        ::to_document(&payload)
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    preferences_doc.remove("reminders");
+
+    let mut set = doc! { "updated_at": Utc::now() };
+    for (field, value) in preferences_doc {
+        set.insert(format!("notification_preferences.{}", field), value);
+    }
+
+    state.db
+        .collection::<User>("users")
+        .update_one(doc! { "_id": user_id }, doc! { "$set": set }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    // Daily totals are kept per local day, so meals near midnight move.
+    if previous.utc_offset_minutes != payload.utc_offset_minutes {
+        let rebuild_state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = progress_service::rebuild_user_totals(&rebuild_state, user_id).await {
+                tracing::error!("Failed to move daily totals of user {} to the new offset: {}", user_id, e);
+            }
+        });
+    }
+
+    let preferences = notification_service
+        ::load_preferences(&state, user_id).await
+        .map_err(AppError::InternalError)?;
+
+    Ok(
+        Json(NotificationPreferencesResponse {
+            success: true,
+            preferences,
+        })
+    )
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReminderPreferencesResponse {
+    pub success: bool,
+    pub reminders: ReminderPreferences,
+    /// The offset reminder times are read in; set it with the preferences.
+    pub utc_offset_minutes: i32,
+}
+
+/// Most hydration nudges a user can schedule per day.
+const MAX_HYDRATION_TIMES: usize = 12;
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications/reminders",
+    tag = "notifications",
+    responses((status = 200, description = "Reminder times and channels", body = ReminderPreferencesResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_reminder_preferences(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let preferences = notification_service
+        ::load_preferences(&state, user_id).await
+        .map_err(AppError::InternalError)?;
+
+    Ok(
+        Json(ReminderPreferencesResponse {
+            success: true,
+            reminders: preferences.reminders,
+            utc_offset_minutes: preferences.utc_offset_minutes,
+        })
+    )
+}
+
+/// Replaces the reminder times and channels.
+#[utoipa::path(
+    put,
+    path = "/api/notifications/reminders",
+    tag = "notifications",
+    request_body = ReminderPreferences,
+    responses((status = 200, description = "Reminders saved", body = ReminderPreferencesResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn update_reminder_preferences(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(mut payload): Json<ReminderPreferences>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let invalid_time = |time: &str| notification_service::parse_time(time).is_none();
+
+    for (index, reminder) in payload.meals.iter().enumerate() {
+        if invalid_time(&reminder.time) {
+            return Err(AppError::BadRequest("Meal reminder times must be HH:MM".to_string()));
+        }
+        if payload.meals[..index].iter().any(|other| other.meal_type.as_str() == reminder.meal_type.as_str()) {
+            return Err(
+                AppError::BadRequest(format!("Only one reminder per meal type; {} is repeated", reminder.meal_type.as_str()))
+            );
+        }
+    }
+    if payload.streak_alert_time.as_deref().is_some_and(invalid_time) {
+        return Err(AppError::BadRequest("streak_alert_time must be HH:MM".to_string()));
+    }
+    if payload.hydration_times.iter().any(|time| invalid_time(time)) {
+        return Err(AppError::BadRequest("Hydration times must be HH:MM".to_string()));
+    }
+    if payload.hydration_times.len() > MAX_HYDRATION_TIMES {
+        return Err(AppError::BadRequest(format!("At most {} hydration times", MAX_HYDRATION_TIMES)));
+    }
+
+    payload.channels = [NotificationChannel::Push, NotificationChannel::Email]
+        .into_iter()
+        .filter(|channel| payload.channels.contains(channel))
+        .collect();
+    payload.hydration_times.sort();
+    payload.hydration_times.dedup();
+
+    let reminders_bson = mongodb::bson
+        ::to_bson(&payload)
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! { "_id": user_id },
+            doc! {
+                "$set": {
+                    "notification_preferences.reminders": reminders_bson,
+                    "updated_at": Utc::now(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let preferences = notification_service
+        ::load_preferences(&state, user_id).await
+        .map_err(AppError::InternalError)?;
+
+    Ok(
+        Json(ReminderPreferencesResponse {
+            success: true,
+            reminders: preferences.reminders,
+            utc_offset_minutes: preferences.utc_offset_minutes,
+        })
+    )
+}
@@ -1,28 +1,62 @@
 use axum::{ extract::State, http::StatusCode, response::IntoResponse, Json };
 use axum_extra::extract::Multipart;
 use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
 
-use crate::{ db::AppState, error::AppError };
+use crate::{
+    db::AppState,
+    error::AppError,
+    services::food_analysis::{ FoodAnalysis, FoodImageAnalysis, FoodRejection, RejectionKind },
+};
 
-#[derive(Debug, Serialize)]
+/// Largest image accepted for analysis.
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// `analysis` is set when `is_valid_food`; otherwise `error_type` and
+/// `message` say why the image wasn't analyzed.
+#[derive(Debug, Serialize, ToSchema)]
 pub struct NutritionAnalysisResponse {
     pub success: bool,
-    pub analysis: String,
+    pub is_valid_food: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub is_valid_food: Option<bool>,
+    #[schema(value_type = Option<Object>)]
+    pub analysis: Option<FoodAnalysis>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub error_type: Option<String>,
+    #[schema(value_type = Option<String>)]
+    pub error_type: Option<RejectionKind>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+impl NutritionAnalysisResponse {
+    pub(crate) fn new(analysis: FoodImageAnalysis, prompt_version: Option<String>) -> Self {
+        let (analysis, rejection) = match analysis {
+            FoodImageAnalysis::Food(analysis) => (Some(*analysis), None),
+            FoodImageAnalysis::Rejected(rejection) => (None, Some(rejection)),
+        };
+
+        Self {
+            success: true,
+            is_valid_food: analysis.is_some(),
+            analysis,
+            error_type: rejection.as_ref().map(|r| r.kind),
+            message: rejection.map(|r| r.message),
+            prompt_version,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct FoodTextRequest {
     pub food_description: String,
 }
 
 #[derive(Debug, Serialize)]
+#[allow(dead_code)]
 pub struct FoodNutritionDetails {
     pub food_name: String,
     pub calories: f64,
@@ -32,19 +66,42 @@ pub struct FoodNutritionDetails {
     pub serving_size: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct QuickCheckResponse {
     pub success: bool,
     pub quick_check: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug, Serialize)]
+#[allow(dead_code)]
 pub struct ErrorResponse {
     pub success: bool,
     pub error: String,
 }
 
+/// Multipart body of the image endpoints; read field by field, never parsed
+/// into this.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct ImageUpload {
+    /// Any `image/*` type, at most 20 MB.
+    #[schema(value_type = String, format = Binary)]
+    pub image: Vec<u8>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/nutrition/analyze",
+    tag = "nutrition",
+    request_body(content = ImageUpload, content_type = "multipart/form-data"),
+    responses((status = 200, description = "Nutrition estimate, or why the image wasn't analyzed", body = NutritionAnalysisResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn analyze_food(
     State(state): State<AppState>,
     mut multipart: Multipart
@@ -85,11 +142,22 @@ pub async fn analyze_food(
 
     tracing::debug!("Processed {} multipart fields total", field_count);
 
+    let (image_data, mime_type) = validate_image(image_data, mime_type)?;
+    let (analysis, prompt_version) = analyze_image(&state, &image_data, &mime_type).await?;
+
+    Ok((StatusCode::OK, Json(NutritionAnalysisResponse::new(analysis, prompt_version))))
+}
+
+/// Checks an uploaded image and settles its MIME type, which defaults to JPEG.
+pub(crate) fn validate_image(
+    image_data: Option<Vec<u8>>,
+    mime_type: Option<String>
+) -> Result<(Vec<u8>, String), AppError> {
     let image_data = image_data.ok_or_else(|| {
         AppError::BadRequest("No image provided. Please upload an image file.".to_string())
     })?;
 
-    if image_data.len() > 20 * 1024 * 1024 {
+    if image_data.len() > MAX_IMAGE_BYTES {
         return Err(AppError::BadRequest("Image too large. Maximum size is 20MB.".to_string()));
     }
 
@@ -99,68 +167,46 @@ pub async fn analyze_food(
         return Err(AppError::BadRequest("Invalid file type. Please upload an image.".to_string()));
     }
 
+    Ok((image_data, mime_type))
+}
+
+/// Runs the food image analysis. A safety block comes back as an
+/// `ai_refusal` rejection rather than an error, with no prompt version.
+pub(crate) async fn analyze_image(
+    state: &AppState,
+    image_data: &[u8],
+    mime_type: &str
+) -> Result<(FoodImageAnalysis, Option<String>), AppError> {
     tracing::info!("Processing image: {} bytes, mime_type: {}", image_data.len(), mime_type);
 
-    let analysis = state.gemini_service
-        .analyze_food_image(&image_data, &mime_type).await
+    let result = state.gemini_service
+        .analyze_food_image(image_data, mime_type).await
         .map_err(|e| {
             tracing::error!("Gemini API error: {}", e);
-            let error_msg = e.to_string();
-            if
-                error_msg.contains("SAFETY") ||
-                error_msg.contains("blocked") ||
-                error_msg.contains("filter")
-            {
-                return AppError::BadRequest(
-                    "This image could not be processed. Please upload a clear photo of food.".to_string()
-                );
-            }
-            AppError::InternalError(e)
-        })?;
-
-    tracing::info!("Successfully analyzed food image");
-
-    let (is_valid_food, error_type, message) = parse_validation_response(&analysis);
-
-    let response = NutritionAnalysisResponse {
-        success: true,
-        analysis,
-        is_valid_food: Some(is_valid_food),
-        error_type,
-        message,
-        timestamp: chrono::Utc::now(),
-    };
+            AppError::from_ai(e)
+        });
 
-    Ok((StatusCode::OK, Json(response)))
-}
-
-fn parse_validation_response(analysis: &str) -> (bool, Option<String>, Option<String>) {
-    if let Some(start) = analysis.find('{') {
-        if let Some(end) = analysis.rfind('}') {
-            let json_str = &analysis[start..=end];
-            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(json_str) {
-                let is_valid = parsed
-                    .get("is_valid_food")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(true);
-
-                if !is_valid {
-                    let error_type = parsed
-                        .get("error_type")
-                        .and_then(|v| v.as_str())
-                        .map(|s| s.to_string());
-                    let message = parsed
-                        .get("message")
-                        .and_then(|v| v.as_str())
-                        .map(|s| s.to_string());
-                    return (false, error_type, message);
-                }
-            }
+    match result {
+        Ok((analysis, prompt_version)) => {
+            tracing::info!("Successfully analyzed food image");
+            Ok((analysis, Some(prompt_version)))
         }
+        Err(AppError::AiRefusal(refusal)) => {
+            tracing::info!("Food image analysis refused: {}", refusal.reason);
+            Ok((FoodImageAnalysis::Rejected(FoodRejection::new(RejectionKind::AiRefusal)), None))
+        }
+        Err(e) => Err(e),
     }
-    (true, None, None)
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/nutrition/quick-check",
+    tag = "nutrition",
+    request_body(content = ImageUpload, content_type = "multipart/form-data"),
+    responses((status = 200, description = "A one-paragraph verdict on the food", body = QuickCheckResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn quick_food_check(
     State(state): State<AppState>,
     mut multipart: Multipart
@@ -199,40 +245,54 @@ pub async fn quick_food_check(
         }
     }
 
-    let image_data = image_data.ok_or_else(|| {
-        AppError::BadRequest("No image provided. Please upload an image file.".to_string())
-    })?;
-
-    if image_data.len() > 20 * 1024 * 1024 {
-        return Err(AppError::BadRequest("Image too large. Maximum size is 20MB.".to_string()));
-    }
-
-    let mime_type = mime_type.unwrap_or_else(|| "image/jpeg".to_string());
-
-    if !mime_type.starts_with("image/") {
-        return Err(AppError::BadRequest("Invalid file type. Please upload an image.".to_string()));
-    }
+    let (image_data, mime_type) = validate_image(image_data, mime_type)?;
 
     tracing::info!("Processing quick check: {} bytes, mime_type: {}", image_data.len(), mime_type);
 
-    let quick_check = state.gemini_service
+    let result = state.gemini_service
         .quick_food_check(&image_data, &mime_type).await
         .map_err(|e| {
             tracing::error!("Gemini API error: {}", e);
-            AppError::InternalError(e)
-        })?;
-
-    tracing::info!("Successfully completed quick food check");
-
-    let response = QuickCheckResponse {
-        success: true,
-        quick_check,
-        timestamp: chrono::Utc::now(),
+            AppError::from_ai(e)
+        });
+
+    let response = match result {
+        Ok((quick_check, prompt_version)) => {
+            tracing::info!("Successfully completed quick food check");
+            QuickCheckResponse {
+                success: true,
+                quick_check,
+                error_type: None,
+                prompt_version: Some(prompt_version),
+                timestamp: chrono::Utc::now(),
+            }
+        }
+        Err(AppError::AiRefusal(refusal)) => {
+            tracing::info!("Quick food check refused: {}", refusal.reason);
+            QuickCheckResponse {
+                success: true,
+                quick_check: refusal.user_message().to_string(),
+                error_type: Some("ai_refusal".to_string()),
+                prompt_version: None,
+                timestamp: chrono::Utc::now(),
+            }
+        }
+        Err(e) => {
+            return Err(e);
+        }
     };
 
     Ok((StatusCode::OK, Json(response)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/nutrition/analyze-text",
+    tag = "nutrition",
+    request_body = FoodTextRequest,
+    responses((status = 200, description = "Nutrition estimate for the described food")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn analyze_food_text(
     State(state): State<AppState>,
     Json(payload): Json<FoodTextRequest>
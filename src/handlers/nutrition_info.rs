@@ -1,11 +1,14 @@
 use axum::{ extract::{ Query, State }, http::StatusCode, response::IntoResponse, Json };
 use serde::{ Deserialize, Serialize };
+use utoipa::IntoParams;
 
-use crate::{ db::AppState, error::AppError };
+use crate::{ db::AppState, error::AppError, services::cache_service };
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct NutritionQuery {
     pub query: String,
+    #[serde(default)]
+    pub no_cache: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -15,18 +18,33 @@ pub struct ApiResponse<T> {
     pub message: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/nutrition-info",
+    tag = "nutrition",
+    params(NutritionQuery),
+    responses(
+        (status = 200, description = "Nutrition facts for the foods named in the query"),
+        (status = 503, description = "The nutrition data provider is unavailable")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_nutrition_info(
     State(state): State<AppState>,
     Query(params): Query<NutritionQuery>
 ) -> Result<impl IntoResponse, AppError> {
     tracing::info!("Fetching nutrition info for query: {}", params.query);
 
-    let result = state.ninja_service.get_nutrition(&params.query).await.map_err(|e| {
-        tracing::error!("Failed to get nutrition info from Ninja API: {}", e);
-        AppError::ExternalApiError(
-            "Nutrition data service is temporarily unavailable. Please try again later.".to_string()
-        )
-    })?;
+    let result = state.cache
+        .get_or_fetch(cache_service::NINJA_NUTRITION, &params.query, params.no_cache, || {
+            state.ninja_service.get_nutrition(&params.query)
+        }).await
+        .map_err(|e| {
+            tracing::error!("Failed to get nutrition info from Ninja API: {}", e);
+            AppError::ExternalApiError(
+                "Nutrition data service is temporarily unavailable. Please try again later.".to_string()
+            )
+        })?;
 
     tracing::info!("Successfully retrieved {} nutrition items", result.len());
 
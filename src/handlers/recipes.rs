@@ -1,19 +1,83 @@
-use axum::{ extract::{ Path, Query, State }, http::StatusCode, response::IntoResponse, Json };
+use axum::{
+    extract::{ Path, Query, State },
+    http::{ header, StatusCode },
+    response::IntoResponse,
+    Extension,
+    Json,
+};
+use mongodb::bson::{ doc, oid::ObjectId };
 use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
 
-use crate::{ db::AppState, error::AppError };
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, ConditionGuidance, HealthProfile, User },
+    services::{
+        cache_service::{ self, CacheBypassQuery },
+        condition_guidance,
+        dietary_filter::{ self, RecipeConflict },
+        mealdb_service::Meal,
+    },
+};
+
+/// Search and detail responses are filtered or annotated with the user's
+/// condition guidance, so only the user's own client may reuse them.
+const PERSONALIZED_CACHE_CONTROL: &str = "private, max-age=3600, stale-while-revalidate=86400";
+/// Category and area listings are the same for everyone.
+const SHARED_CACHE_CONTROL: &str = "public, max-age=3600, stale-while-revalidate=86400";
 
 #[derive(Debug, Deserialize)]
 pub struct SearchQuery {
     pub query: String,
+    /// Skip hiding recipes that conflict with the user's medical conditions.
+    #[serde(default)]
+    pub include_all: bool,
+    #[serde(flatten)]
+    pub profile: ProfileQuery,
+    #[serde(default)]
+    pub no_cache: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RandomQuery {
     #[serde(default = "default_count")]
     pub count: usize,
+    #[serde(default)]
+    pub include_all: bool,
+    #[serde(flatten)]
+    pub profile: ProfileQuery,
+}
+
+/// Opt-in checking of recipes against the user's dietary preferences,
+/// allergies and medical conditions. Takes over from `include_all`.
+#[derive(Debug, Deserialize, Default, IntoParams)]
+pub struct ProfileQuery {
+    #[serde(default)]
+    pub respect_profile: bool,
+    #[serde(default)]
+    pub conflicting: ConflictMode,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictMode {
+    /// Leave conflicting recipes out.
+    #[default]
+    Hide,
+    /// Keep them and list why in `conflicts`.
+    Flag,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RecipeQuery {
+    #[serde(default)]
+    pub respect_profile: bool,
+    #[serde(default)]
+    pub no_cache: bool,
 }
 
+
 fn default_count() -> usize {
     6
 }
@@ -23,100 +87,357 @@ pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub message: Option<String>,
+    /// With `respect_profile=true`: why recipes don't suit the profile.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflicts: Option<Vec<RecipeConflicts>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecipeConflicts {
+    pub recipe_id: String,
+    pub conflicts: Vec<RecipeConflict>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/recipes/search",
+    tag = "recipes",
+    params(
+        ("query" = String, Query, description = "Recipe name to search for"),
+        ("include_all" = Option<bool>, Query, description = "Skip hiding recipes that conflict with the user's medical conditions"),
+        ProfileQuery,
+        CacheBypassQuery
+    ),
+    responses((status = 200, description = "Matching TheMealDB recipes, filtered or flagged against the user's profile")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn search_recipes(
     State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
     Query(params): Query<SearchQuery>
 ) -> Result<impl IntoResponse, AppError> {
-    let result = state.mealdb_service
-        .search_meals(&params.query).await
-        .map_err(|e| AppError::InternalError(e))?;
+    let result = state.cache
+        .get_or_fetch(cache_service::MEALDB_SEARCH, &params.query, params.no_cache, || {
+            state.mealdb_service.search_meals(&params.query)
+        }).await
+        .map_err(AppError::InternalError)?;
+
+    let (result, message, conflicts) = if params.profile.respect_profile {
+        check_against_profile(result, user_profile(&state, &claims).await?.as_ref(), params.profile.conflicting)
+    } else if params.include_all {
+        (result, None, None)
+    } else {
+        let (result, message) = hide_conflicting(result, &user_guidance(&state, &claims).await?);
+        (result, message, None)
+    };
 
     Ok((
         StatusCode::OK,
+        [(header::CACHE_CONTROL, PERSONALIZED_CACHE_CONTROL)],
         Json(ApiResponse {
             success: true,
             data: Some(result),
-            message: None,
+            message,
+            conflicts,
         }),
     ))
 }
 
+/// Recipes looked up directly are never hidden; conflicts are reported in
+/// `message`, and with `respect_profile=true` also in `conflicts`.
+#[utoipa::path(
+    get,
+    path = "/api/recipes/{meal_id}",
+    tag = "recipes",
+    params(("meal_id" = String, Path, description = "TheMealDB recipe ID"), RecipeQuery),
+    responses((status = 200, description = "The recipe, with any conflicts with the user's profile")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_recipe_by_id(
     State(state): State<AppState>,
-    Path(meal_id): Path<String>
+    Extension(claims): Extension<Claims>,
+    Path(meal_id): Path<String>,
+    Query(params): Query<RecipeQuery>
 ) -> Result<impl IntoResponse, AppError> {
-    let result = state.mealdb_service
-        .get_meal_by_id(&meal_id).await
-        .map_err(|e| AppError::InternalError(e))?;
+    let result = state.cache
+        .get_or_fetch(cache_service::MEALDB_MEAL, &meal_id, params.no_cache, || {
+            state.mealdb_service.get_meal_by_id(&meal_id)
+        }).await
+        .map_err(AppError::InternalError)?;
 
     match result {
-        Some(meal) =>
+        Some(meal) => {
+            let (message, conflicts) = if params.respect_profile {
+                let found = user_profile(&state, &claims).await?
+                    .map(|profile| dietary_filter::recipe_conflicts(&profile, &meal))
+                    .unwrap_or_default();
+                let message = (!found.is_empty()).then(|| {
+                    let reasons: Vec<String> = found.iter().map(conflict_reason).collect();
+                    format!("Doesn't suit your health profile: {}", reasons.join(", "))
+                });
+                (message, Some(vec![RecipeConflicts { recipe_id: meal.id_meal.clone(), conflicts: found }]))
+            } else {
+                let guidance = user_guidance(&state, &claims).await?;
+                let conflicts = condition_guidance::conflicts(&guidance, &ingredient_names(&meal));
+                let message = (!conflicts.is_empty()).then(|| {
+                    let items: Vec<String> = conflicts
+                        .iter()
+                        .map(|(g, ingredient)| format!("{} ({})", ingredient, g.title))
+                        .collect();
+                    format!("Contains ingredients to limit for your health profile: {}", items.join(", "))
+                });
+                (message, None)
+            };
+
             Ok((
                 StatusCode::OK,
+                [(header::CACHE_CONTROL, PERSONALIZED_CACHE_CONTROL)],
                 Json(ApiResponse {
                     success: true,
                     data: Some(meal),
-                    message: None,
+                    message,
+                    conflicts,
                 }),
-            )),
+            ))
+        }
         None => Err(AppError::NotFound("Recipe not found".to_string())),
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/recipes/random",
+    tag = "recipes",
+    params(
+        ("count" = Option<usize>, Query, description = "How many recipes; defaults to 6"),
+        ("include_all" = Option<bool>, Query, description = "Skip hiding recipes that conflict with the user's medical conditions"),
+        ProfileQuery
+    ),
+    responses((status = 200, description = "Random recipes suiting the user")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_random_recipes(
     State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
     Query(params): Query<RandomQuery>
 ) -> Result<impl IntoResponse, AppError> {
     let count = params.count.min(10); 
 
     let result = state.mealdb_service
         .get_random_meals(count).await
-        .map_err(|e| AppError::InternalError(e))?;
+        .map_err(AppError::InternalError)?;
+
+    let (result, message, conflicts) = if params.profile.respect_profile {
+        check_against_profile(result, user_profile(&state, &claims).await?.as_ref(), params.profile.conflicting)
+    } else if params.include_all {
+        (result, None, None)
+    } else {
+        let (result, message) = hide_conflicting(result, &user_guidance(&state, &claims).await?);
+        (result, message, None)
+    };
 
     Ok((
         StatusCode::OK,
+        [(header::CACHE_CONTROL, "no-store")],
         Json(ApiResponse {
             success: true,
             data: Some(result),
-            message: None,
+            message,
+            conflicts,
         }),
     ))
 }
 
+/// With `respect_profile=true`, a whole category the user's diet rules out,
+/// such as Pork for a halal diet, comes back empty.
+#[utoipa::path(
+    get,
+    path = "/api/recipes/category/{category}",
+    tag = "recipes",
+    params(("category" = String, Path, description = "TheMealDB category, e.g. `Seafood`"), RecipeQuery),
+    responses((status = 200, description = "Recipes in the category")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn filter_by_category(
     State(state): State<AppState>,
-    Path(category): Path<String>
+    Extension(claims): Extension<Claims>,
+    Path(category): Path<String>,
+    Query(params): Query<RecipeQuery>
 ) -> Result<impl IntoResponse, AppError> {
-    let result = state.mealdb_service
-        .filter_by_category(&category).await
-        .map_err(|e| AppError::InternalError(e))?;
+    let mut result = state.cache
+        .get_or_fetch(cache_service::MEALDB_CATEGORY, &category, params.no_cache, || {
+            state.mealdb_service.filter_by_category(&category)
+        }).await
+        .map_err(AppError::InternalError)?;
+
+    let mut message = None;
+    let mut cache_control = SHARED_CACHE_CONTROL;
+    if params.respect_profile {
+        cache_control = PERSONALIZED_CACHE_CONTROL;
+        let preferences = user_profile(&state, &claims).await?
+            .and_then(|profile| profile.dietary_preferences)
+            .unwrap_or_default();
+        let category_name = result
+            .first()
+            .and_then(|meal| meal.str_category.clone())
+            .unwrap_or_else(|| category.clone());
+        if
+            let Some(preference) = preferences
+                .iter()
+                .find(|p| dietary_filter::preference_conflict(&category_name, &[], p).is_some())
+        {
+            result.clear();
+            message = Some(format!("{} recipes don't suit your {:?} diet", category_name, preference));
+        }
+    }
 
     Ok((
         StatusCode::OK,
+        [(header::CACHE_CONTROL, cache_control)],
         Json(ApiResponse {
             success: true,
             data: Some(result),
-            message: None,
+            message,
+            conflicts: None,
         }),
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/recipes/area/{area}",
+    tag = "recipes",
+    params(("area" = String, Path, description = "TheMealDB area, e.g. `Indonesian`"), CacheBypassQuery),
+    responses((status = 200, description = "Recipes from the area")),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn filter_by_area(
     State(state): State<AppState>,
-    Path(area): Path<String>
+    Path(area): Path<String>,
+    Query(cache): Query<CacheBypassQuery>
 ) -> Result<impl IntoResponse, AppError> {
-    let result = state.mealdb_service
-        .filter_by_area(&area).await
-        .map_err(|e| AppError::InternalError(e))?;
+    let result = state.cache
+        .get_or_fetch(cache_service::MEALDB_AREA, &area, cache.no_cache, || {
+            state.mealdb_service.filter_by_area(&area)
+        }).await
+        .map_err(AppError::InternalError)?;
 
     Ok((
         StatusCode::OK,
+        [(header::CACHE_CONTROL, SHARED_CACHE_CONTROL)],
         Json(ApiResponse {
             success: true,
             data: Some(result),
             message: None,
+            conflicts: None,
         }),
     ))
 }
+
+async fn user_guidance(state: &AppState, claims: &Claims) -> Result<Vec<ConditionGuidance>, AppError> {
+    Ok(
+        user_profile(state, claims).await?
+            .map(|profile| condition_guidance::for_profile(&profile))
+            .unwrap_or_default()
+    )
+}
+
+async fn user_profile(state: &AppState, claims: &Claims) -> Result<Option<HealthProfile>, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(user.and_then(|u| u.health_profile))
+}
+
+fn ingredient_names(meal: &Meal) -> Vec<String> {
+    meal.get_ingredients()
+        .into_iter()
+        .map(|(ingredient, _)| ingredient)
+        .collect()
+}
+
+/// Drops recipes with ingredients the user's conditions say to avoid.
+/// Category and area listings carry no ingredients, so they aren't filtered.
+fn hide_conflicting(meals: Vec<Meal>, guidance: &[ConditionGuidance]) -> (Vec<Meal>, Option<String>) {
+    if guidance.is_empty() {
+        return (meals, None);
+    }
+
+    let total = meals.len();
+    let kept: Vec<Meal> = meals
+        .into_iter()
+        .filter(|meal| condition_guidance::conflicts(guidance, &ingredient_names(meal)).is_empty())
+        .collect();
+
+    let hidden = total - kept.len();
+    let message = (hidden > 0).then(|| {
+        let titles: Vec<&str> = guidance
+            .iter()
+            .map(|g| g.title.as_str())
+            .collect();
+        format!(
+            "Hid {} recipe(s) that conflict with your health profile ({}). Pass include_all=true to show them.",
+            hidden,
+            titles.join("; ")
+        )
+    });
+
+    (kept, message)
+}
+
+/// Hides or flags recipes that conflict with the profile's dietary
+/// preferences, allergies or medical conditions. Without a profile there's
+/// nothing to check.
+fn check_against_profile(
+    meals: Vec<Meal>,
+    profile: Option<&HealthProfile>,
+    mode: ConflictMode
+) -> (Vec<Meal>, Option<String>, Option<Vec<RecipeConflicts>>) {
+    let Some(profile) = profile else {
+        return (meals, None, Some(Vec::new()));
+    };
+
+    let mut kept = Vec::new();
+    let mut flagged = Vec::new();
+    let mut hidden = 0;
+    for meal in meals {
+        let conflicts = dietary_filter::recipe_conflicts(profile, &meal);
+        if conflicts.is_empty() {
+            kept.push(meal);
+        } else if mode == ConflictMode::Hide {
+            hidden += 1;
+        } else {
+            flagged.push(RecipeConflicts { recipe_id: meal.id_meal.clone(), conflicts });
+            kept.push(meal);
+        }
+    }
+
+    let message = match mode {
+        ConflictMode::Hide if hidden > 0 =>
+            Some(
+                format!(
+                    "Hid {} recipe(s) that don't suit your diet, allergies or health conditions. Pass conflicting=flag to show them.",
+                    hidden
+                )
+            ),
+        ConflictMode::Flag if !flagged.is_empty() =>
+            Some(format!("{} recipe(s) don't suit your diet, allergies or health conditions.", flagged.len())),
+        _ => None,
+    };
+
+    (kept, message, Some(flagged))
+}
+
+fn conflict_reason(conflict: &RecipeConflict) -> String {
+    match conflict {
+        RecipeConflict::DietaryPreference { preference, reason } => format!("{} ({:?})", reason, preference),
+        RecipeConflict::Allergy { allergy, ingredient } => format!("{} (allergy: {})", ingredient, allergy),
+        RecipeConflict::MedicalCondition { title, ingredient } => format!("{} ({})", ingredient, title),
+    }
+}
@@ -0,0 +1,297 @@
+use axum::{ extract::{ Path, State }, http::StatusCode, response::{ IntoResponse, Json }, Extension };
+use chrono::{ NaiveTime, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, MealLog, MealSource, MealType, RecurringMeal },
+    services::{ meal_history_service::{ self, ChangeSource }, progress_service, report_service },
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateRecurringMealRequest {
+    pub meal_type: MealType,
+    pub food_name: String,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub source: MealSource,
+    /// ISO weekdays (1 = Monday ... 7 = Sunday); omit for every day.
+    #[serde(default)]
+    pub days_of_week: Vec<u32>,
+    /// UTC `HH:MM`; defaults to a typical time for the meal type.
+    pub time_of_day: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecurringMealDto {
+    pub id: String,
+    pub meal_type: MealType,
+    pub food_name: String,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: Option<String>,
+    pub notes: Option<String>,
+    pub source: MealSource,
+    pub days_of_week: Vec<u32>,
+    pub time_of_day: String,
+    pub active: bool,
+    pub last_logged_date: Option<String>,
+    pub created_at: String,
+}
+
+impl From<RecurringMeal> for RecurringMealDto {
+    fn from(meal: RecurringMeal) -> Self {
+        Self {
+            id: meal.id.map(|id| id.to_hex()).unwrap_or_default(),
+            meal_type: meal.meal_type,
+            food_name: meal.food_name,
+            calories: meal.calories,
+            protein_g: meal.protein_g,
+            carbs_g: meal.carbs_g,
+            fat_g: meal.fat_g,
+            serving_size: meal.serving_size,
+            notes: meal.notes,
+            source: meal.source,
+            days_of_week: meal.days_of_week,
+            time_of_day: meal.time_of_day,
+            active: meal.active,
+            last_logged_date: meal.last_logged_date,
+            created_at: meal.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecurringMealResponse {
+    pub success: bool,
+    pub recurring_meal: RecurringMealDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecurringMealsListResponse {
+    pub success: bool,
+    pub recurring_meals: Vec<RecurringMealDto>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/meals/recurring",
+    tag = "recurring_meals",
+    request_body = CreateRecurringMealRequest,
+    responses((status = 201, description = "Recurring meal scheduled", body = RecurringMealResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn create_recurring_meal(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateRecurringMealRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if payload.food_name.trim().is_empty() {
+        return Err(AppError::BadRequest("food_name cannot be empty".to_string()));
+    }
+    if payload.source.is_verified() {
+        return Err(AppError::BadRequest("source cannot be fdc or barcode".to_string()));
+    }
+    if payload.days_of_week.iter().any(|d| !(1..=7).contains(d)) {
+        return Err(
+            AppError::BadRequest("days_of_week must use 1 (Monday) through 7 (Sunday)".to_string())
+        );
+    }
+
+    let time_of_day = match payload.time_of_day {
+        Some(time) => {
+            NaiveTime::parse_from_str(&time, "%H:%M").map_err(|_|
+                AppError::BadRequest("Invalid time_of_day format. Use HH:MM".to_string())
+            )?;
+            time
+        }
+        None =>
+            (match payload.meal_type {
+                MealType::Breakfast => "07:00",
+                MealType::Lunch => "12:00",
+                MealType::Dinner => "18:00",
+                MealType::Snack => "15:00",
+            }).to_string(),
+    };
+
+    let mut days_of_week = payload.days_of_week;
+    days_of_week.sort_unstable();
+    days_of_week.dedup();
+
+    let mut recurring_meal = RecurringMeal {
+        id: None,
+        user_id,
+        meal_type: payload.meal_type,
+        food_name: payload.food_name,
+        calories: payload.calories,
+        protein_g: payload.protein_g,
+        carbs_g: payload.carbs_g,
+        fat_g: payload.fat_g,
+        serving_size: payload.serving_size,
+        notes: payload.notes,
+        source: payload.source,
+        days_of_week,
+        time_of_day,
+        active: true,
+        last_logged_date: None,
+        created_at: Utc::now(),
+    };
+
+    let result = state.db
+        .collection::<RecurringMeal>("recurring_meals")
+        .insert_one(&recurring_meal, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    recurring_meal.id = result.inserted_id.as_object_id();
+
+    tracing::info!("Created recurring meal {:?} for user {}", recurring_meal.id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(RecurringMealResponse {
+            success: true,
+            recurring_meal: recurring_meal.into(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/meals/recurring",
+    tag = "recurring_meals",
+    responses((status = 200, description = "Active recurring meals", body = RecurringMealsListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_recurring_meals(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let meals: Vec<RecurringMeal> = state.db
+        .collection::<RecurringMeal>("recurring_meals")
+        .find(doc! { "user_id": user_id, "active": true }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(
+        Json(RecurringMealsListResponse {
+            success: true,
+            recurring_meals: meals.into_iter().map(Into::into).collect(),
+        })
+    )
+}
+
+/// Stops future auto-logging. Meals already logged from the schedule are kept.
+#[utoipa::path(
+    delete,
+    path = "/api/meals/recurring/{id}",
+    tag = "recurring_meals",
+    params(("id" = String, Path, description = "Recurring meal ID")),
+    responses(
+        (status = 200, description = "Recurring meal stopped"),
+        (status = 404, description = "Recurring meal not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_recurring_meal(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(recurring_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let recurring_oid = ObjectId::parse_str(&recurring_id).map_err(|_|
+        AppError::BadRequest("Invalid recurring meal ID".to_string())
+    )?;
+
+    let result = state.db
+        .collection::<RecurringMeal>("recurring_meals")
+        .update_one(
+            doc! { "_id": recurring_oid, "user_id": user_id, "active": true },
+            doc! { "$set": { "active": false } },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound("Recurring meal not found".to_string()));
+    }
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Recurring meal stopped"
+    })
+        )
+    )
+}
+
+/// Removes a meal the scheduler logged. Today's run stays claimed, so the meal
+/// is not re-logged until its next scheduled day.
+#[utoipa::path(
+    post,
+    path = "/api/meals/{id}/undo-auto-log",
+    tag = "recurring_meals",
+    params(("id" = String, Path, description = "Meal ID")),
+    responses(
+        (status = 200, description = "Auto-logged meal removed"),
+        (status = 404, description = "No auto-logged meal with this ID")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn undo_auto_logged_meal(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(meal_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let meal_oid = ObjectId::parse_str(&meal_id).map_err(|_|
+        AppError::BadRequest("Invalid meal ID".to_string())
+    )?;
+
+    let meal = state.db
+        .collection::<MealLog>("meal_logs")
+        .find_one_and_delete(doc! { "_id": meal_oid, "user_id": user_id, "auto_logged": true }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Auto-logged meal not found".to_string()))?;
+
+    meal_history_service::record_delete(&state, &meal, ChangeSource::user()).await;
+    report_service::invalidate_compliance_score(&state, user_id).await;
+    progress_service::record_meal_removed(&state, &meal).await?;
+
+    tracing::info!("Undid auto-logged meal {} for user {}", meal_id, claims.sub);
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Auto-logged meal removed"
+    })
+        )
+    )
+}
@@ -1,43 +1,157 @@
 use axum::{
     extract::{Path, Query, State},
+    http::StatusCode,
     response::{IntoResponse, Json},
     Extension,
 };
+use chrono::Utc;
 use mongodb::bson::{doc, oid::ObjectId};
 use serde::{Deserialize, Serialize};
-use chrono::Utc;
+use utoipa::{IntoParams, ToSchema};
 use futures::stream::TryStreamExt;
 
 use crate::{
     db::AppState,
     error::AppError,
-    models::{Claims, MealReport, ReportPeriod, ReportStatus, User, MealLog},
-    services::email_service::EmailService,
+    models::{Claims, MealReport, ReportFrequency, ReportPeriod, ReportSchedule, ReportStatus},
+    services::{report_service, response_cache_service::{self, CachedRoute}, timezone},
+    validation::{Checks, Validate},
 };
 
-#[derive(Debug, Deserialize)]
+/// Longest report period, a leap year.
+const MAX_REPORT_DAYS: i64 = 366;
+
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct GenerateReportQuery {
     pub report_type: String, 
     pub start_date: String,
     pub end_date: String,
     #[serde(default)]
     pub send_email: bool,
+    pub language: Option<String>,
+    /// UTC offset days are counted in, e.g. `+07:00`; defaults to UTC.
+    pub tz: Option<String>,
+}
+
+impl Validate for GenerateReportQuery {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut checks = Checks::new();
+        let parse = |date: &str| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        if let (Some(start), Some(end)) = (parse(&self.start_date), parse(&self.end_date)) {
+            if (end - start).num_days() >= MAX_REPORT_DAYS {
+                checks.add("end_date", format!("A report can cover at most {} days", MAX_REPORT_DAYS));
+            }
+        }
+        check_language(&mut checks, self.language.as_deref());
+        checks.finish()
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ReportResponse {
     pub success: bool,
+    #[schema(value_type = Object)]
     pub report: MealReport,
     pub message: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ReportsListResponse {
     pub success: bool,
+    #[schema(value_type = Vec<Object>)]
     pub reports: Vec<MealReport>,
-    pub total: usize,
+    /// Reports matching the filters, across all pages.
+    pub total: u64,
+    pub skip: u64,
+    pub limit: i64,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ReportsListQuery {
+    pub skip: Option<u64>,
+    pub limit: Option<i64>,
+    pub report_type: Option<String>,
+    /// Reports whose period overlaps `[start_date, end_date]` (`YYYY-MM-DD`).
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+/// Returned when a report is queued; poll `status_url` or wait for the
+/// "report ready" notification.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReportJobResponse {
+    pub success: bool,
+    pub report_id: String,
+    pub status: ReportStatus,
+    pub status_url: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReportStatusResponse {
+    pub success: bool,
+    pub report_id: String,
+    pub report_type: ReportPeriod,
+    pub status: ReportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub generated_at: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScheduleReportRequest {
+    pub frequency: ReportFrequency,
+    /// `false` turns an existing schedule off.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub language: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Validate for ScheduleReportRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut checks = Checks::new();
+        check_language(&mut checks, self.language.as_deref());
+        checks.finish()
+    }
+}
+
+/// The language goes into the coach prompt, so only a plain name such as
+/// "English" or "Bahasa Indonesia" is accepted.
+fn check_language(checks: &mut Checks, language: Option<&str>) {
+    let Some(language) = language else {
+        return;
+    };
+    checks.required_text("language", language, 40);
+    if !language.chars().all(|c| c.is_alphabetic() || c == ' ' || c == '-') {
+        checks.add("language", "language may only contain letters, spaces and hyphens");
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReportScheduleResponse {
+    pub success: bool,
+    pub frequency: ReportFrequency,
+    pub active: bool,
+    pub language: String,
+    /// When the next report goes out, if the schedule is active.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_delivery_date: Option<String>,
+    pub message: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/reports/generate",
+    tag = "reports",
+    params(GenerateReportQuery),
+    responses((status = 202, description = "Report queued; poll `status_url`", body = ReportJobResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn generate_report(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -46,329 +160,289 @@ pub async fn generate_report(
     let user_id = ObjectId::parse_str(&claims.sub)
         .map_err(|_| AppError::BadRequest("Invalid user ID".to_string()))?;
 
-    let user = state.db
-        .collection::<User>("users")
-        .find_one(doc! { "_id": user_id }, None)
-        .await
-        .map_err(|e| AppError::InternalError(e.into()))?
-        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
-
-    let report_type = match query.report_type.to_lowercase().as_str() {
-        "daily" => ReportPeriod::Daily,
-        "weekly" => ReportPeriod::Weekly,
-        "monthly" => ReportPeriod::Monthly,
-        "yearly" => ReportPeriod::Yearly,
-        _ => return Err(AppError::BadRequest("Invalid report type".to_string())),
-    };
+    query.validate()?;
+    let report_type = ReportPeriod::parse(&query.report_type)
+        .ok_or_else(|| AppError::BadRequest("Invalid report type".to_string()))?;
 
     let start_date = chrono::NaiveDate::parse_from_str(&query.start_date, "%Y-%m-%d")
         .map_err(|_| AppError::BadRequest("Invalid start date format".to_string()))?;
     let end_date = chrono::NaiveDate::parse_from_str(&query.end_date, "%Y-%m-%d")
         .map_err(|_| AppError::BadRequest("Invalid end date format".to_string()))?;
 
-    let start_datetime = chrono::TimeZone::from_utc_datetime(&chrono::Utc, &start_date.and_hms_opt(0, 0, 0).unwrap());
-    let end_datetime = chrono::TimeZone::from_utc_datetime(&chrono::Utc, &end_date.and_hms_opt(23, 59, 59).unwrap());
-
-    let start_bson = mongodb::bson::DateTime::from_chrono(start_datetime);
-    let end_bson = mongodb::bson::DateTime::from_chrono(end_datetime);
-
-    tracing::info!("Querying meals for user {} from {} to {}", user_id, start_datetime, end_datetime);
-
-    let mut cursor = state.db
-        .collection::<MealLog>("meal_logs")
-        .find(
-            doc! {
-                "user_id": user_id,
-                "date": {
-                    "$gte": start_bson,
-                    "$lte": end_bson,
-                }
-            },
-            None,
-        )
-        .await
-        .map_err(|e| AppError::InternalError(e.into()))?;
-
-    let mut meals: Vec<MealLog> = Vec::new();
-    while let Some(meal) = cursor.try_next().await.map_err(|e| AppError::InternalError(e.into()))? {
-        tracing::debug!("Found meal: {} on {}", meal.food_name, meal.date);
-        meals.push(meal);
+    if start_date > end_date {
+        return Err(AppError::BadRequest("start_date must be before end_date".to_string()));
     }
-    
-    tracing::info!("Found {} meals with BSON date query for report", meals.len());
-
-    if meals.is_empty() {
-        tracing::warn!("No meals found with BSON query, trying manual filtering");
-        let all_meals_cursor = state.db
-            .collection::<MealLog>("meal_logs")
-            .find(doc! { "user_id": user_id }, None)
-            .await
-            .map_err(|e| AppError::InternalError(e.into()))?;
 
-        let all_meals: Vec<MealLog> = all_meals_cursor
-            .try_collect()
-            .await
-            .map_err(|e| AppError::InternalError(e.into()))?;
+    let offset = timezone::parse(query.tz.as_deref())?;
 
-        tracing::info!("Total meals in DB for user: {}", all_meals.len());
-        
-        meals = all_meals.into_iter()
-            .filter(|meal| {
-                let meal_date = meal.date;
-                let in_range = meal_date >= start_datetime && meal_date <= end_datetime;
-                if in_range {
-                    tracing::debug!("Meal {} is in range: {}", meal.food_name, meal_date);
-                }
-                in_range
-            })
-            .collect();
-        
-        tracing::info!("Manually filtered {} meals for report", meals.len());
+    let mut report = report_service::pending_report(user_id, report_type, start_date, end_date);
+    if !timezone::is_utc(offset) {
+        report.utc_offset_minutes = Some(timezone::minutes(offset));
     }
 
-    let total_days = (end_date - start_date).num_days() as usize + 1;
-    let mut days_with_meals = std::collections::HashSet::new();
-    let mut total_calories = 0.0;
-    let mut total_protein = 0.0;
-    let mut total_carbs = 0.0;
-    let mut total_fat = 0.0;
-
-    for meal in &meals {
-        days_with_meals.insert(meal.date.date_naive());
-        total_calories += meal.calories;
-        total_protein += meal.protein_g;
-        total_carbs += meal.carbs_g;
-        total_fat += meal.fat_g;
-    }
+    let result = state.db
+        .collection::<MealReport>("meal_reports")
+        .insert_one(&report, None)
+        .await
+        .map_err(|e| AppError::InternalError(e.into()))?;
 
-    let days_logged = days_with_meals.len();
-    let avg_calories = if days_logged > 0 { total_calories / days_logged as f64 } else { 0.0 };
-    let avg_protein = if days_logged > 0 { total_protein / days_logged as f64 } else { 0.0 };
-    let avg_carbs = if days_logged > 0 { total_carbs / days_logged as f64 } else { 0.0 };
-    let avg_fat = if days_logged > 0 { total_fat / days_logged as f64 } else { 0.0 };
-
-    let (target_calories, target_protein, target_carbs, target_fat, goal_type) = if let Some(profile) = &user.health_profile {
-        let goal = match profile.goal {
-            crate::models::HealthGoal::LoseWeight => "lose_weight".to_string(),
-            crate::models::HealthGoal::MaintainWeight => "maintain_weight".to_string(),
-            crate::models::HealthGoal::GainWeight => "gain_weight".to_string(),
-            crate::models::HealthGoal::BuildMuscle => "build_muscle".to_string(),
-        };
-        (
-            profile.daily_calories,
-            profile.daily_protein_g,
-            profile.daily_carbs_g,
-            profile.daily_fat_g,
-            goal,
-        )
-    } else {
-        (2000.0, 150.0, 250.0, 67.0, "maintain_weight".to_string())
-    };
+    let report_id = result.inserted_id
+        .as_object_id()
+        .ok_or_else(|| AppError::InternalError(anyhow::anyhow!("Report insert returned no ObjectId")))?;
+
+    tracing::info!("Queued {} report {} for user {}", query.report_type, report_id, claims.sub);
+
+    tokio::spawn(report_service::run_report_job(
+        state.clone(),
+        report_id,
+        query.send_email,
+        query.language.unwrap_or_else(|| "English".to_string()),
+    ));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ReportJobResponse {
+            success: true,
+            report_id: report_id.to_hex(),
+            status: ReportStatus::Pending,
+            status_url: format!("/api/reports/{}/status", report_id.to_hex()),
+            message: if query.send_email {
+                "Report is being generated and will be emailed when ready".to_string()
+            } else {
+                "Report is being generated".to_string()
+            },
+        }),
+    ))
+}
 
-    let calories_compliance = if target_calories > 0.0 {
-        (avg_calories / target_calories * 100.0).min(100.0)
-    } else {
-        0.0
-    };
-    let protein_compliance = if target_protein > 0.0 {
-        (avg_protein / target_protein * 100.0).min(100.0)
-    } else {
-        0.0
-    };
-    let carbs_compliance = if target_carbs > 0.0 {
-        (avg_carbs / target_carbs * 100.0).min(100.0)
-    } else {
-        0.0
-    };
-    let fat_compliance = if target_fat > 0.0 {
-        (avg_fat / target_fat * 100.0).min(100.0)
-    } else {
-        0.0
-    };
+/// Opts in to (or out of) a weekly or monthly report emailed after each period ends.
+#[utoipa::path(
+    post,
+    path = "/api/reports/schedule",
+    tag = "reports",
+    request_body = ScheduleReportRequest,
+    responses((status = 200, description = "Schedule saved", body = ReportScheduleResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn schedule_report(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ScheduleReportRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub)
+        .map_err(|_| AppError::BadRequest("Invalid user ID".to_string()))?;
+    payload.validate()?;
 
-    let days_on_target = days_with_meals.iter().filter(|date| {
-        let day_meals: Vec<&MealLog> = meals.iter()
-            .filter(|m| m.date.date_naive() == **date)
-            .collect();
-        let day_calories: f64 = day_meals.iter().map(|m| m.calories).sum();
-        let diff = (day_calories - target_calories).abs();
-        diff / target_calories <= 0.1
-    }).count();
-
-    let avg_compliance = (calories_compliance + protein_compliance + carbs_compliance + fat_compliance) / 4.0;
-    let goal_achieved = avg_compliance >= 80.0 && days_logged as f64 / total_days as f64 >= 0.7;
-
-    let mut best_day_date = None;
-    let mut best_day_compliance = 0.0;
-    for date in &days_with_meals {
-        let day_meals: Vec<&MealLog> = meals.iter()
-            .filter(|m| m.date.date_naive() == *date)
-            .collect();
-        let day_calories: f64 = day_meals.iter().map(|m| m.calories).sum();
-        let day_protein: f64 = day_meals.iter().map(|m| m.protein_g).sum();
-        let day_carbs: f64 = day_meals.iter().map(|m| m.carbs_g).sum();
-        let day_fat: f64 = day_meals.iter().map(|m| m.fat_g).sum();
-
-        let day_cal_comp = (day_calories / target_calories * 100.0).min(100.0);
-        let day_prot_comp = (day_protein / target_protein * 100.0).min(100.0);
-        let day_carb_comp = (day_carbs / target_carbs * 100.0).min(100.0);
-        let day_fat_comp = (day_fat / target_fat * 100.0).min(100.0);
-        let day_avg_comp = (day_cal_comp + day_prot_comp + day_carb_comp + day_fat_comp) / 4.0;
-
-        if day_avg_comp > best_day_compliance {
-            best_day_compliance = day_avg_comp;
-            best_day_date = Some(date.format("%Y-%m-%d").to_string());
-        }
-    }
+    let schedules = state.db.collection::<ReportSchedule>("report_schedules");
+    let filter = doc! { "user_id": user_id, "frequency": payload.frequency.as_str() };
+    let existing = schedules
+        .find_one(filter.clone(), None)
+        .await
+        .map_err(|e| AppError::InternalError(e.into()))?;
 
-    let mut streak = 0;
-    let mut current_streak = 0;
-    let mut last_date: Option<chrono::NaiveDate> = None;
-    let mut sorted_dates: Vec<_> = days_with_meals.iter().collect();
-    sorted_dates.sort();
-    
-    for date in sorted_dates {
-        if let Some(last) = last_date {
-            if (*date - last).num_days() == 1 {
-                current_streak += 1;
-            } else {
-                streak = streak.max(current_streak);
-                current_streak = 1;
+    let now = Utc::now();
+    let today = now.date_naive();
+    // Start from the period in progress; the one that already ended isn't sent retroactively.
+    let (_, last_period_end) = report_service::last_completed_period(payload.frequency, today);
+    let last_period_end = last_period_end.format("%Y-%m-%d").to_string();
+
+    let language = payload.language
+        .or_else(|| existing.as_ref().map(|s| s.language.clone()))
+        .unwrap_or_else(|| "English".to_string());
+
+    match existing {
+        Some(schedule) => {
+            let mut update = doc! {
+                "active": payload.enabled,
+                "language": &language,
+                "updated_at": now.to_rfc3339(),
+            };
+            if payload.enabled && !schedule.active {
+                update.insert("last_period_end", &last_period_end);
             }
-        } else {
-            current_streak = 1;
+            schedules
+                .update_one(filter, doc! { "$set": update }, None)
+                .await
+                .map_err(|e| AppError::InternalError(e.into()))?;
         }
-        last_date = Some(*date);
-    }
-    streak = streak.max(current_streak);
-
-    let (starting_weight, ending_weight, weight_change, target_weight, weight_goal_achieved) = 
-        if let Some(profile) = &user.health_profile {
-            let starting = Some(profile.weight_kg);
-            let target = match profile.goal {
-                crate::models::HealthGoal::LoseWeight => Some(profile.weight_kg * 0.9),
-                crate::models::HealthGoal::GainWeight => Some(profile.weight_kg * 1.1),
-                crate::models::HealthGoal::BuildMuscle => Some(profile.weight_kg * 1.05),
-                crate::models::HealthGoal::MaintainWeight => Some(profile.weight_kg),
+        None if payload.enabled => {
+            let schedule = ReportSchedule {
+                id: None,
+                user_id,
+                frequency: payload.frequency,
+                language: language.clone(),
+                active: true,
+                last_period_end: Some(last_period_end),
+                created_at: now,
+                updated_at: now,
             };
-            (starting, starting, Some(0.0), target, Some(false))
-        } else {
-            (None, None, None, None, None)
-        };
-
-    let report = MealReport {
-        id: None,
-        user_id,
-        report_type: report_type.clone(),
-        start_date: query.start_date.clone(),
-        end_date: query.end_date.clone(),
-        generated_at: Utc::now(),
-        status: if query.send_email { ReportStatus::Sent } else { ReportStatus::Generated },
-        total_days,
-        days_logged,
-        total_meals: meals.len(),
-        avg_calories,
-        avg_protein_g: avg_protein,
-        avg_carbs_g: avg_carbs,
-        avg_fat_g: avg_fat,
-        goal_type: goal_type.clone(),
-        goal_achieved,
-        calories_compliance_percent: calories_compliance,
-        protein_compliance_percent: protein_compliance,
-        carbs_compliance_percent: carbs_compliance,
-        fat_compliance_percent: fat_compliance,
-        days_on_target,
-        starting_weight,
-        ending_weight,
-        weight_change,
-        target_weight,
-        weight_goal_achieved,
-        best_day_date,
-        best_day_compliance: if best_day_compliance > 0.0 { Some(best_day_compliance) } else { None },
-        streak_days: streak,
-        notes: None,
-    };
-
-    let result = state.db
-        .collection::<MealReport>("meal_reports")
-        .insert_one(&report, None)
-        .await
-        .map_err(|e| AppError::InternalError(e.into()))?;
-
-    let mut saved_report = report.clone();
-    saved_report.id = Some(result.inserted_id.as_object_id().unwrap());
-
-    if query.send_email {
-        let email_service = EmailService::new(
-            state.config.brevo.smtp_host.clone(),
-            state.config.brevo.smtp_port,
-            state.config.brevo.smtp_user.clone(),
-            state.config.brevo.smtp_pass.clone(),
-            state.config.brevo.from_email.clone(),
-            state.config.brevo.from_name.clone(),
-        );
-
-        if let Err(e) = email_service.send_report_email(&user, &saved_report).await {
-            tracing::error!("Failed to send report email: {}", e);
-            state.db
-                .collection::<MealReport>("meal_reports")
-                .update_one(
-                    doc! { "_id": saved_report.id.unwrap() },
-                    doc! { "$set": { "status": "Failed" } },
-                    None,
-                )
+            schedules
+                .insert_one(&schedule, None)
                 .await
                 .map_err(|e| AppError::InternalError(e.into()))?;
         }
+        None => {
+            return Err(AppError::NotFound(format!("No {} report schedule to disable", payload.frequency.as_str())));
+        }
     }
 
-    Ok(Json(ReportResponse {
+    tracing::info!(
+        "{} {} report schedule for user {}",
+        if payload.enabled { "Enabled" } else { "Disabled" },
+        payload.frequency.as_str(),
+        claims.sub
+    );
+
+    Ok(Json(ReportScheduleResponse {
         success: true,
-        report: saved_report,
-        message: if query.send_email {
-            "Report generated and sent to your email".to_string()
+        frequency: payload.frequency,
+        active: payload.enabled,
+        language,
+        next_delivery_date: payload.enabled.then(|| {
+            report_service::next_delivery_date(payload.frequency, today)
+                .format("%Y-%m-%d")
+                .to_string()
+        }),
+        message: if payload.enabled {
+            format!("You'll receive a {} report by email", payload.frequency.as_str())
         } else {
-            "Report generated successfully".to_string()
+            format!("{} report emails turned off", payload.frequency.as_str())
         },
     }))
 }
 
-pub async fn get_user_reports(
+#[utoipa::path(
+    get,
+    path = "/api/reports/{id}/status",
+    tag = "reports",
+    params(("id" = String, Path, description = "Report ID")),
+    responses(
+        (status = 200, description = "Generation status", body = ReportStatusResponse),
+        (status = 404, description = "Report not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_report_status(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
-    Query(params): Query<std::collections::HashMap<String, String>>,
+    Path(report_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     let user_id = ObjectId::parse_str(&claims.sub)
         .map_err(|_| AppError::BadRequest("Invalid user ID".to_string()))?;
 
-    let limit = params
-        .get("limit")
-        .and_then(|s| s.parse::<i64>().ok())
-        .unwrap_or(50);
+    let report_obj_id = ObjectId::parse_str(&report_id)
+        .map_err(|_| AppError::BadRequest("Invalid report ID".to_string()))?;
 
-    let mut cursor = state.db
+    let report = state.db
         .collection::<MealReport>("meal_reports")
-        .find(doc! { "user_id": user_id }, None)
+        .find_one(doc! { "_id": report_obj_id, "user_id": user_id }, None)
         .await
-        .map_err(|e| AppError::InternalError(e.into()))?;
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Report not found".to_string()))?;
+
+    Ok(Json(ReportStatusResponse {
+        success: true,
+        report_id,
+        report_type: report.report_type,
+        status: report.status,
+        error: report.error,
+        generated_at: report.generated_at.to_rfc3339(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/reports",
+    tag = "reports",
+    params(ReportsListQuery),
+    responses((status = 200, description = "A page of reports, newest first", body = ReportsListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_user_reports(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ReportsListQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub)
+        .map_err(|_| AppError::BadRequest("Invalid user ID".to_string()))?;
+
+    let skip = query.skip.unwrap_or(0);
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+
+    let mut filter = doc! { "user_id": user_id };
+    if let Some(report_type) = &query.report_type {
+        let report_type = ReportPeriod::parse(report_type)
+            .ok_or_else(|| AppError::BadRequest("Invalid report type".to_string()))?;
+        let report_type = mongodb::bson::to_bson(&report_type)
+            .map_err(|e| AppError::InternalError(e.into()))?;
+        filter.insert("report_type", report_type);
+    }
 
-    let mut reports: Vec<MealReport> = Vec::new();
-    while let Some(report) = cursor.try_next().await.map_err(|e| AppError::InternalError(e.into()))? {
-        reports.push(report);
-        if reports.len() >= limit as usize {
-            break;
+    let parse_date = |value: &Option<String>, name: &str| -> Result<Option<chrono::NaiveDate>, AppError> {
+        value
+            .as_deref()
+            .map(|v| chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                .map_err(|_| AppError::BadRequest(format!("Invalid {} format", name))))
+            .transpose()
+    };
+    let start_date = parse_date(&query.start_date, "start_date")?;
+    let end_date = parse_date(&query.end_date, "end_date")?;
+    if let (Some(start), Some(end)) = (start_date, end_date) {
+        if start > end {
+            return Err(AppError::BadRequest("start_date must be before end_date".to_string()));
         }
     }
+    // Period dates are stored as YYYY-MM-DD, so string order is date order.
+    if let Some(start) = start_date {
+        filter.insert("end_date", doc! { "$gte": start.format("%Y-%m-%d").to_string() });
+    }
+    if let Some(end) = end_date {
+        filter.insert("start_date", doc! { "$lte": end.format("%Y-%m-%d").to_string() });
+    }
+
+    let collection = state.db.collection::<MealReport>("meal_reports");
+
+    let total = collection
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|e| AppError::InternalError(e.into()))?;
 
-    reports.sort_by(|a, b| b.generated_at.cmp(&a.generated_at));
+    let reports: Vec<MealReport> = collection
+        .find(
+            filter,
+            mongodb::options::FindOptions::builder()
+                .sort(doc! { "generated_at": -1, "_id": -1 })
+                .skip(skip)
+                .limit(limit)
+                .build(),
+        )
+        .await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect()
+        .await
+        .map_err(|e| AppError::InternalError(e.into()))?;
 
     Ok(Json(ReportsListResponse {
         success: true,
-        total: reports.len(),
+        has_more: skip + (reports.len() as u64) < total,
         reports,
+        total,
+        skip,
+        limit,
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/reports/{id}",
+    tag = "reports",
+    params(("id" = String, Path, description = "Report ID")),
+    responses(
+        (status = 200, description = "The report", body = ReportResponse),
+        (status = 404, description = "Report not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn get_report_by_id(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -394,6 +468,17 @@ pub async fn get_report_by_id(
     }))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/reports/{id}",
+    tag = "reports",
+    params(("id" = String, Path, description = "Report ID")),
+    responses(
+        (status = 200, description = "Report deleted"),
+        (status = 404, description = "Report not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
 pub async fn delete_report(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
@@ -415,6 +500,8 @@ pub async fn delete_report(
         return Err(AppError::NotFound("Report not found".to_string()));
     }
 
+    response_cache_service::invalidate(&state, user_id, CachedRoute::Reports).await;
+
     Ok(Json(serde_json::json!({
         "success": true,
         "message": "Report deleted successfully"
@@ -0,0 +1,191 @@
+use axum::{ extract::{ Path, State }, http::StatusCode, response::{ IntoResponse, Json }, Extension };
+use chrono::{ NaiveDate, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, ExportStatus, ResearchCohort, ResearchExport },
+    services::{ audit_service, research_service },
+};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateResearchExportRequest {
+    pub start_date: String,
+    pub end_date: String,
+    pub k_threshold: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResearchExportDto {
+    pub id: String,
+    pub status: ExportStatus,
+    pub start_date: String,
+    pub end_date: String,
+    pub k_threshold: i32,
+    pub cohorts: Vec<ResearchCohort>,
+    pub included_users: i32,
+    pub suppressed_users: i32,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+impl From<ResearchExport> for ResearchExportDto {
+    fn from(export: ResearchExport) -> Self {
+        Self {
+            id: export.id.map(|id| id.to_hex()).unwrap_or_default(),
+            status: export.status,
+            start_date: export.start_date,
+            end_date: export.end_date,
+            k_threshold: export.k_threshold,
+            cohorts: export.cohorts,
+            included_users: export.included_users,
+            suppressed_users: export.suppressed_users,
+            error: export.error,
+            created_at: export.created_at.to_rfc3339(),
+            completed_at: export.completed_at.map(|d| d.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResearchExportResponse {
+    pub success: bool,
+    pub export: ResearchExportDto,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/research-exports",
+    tag = "research",
+    request_body = CreateResearchExportRequest,
+    responses((status = 202, description = "Anonymized cohort export queued", body = ResearchExportResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn create_research_export(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateResearchExportRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let requester_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let start = NaiveDate::parse_from_str(&payload.start_date, "%Y-%m-%d").map_err(|_|
+        AppError::BadRequest("Invalid start_date format. Use YYYY-MM-DD".to_string())
+    )?;
+    let end = NaiveDate::parse_from_str(&payload.end_date, "%Y-%m-%d").map_err(|_|
+        AppError::BadRequest("Invalid end_date format. Use YYYY-MM-DD".to_string())
+    )?;
+
+    if start > end {
+        return Err(AppError::BadRequest("start_date must be on or before end_date".to_string()));
+    }
+
+    let k_threshold = payload.k_threshold.unwrap_or(research_service::DEFAULT_K_THRESHOLD);
+    if k_threshold < research_service::MIN_K_THRESHOLD {
+        return Err(
+            AppError::BadRequest(
+                format!("k_threshold must be at least {}", research_service::MIN_K_THRESHOLD)
+            )
+        );
+    }
+
+    let mut export = ResearchExport {
+        id: None,
+        requested_by: requester_id,
+        status: ExportStatus::Pending,
+        start_date: start.to_string(),
+        end_date: end.to_string(),
+        k_threshold,
+        cohorts: Vec::new(),
+        included_users: 0,
+        suppressed_users: 0,
+        error: None,
+        created_at: Utc::now(),
+        completed_at: None,
+    };
+
+    let result = state.db
+        .collection::<ResearchExport>("research_exports")
+        .insert_one(&export, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let export_id = result.inserted_id.as_object_id().unwrap();
+    export.id = Some(export_id);
+
+    audit_service::record(
+        &state,
+        requester_id,
+        "research_export.requested",
+        Some(export_id),
+        doc! {
+            "start_date": &export.start_date,
+            "end_date": &export.end_date,
+            "k_threshold": k_threshold,
+        }
+    ).await;
+
+    tracing::info!("Queued research export {} for {}", export_id, claims.sub);
+
+    tokio::spawn(research_service::run_export(state.clone(), export_id));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ResearchExportResponse {
+            success: true,
+            export: export.into(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/research-exports/{id}",
+    tag = "research",
+    params(("id" = String, Path, description = "Research export ID")),
+    responses(
+        (status = 200, description = "The export, with cohorts once completed", body = ResearchExportResponse),
+        (status = 404, description = "Research export not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_research_export(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(export_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let requester_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let export_oid = ObjectId::parse_str(&export_id).map_err(|_|
+        AppError::BadRequest("Invalid export ID".to_string())
+    )?;
+
+    let export = state.db
+        .collection::<ResearchExport>("research_exports")
+        .find_one(doc! { "_id": export_oid }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Research export not found".to_string()))?;
+
+    if export.status == ExportStatus::Completed {
+        audit_service::record(
+            &state,
+            requester_id,
+            "research_export.downloaded",
+            Some(export_oid),
+            doc! { "cohorts": export.cohorts.len() as i64 }
+        ).await;
+    }
+
+    Ok(
+        Json(ResearchExportResponse {
+            success: true,
+            export: export.into(),
+        })
+    )
+}
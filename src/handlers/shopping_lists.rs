@@ -0,0 +1,329 @@
+use axum::{ extract::{ Path, State }, http::StatusCode, response::{ IntoResponse, Json }, Extension };
+use chrono::Utc;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::ToSchema;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, ItemQuantity, ShoppingList, ShoppingListItem },
+    services::{ meal_plan_service, shopping_list_service::{ self, MAX_RECIPES } },
+};
+
+/// Lists returned by the list endpoint.
+const LIST_LIMIT: i64 = 20;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateShoppingListRequest {
+    pub name: Option<String>,
+    /// Build from this meal plan's uneaten meals...
+    pub meal_plan_id: Option<String>,
+    /// ...from this day on (`YYYY-MM-DD`), when given.
+    pub from_date: Option<String>,
+    /// ...or from these TheMealDB recipes.
+    pub recipe_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateShoppingListItemRequest {
+    pub checked: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShoppingListItemDto {
+    pub index: usize,
+    pub name: String,
+    pub quantities: Vec<ItemQuantity>,
+    pub notes: Vec<String>,
+    pub used_in: Vec<String>,
+    pub checked: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShoppingListDto {
+    pub id: String,
+    pub name: String,
+    pub meal_plan_id: Option<String>,
+    pub recipe_ids: Vec<String>,
+    pub items: Vec<ShoppingListItemDto>,
+    pub checked_count: usize,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<ShoppingList> for ShoppingListDto {
+    fn from(list: ShoppingList) -> Self {
+        Self {
+            id: list.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: list.name,
+            meal_plan_id: list.meal_plan_id.map(|id| id.to_hex()),
+            recipe_ids: list.recipe_ids,
+            checked_count: list.items
+                .iter()
+                .filter(|item| item.checked)
+                .count(),
+            items: list.items
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| ShoppingListItemDto::from_item(index, item))
+                .collect(),
+            created_at: list.created_at.to_rfc3339(),
+            updated_at: list.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+impl ShoppingListItemDto {
+    fn from_item(index: usize, item: ShoppingListItem) -> Self {
+        Self {
+            index,
+            name: item.name,
+            quantities: item.quantities,
+            notes: item.notes,
+            used_in: item.used_in,
+            checked: item.checked,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShoppingListResponse {
+    pub success: bool,
+    pub list: ShoppingListDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShoppingListsListResponse {
+    pub success: bool,
+    pub lists: Vec<ShoppingListDto>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/shopping-lists",
+    tag = "shopping_lists",
+    request_body = CreateShoppingListRequest,
+    responses((status = 201, description = "Shopping list built", body = ShoppingListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn create_shopping_list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateShoppingListRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let (meal_plan_id, recipe_ids, items, default_name) = match (payload.meal_plan_id, payload.recipe_ids) {
+        (Some(plan_id), None) => {
+            let plan_oid = ObjectId::parse_str(&plan_id).map_err(|_|
+                AppError::BadRequest("Invalid meal plan ID".to_string())
+            )?;
+            let plan = meal_plan_service::find_plan(&state, user_id, plan_oid).await?;
+            let items = shopping_list_service::items_from_meal_plan(&plan, payload.from_date.as_deref())?;
+            (Some(plan_oid), Vec::new(), items, format!("Meal plan from {}", plan.start_date))
+        }
+        (None, Some(recipe_ids)) => {
+            let mut unique: Vec<String> = Vec::new();
+            for id in recipe_ids.iter().map(|id| id.trim()).filter(|id| !id.is_empty()) {
+                if !unique.iter().any(|u| u == id) {
+                    unique.push(id.to_string());
+                }
+            }
+            if unique.is_empty() || unique.len() > MAX_RECIPES {
+                return Err(AppError::BadRequest(format!("recipe_ids must list 1 to {} recipes", MAX_RECIPES)));
+            }
+            let items = shopping_list_service::items_from_recipes(&state, &unique).await?;
+            let name = format!("{} recipe{}", unique.len(), if unique.len() == 1 { "" } else { "s" });
+            (None, unique, items, name)
+        }
+        _ => {
+            return Err(AppError::BadRequest("Provide either meal_plan_id or recipe_ids".to_string()));
+        }
+    };
+
+    if items.is_empty() {
+        return Err(AppError::ValidationError("There are no ingredients to shop for".to_string()));
+    }
+
+    let name = payload.name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(default_name);
+    if name.chars().count() > 120 {
+        return Err(AppError::BadRequest("name must be at most 120 characters".to_string()));
+    }
+
+    let now = Utc::now();
+    let mut list = ShoppingList {
+        id: None,
+        user_id,
+        name,
+        meal_plan_id,
+        recipe_ids,
+        items,
+        created_at: now,
+        updated_at: now,
+    };
+
+    let result = state.db
+        .collection::<ShoppingList>("shopping_lists")
+        .insert_one(&list, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    list.id = result.inserted_id.as_object_id();
+
+    tracing::info!("Created shopping list {:?} with {} items for user {}", list.id, list.items.len(), claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ShoppingListResponse {
+            success: true,
+            list: list.into(),
+        }),
+    ))
+}
+
+/// The 20 most recent lists, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/shopping-lists",
+    tag = "shopping_lists",
+    responses((status = 200, description = "The 20 most recent lists", body = ShoppingListsListResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_shopping_lists(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let lists = shopping_list_service::list_lists(&state, user_id, LIST_LIMIT).await?;
+
+    Ok(
+        Json(ShoppingListsListResponse {
+            success: true,
+            lists: lists.into_iter().map(Into::into).collect(),
+        })
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/shopping-lists/{id}",
+    tag = "shopping_lists",
+    params(("id" = String, Path, description = "Shopping list ID")),
+    responses(
+        (status = 200, description = "The shopping list", body = ShoppingListResponse),
+        (status = 404, description = "Shopping list not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_shopping_list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(list_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let list_oid = ObjectId::parse_str(&list_id).map_err(|_|
+        AppError::BadRequest("Invalid shopping list ID".to_string())
+    )?;
+
+    let list = shopping_list_service::find_list(&state, user_id, list_oid).await?;
+
+    Ok(
+        Json(ShoppingListResponse {
+            success: true,
+            list: list.into(),
+        })
+    )
+}
+
+/// Checks an item off, or back on.
+#[utoipa::path(
+    put,
+    path = "/api/shopping-lists/{id}/items/{index}",
+    tag = "shopping_lists",
+    params(
+        ("id" = String, Path, description = "Shopping list ID"),
+        ("index" = usize, Path, description = "Item index")
+    ),
+    request_body = UpdateShoppingListItemRequest,
+    responses(
+        (status = 200, description = "Item updated", body = ShoppingListResponse),
+        (status = 404, description = "Shopping list or item not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn update_shopping_list_item(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((list_id, index)): Path<(String, usize)>,
+    Json(payload): Json<UpdateShoppingListItemRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let list_oid = ObjectId::parse_str(&list_id).map_err(|_|
+        AppError::BadRequest("Invalid shopping list ID".to_string())
+    )?;
+
+    let list = shopping_list_service::set_checked(&state, user_id, list_oid, index, payload.checked).await?;
+
+    Ok(
+        Json(ShoppingListResponse {
+            success: true,
+            list: list.into(),
+        })
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/shopping-lists/{id}",
+    tag = "shopping_lists",
+    params(("id" = String, Path, description = "Shopping list ID")),
+    responses(
+        (status = 200, description = "Shopping list deleted"),
+        (status = 404, description = "Shopping list not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_shopping_list(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(list_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let list_oid = ObjectId::parse_str(&list_id).map_err(|_|
+        AppError::BadRequest("Invalid shopping list ID".to_string())
+    )?;
+
+    let result = state.db
+        .collection::<ShoppingList>("shopping_lists")
+        .delete_one(doc! { "_id": list_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("Shopping list not found".to_string()));
+    }
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Shopping list deleted"
+    })
+        )
+    )
+}
@@ -1,17 +1,84 @@
-use axum::{ http::StatusCode, Json };
+use axum::{ extract::State, http::StatusCode, Json };
+use serde::Serialize;
 use serde_json::{ json, Value };
+use utoipa::ToSchema;
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    services::{
+        provider_metrics_service::{ self, Provider, ProviderState, ProviderStatus },
+        redis_health_service,
+    },
+};
+
+/// Minutes of calls `/status/providers` judges each provider by.
+const PROVIDER_WINDOW_MINUTES: i64 = 15;
+
+/// Reports `degraded` while Redis is unreachable; features then follow their
+/// `REDIS_*_POLICY`.
+#[utoipa::path(
+    get,
+    path = "/status",
+    tag = "status",
+    responses((status = 200, description = "Service health, including Redis reachability")),
+    security(())
+)]
+pub async fn status_check(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let redis = redis_health_service::check(&state.redis, &state.config).await;
 
-pub async fn status_check() -> (StatusCode, Json<Value>) {
     (
         StatusCode::OK,
         Json(
             json!({
-            "status": "healthy",
+            "status": if redis.available { "healthy" } else { "degraded" },
             "service": "Alimentify API",
             "version": env!("CARGO_PKG_VERSION"),
             "timestamp": chrono::Utc::now().to_rfc3339(),
             "environment": std::env::var("NODE_ENV").unwrap_or_else(|_| "development".to_string()),
+            "redis": redis,
         })
         ),
     )
 }
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProviderStatusResponse {
+    pub success: bool,
+    pub window_minutes: i64,
+    #[schema(value_type = Vec<Object>)]
+    pub providers: Vec<ProviderStatus>,
+    pub generated_at: String,
+}
+
+/// Public: how the external services behind AI analysis, food lookups,
+/// recipes and email have been doing lately, from our own calls to them.
+#[utoipa::path(
+    get,
+    path = "/status/providers",
+    tag = "status",
+    responses((status = 200, description = "Recent health of each external provider", body = ProviderStatusResponse)),
+    security(())
+)]
+pub async fn provider_status(State(state): State<AppState>) -> Result<Json<ProviderStatusResponse>, AppError> {
+    let mut providers = provider_metrics_service
+        ::provider_stats(&state.redis, PROVIDER_WINDOW_MINUTES).await
+        .map_err(AppError::InternalError)?;
+
+    // Calls fail fast without reaching Gemini while its breaker is open, so
+    // they never show up in the numbers.
+    if state.gemini_service.is_paused() {
+        if let Some(gemini) = providers.iter_mut().find(|status| status.provider == Provider::Gemini) {
+            gemini.status = ProviderState::Down;
+        }
+    }
+
+    Ok(
+        Json(ProviderStatusResponse {
+            success: true,
+            window_minutes: PROVIDER_WINDOW_MINUTES,
+            providers,
+            generated_at: chrono::Utc::now().to_rfc3339(),
+        })
+    )
+}
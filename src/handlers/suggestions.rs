@@ -0,0 +1,39 @@
+use axum::{ extract::State, response::{ IntoResponse, Json }, Extension };
+use mongodb::bson::oid::ObjectId;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{ db::AppState, error::AppError, models::Claims, services::suggestion_service };
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SnackSuggestionsResponse {
+    pub success: bool,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub suggestions: suggestion_service::SnackSuggestions,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/suggestions/snack",
+    tag = "suggestions",
+    responses((status = 200, description = "Snacks that fit the rest of today's targets", body = SnackSuggestionsResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_snack_suggestions(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let suggestions = suggestion_service::suggest_snacks(&state, user_id).await?;
+
+    Ok(
+        Json(SnackSuggestionsResponse {
+            success: true,
+            suggestions,
+        })
+    )
+}
@@ -0,0 +1,121 @@
+use axum::{ extract::{ Query, State }, response::{ IntoResponse, Json }, Extension };
+use chrono::{ NaiveDate, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+
+use crate::{ db::AppState, error::AppError, models::{ Claims, User }, services::water_service };
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogWaterRequest {
+    pub amount_ml: f64,
+    /// `YYYY-MM-DD`; defaults to today (UTC).
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct WaterDailyQuery {
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WaterDailyResponse {
+    pub success: bool,
+    pub date: String,
+    pub water_ml: f64,
+    pub target_ml: f64,
+    pub remaining_ml: f64,
+}
+
+fn parse_day(date: Option<&str>) -> Result<NaiveDate, AppError> {
+    match date {
+        Some(date) =>
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid date format. Use YYYY-MM-DD".to_string())
+            ),
+        None => Ok(Utc::now().date_naive()),
+    }
+}
+
+async fn daily_response(
+    state: &AppState,
+    user_id: ObjectId,
+    day: NaiveDate,
+    water_ml: f64
+) -> Result<WaterDailyResponse, AppError> {
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let target_ml = water_service::water_target_ml(&user);
+
+    Ok(WaterDailyResponse {
+        success: true,
+        date: day.format("%Y-%m-%d").to_string(),
+        water_ml,
+        target_ml,
+        remaining_ml: (target_ml - water_ml).max(0.0),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/water/log",
+    tag = "water",
+    request_body = LogWaterRequest,
+    responses((status = 200, description = "Water logged; the day's new total", body = WaterDailyResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_water(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<LogWaterRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if payload.amount_ml <= 0.0 || payload.amount_ml > water_service::MAX_WATER_LOG_ML {
+        return Err(
+            AppError::BadRequest(
+                format!("amount_ml must be between 0 and {}", water_service::MAX_WATER_LOG_ML)
+            )
+        );
+    }
+
+    let day = parse_day(payload.date.as_deref())?;
+    if day > Utc::now().date_naive() {
+        return Err(AppError::BadRequest("Cannot log water for a future date".to_string()));
+    }
+
+    let water_ml = water_service::log_water(&state, user_id, day, payload.amount_ml).await?;
+
+    tracing::info!("Logged {} ml of water for user {} on {}", payload.amount_ml, claims.sub, day);
+
+    Ok(Json(daily_response(&state, user_id, day, water_ml).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/water/daily",
+    tag = "water",
+    params(WaterDailyQuery),
+    responses((status = 200, description = "Water drunk on the day against the target", body = WaterDailyResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_daily_water(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<WaterDailyQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let day = parse_day(query.date.as_deref())?;
+    let water_ml = water_service::water_for_day(&state, user_id, day).await?;
+
+    Ok(Json(daily_response(&state, user_id, day, water_ml).await?))
+}
@@ -0,0 +1,260 @@
+use axum::{
+    extract::{ Path, Query, State },
+    http::StatusCode,
+    response::{ IntoResponse, Json },
+    Extension,
+};
+use chrono::{ Duration, NaiveDate, TimeZone, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::{ Claims, WeightLog },
+    services::weight_service::{ self, WeightTrend },
+};
+
+const MIN_WEIGHT_KG: f64 = 20.0;
+const MAX_WEIGHT_KG: f64 = 500.0;
+const DEFAULT_HISTORY_DAYS: i64 = 90;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogWeightRequest {
+    pub weight_kg: f64,
+    /// `YYYY-MM-DD`; defaults to now. Past dates are recorded at noon UTC.
+    pub date: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct WeightHistoryQuery {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WeightLogDto {
+    pub id: String,
+    pub weight_kg: f64,
+    pub date: String,
+    pub notes: Option<String>,
+    /// Trailing 7-day mean as of this weigh-in.
+    pub moving_average_kg: f64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WeightLogResponse {
+    pub success: bool,
+    pub entry: WeightLogDto,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WeightHistoryResponse {
+    pub success: bool,
+    pub start_date: String,
+    pub end_date: String,
+    pub entries: Vec<WeightLogDto>,
+    #[schema(value_type = Option<Object>)]
+    pub trend: Option<WeightTrend>,
+}
+
+pub fn to_dto(log: &WeightLog, history: &[WeightLog]) -> WeightLogDto {
+    WeightLogDto {
+        id: log.id.map(|id| id.to_hex()).unwrap_or_default(),
+        weight_kg: log.weight_kg,
+        date: log.date.to_rfc3339(),
+        notes: log.notes.clone(),
+        moving_average_kg: weight_service::moving_average(history, log.date),
+        created_at: log.created_at.to_rfc3339(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/weight",
+    tag = "weight",
+    request_body = LogWeightRequest,
+    responses((status = 201, description = "Weigh-in logged", body = WeightLogResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn log_weight(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<LogWeightRequest>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if !(MIN_WEIGHT_KG..=MAX_WEIGHT_KG).contains(&payload.weight_kg) {
+        return Err(
+            AppError::BadRequest(
+                format!("weight_kg must be between {} and {}", MIN_WEIGHT_KG, MAX_WEIGHT_KG)
+            )
+        );
+    }
+
+    let now = Utc::now();
+    let date = match payload.date.as_deref() {
+        Some(date) => {
+            let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid date format. Use YYYY-MM-DD".to_string())
+            )?;
+            if day > now.date_naive() {
+                return Err(AppError::BadRequest("Cannot log a weigh-in in the future".to_string()));
+            }
+            if day == now.date_naive() {
+                now
+            } else {
+                Utc.from_utc_datetime(&day.and_hms_opt(12, 0, 0).unwrap())
+            }
+        }
+        None => now,
+    };
+
+    let mut weight_log = WeightLog {
+        id: None,
+        user_id,
+        weight_kg: payload.weight_kg,
+        date,
+        notes: payload.notes,
+        created_at: now,
+    };
+
+    let result = state.db
+        .collection::<WeightLog>("weight_logs")
+        .insert_one(&weight_log, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    weight_log.id = result.inserted_id.as_object_id();
+
+    let history = weight_service::fetch_weight_logs_between(
+        &state,
+        user_id,
+        date - Duration::days(7),
+        date
+    ).await?;
+
+    tracing::info!("Logged weigh-in {:?} for user {}", weight_log.id, claims.sub);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WeightLogResponse {
+            success: true,
+            entry: to_dto(&weight_log, &history),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/weight",
+    tag = "weight",
+    params(WeightHistoryQuery),
+    responses((status = 200, description = "Weigh-ins in the range, the last 90 days by default", body = WeightHistoryResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn get_weight_history(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<WeightHistoryQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let today = Utc::now().date_naive();
+    let end_date = match query.end_date.as_deref() {
+        Some(date) =>
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid end_date format. Use YYYY-MM-DD".to_string())
+            )?,
+        None => today,
+    };
+    let start_date = match query.start_date.as_deref() {
+        Some(date) =>
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+                AppError::BadRequest("Invalid start_date format. Use YYYY-MM-DD".to_string())
+            )?,
+        None => end_date - Duration::days(DEFAULT_HISTORY_DAYS - 1),
+    };
+
+    if start_date > end_date {
+        return Err(AppError::BadRequest("start_date must be before end_date".to_string()));
+    }
+
+    let start = Utc.from_utc_datetime(&start_date.and_hms_opt(0, 0, 0).unwrap());
+    let end = Utc.from_utc_datetime(&end_date.and_hms_opt(23, 59, 59).unwrap());
+
+    // Pull the week before the range too so the first moving averages are full.
+    let history = weight_service::fetch_weight_logs_between(
+        &state,
+        user_id,
+        start - Duration::days(7),
+        end
+    ).await?;
+    let in_range: Vec<WeightLog> = history
+        .iter()
+        .filter(|log| log.date >= start)
+        .cloned()
+        .collect();
+
+    Ok(
+        Json(WeightHistoryResponse {
+            success: true,
+            start_date: start_date.format("%Y-%m-%d").to_string(),
+            end_date: end_date.format("%Y-%m-%d").to_string(),
+            entries: in_range
+                .iter()
+                .map(|log| to_dto(log, &history))
+                .collect(),
+            trend: weight_service::trend(&in_range),
+        })
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/weight/{id}",
+    tag = "weight",
+    params(("id" = String, Path, description = "Weigh-in ID")),
+    responses(
+        (status = 200, description = "Weigh-in deleted"),
+        (status = 404, description = "Weigh-in not found")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn delete_weight_log(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(entry_id): Path<String>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let entry_oid = ObjectId::parse_str(&entry_id).map_err(|_|
+        AppError::BadRequest("Invalid weight entry ID".to_string())
+    )?;
+
+    let result = state.db
+        .collection::<WeightLog>("weight_logs")
+        .delete_one(doc! { "_id": entry_oid, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("Weight entry not found".to_string()));
+    }
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Weight entry deleted"
+    })
+        )
+    )
+}
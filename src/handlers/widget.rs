@@ -0,0 +1,151 @@
+use axum::{
+    extract::{ Query, State },
+    http::{ header, StatusCode },
+    response::{ IntoResponse, Json },
+    Extension,
+};
+use chrono::{ DateTime, Utc };
+use mongodb::bson::oid::ObjectId;
+use serde::{ Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
+
+use crate::{
+    db::AppState,
+    error::AppError,
+    models::Claims,
+    services::widget_service::{ self, WidgetSummary },
+};
+
+/// Embeds are rendered on every page view of the user's site; a few minutes
+/// of staleness is fine for a badge.
+const WIDGET_CACHE_CONTROL: &str = "public, max-age=300";
+
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct CreateWidgetTokenRequest {
+    /// Defaults to a year.
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WidgetTokenResponse {
+    pub success: bool,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    /// Path the embed calls, with the token already in the query.
+    pub summary_url: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct WidgetSummaryQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WidgetSummaryResponse {
+    pub success: bool,
+    #[schema(value_type = Object)]
+    pub summary: WidgetSummary,
+}
+
+/// Issues a widget token, revoking any earlier one.
+#[utoipa::path(
+    post,
+    path = "/api/widget/token",
+    tag = "widget",
+    request_body(content = Option<CreateWidgetTokenRequest>, description = "Optional; the token lasts a year without it"),
+    responses((status = 201, description = "Token issued", body = WidgetTokenResponse)),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn create_widget_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    payload: Option<Json<CreateWidgetTokenRequest>>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let days = payload.expires_in_days.unwrap_or(widget_service::DEFAULT_WIDGET_TOKEN_DAYS);
+    if !(1..=widget_service::MAX_WIDGET_TOKEN_DAYS).contains(&days) {
+        return Err(
+            AppError::BadRequest(
+                format!("expires_in_days must be between 1 and {}", widget_service::MAX_WIDGET_TOKEN_DAYS)
+            )
+        );
+    }
+
+    let (token, expires_at) = widget_service::issue_token(&state, user_id, days).await?;
+
+    tracing::info!("Issued widget token for user {} (expires {})", claims.sub, expires_at);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WidgetTokenResponse {
+            success: true,
+            summary_url: format!("/api/widget/summary?token={}", token),
+            token,
+            expires_at,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/widget/token",
+    tag = "widget",
+    responses(
+        (status = 200, description = "Token revoked"),
+        (status = 404, description = "No token to revoke")
+    ),
+    security(("api_key" = [], "bearer_auth" = []))
+)]
+pub async fn revoke_widget_token(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_|
+        AppError::BadRequest("Invalid user ID".to_string())
+    )?;
+
+    if !widget_service::revoke_token(&state, user_id).await? {
+        return Err(AppError::NotFound("No widget token to revoke".to_string()));
+    }
+
+    tracing::info!("Revoked widget token for user {}", claims.sub);
+
+    Ok(
+        Json(
+            serde_json::json!({
+        "success": true,
+        "message": "Widget token revoked"
+    })
+        )
+    )
+}
+
+/// Public: authorized only by the widget token, which reads this and nothing else.
+#[utoipa::path(
+    get,
+    path = "/api/widget/summary",
+    tag = "widget",
+    params(WidgetSummaryQuery),
+    responses((status = 200, description = "Today's summary for the embed", body = WidgetSummaryResponse)),
+    security(())
+)]
+pub async fn get_widget_summary(
+    State(state): State<AppState>,
+    Query(query): Query<WidgetSummaryQuery>
+) -> Result<impl IntoResponse, AppError> {
+    let user = widget_service::verify_token(&state, &query.token).await?;
+    let summary = widget_service::widget_summary(&state, &user).await?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, WIDGET_CACHE_CONTROL)],
+        Json(WidgetSummaryResponse {
+            success: true,
+            summary,
+        }),
+    ))
+}
@@ -0,0 +1,342 @@
+//! Localized error messages. Each message has a stable `code` that clients
+//! can match on; the text comes from the catalog in the request's language.
+
+use axum::http::HeaderMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Id,
+}
+
+impl Locale {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Id => "id",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next()?.trim().to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(Locale::En),
+            "id" | "in" => Some(Locale::Id),
+            _ => None,
+        }
+    }
+
+    /// The supported language the client prefers most, by `Accept-Language`
+    /// q-values; English when none is supported.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get("accept-language").and_then(|v| v.to_str().ok()) else {
+            return Locale::En;
+        };
+
+        accept
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let locale = Locale::from_tag(parts.next()?)?;
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                Some((locale, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .fold(None, |best: Option<(Locale, f32)>, (locale, quality)| {
+                match best {
+                    Some((_, best_quality)) if best_quality >= quality => best,
+                    _ => Some((locale, quality)),
+                }
+            })
+            .map_or(Locale::En, |(locale, _)| locale)
+    }
+}
+
+tokio::task_local! {
+    static LOCALE: Locale;
+}
+
+/// Runs `future` with `locale` as the request's language.
+pub async fn with_locale<F: std::future::Future>(locale: Locale, future: F) -> F::Output {
+    LOCALE.scope(locale, future).await
+}
+
+/// The current request's language; English outside a request.
+pub fn current() -> Locale {
+    LOCALE.try_with(|locale| *locale).unwrap_or_default()
+}
+
+/// `(code, English, Indonesian)`. The English text is also what handlers
+/// pass to `AppError`, which is how their messages are found here.
+const CATALOG: &[(&str, &str, &str)] = &[
+    // Generic messages, one per error kind.
+    ("not_found", "Not found", "Tidak ditemukan"),
+    ("bad_request", "Bad request", "Permintaan tidak valid"),
+    ("validation_error", "Validation failed", "Validasi gagal"),
+    ("conflict", "Conflict", "Terjadi konflik"),
+    ("internal_error", "Internal server error", "Terjadi kesalahan pada server"),
+    ("external_api_unavailable", "External service unavailable", "Layanan eksternal sedang tidak tersedia"),
+    (
+        "ai_refusal",
+        "I can't help with that request. Please try rephrasing it or ask about food, meals, or nutrition.",
+        "Saya tidak dapat membantu permintaan itu. Coba ubah kalimatnya atau tanyakan tentang makanan, menu, atau gizi.",
+    ),
+    (
+        "ai_unavailable",
+        "AI is temporarily unavailable. Please try again in a few minutes.",
+        "AI sedang tidak tersedia. Silakan coba lagi dalam beberapa menit.",
+    ),
+    (
+        "ai_malformed_response",
+        "The AI returned an unreadable answer. Please try again.",
+        "AI memberikan jawaban yang tidak dapat dibaca. Silakan coba lagi.",
+    ),
+    (
+        "ai_recitation",
+        "I couldn't put together an original answer for that. Please try rephrasing your request.",
+        "Saya tidak dapat menyusun jawaban orisinal untuk itu. Coba ubah kalimat permintaan Anda.",
+    ),
+    // IDs and lookups.
+    ("invalid_user_id", "Invalid user ID", "ID pengguna tidak valid"),
+    ("user_not_found", "User not found", "Pengguna tidak ditemukan"),
+    ("user_has_no_id", "User has no ID", "Pengguna tidak memiliki ID"),
+    ("account_not_found", "Account not found", "Akun tidak ditemukan"),
+    ("invalid_session_id", "Invalid session ID", "ID sesi tidak valid"),
+    ("chat_session_not_found", "Chat session not found", "Sesi obrolan tidak ditemukan"),
+    ("message_not_found", "Message not found", "Pesan tidak ditemukan"),
+    ("invalid_report_id", "Invalid report ID", "ID laporan tidak valid"),
+    ("report_not_found", "Report not found", "Laporan tidak ditemukan"),
+    ("invalid_report_type", "Invalid report type", "Jenis laporan tidak valid"),
+    ("invalid_meal_id", "Invalid meal ID", "ID makanan tidak valid"),
+    ("meal_not_found", "Meal not found", "Makanan tidak ditemukan"),
+    ("auto_logged_meal_not_found", "Auto-logged meal not found", "Makanan yang dicatat otomatis tidak ditemukan"),
+    ("recurring_meal_not_found", "Recurring meal not found", "Makanan berulang tidak ditemukan"),
+    ("invalid_meal_plan_id", "Invalid meal plan ID", "ID rencana makan tidak valid"),
+    ("meal_plan_not_found", "Meal plan not found", "Rencana makan tidak ditemukan"),
+    ("planned_meal_not_found", "Planned meal not found", "Menu dalam rencana tidak ditemukan"),
+    ("invalid_custom_food_id", "Invalid custom food ID", "ID makanan kustom tidak valid"),
+    ("custom_food_not_found", "Custom food not found", "Makanan kustom tidak ditemukan"),
+    ("invalid_favorite_id", "Invalid favorite ID", "ID favorit tidak valid"),
+    ("favorite_food_not_found", "Favorite food not found", "Makanan favorit tidak ditemukan"),
+    ("invalid_shopping_list_id", "Invalid shopping list ID", "ID daftar belanja tidak valid"),
+    ("shopping_list_not_found", "Shopping list not found", "Daftar belanja tidak ditemukan"),
+    ("shopping_list_item_not_found", "Shopping list item not found", "Barang di daftar belanja tidak ditemukan"),
+    ("recipe_not_found", "Recipe not found", "Resep tidak ditemukan"),
+    ("weight_entry_not_found", "Weight entry not found", "Catatan berat badan tidak ditemukan"),
+    ("exercise_session_not_found", "Exercise session not found", "Sesi olahraga tidak ditemukan"),
+    ("notification_not_found", "Notification not found", "Notifikasi tidak ditemukan"),
+    ("merge_not_found", "Merge not found", "Penggabungan akun tidak ditemukan"),
+    ("research_export_not_found", "Research export not found", "Ekspor riset tidak ditemukan"),
+    ("survey_draft_not_found", "No saved health survey draft", "Tidak ada draf survei kesehatan yang tersimpan"),
+    (
+        "health_profile_not_found",
+        "Health profile not found. Please complete the health survey.",
+        "Profil kesehatan tidak ditemukan. Silakan lengkapi survei kesehatan.",
+    ),
+    ("invalid_cursor", "Invalid cursor", "Kursor tidak valid"),
+    // Tokens.
+    ("invalid_verification_token", "Invalid verification token", "Token verifikasi tidak valid"),
+    (
+        "verification_token_expired",
+        "Verification link has expired",
+        "Tautan verifikasi sudah kedaluwarsa",
+    ),
+    ("verification_token_used", "Email already verified", "Email sudah diverifikasi"),
+    ("invalid_refresh_token", "Invalid or expired refresh token", "Refresh token tidak valid atau kedaluwarsa"),
+    ("invalid_oauth_state", "Invalid or expired OAuth state", "State OAuth tidak valid atau kedaluwarsa"),
+    (
+        "invalid_authorization_code",
+        "Invalid or expired authorization code",
+        "Kode otorisasi tidak valid atau kedaluwarsa",
+    ),
+    ("invalid_token", "Invalid or expired token", "Token tidak valid atau kedaluwarsa"),
+    (
+        "invalid_confirmation_token",
+        "Invalid or expired confirmation token",
+        "Token konfirmasi tidak valid atau kedaluwarsa",
+    ),
+    // Dates and times.
+    ("invalid_date", "Invalid date format. Use YYYY-MM-DD", "Format tanggal tidak valid. Gunakan YYYY-MM-DD"),
+    ("invalid_time", "Invalid time format. Use HH:MM", "Format waktu tidak valid. Gunakan HH:MM"),
+    ("invalid_start_date", "Invalid start_date format", "Format start_date tidak valid"),
+    ("invalid_end_date", "Invalid end_date format", "Format end_date tidak valid"),
+    (
+        "invalid_start_date",
+        "Invalid start_date format. Use YYYY-MM-DD",
+        "Format start_date tidak valid. Gunakan YYYY-MM-DD",
+    ),
+    ("invalid_end_date", "Invalid end_date format. Use YYYY-MM-DD", "Format end_date tidak valid. Gunakan YYYY-MM-DD"),
+    ("invalid_start_date", "Invalid start date format", "Format tanggal mulai tidak valid"),
+    ("invalid_end_date", "Invalid end date format", "Format tanggal akhir tidak valid"),
+    ("date_range_reversed", "start_date must be before end_date", "start_date harus sebelum end_date"),
+    ("date_range_reversed", "start_date must not be after end_date", "start_date tidak boleh setelah end_date"),
+    ("date_range_reversed", "start_date must be on or before end_date", "start_date harus sama dengan atau sebelum end_date"),
+    ("date_range_reversed", "start must be on or before end", "start harus sama dengan atau sebelum end"),
+    (
+        "invalid_source_date",
+        "Invalid source_date format. Use YYYY-MM-DD",
+        "Format source_date tidak valid. Gunakan YYYY-MM-DD",
+    ),
+    (
+        "invalid_target_date",
+        "Invalid target_date format. Use YYYY-MM-DD",
+        "Format target_date tidak valid. Gunakan YYYY-MM-DD",
+    ),
+    ("same_copy_dates", "source_date and target_date must differ", "source_date dan target_date harus berbeda"),
+    ("future_meal", "Cannot log a meal in the future", "Tidak dapat mencatat makanan di masa depan"),
+    // Meals and food.
+    ("food_name_required", "food_name cannot be empty", "food_name tidak boleh kosong"),
+    ("name_required", "name cannot be empty", "name tidak boleh kosong"),
+    ("name_too_long", "name must be at most 120 characters", "name maksimal 120 karakter"),
+    ("invalid_hunger_level", "hunger_level must be between 1 and 5", "hunger_level harus antara 1 dan 5"),
+    ("invalid_portion_weight", "Portion weights must be greater than 0", "Berat porsi harus lebih dari 0"),
+    ("planned_meal_already_eaten", "This planned meal was already marked as eaten", "Menu ini sudah ditandai telah dimakan"),
+    ("nothing_to_shop_for", "There are no ingredients to shop for", "Tidak ada bahan yang perlu dibeli"),
+    ("verified_source_claimed", "source cannot be fdc or barcode", "source tidak boleh fdc atau barcode"),
+    (
+        "verified_source_claimed",
+        "source cannot be fdc or barcode; log those with /api/meals/log-fdc or /api/meals/log-barcode",
+        "source tidak boleh fdc atau barcode; catat dengan /api/meals/log-fdc atau /api/meals/log-barcode",
+    ),
+    ("invalid_barcode", "A barcode is 8 to 14 digits", "Barcode terdiri dari 8 sampai 14 digit"),
+    ("barcode_not_found", "No food found for this barcode", "Tidak ada makanan untuk barcode ini"),
+    ("invalid_serving", "Send either grams or portion_id", "Kirim grams atau portion_id"),
+    ("no_meals_to_copy", "No meals to copy on source_date", "Tidak ada makanan untuk disalin pada source_date"),
+    ("search_query_too_long", "q must be at most 100 characters", "q maksimal 100 karakter"),
+    ("invalid_calorie_filter", "Calorie filters must be 0 or more", "Filter kalori harus 0 atau lebih"),
+    (
+        "calorie_range_reversed",
+        "min_calories must not be more than max_calories",
+        "min_calories tidak boleh lebih dari max_calories",
+    ),
+    // Images.
+    ("image_required", "No image provided. Please upload an image file.", "Tidak ada gambar. Silakan unggah file gambar."),
+    ("invalid_image_type", "Invalid file type. Please upload an image.", "Jenis file tidak valid. Silakan unggah gambar."),
+    ("image_too_large", "Image too large. Maximum size is 20MB.", "Gambar terlalu besar. Ukuran maksimum 20MB."),
+    // Accounts and chat.
+    (
+        "merge_in_progress",
+        "An account merge is in progress; wait for it to finish first",
+        "Penggabungan akun sedang berjalan; tunggu hingga selesai",
+    ),
+    ("title_required", "title must not be empty", "Judul tidak boleh kosong"),
+    // Notifications.
+    ("invalid_digest_time", "digest_time must be HH:MM", "digest_time harus dalam format HH:MM"),
+    ("invalid_quiet_hours", "Quiet hours must be HH:MM", "Jam tenang harus dalam format HH:MM"),
+    (
+        "quiet_hours_incomplete",
+        "quiet_hours_start and quiet_hours_end must be set together",
+        "quiet_hours_start dan quiet_hours_end harus diisi bersamaan",
+    ),
+    (
+        "invalid_utc_offset",
+        "utc_offset_minutes must be between -720 and 840",
+        "utc_offset_minutes harus antara -720 dan 840",
+    ),
+    (
+        "quiet_hours_empty",
+        "Quiet hours must not start and end at the same time",
+        "Jam tenang tidak boleh dimulai dan berakhir pada waktu yang sama",
+    ),
+    ("invalid_reminder_time", "Meal reminder times must be HH:MM", "Waktu pengingat makan harus dalam format HH:MM"),
+    ("invalid_reminder_time", "streak_alert_time must be HH:MM", "streak_alert_time harus dalam format HH:MM"),
+    ("invalid_reminder_time", "Hydration times must be HH:MM", "Waktu pengingat minum harus dalam format HH:MM"),
+    // Meal logging gateway.
+    ("gateway_disabled", "The inbound gateway is not enabled", "Gateway pesan masuk tidak diaktifkan"),
+    ("invalid_gateway_signature", "Invalid gateway signature", "Tanda tangan gateway tidak valid"),
+    (
+        "invalid_phone_number",
+        "Invalid phone number. Use international format, e.g. +6281234567890",
+        "Nomor telepon tidak valid. Gunakan format internasional, misalnya +6281234567890",
+    ),
+    (
+        "whatsapp_number_taken",
+        "This WhatsApp number is linked to another account",
+        "Nomor WhatsApp ini sudah terhubung ke akun lain",
+    ),
+    ("whatsapp_not_linked", "No WhatsApp number linked", "Tidak ada nomor WhatsApp yang terhubung"),
+];
+
+/// Per-field messages from `validation::Checks`: (code, English, Indonesian),
+/// where `{0}`, `{1}`, ... stand for the field name and limits, in order.
+/// Messages with their own catalog entry take that one instead.
+const FIELD_TEMPLATES: &[(&str, &str, &str)] = &[
+    ("out_of_range", "{0} must be between {1} and {2}", "{0} harus antara {1} dan {2}"),
+    ("required", "{0} must not be empty", "{0} tidak boleh kosong"),
+    ("too_long", "{0} must be at most {1} characters", "{0} maksimal {1} karakter"),
+    ("too_many_entries", "{0} must have at most {1} entries", "{0} maksimal {1} entri"),
+    ("invalid_diastolic", "{0} must be below systolic", "{0} harus di bawah sistolik"),
+    ("invalid_email", "{0} must be an email address", "{0} harus berupa alamat email"),
+    ("invalid_language", "{0} may only contain letters, spaces and hyphens", "{0} hanya boleh berisi huruf, spasi, dan tanda hubung"),
+    ("report_too_long", "A report can cover at most {0} days", "Laporan mencakup paling lama {0} hari"),
+];
+
+/// Translates a message raised in English into `locale`, returning its code.
+/// Messages missing from the catalog keep their English text and get
+/// `fallback_code`.
+pub fn localize(english: &str, fallback_code: &'static str, locale: Locale) -> (&'static str, String) {
+    match CATALOG.iter().find(|(_, en, _)| *en == english) {
+        Some((code, en, id)) => {
+            let message = match locale {
+                Locale::En => *en,
+                Locale::Id => *id,
+            };
+            (code, message.to_string())
+        }
+        None => (fallback_code, english.to_string()),
+    }
+}
+
+/// Like [`localize`], for one field's message from `validation::Checks`.
+pub fn localize_field(english: &str, locale: Locale) -> (&'static str, String) {
+    if CATALOG.iter().any(|(_, en, _)| *en == english) {
+        return localize(english, "validation_error", locale);
+    }
+
+    for (code, en, id) in FIELD_TEMPLATES {
+        if let Some(args) = match_template(en, english) {
+            let template = match locale {
+                Locale::En => *en,
+                Locale::Id => *id,
+            };
+            let message = args
+                .iter()
+                .enumerate()
+                .fold(template.to_string(), |message, (i, arg)| message.replace(&format!("{{{}}}", i), arg));
+            return (code, message);
+        }
+    }
+
+    ("validation_error", english.to_string())
+}
+
+/// The values `text` has in place of the template's `{0}`, `{1}`, ... or
+/// `None` if it doesn't fit the template.
+fn match_template<'a>(template: &str, text: &'a str) -> Option<Vec<&'a str>> {
+    let mut literals = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        literals.push(&rest[..start]);
+        rest = &rest[end + 1..];
+    }
+    let (first, between) = literals.split_first()?;
+
+    let mut remaining = text.strip_prefix(*first)?;
+    let mut args = Vec::new();
+    for literal in between {
+        let at = remaining.find(*literal)?;
+        args.push(&remaining[..at]);
+        remaining = &remaining[at + literal.len()..];
+    }
+    // The text after the last placeholder.
+    args.push(remaining.strip_suffix(rest)?);
+
+    if args.iter().any(|arg| arg.is_empty()) {
+        return None;
+    }
+    Some(args)
+}
@@ -4,13 +4,16 @@ mod routes;
 mod handlers;
 mod models;
 mod error;
+mod field_encryption;
+mod i18n;
 mod middleware;
+mod openapi;
+mod sdk;
 mod services;
+mod validation;
 
-use std::net::SocketAddr;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{ layer::SubscriberExt, util::SubscriberInitExt };
-use axum::extract::DefaultBodyLimit;
 
 use config::Config;
 use db::AppState;
@@ -29,21 +32,52 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    // `alimentify sdk ...` generates the API clients instead of serving.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("sdk") {
+        if let Err(e) = sdk::run(&args[1..]) {
+            tracing::error!("SDK generation failed: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let config = Config::from_env().expect("Failed to load configuration");
 
-    tracing::info!("Environment: {:?}", config.server.environment);
-    tracing::info!("CORS enabled: {}", config.security.cors_enabled);
-    tracing::info!("API key enabled: {}", config.security.api_key_enabled);
+    config.log_startup_banner();
+
+    field_encryption
+        ::init(config.security.health_data_key.as_deref())
+        .expect("Invalid HEALTH_DATA_ENCRYPTION_KEY");
+    if !field_encryption::is_enabled() {
+        tracing::warn!("HEALTH_DATA_ENCRYPTION_KEY is not set; sensitive health fields are stored unencrypted");
+    }
 
-    let db = db::setup_database(&config).await.expect("Failed to connect to MongoDB");
+    let mongodb = db::connect_mongodb(&config).await.expect("Failed to connect to MongoDB");
+    let db = db
+        ::setup_database(&mongodb, &config.mongodb.database_name).await
+        .expect("Failed to connect to MongoDB");
 
     let redis = db::setup_redis(&config).await.expect("Failed to connect to Redis");
+    services::provider_metrics_service::init(redis.clone());
 
     let gemini_api_key = std::env
         ::var("GEMINI_API_KEY")
         .expect("GEMINI_API_KEY must be set in environment variables");
+    let prompts = std::sync::Arc::new(
+        services::prompt_service::PromptRegistry::load(
+            &config.prompts.dir,
+            config.prompts.hot_reload
+        )
+    );
+    tracing::info!(
+        "Loaded prompt templates: {} (hot reload: {})",
+        prompts.versions().join(", "),
+        config.prompts.hot_reload
+    );
+
     let gemini_service = std::sync::Arc::new(
-        services::gemini_service::GeminiService::new(gemini_api_key)
+        services::gemini_service::GeminiService::new(gemini_api_key, prompts, config.gemini.clone())
     );
     tracing::info!("Initialized Gemini AI service");
 
@@ -64,6 +98,8 @@ async fn main() {
     let mealdb_service = std::sync::Arc::new(services::mealdb_service::MealDbService::new());
     tracing::info!("Initialized MealDB service");
 
+    let cache = std::sync::Arc::new(services::cache_service::CacheService::new(redis.clone()));
+
     let state = AppState {
         db,
         redis,
@@ -72,11 +108,37 @@ async fn main() {
         fdc_service,
         ninja_service,
         mealdb_service,
+        cache,
+        live: std::sync::Arc::new(services::live_update_service::LiveUpdates::default()),
     };
 
-    let app = routes
-        ::create_routes(state.clone())
-        .layer(DefaultBodyLimit::max(25 * 1024 * 1024)) 
+    services::scheduler::start(state.clone());
+    tracing::info!("Started background scheduler");
+
+    let mut tenant_routes = std::collections::HashMap::new();
+    for tenant in &config.mongodb.tenants {
+        let tenant_db = db
+            ::setup_database(&mongodb, &tenant.database_name).await
+            .expect("Failed to set up tenant database");
+        let tenant_state = AppState {
+            db: tenant_db,
+            config: config.for_tenant(tenant),
+            live: std::sync::Arc::new(services::live_update_service::LiveUpdates::default()),
+            ..state.clone()
+        };
+
+        services::scheduler::start(tenant_state.clone());
+        tenant_routes.insert(tenant.host.clone(), routes::create_routes(tenant_state));
+        tracing::info!("Serving tenant {} from database {}", tenant.host, tenant.database_name);
+    }
+
+    let app = (
+        if tenant_routes.is_empty() {
+            routes::create_routes(state.clone())
+        } else {
+            routes::dispatch_by_host(routes::create_routes(state.clone()), tenant_routes)
+        }
+    )
         .layer(middleware::cors::setup_cors(&config))
         .layer(TraceLayer::new_for_http());
 
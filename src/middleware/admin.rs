@@ -0,0 +1,82 @@
+use axum::{
+    extract::{ Request, State },
+    http::StatusCode,
+    middleware::Next,
+    response::{ IntoResponse, Response },
+    Json,
+};
+use mongodb::bson::doc;
+use serde_json::json;
+
+use crate::{ db::AppState, models::{ Claims, User, UserRole } };
+
+/// Must run after `auth_middleware`, which provides the `Claims` extension.
+pub async fn admin_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next
+) -> Result<Response, Response> {
+    let claims = request.extensions().get::<Claims>().cloned();
+    let user = load_user(&state, claims, "Admin access required").await?;
+
+    let is_admin =
+        user.role == UserRole::Admin ||
+        state.config.security.admin_emails.contains(&user.gmail.to_lowercase());
+
+    if !is_admin {
+        return Err(forbidden("Admin access required"));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Gate for anonymized research exports. Admins are not let through
+/// implicitly; research access has to be granted on its own.
+pub async fn researcher_middleware(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next
+) -> Result<Response, Response> {
+    let claims = request.extensions().get::<Claims>().cloned();
+    let user = load_user(&state, claims, "Researcher access required").await?;
+
+    let is_researcher =
+        user.role == UserRole::Researcher ||
+        state.config.security.researcher_emails.contains(&user.gmail.to_lowercase());
+
+    if !is_researcher {
+        return Err(forbidden("Researcher access required"));
+    }
+
+    Ok(next.run(request).await)
+}
+
+fn forbidden(message: &str) -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        Json(json!({
+            "error": message
+        })),
+    ).into_response()
+}
+
+async fn load_user(state: &AppState, claims: Option<Claims>, message: &str) -> Result<User, Response> {
+    let claims = claims.ok_or_else(|| forbidden(message))?;
+
+    let user_id = mongodb::bson::oid::ObjectId
+        ::parse_str(&claims.sub)
+        .map_err(|_| forbidden(message))?;
+
+    state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "error": "Failed to verify user"
+                })),
+            ).into_response()
+        })?
+        .ok_or_else(|| forbidden(message))
+}
@@ -6,15 +6,31 @@ use axum::{
 };
 use serde_json::json;
 
-use crate::db::AppState;
+use crate::{ db::AppState, services::api_key_service };
 
 const PUBLIC_PATHS: &[&str] = &[
     "/",
     "/docs",
+    // Behind the docs credentials instead, so Swagger UI can fetch it.
+    "/api/openapi.json",
     "/status",
+    "/status/providers",
     "/api/auth/google",
     "/api/auth/google/callback",
     "/api/auth/verify-email",
+    // Called from third-party pages, which can't hold an API key.
+    "/api/widget/summary",
+    // Browsers can't set headers on a WebSocket handshake; the access token
+    // is checked instead.
+    "/api/ws",
+];
+
+/// Path prefixes that carry their own authorization.
+const PUBLIC_PREFIXES: &[&str] = &[
+    // Signed image URLs, loaded by `<img>` tags.
+    "/api/images/",
+    // Provider webhooks, signed with the gateway secret.
+    "/api/gateway/inbound/",
 ];
 
 pub async fn api_key_middleware(
@@ -31,7 +47,7 @@ pub async fn api_key_middleware(
 
     let is_public = PUBLIC_PATHS.iter().any(|&public_path| {
         path == public_path || path.starts_with(&format!("{}?", public_path))
-    });
+    }) || PUBLIC_PREFIXES.iter().any(|&prefix| path.starts_with(prefix));
     
     if is_public {
         return Ok(next.run(request).await);
@@ -49,7 +65,17 @@ pub async fn api_key_middleware(
             ).into_response()
         })?;
 
-    if !state.config.security.api_keys.contains(&api_key.to_string()) {
+    let scopes = api_key_service::scopes_for(&state, api_key).await.map_err(|e| {
+        tracing::error!("Failed to look up API key: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(json!({
+                "error": "Failed to verify API key"
+            })),
+        ).into_response()
+    })?;
+
+    let Some(scopes) = scopes else {
         return Err(
             (
                 StatusCode::UNAUTHORIZED,
@@ -58,6 +84,19 @@ pub async fn api_key_middleware(
             })),
             ).into_response()
         );
+    };
+
+    if let Some(scope) = api_key_service::required_scope(request.method(), path) {
+        if !scopes.contains(&scope) {
+            return Err(
+                (
+                    StatusCode::FORBIDDEN,
+                    axum::Json(json!({
+                        "error": format!("API key lacks the {} scope", scope.as_str())
+                    })),
+                ).into_response()
+            );
+        }
     }
 
     Ok(next.run(request).await)
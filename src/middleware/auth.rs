@@ -9,7 +9,13 @@ use jsonwebtoken::{ decode, DecodingKey, Validation };
 use mongodb::bson::doc;
 use serde_json::json;
 
-use crate::{ db::AppState, models::{ Claims, User } };
+use crate::{
+    config::RedisPolicy,
+    db::AppState,
+    error::AppError,
+    models::{ Claims, User },
+    services::{ auth_service, redis_health_service::RedisFeature },
+};
 
 pub async fn auth_middleware(
     State(state): State<AppState>,
@@ -32,18 +38,59 @@ pub async fn auth_middleware(
             ).into_response()
         })?;
 
+    let claims = authenticate(&state, token).await?;
+    request.extensions_mut().insert(claims);
+
+    Ok(next.run(request).await)
+}
+
+/// Checks an access token: signature, expiry and tenant, plus the session and
+/// email verification when those are required. Also used by routes that take
+/// the token from somewhere other than the `Authorization` header.
+pub async fn authenticate(state: &AppState, token: &str) -> Result<Claims, Response> {
+    // Tokens from another tenant's host carry a different (or no) tenant.
     let token_data = decode::<Claims>(
         token,
         &DecodingKey::from_secret(state.config.jwt.secret.as_bytes()),
         &Validation::default()
-    ).map_err(|_| {
-        (
-            StatusCode::UNAUTHORIZED,
-            Json(json!({
+    )
+        .ok()
+        .filter(|data| data.claims.tenant.as_deref() == state.config.tenant_database())
+        .ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({
                 "error": "Invalid or expired token"
             })),
-        ).into_response()
-    })?;
+            ).into_response()
+        })?;
+
+    if state.config.security.require_session {
+        // While Redis is down a fail-open policy trusts the token alone.
+        let session = match auth_service::get_session(&state.redis, &token_data.claims.sub).await {
+            Ok(session) => session.map(|_| ()),
+            Err(AppError::ExternalApiError(_)) if
+                RedisFeature::Sessions.policy(&state.config) == RedisPolicy::FailOpen
+            => Some(()),
+            Err(e) => {
+                tracing::error!("Failed to look up session: {}", e);
+                return Err(e.into_response());
+            }
+        };
+
+        if session.is_none() {
+            return Err(
+                (
+                    StatusCode::UNAUTHORIZED,
+                    Json(
+                        json!({
+                        "error": "Session expired or logged out"
+                    })
+                    ),
+                ).into_response()
+            );
+        }
+    }
 
     if state.config.security.require_email_verification {
         let users_collection = state.db.collection::<User>("users");
@@ -98,7 +145,5 @@ pub async fn auth_middleware(
         }
     }
 
-    request.extensions_mut().insert(token_data.claims);
-
-    Ok(next.run(request).await)
+    Ok(token_data.claims)
 }
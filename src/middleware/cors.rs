@@ -1,5 +1,5 @@
 ﻿use axum::http::{ header, Method, HeaderValue, HeaderName };
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{ Any, CorsLayer };
 
 use crate::config::Config;
 
@@ -27,8 +27,17 @@ pub fn setup_cors(config: &Config) -> CorsLayer {
             header::CONTENT_TYPE,
             header::AUTHORIZATION,
             header::ACCEPT,
+            header::IF_MATCH,
             HeaderName::from_static("x-api-key"),
         ])
+        // Clients read the version to send back in If-Match.
+        .expose_headers([header::ETAG])
         .allow_credentials(true)
         .max_age(std::time::Duration::from_secs(3600))
 }
+
+/// For the widget summary, which embeds call from the browser on any site.
+/// It is authorized by the widget token in the query, never by cookies.
+pub fn widget_cors() -> CorsLayer {
+    CorsLayer::new().allow_origin(Any).allow_methods([Method::GET])
+}
@@ -0,0 +1,34 @@
+use axum::{ extract::{ MatchedPath, Request, State }, middleware::Next, response::Response };
+use std::time::Instant;
+
+use crate::{ db::AppState, services::latency_service };
+
+/// Times every matched route and records it for the latency budget report.
+/// Must be a route layer so the matched path pattern is known; unmatched
+/// paths (404s) aren't recorded.
+pub async fn latency_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if !state.config.latency.enabled {
+        return next.run(request).await;
+    }
+    let Some(path) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string()) else {
+        return next.run(request).await;
+    };
+    let route = format!("{} {}", request.method(), path);
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+
+    // Recorded off the request path so a slow Redis doesn't add to the timing.
+    let redis = state.redis.clone();
+    tokio::spawn(async move {
+        if let Err(e) = latency_service::record(&redis, &route, elapsed_ms).await {
+            tracing::warn!("Failed to record latency for {}: {}", route, e);
+        }
+    });
+
+    response
+}
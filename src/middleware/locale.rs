@@ -0,0 +1,10 @@
+use axum::{ extract::Request, middleware::Next, response::Response };
+
+use crate::i18n::{ self, Locale };
+
+/// Picks the response language from `Accept-Language` for the rest of the
+/// request, so errors raised anywhere below are rendered in it.
+pub async fn locale_middleware(request: Request, next: Next) -> Response {
+    let locale = Locale::from_headers(request.headers());
+    i18n::with_locale(locale, next.run(request)).await
+}
@@ -1,3 +1,7 @@
 pub mod api_key;
 pub mod auth;
 pub mod cors;
+pub mod admin;
+pub mod response_cache;
+pub mod latency;
+pub mod locale;
@@ -0,0 +1,117 @@
+use axum::{
+    body::{ self, Body },
+    extract::{ Request, State },
+    http::{ header, HeaderValue, Method, StatusCode },
+    middleware::Next,
+    response::{ IntoResponse, Response },
+};
+
+use crate::{
+    config::RedisPolicy,
+    db::AppState,
+    models::Claims,
+    services::{
+        redis_health_service::{ self, RedisFeature },
+        response_cache_service::{ self, CachedResponse, CachedRoute },
+    },
+};
+
+/// Responses larger than this are served but not cached.
+const MAX_CACHED_BODY_BYTES: usize = 512 * 1024;
+
+/// Serves allowlisted GET routes from Redis, keyed per user and query string.
+/// Must run after `auth_middleware`, which provides the user. A request with
+/// `Cache-Control: no-cache` skips the cached copy and refreshes it.
+pub async fn response_cache_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if !state.config.response_cache.enabled || request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let Some(route) = CachedRoute::for_path(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let Some(user_id) = request
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.sub.clone()) else {
+        return next.run(request).await;
+    };
+
+    let query = request.uri().query().unwrap_or_default().to_string();
+    let bypass = request
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("no-cache"));
+
+    // Fail-closed keeps an outage from sending every cached read to MongoDB
+    // at once.
+    let fail_closed = RedisFeature::ResponseCache.policy(&state.config) == RedisPolicy::FailClosed;
+    let key = match response_cache_service::response_key(&state, &user_id, route, &query).await {
+        Ok(key) => key,
+        Err(_) if !bypass && fail_closed => {
+            return redis_health_service::unavailable(RedisFeature::ResponseCache).into_response();
+        }
+        Err(_) => {
+            return next.run(request).await;
+        }
+    };
+
+    if !bypass {
+        match response_cache_service::get(&state, &key).await {
+            Ok(Some(cached)) => {
+                return cached_response(cached);
+            }
+            Ok(None) => {}
+            Err(_) if fail_closed => {
+                return redis_health_service::unavailable(RedisFeature::ResponseCache).into_response();
+            }
+            Err(_) => {}
+        }
+    }
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer response for caching: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if bytes.len() <= MAX_CACHED_BODY_BYTES {
+        if let Ok(text) = std::str::from_utf8(&bytes) {
+            let header_text = |name| {
+                parts.headers
+                    .get(name)
+                    .and_then(|v: &HeaderValue| v.to_str().ok())
+                    .map(str::to_string)
+            };
+            let cached = CachedResponse {
+                content_type: header_text(header::CONTENT_TYPE),
+                etag: header_text(header::ETAG),
+                body: text.to_string(),
+            };
+            response_cache_service::put(&state, &key, &cached).await;
+        }
+    }
+
+    parts.headers.insert("x-cache", HeaderValue::from_static("MISS"));
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn cached_response(cached: CachedResponse) -> Response {
+    let mut response = Response::new(Body::from(cached.body));
+    let headers = response.headers_mut();
+    for (name, value) in [(header::CONTENT_TYPE, cached.content_type), (header::ETAG, cached.etag)] {
+        if let Some(value) = value.and_then(|v| HeaderValue::from_str(&v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    headers.insert("x-cache", HeaderValue::from_static("HIT"));
+    response
+}
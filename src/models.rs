@@ -1,6 +1,8 @@
-﻿use chrono::{ DateTime, Utc };
+﻿use chrono::{ DateTime, Datelike, NaiveDate, Utc };
 use serde::{ Deserialize, Serialize };
 use mongodb::bson::oid::ObjectId;
+use utoipa::ToSchema;
+use crate::field_encryption;
 
 mod bson_datetime {
     use chrono::{ DateTime, Utc, TimeZone };
@@ -75,6 +77,26 @@ mod bson_datetime {
     }
 }
 
+/// Like `bson_datetime`, but stores a BSON date so the field can be used in
+/// range queries and aggregation date operators. Still reads legacy RFC 3339
+/// strings.
+mod bson_date {
+    use chrono::{ DateTime, Utc };
+    use serde::{ Deserializer, Serialize, Serializer };
+
+    pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        mongodb::bson::DateTime::from_chrono(*date).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+        where D: Deserializer<'de>
+    {
+        super::bson_datetime::deserialize(deserializer)
+    }
+}
+
 fn serialize_object_id_as_string<S>(
     id: &Option<mongodb::bson::oid::ObjectId>,
     serializer: S
@@ -182,6 +204,9 @@ pub struct User {
     pub email_verification_status: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub email_verification_token: Option<String>,
+    /// When `email_verification_token` was issued.
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub email_verification_sent_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
     pub email_verified_at: Option<DateTime<Utc>>,
     #[serde(with = "bson_datetime")]
@@ -193,9 +218,53 @@ pub struct User {
     pub health_profile: Option<HealthProfile>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub has_completed_health_survey: Option<bool>,
+    /// Google accounts of secondary users merged into this one; they sign in here.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub linked_google_ids: Vec<String>,
+    #[serde(default)]
+    pub role: UserRole,
+    /// Chat messages (and the photos sent in them) older than this many months
+    /// are deleted by the retention job. `None` keeps them indefinitely.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub chat_retention_months: Option<i32>,
+    /// `None` uses `NotificationPreferences::default()`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub notification_preferences: Option<NotificationPreferences>,
+    /// `jti` of the user's live widget token; issuing a new one or revoking
+    /// replaces it, which invalidates the old token.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub widget_token_id: Option<String>,
+    /// WhatsApp number meals can be logged from through the inbound gateway.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub whatsapp: Option<WhatsAppLink>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A WhatsApp number linked to the account. It is verified by sending
+/// `verification_code` from the number to the gateway; until then no
+/// messages from it are logged.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WhatsAppLink {
+    /// E.164, e.g. `+6281234567890`.
+    pub number: String,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub verified_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub verification_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub code_expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    User,
+    Admin,
+    /// May run anonymized research exports; grants no other admin access.
+    Researcher,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserResponse {
     pub id: String,
     pub google_id: String,
@@ -208,6 +277,10 @@ pub struct UserResponse {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub has_completed_health_survey: Option<bool>,
+    pub role: UserRole,
+    pub chat_retention_months: Option<i32>,
+    /// Verified WhatsApp number for the meal logging gateway.
+    pub whatsapp_number: Option<String>,
 }
 
 impl From<User> for UserResponse {
@@ -224,6 +297,11 @@ impl From<User> for UserResponse {
             created_at: user.created_at,
             updated_at: user.updated_at,
             has_completed_health_survey: user.has_completed_health_survey,
+            role: user.role,
+            chat_retention_months: user.chat_retention_months,
+            whatsapp_number: user.whatsapp
+                .filter(|link| link.verified_at.is_some())
+                .map(|link| link.number),
         }
     }
 }
@@ -240,6 +318,7 @@ pub struct GoogleUserInfo {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct AuthResponse {
     pub token: String,
     pub user: UserResponse,
@@ -251,6 +330,42 @@ pub struct Claims {
     pub email: String,
     pub exp: i64,
     pub iat: i64,
+    /// Database of the white-label tenant that issued the token; unset for
+    /// the default database.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tenant: Option<String>,
+}
+
+/// Claims of a widget token: grants read access to the user's public widget
+/// summary and nothing else. Lacks `email`, so it never decodes as [`Claims`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WidgetClaims {
+    pub sub: String,
+    pub aud: String,
+    pub jti: String,
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tenant: Option<String>,
+}
+
+/// Redis entry for a one-time sign-in code from the Google callback, keyed by
+/// the code itself.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthCodeRecord {
+    pub user_id: String,
+    #[serde(default)]
+    pub tenant: Option<String>,
+}
+
+/// Redis entry for an issued refresh token, keyed by the token itself.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshTokenRecord {
+    pub user_id: String,
+    /// Tenant database the token was issued for, like `Claims::tenant`.
+    #[serde(default)]
+    pub tenant: Option<String>,
+    pub expires_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -271,14 +386,17 @@ pub struct HealthProfile {
     pub weight_kg: f64,
     pub activity_level: ActivityLevel,
     pub goal: HealthGoal,
+    #[serde(default)]
+    pub macro_strategy: MacroStrategy,
 
-    #[serde(skip_serializing_if = "Option::is_none", default)]
+    /// The four fields below are encrypted at rest; see `field_encryption`.
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "field_encryption::deserialize")]
     pub medical_conditions: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "field_encryption::deserialize")]
     pub blood_pressure: Option<BloodPressure>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "field_encryption::deserialize")]
     pub fasting_blood_sugar: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "field_encryption::deserialize")]
     pub allergies: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub dietary_preferences: Option<Vec<DietaryPreference>>,
@@ -295,9 +413,24 @@ pub struct HealthProfile {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub ai_recommendations: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ai_recommendations_prompt_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     pub recommended_foods: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub foods_to_avoid: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub condition_guidance: Option<Vec<ConditionGuidance>>,
+    /// Preset whose calorie adjustment and macro split replace the goal's.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub goal_preset: Option<AppliedGoalPreset>,
+    /// Targets for some weekdays that replace the daily ones above, e.g.
+    /// more carbs on training days. See [`HealthProfile::targets_on`].
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub target_cycle: Vec<CycleDayTargets>,
+
+    /// Incremented on every save; clients send it back in `If-Match`.
+    #[serde(default)]
+    pub version: i64,
 
     #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
@@ -305,14 +438,14 @@ pub struct HealthProfile {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Gender {
     Male,
     Female,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ActivityLevel {
     Sedentary,
@@ -334,7 +467,7 @@ impl ActivityLevel {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HealthGoal {
     LoseWeight,
@@ -354,7 +487,78 @@ impl HealthGoal {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// How daily calories are divided into protein, carbs and fat.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MacroStrategy {
+    /// Fixed shares per goal, or the applied goal preset's shares.
+    #[default]
+    PercentageSplit,
+    /// Protein from body weight, a quarter of calories from fat and the rest
+    /// from carbs. Suits athletes, whose protein needs follow their weight.
+    ProteinPerKg {
+        grams_per_kg: f64,
+    },
+    /// 75% fat, 20% protein and 5% carbs.
+    Keto,
+}
+
+impl MacroStrategy {
+    pub const MIN_PROTEIN_G_PER_KG: f64 = 0.8;
+    pub const MAX_PROTEIN_G_PER_KG: f64 = 3.0;
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            MacroStrategy::ProteinPerKg { grams_per_kg } if
+                !(Self::MIN_PROTEIN_G_PER_KG..=Self::MAX_PROTEIN_G_PER_KG).contains(grams_per_kg)
+            =>
+                Err(
+                    format!(
+                        "grams_per_kg must be between {} and {}",
+                        Self::MIN_PROTEIN_G_PER_KG,
+                        Self::MAX_PROTEIN_G_PER_KG
+                    )
+                ),
+            _ => Ok(()),
+        }
+    }
+
+    /// (protein, carbs, fat) grams for `daily_calories`.
+    pub fn calculate(
+        &self,
+        daily_calories: f64,
+        weight_kg: f64,
+        goal: &HealthGoal,
+        preset: Option<&AppliedGoalPreset>
+    ) -> (f64, f64, f64) {
+        match self {
+            MacroStrategy::PercentageSplit =>
+                match preset {
+                    Some(preset) =>
+                        (
+                            (daily_calories * preset.protein_share) / 4.0,
+                            (daily_calories * preset.carbs_share) / 4.0,
+                            (daily_calories * preset.fat_share) / 9.0,
+                        ),
+                    None => HealthProfile::calculate_macros(daily_calories, goal),
+                }
+            MacroStrategy::ProteinPerKg { grams_per_kg } => {
+                let protein_g = weight_kg * grams_per_kg;
+                let fat_g = (daily_calories * 0.25) / 9.0;
+                let carbs_g = ((daily_calories - protein_g * 4.0 - fat_g * 9.0) / 4.0).max(0.0);
+                (protein_g, carbs_g, fat_g)
+            }
+            MacroStrategy::Keto => {
+                let protein_g = (daily_calories * 0.2) / 4.0;
+                let carbs_g = (daily_calories * 0.05) / 4.0;
+                let fat_g = (daily_calories * 0.75) / 9.0;
+                (protein_g, carbs_g, fat_g)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct BloodPressure {
     pub systolic: i32,
     pub diastolic: i32,
@@ -362,7 +566,63 @@ pub struct BloodPressure {
     pub measured_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MedicalCondition {
+    Hypertension,
+    Type2Diabetes,
+    Gout,
+}
+
+/// Dietary constraints derived from a medical condition on the health profile.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConditionGuidance {
+    pub condition: MedicalCondition,
+    pub title: String,
+    pub guidelines: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub daily_sodium_limit_mg: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_carbs_per_meal_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_carbs_per_snack_g: Option<f64>,
+    /// Lowercase ingredient keywords matched against recipes and snacks.
+    pub avoid_ingredients: Vec<String>,
+}
+
+/// A goal preset as applied to a health profile. The split is copied so
+/// targets stay put if the preset library changes.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppliedGoalPreset {
+    pub id: String,
+    pub name: String,
+    pub calorie_adjustment: f64,
+    /// Shares of daily calories; they add up to 1.
+    pub protein_share: f64,
+    pub carbs_share: f64,
+    pub fat_share: f64,
+    pub guidelines: Vec<String>,
+    pub recommended_foods: Vec<String>,
+    pub foods_to_avoid: Vec<String>,
+    #[serde(with = "bson_datetime")]
+    pub applied_at: DateTime<Utc>,
+}
+
+/// Calorie and macro targets for the weekdays in `days_of_week`, such as
+/// training days.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CycleDayTargets {
+    /// Shown to the user, e.g. "Training day".
+    pub label: String,
+    /// ISO weekdays (1 = Monday ... 7 = Sunday).
+    pub days_of_week: Vec<u32>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum DietaryPreference {
     Vegetarian,
@@ -376,137 +636,1025 @@ pub enum DietaryPreference {
     Keto,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MealLog {
+/// Health survey answers saved part-way through. Derived values (BMI, TDEE,
+/// targets) are only computed when the survey is submitted.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HealthSurveyDraft {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub user_id: ObjectId,
+    #[serde(default)]
+    pub age: Option<i32>,
+    #[serde(default)]
+    pub gender: Option<Gender>,
+    #[serde(default)]
+    pub height_cm: Option<f64>,
+    #[serde(default)]
+    pub weight_kg: Option<f64>,
+    #[serde(default)]
+    pub activity_level: Option<ActivityLevel>,
+    #[serde(default)]
+    pub goal: Option<HealthGoal>,
+    #[serde(default)]
+    pub macro_strategy: Option<MacroStrategy>,
+    #[serde(default, deserialize_with = "field_encryption::deserialize")]
+    pub medical_conditions: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "field_encryption::deserialize")]
+    pub blood_pressure: Option<BloodPressure>,
+    #[serde(default, deserialize_with = "field_encryption::deserialize")]
+    pub fasting_blood_sugar: Option<f64>,
+    #[serde(default, deserialize_with = "field_encryption::deserialize")]
+    pub allergies: Option<Vec<String>>,
+    #[serde(default)]
+    pub dietary_preferences: Option<Vec<DietaryPreference>>,
     #[serde(with = "bson_datetime")]
-    pub date: DateTime<Utc>,
-    pub meal_type: MealType,
-    pub food_name: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One food of a meal made of several, e.g. the rice on a plate of rice and
+/// chicken.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct MealEntry {
+    pub name: String,
+    /// Free text, e.g. "1 cup" or "150 g".
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub quantity: Option<String>,
     pub calories: f64,
     pub protein_g: f64,
     pub carbs_g: f64,
     pub fat_g: f64,
-    pub serving_size: Option<String>,
-    pub notes: Option<String>,
-    #[serde(with = "bson_datetime")]
-    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fiber_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sugar_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sodium_mg: Option<f64>,
+    #[serde(default)]
+    pub source: MealSource,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "lowercase")]
-pub enum MealType {
-    Breakfast,
-    Lunch,
-    Dinner,
-    Snack,
+impl MealEntry {
+    /// Multiplies the nutrition values, for a portion correction.
+    pub fn scaled(mut self, factor: f64) -> Self {
+        self.calories *= factor;
+        self.protein_g *= factor;
+        self.carbs_g *= factor;
+        self.fat_g *= factor;
+        self.fiber_g = self.fiber_g.map(|g| g * factor);
+        self.sugar_g = self.sugar_g.map(|g| g * factor);
+        self.sodium_mg = self.sodium_mg.map(|mg| mg * factor);
+        self
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct DailyProgress {
+pub struct MealLog {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub user_id: ObjectId,
-    #[serde(with = "bson_datetime")]
+    #[serde(with = "bson_date")]
     pub date: DateTime<Utc>,
-    pub total_calories: f64,
-    pub total_protein_g: f64,
-    pub total_carbs_g: f64,
-    pub total_fat_g: f64,
-    pub water_ml: Option<f64>,
-    pub weight_kg: Option<f64>,
+    pub meal_type: MealType,
+    pub food_name: String,
+    /// For a meal with `items`, the sums of its items.
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    /// Fiber, total sugars and sodium, when the source reports them. They
+    /// feed the food quality score.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fiber_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sugar_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sodium_mg: Option<f64>,
+    /// The foods making up the meal; empty for a single food.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub items: Vec<MealEntry>,
+    pub serving_size: Option<String>,
     pub notes: Option<String>,
+    #[serde(default)]
+    pub source: MealSource,
+    #[serde(default)]
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub nutrition_snapshot: Option<NutritionSnapshot>,
+    /// Portion weight in grams the macros correspond to, when known (image estimate or user correction).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub portion_weight_g: Option<f64>,
+    /// Created by the recurring-meal scheduler rather than by the user.
+    #[serde(default)]
+    pub auto_logged: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub recurring_meal_id: Option<ObjectId>,
+    /// How hungry the user was before eating, from 1 (not at all) to 5 (starving).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub hunger_level: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mood: Option<MealMood>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub eating_context: Option<EatingContext>,
+    /// Incremented on every edit; clients send it back in `If-Match`.
+    #[serde(default)]
+    pub version: i64,
     #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
-    #[serde(with = "bson_datetime")]
-    pub updated_at: DateTime<Utc>,
 }
 
-impl HealthProfile {
-    pub fn calculate_bmi(weight_kg: f64, height_cm: f64) -> f64 {
-        let height_m = height_cm / 100.0;
-        weight_kg / (height_m * height_m)
-    }
+/// Nutrition of a meal made of several items. Fiber is only known when every
+/// item reports it; sugar and sodium are summed over the items that do.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ItemTotals {
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub fiber_g: Option<f64>,
+    pub sugar_g: Option<f64>,
+    pub sodium_mg: Option<f64>,
+}
 
-    pub fn bmi_category(bmi: f64) -> String {
-        match bmi {
-            bmi if bmi < 18.5 => "Underweight".to_string(),
-            bmi if bmi < 25.0 => "Normal".to_string(),
-            bmi if bmi < 30.0 => "Overweight".to_string(),
-            _ => "Obese".to_string(),
-        }
-    }
+impl ItemTotals {
+    pub fn of(items: &[MealEntry]) -> Self {
+        let sum_reported = |value: fn(&MealEntry) -> Option<f64>| {
+            items.iter().filter_map(value).reduce(|a, b| a + b)
+        };
 
-    pub fn calculate_bmr(weight_kg: f64, height_cm: f64, age: i32, gender: &Gender) -> f64 {
-        match gender {
-            Gender::Male => 10.0 * weight_kg + 6.25 * height_cm - 5.0 * (age as f64) + 5.0,
-            Gender::Female => 10.0 * weight_kg + 6.25 * height_cm - 5.0 * (age as f64) - 161.0,
+        ItemTotals {
+            calories: items.iter().map(|item| item.calories).sum(),
+            protein_g: items.iter().map(|item| item.protein_g).sum(),
+            carbs_g: items.iter().map(|item| item.carbs_g).sum(),
+            fat_g: items.iter().map(|item| item.fat_g).sum(),
+            fiber_g: items
+                .iter()
+                .map(|item| item.fiber_g)
+                .sum::<Option<f64>>()
+                .filter(|_| !items.is_empty()),
+            sugar_g: sum_reported(|item| item.sugar_g),
+            sodium_mg: sum_reported(|item| item.sodium_mg),
         }
     }
+}
 
-    pub fn calculate_tdee(bmr: f64, activity_level: &ActivityLevel) -> f64 {
-        bmr * activity_level.multiplier()
-    }
-
-    pub fn calculate_daily_calories(tdee: f64, goal: &HealthGoal) -> f64 {
-        (tdee + goal.calorie_adjustment()).max(1200.0)
+impl MealLog {
+    /// Replaces the meal's items and sets its nutrition to their totals.
+    pub fn set_items(&mut self, items: Vec<MealEntry>) {
+        let totals = ItemTotals::of(&items);
+        self.calories = totals.calories;
+        self.protein_g = totals.protein_g;
+        self.carbs_g = totals.carbs_g;
+        self.fat_g = totals.fat_g;
+        self.fiber_g = totals.fiber_g;
+        self.sugar_g = totals.sugar_g;
+        self.sodium_mg = totals.sodium_mg;
+        self.items = items;
     }
 
-    pub fn calculate_macros(daily_calories: f64, goal: &HealthGoal) -> (f64, f64, f64) {
-        match goal {
-            HealthGoal::LoseWeight => {
-                let protein_g = (daily_calories * 0.3) / 4.0;
-                let carbs_g = (daily_calories * 0.4) / 4.0;
-                let fat_g = (daily_calories * 0.3) / 9.0;
-                (protein_g, carbs_g, fat_g)
-            }
-            HealthGoal::BuildMuscle => {
-                let protein_g = (daily_calories * 0.35) / 4.0;
-                let carbs_g = (daily_calories * 0.4) / 4.0;
-                let fat_g = (daily_calories * 0.25) / 9.0;
-                (protein_g, carbs_g, fat_g)
-            }
-            _ => {
-                let protein_g = (daily_calories * 0.25) / 4.0;
-                let carbs_g = (daily_calories * 0.45) / 4.0;
-                let fat_g = (daily_calories * 0.3) / 9.0;
-                (protein_g, carbs_g, fat_g)
-            }
+    /// The meal's items, or the meal itself as its only item when it was
+    /// logged as a single food.
+    pub fn items_or_single(&self) -> Vec<MealEntry> {
+        if !self.items.is_empty() {
+            return self.items.clone();
         }
+        vec![MealEntry {
+            name: self.food_name.clone(),
+            quantity: self.serving_size.clone(),
+            calories: self.calories,
+            protein_g: self.protein_g,
+            carbs_g: self.carbs_g,
+            fat_g: self.fat_g,
+            fiber_g: self.fiber_g,
+            sugar_g: self.sugar_g,
+            sodium_mg: self.sodium_mg,
+            source: self.source,
+        }]
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum ReportPeriod {
-    Daily,
-    Weekly,
-    Monthly,
-    Yearly,
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MealChangeAction {
+    Updated,
+    Deleted,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum ReportStatus {
-    Generated,
-    Sent,
-    Failed,
+/// Who made a meal change: the user through the API, or the chat assistant
+/// through one of its tools.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MealChangeActor {
+    User,
+    Assistant,
+}
+
+/// Meal fields as recorded in its history. Update entries only hold the
+/// fields that changed; a deletion holds everything needed to log it again.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MealValues {
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub meal_type: Option<MealType>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub food_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub calories: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub protein_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub carbs_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fat_g: Option<f64>,
+    /// `Some(vec![])` records a meal without items.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub items: Option<Vec<MealEntry>>,
+    /// `Some(None)` records a value that was cleared.
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "double_option")]
+    pub serving_size: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "double_option")]
+    pub notes: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "double_option")]
+    pub portion_weight_g: Option<Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub source: Option<MealSource>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub verified: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "double_option")]
+    pub hunger_level: Option<Option<i32>>,
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "double_option")]
+    pub mood: Option<Option<MealMood>>,
+    #[serde(skip_serializing_if = "Option::is_none", default, deserialize_with = "double_option")]
+    pub eating_context: Option<Option<EatingContext>>,
 }
 
+impl From<&MealLog> for MealValues {
+    fn from(meal: &MealLog) -> Self {
+        MealValues {
+            date: Some(meal.date),
+            meal_type: Some(meal.meal_type.clone()),
+            food_name: Some(meal.food_name.clone()),
+            calories: Some(meal.calories),
+            protein_g: Some(meal.protein_g),
+            carbs_g: Some(meal.carbs_g),
+            fat_g: Some(meal.fat_g),
+            items: Some(meal.items.clone()),
+            serving_size: Some(meal.serving_size.clone()),
+            notes: Some(meal.notes.clone()),
+            portion_weight_g: Some(meal.portion_weight_g),
+            source: Some(meal.source),
+            verified: Some(meal.verified),
+            hunger_level: Some(meal.hunger_level),
+            mood: Some(meal.mood),
+            eating_context: Some(meal.eating_context),
+        }
+    }
+}
+
+/// Reads a present `null` as `Some(None)`, so a cleared value isn't mistaken
+/// for an unchanged one.
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where D: serde::Deserializer<'de>, T: Deserialize<'de>
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// One append-only entry in `meal_history`. Entries outlive the meal, so a
+/// deleted meal's values can still be recovered.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct MealReport {
-    #[serde(
-        rename = "_id",
-        skip_serializing_if = "Option::is_none",
-        serialize_with = "serialize_object_id_as_string"
-    )]
+pub struct MealHistoryEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    pub meal_id: ObjectId,
     pub user_id: ObjectId,
-    pub report_type: ReportPeriod,
-    pub start_date: String,
-    pub end_date: String,
-    pub generated_at: DateTime<Utc>,
-    pub status: ReportStatus,
+    pub action: MealChangeAction,
+    pub actor: MealChangeActor,
+    /// Chat session the assistant made the change in.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub chat_session_id: Option<ObjectId>,
+    pub old_values: MealValues,
+    /// Absent for deletions.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub new_values: Option<MealValues>,
+    /// The meal's version after the change.
+    pub version: i64,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MealHistoryEntryDto {
+    pub id: String,
+    pub action: MealChangeAction,
+    pub actor: MealChangeActor,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_session_id: Option<String>,
+    pub old_values: MealValues,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_values: Option<MealValues>,
+    pub version: i64,
+    pub created_at: String,
+}
+
+impl From<MealHistoryEntry> for MealHistoryEntryDto {
+    fn from(entry: MealHistoryEntry) -> Self {
+        MealHistoryEntryDto {
+            id: entry.id.map(|id| id.to_hex()).unwrap_or_default(),
+            action: entry.action,
+            actor: entry.actor,
+            chat_session_id: entry.chat_session_id.map(|id| id.to_hex()),
+            old_values: entry.old_values,
+            new_values: entry.new_values,
+            version: entry.version,
+            created_at: entry.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// A meal the user eats on a fixed schedule; the scheduler logs it automatically.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecurringMeal {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub meal_type: MealType,
+    pub food_name: String,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub source: MealSource,
+    /// ISO weekdays (1 = Monday ... 7 = Sunday); empty means every day.
+    #[serde(default)]
+    pub days_of_week: Vec<u32>,
+    /// UTC time of day (`HH:MM`) from which the meal is logged.
+    pub time_of_day: String,
+    pub active: bool,
+    /// Last UTC date (`YYYY-MM-DD`) the scheduler logged this meal.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub last_logged_date: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// A saved food the user logs repeatedly with one call.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FavoriteFood {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub food_name: String,
+    /// Used when logging without an explicit meal type.
+    pub meal_type: MealType,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub source: MealSource,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub portion_weight_g: Option<f64>,
+    #[serde(default)]
+    pub times_logged: i64,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub last_logged_at: Option<DateTime<Utc>>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// A food the user defined with their own macros, such as a homemade recipe
+/// or a local dish missing from FDC. Values are per serving.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomFood {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub name: String,
+    pub brand: Option<String>,
+    pub serving_size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub serving_weight_g: Option<f64>,
+    /// Named portions such as "1 piece" = 35 g; scaling them needs
+    /// `serving_weight_g`.
+    #[serde(default)]
+    pub serving_presets: Vec<ServingPreset>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fiber_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sugar_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sodium_mg: Option<f64>,
+    pub notes: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ServingPreset {
+    pub name: String,
+    pub grams: f64,
+}
+
+/// A week of AI-planned meals fitted to the user's targets, allergies and
+/// dietary preferences.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MealPlan {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    /// `YYYY-MM-DD` of the first day.
+    pub start_date: String,
+    pub days: Vec<MealPlanDay>,
+    /// Daily calorie target the plan was built for.
+    pub target_calories: f64,
+    /// `meal_plan@vN` of the most recent generation.
+    pub prompt_version: String,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MealPlanDay {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub meals: Vec<PlannedMeal>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlannedMeal {
+    pub meal_type: MealType,
+    pub food_name: String,
+    pub description: Option<String>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: Option<String>,
+    /// Ingredients for one serving. Empty on plans from `meal_plan@v1`.
+    #[serde(default)]
+    pub ingredients: Vec<PlannedIngredient>,
+    /// The meal logged when the user marked this as eaten.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub meal_log_id: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub eaten_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PlannedIngredient {
+    pub name: String,
+    pub quantity: f64,
+    /// `g`, `ml`, `tbsp`, `tsp`, `cup`, or empty for whole items.
+    pub unit: String,
+}
+
+/// Ingredients to buy for a meal plan or a set of recipes, merged by name.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShoppingList {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub meal_plan_id: Option<ObjectId>,
+    /// TheMealDB recipe IDs the list was built from.
+    #[serde(default)]
+    pub recipe_ids: Vec<String>,
+    pub items: Vec<ShoppingListItem>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShoppingListItem {
+    pub name: String,
+    /// One total per unit, since e.g. cups and grams can't be added up.
+    pub quantities: Vec<ItemQuantity>,
+    /// Measures that aren't a number and unit, such as "to taste".
+    #[serde(default)]
+    pub notes: Vec<String>,
+    /// Meals or recipes that need the ingredient.
+    #[serde(default)]
+    pub used_in: Vec<String>,
+    #[serde(default)]
+    pub checked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ItemQuantity {
+    pub amount: f64,
+    /// Empty for whole items.
+    pub unit: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExerciseIntensity {
+    Light,
+    Moderate,
+    Vigorous,
+}
+
+impl ExerciseIntensity {
+    /// Weight used to convert minutes into moderate-intensity-equivalent
+    /// minutes, following the WHO convention of counting vigorous minutes twice.
+    pub fn moderate_equivalent(&self) -> f64 {
+        match self {
+            ExerciseIntensity::Light => 0.5,
+            ExerciseIntensity::Moderate => 1.0,
+            ExerciseIntensity::Vigorous => 2.0,
+        }
+    }
+}
+
+/// A manually logged workout or activity session.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExerciseLog {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub activity: String,
+    pub duration_minutes: f64,
+    pub intensity: ExerciseIntensity,
+    pub calories_burned: Option<f64>,
+    #[serde(with = "bson_date")]
+    pub date: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// One stretch of time with the same goal, in `goal_history`. A new era
+/// starts whenever the profile's goal or goal preset changes, so progress is
+/// measured from the weight and towards the target set at that point.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GoalEra {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub goal: HealthGoal,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub goal_preset_id: Option<String>,
+    pub start_weight_kg: f64,
+    pub target_weight_kg: f64,
+    /// Daily calorie target when the era started.
+    pub daily_calories: f64,
+    #[serde(with = "bson_datetime")]
+    pub started_at: DateTime<Utc>,
+    /// `None` for the current era.
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+/// A single weigh-in. Reports and period stats derive weight change from these.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WeightLog {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub weight_kg: f64,
+    #[serde(with = "bson_date")]
+    pub date: DateTime<Utc>,
+    pub notes: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Copy of the FDC nutrient panel taken when the meal was logged, so per-meal
+/// micronutrient views don't change if the upstream entry is edited or removed.
+/// Amounts are as reported by FDC (per 100 g for most data types).
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct NutritionSnapshot {
+    pub fdc_id: i32,
+    pub description: String,
+    pub data_type: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub brand_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub gtin_upc: Option<String>,
+    pub nutrients: Vec<SnapshotNutrient>,
+    #[serde(with = "bson_datetime")]
+    pub captured_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SnapshotNutrient {
+    pub number: String,
+    pub name: String,
+    pub unit: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MealType {
+    Breakfast,
+    Lunch,
+    Dinner,
+    Snack,
+}
+
+impl MealType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MealType::Breakfast => "breakfast",
+            MealType::Lunch => "lunch",
+            MealType::Dinner => "dinner",
+            MealType::Snack => "snack",
+        }
+    }
+}
+
+/// How the user felt when eating, for spotting emotional eating.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MealMood {
+    Happy,
+    Calm,
+    Neutral,
+    Bored,
+    Tired,
+    Stressed,
+    Anxious,
+    Sad,
+}
+
+impl MealMood {
+    /// Moods commonly behind eating without hunger.
+    pub fn is_negative(&self) -> bool {
+        matches!(self, MealMood::Bored | MealMood::Tired | MealMood::Stressed | MealMood::Anxious | MealMood::Sad)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MealMood::Happy => "happy",
+            MealMood::Calm => "calm",
+            MealMood::Neutral => "neutral",
+            MealMood::Bored => "bored",
+            MealMood::Tired => "tired",
+            MealMood::Stressed => "stressed",
+            MealMood::Anxious => "anxious",
+            MealMood::Sad => "sad",
+        }
+    }
+}
+
+/// Where or how the meal was eaten.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EatingContext {
+    Home,
+    Work,
+    Restaurant,
+    Social,
+    OnTheGo,
+    /// In front of the TV, phone or computer.
+    Screen,
+}
+
+impl EatingContext {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EatingContext::Home => "home",
+            EatingContext::Work => "work",
+            EatingContext::Restaurant => "restaurant",
+            EatingContext::Social => "social",
+            EatingContext::OnTheGo => "on_the_go",
+            EatingContext::Screen => "screen",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MealSource {
+    #[default]
+    Manual,
+    AiImage,
+    AiText,
+    Fdc,
+    Barcode,
+    Recipe,
+    MealPlan,
+    CustomFood,
+}
+
+impl MealSource {
+    /// Entries backed by a curated nutrition database rather than user input or AI estimates.
+    /// Only the server sets these, for foods it looked up itself.
+    pub fn is_verified(&self) -> bool {
+        matches!(self, MealSource::Fdc | MealSource::Barcode)
+    }
+
+    /// The source to record for nutrition the server didn't look up itself:
+    /// a claimed database source becomes manual.
+    pub fn unverified(self) -> MealSource {
+        if self.is_verified() { MealSource::Manual } else { self }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MealSource::Manual => "manual",
+            MealSource::AiImage => "ai_image",
+            MealSource::AiText => "ai_text",
+            MealSource::Fdc => "fdc",
+            MealSource::Barcode => "barcode",
+            MealSource::Recipe => "recipe",
+            MealSource::MealPlan => "meal_plan",
+            MealSource::CustomFood => "custom_food",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct DailyProgress {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    #[serde(with = "bson_date")]
+    pub date: DateTime<Utc>,
+    pub total_calories: f64,
+    pub total_protein_g: f64,
+    pub total_carbs_g: f64,
+    pub total_fat_g: f64,
+    /// Meals counted in the totals above.
+    #[serde(default)]
+    pub meal_count: i64,
+    pub water_ml: Option<f64>,
+    pub weight_kg: Option<f64>,
+    pub notes: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Meal totals for one UTC day, stored inside [`MonthlyStats`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DayTotals {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub meal_count: usize,
+}
+
+/// Precomputed aggregates for one completed calendar month (UTC). Only
+/// completed months are stored; meal changes in a month delete its document
+/// so it's recomputed on the next read.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonthlyStats {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    /// `YYYY-MM`.
+    pub month: String,
+    pub total_days: usize,
+    pub days_logged: usize,
+    pub total_meals: usize,
+    pub total_calories: f64,
+    pub total_protein_g: f64,
+    pub total_carbs_g: f64,
+    pub total_fat_g: f64,
+    /// Per logged day.
+    pub avg_calories: f64,
+    pub avg_protein_g: f64,
+    pub avg_carbs_g: f64,
+    pub avg_fat_g: f64,
+    /// The profile's targets when the month was computed.
+    pub target_calories: f64,
+    pub target_protein_g: f64,
+    pub target_carbs_g: f64,
+    pub target_fat_g: f64,
+    pub calories_compliance_percent: f64,
+    pub protein_compliance_percent: f64,
+    pub carbs_compliance_percent: f64,
+    pub fat_compliance_percent: f64,
+    /// Logged days within 10% of the calorie target.
+    pub days_on_target: usize,
+    pub longest_streak: usize,
+    /// Consecutive logged days from the 1st and up to the last day, for
+    /// joining streaks across months.
+    pub streak_at_start: usize,
+    pub streak_at_end: usize,
+    /// Logged days only, oldest first.
+    pub days: Vec<DayTotals>,
+    #[serde(with = "bson_datetime")]
+    pub computed_at: DateTime<Utc>,
+}
+
+impl HealthProfile {
+    pub fn calculate_bmi(weight_kg: f64, height_cm: f64) -> f64 {
+        let height_m = height_cm / 100.0;
+        weight_kg / (height_m * height_m)
+    }
+
+    pub fn bmi_category(bmi: f64) -> String {
+        match bmi {
+            bmi if bmi < 18.5 => "Underweight".to_string(),
+            bmi if bmi < 25.0 => "Normal".to_string(),
+            bmi if bmi < 30.0 => "Overweight".to_string(),
+            _ => "Obese".to_string(),
+        }
+    }
+
+    pub fn calculate_bmr(weight_kg: f64, height_cm: f64, age: i32, gender: &Gender) -> f64 {
+        match gender {
+            Gender::Male => 10.0 * weight_kg + 6.25 * height_cm - 5.0 * (age as f64) + 5.0,
+            Gender::Female => 10.0 * weight_kg + 6.25 * height_cm - 5.0 * (age as f64) - 161.0,
+        }
+    }
+
+    pub fn calculate_tdee(bmr: f64, activity_level: &ActivityLevel) -> f64 {
+        bmr * activity_level.multiplier()
+    }
+
+    pub fn calculate_daily_calories(tdee: f64, goal: &HealthGoal) -> f64 {
+        (tdee + goal.calorie_adjustment()).max(1200.0)
+    }
+
+    /// Daily calories and (protein, carbs, fat) grams for this profile at
+    /// `tdee`, honouring its goal preset and macro strategy.
+    pub fn daily_targets(&self, tdee: f64) -> (f64, (f64, f64, f64)) {
+        let daily_calories = match &self.goal_preset {
+            Some(preset) => (tdee + preset.calorie_adjustment).max(1200.0),
+            None => Self::calculate_daily_calories(tdee, &self.goal),
+        };
+        let macros = self.macro_strategy.calculate(
+            daily_calories,
+            self.weight_kg,
+            &self.goal,
+            self.goal_preset.as_ref()
+        );
+        (daily_calories, macros)
+    }
+
+    /// Calorie, protein, carbs and fat targets for `date`: the target cycle's
+    /// entry for its weekday, or the flat daily targets.
+    pub fn targets_on(&self, date: NaiveDate) -> (f64, f64, f64, f64) {
+        let weekday = date.weekday().number_from_monday();
+        match self.target_cycle.iter().find(|day| day.days_of_week.contains(&weekday)) {
+            Some(day) => (day.calories, day.protein_g, day.carbs_g, day.fat_g),
+            None => (self.daily_calories, self.daily_protein_g, self.daily_carbs_g, self.daily_fat_g),
+        }
+    }
+
+    /// Mean targets over `days`, e.g. the days logged in a period, to compare
+    /// with the average intake on those days. The flat daily targets when
+    /// there's no cycle or no days.
+    pub fn average_targets(&self, days: impl IntoIterator<Item = NaiveDate>) -> (f64, f64, f64, f64) {
+        let flat = (self.daily_calories, self.daily_protein_g, self.daily_carbs_g, self.daily_fat_g);
+        if self.target_cycle.is_empty() {
+            return flat;
+        }
+
+        let (sum, n) = days
+            .into_iter()
+            .map(|day| self.targets_on(day))
+            .fold(((0.0, 0.0, 0.0, 0.0), 0.0), |((c, p, cb, f), n), (dc, dp, dcb, df)| {
+                ((c + dc, p + dp, cb + dcb, f + df), n + 1.0)
+            });
+        if n == 0.0 {
+            return flat;
+        }
+        (sum.0 / n, sum.1 / n, sum.2 / n, sum.3 / n)
+    }
+
+    pub fn calculate_macros(daily_calories: f64, goal: &HealthGoal) -> (f64, f64, f64) {
+        match goal {
+            HealthGoal::LoseWeight => {
+                let protein_g = (daily_calories * 0.3) / 4.0;
+                let carbs_g = (daily_calories * 0.4) / 4.0;
+                let fat_g = (daily_calories * 0.3) / 9.0;
+                (protein_g, carbs_g, fat_g)
+            }
+            HealthGoal::BuildMuscle => {
+                let protein_g = (daily_calories * 0.35) / 4.0;
+                let carbs_g = (daily_calories * 0.4) / 4.0;
+                let fat_g = (daily_calories * 0.25) / 9.0;
+                (protein_g, carbs_g, fat_g)
+            }
+            _ => {
+                let protein_g = (daily_calories * 0.25) / 4.0;
+                let carbs_g = (daily_calories * 0.45) / 4.0;
+                let fat_g = (daily_calories * 0.3) / 9.0;
+                (protein_g, carbs_g, fat_g)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub enum ReportPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl ReportPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportPeriod::Daily => "daily",
+            ReportPeriod::Weekly => "weekly",
+            ReportPeriod::Monthly => "monthly",
+            ReportPeriod::Yearly => "yearly",
+        }
+    }
+
+    /// Parses `as_str` output, ignoring case.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "daily" => Some(ReportPeriod::Daily),
+            "weekly" => Some(ReportPeriod::Weekly),
+            "monthly" => Some(ReportPeriod::Monthly),
+            "yearly" => Some(ReportPeriod::Yearly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub enum ReportStatus {
+    /// Queued; the statistics below are placeholders until the job finishes.
+    Pending,
+    Generated,
+    Sent,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFrequency {
+    Weekly,
+    Monthly,
+}
+
+impl ReportFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportFrequency::Weekly => "weekly",
+            ReportFrequency::Monthly => "monthly",
+        }
+    }
+
+    pub fn period(&self) -> ReportPeriod {
+        match self {
+            ReportFrequency::Weekly => ReportPeriod::Weekly,
+            ReportFrequency::Monthly => ReportPeriod::Monthly,
+        }
+    }
+}
+
+/// Opt-in for a report emailed after every completed week or month.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportSchedule {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub frequency: ReportFrequency,
+    pub language: String,
+    pub active: bool,
+    /// Last day (`YYYY-MM-DD`) of the most recent period a report was sent for.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub last_period_end: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MealReport {
+    #[serde(
+        rename = "_id",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_object_id_as_string"
+    )]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub report_type: ReportPeriod,
+    pub start_date: String,
+    pub end_date: String,
+    pub generated_at: DateTime<Utc>,
+    pub status: ReportStatus,
 
     pub total_days: usize,
     pub days_logged: usize,
@@ -529,11 +1677,43 @@ pub struct MealReport {
     pub weight_change: Option<f64>,
     pub target_weight: Option<f64>,
     pub weight_goal_achieved: Option<bool>,
+    /// Day the goal was changed, when it changed during the period; the weight
+    /// figures cover only the days since.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub goal_started_on: Option<String>,
 
     pub best_day_date: Option<String>,
     pub best_day_compliance: Option<f64>,
     pub streak_days: usize,
     pub notes: Option<String>,
+    #[serde(default)]
+    pub coach_commentary: Option<String>,
+    /// Template version (`name@vN`) the commentary was generated with.
+    #[serde(default)]
+    pub coach_prompt_version: Option<String>,
+    #[serde(default)]
+    pub data_quality: Vec<ReportAnomaly>,
+    /// Why generation failed, when `status` is `Failed` before any data was computed.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+    /// Days were counted at this UTC offset (minutes east); absent means UTC.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub utc_offset_minutes: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    VeryLowIntake,
+    ImplausibleIntake,
+    LoggingGap,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportAnomaly {
+    pub kind: AnomalyKind,
+    pub date: String,
+    pub detail: String,
 }
 
 // ==================== Chat Models ====================
@@ -559,17 +1739,28 @@ pub struct ChatMessage {
     pub user_id: ObjectId,
     pub role: MessageRole,
     pub content: String,
+    /// Legacy inline `data:` URL; new images are stored under `image_id`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_url: Option<String>,
+    /// Photo attached to the message, in the `chat_images` GridFS bucket.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub image_id: Option<ObjectId>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_results: Option<Vec<ToolResult>>,
+    /// Set when a user reports the message; flagged content is kept out of the
+    /// agent's context and out of exports.
+    #[serde(default)]
+    pub flagged: bool,
+    /// Template version(s) behind an assistant reply, e.g. `chat_system@v1`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub prompt_version: Option<String>,
     #[serde(with = "bson_datetime")]
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum MessageRole {
     User,
@@ -589,3 +1780,602 @@ pub struct ToolResult {
     pub result: serde_json::Value,
     pub success: bool,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Milestone {
+    FirstPerfectWeek,
+    TargetWeightReached,
+    ThirtyDayStreak,
+}
+
+impl Milestone {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Milestone::FirstPerfectWeek => "first_perfect_week",
+            Milestone::TargetWeightReached => "target_weight_reached",
+            Milestone::ThirtyDayStreak => "thirty_day_streak",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Milestone::FirstPerfectWeek => "First perfect week! 🎉",
+            Milestone::TargetWeightReached => "Target weight reached! 🏆",
+            Milestone::ThirtyDayStreak => "30-day streak! 🔥",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Milestone::FirstPerfectWeek =>
+                "You logged your meals every single day for a whole week. Consistency like this is what makes goals happen!",
+            Milestone::TargetWeightReached =>
+                "You've reached your target weight. That's a huge achievement - take a moment to celebrate how far you've come!",
+            Milestone::ThirtyDayStreak =>
+                "30 days of logging in a row! Tracking has officially become a habit. Keep the streak alive!",
+        }
+    }
+}
+
+/// A user's streaks and badges, one document per user in `achievements`.
+/// Streaks are recomputed from the meal logs on every refresh; badges are
+/// only ever added.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserAchievements {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    /// Consecutive days with at least one meal logged.
+    pub logging_streak: Streak,
+    /// Consecutive days with calories within 10% of the day's target.
+    pub goal_streak: Streak,
+    pub badges: Vec<Badge>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Streak {
+    /// Ending today, or yesterday before anything counts today.
+    pub current_days: usize,
+    pub longest_days: usize,
+    /// Length of the next badge for this streak not earned yet, if any is left.
+    pub next_badge_days: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Badge {
+    /// E.g. `logging_streak_7`.
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    #[serde(with = "bson_datetime")]
+    pub earned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Milestone,
+    ReportReady,
+    MealReminder,
+    StreakAtRisk,
+    HydrationNudge,
+}
+
+impl NotificationKind {
+    /// Low-priority notifications wait for the daily digest.
+    pub fn priority(&self) -> NotificationPriority {
+        match self {
+            NotificationKind::Milestone => NotificationPriority::Low,
+            NotificationKind::ReportReady
+            | NotificationKind::MealReminder
+            | NotificationKind::StreakAtRisk
+            | NotificationKind::HydrationNudge => NotificationPriority::High,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationPriority {
+    High,
+    Low,
+}
+
+/// Channels outside the in-app inbox, which always receives every notification.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Push,
+    Email,
+}
+
+/// When and where the user wants notifications. Times are `HH:MM` in the
+/// user's local time.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct NotificationPreferences {
+    /// Minutes east of UTC, e.g. 420 for UTC+7. A fixed offset, so clients
+    /// send the new one when daylight saving time changes it.
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// No push notifications from `quiet_hours_start` until
+    /// `quiet_hours_end`; they are held until it ends. Both or neither.
+    #[serde(default)]
+    pub quiet_hours_start: Option<String>,
+    #[serde(default)]
+    pub quiet_hours_end: Option<String>,
+    #[serde(default = "default_true")]
+    pub push_enabled: bool,
+    /// Sends the daily digest by email as well as by push.
+    #[serde(default)]
+    pub email_digest_enabled: bool,
+    /// When the day's low-priority notifications are sent as one digest.
+    #[serde(default = "default_digest_time")]
+    pub digest_time: String,
+    /// Changed through its own endpoint, so updating the other preferences
+    /// leaves it alone.
+    #[serde(default)]
+    pub reminders: ReminderPreferences,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            utc_offset_minutes: 0,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            push_enabled: true,
+            email_digest_enabled: false,
+            digest_time: default_digest_time(),
+            reminders: ReminderPreferences::default(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_digest_time() -> String {
+    "18:00".to_string()
+}
+
+/// Reminders the scheduler sends at the user's local times. Each one is off
+/// until a time is set, and none is sent during quiet hours.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ReminderPreferences {
+    /// Where reminders go besides the in-app inbox.
+    #[serde(default = "default_reminder_channels")]
+    pub channels: Vec<NotificationChannel>,
+    /// Sent when the meal type hasn't been logged today by `time`.
+    #[serde(default)]
+    pub meals: Vec<MealReminder>,
+    /// Sent when yesterday extended a logging streak and today has no meals yet.
+    #[serde(default)]
+    pub streak_alert_time: Option<String>,
+    /// Sent at each time while the day's water is below target.
+    #[serde(default)]
+    pub hydration_times: Vec<String>,
+}
+
+impl Default for ReminderPreferences {
+    fn default() -> Self {
+        Self {
+            channels: default_reminder_channels(),
+            meals: Vec::new(),
+            streak_alert_time: None,
+            hydration_times: Vec::new(),
+        }
+    }
+}
+
+impl ReminderPreferences {
+    pub fn any_enabled(&self) -> bool {
+        !self.meals.is_empty() || self.streak_alert_time.is_some() || !self.hydration_times.is_empty()
+    }
+}
+
+fn default_reminder_channels() -> Vec<NotificationChannel> {
+    vec![NotificationChannel::Push]
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MealReminder {
+    pub meal_type: MealType,
+    /// `HH:MM`, local time.
+    pub time: String,
+}
+
+/// A notification waiting to go out on one channel, either when quiet hours
+/// end or with the next digest.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueuedNotification {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub notification_id: ObjectId,
+    pub channel: NotificationChannel,
+    pub digest: bool,
+    #[serde(with = "bson_date")]
+    pub deliver_after: DateTime<Utc>,
+    /// Set by the delivery job that picked the entry up.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub claim_id: Option<ObjectId>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Notification {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub milestone: Option<Milestone>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub report_id: Option<ObjectId>,
+    #[serde(default)]
+    pub read: bool,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl MergeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeStatus::Pending => "pending",
+            MergeStatus::Running => "running",
+            MergeStatus::Completed => "completed",
+            MergeStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountMerge {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub primary_user_id: ObjectId,
+    pub secondary_user_id: ObjectId,
+    pub secondary_email: String,
+    pub status: MergeStatus,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub current_step: Option<String>,
+    pub completed_steps: i32,
+    pub total_steps: i32,
+    #[serde(default)]
+    pub migrated: std::collections::BTreeMap<String, i64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageReportReason {
+    Harmful,
+    Incorrect,
+    Offensive,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageReportStatus {
+    Open,
+    Upheld,
+    Dismissed,
+}
+
+impl MessageReportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageReportStatus::Open => "open",
+            MessageReportStatus::Upheld => "upheld",
+            MessageReportStatus::Dismissed => "dismissed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageReport {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub message_id: ObjectId,
+    pub session_id: ObjectId,
+    pub reporter_id: ObjectId,
+    pub reason: MessageReportReason,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub details: Option<String>,
+    pub message_excerpt: String,
+    pub status: MessageReportStatus,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reviewed_by: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub review_note: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditLog {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub actor_id: ObjectId,
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub target_id: Option<ObjectId>,
+    #[serde(default)]
+    pub details: mongodb::bson::Document,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl ExportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportStatus::Pending => "pending",
+            ExportStatus::Running => "running",
+            ExportStatus::Completed => "completed",
+            ExportStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Aggregated, de-identified dataset for internal research. Holds no user ids,
+/// names or emails; cohorts smaller than `k_threshold` are suppressed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResearchExport {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub requested_by: ObjectId,
+    pub status: ExportStatus,
+    pub start_date: String,
+    pub end_date: String,
+    pub k_threshold: i32,
+    #[serde(default)]
+    pub cohorts: Vec<ResearchCohort>,
+    #[serde(default)]
+    pub included_users: i32,
+    #[serde(default)]
+    pub suppressed_users: i32,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// One group of users sharing the same generalized quasi-identifiers.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ResearchCohort {
+    pub age_band: String,
+    pub gender: String,
+    pub goal: String,
+    pub bmi_category: String,
+    pub user_count: i32,
+    pub avg_days_logged: f64,
+    pub avg_daily_calories: f64,
+    pub avg_daily_protein_g: f64,
+    pub avg_daily_carbs_g: f64,
+    pub avg_daily_fat_g: f64,
+    /// Average of (daily calories / calorie target) across the cohort.
+    pub avg_calorie_target_ratio: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RecomputeStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl RecomputeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecomputeStatus::Pending => "pending",
+            RecomputeStatus::Running => "running",
+            RecomputeStatus::Completed => "completed",
+            RecomputeStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Derived data a recompute job can rebuild, in the order it runs them.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RecomputeStep {
+    /// Meal totals in `daily_progress`, from `meal_logs`.
+    DailyProgress,
+    /// Stored `monthly_stats` of completed months.
+    MonthlyStats,
+    /// Streak milestones missed by earlier rollups.
+    Milestones,
+    /// Statistics of generated reports; their commentary is kept.
+    Reports,
+}
+
+impl RecomputeStep {
+    pub const ALL: [RecomputeStep; 4] = [
+        RecomputeStep::DailyProgress,
+        RecomputeStep::MonthlyStats,
+        RecomputeStep::Milestones,
+        RecomputeStep::Reports,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecomputeStep::DailyProgress => "daily_progress",
+            RecomputeStep::MonthlyStats => "monthly_stats",
+            RecomputeStep::Milestones => "milestones",
+            RecomputeStep::Reports => "reports",
+        }
+    }
+}
+
+/// Admin job rebuilding derived data for one user or everyone, e.g. after a
+/// bug fix. Users are processed in `_id` order, in batches; every step
+/// overwrites what it derives, so running a job again is harmless.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecomputeJob {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub requested_by: ObjectId,
+    /// `None` recomputes every user.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub user_id: Option<ObjectId>,
+    pub steps: Vec<RecomputeStep>,
+    pub status: RecomputeStatus,
+    #[serde(default)]
+    pub total_users: i64,
+    #[serde(default)]
+    pub processed_users: i64,
+    /// Last user of the last finished batch.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub last_user_id: Option<ObjectId>,
+    /// Documents rewritten per step, keyed by `RecomputeStep::as_str`.
+    #[serde(default)]
+    pub updated: std::collections::BTreeMap<String, i64>,
+    /// Users a step failed for (first 100); the job carries on without them.
+    #[serde(default)]
+    pub failed_user_ids: Vec<ObjectId>,
+    #[serde(default)]
+    pub failed_users: i64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "bson_datetime")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// What a managed API key may call. Each area has a read scope (`GET`,
+/// `HEAD`) and a write scope (every other method); `admin` covers
+/// `/api/admin/*`. Keys from `API_KEYS` have every scope.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+pub enum ApiKeyScope {
+    #[serde(rename = "account:read")]
+    AccountRead,
+    #[serde(rename = "account:write")]
+    AccountWrite,
+    #[serde(rename = "nutrition:read")]
+    NutritionRead,
+    #[serde(rename = "nutrition:write")]
+    NutritionWrite,
+    #[serde(rename = "health:read")]
+    HealthRead,
+    #[serde(rename = "health:write")]
+    HealthWrite,
+    #[serde(rename = "meals:read")]
+    MealsRead,
+    #[serde(rename = "meals:write")]
+    MealsWrite,
+    #[serde(rename = "reports:read")]
+    ReportsRead,
+    #[serde(rename = "reports:write")]
+    ReportsWrite,
+    #[serde(rename = "chat:read")]
+    ChatRead,
+    #[serde(rename = "chat:write")]
+    ChatWrite,
+    #[serde(rename = "notifications:read")]
+    NotificationsRead,
+    #[serde(rename = "notifications:write")]
+    NotificationsWrite,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl ApiKeyScope {
+    pub const ALL: [ApiKeyScope; 15] = [
+        ApiKeyScope::AccountRead,
+        ApiKeyScope::AccountWrite,
+        ApiKeyScope::NutritionRead,
+        ApiKeyScope::NutritionWrite,
+        ApiKeyScope::HealthRead,
+        ApiKeyScope::HealthWrite,
+        ApiKeyScope::MealsRead,
+        ApiKeyScope::MealsWrite,
+        ApiKeyScope::ReportsRead,
+        ApiKeyScope::ReportsWrite,
+        ApiKeyScope::ChatRead,
+        ApiKeyScope::ChatWrite,
+        ApiKeyScope::NotificationsRead,
+        ApiKeyScope::NotificationsWrite,
+        ApiKeyScope::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::AccountRead => "account:read",
+            ApiKeyScope::AccountWrite => "account:write",
+            ApiKeyScope::NutritionRead => "nutrition:read",
+            ApiKeyScope::NutritionWrite => "nutrition:write",
+            ApiKeyScope::HealthRead => "health:read",
+            ApiKeyScope::HealthWrite => "health:write",
+            ApiKeyScope::MealsRead => "meals:read",
+            ApiKeyScope::MealsWrite => "meals:write",
+            ApiKeyScope::ReportsRead => "reports:read",
+            ApiKeyScope::ReportsWrite => "reports:write",
+            ApiKeyScope::ChatRead => "chat:read",
+            ApiKeyScope::ChatWrite => "chat:write",
+            ApiKeyScope::NotificationsRead => "notifications:read",
+            ApiKeyScope::NotificationsWrite => "notifications:write",
+            ApiKeyScope::Admin => "admin",
+        }
+    }
+}
+
+/// API key managed by operators through `/api/admin/api-keys`. Only a hash
+/// of the key is stored; the key itself is shown once, when issued.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKeyRecord {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    /// SHA-256 of the key, hex.
+    pub key_hash: String,
+    /// First characters of the key, to tell keys apart in listings.
+    pub key_prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_by: ObjectId,
+    #[serde(with = "bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub rotated_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none", default, with = "bson_datetime_option")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
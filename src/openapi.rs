@@ -0,0 +1,382 @@
+use utoipa::{
+    openapi::security::{ ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme },
+    Modify,
+    OpenApi,
+};
+
+use crate::{ handlers, models };
+
+/// The API's OpenAPI document, built from the `#[utoipa::path]` annotations on
+/// the handlers. A route missing from `paths` is missing from `/docs`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Alimentify API",
+        description = "Errors are JSON with `error`, a stable `code` and a localized `message`; validation errors add `fields`."
+    ),
+    paths(
+        handlers::auth::logout,
+        handlers::auth::get_current_user,
+        handlers::account::start_account_merge,
+        handlers::account::get_account_merge,
+        handlers::account::get_retention,
+        handlers::account::update_retention,
+        handlers::account::export_account_data,
+        handlers::account::delete_account,
+        handlers::account::delete_ai_data,
+        handlers::nutrition::analyze_food,
+        handlers::nutrition::analyze_food_text,
+        handlers::nutrition::quick_food_check,
+        handlers::nutrition_info::get_nutrition_info,
+        handlers::food_wiki::search_foods,
+        handlers::food_wiki::get_food_details,
+        handlers::food_wiki::get_scaled_food,
+        handlers::food_wiki::get_foods,
+        handlers::recipes::search_recipes,
+        handlers::recipes::get_random_recipes,
+        handlers::recipes::get_recipe_by_id,
+        handlers::meals::log_recipe,
+        handlers::recipes::filter_by_category,
+        handlers::recipes::filter_by_area,
+        handlers::health::create_or_update_profile,
+        handlers::health::get_profile,
+        handlers::health::update_profile,
+        handlers::health::get_health_summary,
+        handlers::health::save_survey_draft,
+        handlers::health::get_survey_draft,
+        handlers::health::submit_survey_draft,
+        handlers::health::get_goal_presets,
+        handlers::health::apply_goal_preset,
+        handlers::health::get_goal_history,
+        handlers::health::get_goal_era,
+        handlers::health::set_target_cycle,
+        handlers::activity::get_activity_suggestion,
+        handlers::activity::apply_activity_suggestion,
+        handlers::meals::log_meal,
+        handlers::meals::log_meal_from_image,
+        handlers::meals::log_favorite,
+        handlers::meals::log_custom_food,
+        handlers::meals::log_fdc_food,
+        handlers::meals::log_barcode_food,
+        handlers::meals::get_daily_meals,
+        handlers::meals::search_meals,
+        handlers::meals::get_period_stats,
+        handlers::meals::get_source_stats,
+        handlers::meals::get_mood_stats,
+        handlers::meals::get_monthly_stats,
+        handlers::meals::get_compliance_score,
+        handlers::meals::export_diary_pdf,
+        handlers::meals::copy_day,
+        handlers::recurring_meals::create_recurring_meal,
+        handlers::recurring_meals::get_recurring_meals,
+        handlers::recurring_meals::delete_recurring_meal,
+        handlers::recurring_meals::undo_auto_logged_meal,
+        handlers::meals::get_meal_history,
+        handlers::meals::repeat_meal,
+        handlers::meals::update_meal,
+        handlers::meals::delete_meal,
+        handlers::meals::add_meal_item,
+        handlers::meals::update_meal_item,
+        handlers::meals::delete_meal_item,
+        handlers::favorites::create_favorite,
+        handlers::favorites::get_favorites,
+        handlers::favorites::delete_favorite,
+        handlers::custom_foods::create_custom_food,
+        handlers::custom_foods::get_custom_foods,
+        handlers::custom_foods::get_custom_food,
+        handlers::custom_foods::update_custom_food,
+        handlers::custom_foods::delete_custom_food,
+        handlers::meal_plans::get_meal_plans,
+        handlers::meal_plans::generate_meal_plan,
+        handlers::meal_plans::get_meal_plan,
+        handlers::meal_plans::regenerate_meal_plan_day,
+        handlers::meals::log_planned_meal,
+        handlers::shopping_lists::create_shopping_list,
+        handlers::shopping_lists::get_shopping_lists,
+        handlers::shopping_lists::get_shopping_list,
+        handlers::shopping_lists::delete_shopping_list,
+        handlers::shopping_lists::update_shopping_list_item,
+        handlers::suggestions::get_snack_suggestions,
+        handlers::weight::log_weight,
+        handlers::weight::get_weight_history,
+        handlers::weight::delete_weight_log,
+        handlers::water::log_water,
+        handlers::water::get_daily_water,
+        handlers::activity::log_exercise,
+        handlers::activity::get_exercise_logs,
+        handlers::activity::delete_exercise_log,
+        handlers::reports::generate_report,
+        handlers::reports::schedule_report,
+        handlers::reports::get_user_reports,
+        handlers::reports::get_report_by_id,
+        handlers::reports::get_report_status,
+        handlers::reports::delete_report,
+        handlers::chat::create_chat_session,
+        handlers::chat::get_chat_sessions,
+        handlers::chat::get_chat_session,
+        handlers::chat::update_chat_session,
+        handlers::chat::delete_chat_session,
+        handlers::chat::send_message,
+        handlers::chat::get_chat_messages,
+        handlers::chat::report_message,
+        handlers::chat::get_quick_actions,
+        handlers::notifications::get_notifications,
+        handlers::notifications::get_notification_preferences,
+        handlers::notifications::update_notification_preferences,
+        handlers::notifications::get_reminder_preferences,
+        handlers::notifications::update_reminder_preferences,
+        handlers::notifications::mark_all_notifications_read,
+        handlers::notifications::mark_notification_read,
+        handlers::widget::create_widget_token,
+        handlers::widget::revoke_widget_token,
+        handlers::achievements::get_achievements,
+        handlers::gateway::link_whatsapp,
+        handlers::gateway::unlink_whatsapp,
+        handlers::admin::get_message_reports,
+        handlers::admin::review_message_report,
+        handlers::admin::get_cache_stats,
+        handlers::admin::get_latency_report,
+        handlers::admin::get_config,
+        handlers::admin::seed_demo_account,
+        handlers::admin::create_recompute_job,
+        handlers::admin::get_recompute_job,
+        handlers::admin::create_api_key,
+        handlers::admin::get_api_keys,
+        handlers::admin::rotate_api_key,
+        handlers::admin::revoke_api_key,
+        handlers::research::create_research_export,
+        handlers::research::get_research_export,
+        handlers::branding::get_branding,
+        handlers::auth::google_auth_url,
+        handlers::auth::google_callback,
+        handlers::auth::verify_email,
+        handlers::auth::resend_verification,
+        handlers::auth::exchange_auth_code,
+        handlers::auth::refresh_token,
+        handlers::widget::get_widget_summary,
+        handlers::images::get_image,
+        handlers::gateway::receive_inbound,
+        handlers::live::live_updates,
+        handlers::status::status_check,
+        handlers::status::provider_status
+    ),
+    components(
+        schemas(
+            models::UserRole,
+            models::UserResponse,
+            models::Gender,
+            models::ActivityLevel,
+            models::HealthGoal,
+            models::MacroStrategy,
+            models::BloodPressure,
+            models::MedicalCondition,
+            models::CycleDayTargets,
+            models::DietaryPreference,
+            models::MealEntry,
+            models::MealChangeAction,
+            models::MealChangeActor,
+            models::ServingPreset,
+            models::PlannedIngredient,
+            models::ItemQuantity,
+            models::ExerciseIntensity,
+            models::NutritionSnapshot,
+            models::SnapshotNutrient,
+            models::MealType,
+            models::MealMood,
+            models::EatingContext,
+            models::MealSource,
+            models::ReportPeriod,
+            models::ReportStatus,
+            models::ReportFrequency,
+            models::AnomalyKind,
+            models::MessageRole,
+            models::Milestone,
+            models::NotificationKind,
+            models::NotificationPriority,
+            models::NotificationChannel,
+            models::NotificationPreferences,
+            models::ReminderPreferences,
+            models::MealReminder,
+            models::MergeStatus,
+            models::MessageReportReason,
+            models::MessageReportStatus,
+            models::ExportStatus,
+            models::ResearchCohort,
+            models::RecomputeStatus,
+            models::RecomputeStep,
+            models::ApiKeyScope,
+            handlers::status::ProviderStatusResponse,
+            handlers::branding::BrandingResponse,
+            handlers::auth::AuthUrlResponse,
+            handlers::auth::ResendVerificationRequest,
+            handlers::auth::ExchangeAuthCodeRequest,
+            handlers::auth::ExchangeAuthCodeResponse,
+            handlers::auth::RefreshTokenRequest,
+            handlers::auth::RefreshTokenResponse,
+            handlers::nutrition::NutritionAnalysisResponse,
+            handlers::nutrition::FoodTextRequest,
+            handlers::nutrition::QuickCheckResponse,
+            handlers::nutrition::ImageUpload,
+            handlers::food_wiki::GetFoodsRequest,
+            handlers::recipes::ConflictMode,
+            handlers::health::CreateHealthProfileRequest,
+            handlers::health::HealthProfileResponse,
+            handlers::health::HealthSurveyAnswers,
+            handlers::health::HealthSurveyDraftResponse,
+            handlers::health::UpdateHealthProfileRequest,
+            handlers::health::GoalPresetsResponse,
+            handlers::health::GoalEraDto,
+            handlers::health::GoalHistoryResponse,
+            handlers::health::GoalEraResponse,
+            handlers::health::TargetCycleRequest,
+            handlers::health::HealthSummaryResponse,
+            handlers::meals::LogMealRequest,
+            handlers::meals::LogFavoriteRequest,
+            handlers::meals::LogCustomFoodRequest,
+            handlers::meals::LogFdcFoodRequest,
+            handlers::meals::LogRecipeRequest,
+            handlers::meals::LogPlannedMealRequest,
+            handlers::meals::RepeatMealRequest,
+            handlers::meals::CopyDayRequest,
+            handlers::meals::MealLogDto,
+            handlers::meals::MealLogResponse,
+            handlers::meals::CopyDayResponse,
+            handlers::meals::SearchMealsResponse,
+            handlers::meals::DailyTotals,
+            handlers::meals::LogFromImageForm,
+            handlers::meals::LogFromImageResponse,
+            handlers::meals::PeriodStatsResponse,
+            handlers::meals::DailyDataPoint,
+            handlers::meals::PeriodAverages,
+            handlers::meals::PeriodTotals,
+            handlers::meals::WeightGoalInfo,
+            handlers::meals::GoalProgress,
+            handlers::meals::MonthlyStatsDto,
+            handlers::meals::MonthlyStatsResponse,
+            handlers::meals::SourceBreakdown,
+            handlers::meals::SourceStatsResponse,
+            handlers::meals::MoodComparison,
+            handlers::meals::MoodStatsResponse,
+            handlers::meals::ComplianceScoreResponse,
+            handlers::reports::ReportResponse,
+            handlers::reports::ReportsListResponse,
+            handlers::reports::ReportJobResponse,
+            handlers::reports::ReportStatusResponse,
+            handlers::reports::ScheduleReportRequest,
+            handlers::reports::ReportScheduleResponse,
+            handlers::chat::CreateChatRequest,
+            handlers::chat::UpdateChatSessionRequest,
+            handlers::chat::ChatSessionResponse,
+            handlers::chat::ChatSessionDto,
+            handlers::chat::ChatSessionsListResponse,
+            handlers::chat::SendMessageRequest,
+            handlers::chat::SendMessageResponse,
+            handlers::chat::ChatMessageDto,
+            handlers::chat::ToolCallDto,
+            handlers::chat::ToolResultDto,
+            handlers::chat::ChatMessagesResponse,
+            handlers::chat::ReportMessageRequest,
+            handlers::chat::QuickActionsResponse,
+            handlers::notifications::NotificationDto,
+            handlers::notifications::NotificationsListResponse,
+            handlers::notifications::NotificationPreferencesResponse,
+            handlers::notifications::ReminderPreferencesResponse,
+            handlers::account::StartMergeRequest,
+            handlers::account::AccountMergeDto,
+            handlers::account::AccountMergeResponse,
+            handlers::account::UpdateRetentionRequest,
+            handlers::account::RetentionResponse,
+            handlers::account::DeletionRequestedResponse,
+            handlers::account::AccountDeletedResponse,
+            handlers::account::AiDataDeletedResponse,
+            handlers::admin::MessageReportDto,
+            handlers::admin::MessageReportsListResponse,
+            handlers::admin::ReviewAction,
+            handlers::admin::ReviewMessageReportRequest,
+            handlers::admin::RouteLatencyDto,
+            handlers::admin::LatencyReportResponse,
+            handlers::admin::CacheStatsResponse,
+            handlers::admin::CreateRecomputeJobRequest,
+            handlers::admin::RecomputeJobDto,
+            handlers::admin::RecomputeJobResponse,
+            handlers::admin::SeedDemoAccountRequest,
+            handlers::admin::DemoAccountResponse,
+            handlers::admin::ConfigResponse,
+            handlers::admin::CreateApiKeyRequest,
+            handlers::admin::ApiKeyDto,
+            handlers::admin::ApiKeyResponse,
+            handlers::admin::IssuedApiKeyResponse,
+            handlers::admin::ApiKeysListResponse,
+            handlers::research::CreateResearchExportRequest,
+            handlers::research::ResearchExportDto,
+            handlers::research::ResearchExportResponse,
+            handlers::recurring_meals::CreateRecurringMealRequest,
+            handlers::recurring_meals::RecurringMealDto,
+            handlers::recurring_meals::RecurringMealResponse,
+            handlers::recurring_meals::RecurringMealsListResponse,
+            handlers::suggestions::SnackSuggestionsResponse,
+            handlers::weight::LogWeightRequest,
+            handlers::weight::WeightLogDto,
+            handlers::weight::WeightLogResponse,
+            handlers::weight::WeightHistoryResponse,
+            handlers::water::LogWaterRequest,
+            handlers::water::WaterDailyResponse,
+            handlers::activity::LogExerciseRequest,
+            handlers::activity::ExerciseLogDto,
+            handlers::activity::ExerciseLogResponse,
+            handlers::activity::ExerciseLogsResponse,
+            handlers::activity::ActivitySuggestionResponse,
+            handlers::activity::ApplyActivityLevelResponse,
+            handlers::favorites::CreateFavoriteRequest,
+            handlers::favorites::FavoriteFoodDto,
+            handlers::favorites::FavoriteFoodResponse,
+            handlers::favorites::FavoriteFoodsListResponse,
+            handlers::custom_foods::CustomFoodRequest,
+            handlers::custom_foods::CustomFoodDto,
+            handlers::custom_foods::CustomFoodResponse,
+            handlers::custom_foods::CustomFoodsListResponse,
+            handlers::meal_plans::GenerateMealPlanRequest,
+            handlers::meal_plans::PlannedMealDto,
+            handlers::meal_plans::MealPlanDayDto,
+            handlers::meal_plans::MealPlanDto,
+            handlers::meal_plans::MealPlanResponse,
+            handlers::meal_plans::MealPlansListResponse,
+            handlers::shopping_lists::CreateShoppingListRequest,
+            handlers::shopping_lists::UpdateShoppingListItemRequest,
+            handlers::shopping_lists::ShoppingListItemDto,
+            handlers::shopping_lists::ShoppingListDto,
+            handlers::shopping_lists::ShoppingListResponse,
+            handlers::shopping_lists::ShoppingListsListResponse,
+            handlers::widget::CreateWidgetTokenRequest,
+            handlers::widget::WidgetTokenResponse,
+            handlers::widget::WidgetSummaryResponse,
+            handlers::achievements::AchievementsDto,
+            handlers::achievements::AchievementsResponse,
+            handlers::gateway::LinkWhatsAppRequest,
+            handlers::gateway::LinkWhatsAppResponse,
+            handlers::gateway::InboundResponse,
+            handlers::live::LiveMessage
+        )
+    ),
+    modifiers(&SecuritySchemes),
+    security(("api_key" = []))
+)]
+pub struct ApiDoc;
+
+/// `x-api-key` is checked on every route outside the public list of
+/// `api_key_middleware`; user routes also take the access token.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key")))
+        );
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build())
+        );
+    }
+}
@@ -1,54 +1,229 @@
-﻿use axum::{ middleware, routing::{ delete, get, post, put }, Router };
+﻿use axum::{
+    extract::{ DefaultBodyLimit, Request },
+    http::header,
+    middleware,
+    routing::{ delete, get, patch, post, put },
+    Router,
+};
+use std::{ collections::HashMap, sync::Arc };
+use tower::Service;
 
 use crate::{ db::AppState, handlers, middleware as mw };
 
 pub fn create_routes(state: AppState) -> Router {
+    // Routes taking images get the larger upload limit; the rest fall back
+    // to the default set on the whole router below.
+    let upload_limit = DefaultBodyLimit::max(state.config.body_limits.upload_bytes);
+
     let protected_routes = Router::new()
         .route("/api/auth/logout", post(handlers::auth::logout))
         .route("/api/auth/me", get(handlers::auth::get_current_user))
-        .route("/api/nutrition/analyze", post(handlers::nutrition::analyze_food))
+        .route("/api/account/merge", post(handlers::account::start_account_merge))
+        .route("/api/account/merge/:id", get(handlers::account::get_account_merge))
+        .route("/api/account/retention", get(handlers::account::get_retention))
+        .route("/api/account/retention", put(handlers::account::update_retention))
+        .route("/api/account/export", get(handlers::account::export_account_data))
+        .route("/api/account", delete(handlers::account::delete_account))
+        .route("/api/users/me/ai-data", delete(handlers::account::delete_ai_data))
+        .route("/api/nutrition/analyze", post(handlers::nutrition::analyze_food).layer(upload_limit))
         .route("/api/nutrition/analyze-text", post(handlers::nutrition::analyze_food_text))
-        .route("/api/nutrition/quick-check", post(handlers::nutrition::quick_food_check))
+        .route("/api/nutrition/quick-check", post(handlers::nutrition::quick_food_check).layer(upload_limit))
         .route("/api/nutrition-info", get(handlers::nutrition_info::get_nutrition_info))
         .route("/api/food-wiki/search", get(handlers::food_wiki::search_foods))
         .route("/api/food-wiki/:fdc_id", get(handlers::food_wiki::get_food_details))
+        .route("/api/food-wiki/:fdc_id/scaled", get(handlers::food_wiki::get_scaled_food))
         .route("/api/food-wiki/foods", post(handlers::food_wiki::get_foods))
         .route("/api/recipes/search", get(handlers::recipes::search_recipes))
         .route("/api/recipes/random", get(handlers::recipes::get_random_recipes))
         .route("/api/recipes/:meal_id", get(handlers::recipes::get_recipe_by_id))
+        .route("/api/recipes/:meal_id/log", post(handlers::meals::log_recipe))
         .route("/api/recipes/category/:category", get(handlers::recipes::filter_by_category))
         .route("/api/recipes/area/:area", get(handlers::recipes::filter_by_area))
         .route("/api/health/profile", post(handlers::health::create_or_update_profile))
         .route("/api/health/profile", get(handlers::health::get_profile))
+        .route("/api/health/profile", patch(handlers::health::update_profile))
+        .route("/api/health/summary", get(handlers::health::get_health_summary))
+        .route("/api/health/survey/draft", patch(handlers::health::save_survey_draft))
+        .route("/api/health/survey/draft", get(handlers::health::get_survey_draft))
+        .route("/api/health/survey/submit", post(handlers::health::submit_survey_draft))
+        .route("/api/health/presets", get(handlers::health::get_goal_presets))
+        .route("/api/health/presets/:id/apply", post(handlers::health::apply_goal_preset))
+        .route("/api/health/goals", get(handlers::health::get_goal_history))
+        .route("/api/health/goals/:id", get(handlers::health::get_goal_era))
+        .route("/api/health/target-cycle", put(handlers::health::set_target_cycle))
+        .route("/api/health/activity-suggestion", get(handlers::activity::get_activity_suggestion))
+        .route("/api/health/activity-suggestion/apply", post(handlers::activity::apply_activity_suggestion))
         .route("/api/meals/log", post(handlers::meals::log_meal))
+        .route("/api/meals/log-from-image", post(handlers::meals::log_meal_from_image).layer(upload_limit))
+        .route("/api/meals/log-favorite/:id", post(handlers::meals::log_favorite))
+        .route("/api/meals/log-custom/:id", post(handlers::meals::log_custom_food))
+        .route("/api/meals/log-fdc/:fdc_id", post(handlers::meals::log_fdc_food))
+        .route("/api/meals/log-barcode/:barcode", post(handlers::meals::log_barcode_food))
         .route("/api/meals/daily", get(handlers::meals::get_daily_meals))
+        .route("/api/meals/search", get(handlers::meals::search_meals))
         .route("/api/meals/period-stats", get(handlers::meals::get_period_stats))
+        .route("/api/meals/source-stats", get(handlers::meals::get_source_stats))
+        .route("/api/meals/mood-stats", get(handlers::meals::get_mood_stats))
+        .route("/api/meals/monthly-stats", get(handlers::meals::get_monthly_stats))
+        .route("/api/meals/compliance-score", get(handlers::meals::get_compliance_score))
+        .route("/api/meals/export.pdf", get(handlers::meals::export_diary_pdf))
+        .route("/api/meals/copy-day", post(handlers::meals::copy_day))
+        .route("/api/meals/recurring", post(handlers::recurring_meals::create_recurring_meal))
+        .route("/api/meals/recurring", get(handlers::recurring_meals::get_recurring_meals))
+        .route("/api/meals/recurring/:id", delete(handlers::recurring_meals::delete_recurring_meal))
+        .route("/api/meals/:id/undo-auto-log", post(handlers::recurring_meals::undo_auto_logged_meal))
+        .route("/api/meals/:id/history", get(handlers::meals::get_meal_history))
+        .route("/api/meals/:id/repeat", post(handlers::meals::repeat_meal))
         .route("/api/meals/:id", put(handlers::meals::update_meal))
         .route("/api/meals/:id", delete(handlers::meals::delete_meal))
+        .route("/api/meals/:id/items", post(handlers::meals::add_meal_item))
+        .route("/api/meals/:id/items/:index", put(handlers::meals::update_meal_item))
+        .route("/api/meals/:id/items/:index", delete(handlers::meals::delete_meal_item))
+        .route("/api/foods/favorites", post(handlers::favorites::create_favorite))
+        .route("/api/foods/favorites", get(handlers::favorites::get_favorites))
+        .route("/api/foods/favorites/:id", delete(handlers::favorites::delete_favorite))
+        .route("/api/foods/custom", post(handlers::custom_foods::create_custom_food))
+        .route("/api/foods/custom", get(handlers::custom_foods::get_custom_foods))
+        .route("/api/foods/custom/:id", get(handlers::custom_foods::get_custom_food))
+        .route("/api/foods/custom/:id", put(handlers::custom_foods::update_custom_food))
+        .route("/api/foods/custom/:id", delete(handlers::custom_foods::delete_custom_food))
+        .route("/api/meal-plans", get(handlers::meal_plans::get_meal_plans))
+        .route("/api/meal-plans/generate", post(handlers::meal_plans::generate_meal_plan))
+        .route("/api/meal-plans/:id", get(handlers::meal_plans::get_meal_plan))
+        .route(
+            "/api/meal-plans/:id/days/:date/regenerate",
+            post(handlers::meal_plans::regenerate_meal_plan_day)
+        )
+        .route("/api/meal-plans/:id/days/:date/meals/:index/eaten", post(handlers::meals::log_planned_meal))
+        .route("/api/shopping-lists", post(handlers::shopping_lists::create_shopping_list))
+        .route("/api/shopping-lists", get(handlers::shopping_lists::get_shopping_lists))
+        .route("/api/shopping-lists/:id", get(handlers::shopping_lists::get_shopping_list))
+        .route("/api/shopping-lists/:id", delete(handlers::shopping_lists::delete_shopping_list))
+        .route("/api/shopping-lists/:id/items/:index", put(handlers::shopping_lists::update_shopping_list_item))
+        .route("/api/suggestions/snack", get(handlers::suggestions::get_snack_suggestions))
+        .route("/api/weight", post(handlers::weight::log_weight))
+        .route("/api/weight", get(handlers::weight::get_weight_history))
+        .route("/api/weight/:id", delete(handlers::weight::delete_weight_log))
+        .route("/api/water/log", post(handlers::water::log_water))
+        .route("/api/water/daily", get(handlers::water::get_daily_water))
+        .route("/api/activity", post(handlers::activity::log_exercise))
+        .route("/api/activity", get(handlers::activity::get_exercise_logs))
+        .route("/api/activity/:id", delete(handlers::activity::delete_exercise_log))
         .route("/api/reports/generate", post(handlers::reports::generate_report))
+        .route("/api/reports/schedule", post(handlers::reports::schedule_report))
         .route("/api/reports", get(handlers::reports::get_user_reports))
         .route("/api/reports/:id", get(handlers::reports::get_report_by_id))
+        .route("/api/reports/:id/status", get(handlers::reports::get_report_status))
         .route("/api/reports/:id", delete(handlers::reports::delete_report))
         .route("/api/chat/sessions", post(handlers::chat::create_chat_session))
         .route("/api/chat/sessions", get(handlers::chat::get_chat_sessions))
         .route("/api/chat/sessions/:id", get(handlers::chat::get_chat_session))
+        .route("/api/chat/sessions/:id", patch(handlers::chat::update_chat_session))
         .route("/api/chat/sessions/:id", delete(handlers::chat::delete_chat_session))
-        .route("/api/chat/sessions/:id/messages", post(handlers::chat::send_message))
+        .route("/api/chat/sessions/:id/messages", post(handlers::chat::send_message).layer(upload_limit))
         .route("/api/chat/sessions/:id/messages", get(handlers::chat::get_chat_messages))
+        .route("/api/chat/messages/:id/report", post(handlers::chat::report_message))
+        .route("/api/chat/suggestions", get(handlers::chat::get_quick_actions))
+        .route("/api/notifications", get(handlers::notifications::get_notifications))
+        .route("/api/notifications/preferences", get(handlers::notifications::get_notification_preferences))
+        .route("/api/notifications/preferences", put(handlers::notifications::update_notification_preferences))
+        .route("/api/notifications/reminders", get(handlers::notifications::get_reminder_preferences))
+        .route("/api/notifications/reminders", put(handlers::notifications::update_reminder_preferences))
+        .route("/api/notifications/read-all", put(handlers::notifications::mark_all_notifications_read))
+        .route("/api/notifications/:id/read", put(handlers::notifications::mark_notification_read))
+        .route("/api/widget/token", post(handlers::widget::create_widget_token))
+        .route("/api/widget/token", delete(handlers::widget::revoke_widget_token))
+        .route("/api/achievements", get(handlers::achievements::get_achievements))
+        .route("/api/gateway/whatsapp", put(handlers::gateway::link_whatsapp))
+        .route("/api/gateway/whatsapp", delete(handlers::gateway::unlink_whatsapp))
+        // Runs after auth, which provides the user the cache is keyed by.
+        .route_layer(
+            middleware::from_fn_with_state(state.clone(), mw::response_cache::response_cache_middleware)
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), mw::auth::auth_middleware));
+
+    let admin_routes = Router::new()
+        .route("/api/admin/message-reports", get(handlers::admin::get_message_reports))
+        .route("/api/admin/message-reports/:id", put(handlers::admin::review_message_report))
+        .route("/api/admin/cache-stats", get(handlers::admin::get_cache_stats))
+        .route("/api/admin/latency", get(handlers::admin::get_latency_report))
+        .route("/api/admin/config", get(handlers::admin::get_config))
+        .route("/api/admin/demo-account", post(handlers::admin::seed_demo_account))
+        .route("/api/admin/recompute-jobs", post(handlers::admin::create_recompute_job))
+        .route("/api/admin/recompute-jobs/:id", get(handlers::admin::get_recompute_job))
+        .route("/api/admin/api-keys", post(handlers::admin::create_api_key))
+        .route("/api/admin/api-keys", get(handlers::admin::get_api_keys))
+        .route("/api/admin/api-keys/:id/rotate", post(handlers::admin::rotate_api_key))
+        .route("/api/admin/api-keys/:id", delete(handlers::admin::revoke_api_key))
+        .route_layer(middleware::from_fn_with_state(state.clone(), mw::admin::admin_middleware))
+        .route_layer(middleware::from_fn_with_state(state.clone(), mw::auth::auth_middleware));
+
+    let research_routes = Router::new()
+        .route("/api/admin/research-exports", post(handlers::research::create_research_export))
+        .route("/api/admin/research-exports/:id", get(handlers::research::get_research_export))
+        .route_layer(
+            middleware::from_fn_with_state(state.clone(), mw::admin::researcher_middleware)
+        )
         .route_layer(middleware::from_fn_with_state(state.clone(), mw::auth::auth_middleware));
 
     let public_routes = Router::new()
+        .route("/api/branding", get(handlers::branding::get_branding))
         .route("/api/auth/google", get(handlers::auth::google_auth_url))
         .route("/api/auth/google/callback", get(handlers::auth::google_callback))
-        .route("/api/auth/verify-email", get(handlers::auth::verify_email));
-    // .route("/api/auth/debug-config", get(handlers::auth::debug_config));
+        .route("/api/auth/verify-email", get(handlers::auth::verify_email))
+        .route("/api/auth/resend-verification", post(handlers::auth::resend_verification))
+        .route("/api/auth/exchange", post(handlers::auth::exchange_auth_code))
+        .route("/api/auth/refresh", post(handlers::auth::refresh_token))
+        .route(
+            "/api/widget/summary",
+            get(handlers::widget::get_widget_summary).layer(mw::cors::widget_cors())
+        )
+        .route("/api/images/:id", get(handlers::images::get_image))
+        .route("/api/gateway/inbound/:channel", post(handlers::gateway::receive_inbound))
+        .route("/api/ws", get(handlers::live::live_updates));
 
     Router::new()
         .route("/", get(handlers::dashboard::serve_dashboard))
         .route("/docs", get(handlers::dashboard::serve_docs))
+        .route("/api/openapi.json", get(handlers::dashboard::serve_openapi))
         .route("/status", get(handlers::status::status_check))
+        .route("/status/providers", get(handlers::status::provider_status))
         .merge(protected_routes)
+        .merge(admin_routes)
+        .merge(research_routes)
         .merge(public_routes)
+        // A route layer, so the matched path pattern is available.
+        .route_layer(middleware::from_fn_with_state(state.clone(), mw::latency::latency_middleware))
         .with_state(state.clone())
+        .layer(DefaultBodyLimit::max(state.config.body_limits.default_bytes))
         .layer(middleware::from_fn_with_state(state.clone(), mw::api_key::api_key_middleware))
+        .layer(middleware::from_fn(mw::locale::locale_middleware))
+}
+
+/// Sends each request to the router of the tenant owning its `Host`, or to
+/// `default` for any other host.
+pub fn dispatch_by_host(default: Router, tenants: HashMap<String, Router>) -> Router {
+    let tenants = Arc::new(tenants);
+
+    Router::new().fallback(move |request: Request| {
+        let mut router = request_host(&request)
+            .and_then(|host| tenants.get(&host))
+            .unwrap_or(&default)
+            .clone();
+        // A router is always ready, so it can be called without `poll_ready`.
+        router.call(request)
+    })
+}
+
+/// Lowercase host without the port, from `Host` or, for HTTP/2, the URI.
+/// IP literals never name a tenant, so mangling IPv6 ones is harmless.
+fn request_host(request: &Request) -> Option<String> {
+    let host = request.headers()
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| request.uri().host())?;
+
+    host.split(':')
+        .next()
+        .map(str::to_lowercase)
 }
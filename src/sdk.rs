@@ -0,0 +1,122 @@
+//! `alimentify sdk`: writes the OpenAPI document from [`ApiDoc`] and generates
+//! the TypeScript and Dart clients from it with openapi-generator, so the web
+//! and Flutter apps use the server's DTOs instead of hand-written copies.
+
+use anyhow::{ bail, Context, Result };
+use std::{ fs, path::{ Path, PathBuf }, process::Command };
+use utoipa::OpenApi;
+
+use crate::openapi::ApiDoc;
+
+/// Pinned so regenerating from the same spec gives the same code.
+const GENERATOR_CLI: &str = "@openapitools/openapi-generator-cli@2.13.4";
+
+struct Client {
+    /// Output directory under `--out`.
+    dir: &'static str,
+    generator: &'static str,
+    /// `--additional-properties`, without the version.
+    properties: &'static str,
+    version_property: &'static str,
+    /// Run in the output directory after generating.
+    build: &'static [&'static [&'static str]],
+    publish: &'static [&'static str],
+}
+
+const CLIENTS: [Client; 2] = [
+    Client {
+        dir: "typescript",
+        generator: "typescript-fetch",
+        properties: "npmName=@alimentify/api-client,supportsES6=true",
+        version_property: "npmVersion",
+        build: &[&["npm", "install"], &["npm", "run", "build"]],
+        publish: &["npm", "publish", "--access", "public"],
+    },
+    Client {
+        dir: "dart",
+        generator: "dart-dio",
+        properties: "pubName=alimentify_api",
+        version_property: "pubVersion",
+        build: &[&["dart", "pub", "get"], &["dart", "run", "build_runner", "build", "--delete-conflicting-outputs"]],
+        publish: &["dart", "pub", "publish", "--force"],
+    },
+];
+
+/// `sdk [--out <dir>] [--publish]`. Clients are versioned with the crate;
+/// `--publish` also pushes them to npm and pub.dev with the credentials
+/// those tools are logged in with.
+pub fn run(args: &[String]) -> Result<()> {
+    let mut out = PathBuf::from("sdk");
+    let mut publish = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => {
+                out = args.next().context("--out needs a directory")?.into();
+            }
+            "--publish" => {
+                publish = true;
+            }
+            other => bail!("Unknown sdk argument {}", other),
+        }
+    }
+
+    fs::create_dir_all(&out).with_context(|| format!("Failed to create {}", out.display()))?;
+    // The commands run in other directories.
+    let out = fs::canonicalize(&out)?;
+    let spec = out.join("openapi.json");
+    fs::write(&spec, ApiDoc::openapi().to_pretty_json()?).with_context(|| format!("Failed to write {}", spec.display()))?;
+    tracing::info!("Wrote {}", spec.display());
+
+    let version = env!("CARGO_PKG_VERSION");
+    for client in &CLIENTS {
+        let dir = out.join(client.dir);
+        if dir.exists() {
+            // Types removed from the spec must not linger in the client.
+            fs::remove_dir_all(&dir).with_context(|| format!("Failed to clear {}", dir.display()))?;
+        }
+
+        let spec_arg = spec.to_string_lossy();
+        let dir_arg = dir.to_string_lossy();
+        let properties = format!("{},{}={}", client.properties, client.version_property, version);
+        run_command(&out, &[
+            "npx",
+            "--yes",
+            GENERATOR_CLI,
+            "generate",
+            "-i",
+            &spec_arg,
+            "-g",
+            client.generator,
+            "-o",
+            &dir_arg,
+            "--additional-properties",
+            &properties,
+        ])?;
+
+        for command in client.build {
+            run_command(&dir, command)?;
+        }
+        if publish {
+            run_command(&dir, client.publish)?;
+        }
+
+        tracing::info!("Generated the {} client {} in {}", client.generator, version, dir.display());
+    }
+
+    Ok(())
+}
+
+fn run_command(dir: &Path, command: &[&str]) -> Result<()> {
+    let (program, args) = command.split_first().context("Empty command")?;
+    let status = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .with_context(|| format!("Failed to run {}; is it installed?", program))?;
+
+    if !status.success() {
+        bail!("{} failed with {}", command.join(" "), status);
+    }
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId, Bson, Document };
+use redis::AsyncCommands;
+use std::collections::BTreeMap;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    field_encryption,
+    models::{ AccountMerge, MergeStatus, User, UserResponse },
+    services::{
+        account_merge_service::OWNED_COLLECTIONS,
+        audit_service,
+        auth_service,
+        email_service::EmailService,
+        image_service,
+        report_service,
+        response_cache_service,
+    },
+};
+
+/// Owned collections that aren't moved by an account merge but still hold the
+/// user's data, so they are exported and deleted with the rest.
+const EXTRA_COLLECTIONS: [&str; 4] = [
+    "health_survey_drafts",
+    "monthly_stats",
+    "achievements",
+    "goal_history",
+];
+
+/// How long a deletion confirmation token stays valid.
+pub const DELETION_TOKEN_MINUTES: u64 = 60;
+
+fn deletion_token_key(user_id: ObjectId) -> String {
+    format!("account_deletion:{}", user_id.to_hex())
+}
+
+fn owned_collections() -> impl Iterator<Item = &'static str> {
+    OWNED_COLLECTIONS.into_iter().chain(EXTRA_COLLECTIONS)
+}
+
+/// Everything stored for the user as one JSON document: the profile and every
+/// owned collection. Flagged chat messages are left out, as in other exports.
+pub async fn export(state: &AppState, user_id: ObjectId) -> Result<serde_json::Value> {
+    let user = find_user(state, user_id).await?;
+
+    let mut collections = serde_json::Map::new();
+    for collection in owned_collections() {
+        let mut filter = doc! { "user_id": user_id };
+        if collection == "chat_messages" {
+            filter.insert("flagged", doc! { "$ne": true });
+        }
+
+        let documents: Vec<Document> = state.db
+            .collection::<Document>(collection)
+            .find(filter, None).await
+            .map_err(|e| AppError::InternalError(e.into()))?
+            .try_collect().await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+
+        collections.insert(
+            collection.to_string(),
+            serde_json::Value::Array(
+                documents
+                    .into_iter()
+                    .map(|mut document| {
+                        field_encryption::decrypt_fields(&mut document).map_err(AppError::InternalError)?;
+                        if let Ok(image_id) = document.get_object_id("image_id") {
+                            document.insert("image_url", image_service::signed_url(&state.config, image_id));
+                        }
+                        Ok(Bson::Document(document).into_relaxed_extjson())
+                    })
+                    .collect::<Result<_>>()?
+            )
+        );
+    }
+
+    let health_profile = user.health_profile.clone();
+    let notification_preferences = user.notification_preferences.clone();
+
+    Ok(
+        serde_json::json!({
+            "exported_at": Utc::now().to_rfc3339(),
+            "profile": UserResponse::from(user),
+            "health_profile": health_profile,
+            "notification_preferences": notification_preferences,
+            "collections": collections,
+        })
+    )
+}
+
+/// First step of deleting an account: stores a single-use token and emails a
+/// link with it to the account's address. A new request replaces the old token.
+pub async fn request_deletion(state: &AppState, user_id: ObjectId) -> Result<()> {
+    let user = find_user(state, user_id).await?;
+    ensure_no_merge_in_progress(state, user_id).await?;
+
+    let token = auth_service::generate_confirmation_token();
+    let mut conn = state.redis.clone();
+    conn
+        .set_ex::<_, _, ()>(deletion_token_key(user_id), &token, DELETION_TOKEN_MINUTES * 60).await
+        .map_err(|e| AppError::InternalError(anyhow::anyhow!("Redis error: {}", e)))?;
+
+    // The tenant's frontend, so white-label users land on their own app.
+    let confirm_url = format!("{}/account/delete/confirm?token={}", state.config.server.frontend_url, token);
+
+    let email_service = EmailService::new(
+        state.config.brevo.smtp_host.clone(),
+        state.config.brevo.smtp_port,
+        state.config.brevo.smtp_user.clone(),
+        state.config.brevo.smtp_pass.clone(),
+        state.config.brevo.from_email.clone(),
+        state.config.brevo.from_name.clone(),
+        state.config.branding.clone()
+    );
+    email_service.send_account_deletion_email(&user, &confirm_url, DELETION_TOKEN_MINUTES).await?;
+
+    tracing::info!("Account deletion requested for user {}", user_id);
+
+    Ok(())
+}
+
+/// Second step: checks the emailed token, then deletes every owned document,
+/// the user and their sessions. Returns the documents deleted per collection.
+pub async fn confirm_deletion(state: &AppState, user_id: ObjectId, token: &str) -> Result<BTreeMap<String, u64>> {
+    ensure_no_merge_in_progress(state, user_id).await?;
+
+    // GETDEL so the token works once, even for concurrent requests.
+    let mut conn = state.redis.clone();
+    let stored: Option<String> = redis
+        ::cmd("GETDEL")
+        .arg(deletion_token_key(user_id))
+        .query_async(&mut conn).await
+        .map_err(|e| AppError::InternalError(anyhow::anyhow!("Redis error: {}", e)))?;
+
+    if stored.as_deref() != Some(token) {
+        return Err(AppError::BadRequest("Invalid or expired confirmation token".to_string()));
+    }
+
+    let deleted = delete_user_data(state, user_id).await?;
+
+    // Only counts are kept, so the log entry holds none of the deleted data.
+    let mut details = Document::new();
+    for (collection, count) in &deleted {
+        details.insert(collection, *count as i64);
+    }
+    audit_service::record(state, user_id, "account.deleted", Some(user_id), details).await;
+
+    tracing::info!("Deleted account {}", user_id);
+
+    Ok(deleted)
+}
+
+/// Deletes the user, every document they own and their sessions, without
+/// any confirmation. Returns the documents deleted per collection.
+pub async fn delete_user_data(state: &AppState, user_id: ObjectId) -> Result<BTreeMap<String, u64>> {
+    let mut deleted = BTreeMap::new();
+    let images = image_service::delete_message_images(&state.db, doc! { "user_id": user_id }).await?;
+    deleted.insert("chat_images".to_string(), images);
+
+    for collection in owned_collections() {
+        let result = state.db
+            .collection::<Document>(collection)
+            .delete_many(doc! { "user_id": user_id }, None).await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+        deleted.insert(collection.to_string(), result.deleted_count);
+    }
+
+    let reports = state.db
+        .collection::<Document>("message_reports")
+        .delete_many(doc! { "reporter_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    deleted.insert("message_reports".to_string(), reports.deleted_count);
+
+    let merges = state.db
+        .collection::<AccountMerge>("account_merges")
+        .delete_many(
+            doc! { "$or": [{ "primary_user_id": user_id }, { "secondary_user_id": user_id }] },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    deleted.insert("account_merges".to_string(), merges.deleted_count);
+
+    state.db
+        .collection::<User>("users")
+        .delete_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let user_hex = user_id.to_hex();
+    auth_service::tolerate_outage(&state.config, auth_service::delete_session(&state.redis, &user_hex).await)?;
+    auth_service::tolerate_outage(
+        &state.config,
+        auth_service::revoke_refresh_tokens(&state.redis, &user_hex).await
+    )?;
+    report_service::invalidate_compliance_score(state, user_id).await;
+    response_cache_service::invalidate_all(state, user_id).await;
+
+    Ok(deleted)
+}
+
+/// Health profile fields written from the AI recommendations.
+const AI_PROFILE_FIELDS: [&str; 4] = [
+    "ai_recommendations",
+    "ai_recommendations_prompt_version",
+    "recommended_foods",
+    "foods_to_avoid",
+];
+
+/// Report fields written by the AI coach; the rest of each report is kept.
+const AI_REPORT_FIELDS: [&str; 2] = ["coach_commentary", "coach_prompt_version"];
+
+/// Deletes what the AI produced for the user while keeping their tracking
+/// data: chats with their images and reports, generated meal plans and the
+/// shopping lists built from them, the profile's recommendations, report
+/// coaching and cached responses. Meals, including ones logged from a photo,
+/// and reports stay. Returns the documents deleted or cleared per
+/// collection.
+pub async fn delete_ai_data(state: &AppState, user_id: ObjectId) -> Result<BTreeMap<String, u64>> {
+    find_user(state, user_id).await?;
+    ensure_no_merge_in_progress(state, user_id).await?;
+
+    let mut deleted = BTreeMap::new();
+    let images = image_service::delete_message_images(&state.db, doc! { "user_id": user_id }).await?;
+    deleted.insert("chat_images".to_string(), images);
+
+    for collection in ["chat_messages", "chat_sessions"] {
+        let result = state.db
+            .collection::<Document>(collection)
+            .delete_many(doc! { "user_id": user_id }, None).await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+        deleted.insert(collection.to_string(), result.deleted_count);
+    }
+
+    // They quote the reported reply.
+    let reports = state.db
+        .collection::<Document>("message_reports")
+        .delete_many(doc! { "reporter_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    deleted.insert("message_reports".to_string(), reports.deleted_count);
+
+    // Lists built from recipes the user picked stay; ones built from a
+    // generated plan carry its contents.
+    let shopping_lists = state.db
+        .collection::<Document>("shopping_lists")
+        .delete_many(doc! { "user_id": user_id, "meal_plan_id": { "$exists": true } }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    deleted.insert("shopping_lists".to_string(), shopping_lists.deleted_count);
+
+    let meal_plans = state.db
+        .collection::<Document>("meal_plans")
+        .delete_many(doc! { "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    deleted.insert("meal_plans".to_string(), meal_plans.deleted_count);
+
+    let unset_profile: Document = AI_PROFILE_FIELDS.iter()
+        .map(|field| (format!("health_profile.{}", field), Bson::String(String::new())))
+        .collect();
+    let profile = state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! { "_id": user_id, "health_profile": { "$type": "object" } },
+            doc! { "$unset": unset_profile },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    deleted.insert("health_profile".to_string(), profile.modified_count);
+
+    let unset_reports: Document = AI_REPORT_FIELDS.iter()
+        .map(|field| (field.to_string(), Bson::String(String::new())))
+        .collect();
+    let coached: Vec<Document> = AI_REPORT_FIELDS.iter()
+        .map(|field| doc! { *field: { "$exists": true } })
+        .collect();
+    let meal_reports = state.db
+        .collection::<Document>("meal_reports")
+        .update_many(doc! { "user_id": user_id, "$or": coached }, doc! { "$unset": unset_reports }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    deleted.insert("meal_reports".to_string(), meal_reports.modified_count);
+
+    response_cache_service::invalidate_all(state, user_id).await;
+
+    let mut details = Document::new();
+    for (collection, count) in &deleted {
+        details.insert(collection, *count as i64);
+    }
+    audit_service::record(state, user_id, "account.ai_data_deleted", Some(user_id), details).await;
+
+    tracing::info!("Deleted AI data for user {}", user_id);
+
+    Ok(deleted)
+}
+
+/// A running merge would move documents while they are being deleted.
+async fn ensure_no_merge_in_progress(state: &AppState, user_id: ObjectId) -> Result<()> {
+    let in_progress = state.db
+        .collection::<AccountMerge>("account_merges")
+        .find_one(
+            doc! {
+                "status": { "$in": [MergeStatus::Pending.as_str(), MergeStatus::Running.as_str()] },
+                "$or": [{ "primary_user_id": user_id }, { "secondary_user_id": user_id }],
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if in_progress.is_some() {
+        return Err(
+            AppError::Conflict("An account merge is in progress; wait for it to finish first".to_string())
+        );
+    }
+
+    Ok(())
+}
+
+async fn find_user(state: &AppState, user_id: ObjectId) -> Result<User> {
+    state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
@@ -0,0 +1,279 @@
+use anyhow::Result;
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId, Bson, Document };
+
+use crate::{
+    db::AppState,
+    field_encryption,
+    models::{ AccountMerge, MergeStatus, User },
+    services::{
+        achievement_service,
+        auth_service,
+        monthly_stats_service,
+        progress_service,
+        report_service,
+        response_cache_service,
+    },
+};
+
+/// Collections whose documents are owned through a `user_id` field and move
+/// wholesale to the primary account.
+pub const OWNED_COLLECTIONS: [&str; 17] = [
+    "meal_logs",
+    "meal_history",
+    "chat_sessions",
+    "chat_messages",
+    "meal_reports",
+    "notifications",
+    "notification_outbox",
+    "user_milestones",
+    "weight_logs",
+    "daily_progress",
+    "exercise_logs",
+    "report_schedules",
+    "favorite_foods",
+    "custom_foods",
+    "meal_plans",
+    "shopping_lists",
+    "recurring_meals",
+];
+
+/// Owned collections plus the profile copy and the final cleanup step.
+pub const TOTAL_STEPS: i32 = (OWNED_COLLECTIONS.len() as i32) + 2;
+
+pub async fn run_merge(state: AppState, merge_id: ObjectId) {
+    if let Err(e) = execute_merge(&state, merge_id).await {
+        tracing::error!("Account merge {} failed: {}", merge_id, e);
+        let _ = state.db
+            .collection::<AccountMerge>("account_merges")
+            .update_one(
+                doc! { "_id": merge_id },
+                doc! {
+                    "$set": {
+                        "status": MergeStatus::Failed.as_str(),
+                        "error": e.to_string(),
+                        "updated_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+                    }
+                },
+                None
+            ).await;
+    }
+}
+
+async fn execute_merge(state: &AppState, merge_id: ObjectId) -> Result<()> {
+    let merges = state.db.collection::<AccountMerge>("account_merges");
+
+    let merge = merges
+        .find_one(doc! { "_id": merge_id }, None).await?
+        .ok_or_else(|| anyhow::anyhow!("Merge job not found"))?;
+
+    let primary_id = merge.primary_user_id;
+    let secondary_id = merge.secondary_user_id;
+
+    tracing::info!("Merging account {} into {}", secondary_id, primary_id);
+
+    set_progress(state, merge_id, MergeStatus::Running, "starting", 0, None).await?;
+
+    let mut completed = 0;
+    for collection in OWNED_COLLECTIONS {
+        set_progress(state, merge_id, MergeStatus::Running, collection, completed, None).await?;
+
+        let moved = if collection == "daily_progress" {
+            merge_daily_progress(state, primary_id, secondary_id).await?
+        } else if collection == "user_milestones" {
+            merge_milestones(state, primary_id, secondary_id).await?
+        } else {
+            state.db
+                .collection::<Document>(collection)
+                .update_many(
+                    doc! { "user_id": secondary_id },
+                    doc! { "$set": { "user_id": primary_id } },
+                    None
+                ).await?.modified_count
+        };
+
+        completed += 1;
+        set_progress(
+            state,
+            merge_id,
+            MergeStatus::Running,
+            collection,
+            completed,
+            Some((collection, moved as i64))
+        ).await?;
+    }
+
+    set_progress(state, merge_id, MergeStatus::Running, "profile", completed, None).await?;
+
+    let users = state.db.collection::<User>("users");
+    let primary = users
+        .find_one(doc! { "_id": primary_id }, None).await?
+        .ok_or_else(|| anyhow::anyhow!("Primary account not found"))?;
+    let secondary = users.find_one(doc! { "_id": secondary_id }, None).await?;
+
+    let mut profile_copied = 0;
+    if let Some(secondary) = &secondary {
+        if primary.health_profile.is_none() {
+            if let Some(profile) = &secondary.health_profile {
+                users.update_one(
+                    doc! { "_id": primary_id },
+                    doc! {
+                        "$set": {
+                            "health_profile": field_encryption::profile_to_bson(profile)?,
+                            "has_completed_health_survey": secondary.has_completed_health_survey.unwrap_or(true),
+                            "updated_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+                        }
+                    },
+                    None
+                ).await?;
+                profile_copied = 1;
+            }
+        }
+    }
+
+    completed += 1;
+    set_progress(
+        state,
+        merge_id,
+        MergeStatus::Running,
+        "profile",
+        completed,
+        Some(("health_profile", profile_copied))
+    ).await?;
+
+    set_progress(state, merge_id, MergeStatus::Running, "cleanup", completed, None).await?;
+
+    if let Some(secondary) = &secondary {
+        let mut google_ids = vec![secondary.google_id.clone()];
+        google_ids.extend(secondary.linked_google_ids.iter().cloned());
+        users.update_one(
+            doc! { "_id": primary_id },
+            doc! { "$addToSet": { "linked_google_ids": { "$each": google_ids } } },
+            None
+        ).await?;
+    }
+
+    users.delete_one(doc! { "_id": secondary_id }, None).await?;
+    if let Err(e) = auth_service::delete_session(&state.redis, &secondary_id.to_hex()).await {
+        tracing::warn!("Failed to clear session for merged account {}: {}", secondary_id, e);
+    }
+    if let Err(e) = auth_service::revoke_refresh_tokens(&state.redis, &secondary_id.to_hex()).await {
+        tracing::warn!("Failed to revoke refresh tokens for merged account {}: {}", secondary_id, e);
+    }
+    report_service::invalidate_compliance_score(state, primary_id).await;
+    monthly_stats_service::invalidate_all(state, primary_id).await;
+    monthly_stats_service::invalidate_all(state, secondary_id).await;
+    // Achievements are derived from the meals, now all the primary's, so the
+    // secondary's are dropped and the primary's recomputed.
+    state.db.collection::<Document>("achievements").delete_one(doc! { "user_id": secondary_id }, None).await?;
+    if let Err(e) = achievement_service::refresh(state, primary_id, None).await {
+        tracing::warn!("Failed to refresh achievements after merging into {}: {}", primary_id, e);
+    }
+    // The primary keeps its own profile, so only its goal history applies.
+    state.db.collection::<Document>("goal_history").delete_many(doc! { "user_id": secondary_id }, None).await?;
+    // A half-filled survey would only ever have built the secondary's profile.
+    state.db
+        .collection::<Document>("health_survey_drafts")
+        .delete_many(doc! { "user_id": secondary_id }, None).await?;
+    // The secondary's meal totals were added in at its own offset's days.
+    progress_service::rebuild_user_totals(state, primary_id).await?;
+
+    completed += 1;
+    set_progress(state, merge_id, MergeStatus::Completed, "done", completed, None).await?;
+
+    response_cache_service::invalidate_all(state, primary_id).await;
+
+    tracing::info!("Account merge {} completed", merge_id);
+
+    Ok(())
+}
+
+/// `daily_progress` is unique per user and day, so days both accounts logged
+/// are added into the primary's document instead of being reassigned.
+async fn merge_daily_progress(state: &AppState, primary_id: ObjectId, secondary_id: ObjectId) -> Result<u64> {
+    let collection = state.db.collection::<Document>("daily_progress");
+    let mut cursor = collection.find(doc! { "user_id": secondary_id }, None).await?;
+
+    let mut merged = 0;
+    while let Some(day) = cursor.try_next().await? {
+        let number = |field: &str| day.get(field).and_then(Bson::as_f64).unwrap_or(0.0);
+        let integer = |field: &str| {
+            day.get(field)
+                .and_then(|v| v.as_i64().or_else(|| v.as_i32().map(i64::from)))
+                .unwrap_or(0)
+        };
+        let now = Utc::now().to_rfc3339();
+
+        progress_service::upsert(
+            state,
+            doc! { "user_id": primary_id, "date": day.get("date").cloned().unwrap_or(Bson::Null) },
+            doc! {
+                "$inc": {
+                    "total_calories": number("total_calories"),
+                    "total_protein_g": number("total_protein_g"),
+                    "total_carbs_g": number("total_carbs_g"),
+                    "total_fat_g": number("total_fat_g"),
+                    "meal_count": integer("meal_count"),
+                    "water_ml": number("water_ml"),
+                },
+                "$set": { "updated_at": &now },
+                "$setOnInsert": { "created_at": &now },
+            }
+        ).await?;
+
+        collection.delete_one(doc! { "_id": day.get("_id").cloned().unwrap_or(Bson::Null) }, None).await?;
+        merged += 1;
+    }
+
+    Ok(merged)
+}
+
+/// `user_milestones` is unique per user and milestone, so ones the primary
+/// already reached are dropped and only the rest move.
+async fn merge_milestones(state: &AppState, primary_id: ObjectId, secondary_id: ObjectId) -> Result<u64> {
+    let collection = state.db.collection::<Document>("user_milestones");
+    let reached: Vec<Bson> = collection
+        .find(doc! { "user_id": primary_id }, None).await?
+        .try_collect::<Vec<Document>>().await?
+        .into_iter()
+        .filter_map(|milestone| milestone.get("milestone").cloned())
+        .collect();
+
+    collection.delete_many(doc! { "user_id": secondary_id, "milestone": { "$in": reached } }, None).await?;
+
+    let moved = collection.update_many(
+        doc! { "user_id": secondary_id },
+        doc! { "$set": { "user_id": primary_id } },
+        None
+    ).await?;
+
+    Ok(moved.modified_count)
+}
+
+async fn set_progress(
+    state: &AppState,
+    merge_id: ObjectId,
+    status: MergeStatus,
+    step: &str,
+    completed_steps: i32,
+    migrated: Option<(&str, i64)>
+) -> Result<()> {
+    let mut set =
+        doc! {
+        "status": status.as_str(),
+        "current_step": step,
+        "completed_steps": completed_steps,
+        "updated_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+    };
+
+    if let Some((key, count)) = migrated {
+        set.insert(format!("migrated.{}", key), count);
+    }
+
+    state.db
+        .collection::<AccountMerge>("account_merges")
+        .update_one(doc! { "_id": merge_id }, doc! { "$set": set }, None).await?;
+
+    Ok(())
+}
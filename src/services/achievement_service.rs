@@ -0,0 +1,187 @@
+use chrono::{ DateTime, Duration, FixedOffset, NaiveDate, Utc };
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::ReplaceOptions };
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ Badge, Streak, User, UserAchievements },
+    services::{ report_service, timezone },
+};
+
+/// Streak lengths, in days, that earn a badge.
+pub const BADGE_DAYS: [usize; 3] = [7, 30, 100];
+/// A day hits the goal when its calories are within this share of its target,
+/// the same rule reports use for days on target.
+const GOAL_TOLERANCE: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy)]
+enum StreakKind {
+    Logging,
+    Goal,
+}
+
+impl StreakKind {
+    fn badge_id(&self, days: usize) -> String {
+        match self {
+            StreakKind::Logging => format!("logging_streak_{}", days),
+            StreakKind::Goal => format!("goal_streak_{}", days),
+        }
+    }
+
+    fn badge(&self, days: usize, earned_at: DateTime<Utc>) -> Badge {
+        let (name, description) = match self {
+            StreakKind::Logging =>
+                (format!("{}-day logging streak", days), format!("Logged meals {} days in a row", days)),
+            StreakKind::Goal =>
+                (format!("{} days on target", days), format!("Hit the calorie target {} days in a row", days)),
+        };
+        Badge { id: self.badge_id(days), name, description, earned_at }
+    }
+}
+
+/// Recomputes the user's streaks from their meal logs, awards any badge a
+/// streak has reached and saves the result. Returns the achievements and the
+/// badges earned by this refresh. Days run midnight to midnight at `offset`;
+/// without one, the offset saved with the notification preferences, else UTC.
+pub async fn refresh(
+    state: &AppState,
+    user_id: ObjectId,
+    offset: Option<FixedOffset>
+) -> Result<(UserAchievements, Vec<Badge>)> {
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let offset = offset
+        .or_else(|| {
+            user.notification_preferences
+                .as_ref()
+                .and_then(|preferences| timezone::from_minutes(preferences.utc_offset_minutes))
+        })
+        .unwrap_or_else(timezone::utc);
+    let today = timezone::today(offset);
+
+    let totals = report_service::daily_meal_totals_in(
+        state,
+        user_id,
+        DateTime::<Utc>::UNIX_EPOCH,
+        timezone::start_of_day(today + Duration::days(1), offset),
+        offset
+    ).await?;
+
+    let logged_days: Vec<NaiveDate> = totals
+        .iter()
+        .filter(|day| day.totals.meal_count > 0)
+        .map(|day| day.date)
+        .collect();
+    let goal_days: Vec<NaiveDate> = match &user.health_profile {
+        Some(profile) =>
+            totals
+                .iter()
+                .filter(|day| {
+                    let (target, ..) = profile.targets_on(day.date);
+                    target > 0.0 && (day.totals.calories - target).abs() / target <= GOAL_TOLERANCE
+                })
+                .map(|day| day.date)
+                .collect(),
+        None => Vec::new(),
+    };
+
+    let collection = state.db.collection::<UserAchievements>("achievements");
+    let stored = collection
+        .find_one(doc! { "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let mut badges = stored.as_ref().map(|stored| stored.badges.clone()).unwrap_or_default();
+    let mut new_badges = Vec::new();
+    let mut streak = |kind: StreakKind, days: &[NaiveDate]| {
+        let (longest, reached) = scan_runs(days);
+        for (length, day) in reached {
+            let id = kind.badge_id(length);
+            if !badges.iter().any(|badge| badge.id == id) {
+                let badge = kind.badge(length, timezone::start_of_day(day, offset));
+                badges.push(badge.clone());
+                new_badges.push(badge);
+            }
+        }
+        Streak {
+            current_days: current_streak(days, today),
+            longest_days: longest,
+            next_badge_days: BADGE_DAYS.into_iter().find(|length| {
+                let id = kind.badge_id(*length);
+                !badges.iter().any(|badge| badge.id == id)
+            }),
+        }
+    };
+    let logging_streak = streak(StreakKind::Logging, &logged_days);
+    let goal_streak = streak(StreakKind::Goal, &goal_days);
+    badges.sort_by_key(|badge| badge.earned_at);
+
+    let achievements = UserAchievements {
+        id: stored.and_then(|stored| stored.id),
+        user_id,
+        logging_streak,
+        goal_streak,
+        badges,
+        updated_at: Utc::now(),
+    };
+
+    collection
+        .replace_one(
+            doc! { "user_id": user_id },
+            &achievements,
+            ReplaceOptions::builder().upsert(true).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if !new_badges.is_empty() {
+        tracing::info!(
+            "User {} earned badges: {}",
+            user_id,
+            new_badges.iter().map(|badge| badge.id.as_str()).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    Ok((achievements, new_badges))
+}
+
+/// Length of the run of consecutive days in `days` (sorted ascending) that
+/// ends today, or yesterday when today has nothing logged yet.
+pub fn current_streak(days: &[NaiveDate], today: NaiveDate) -> usize {
+    let mut expected = if days.last() == Some(&today) { today } else { today - Duration::days(1) };
+    let mut streak = 0;
+    for day in days.iter().rev() {
+        if *day != expected {
+            break;
+        }
+        streak += 1;
+        expected -= Duration::days(1);
+    }
+    streak
+}
+
+/// The longest run of consecutive days in `days` (sorted ascending), and the
+/// day each badge length in [`BADGE_DAYS`] was first reached.
+fn scan_runs(days: &[NaiveDate]) -> (usize, Vec<(usize, NaiveDate)>) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    let mut reached = Vec::new();
+    for &day in days {
+        run = match previous {
+            Some(prev) if (day - prev).num_days() == 1 => run + 1,
+            _ => 1,
+        };
+        // A length is first reached when the run overtakes every earlier one.
+        if run > longest {
+            longest = run;
+            if BADGE_DAYS.contains(&run) {
+                reached.push((run, day));
+            }
+        }
+        previous = Some(day);
+    }
+    (longest, reached)
+}
@@ -0,0 +1,167 @@
+use chrono::{ DateTime, Duration, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::FindOptions };
+use serde::Serialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    field_encryption,
+    models::{ ActivityLevel, ExerciseLog, HealthProfile, User },
+    services::{ condition_guidance, response_cache_service, versioning },
+};
+
+/// How far back logged sessions count towards the suggestion.
+pub const OBSERVATION_DAYS: i64 = 28;
+/// Averages are never taken over less than a week, so one workout logged
+/// yesterday doesn't read as a very active week.
+const MIN_OBSERVATION_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivitySuggestion {
+    pub current_level: ActivityLevel,
+    pub suggested_level: ActivityLevel,
+    pub suggested_multiplier: f64,
+    /// Moderate-intensity-equivalent minutes per week.
+    pub weekly_active_minutes: f64,
+    pub weekly_sessions: f64,
+    pub days_observed: i64,
+    pub should_update: bool,
+    pub current_daily_calories: f64,
+    pub suggested_daily_calories: f64,
+}
+
+/// Exercise sessions for the user in `[start, end]`, oldest first.
+pub async fn fetch_exercise_logs_between(
+    state: &AppState,
+    user_id: ObjectId,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>
+) -> Result<Vec<ExerciseLog>> {
+    state.db
+        .collection::<ExerciseLog>("exercise_logs")
+        .find(
+            doc! {
+                "user_id": user_id,
+                "date": {
+                    "$gte": mongodb::bson::DateTime::from_chrono(start),
+                    "$lte": mongodb::bson::DateTime::from_chrono(end)
+                }
+            },
+            FindOptions::builder().sort(doc! { "date": 1 }).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+/// Maps weekly moderate-equivalent minutes onto the profile's activity levels.
+/// 150 minutes is the WHO minimum recommendation for adults.
+pub fn level_for_weekly_minutes(minutes: f64) -> ActivityLevel {
+    match minutes {
+        m if m < 30.0 => ActivityLevel::Sedentary,
+        m if m < 150.0 => ActivityLevel::LightlyActive,
+        m if m < 300.0 => ActivityLevel::ModeratelyActive,
+        m if m < 450.0 => ActivityLevel::VeryActive,
+        _ => ActivityLevel::ExtraActive,
+    }
+}
+
+/// `None` when the user has no health profile or no sessions in the last
+/// four weeks.
+pub async fn suggest_activity_level(state: &AppState, user: &User) -> Result<Option<ActivitySuggestion>> {
+    let Some(profile) = &user.health_profile else {
+        return Ok(None);
+    };
+    let user_id = user.id.ok_or_else(|| AppError::BadRequest("User has no ID".to_string()))?;
+
+    let now = Utc::now();
+    let logs = fetch_exercise_logs_between(
+        state,
+        user_id,
+        now - Duration::days(OBSERVATION_DAYS),
+        now
+    ).await?;
+
+    let Some(first) = logs.first() else {
+        return Ok(None);
+    };
+
+    let days_observed = (now - first.date)
+        .num_days()
+        .clamp(MIN_OBSERVATION_DAYS, OBSERVATION_DAYS);
+    let weeks = (days_observed as f64) / 7.0;
+
+    let active_minutes: f64 = logs
+        .iter()
+        .map(|log| log.duration_minutes * log.intensity.moderate_equivalent())
+        .sum();
+    let weekly_active_minutes = (active_minutes / weeks).round();
+    let suggested_level = level_for_weekly_minutes(weekly_active_minutes);
+
+    Ok(
+        Some(ActivitySuggestion {
+            current_level: profile.activity_level,
+            suggested_level,
+            suggested_multiplier: suggested_level.multiplier(),
+            weekly_active_minutes,
+            weekly_sessions: (((logs.len() as f64) / weeks) * 10.0).round() / 10.0,
+            days_observed,
+            should_update: suggested_level != profile.activity_level,
+            current_daily_calories: profile.daily_calories,
+            suggested_daily_calories: profile
+                .daily_targets(HealthProfile::calculate_tdee(profile.bmr, &suggested_level)).0,
+        })
+    )
+}
+
+/// Switches the profile to `level` and recalculates TDEE, calorie and macro targets.
+pub async fn apply_activity_level(
+    state: &AppState,
+    user_id: ObjectId,
+    profile: &HealthProfile,
+    level: ActivityLevel
+) -> Result<HealthProfile> {
+    let mut profile = profile.clone();
+    profile.activity_level = level;
+    profile.tdee = HealthProfile::calculate_tdee(profile.bmr, &level);
+    // An applied preset keeps its own adjustment and split.
+    let (daily_calories, (protein_g, carbs_g, fat_g)) = profile.daily_targets(profile.tdee);
+    profile.daily_calories = daily_calories;
+    profile.daily_protein_g = protein_g;
+    profile.daily_carbs_g = carbs_g;
+    profile.daily_fat_g = fat_g;
+    // Carb limits in the guidance scale with the daily target.
+    profile.condition_guidance = Some(
+        condition_guidance::build(profile.medical_conditions.as_deref(), carbs_g)
+    );
+    profile.version += 1;
+    profile.updated_at = Utc::now();
+
+    let profile_bson = field_encryption::profile_to_bson(&profile).map_err(AppError::InternalError)?;
+
+    // Only overwrite the profile this was computed from.
+    let result = state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! {
+                "_id": user_id,
+                "health_profile.version": versioning::version_filter(profile.version - 1),
+            },
+            doc! {
+                "$set": {
+                    "health_profile": profile_bson,
+                    "updated_at": Utc::now(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.matched_count == 0 {
+        return Err(versioning::conflict("health profile"));
+    }
+    response_cache_service::invalidate_profile(state, user_id).await;
+
+    Ok(profile)
+}
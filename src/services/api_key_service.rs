@@ -0,0 +1,181 @@
+use axum::http::Method;
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::{ FindOneAndUpdateOptions, FindOptions, ReturnDocument } };
+use ring::digest;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ ApiKeyRecord, ApiKeyScope },
+    services::auth_service,
+};
+
+const COLLECTION: &str = "api_keys";
+/// Marks managed keys, so a leaked one is recognizable in logs and scanners.
+const KEY_PREFIX: &str = "alk_";
+const KEY_LENGTH: usize = 40;
+/// Characters kept in `key_prefix`, `alk_` included.
+const DISPLAY_PREFIX_LENGTH: usize = 10;
+
+/// (path prefix, read scope, write scope) of each route group, matched on
+/// whole path segments. Paths outside every group take any valid key.
+const SCOPED_PREFIXES: &[(&str, ApiKeyScope, ApiKeyScope)] = &[
+    ("/api/admin", ApiKeyScope::Admin, ApiKeyScope::Admin),
+    ("/api/auth", ApiKeyScope::AccountRead, ApiKeyScope::AccountWrite),
+    ("/api/account", ApiKeyScope::AccountRead, ApiKeyScope::AccountWrite),
+    ("/api/users", ApiKeyScope::AccountRead, ApiKeyScope::AccountWrite),
+    ("/api/widget", ApiKeyScope::AccountRead, ApiKeyScope::AccountWrite),
+    ("/api/achievements", ApiKeyScope::AccountRead, ApiKeyScope::AccountWrite),
+    ("/api/gateway", ApiKeyScope::AccountRead, ApiKeyScope::AccountWrite),
+    ("/api/nutrition", ApiKeyScope::NutritionRead, ApiKeyScope::NutritionWrite),
+    ("/api/nutrition-info", ApiKeyScope::NutritionRead, ApiKeyScope::NutritionWrite),
+    ("/api/food-wiki", ApiKeyScope::NutritionRead, ApiKeyScope::NutritionWrite),
+    ("/api/recipes", ApiKeyScope::NutritionRead, ApiKeyScope::NutritionWrite),
+    ("/api/health", ApiKeyScope::HealthRead, ApiKeyScope::HealthWrite),
+    ("/api/weight", ApiKeyScope::HealthRead, ApiKeyScope::HealthWrite),
+    ("/api/water", ApiKeyScope::HealthRead, ApiKeyScope::HealthWrite),
+    ("/api/activity", ApiKeyScope::HealthRead, ApiKeyScope::HealthWrite),
+    ("/api/meals", ApiKeyScope::MealsRead, ApiKeyScope::MealsWrite),
+    ("/api/foods", ApiKeyScope::MealsRead, ApiKeyScope::MealsWrite),
+    ("/api/meal-plans", ApiKeyScope::MealsRead, ApiKeyScope::MealsWrite),
+    ("/api/shopping-lists", ApiKeyScope::MealsRead, ApiKeyScope::MealsWrite),
+    ("/api/suggestions", ApiKeyScope::MealsRead, ApiKeyScope::MealsWrite),
+    ("/api/reports", ApiKeyScope::ReportsRead, ApiKeyScope::ReportsWrite),
+    ("/api/chat", ApiKeyScope::ChatRead, ApiKeyScope::ChatWrite),
+    ("/api/notifications", ApiKeyScope::NotificationsRead, ApiKeyScope::NotificationsWrite),
+];
+
+/// The scope a request needs, `None` if any valid key will do.
+pub fn required_scope(method: &Method, path: &str) -> Option<ApiKeyScope> {
+    let (_, read, write) = SCOPED_PREFIXES.iter().find(|(prefix, _, _)| {
+        path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })?;
+
+    if method == Method::GET || method == Method::HEAD {
+        Some(*read)
+    } else {
+        Some(*write)
+    }
+}
+
+/// Scopes of `key`: all of them for a key from `API_KEYS`, those of the
+/// record for a live managed key, `None` for anything else.
+pub async fn scopes_for(state: &AppState, key: &str) -> Result<Option<Vec<ApiKeyScope>>> {
+    if state.config.security.api_keys.iter().any(|configured| configured == key) {
+        return Ok(Some(ApiKeyScope::ALL.to_vec()));
+    }
+    if !key.starts_with(KEY_PREFIX) {
+        return Ok(None);
+    }
+
+    let record = state.db
+        .collection::<ApiKeyRecord>(COLLECTION)
+        .find_one(doc! { "key_hash": hash_key(key), "revoked_at": { "$exists": false } }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(record.map(|record| record.scopes))
+}
+
+/// Stores a new key and returns it with the only copy of the key itself.
+pub async fn create(
+    state: &AppState,
+    name: &str,
+    scopes: Vec<ApiKeyScope>,
+    created_by: ObjectId
+) -> Result<(ApiKeyRecord, String)> {
+    let key = generate_key();
+    let mut record = ApiKeyRecord {
+        id: None,
+        name: name.to_string(),
+        key_hash: hash_key(&key),
+        key_prefix: display_prefix(&key),
+        scopes,
+        created_by,
+        created_at: Utc::now(),
+        rotated_at: None,
+        revoked_at: None,
+    };
+
+    let result = state.db
+        .collection::<ApiKeyRecord>(COLLECTION)
+        .insert_one(&record, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    record.id = result.inserted_id.as_object_id();
+
+    Ok((record, key))
+}
+
+pub async fn list(state: &AppState) -> Result<Vec<ApiKeyRecord>> {
+    let options = FindOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .build();
+
+    state.db
+        .collection::<ApiKeyRecord>(COLLECTION)
+        .find(doc! {}, options).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+/// Replaces the key of a live record, keeping its name and scopes. The old
+/// key stops working at once.
+pub async fn rotate(state: &AppState, id: ObjectId) -> Result<(ApiKeyRecord, String)> {
+    let key = generate_key();
+    let options = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+
+    let record = state.db
+        .collection::<ApiKeyRecord>(COLLECTION)
+        .find_one_and_update(
+            doc! { "_id": id, "revoked_at": { "$exists": false } },
+            doc! {
+                "$set": {
+                    "key_hash": hash_key(&key),
+                    "key_prefix": display_prefix(&key),
+                    "rotated_at": Utc::now().to_rfc3339(),
+                },
+            },
+            options
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("API key not found".to_string()))?;
+
+    Ok((record, key))
+}
+
+/// Revokes a live record. Revoked records are kept for the audit trail.
+pub async fn revoke(state: &AppState, id: ObjectId) -> Result<ApiKeyRecord> {
+    let options = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+
+    state.db
+        .collection::<ApiKeyRecord>(COLLECTION)
+        .find_one_and_update(
+            doc! { "_id": id, "revoked_at": { "$exists": false } },
+            doc! { "$set": { "revoked_at": Utc::now().to_rfc3339() } },
+            options
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("API key not found".to_string()))
+}
+
+fn generate_key() -> String {
+    format!("{}{}", KEY_PREFIX, auth_service::random_token(KEY_LENGTH))
+}
+
+fn display_prefix(key: &str) -> String {
+    key.chars().take(DISPLAY_PREFIX_LENGTH).collect()
+}
+
+fn hash_key(key: &str) -> String {
+    digest
+        ::digest(&digest::SHA256, key.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
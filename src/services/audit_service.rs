@@ -0,0 +1,27 @@
+use chrono::Utc;
+use mongodb::bson::{ oid::ObjectId, Document };
+
+use crate::{ db::AppState, models::AuditLog };
+
+/// Appends an entry to `audit_logs`. Failures are logged rather than returned so
+/// that auditing never blocks the action itself.
+pub async fn record(
+    state: &AppState,
+    actor_id: ObjectId,
+    action: &str,
+    target_id: Option<ObjectId>,
+    details: Document
+) {
+    let entry = AuditLog {
+        id: None,
+        actor_id,
+        action: action.to_string(),
+        target_id,
+        details,
+        created_at: Utc::now(),
+    };
+
+    if let Err(e) = state.db.collection::<AuditLog>("audit_logs").insert_one(&entry, None).await {
+        tracing::error!("Failed to write audit log entry {}: {}", action, e);
+    }
+}
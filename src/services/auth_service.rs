@@ -1,4 +1,5 @@
-﻿use chrono::{ Duration, Utc };
+﻿use base64::{ engine::general_purpose, Engine as _ };
+use chrono::{ DateTime, Duration, TimeZone, Utc };
 use jsonwebtoken::{ encode, EncodingKey, Header };
 use oauth2::{
     basic::BasicClient,
@@ -10,21 +11,37 @@ use oauth2::{
     AuthorizationCode,
     TokenResponse,
 };
+use mongodb::bson::oid::ObjectId;
 use rand::Rng;
 use redis::AsyncCommands;
 use reqwest;
+use ring::hmac;
 
 use crate::{
-    config::Config,
+    config::{ Config, RedisPolicy },
     error::{ AppError, Result },
-    models::{ Claims, GoogleUserInfo, Session, User },
+    models::{ AuthCodeRecord, Claims, GoogleUserInfo, RefreshTokenRecord, Session, User },
+    services::redis_health_service::{ self, RedisFeature },
 };
 
 const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
 const GOOGLE_USER_INFO_URL: &str = "https://www.googleapis.com/oauth2/v2/userinfo";
+/// Email verification links work this long.
+pub const VERIFICATION_TOKEN_HOURS: i64 = 24;
+/// A new verification link is sent at most this often per user.
+pub const VERIFICATION_RESEND_COOLDOWN_SECONDS: i64 = 60;
+/// How long the user has to get through Google's consent screen.
+const OAUTH_STATE_SECONDS: u64 = 600;
+/// How long the frontend has to trade a sign-in code for tokens.
+const AUTH_CODE_SECONDS: u64 = 60;
 
-pub fn generate_google_auth_url(config: &Config) -> Result<String> {
+/// The Google consent URL and its `state`, which is stored so the callback can
+/// check it came back from a URL we issued.
+pub async fn generate_google_auth_url(
+    redis: &redis::aio::ConnectionManager,
+    config: &Config
+) -> Result<(String, String)> {
     let client = BasicClient::new(
         ClientId::new(config.google_oauth.client_id.clone()),
         Some(ClientSecret::new(config.google_oauth.client_secret.clone())),
@@ -40,13 +57,47 @@ pub fn generate_google_auth_url(config: &Config) -> Result<String> {
         )?
     );
 
-    let (auth_url, _csrf_token) = client
+    let (auth_url, csrf_token) = client
         .authorize_url(oauth2::CsrfToken::new_random)
         .add_scope(oauth2::Scope::new("email".to_string()))
         .add_scope(oauth2::Scope::new("profile".to_string()))
         .url();
+    let state = csrf_token.secret().clone();
+
+    let mut conn = redis.clone();
+    conn.set_ex::<_, _, ()>(
+        oauth_state_key(&state),
+        config.tenant_database().unwrap_or_default(),
+        OAUTH_STATE_SECONDS
+    ).await.map_err(redis_unavailable)?;
 
-    Ok(auth_url.to_string())
+    Ok((auth_url.to_string(), state))
+}
+
+/// Consumes the `state` a Google callback came back with. Each works once,
+/// for `OAUTH_STATE_SECONDS`, and only on the tenant it was issued for.
+/// Unlike sessions this never fails open: without Redis the callback can't
+/// tell a forged request from a real one.
+pub async fn consume_oauth_state(
+    redis: &redis::aio::ConnectionManager,
+    state: &str,
+    config: &Config
+) -> Result<()> {
+    let mut conn = redis.clone();
+    let tenant: Option<String> = redis
+        ::cmd("GETDEL")
+        .arg(oauth_state_key(state))
+        .query_async(&mut conn).await
+        .map_err(redis_unavailable)?;
+
+    match tenant {
+        Some(tenant) if tenant == config.tenant_database().unwrap_or_default() => Ok(()),
+        _ => Err(AppError::BadRequest("Invalid or expired OAuth state".to_string())),
+    }
+}
+
+fn oauth_state_key(state: &str) -> String {
+    format!("oauth_state:{}", state)
 }
 
 pub async fn exchange_code_for_user(code: &str, config: &Config) -> Result<GoogleUserInfo> {
@@ -89,7 +140,7 @@ pub async fn exchange_code_for_user(code: &str, config: &Config) -> Result<Googl
 
 pub fn generate_jwt_token(user: &User, config: &Config) -> Result<String> {
     let now = Utc::now().timestamp();
-    let exp = now + config.jwt.expiration_hours * 3600;
+    let exp = now + config.jwt.access_token_minutes * 60;
 
     let user_id = user.id
         .as_ref()
@@ -101,6 +152,7 @@ pub fn generate_jwt_token(user: &User, config: &Config) -> Result<String> {
         email: user.gmail.clone(),
         exp,
         iat: now,
+        tenant: config.tenant_database().map(str::to_string),
     };
 
     encode(
@@ -110,9 +162,54 @@ pub fn generate_jwt_token(user: &User, config: &Config) -> Result<String> {
     ).map_err(|e| AppError::InternalError(e.into()))
 }
 
-pub fn generate_verification_token() -> String {
+/// Random single-use token for links confirming an action, e.g. deleting
+/// the account. The caller stores it to compare against.
+pub fn generate_confirmation_token() -> String {
+    random_token(32)
+}
+
+/// Token for the email verification link: `{user_id}.{expires}.{nonce}.{signature}`,
+/// signed with the JWT secret over the tenant, user and expiry. The nonce
+/// makes each link different; the user document stores the current one so
+/// a used or replaced link stops working.
+pub fn generate_verification_token(config: &Config, user_id: ObjectId) -> String {
+    let expires = (Utc::now() + Duration::hours(VERIFICATION_TOKEN_HOURS)).timestamp();
+    let nonce = random_token(16);
+    let tag = hmac::sign(&verification_key(config), verification_message(config, user_id, expires, &nonce).as_bytes());
+    format!("{}.{}.{}.{}", user_id.to_hex(), expires, nonce, general_purpose::URL_SAFE_NO_PAD.encode(tag.as_ref()))
+}
+
+/// The user a verification token was issued to and when it expires, if the
+/// signature is valid. Doesn't check the expiry, so callers can tell an
+/// expired link from a forged one.
+pub fn verification_token_claims(config: &Config, token: &str) -> Option<(ObjectId, DateTime<Utc>)> {
+    let mut parts = token.split('.');
+    let (user_id, expires, nonce, signature) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let user_id = ObjectId::parse_str(user_id).ok()?;
+    let expires: i64 = expires.parse().ok()?;
+    let tag = general_purpose::URL_SAFE_NO_PAD.decode(signature).ok()?;
+    hmac
+        ::verify(&verification_key(config), verification_message(config, user_id, expires, nonce).as_bytes(), &tag)
+        .ok()?;
+
+    Some((user_id, Utc.timestamp_opt(expires, 0).single()?))
+}
+
+fn verification_key(config: &Config) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, config.jwt.secret.as_bytes())
+}
+
+fn verification_message(config: &Config, user_id: ObjectId, expires: i64, nonce: &str) -> String {
+    format!("email_verification:{}:{}:{}:{}", config.tenant_database().unwrap_or(""), user_id.to_hex(), expires, nonce)
+}
+
+pub fn random_token(length: usize) -> String {
     let mut rng = rand::thread_rng();
-    let token: String = (0..32)
+    let token: String = (0..length)
         .map(|_| {
             let idx = rng.gen_range(0..62);
             match idx {
@@ -155,21 +252,229 @@ pub async fn store_session(
 
     let key = format!("session:{}", user_id);
 
-    conn.set_ex::<_, _, ()>(&key, session_json, 86400).await.map_err(|e| {
-        tracing::error!("Failed to set session in Redis: {:?}", e);
-        AppError::InternalError(anyhow::anyhow!("Redis error: {}", e))
-    })?;
+    conn.set_ex::<_, _, ()>(&key, session_json, 86400).await.map_err(redis_unavailable)?;
 
     tracing::info!("Successfully stored session for user {}", user_id);
 
     Ok(())
 }
 
+/// The user's session, or `None` once it expired or they logged out.
+pub async fn get_session(redis: &redis::aio::ConnectionManager, user_id: &str) -> Result<Option<Session>> {
+    let mut conn = redis.clone();
+    let key = format!("session:{}", user_id);
+
+    let session_json: Option<String> = conn.get(&key).await.map_err(redis_unavailable)?;
+
+    let session = match session_json {
+        Some(json) => Some(serde_json::from_str::<Session>(&json).map_err(|e| AppError::InternalError(e.into()))?),
+        None => None,
+    };
+
+    Ok(session.filter(|s| s.expires_at > Utc::now()))
+}
+
 pub async fn delete_session(redis: &redis::aio::ConnectionManager, user_id: &str) -> Result<()> {
     let mut conn = redis.clone();
     let key = format!("session:{}", user_id);
 
-    conn.del::<_, ()>(&key).await.map_err(|e| AppError::InternalError(e.into()))?;
+    conn.del::<_, ()>(&key).await.map_err(redis_unavailable)?;
+
+    Ok(())
+}
+
+fn refresh_token_key(token: &str) -> String {
+    format!("refresh_token:{}", token)
+}
+
+/// Marks a rotated token so that presenting it again can be recognised.
+fn used_refresh_token_key(token: &str) -> String {
+    format!("refresh_token_used:{}", token)
+}
+
+/// Every live refresh token of a user, so logout can revoke them all.
+fn user_refresh_tokens_key(user_id: &str) -> String {
+    format!("refresh_tokens:{}", user_id)
+}
+
+/// Issues a refresh token for the user and stores it in Redis.
+pub async fn issue_refresh_token(
+    redis: &redis::aio::ConnectionManager,
+    user: &User,
+    config: &Config
+) -> Result<String> {
+    let mut conn = redis.clone();
+    let user_id = user.id
+        .as_ref()
+        .ok_or_else(|| AppError::InternalError(anyhow::anyhow!("User has no ID")))?
+        .to_hex();
+    let token = random_token(64);
+    let ttl_seconds = (config.jwt.refresh_token_days * 86400) as u64;
+
+    let record = RefreshTokenRecord {
+        user_id: user_id.clone(),
+        tenant: config.tenant_database().map(str::to_string),
+        expires_at: Utc::now() + Duration::days(config.jwt.refresh_token_days),
+    };
+    let record_json = serde_json::to_string(&record).map_err(|e| AppError::InternalError(e.into()))?;
+
+    let user_key = user_refresh_tokens_key(&user_id);
+    redis
+        ::pipe()
+        .set_ex(refresh_token_key(&token), record_json, ttl_seconds)
+        .ignore()
+        .sadd(&user_key, &token)
+        .ignore()
+        .expire(&user_key, ttl_seconds as i64)
+        .ignore()
+        .query_async::<_, ()>(&mut conn).await
+        .map_err(redis_unavailable)?;
+
+    Ok(token)
+}
+
+/// Issues the one-time code the Google callback redirects with. The frontend
+/// trades it for tokens at `/api/auth/exchange`, so no token ends up in a URL.
+pub async fn issue_auth_code(
+    redis: &redis::aio::ConnectionManager,
+    user: &User,
+    config: &Config
+) -> Result<String> {
+    let mut conn = redis.clone();
+    let user_id = user.id
+        .as_ref()
+        .ok_or_else(|| AppError::InternalError(anyhow::anyhow!("User has no ID")))?
+        .to_hex();
+    let code = random_token(48);
+
+    let record = AuthCodeRecord {
+        user_id,
+        tenant: config.tenant_database().map(str::to_string),
+    };
+    let record_json = serde_json::to_string(&record).map_err(|e| AppError::InternalError(e.into()))?;
+
+    conn.set_ex::<_, _, ()>(auth_code_key(&code), record_json, AUTH_CODE_SECONDS).await.map_err(redis_unavailable)?;
+
+    Ok(code)
+}
+
+/// Consumes a sign-in code and returns the user it was issued to. Each code
+/// works once.
+pub async fn consume_auth_code(redis: &redis::aio::ConnectionManager, code: &str, config: &Config) -> Result<String> {
+    let invalid = || AppError::BadRequest("Invalid or expired authorization code".to_string());
+    let mut conn = redis.clone();
+
+    let record_json: Option<String> = redis
+        ::cmd("GETDEL")
+        .arg(auth_code_key(code))
+        .query_async(&mut conn).await
+        .map_err(redis_unavailable)?;
+
+    let record: AuthCodeRecord = serde_json
+        ::from_str(&record_json.ok_or_else(invalid)?)
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if record.tenant.as_deref() != config.tenant_database() {
+        return Err(invalid());
+    }
+
+    Ok(record.user_id)
+}
+
+fn auth_code_key(code: &str) -> String {
+    format!("auth_code:{}", code)
+}
+
+/// Consumes `token` and returns the user it was issued to. Each refresh token
+/// works once; presenting a rotated one again means it leaked, so every
+/// refresh token of that user is revoked.
+pub async fn consume_refresh_token(
+    redis: &redis::aio::ConnectionManager,
+    token: &str,
+    config: &Config
+) -> Result<String> {
+    let invalid = || AppError::BadRequest("Invalid or expired refresh token".to_string());
+    let mut conn = redis.clone();
+
+    // GETDEL so two concurrent refreshes can't both use the token.
+    let record_json: Option<String> = redis
+        ::cmd("GETDEL")
+        .arg(refresh_token_key(token))
+        .query_async(&mut conn).await
+        .map_err(redis_unavailable)?;
+
+    let Some(record_json) = record_json else {
+        let reused_by: Option<String> = conn
+            .get(used_refresh_token_key(token)).await
+            .map_err(redis_unavailable)?;
+        if let Some(user_id) = reused_by {
+            tracing::warn!("Rotated refresh token reused for user {}; revoking all refresh tokens", user_id);
+            revoke_refresh_tokens(redis, &user_id).await?;
+        }
+        return Err(invalid());
+    };
+
+    let record: RefreshTokenRecord = serde_json
+        ::from_str(&record_json)
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let remaining_seconds = (record.expires_at - Utc::now()).num_seconds();
+    if remaining_seconds > 0 {
+        redis
+            ::pipe()
+            .set_ex(used_refresh_token_key(token), &record.user_id, remaining_seconds as u64)
+            .ignore()
+            .srem(user_refresh_tokens_key(&record.user_id), token)
+            .ignore()
+            .query_async::<_, ()>(&mut conn).await
+            .map_err(redis_unavailable)?;
+    }
+
+    // Tokens from another tenant's host were issued for a different database.
+    if remaining_seconds <= 0 || record.tenant.as_deref() != config.tenant_database() {
+        return Err(invalid());
+    }
+
+    Ok(record.user_id)
+}
+
+/// Revokes every refresh token of the user, e.g. on logout.
+pub async fn revoke_refresh_tokens(redis: &redis::aio::ConnectionManager, user_id: &str) -> Result<()> {
+    let mut conn = redis.clone();
+    let user_key = user_refresh_tokens_key(user_id);
+
+    let tokens: Vec<String> = conn
+        .smembers(&user_key).await
+        .map_err(redis_unavailable)?;
+
+    let mut keys: Vec<String> = tokens
+        .iter()
+        .map(|token| refresh_token_key(token))
+        .collect();
+    keys.push(user_key);
+
+    conn.del::<_, ()>(keys).await.map_err(redis_unavailable)?;
 
     Ok(())
 }
+
+/// Redis errors in session and refresh-token handling are outages, reported
+/// as a 503 rather than an internal error.
+fn redis_unavailable(e: redis::RedisError) -> AppError {
+    redis_health_service::note_failure(RedisFeature::Sessions, &e);
+    redis_health_service::unavailable(RedisFeature::Sessions)
+}
+
+/// Under the fail-open session policy, a Redis outage while storing or
+/// deleting a session is logged and ignored; otherwise `result` is returned.
+pub fn tolerate_outage(config: &Config, result: Result<()>) -> Result<()> {
+    match result {
+        Err(AppError::ExternalApiError(message)) if
+            RedisFeature::Sessions.policy(config) == RedisPolicy::FailOpen
+        => {
+            tracing::warn!("Continuing without Redis: {}", message);
+            Ok(())
+        }
+        other => other,
+    }
+}
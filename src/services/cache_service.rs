@@ -0,0 +1,128 @@
+use anyhow::Result;
+use redis::{ aio::ConnectionManager, AsyncCommands };
+use serde::{ de::DeserializeOwned, Deserialize, Serialize };
+use std::{ collections::BTreeMap, future::Future, sync::Mutex };
+use utoipa::IntoParams;
+
+/// A group of cached responses sharing a key prefix and TTL.
+#[derive(Debug, Clone, Copy)]
+pub struct CachePolicy {
+    pub name: &'static str,
+    pub ttl_seconds: u64,
+}
+
+pub const NINJA_NUTRITION: CachePolicy = CachePolicy { name: "ninja_nutrition", ttl_seconds: 24 * 3600 };
+/// FDC data changes with quarterly releases, so entries can live for a week.
+pub const FDC_SEARCH: CachePolicy = CachePolicy { name: "fdc_search", ttl_seconds: 7 * 24 * 3600 };
+pub const FDC_FOOD: CachePolicy = CachePolicy { name: "fdc_food", ttl_seconds: 7 * 24 * 3600 };
+pub const FDC_FOODS: CachePolicy = CachePolicy { name: "fdc_foods", ttl_seconds: 7 * 24 * 3600 };
+pub const MEALDB_SEARCH: CachePolicy = CachePolicy { name: "mealdb_search", ttl_seconds: 24 * 3600 };
+pub const MEALDB_MEAL: CachePolicy = CachePolicy { name: "mealdb_meal", ttl_seconds: 24 * 3600 };
+pub const MEALDB_CATEGORY: CachePolicy = CachePolicy { name: "mealdb_category", ttl_seconds: 24 * 3600 };
+pub const MEALDB_AREA: CachePolicy = CachePolicy { name: "mealdb_area", ttl_seconds: 24 * 3600 };
+
+/// `?no_cache=true` skips the cached copy and refreshes it from the upstream API.
+#[derive(Debug, Deserialize, Default, IntoParams)]
+pub struct CacheBypassQuery {
+    #[serde(default)]
+    pub no_cache: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CacheCounters {
+    pub hits: u64,
+    pub misses: u64,
+    pub bypasses: u64,
+    /// Redis reads or writes that failed; the request still went upstream.
+    pub errors: u64,
+}
+
+/// Read-through Redis cache for external API responses. Counters are per
+/// process and reset on restart.
+pub struct CacheService {
+    redis: ConnectionManager,
+    counters: Mutex<BTreeMap<&'static str, CacheCounters>>,
+}
+
+impl CacheService {
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self {
+            redis,
+            counters: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the cached value for `query`, or calls `fetch` and caches its
+    /// result. Upstream errors are returned as-is and never cached; Redis
+    /// errors only cost the cache.
+    pub async fn get_or_fetch<T, F, Fut>(
+        &self,
+        policy: CachePolicy,
+        query: &str,
+        bypass: bool,
+        fetch: F
+    ) -> Result<T>
+        where T: Serialize + DeserializeOwned, F: FnOnce() -> Fut, Fut: Future<Output = Result<T>>
+    {
+        let key = cache_key(policy.name, query);
+        let mut conn = self.redis.clone();
+
+        if bypass {
+            self.record(policy.name, |c| c.bypasses += 1);
+        } else {
+            match conn.get::<_, Option<String>>(&key).await {
+                Ok(Some(json)) =>
+                    match serde_json::from_str(&json) {
+                        Ok(value) => {
+                            tracing::debug!("Cache hit: {}", key);
+                            self.record(policy.name, |c| c.hits += 1);
+                            return Ok(value);
+                        }
+                        Err(e) => {
+                            tracing::warn!("Discarding unreadable cache entry {}: {}", key, e);
+                        }
+                    }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!("Cache read failed for {}: {}", key, e);
+                    self.record(policy.name, |c| c.errors += 1);
+                }
+            }
+            tracing::debug!("Cache miss: {}", key);
+            self.record(policy.name, |c| c.misses += 1);
+        }
+
+        let value = fetch().await?;
+
+        match serde_json::to_string(&value) {
+            Ok(json) => {
+                if let Err(e) = conn.set_ex::<_, _, ()>(&key, json, policy.ttl_seconds).await {
+                    tracing::warn!("Cache write failed for {}: {}", key, e);
+                    self.record(policy.name, |c| c.errors += 1);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to serialize cache entry {}: {}", key, e);
+            }
+        }
+
+        Ok(value)
+    }
+
+    pub fn stats(&self) -> BTreeMap<&'static str, CacheCounters> {
+        self.counters.lock().unwrap().clone()
+    }
+
+    fn record(&self, name: &'static str, update: impl FnOnce(&mut CacheCounters)) {
+        update(self.counters.lock().unwrap().entry(name).or_default());
+    }
+}
+
+/// Queries differing only in case or spacing share an entry.
+pub fn normalize_query(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+fn cache_key(name: &str, query: &str) -> String {
+    format!("cache:{}:{}", name, normalize_query(query))
+}
@@ -1,6 +1,10 @@
 use anyhow::Result;
-use mongodb::bson::{ doc, oid::ObjectId };
-use chrono::{ Utc, TimeZone };
+use mongodb::{
+    bson::{ doc, oid::ObjectId },
+    options::{ FindOneAndUpdateOptions, ReturnDocument },
+};
+use redis::AsyncCommands;
+use chrono::{ FixedOffset, NaiveDate, Utc, TimeZone };
 use serde::{ Deserialize, Serialize };
 use serde_json::{ json, Value };
 use std::sync::Arc;
@@ -8,10 +12,24 @@ use std::sync::Arc;
 use crate::{
     db::AppState,
     models::*,
-    services::{ gemini_service::GeminiService, email_service::EmailService },
+    services::{
+        chat_tools,
+        gemini_service::{ FunctionResponse, GeminiService, ModelPart, Turn },
+        email_service::EmailService,
+        meal_history_service::{ self, ChangeSource },
+        prompt_service::RenderedPrompt,
+        report_service,
+        monthly_stats_service,
+        progress_service,
+        timezone,
+        versioning,
+        water_service,
+        condition_guidance,
+    },
 };
 
 #[derive(Debug, Serialize)]
+#[allow(dead_code)]
 struct AgentRequest {
     user_context: UserContext,
     conversation_history: Vec<ChatMessageDto>,
@@ -25,6 +43,7 @@ struct UserContext {
     health_profile: Option<HealthProfile>,
     daily_targets: Option<DailyTargets>,
     has_completed_health_survey: bool,
+    today: NaiveDate,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,22 +60,83 @@ struct ChatMessageDto {
     content: String,
 }
 
-#[derive(Debug, Deserialize)]
-struct AgentResponse {
-    #[serde(default)]
-    response: String,
-    #[serde(default)]
-    tool_calls: Vec<ToolCallRequest>,
-}
-
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Clone)]
 struct ToolCallRequest {
     tool_name: String,
     parameters: Value,
 }
 
+/// One user message being answered. A change proposed in a turn can only be
+/// confirmed in a later one, after the user has seen it.
+#[derive(Debug, Clone, Copy)]
+struct AgentTurn {
+    session_id: ObjectId,
+    id: ObjectId,
+    /// The user's UTC offset, from their notification preferences, so "today"
+    /// and date ranges mean the user's local days.
+    offset: FixedOffset,
+}
+
+/// Rounds of function calls the model gets per message before it has to
+/// answer with what it has.
+const MAX_TOOL_ROUNDS: usize = 5;
+
+/// Longest custom range GET_NUTRITION_STATS accepts.
+const MAX_STATS_RANGE_DAYS: i64 = 366;
+
+/// How long a proposed meal change waits for the user's confirmation.
+const PENDING_ACTION_TTL_SECONDS: u64 = 10 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum MealAction {
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct MealChanges {
+    meal_type: Option<MealType>,
+    food_name: Option<String>,
+    calories: Option<f64>,
+    protein_g: Option<f64>,
+    carbs_g: Option<f64>,
+    fat_g: Option<f64>,
+    serving_size: Option<String>,
+    notes: Option<String>,
+    portion_weight_g: Option<f64>,
+}
+
+impl MealChanges {
+    fn is_empty(&self) -> bool {
+        self.meal_type.is_none() &&
+            self.food_name.is_none() &&
+            !self.changes_nutrition() &&
+            self.serving_size.is_none() &&
+            self.notes.is_none() &&
+            self.portion_weight_g.is_none()
+    }
+
+    fn changes_nutrition(&self) -> bool {
+        self.calories.is_some() || self.protein_g.is_some() || self.carbs_g.is_some() || self.fat_g.is_some()
+    }
+}
+
+/// A meal change waiting for confirmation, one per chat session.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingMealAction {
+    action: MealAction,
+    turn_id: String,
+    meal_id: String,
+    /// The meal's version when the change was proposed; an edit made since
+    /// then cancels it.
+    version: i64,
+    #[serde(default)]
+    changes: MealChanges,
+}
+
 pub struct ChatAgentService {
     gemini: Arc<GeminiService>,
+    #[allow(dead_code)]
     email_service: Arc<EmailService>,
 }
 
@@ -72,15 +152,20 @@ impl ChatAgentService {
         &self,
         state: &AppState,
         user_id: ObjectId,
-        _session_id: ObjectId,
+        session_id: ObjectId,
         message: &str,
         conversation_history: Vec<ChatMessage>
-    ) -> Result<(String, Vec<ToolCall>, Vec<ToolResult>)> {
+    ) -> Result<(String, Vec<ToolCall>, Vec<ToolResult>, String)> {
         let user = state.db
             .collection::<User>("users")
             .find_one(doc! { "_id": user_id }, None).await?
             .ok_or_else(|| anyhow::anyhow!("User not found"))?;
 
+        let offset = user.notification_preferences
+            .as_ref()
+            .and_then(|preferences| timezone::from_minutes(preferences.utc_offset_minutes))
+            .unwrap_or_else(timezone::utc);
+
         let user_context = UserContext {
             name: user.name.clone(),
             username: user.username.clone(),
@@ -92,6 +177,7 @@ impl ChatAgentService {
                 fat_g: hp.daily_fat_g,
             }),
             has_completed_health_survey: user.has_completed_health_survey.unwrap_or(false),
+            today: timezone::today(offset),
         };
 
         let history: Vec<ChatMessageDto> = conversation_history
@@ -102,208 +188,209 @@ impl ChatAgentService {
             })
             .collect();
 
-        let system_prompt = self.build_system_prompt(&user_context);
+        let system_prompt = self.build_system_prompt(&user_context)?;
 
-        let full_prompt = self.build_full_prompt(&system_prompt, &user_context, &history, message);
+        let mut turns = self.build_turns(&history, message);
+
+        let meal_source = if message.contains("[Image Analysis]") {
+            MealSource::AiImage
+        } else {
+            MealSource::AiText
+        };
+
+        let turn = AgentTurn { session_id, id: ObjectId::new(), offset };
 
         tracing::info!("Sending message to Gemini AI agent");
 
-        let ai_response = self.gemini.get_text_response(&full_prompt).await?;
+        let (response_text, tool_calls, tool_results) =
+            self.run_agent_loop(state, user_id, turn, &system_prompt.text, &mut turns, meal_source).await?;
 
         tracing::info!("Received response from Gemini AI agent");
 
-        let (response_text, tool_calls, tool_results) = self.parse_and_execute_tools(
-            state,
-            user_id,
-            &ai_response
-        ).await?;
-
-        Ok((response_text, tool_calls, tool_results))
+        Ok((response_text, tool_calls, tool_results, system_prompt.version))
     }
 
-    fn build_system_prompt(&self, user_context: &UserContext) -> String {
-        format!(
-            r#"You are Alimentify AI, a personal nutrition and meal tracking assistant. You are helping {}.
-
-YOUR CAPABILITIES (Tools you can use - ONLY for meal logging, stats, and reports):
-1. LOG_MEAL - Log a meal with nutritional information
-   Required parameters: meal_type (breakfast/lunch/dinner/snack), food_name, calories, protein_g, carbs_g, fat_g
-   Optional parameters: serving_size, notes
-2. GET_MEAL_LOGS - Retrieve past meal logs for a specific date or date range
-3. GET_NUTRITION_STATS - Get nutrition statistics for a time period
-   Parameters: period (daily/weekly/monthly/yearly) - defaults to weekly if not specified
-   Returns: consumed and target values for calories, protein, carbs, fat
-4. GET_HEALTH_PROFILE - Get user's health profile and goals
-5. GENERATE_REPORT - Generate and optionally email nutrition reports
-   Parameters: report_type (daily/weekly/monthly/yearly) - defaults to weekly, send_email (true/false)
-   Returns: report_id and report_url for viewing the detailed report
-6. CHECK_GOAL_PROGRESS - Check progress towards nutrition goals
-
-USER PROFILE:
-- Name: {}
-- Username: {}
-- Health Survey Completed: {}
-{}
-
-RESPONSE FORMAT:
-When you need to use a tool, respond in this EXACT JSON format:
-{{
-  "response": "Your message to the user explaining what you're doing",
-  "tool_calls": [
-    {{
-      "tool_name": "TOOL_NAME",
-      "parameters": {{
-        "param1": "value1",
-        "param2": "value2"
-      }}
-    }}
-  ]
-}}
-
-When just responding without tools, respond naturally in plain text.
-
-IMPORTANT GUIDELINES:
-1. Be friendly, conversational, and supportive - NEVER show raw JSON or technical data to users
-2. Always consider the user's health profile when making suggestions
-3. If the user hasn't completed their health survey, gently encourage them to do so
-4. When analyzing meals, be constructive and provide helpful feedback in natural language
-5. Proactively offer to help with meal logging, tracking, and goal setting
-6. Use tools when appropriate to provide accurate, data-driven responses
-7. Keep responses concise but informative
-8. When user sends a meal image with analysis results, extract ALL nutrition values and use LOG_MEAL
-9. For LOG_MEAL, you MUST provide all required numeric parameters: calories, protein_g, carbs_g, fat_g
-10. Always verify user intent before executing actions like sending emails
-11. When logging meals from images, parse the nutrition information from the message context
-12. CRITICAL: Transform image analysis data into friendly conversation - describe the food, nutrition, and health insights naturally
-13. Example: Instead of showing JSON, say "I can see that's a cheeseburger with about 550 calories, 25g protein, 45g carbs, and 30g fat. I'll log that for you!"
-14. When GENERATE_REPORT returns a report_url, ALWAYS include a clickable markdown link in your response
-    Example format: "Your weekly report is ready! [Click here to view it](http://localhost:3000/my/reports/ID)"
-    CRITICAL: NO SPACES between ]( in markdown links - must be ](URL) not ] (URL)
-
-CONVERSATION STYLE:
-- Use natural language, avoid being overly formal
-- Use emojis occasionally to be friendly (but not excessively)
-- Ask clarifying questions when needed
-- Provide context for your recommendations
-- Celebrate user achievements and progress
-"#,
-            user_context.name,
-            user_context.name,
-            user_context.username,
-            user_context.has_completed_health_survey,
-            if let Some(ref profile) = user_context.health_profile {
-                format!(
-                    "\n- Goal: {:?}\n- Daily Calorie Target: {:.0} kcal\n- Activity Level: {:?}",
-                    profile.goal,
-                    profile.daily_calories,
-                    profile.activity_level
-                )
-            } else {
-                "\n- No health profile set yet".to_string()
+    fn build_system_prompt(&self, user_context: &UserContext) -> Result<RenderedPrompt> {
+        let profile_section = if let Some(ref profile) = user_context.health_profile {
+            let mut section = format!(
+                "\n- Goal: {:?}\n- Daily Calorie Target: {:.0} kcal\n- Activity Level: {:?}",
+                profile.goal,
+                profile.daily_calories,
+                profile.activity_level
+            );
+            for day in &profile.target_cycle {
+                section.push_str(
+                    &format!(
+                        "\n- {} (ISO weekdays {:?}): {:.0} kcal, {:.0}g protein, {:.0}g carbs, {:.0}g fat",
+                        day.label,
+                        day.days_of_week,
+                        day.calories,
+                        day.protein_g,
+                        day.carbs_g,
+                        day.fat_g
+                    )
+                );
             }
-        )
-    }
-
-    fn build_full_prompt(
-        &self,
-        system_prompt: &str,
-        _user_context: &UserContext,
-        history: &[ChatMessageDto],
-        current_message: &str
-    ) -> String {
-        let mut prompt = format!("{}\n\nCONVERSATION HISTORY:\n", system_prompt);
+            // Meal plans and food suggestions from the assistant must respect these.
+            let guidance = condition_guidance::prompt_block(&condition_guidance::for_profile(profile));
+            if !guidance.is_empty() {
+                section.push_str(&format!("\n\n{}", guidance.trim_end()));
+            }
+            section
+        } else {
+            "\n- No health profile set yet".to_string()
+        };
 
-        let recent_history: Vec<&ChatMessageDto> = history.iter().rev().take(10).rev().collect();
+        self.gemini.prompts().render("chat_system", &[
+            ("name", user_context.name.clone()),
+            ("username", user_context.username.clone()),
+            ("health_survey_completed", user_context.has_completed_health_survey.to_string()),
+            ("today", user_context.today.format("%Y-%m-%d (%A)").to_string()),
+            ("profile_section", profile_section),
+        ])
+    }
 
-        for msg in recent_history {
-            prompt.push_str(&format!("{}: {}\n", msg.role.to_uppercase(), msg.content));
-        }
+    /// The last 10 history messages and the new one, as Gemini turns.
+    fn build_turns(&self, history: &[ChatMessageDto], current_message: &str) -> Vec<Turn> {
+        let mut turns: Vec<Turn> = history
+            .iter()
+            .rev()
+            .take(10)
+            .rev()
+            .filter_map(|msg| match msg.role.as_str() {
+                "user" => Some(Turn::User(msg.content.clone())),
+                "assistant" =>
+                    Some(
+                        Turn::Model(
+                            vec![ModelPart {
+                                text: Some(msg.content.clone()),
+                                function_call: None,
+                                thought_signature: None,
+                            }]
+                        )
+                    ),
+                _ => None,
+            })
+            .collect();
 
-        prompt.push_str(&format!("\nUSER: {}\n\nASSISTANT:", current_message));
+        turns.push(Turn::User(current_message.to_string()));
 
-        prompt
+        turns
     }
 
-    async fn parse_and_execute_tools(
+    /// Lets the model call tools until it answers in text. Each round's calls
+    /// run in order and their results go back in one turn; after
+    /// `MAX_TOOL_ROUNDS` the model must answer without more calls.
+    async fn run_agent_loop(
         &self,
         state: &AppState,
         user_id: ObjectId,
-        ai_response: &str
+        turn: AgentTurn,
+        system_prompt: &str,
+        turns: &mut Vec<Turn>,
+        meal_source: MealSource
     ) -> Result<(String, Vec<ToolCall>, Vec<ToolResult>)> {
-        if let Ok(agent_response) = serde_json::from_str::<AgentResponse>(ai_response) {
-            if !agent_response.tool_calls.is_empty() {
-                let mut tool_calls = Vec::new();
-                let mut tool_results = Vec::new();
+        let declarations = chat_tools::declarations();
+        let mut tool_calls = Vec::new();
+        let mut tool_results = Vec::new();
+
+        for round in 0..=MAX_TOOL_ROUNDS {
+            let reply = self.gemini.generate_with_tools(
+                system_prompt,
+                turns,
+                &declarations,
+                round < MAX_TOOL_ROUNDS
+            ).await?;
 
-                for tool_call in agent_response.tool_calls {
-                    tracing::info!("Executing tool: {}", tool_call.tool_name);
+            let requests: Vec<ToolCallRequest> = reply
+                .iter()
+                .filter_map(|part| part.function_call.as_ref())
+                .map(|call| ToolCallRequest {
+                    tool_name: call.name.clone(),
+                    parameters: call.args.clone(),
+                })
+                .collect();
 
-                    let result = self.execute_tool(state, user_id, &tool_call).await;
+            if requests.is_empty() {
+                let text = reply
+                    .iter()
+                    .filter_map(|part| part.text.as_deref())
+                    .collect::<Vec<&str>>()
+                    .join("");
+                return Ok((text, tool_calls, tool_results));
+            }
 
-                    let (success, result_value) = match result {
-                        Ok(value) => (true, value),
-                        Err(e) => {
-                            tracing::error!("Tool execution failed: {}", e);
-                            (false, json!({ "error": e.to_string() }))
-                        }
-                    };
-
-                    tool_calls.push(ToolCall {
-                        tool_name: tool_call.tool_name.clone(),
-                        parameters: tool_call.parameters.clone(),
-                    });
-
-                    tool_results.push(ToolResult {
-                        tool_name: tool_call.tool_name.clone(),
-                        result: result_value.clone(),
-                        success,
-                    });
-                }
+            turns.push(Turn::Model(reply));
 
-                let tool_results_text = tool_results
-                    .iter()
-                    .map(|tr|
-                        format!(
-                            "Tool: {}\nResult: {}",
-                            tr.tool_name,
-                            serde_json::to_string_pretty(&tr.result).unwrap_or_default()
-                        )
-                    )
-                    .collect::<Vec<String>>()
-                    .join("\n\n");
+            let mut responses = Vec::new();
+            for tool_call in requests {
+                tracing::info!("Executing tool: {}", tool_call.tool_name);
 
-                let follow_up_prompt = format!(
-                    "{}\n\nTOOL RESULTS:\n{}\n\nNow provide a natural, conversational response to the user using the tool results above. Format the data in a friendly, easy-to-read way.",
-                    agent_response.response,
-                    tool_results_text
-                );
+                let result = self.execute_tool(state, user_id, turn, &tool_call, meal_source).await;
 
-                let final_response = self.gemini.get_text_response(&follow_up_prompt).await?;
+                let (success, result_value) = match result {
+                    Ok(value) => (true, value),
+                    Err(e) => {
+                        tracing::error!("Tool execution failed: {}", e);
+                        (false, json!({ "error": e.to_string() }))
+                    }
+                };
 
-                return Ok((final_response, tool_calls, tool_results));
+                responses.push(FunctionResponse {
+                    name: tool_call.tool_name.clone(),
+                    response: result_value.clone(),
+                });
+
+                tool_results.push(ToolResult {
+                    tool_name: tool_call.tool_name.clone(),
+                    result: result_value,
+                    success,
+                });
+
+                tool_calls.push(ToolCall {
+                    tool_name: tool_call.tool_name,
+                    parameters: tool_call.parameters,
+                });
             }
 
-            return Ok((agent_response.response, vec![], vec![]));
+            turns.push(Turn::FunctionResponses(responses));
         }
 
-        Ok((ai_response.to_string(), vec![], vec![]))
+        Err(anyhow::anyhow!("Gemini kept calling tools after they were disabled"))
     }
 
     async fn execute_tool(
         &self,
         state: &AppState,
         user_id: ObjectId,
-        tool_call: &ToolCallRequest
+        turn: AgentTurn,
+        tool_call: &ToolCallRequest,
+        meal_source: MealSource
     ) -> Result<Value> {
         match tool_call.tool_name.as_str() {
-            "LOG_MEAL" => self.tool_log_meal(state, user_id, &tool_call.parameters).await,
+            "LOG_MEAL" =>
+                self.tool_log_meal(state, user_id, &tool_call.parameters, meal_source).await,
             "GET_MEAL_LOGS" => self.tool_get_meal_logs(state, user_id, &tool_call.parameters).await,
+            "UPDATE_MEAL" =>
+                self.tool_change_meal(state, user_id, turn, MealAction::Update, &tool_call.parameters).await,
+            "DELETE_MEAL" =>
+                self.tool_change_meal(state, user_id, turn, MealAction::Delete, &tool_call.parameters).await,
             "GET_NUTRITION_STATS" | "GET_DAILY_STATS" =>
-                self.tool_get_nutrition_stats(state, user_id, &tool_call.parameters).await,
-            "GET_HEALTH_PROFILE" => self.tool_get_health_profile(state, user_id).await,
+                self.tool_get_nutrition_stats(state, user_id, turn.offset, &tool_call.parameters).await,
+            "GET_HEALTH_PROFILE" => self.tool_get_health_profile(state, user_id, turn.offset).await,
             "GENERATE_REPORT" =>
                 self.tool_generate_report(state, user_id, &tool_call.parameters).await,
-            "CHECK_GOAL_PROGRESS" => self.tool_check_goal_progress(state, user_id).await,
+            "CHECK_GOAL_PROGRESS" => self.tool_check_goal_progress(state, user_id, turn.offset).await,
+            "GET_COMPLIANCE_SCORE" => self.tool_get_compliance_score(state, user_id).await,
+            "GET_ACHIEVEMENTS" => self.tool_get_achievements(state, user_id, turn.offset).await,
+            "GET_FOOD_QUALITY" =>
+                self.tool_get_food_quality(state, user_id, turn.offset, &tool_call.parameters).await,
+            "SUGGEST_SNACK" => self.tool_suggest_snack(state, user_id).await,
+            "LOOKUP_FOOD_NUTRITION" => self.tool_lookup_food_nutrition(state, &tool_call.parameters).await,
+            "SEARCH_RECIPES" => self.tool_suggest_recipes(state, user_id, &tool_call.parameters, false).await,
+            "RECOMMEND_RECIPE" => self.tool_suggest_recipes(state, user_id, &tool_call.parameters, true).await,
+            "LOG_WATER" => self.tool_log_water(state, user_id, turn.offset, &tool_call.parameters).await,
+            "LOG_FAVORITE" => self.tool_log_favorite(state, user_id, &tool_call.parameters).await,
             _ => Err(anyhow::anyhow!("Unknown tool: {}", tool_call.tool_name)),
         }
     }
@@ -312,7 +399,8 @@ CONVERSATION STYLE:
         &self,
         state: &AppState,
         user_id: ObjectId,
-        params: &Value
+        params: &Value,
+        meal_source: MealSource
     ) -> Result<Value> {
         let meal_type_str = params["meal_type"]
             .as_str()
@@ -336,16 +424,22 @@ CONVERSATION STYLE:
                 .unwrap_or(0.0)
         };
 
+        // Absent rather than 0 when the value isn't known.
+        let get_optional = |key: &str| -> Option<f64> {
+            (!params[key].is_null()).then(|| get_numeric(key)).filter(|value| *value >= 0.0)
+        };
+
         let calories = get_numeric("calories");
         let protein_g = get_numeric("protein_g");
         let carbs_g = get_numeric("carbs_g");
         let fat_g = get_numeric("fat_g");
+        let items = meal_items(&params["items"], meal_source)?;
 
-        if calories == 0.0 {
+        if calories == 0.0 && items.is_empty() {
             return Err(anyhow::anyhow!("calories must be greater than 0"));
         }
 
-        let meal_log = MealLog {
+        let mut meal_log = MealLog {
             id: None,
             user_id,
             date: Utc::now(),
@@ -358,13 +452,35 @@ CONVERSATION STYLE:
             protein_g,
             carbs_g,
             fat_g,
+            fiber_g: get_optional("fiber_g"),
+            sugar_g: get_optional("sugar_g"),
+            sodium_mg: get_optional("sodium_mg"),
+            items: Vec::new(),
             serving_size: params["serving_size"].as_str().map(|s| s.to_string()),
             notes: params["notes"].as_str().map(|s| s.to_string()),
+            source: meal_source,
+            verified: meal_source.is_verified(),
+            nutrition_snapshot: None,
+            portion_weight_g: Some(get_numeric("portion_weight_g")).filter(|w| *w > 0.0),
+            auto_logged: false,
+            recurring_meal_id: None,
+            hunger_level: None,
+            mood: None,
+            eating_context: None,
+            version: 0,
             created_at: Utc::now(),
         };
+        if !items.is_empty() {
+            meal_log.set_items(items);
+        }
 
         let result = state.db.collection::<MealLog>("meal_logs").insert_one(&meal_log, None).await?;
 
+        crate::services::report_service::invalidate_compliance_score(state, user_id).await;
+        crate::services::progress_service
+            ::record_meal_added(state, &meal_log).await
+            .map_err(|e| anyhow::anyhow!("Failed to update daily totals: {}", e))?;
+
         Ok(
             json!({
             "success": true,
@@ -429,8 +545,11 @@ CONVERSATION STYLE:
                 "protein_g": meal.protein_g,
                 "carbs_g": meal.carbs_g,
                 "fat_g": meal.fat_g,
+                "items": (!meal.items.is_empty()).then_some(&meal.items),
                 "serving_size": meal.serving_size,
                 "notes": meal.notes,
+                "source": meal.source,
+                "verified": meal.verified,
                 "date": meal.date.to_rfc3339(),
             })
             );
@@ -445,127 +564,258 @@ CONVERSATION STYLE:
         )
     }
 
-    async fn tool_get_nutrition_stats(
+    /// UPDATE_MEAL and DELETE_MEAL. Without `confirm`, finds the meal and
+    /// stores the change for the session without applying it; the assistant
+    /// asks the user, and a call with `confirm: true` in a later turn applies it.
+    async fn tool_change_meal(
         &self,
         state: &AppState,
         user_id: ObjectId,
+        turn: AgentTurn,
+        action: MealAction,
         params: &Value
     ) -> Result<Value> {
-        use futures::stream::TryStreamExt;
-
-        let period = params["period"].as_str().unwrap_or("weekly");
-
-        tracing::info!("GET_NUTRITION_STATS: Fetching {} stats", period);
+        if params["confirm"].as_bool() == Some(true) {
+            return self.confirm_meal_change(state, user_id, turn, action).await;
+        }
 
-        let today = Utc::now();
-        let (start_date, end_date) = match period {
-            "daily" => {
-                let start_of_day = chrono::Utc.from_utc_datetime(
-                    &today.date_naive().and_hms_opt(0, 0, 0).unwrap()
-                );
-                (start_of_day, start_of_day + chrono::Duration::days(1))
-            }
-            "monthly" => {
-                let start = today - chrono::Duration::days(29);
-                let start_of_period = chrono::Utc.from_utc_datetime(
-                    &start.date_naive().and_hms_opt(0, 0, 0).unwrap()
-                );
-                (start_of_period, today)
+        let changes = match action {
+            MealAction::Update => {
+                let changes = meal_changes(params)?;
+                if changes.is_empty() {
+                    return Err(anyhow::anyhow!("No changes given"));
+                }
+                changes
             }
-            "yearly" => {
-                let start = today - chrono::Duration::days(364);
-                let start_of_period = chrono::Utc.from_utc_datetime(
-                    &start.date_naive().and_hms_opt(0, 0, 0).unwrap()
+            MealAction::Delete => MealChanges::default(),
+        };
+
+        let meal = match find_meal_to_change(state, user_id, params).await? {
+            MealMatch::One(meal) => meal,
+            MealMatch::None => {
+                return Ok(
+                    json!({
+                    "success": false,
+                    "message": "No logged meal matches. Ask the user which meal they mean.",
+                })
                 );
-                (start_of_period, today)
             }
-            _ => {
-                let start = today - chrono::Duration::days(6);
-                let start_of_period = chrono::Utc.from_utc_datetime(
-                    &start.date_naive().and_hms_opt(0, 0, 0).unwrap()
+            MealMatch::Ambiguous(candidates) => {
+                return Ok(
+                    json!({
+                    "success": false,
+                    "message": "Several meals match. Ask the user which one they mean, then call again with its meal_id.",
+                    "candidates": candidates.iter().map(meal_summary).collect::<Vec<_>>(),
+                })
                 );
-                (start_of_period, today)
             }
         };
+        let meal_id = meal.id.ok_or_else(|| anyhow::anyhow!("Meal has no ID"))?;
+
+        let pending = PendingMealAction {
+            action,
+            turn_id: turn.id.to_hex(),
+            meal_id: meal_id.to_hex(),
+            version: meal.version,
+            changes,
+        };
+        let mut conn = state.redis.clone();
+        conn.set_ex::<_, _, ()>(
+            pending_action_key(turn.session_id),
+            serde_json::to_string(&pending)?,
+            PENDING_ACTION_TTL_SECONDS
+        ).await?;
 
-        let start_bson = mongodb::bson::DateTime::from_chrono(start_date);
-        let end_bson = mongodb::bson::DateTime::from_chrono(end_date);
-
-        tracing::info!("GET_NUTRITION_STATS: Querying meals from {} to {}", start_date, end_date);
-
-        let all_meals_cursor = state.db
-            .collection::<MealLog>("meal_logs")
-            .find(doc! { "user_id": user_id }, None).await?;
+        Ok(
+            json!({
+            "success": true,
+            "requires_confirmation": true,
+            "action": match action {
+                MealAction::Update => "update",
+                MealAction::Delete => "delete",
+            },
+            "meal": meal_summary(&meal),
+            "changes": (action == MealAction::Update).then_some(&pending.changes),
+            "message": "Nothing has changed yet. Describe the change and ask the user to confirm; when they do, call the same tool with confirm: true.",
+        })
+        )
+    }
 
-        let all_meals: Vec<MealLog> = all_meals_cursor.try_collect().await?;
-        tracing::info!("GET_NUTRITION_STATS: Total meals in DB for user: {}", all_meals.len());
+    async fn confirm_meal_change(
+        &self,
+        state: &AppState,
+        user_id: ObjectId,
+        turn: AgentTurn,
+        action: MealAction
+    ) -> Result<Value> {
+        let key = pending_action_key(turn.session_id);
+        let mut conn = state.redis.clone();
+        let pending: Option<String> = conn.get(&key).await?;
+        let pending: PendingMealAction = match pending {
+            Some(json) => serde_json::from_str(&json)?,
+            None => {
+                return Err(
+                    anyhow::anyhow!("No change is waiting for confirmation; it may have expired. Call again without confirm.")
+                );
+            }
+        };
 
-        for (i, meal) in all_meals.iter().take(3).enumerate() {
-            tracing::info!(
-                "GET_NUTRITION_STATS: Sample meal {}: {} at {}",
-                i + 1,
-                meal.food_name,
-                meal.date
-            );
+        if pending.action != action {
+            return Err(anyhow::anyhow!("The change waiting for confirmation is a different action"));
         }
-
-        let mut cursor = state.db
-            .collection::<MealLog>("meal_logs")
-            .find(
-                doc! {
-                    "user_id": user_id,
-                    "date": {
-                        "$gte": start_bson,
-                        "$lt": end_bson,
-                    }
-                },
-                None
-            ).await?;
-
-        let mut meals_in_range: Vec<MealLog> = Vec::new();
-        while let Some(meal) = cursor.try_next().await? {
-            meals_in_range.push(meal);
+        if pending.turn_id == turn.id.to_hex() {
+            return Err(anyhow::anyhow!("The user has to confirm the change in their next message"));
+        }
+        // Only the request that removes the pending change applies it.
+        let removed: u32 = conn.del(&key).await?;
+        if removed == 0 {
+            return Err(anyhow::anyhow!("The change was already confirmed"));
         }
 
-        tracing::info!("GET_NUTRITION_STATS: Found {} meals with date query", meals_in_range.len());
-
-        if meals_in_range.is_empty() && !all_meals.is_empty() {
-            tracing::warn!("GET_NUTRITION_STATS: Date query returned 0, filtering manually");
-            meals_in_range = all_meals
-                .into_iter()
-                .filter(|meal| {
-                    let meal_date = meal.date;
-                    let in_range = meal_date >= start_date && meal_date < end_date;
-                    if in_range {
-                        tracing::info!(
-                            "GET_NUTRITION_STATS: Manual filter matched: {} at {}",
-                            meal.food_name,
-                            meal_date
-                        );
+        let meal_id = ObjectId::parse_str(&pending.meal_id)?;
+        let filter =
+            doc! {
+            "_id": meal_id,
+            "user_id": user_id,
+            "version": versioning::version_filter(pending.version),
+        };
+        let changed_since = || anyhow::anyhow!("The meal was changed or deleted since the change was proposed. Look it up again.");
+
+        match action {
+            MealAction::Delete => {
+                let meal = state.db
+                    .collection::<MealLog>("meal_logs")
+                    .find_one_and_delete(filter, None).await?
+                    .ok_or_else(changed_since)?;
+
+                meal_history_service::record_delete(state, &meal, ChangeSource::assistant(turn.session_id)).await;
+                report_service::invalidate_compliance_score(state, user_id).await;
+                progress_service
+                    ::record_meal_removed(state, &meal).await
+                    .map_err(|e| anyhow::anyhow!("Failed to update daily totals: {}", e))?;
+
+                Ok(
+                    json!({
+                    "success": true,
+                    "deleted": meal_summary(&meal),
+                    "message": "Meal deleted successfully"
+                })
+                )
+            }
+            MealAction::Update => {
+                let changes = &pending.changes;
+                let mut set = doc! {};
+                if let Some(meal_type) = &changes.meal_type {
+                    set.insert("meal_type", meal_type.as_str());
+                }
+                if let Some(food_name) = &changes.food_name {
+                    set.insert("food_name", food_name);
+                }
+                for (field, value) in [
+                    ("calories", changes.calories),
+                    ("protein_g", changes.protein_g),
+                    ("carbs_g", changes.carbs_g),
+                    ("fat_g", changes.fat_g),
+                    ("portion_weight_g", changes.portion_weight_g),
+                ] {
+                    if let Some(value) = value {
+                        set.insert(field, value);
                     }
-                    in_range
+                }
+                if let Some(serving_size) = &changes.serving_size {
+                    set.insert("serving_size", serving_size);
+                }
+                if let Some(notes) = &changes.notes {
+                    set.insert("notes", notes);
+                }
+                let mut update = doc! { "$inc": { "version": 1_i64 } };
+                // Corrected numbers no longer come from the verified source,
+                // nor add up from the meal's items.
+                if changes.changes_nutrition() {
+                    set.insert("verified", false);
+                    update.insert("$unset", doc! { "items": "" });
+                }
+                update.insert("$set", set);
+
+                let previous = state.db
+                    .collection::<MealLog>("meal_logs")
+                    .find_one_and_update(
+                        filter,
+                        update,
+                        FindOneAndUpdateOptions::builder().return_document(ReturnDocument::Before).build()
+                    ).await?
+                    .ok_or_else(changed_since)?;
+
+                let delta = |new: Option<f64>, old: f64| new.map_or(0.0, |new| new - old);
+                let day = progress_service
+                    ::meal_day(state, &previous).await
+                    .map_err(|e| anyhow::anyhow!("Failed to update daily totals: {}", e))?;
+                progress_service
+                    ::apply_meal_delta(
+                        state,
+                        user_id,
+                        day,
+                        progress_service::MealDelta {
+                            calories: delta(changes.calories, previous.calories),
+                            protein_g: delta(changes.protein_g, previous.protein_g),
+                            carbs_g: delta(changes.carbs_g, previous.carbs_g),
+                            fat_g: delta(changes.fat_g, previous.fat_g),
+                            meal_count: 0,
+                        }
+                    ).await
+                    .map_err(|e| anyhow::anyhow!("Failed to update daily totals: {}", e))?;
+                report_service::invalidate_compliance_score(state, user_id).await;
+
+                let updated = state.db
+                    .collection::<MealLog>("meal_logs")
+                    .find_one(doc! { "_id": meal_id }, None).await?
+                    .ok_or_else(changed_since)?;
+                meal_history_service::record_update(
+                    state,
+                    &previous,
+                    &updated,
+                    ChangeSource::assistant(turn.session_id)
+                ).await;
+
+                Ok(
+                    json!({
+                    "success": true,
+                    "meal": meal_summary(&updated),
+                    "message": "Meal updated successfully"
                 })
-                .collect();
-            tracing::info!("GET_NUTRITION_STATS: Manually filtered {} meals", meals_in_range.len());
+                )
+            }
         }
+    }
 
-        let mut total_calories = 0.0;
-        let mut total_protein = 0.0;
-        let mut total_carbs = 0.0;
-        let mut total_fat = 0.0;
-        let meal_count = meals_in_range.len();
-
-        for meal in meals_in_range {
-            tracing::info!(
-                "GET_NUTRITION_STATS: Including meal - {} ({}cal)",
-                meal.food_name,
-                meal.calories
-            );
-            total_calories += meal.calories;
-            total_protein += meal.protein_g;
-            total_carbs += meal.carbs_g;
-            total_fat += meal.fat_g;
-        }
+    async fn tool_get_nutrition_stats(
+        &self,
+        state: &AppState,
+        user_id: ObjectId,
+        offset: FixedOffset,
+        params: &Value
+    ) -> Result<Value> {
+        let (period, start_date, end_date) = stats_range(params, timezone::today(offset))?;
+
+        tracing::info!("GET_NUTRITION_STATS: Querying {} meals from {} to {}", period, start_date, end_date);
+
+        // Whole days, so completed months come from their precomputed stats.
+        let daily = monthly_stats_service
+            ::daily_totals(state, user_id, start_date, end_date, offset).await
+            .map_err(|e| anyhow::anyhow!("Failed to total meals: {}", e))?;
+        let (total_calories, total_protein, total_carbs, total_fat) = daily
+            .iter()
+            .fold((0.0, 0.0, 0.0, 0.0), |(c, p, cb, f), day| {
+                (c + day.totals.calories, p + day.totals.protein_g, cb + day.totals.carbs_g, f + day.totals.fat_g)
+            });
+        let meal_count: usize = daily
+            .iter()
+            .map(|day| day.totals.meal_count)
+            .sum();
+        let days_logged = daily
+            .iter()
+            .filter(|day| day.totals.meal_count > 0)
+            .count();
 
         tracing::info!(
             "GET_NUTRITION_STATS: Totals - {} meals, calories: {}, protein: {}, carbs: {}, fat: {}",
@@ -581,22 +831,46 @@ CONVERSATION STYLE:
             .find_one(doc! { "_id": user_id }, None).await?
             .ok_or_else(|| anyhow::anyhow!("User not found"))?;
 
+        // A target cycle gives days different targets: one day uses its own,
+        // longer periods the mean over the days logged.
         let (target_calories, target_protein, target_carbs, target_fat) = if
             let Some(ref profile) = user.health_profile
         {
-            (
-                profile.daily_calories,
-                profile.daily_protein_g,
-                profile.daily_carbs_g,
-                profile.daily_fat_g,
-            )
+            if start_date == end_date {
+                profile.targets_on(end_date)
+            } else {
+                profile.average_targets(daily.iter().map(|day| day.date))
+            }
         } else {
             (2000.0, 50.0, 250.0, 70.0) 
         };
 
+        let average = |total: f64| if days_logged > 0 { total / (days_logged as f64) } else { 0.0 };
+        let days = (end_date - start_date).num_days() + 1;
+
+        // Water is logged against a date, so it isn't shifted by the offset.
+        let water = water_service
+            ::water_between(state, user_id, start_date, end_date).await
+            .map_err(|e| anyhow::anyhow!("Failed to total water: {}", e))?;
+        let water_ml: f64 = water.values().sum();
+        let water_target_ml = water_service::water_target_ml(&user);
+
         Ok(
             json!({
             "success": true,
+            "period": period,
+            "start_date": start_date.format("%Y-%m-%d").to_string(),
+            "end_date": end_date.format("%Y-%m-%d").to_string(),
+            "timezone": timezone::mongo_timezone(offset),
+            "days": days,
+            "days_logged": days_logged,
+            "meal_count": meal_count,
+            "daily_average": {
+                "calories": average(total_calories),
+                "protein_g": average(total_protein),
+                "carbs_g": average(total_carbs),
+                "fat_g": average(total_fat)
+            },
             "current": {
                 "calories": total_calories,
                 "protein_g": total_protein,
@@ -620,18 +894,26 @@ CONVERSATION STYLE:
                 "protein": (total_protein / target_protein * 100.0).min(100.0),
                 "carbs": (total_carbs / target_carbs * 100.0).min(100.0),
                 "fat": (total_fat / target_fat * 100.0).min(100.0)
+            },
+            "hydration": {
+                "water_ml": water_ml,
+                "target_ml_per_day": water_target_ml,
+                "daily_average_ml": water_ml / (days as f64),
+                "days_target_met": water.values().filter(|ml| **ml >= water_target_ml).count(),
+                "remaining_ml": (days == 1).then(|| (water_target_ml - water_ml).max(0.0))
             }
         })
         )
     }
 
-    async fn tool_get_health_profile(&self, state: &AppState, user_id: ObjectId) -> Result<Value> {
+    async fn tool_get_health_profile(&self, state: &AppState, user_id: ObjectId, offset: FixedOffset) -> Result<Value> {
         let user = state.db
             .collection::<User>("users")
             .find_one(doc! { "_id": user_id }, None).await?
             .ok_or_else(|| anyhow::anyhow!("User not found"))?;
 
         if let Some(profile) = user.health_profile {
+            let (calories, protein_g, carbs_g, fat_g) = profile.targets_on(timezone::today(offset));
             Ok(
                 json!({
                 "success": true,
@@ -648,8 +930,17 @@ CONVERSATION STYLE:
                     "daily_protein_g": profile.daily_protein_g,
                     "daily_carbs_g": profile.daily_carbs_g,
                     "daily_fat_g": profile.daily_fat_g,
+                    "target_cycle": profile.target_cycle,
+                    "todays_targets": {
+                        "calories": calories,
+                        "protein_g": protein_g,
+                        "carbs_g": carbs_g,
+                        "fat_g": fat_g
+                    },
                     "dietary_preferences": profile.dietary_preferences,
                     "allergies": profile.allergies,
+                    "medical_conditions": profile.medical_conditions,
+                    "condition_guidance": condition_guidance::for_profile(&profile),
                 }
             })
             )
@@ -669,26 +960,14 @@ CONVERSATION STYLE:
         user_id: ObjectId,
         params: &Value
     ) -> Result<Value> {
-        use crate::models::{ MealReport, ReportPeriod, ReportStatus, MealLog };
-        use crate::services::email_service::EmailService;
-        use chrono::{ Utc, Duration, Timelike };
-        use futures::stream::TryStreamExt;
+        use crate::models::{ MealReport, ReportPeriod };
+        use chrono::{ Utc, Duration };
 
         let report_type_str = params["report_type"].as_str().unwrap_or("weekly");
         let send_email = params["send_email"].as_bool().unwrap_or(false);
+        let language = params["language"].as_str().unwrap_or("English");
 
-        let user = state.db
-            .collection::<User>("users")
-            .find_one(doc! { "_id": user_id }, None).await?
-            .ok_or_else(|| anyhow::anyhow!("User not found"))?;
-
-        let report_type = match report_type_str.to_lowercase().as_str() {
-            "daily" => ReportPeriod::Daily,
-            "weekly" => ReportPeriod::Weekly,
-            "monthly" => ReportPeriod::Monthly,
-            "yearly" => ReportPeriod::Yearly,
-            _ => ReportPeriod::Weekly,
-        };
+        let report_type = ReportPeriod::parse(report_type_str).unwrap_or(ReportPeriod::Weekly);
 
         let now = Utc::now();
         let (start_date, end_date) = match report_type {
@@ -713,297 +992,21 @@ CONVERSATION STYLE:
             }
         };
 
-        let start_datetime = chrono::TimeZone::from_utc_datetime(
-            &chrono::Utc,
-            &start_date.and_hms_opt(0, 0, 0).unwrap()
-        );
-        let end_datetime = chrono::TimeZone::from_utc_datetime(
-            &chrono::Utc,
-            &end_date.and_hms_opt(23, 59, 59).unwrap()
-        );
-
-        let start_bson = mongodb::bson::DateTime::from_chrono(start_datetime);
-        let end_bson = mongodb::bson::DateTime::from_chrono(end_datetime);
-
-        tracing::info!(
-            "Chat Agent: Generating {} report for user {} from {} to {}",
-            report_type_str,
-            user_id,
-            start_datetime,
-            end_datetime
-        );
-
-        let mut cursor = state.db
-            .collection::<MealLog>("meal_logs")
-            .find(
-                doc! {
-                    "user_id": user_id,
-                    "date": {
-                        "$gte": start_bson,
-                        "$lte": end_bson,
-                    }
-                },
-                None
-            ).await?;
-
-        let mut meals: Vec<MealLog> = Vec::new();
-        while let Some(meal) = cursor.try_next().await? {
-            meals.push(meal);
-        }
-
-        tracing::info!("Chat Agent: Found {} meals with BSON date query", meals.len());
-
-        if meals.is_empty() {
-            tracing::warn!("Chat Agent: No meals found with BSON query, trying manual filtering");
-            let all_meals_cursor = state.db
-                .collection::<MealLog>("meal_logs")
-                .find(doc! { "user_id": user_id }, None).await?;
-
-            let all_meals: Vec<MealLog> = all_meals_cursor.try_collect().await?;
-
-            meals = all_meals
-                .into_iter()
-                .filter(|meal| {
-                    let meal_date = meal.date;
-                    meal_date >= start_datetime && meal_date <= end_datetime
-                })
-                .collect();
-
-            tracing::info!("Chat Agent: Manually filtered {} meals", meals.len());
-        }
-
-        let total_days = ((end_date - start_date).num_days() as usize) + 1;
-        let mut days_with_meals = std::collections::HashSet::new();
-        let mut total_calories = 0.0;
-        let mut total_protein = 0.0;
-        let mut total_carbs = 0.0;
-        let mut total_fat = 0.0;
-
-        for meal in &meals {
-            days_with_meals.insert(meal.date.date_naive());
-            total_calories += meal.calories;
-            total_protein += meal.protein_g;
-            total_carbs += meal.carbs_g;
-            total_fat += meal.fat_g;
-        }
-
-        let days_logged = days_with_meals.len();
-        let avg_calories = if days_logged > 0 {
-            total_calories / (days_logged as f64)
-        } else {
-            0.0
-        };
-        let avg_protein = if days_logged > 0 { total_protein / (days_logged as f64) } else { 0.0 };
-        let avg_carbs = if days_logged > 0 { total_carbs / (days_logged as f64) } else { 0.0 };
-        let avg_fat = if days_logged > 0 { total_fat / (days_logged as f64) } else { 0.0 };
-
-        let (target_calories, target_protein, target_carbs, target_fat, goal_type) = if
-            let Some(profile) = &user.health_profile
-        {
-            let goal = match profile.goal {
-                crate::models::HealthGoal::LoseWeight => "lose_weight".to_string(),
-                crate::models::HealthGoal::MaintainWeight => "maintain_weight".to_string(),
-                crate::models::HealthGoal::GainWeight => "gain_weight".to_string(),
-                crate::models::HealthGoal::BuildMuscle => "build_muscle".to_string(),
-            };
-            (
-                profile.daily_calories,
-                profile.daily_protein_g,
-                profile.daily_carbs_g,
-                profile.daily_fat_g,
-                goal,
-            )
-        } else {
-            (2000.0, 150.0, 250.0, 67.0, "maintain_weight".to_string())
-        };
-
-        let calories_compliance = if target_calories > 0.0 {
-            ((avg_calories / target_calories) * 100.0).min(100.0)
-        } else {
-            0.0
-        };
-        let protein_compliance = if target_protein > 0.0 {
-            ((avg_protein / target_protein) * 100.0).min(100.0)
-        } else {
-            0.0
-        };
-        let carbs_compliance = if target_carbs > 0.0 {
-            ((avg_carbs / target_carbs) * 100.0).min(100.0)
-        } else {
-            0.0
-        };
-        let fat_compliance = if target_fat > 0.0 {
-            ((avg_fat / target_fat) * 100.0).min(100.0)
-        } else {
-            0.0
-        };
-
-        let days_on_target = days_with_meals
-            .iter()
-            .filter(|date| {
-                let day_meals: Vec<&MealLog> = meals
-                    .iter()
-                    .filter(|m| m.date.date_naive() == **date)
-                    .collect();
-                let day_calories: f64 = day_meals
-                    .iter()
-                    .map(|m| m.calories)
-                    .sum();
-                let diff = (day_calories - target_calories).abs();
-                diff / target_calories <= 0.1
-            })
-            .count();
-
-        let avg_compliance =
-            (calories_compliance + protein_compliance + carbs_compliance + fat_compliance) / 4.0;
-        let goal_achieved =
-            avg_compliance >= 80.0 && (days_logged as f64) / (total_days as f64) >= 0.7;
-
-        let mut best_day_date = None;
-        let mut best_day_compliance = 0.0;
-        for date in &days_with_meals {
-            let day_meals: Vec<&MealLog> = meals
-                .iter()
-                .filter(|m| m.date.date_naive() == *date)
-                .collect();
-            let day_calories: f64 = day_meals
-                .iter()
-                .map(|m| m.calories)
-                .sum();
-            let day_protein: f64 = day_meals
-                .iter()
-                .map(|m| m.protein_g)
-                .sum();
-            let day_carbs: f64 = day_meals
-                .iter()
-                .map(|m| m.carbs_g)
-                .sum();
-            let day_fat: f64 = day_meals
-                .iter()
-                .map(|m| m.fat_g)
-                .sum();
-
-            let day_cal_comp = ((day_calories / target_calories) * 100.0).min(100.0);
-            let day_prot_comp = ((day_protein / target_protein) * 100.0).min(100.0);
-            let day_carb_comp = ((day_carbs / target_carbs) * 100.0).min(100.0);
-            let day_fat_comp = ((day_fat / target_fat) * 100.0).min(100.0);
-            let day_avg_comp = (day_cal_comp + day_prot_comp + day_carb_comp + day_fat_comp) / 4.0;
-
-            if day_avg_comp > best_day_compliance {
-                best_day_compliance = day_avg_comp;
-                best_day_date = Some(date.format("%Y-%m-%d").to_string());
-            }
-        }
-
-        let mut streak = 0;
-        let mut current_streak = 0;
-        let mut last_date: Option<chrono::NaiveDate> = None;
-        let mut sorted_dates: Vec<_> = days_with_meals.iter().collect();
-        sorted_dates.sort();
-
-        for date in sorted_dates {
-            if let Some(last) = last_date {
-                if (*date - last).num_days() == 1 {
-                    current_streak += 1;
-                } else {
-                    streak = streak.max(current_streak);
-                    current_streak = 1;
-                }
-            } else {
-                current_streak = 1;
-            }
-            last_date = Some(*date);
-        }
-        streak = streak.max(current_streak);
-
-        let (starting_weight, ending_weight, weight_change, target_weight, weight_goal_achieved) =
-            if let Some(profile) = &user.health_profile {
-                let starting = Some(profile.weight_kg);
-                let target = match profile.goal {
-                    crate::models::HealthGoal::LoseWeight => Some(profile.weight_kg * 0.9),
-                    crate::models::HealthGoal::GainWeight => Some(profile.weight_kg * 1.1),
-                    crate::models::HealthGoal::BuildMuscle => Some(profile.weight_kg * 1.05),
-                    crate::models::HealthGoal::MaintainWeight => Some(profile.weight_kg),
-                };
-                (starting, starting, Some(0.0), target, Some(false))
-            } else {
-                (None, None, None, None, None)
-            };
-
-        let report = MealReport {
-            id: None,
-            user_id,
-            report_type: report_type.clone(),
-            start_date: start_date.format("%Y-%m-%d").to_string(),
-            end_date: end_date.format("%Y-%m-%d").to_string(),
-            generated_at: Utc::now(),
-            status: if send_email {
-                ReportStatus::Sent
-            } else {
-                ReportStatus::Generated
-            },
-            total_days,
-            days_logged,
-            total_meals: meals.len(),
-            avg_calories,
-            avg_protein_g: avg_protein,
-            avg_carbs_g: avg_carbs,
-            avg_fat_g: avg_fat,
-            goal_type: goal_type.clone(),
-            goal_achieved,
-            calories_compliance_percent: calories_compliance,
-            protein_compliance_percent: protein_compliance,
-            carbs_compliance_percent: carbs_compliance,
-            fat_compliance_percent: fat_compliance,
-            days_on_target,
-            starting_weight,
-            ending_weight,
-            weight_change,
-            target_weight,
-            weight_goal_achieved,
-            best_day_date,
-            best_day_compliance: if best_day_compliance > 0.0 {
-                Some(best_day_compliance)
-            } else {
-                None
-            },
-            streak_days: streak,
-            notes: None,
-        };
+        let report = report_service::pending_report(user_id, report_type, start_date, end_date);
 
         let result = state.db
             .collection::<MealReport>("meal_reports")
             .insert_one(&report, None).await?;
 
-        let report_id = result.inserted_id.as_object_id().unwrap();
-        let mut saved_report = report.clone();
-        saved_report.id = Some(report_id);
-
-        if send_email {
-            let email_service = EmailService::new(
-                state.config.brevo.smtp_host.clone(),
-                state.config.brevo.smtp_port,
-                state.config.brevo.smtp_user.clone(),
-                state.config.brevo.smtp_pass.clone(),
-                state.config.brevo.from_email.clone(),
-                state.config.brevo.from_name.clone()
-            );
+        let report_id = result.inserted_id
+            .as_object_id()
+            .ok_or_else(|| anyhow::anyhow!("Report insert returned no ObjectId"))?;
 
-            if let Err(e) = email_service.send_report_email(&user, &saved_report).await {
-                tracing::error!("Chat Agent: Failed to send report email: {}", e);
-                state.db
-                    .collection::<MealReport>("meal_reports")
-                    .update_one(
-                        doc! { "_id": report_id },
-                        doc! { "$set": { "status": "Failed" } },
-                        None
-                    ).await?;
-            } else {
-                tracing::info!("Chat Agent: Report email sent successfully");
-            }
-        }
+        tokio::spawn(
+            report_service::run_report_job(state.clone(), report_id, send_email, language.to_string())
+        );
 
-        tracing::info!("Chat Agent: Report generated successfully with ID: {}", report_id);
+        tracing::info!("Chat Agent: Queued {} report {} for user {}", report_type_str, report_id, user_id);
 
         let report_url = format!(
             "{}/my/reports/{}",
@@ -1016,27 +1019,25 @@ CONVERSATION STYLE:
             "success": true,
             "report_id": report_id.to_hex(),
             "report_url": report_url,
+            "status": "pending",
             "report_type": report_type_str,
             "start_date": start_date.format("%Y-%m-%d").to_string(),
             "end_date": end_date.format("%Y-%m-%d").to_string(),
-            "days_logged": days_logged,
-            "total_days": total_days,
-            "goal_achieved": goal_achieved,
-            "avg_compliance": format!("{:.1}%", avg_compliance),
-            "email_sent": send_email,
+            "email_requested": send_email,
             "message": if send_email {
-                format!("Your {} report has been generated and sent to your email!", report_type_str)
+                format!("Your {} report is being generated and will be sent to your email when it's ready.", report_type_str)
             } else {
-                format!("Your {} report has been generated successfully!", report_type_str)
+                format!("Your {} report is being generated. You'll get a notification when it's ready.", report_type_str)
             }
         })
         )
     }
 
-    async fn tool_check_goal_progress(&self, state: &AppState, user_id: ObjectId) -> Result<Value> {
+    async fn tool_check_goal_progress(&self, state: &AppState, user_id: ObjectId, offset: FixedOffset) -> Result<Value> {
         let stats = self.tool_get_nutrition_stats(
             state,
             user_id,
+            offset,
             &json!({"period": "daily"})
         ).await?;
 
@@ -1061,18 +1062,522 @@ CONVERSATION STYLE:
         )
     }
 
-    pub async fn generate_chat_title(&self, first_message: &str) -> Result<String> {
-        let prompt =
-            format!(r#"Generate a short, concise title (maximum 5 words) for a chat conversation that starts with this message:
+    async fn tool_get_compliance_score(&self, state: &AppState, user_id: ObjectId) -> Result<Value> {
+        let score = crate::services::report_service
+            ::get_compliance_score(state, user_id).await
+            .map_err(|e| anyhow::anyhow!("Failed to compute compliance score: {}", e))?;
+
+        Ok(
+            json!({
+            "success": true,
+            "score": score.score,
+            "logging_score": score.logging_score,
+            "macro_score": score.macro_score,
+            "days_logged": score.days_logged,
+            "window_days": score.window_days,
+            "start_date": score.start_date,
+            "end_date": score.end_date,
+        })
+        )
+    }
+
+    async fn tool_get_achievements(
+        &self,
+        state: &AppState,
+        user_id: ObjectId,
+        offset: FixedOffset
+    ) -> Result<Value> {
+        let (achievements, new_badges) = crate::services::achievement_service
+            ::refresh(state, user_id, Some(offset)).await
+            .map_err(|e| anyhow::anyhow!("Failed to load achievements: {}", e))?;
+
+        let badge_names = |badges: &[Badge]| badges.iter().map(|badge| badge.name.clone()).collect::<Vec<_>>();
+
+        Ok(
+            json!({
+            "success": true,
+            "logging_streak": achievements.logging_streak,
+            "goal_streak": achievements.goal_streak,
+            "badges": badge_names(&achievements.badges),
+            "new_badges": badge_names(&new_badges),
+        })
+        )
+    }
+
+    async fn tool_get_food_quality(
+        &self,
+        state: &AppState,
+        user_id: ObjectId,
+        offset: FixedOffset,
+        params: &Value
+    ) -> Result<Value> {
+        let day = match params["date"].as_str() {
+            Some(date) => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")?,
+            None => crate::services::timezone::today(offset),
+        };
+
+        let user = state.db
+            .collection::<User>("users")
+            .find_one(doc! { "_id": user_id }, None).await?
+            .ok_or_else(|| anyhow::anyhow!("User not found"))?;
 
-"{}"
+        let quality = crate::services::food_quality_service
+            ::day_quality(state, user_id, user.health_profile.as_ref(), day, offset).await
+            .map_err(|e| anyhow::anyhow!("Failed to compute food quality: {}", e))?;
 
-Return ONLY the title, nothing else. Make it descriptive but brief."#, first_message);
+        Ok(match quality {
+            Some(quality) =>
+                json!({
+                    "success": true,
+                    "date": day.format("%Y-%m-%d").to_string(),
+                    "quality": quality,
+                }),
+            None =>
+                json!({
+                    "success": false,
+                    "date": day.format("%Y-%m-%d").to_string(),
+                    "message": "No meals logged on this day",
+                }),
+        })
+    }
+
+    async fn tool_suggest_snack(&self, state: &AppState, user_id: ObjectId) -> Result<Value> {
+        let result = crate::services::suggestion_service
+            ::suggest_snacks(state, user_id).await
+            .map_err(|e| anyhow::anyhow!("Failed to suggest snacks: {}", e))?;
 
-        let title = self.gemini.get_text_response(&prompt).await?;
+        Ok(
+            json!({
+            "success": true,
+            "remaining": result.remaining,
+            "suggestions": result.suggestions,
+        })
+        )
+    }
+
+    /// Looks the food up in real databases instead of leaving the numbers to
+    /// the model. Nothing is logged; `log_meal` holds LOG_MEAL parameters for
+    /// when the user wants it logged.
+    async fn tool_lookup_food_nutrition(&self, state: &AppState, params: &Value) -> Result<Value> {
+        use crate::services::food_lookup_service;
+
+        let query = params["query"]
+            .as_str()
+            .or_else(|| params["food_name"].as_str())
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("query is required"))?;
+
+        let lookup = food_lookup_service
+            ::lookup(state, query).await
+            .map_err(|e| anyhow::anyhow!("Failed to look up nutrition: {}", e))?;
+
+        let Some(lookup) = lookup else {
+            return Ok(
+                json!({
+                "success": false,
+                "message": format!("No nutrition data found for \"{}\"", query),
+            })
+            );
+        };
+
+        let total = |value: fn(&food_lookup_service::FoodNutrition) -> f64| -> f64 {
+            (lookup.foods.iter().map(value).sum::<f64>() * 10.0).round() / 10.0
+        };
+        let calories = total(|f| f.calories).round();
+        let protein_g = total(|f| f.protein_g);
+        let carbs_g = total(|f| f.carbs_g);
+        let fat_g = total(|f| f.fat_g);
+        let grams: Option<f64> = lookup.foods
+            .iter()
+            .map(|f| f.grams)
+            .sum();
+        let serving_size = lookup.foods
+            .iter()
+            .map(|f| f.serving_size.as_str())
+            .collect::<Vec<_>>()
+            .join(" + ");
+
+        Ok(
+            json!({
+            "success": true,
+            "query": query,
+            "source": lookup.source,
+            "foods": lookup.foods,
+            "total": {
+                "calories": calories,
+                "protein_g": protein_g,
+                "carbs_g": carbs_g,
+                "fat_g": fat_g,
+            },
+            "log_meal": {
+                "food_name": query,
+                "calories": calories,
+                "protein_g": protein_g,
+                "carbs_g": carbs_g,
+                "fat_g": fat_g,
+                "serving_size": serving_size,
+                "portion_weight_g": grams,
+            },
+        })
+        )
+    }
+
+    /// SEARCH_RECIPES needs a query and also returns recipes that don't fit
+    /// today's budget; RECOMMEND_RECIPE only returns ones that do.
+    async fn tool_suggest_recipes(
+        &self,
+        state: &AppState,
+        user_id: ObjectId,
+        params: &Value,
+        recommend: bool
+    ) -> Result<Value> {
+        let query = params["query"].as_str().map(str::trim).filter(|q| !q.is_empty());
+        if !recommend && query.is_none() {
+            return Err(anyhow::anyhow!("query is required"));
+        }
+        let meal_type = params["meal_type"].as_str().map(parse_meal_type).transpose()?;
+
+        let result = crate::services::suggestion_service
+            ::suggest_recipes(state, user_id, query, meal_type.as_ref(), recommend).await
+            .map_err(|e| anyhow::anyhow!("Failed to find recipes: {}", e))?;
+
+        Ok(
+            json!({
+            "success": true,
+            "remaining": result.remaining,
+            "recipes": result.recipes,
+            "hidden": result.hidden,
+            "message": if result.recipes.is_empty() {
+                "No recipes matched the user's preferences and remaining budget"
+            } else {
+                "Calories and macros are estimates for one of the recipe's servings"
+            }
+        })
+        )
+    }
+
+    async fn tool_log_water(&self, state: &AppState, user_id: ObjectId, offset: FixedOffset, params: &Value) -> Result<Value> {
+        let amount_ml = params["amount_ml"]
+            .as_f64()
+            .or_else(|| params["amount_ml"].as_str().and_then(|s| s.parse().ok()))
+            .ok_or_else(|| anyhow::anyhow!("amount_ml is required"))?;
+
+        if amount_ml <= 0.0 || amount_ml > water_service::MAX_WATER_LOG_ML {
+            return Err(
+                anyhow::anyhow!("amount_ml must be between 0 and {}", water_service::MAX_WATER_LOG_ML)
+            );
+        }
+
+        let user = state.db
+            .collection::<User>("users")
+            .find_one(doc! { "_id": user_id }, None).await?
+            .ok_or_else(|| anyhow::anyhow!("User not found"))?;
+
+        let today = timezone::today(offset);
+        let water_ml = water_service
+            ::log_water(state, user_id, today, amount_ml).await
+            .map_err(|e| anyhow::anyhow!("Failed to log water: {}", e))?;
+        let target_ml = water_service::water_target_ml(&user);
+
+        Ok(
+            json!({
+            "success": true,
+            "logged_ml": amount_ml,
+            "water_ml_today": water_ml,
+            "target_ml": target_ml,
+            "remaining_ml": (target_ml - water_ml).max(0.0),
+        })
+        )
+    }
+
+    async fn tool_log_favorite(&self, state: &AppState, user_id: ObjectId, params: &Value) -> Result<Value> {
+        use crate::services::favorite_service;
+
+        let name = params["food_name"]
+            .as_str()
+            .or_else(|| params["name"].as_str())
+            .ok_or_else(|| anyhow::anyhow!("food_name is required"))?;
+
+        let favorites = favorite_service
+            ::list_favorites(state, user_id).await
+            .map_err(|e| anyhow::anyhow!("Failed to load favorites: {}", e))?;
+
+        let Some(favorite) = favorite_service::match_by_name(&favorites, name) else {
+            return Ok(
+                json!({
+                "success": false,
+                "message": format!("No favorite food matches \"{}\"", name),
+                "favorites": favorites.iter().map(|f| f.food_name.as_str()).collect::<Vec<_>>(),
+            })
+            );
+        };
+
+        let meal_type = match params["meal_type"].as_str().map(|s| s.to_lowercase()) {
+            None => None,
+            Some(meal_type) =>
+                Some(match meal_type.as_str() {
+                    "breakfast" => MealType::Breakfast,
+                    "lunch" => MealType::Lunch,
+                    "dinner" => MealType::Dinner,
+                    "snack" => MealType::Snack,
+                    _ => {
+                        return Err(anyhow::anyhow!("Invalid meal_type"));
+                    }
+                }),
+        };
+        let servings = params["servings"]
+            .as_f64()
+            .or_else(|| params["servings"].as_str().and_then(|s| s.parse().ok()))
+            .unwrap_or(1.0);
+
+        let (meal, _) = favorite_service
+            ::log_favorite(state, favorite, meal_type, Utc::now(), servings).await
+            .map_err(|e| anyhow::anyhow!("Failed to log favorite: {}", e))?;
+
+        Ok(
+            json!({
+            "success": true,
+            "meal_id": meal.id.map(|id| id.to_hex()),
+            "food_name": meal.food_name,
+            "meal_type": meal.meal_type,
+            "calories": meal.calories,
+            "protein_g": meal.protein_g,
+            "carbs_g": meal.carbs_g,
+            "fat_g": meal.fat_g,
+            "message": "Favorite logged successfully"
+        })
+        )
+    }
+
+    pub async fn generate_chat_title(&self, first_message: &str) -> Result<String> {
+        let prompt = self.gemini.prompts().render("chat_title", &[
+            ("message", first_message.to_string()),
+        ])?;
+
+        let title = self.gemini.get_text_response(&prompt.text).await?;
 
         let clean_title = title.trim().trim_matches('"').chars().take(50).collect::<String>();
 
         Ok(if clean_title.is_empty() { "New Chat".to_string() } else { clean_title })
     }
 }
+
+fn pending_action_key(session_id: ObjectId) -> String {
+    format!("chat_pending_action:{}", session_id.to_hex())
+}
+
+/// The days GET_NUTRITION_STATS covers, both inclusive: `start_date` and
+/// `end_date` when given, otherwise the `period` preset ending today.
+fn stats_range(params: &Value, today: NaiveDate) -> Result<(&'static str, NaiveDate, NaiveDate)> {
+    let parse_date = |field: &str| -> Result<Option<NaiveDate>> {
+        params[field]
+            .as_str()
+            .map(|value| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_|
+                    anyhow::anyhow!("{} must be a date in YYYY-MM-DD format", field)
+                )
+            })
+            .transpose()
+    };
+
+    let start = parse_date("start_date")?;
+    let end = parse_date("end_date")?;
+
+    if start.is_none() && end.is_none() {
+        let (period, days) = match params["period"].as_str().unwrap_or("weekly") {
+            "daily" => ("daily", 1),
+            "monthly" => ("monthly", 30),
+            "yearly" => ("yearly", 365),
+            _ => ("weekly", 7),
+        };
+        return Ok((period, today - chrono::Duration::days(days - 1), today));
+    }
+
+    let end = end.unwrap_or(today).min(today);
+    let start = start.ok_or_else(|| anyhow::anyhow!("start_date is required with end_date"))?;
+    if start > end {
+        return Err(anyhow::anyhow!("start_date must be on or before end_date and not in the future"));
+    }
+    if (end - start).num_days() >= MAX_STATS_RANGE_DAYS {
+        return Err(anyhow::anyhow!("The range cannot be longer than {} days", MAX_STATS_RANGE_DAYS));
+    }
+
+    Ok(("custom", start, end))
+}
+
+fn parse_meal_type(value: &str) -> Result<MealType> {
+    match value.to_lowercase().as_str() {
+        "breakfast" => Ok(MealType::Breakfast),
+        "lunch" => Ok(MealType::Lunch),
+        "dinner" => Ok(MealType::Dinner),
+        "snack" => Ok(MealType::Snack),
+        _ => Err(anyhow::anyhow!("Invalid meal_type")),
+    }
+}
+
+/// The fields UPDATE_MEAL was asked to change.
+fn meal_changes(params: &Value) -> Result<MealChanges> {
+    let number = |key: &str| -> Result<Option<f64>> {
+        let value = params[key]
+            .as_f64()
+            .or_else(|| params[key].as_str().and_then(|s| s.parse::<f64>().ok()));
+        match value {
+            Some(value) if !value.is_finite() || value < 0.0 => Err(anyhow::anyhow!("{} must not be negative", key)),
+            value => Ok(value),
+        }
+    };
+    let text = |key: &str| params[key].as_str().map(|s| s.trim().to_string());
+
+    let changes = MealChanges {
+        meal_type: params["new_meal_type"].as_str().map(parse_meal_type).transpose()?,
+        food_name: text("new_food_name").filter(|name| !name.is_empty()),
+        calories: number("calories")?,
+        protein_g: number("protein_g")?,
+        carbs_g: number("carbs_g")?,
+        fat_g: number("fat_g")?,
+        serving_size: text("serving_size"),
+        notes: text("notes"),
+        portion_weight_g: number("portion_weight_g")?.filter(|w| *w > 0.0),
+    };
+    if changes.calories == Some(0.0) {
+        return Err(anyhow::anyhow!("calories must be greater than 0"));
+    }
+    Ok(changes)
+}
+
+/// LOG_MEAL's `items`, for a meal of several foods. Empty when absent.
+fn meal_items(items: &Value, source: MealSource) -> Result<Vec<MealEntry>> {
+    let Some(items) = items.as_array() else {
+        return Ok(Vec::new());
+    };
+
+    items
+        .iter()
+        .map(|item| {
+            let number = |key: &str| -> Result<Option<f64>> {
+                let value = item[key]
+                    .as_f64()
+                    .or_else(|| item[key].as_str().and_then(|s| s.parse::<f64>().ok()));
+                match value {
+                    Some(value) if !value.is_finite() || value < 0.0 =>
+                        Err(anyhow::anyhow!("{} of an item must not be negative", key)),
+                    value => Ok(value),
+                }
+            };
+            let name = item["name"]
+                .as_str()
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("Every item needs a name"))?;
+
+            Ok(MealEntry {
+                name: name.to_string(),
+                quantity: item["quantity"].as_str().map(|s| s.to_string()),
+                calories: number("calories")?.unwrap_or(0.0),
+                protein_g: number("protein_g")?.unwrap_or(0.0),
+                carbs_g: number("carbs_g")?.unwrap_or(0.0),
+                fat_g: number("fat_g")?.unwrap_or(0.0),
+                fiber_g: number("fiber_g")?,
+                sugar_g: number("sugar_g")?,
+                sodium_mg: number("sodium_mg")?,
+                source,
+            })
+        })
+        .collect()
+}
+
+enum MealMatch {
+    One(Box<MealLog>),
+    None,
+    Ambiguous(Vec<MealLog>),
+}
+
+/// By `meal_id`, or else by `food_name` among the meals logged on `date`
+/// (today by default), optionally narrowed by `meal_type`.
+async fn find_meal_to_change(state: &AppState, user_id: ObjectId, params: &Value) -> Result<MealMatch> {
+    use futures::stream::TryStreamExt;
+
+    let meals = state.db.collection::<MealLog>("meal_logs");
+
+    if let Some(meal_id) = params["meal_id"].as_str() {
+        let meal_id = ObjectId::parse_str(meal_id).map_err(|_| anyhow::anyhow!("Invalid meal_id"))?;
+        let meal = meals.find_one(doc! { "_id": meal_id, "user_id": user_id }, None).await?;
+        return Ok(meal.map_or(MealMatch::None, |meal| MealMatch::One(Box::new(meal))));
+    }
+
+    let food_name = params["food_name"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("meal_id or food_name is required"))?;
+    let day = match params["date"].as_str() {
+        Some(date) => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")?,
+        None => Utc::now().date_naive(),
+    };
+
+    let mut filter =
+        doc! {
+        "user_id": user_id,
+        "date": {
+            "$gte": mongodb::bson::DateTime::from_chrono(timezone::start_of_day(day, timezone::utc())),
+            "$lt": mongodb::bson::DateTime::from_chrono(timezone::start_of_day(day + chrono::Duration::days(1), timezone::utc())),
+        },
+    };
+    if let Some(meal_type) = params["meal_type"].as_str() {
+        filter.insert("meal_type", parse_meal_type(meal_type)?.as_str());
+    }
+
+    let candidates: Vec<MealLog> = meals.find(filter, None).await?.try_collect().await?;
+    let scored: Vec<(u8, MealLog)> = candidates
+        .into_iter()
+        .map(|meal| (name_match_score(&meal.food_name, food_name), meal))
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    let Some(best) = scored.iter().map(|(score, _)| *score).max() else {
+        return Ok(MealMatch::None);
+    };
+
+    let mut best_matches: Vec<MealLog> = scored
+        .into_iter()
+        .filter(|(score, _)| *score == best)
+        .map(|(_, meal)| meal)
+        .collect();
+    Ok(if best_matches.len() == 1 { MealMatch::One(Box::new(best_matches.remove(0))) } else { MealMatch::Ambiguous(best_matches) })
+}
+
+/// 3 for the same name, 2 when one contains the other, 1 when they share a
+/// word, 0 otherwise. Case is ignored.
+fn name_match_score(logged: &str, query: &str) -> u8 {
+    let logged = logged.trim().to_lowercase();
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return 0;
+    }
+    if logged == query {
+        return 3;
+    }
+    if logged.contains(&query) || query.contains(&logged) {
+        return 2;
+    }
+    let words = |s: &str| {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.chars().count() >= 3)
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    let logged_words = words(&logged);
+    if words(&query).iter().any(|w| logged_words.contains(w)) { 1 } else { 0 }
+}
+
+fn meal_summary(meal: &MealLog) -> Value {
+    json!({
+        "meal_id": meal.id.map(|id| id.to_hex()),
+        "meal_type": meal.meal_type.as_str(),
+        "food_name": meal.food_name,
+        "calories": meal.calories,
+        "protein_g": meal.protein_g,
+        "carbs_g": meal.carbs_g,
+        "fat_g": meal.fat_g,
+        "serving_size": meal.serving_size,
+        "date": meal.date.to_rfc3339(),
+    })
+}
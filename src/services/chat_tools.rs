@@ -0,0 +1,227 @@
+//! Function declarations for the chat agent's tools, sent to Gemini with every
+//! chat request. Names match the tool handlers in `chat_agent_service`.
+
+use serde_json::{ json, Map, Value };
+
+use crate::services::gemini_service::FunctionDeclaration;
+
+fn object(properties: Value, required: &[&str]) -> Option<Value> {
+    Some(json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    }))
+}
+
+const MEAL_TYPE: &str = "breakfast, lunch, dinner or snack";
+
+/// Parameters UPDATE_MEAL and DELETE_MEAL use to find the meal.
+fn meal_selector() -> Map<String, Value> {
+    let selector = json!({
+        "meal_id": { "type": "string", "description": "ID from GET_MEAL_LOGS or an earlier result" },
+        "food_name": { "type": "string", "description": "Name of the logged food, when meal_id isn't known" },
+        "date": { "type": "string", "description": "YYYY-MM-DD, defaults to today" },
+        "meal_type": { "type": "string", "description": MEAL_TYPE },
+        "confirm": {
+            "type": "boolean",
+            "description": "Only true after the user agreed, in a later message, to the change proposed by the previous call",
+        },
+    });
+    selector.as_object().cloned().unwrap_or_default()
+}
+
+pub fn declarations() -> Vec<FunctionDeclaration> {
+    let mut update_meal = meal_selector();
+    for (field, kind) in [
+        ("new_food_name", "string"),
+        ("new_meal_type", "string"),
+        ("calories", "number"),
+        ("protein_g", "number"),
+        ("carbs_g", "number"),
+        ("fat_g", "number"),
+        ("serving_size", "string"),
+        ("notes", "string"),
+        ("portion_weight_g", "number"),
+    ] {
+        update_meal.insert(field.to_string(), json!({ "type": kind }));
+    }
+
+    vec![
+        FunctionDeclaration {
+            name: "LOG_MEAL",
+            description: "Log a meal with its nutrition. For meal images, use the values from the image analysis. For a plate of several foods, give items instead of calories and macros; the meal's totals are their sums.",
+            parameters: object(
+                json!({
+                    "meal_type": { "type": "string", "description": MEAL_TYPE },
+                    "food_name": { "type": "string" },
+                    "calories": { "type": "number" },
+                    "protein_g": { "type": "number" },
+                    "carbs_g": { "type": "number" },
+                    "fat_g": { "type": "number" },
+                    "fiber_g": { "type": "number", "description": "Only when known, e.g. from an image analysis" },
+                    "sugar_g": { "type": "number", "description": "Only when known" },
+                    "sodium_mg": { "type": "number", "description": "Only when known" },
+                    "serving_size": { "type": "string" },
+                    "notes": { "type": "string" },
+                    "portion_weight_g": {
+                        "type": "number",
+                        "description": "Grams, from estimated_weight_g in an image analysis or a weight the user gives",
+                    },
+                    "items": {
+                        "type": "array",
+                        "description": "The foods of a meal made of several, each with its own nutrition",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "quantity": { "type": "string", "description": "e.g. 1 cup or 150 g" },
+                                "calories": { "type": "number" },
+                                "protein_g": { "type": "number" },
+                                "carbs_g": { "type": "number" },
+                                "fat_g": { "type": "number" },
+                                "fiber_g": { "type": "number", "description": "Only when known" },
+                                "sugar_g": { "type": "number", "description": "Only when known" },
+                                "sodium_mg": { "type": "number", "description": "Only when known" },
+                            },
+                            "required": ["name", "calories", "protein_g", "carbs_g", "fat_g"],
+                        },
+                    },
+                }),
+                &["meal_type", "food_name"]
+            ),
+        },
+        FunctionDeclaration {
+            name: "GET_MEAL_LOGS",
+            description: "The meals logged on a day.",
+            parameters: object(json!({ "date": { "type": "string", "description": "YYYY-MM-DD, defaults to today" } }), &[]),
+        },
+        FunctionDeclaration {
+            name: "UPDATE_MEAL",
+            description: "Correct a logged meal. Pass only the fields that change. The first call changes nothing and returns requires_confirmation; if several meals match, it returns candidates to ask the user about.",
+            parameters: object(Value::Object(update_meal), &[]),
+        },
+        FunctionDeclaration {
+            name: "DELETE_MEAL",
+            description: "Remove a meal logged by mistake. The first call deletes nothing and returns requires_confirmation; if several meals match, it returns candidates to ask the user about.",
+            parameters: object(Value::Object(meal_selector()), &[]),
+        },
+        FunctionDeclaration {
+            name: "GET_NUTRITION_STATS",
+            description: "Consumed calories, protein, carbs and fat over a period of the user's local days, with daily averages and targets, and water drunk against the daily water target. Use start_date and end_date for a specific range, e.g. a holiday or Ramadan. Use period daily for \"have I drunk enough water today?\".",
+            parameters: object(
+                json!({
+                    "period": {
+                        "type": "string",
+                        "enum": ["daily", "weekly", "monthly", "yearly"],
+                        "description": "The last 1, 7, 30 or 365 days, ending today",
+                    },
+                    "start_date": { "type": "string", "description": "YYYY-MM-DD, first day of a custom range" },
+                    "end_date": { "type": "string", "description": "YYYY-MM-DD, last day of a custom range, defaults to today" },
+                }),
+                &[]
+            ),
+        },
+        FunctionDeclaration {
+            name: "GET_HEALTH_PROFILE",
+            description: "The user's health profile, goal and daily targets.",
+            parameters: None,
+        },
+        FunctionDeclaration {
+            name: "GENERATE_REPORT",
+            description: "Start generating a nutrition report in the background, optionally emailing it. Returns report_url; the user is notified when it's ready.",
+            parameters: object(
+                json!({
+                    "report_type": { "type": "string", "enum": ["daily", "weekly", "monthly", "yearly"] },
+                    "send_email": { "type": "boolean" },
+                    "language": { "type": "string", "description": "The language the user is writing in, e.g. Indonesian" },
+                }),
+                &[]
+            ),
+        },
+        FunctionDeclaration {
+            name: "CHECK_GOAL_PROGRESS",
+            description: "Today's progress towards the user's nutrition goal.",
+            parameters: None,
+        },
+        FunctionDeclaration {
+            name: "GET_COMPLIANCE_SCORE",
+            description: "The user's 0-100 consistency score for the last 7 days, with logging_score and macro_score.",
+            parameters: None,
+        },
+        FunctionDeclaration {
+            name: "GET_ACHIEVEMENTS",
+            description: "The user's logging streak and goal streak (days in a row with calories within 10% of target), current and longest, their badges for 7, 30 and 100-day streaks, and new_badges earned just now.",
+            parameters: None,
+        },
+        FunctionDeclaration {
+            name: "GET_FOOD_QUALITY",
+            description: "A day's 0-100 food quality score from protein, fiber, sugar and sodium, with the deductions that lowered it, biggest first.",
+            parameters: object(json!({ "date": { "type": "string", "description": "YYYY-MM-DD, defaults to today" } }), &[]),
+        },
+        FunctionDeclaration {
+            name: "SUGGEST_SNACK",
+            description: "3-5 snacks that fit the calories and macros the user has left today.",
+            parameters: None,
+        },
+        FunctionDeclaration {
+            name: "LOOKUP_FOOD_NUTRITION",
+            description: "Calories and macros for a food from nutrition databases. Logs nothing; log_meal holds LOG_MEAL parameters.",
+            parameters: object(
+                json!({
+                    "query": {
+                        "type": "string",
+                        "description": "The food with its amount as the user said it, e.g. \"200g salmon\"",
+                    },
+                }),
+                &["query"]
+            ),
+        },
+        FunctionDeclaration {
+            name: "SEARCH_RECIPES",
+            description: "Up to 3 recipes by name or main ingredient, without ones that conflict with the user's diet, allergies or conditions. Includes links, estimated macros per serving and whether a serving fits today's budget.",
+            parameters: object(
+                json!({
+                    "query": { "type": "string" },
+                    "meal_type": { "type": "string", "description": MEAL_TYPE },
+                }),
+                &["query"]
+            ),
+        },
+        FunctionDeclaration {
+            name: "RECOMMEND_RECIPE",
+            description: "Up to 3 recipes to cook whose serving fits the calories left today, e.g. for \"what should I cook tonight?\".",
+            parameters: object(
+                json!({
+                    "meal_type": { "type": "string", "description": "Use dinner for \"tonight\"" },
+                    "query": { "type": "string", "description": "An ingredient or dish the user mentions" },
+                }),
+                &[]
+            ),
+        },
+        FunctionDeclaration {
+            name: "LOG_WATER",
+            description: "Record water the user drank today.",
+            parameters: object(
+                json!({
+                    "amount_ml": {
+                        "type": "number",
+                        "description": "1 glass = 250 ml and 1 bottle = 600 ml unless the user says otherwise",
+                    },
+                }),
+                &["amount_ml"]
+            ),
+        },
+        FunctionDeclaration {
+            name: "LOG_FAVORITE",
+            description: "Log one of the user's saved favorite foods, e.g. \"log my usual oatmeal\". If nothing matches, favorites lists the saved names.",
+            parameters: object(
+                json!({
+                    "food_name": { "type": "string", "description": "The favorite's name, or part of it" },
+                    "meal_type": { "type": "string", "description": "Defaults to the favorite's meal type" },
+                    "servings": { "type": "number", "description": "Defaults to 1" },
+                }),
+                &["food_name"]
+            ),
+        },
+    ]
+}
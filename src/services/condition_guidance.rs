@@ -0,0 +1,213 @@
+use crate::models::{ ConditionGuidance, HealthProfile, MedicalCondition };
+
+/// WHO/AHA guidance for adults with hypertension.
+const HYPERTENSION_SODIUM_LIMIT_MG: f64 = 1500.0;
+/// Share of the daily carbohydrate target allowed in one main meal, so the
+/// day's carbs are spread over three meals and a snack.
+const MEAL_CARB_SHARE: f64 = 0.3;
+const SNACK_CARB_SHARE: f64 = 0.1;
+const MAX_CARBS_PER_MEAL_G: f64 = 75.0;
+const MAX_CARBS_PER_SNACK_G: f64 = 25.0;
+
+const HYPERTENSION_KEYWORDS: &[&str] = &[
+    "hypertension",
+    "high blood pressure",
+    "hipertensi",
+    "darah tinggi",
+];
+const DIABETES_KEYWORDS: &[&str] = &["diabetes", "t2d", "kencing manis"];
+/// Type 1 diabetes needs insulin-dose-specific advice the type 2 rules don't cover.
+const TYPE1_KEYWORDS: &[&str] = &["type 1", "type i ", "t1d", "tipe 1"];
+const GOUT_KEYWORDS: &[&str] = &["gout", "hyperuricemia", "asam urat"];
+
+const HIGH_SODIUM_INGREDIENTS: &[&str] = &[
+    "soy sauce",
+    "fish sauce",
+    "oyster sauce",
+    "stock cube",
+    "bouillon",
+    "bacon",
+    "ham",
+    "salami",
+    "chorizo",
+    "anchovy",
+    "anchovies",
+    "parmesan",
+    "pickle",
+];
+const ADDED_SUGAR_INGREDIENTS: &[&str] = &[
+    "sugar",
+    "syrup",
+    "honey",
+    "condensed milk",
+    "molasses",
+    "treacle",
+    "jam",
+];
+const HIGH_PURINE_INGREDIENTS: &[&str] = &[
+    "liver",
+    "lamb kidney",
+    "pork kidney",
+    "beef kidney",
+    "kidneys",
+    "anchovy",
+    "anchovies",
+    "sardine",
+    "herring",
+    "mackerel",
+    "mussels",
+    "scallops",
+    "prawns",
+    "shrimp",
+    "beer",
+    "offal",
+];
+
+/// Conditions recognised in the free-text `medical_conditions` list.
+pub fn detect(medical_conditions: Option<&[String]>) -> Vec<MedicalCondition> {
+    let Some(conditions) = medical_conditions else {
+        return Vec::new();
+    };
+
+    let mut found = Vec::new();
+    for condition in conditions {
+        let text = format!("{} ", condition.trim().to_lowercase());
+        let matches = |keywords: &[&str]| keywords.iter().any(|k| text.contains(k));
+
+        let kind = if matches(HYPERTENSION_KEYWORDS) {
+            Some(MedicalCondition::Hypertension)
+        } else if matches(DIABETES_KEYWORDS) && !matches(TYPE1_KEYWORDS) {
+            Some(MedicalCondition::Type2Diabetes)
+        } else if matches(GOUT_KEYWORDS) {
+            Some(MedicalCondition::Gout)
+        } else {
+            None
+        };
+
+        if let Some(kind) = kind {
+            if !found.contains(&kind) {
+                found.push(kind);
+            }
+        }
+    }
+    found
+}
+
+pub fn build(medical_conditions: Option<&[String]>, daily_carbs_g: f64) -> Vec<ConditionGuidance> {
+    detect(medical_conditions)
+        .into_iter()
+        .map(|condition| guidance(condition, daily_carbs_g))
+        .collect()
+}
+
+/// Stored guidance, or guidance derived on the fly for profiles saved before
+/// it was stored.
+pub fn for_profile(profile: &HealthProfile) -> Vec<ConditionGuidance> {
+    match &profile.condition_guidance {
+        Some(guidance) => guidance.clone(),
+        None => build(profile.medical_conditions.as_deref(), profile.daily_carbs_g),
+    }
+}
+
+fn guidance(condition: MedicalCondition, daily_carbs_g: f64) -> ConditionGuidance {
+    match condition {
+        MedicalCondition::Hypertension =>
+            ConditionGuidance {
+                condition,
+                title: "High blood pressure: limit sodium".to_string(),
+                guidelines: vec![
+                    format!("Keep sodium under {:.0} mg a day (about 3/4 teaspoon of salt)", HYPERTENSION_SODIUM_LIMIT_MG),
+                    "Prefer fresh over processed, cured or instant foods".to_string(),
+                    "Season with herbs, garlic, citrus and spices instead of salt and salty sauces".to_string(),
+                    "Include potassium-rich vegetables, fruit and legumes daily".to_string()
+                ],
+                daily_sodium_limit_mg: Some(HYPERTENSION_SODIUM_LIMIT_MG),
+                max_carbs_per_meal_g: None,
+                max_carbs_per_snack_g: None,
+                avoid_ingredients: to_strings(HIGH_SODIUM_INGREDIENTS),
+            },
+        MedicalCondition::Type2Diabetes => {
+            let per_meal = (daily_carbs_g * MEAL_CARB_SHARE).min(MAX_CARBS_PER_MEAL_G).round();
+            let per_snack = (daily_carbs_g * SNACK_CARB_SHARE).min(MAX_CARBS_PER_SNACK_G).round();
+            ConditionGuidance {
+                condition,
+                title: "Type 2 diabetes: spread carbohydrates evenly".to_string(),
+                guidelines: vec![
+                    format!("Keep each main meal to about {:.0} g of carbohydrates and snacks to {:.0} g", per_meal, per_snack),
+                    "Choose whole grains, legumes and vegetables over refined starches".to_string(),
+                    "Pair carbohydrates with protein or fat to slow absorption".to_string(),
+                    "Avoid sugary drinks and foods with added sugar".to_string()
+                ],
+                daily_sodium_limit_mg: None,
+                max_carbs_per_meal_g: Some(per_meal),
+                max_carbs_per_snack_g: Some(per_snack),
+                avoid_ingredients: to_strings(ADDED_SUGAR_INGREDIENTS),
+            }
+        }
+        MedicalCondition::Gout =>
+            ConditionGuidance {
+                condition,
+                title: "Gout: limit high-purine foods".to_string(),
+                guidelines: vec![
+                    "Avoid organ meats, anchovies, sardines and shellfish".to_string(),
+                    "Limit red meat to small portions and avoid beer".to_string(),
+                    "Limit drinks sweetened with fructose".to_string(),
+                    "Drink plenty of water; low-fat dairy is a good protein source".to_string()
+                ],
+                daily_sodium_limit_mg: None,
+                max_carbs_per_meal_g: None,
+                max_carbs_per_snack_g: None,
+                avoid_ingredients: to_strings(HIGH_PURINE_INGREDIENTS),
+            },
+    }
+}
+
+/// Guidance as a block for AI prompts; empty when there is none.
+pub fn prompt_block(guidance: &[ConditionGuidance]) -> String {
+    if guidance.is_empty() {
+        return String::new();
+    }
+
+    let mut block = String::from("Condition-specific constraints:\n");
+    for g in guidance {
+        block.push_str(&format!("- {}\n", g.title));
+        for line in &g.guidelines {
+            block.push_str(&format!("  - {}\n", line));
+        }
+    }
+    block
+}
+
+/// Avoided ingredients found in `ingredients`, with the condition they conflict with.
+pub fn conflicts<'a>(
+    guidance: &'a [ConditionGuidance],
+    ingredients: &[String]
+) -> Vec<(&'a ConditionGuidance, String)> {
+    let mut found = Vec::new();
+    for g in guidance {
+        for ingredient in ingredients {
+            let lower = ingredient.to_lowercase();
+            if g.avoid_ingredients.iter().any(|avoid| contains_word(&lower, avoid)) {
+                found.push((g, ingredient.clone()));
+            }
+        }
+    }
+    found
+}
+
+/// Whole-word match, so "ham" doesn't flag "graham crackers".
+pub fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) &&
+            !after.is_some_and(|c| c.is_alphanumeric() && c != 's')
+    })
+}
+
+fn to_strings(items: &[&str]) -> Vec<String> {
+    items
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
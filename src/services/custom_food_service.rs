@@ -0,0 +1,201 @@
+use chrono::{ DateTime, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::FindOptions };
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ CustomFood, DailyProgress, MealLog, MealSource, MealType },
+    services::{ favorite_service::MAX_SERVINGS, progress_service, report_service },
+};
+
+pub const MAX_CUSTOM_FOODS: u64 = 500;
+pub const MAX_SERVING_PRESETS: usize = 10;
+/// Largest single portion that can be logged by weight.
+const MAX_PORTION_G: f64 = 5000.0;
+/// Custom foods returned alongside FDC results in a food wiki search.
+pub const SEARCH_LIMIT: i64 = 10;
+
+/// The user's custom foods whose name or brand contains `query`, ignoring case,
+/// alphabetically. An empty query lists them all.
+pub async fn search(state: &AppState, user_id: ObjectId, query: &str, limit: Option<i64>) -> Result<Vec<CustomFood>> {
+    let query = query.trim();
+    let mut filter = doc! { "user_id": user_id };
+    if !query.is_empty() {
+        let pattern = escape_regex(query);
+        filter.insert(
+            "$or",
+            vec![
+                doc! { "name": { "$regex": &pattern, "$options": "i" } },
+                doc! { "brand": { "$regex": &pattern, "$options": "i" } }
+            ]
+        );
+    }
+
+    state.db
+        .collection::<CustomFood>("custom_foods")
+        .find(filter, FindOptions::builder().sort(doc! { "name": 1 }).limit(limit).build()).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+pub async fn find_custom_food(state: &AppState, user_id: ObjectId, food_id: ObjectId) -> Result<CustomFood> {
+    state.db
+        .collection::<CustomFood>("custom_foods")
+        .find_one(doc! { "_id": food_id, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Custom food not found".to_string()))
+}
+
+/// Whether another of the user's custom foods already has this name, ignoring case.
+pub async fn name_taken(state: &AppState, user_id: ObjectId, name: &str, except: Option<ObjectId>) -> Result<bool> {
+    let mut filter =
+        doc! {
+        "user_id": user_id,
+        "name": { "$regex": format!("^{}$", escape_regex(name)), "$options": "i" },
+    };
+    if let Some(except) = except {
+        filter.insert("_id", doc! { "$ne": except });
+    }
+
+    let existing = state.db
+        .collection::<CustomFood>("custom_foods")
+        .find_one(filter, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(existing.is_some())
+}
+
+/// How much of a custom food was eaten: a number of `serving` presets (or of
+/// its base serving without one), or a weight in grams.
+#[derive(Debug, Default)]
+pub struct Portion {
+    pub serving: Option<String>,
+    pub servings: Option<f64>,
+    pub grams: Option<f64>,
+}
+
+impl Portion {
+    /// Factor applied to the per-serving values, the label stored as the
+    /// meal's serving size and the portion weight when it is known.
+    fn resolve(&self, food: &CustomFood) -> Result<(f64, Option<String>, Option<f64>)> {
+        let servings = self.servings.unwrap_or(1.0);
+        if !servings.is_finite() || servings <= 0.0 || servings > MAX_SERVINGS {
+            return Err(AppError::BadRequest(format!("servings must be between 0 and {}", MAX_SERVINGS)));
+        }
+
+        let weight_g = match (&self.serving, self.grams) {
+            (Some(_), Some(_)) => {
+                return Err(AppError::BadRequest("Provide either serving or grams, not both".to_string()));
+            }
+            (None, Some(grams)) => {
+                if !grams.is_finite() || grams <= 0.0 || grams > MAX_PORTION_G {
+                    return Err(AppError::BadRequest(format!("grams must be between 0 and {}", MAX_PORTION_G)));
+                }
+                grams
+            }
+            (Some(name), None) => {
+                let preset = food.serving_presets
+                    .iter()
+                    .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+                    .ok_or_else(|| {
+                        AppError::BadRequest(
+                            format!("\"{}\" has no serving named \"{}\"", food.name, name.trim())
+                        )
+                    })?;
+                preset.grams * servings
+            }
+            (None, None) => {
+                let label = match (&food.serving_size, servings == 1.0) {
+                    (serving, true) => serving.clone(),
+                    (Some(serving), false) => Some(format!("{} x {}", servings, serving)),
+                    (None, false) => Some(format!("{} servings", servings)),
+                };
+                return Ok((servings, label, food.serving_weight_g.map(|g| g * servings)));
+            }
+        };
+
+        let serving_weight_g = food.serving_weight_g.ok_or_else(|| {
+            AppError::BadRequest(
+                format!("\"{}\" has no serving_weight_g, so it can only be logged in servings", food.name)
+            )
+        })?;
+
+        let label = match (&self.serving, servings == 1.0) {
+            (Some(name), true) => format!("{} ({} g)", name.trim(), round1(weight_g)),
+            (Some(name), false) => format!("{} x {} ({} g)", servings, name.trim(), round1(weight_g)),
+            (None, _) => format!("{} g", round1(weight_g)),
+        };
+
+        Ok((weight_g / serving_weight_g, Some(label), Some(weight_g)))
+    }
+}
+
+/// Logs a portion of the custom food at `date`. Returns the meal and the
+/// day's updated progress.
+pub async fn log_custom_food(
+    state: &AppState,
+    food: &CustomFood,
+    meal_type: MealType,
+    date: DateTime<Utc>,
+    portion: &Portion
+) -> Result<(MealLog, DailyProgress)> {
+    let (factor, serving_size, portion_weight_g) = portion.resolve(food)?;
+
+    let mut meal_log = MealLog {
+        id: None,
+        user_id: food.user_id,
+        date,
+        meal_type,
+        food_name: food.name.clone(),
+        calories: food.calories * factor,
+        protein_g: food.protein_g * factor,
+        carbs_g: food.carbs_g * factor,
+        fat_g: food.fat_g * factor,
+        fiber_g: food.fiber_g.map(|g| g * factor),
+        sugar_g: food.sugar_g.map(|g| g * factor),
+        sodium_mg: food.sodium_mg.map(|mg| mg * factor),
+        items: Vec::new(),
+        serving_size,
+        notes: None,
+        source: MealSource::CustomFood,
+        verified: false,
+        nutrition_snapshot: None,
+        portion_weight_g,
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: None,
+        mood: None,
+        eating_context: None,
+        version: 0,
+        created_at: Utc::now(),
+    };
+
+    let result = state.db
+        .collection::<MealLog>("meal_logs")
+        .insert_one(&meal_log, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    meal_log.id = result.inserted_id.as_object_id();
+
+    report_service::invalidate_compliance_score(state, food.user_id).await;
+    let progress = progress_service::record_meal_added(state, &meal_log).await?;
+
+    Ok((meal_log, progress))
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// User input is matched literally, not as a pattern.
+pub fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,507 @@
+use chrono::{ DateTime, Datelike, Duration, NaiveDate, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
+use rand::{ rngs::StdRng, Rng, SeedableRng };
+use std::collections::BTreeMap;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::*,
+    services::{ account_data_service, food_table, report_service, timezone },
+};
+
+pub const DEFAULT_DEMO_EMAIL: &str = "demo@alimentify.app";
+pub const DEFAULT_DEMO_DAYS: i64 = 90;
+pub const MAX_DEMO_DAYS: i64 = 180;
+/// Demo users have this prefix instead of a Google ID, so Google sign-in never
+/// reaches them and reseeding can tell them from real accounts.
+const DEMO_GOOGLE_ID_PREFIX: &str = "demo:";
+/// Fixed so every reseed produces the same account.
+const SEED: u64 = 20_240_101;
+/// The last two weeks are always logged, so the account shows a live streak.
+const STREAK_DAYS: i64 = 14;
+
+/// A meal made of food table entries and their number of standard servings.
+struct MealTemplate {
+    name: &'static str,
+    items: &'static [(&'static str, f64)],
+}
+
+const BREAKFASTS: &[MealTemplate] = &[
+    MealTemplate {
+        name: "Oatmeal with banana",
+        items: &[("Oatmeal (cooked with water)", 1.0), ("Banana", 1.0)],
+    },
+    MealTemplate {
+        name: "Boiled eggs and toast",
+        items: &[("Egg (boiled)", 2.0), ("White bread", 2.0)],
+    },
+    MealTemplate {
+        name: "Greek yogurt with almonds",
+        items: &[("Greek yogurt (plain, nonfat)", 1.0), ("Almonds", 0.5)],
+    },
+    MealTemplate {
+        name: "Nasi goreng with fried egg",
+        items: &[("Fried rice", 1.0), ("Fried egg", 1.0)],
+    },
+];
+
+const LUNCHES: &[MealTemplate] = &[
+    MealTemplate {
+        name: "Rice, grilled chicken and tofu",
+        items: &[("White rice (cooked)", 1.0), ("Chicken breast (cooked, skinless)", 1.0), ("Tofu", 1.0)],
+    },
+    MealTemplate {
+        name: "Brown rice with salmon",
+        items: &[("Brown rice (cooked)", 1.0), ("Salmon (cooked)", 1.0)],
+    },
+    MealTemplate {
+        name: "Nasi ayam goreng",
+        items: &[("White rice (cooked)", 1.0), ("Fried chicken", 1.0)],
+    },
+    MealTemplate {
+        name: "Nasi merah with tempeh and tofu",
+        items: &[("Brown rice (cooked)", 1.0), ("Tempeh", 1.0), ("Tofu", 1.0)],
+    },
+];
+
+const DINNERS: &[MealTemplate] = &[
+    MealTemplate {
+        name: "Chicken breast with sweet potato",
+        items: &[("Chicken breast (cooked, skinless)", 1.0), ("Sweet potato (boiled)", 1.0)],
+    },
+    MealTemplate {
+        name: "Salmon with boiled potatoes",
+        items: &[("Salmon (cooked)", 1.0), ("Potato (boiled)", 1.0)],
+    },
+    MealTemplate {
+        name: "Rice with fried tempeh and tofu",
+        items: &[("White rice (cooked)", 1.0), ("Fried tempeh", 1.0), ("Fried tofu", 1.0)],
+    },
+    MealTemplate {
+        name: "Fried rice",
+        items: &[("Fried rice", 1.5)],
+    },
+];
+
+const SNACKS: &[MealTemplate] = &[
+    MealTemplate { name: "Apple", items: &[("Apple", 1.0)] },
+    MealTemplate { name: "Banana", items: &[("Banana", 1.0)] },
+    MealTemplate { name: "Almonds", items: &[("Almonds", 1.0)] },
+    MealTemplate { name: "Edamame", items: &[("Edamame", 1.0)] },
+    MealTemplate { name: "Roasted peanuts", items: &[("Roasted peanuts", 1.0)] },
+    MealTemplate { name: "Greek yogurt", items: &[("Greek yogurt (plain, nonfat)", 1.0)] },
+];
+
+/// A past conversation: user and assistant turns, started `days_ago` days ago.
+struct DemoChat {
+    days_ago: i64,
+    title: &'static str,
+    turns: &'static [(MessageRole, &'static str)],
+}
+
+const DEMO_CHATS: &[DemoChat] = &[
+    DemoChat {
+        days_ago: 6,
+        title: "Logging breakfast",
+        turns: &[
+            (MessageRole::User, "I had oatmeal with a banana for breakfast"),
+            (
+                MessageRole::Assistant,
+                "Nice start to the day! 🍌 I've logged oatmeal with a banana as breakfast: about 270 calories, 7g protein, 55g carbs and 3g fat.",
+            ),
+        ],
+    },
+    DemoChat {
+        days_ago: 3,
+        title: "High-protein snack ideas",
+        turns: &[
+            (MessageRole::User, "What's a good high-protein snack under 200 calories?"),
+            (
+                MessageRole::Assistant,
+                "A cup of edamame (about 190 calories, 17g protein) or a pot of plain Greek yogurt (about 100 calories, 17g protein) both fit. A boiled egg adds 6g protein for under 80 calories.",
+            ),
+        ],
+    },
+    DemoChat {
+        days_ago: 1,
+        title: "How was my week?",
+        turns: &[
+            (MessageRole::User, "How did I do this week?"),
+            (
+                MessageRole::Assistant,
+                "You logged every day this week and averaged close to your calorie target 🎉 Protein was a little under on a couple of days; adding eggs or tofu at breakfast would close the gap.",
+            ),
+        ],
+    },
+];
+
+pub struct DemoAccount {
+    pub user: User,
+    /// Documents created per collection.
+    pub seeded: BTreeMap<&'static str, usize>,
+}
+
+/// Creates a demo user with `days` days of meals, water, weigh-ins, chats and
+/// reports, ending today. An earlier demo user with the same email is deleted
+/// first; a real account with it is left alone.
+pub async fn seed_demo_account(state: &AppState, email: &str, name: &str, days: i64) -> Result<DemoAccount> {
+    let users = state.db.collection::<User>("users");
+
+    if let Some(existing) = users
+        .find_one(doc! { "gmail": email }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+    {
+        if !existing.google_id.starts_with(DEMO_GOOGLE_ID_PREFIX) {
+            return Err(AppError::Conflict("This email belongs to a real account".to_string()));
+        }
+        if let Some(existing_id) = existing.id {
+            account_data_service::delete_user_data(state, existing_id).await?;
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let today = Utc::now().date_naive();
+    let first_day = today - Duration::days(days - 1);
+    let user_id = ObjectId::new();
+    let mut seeded = BTreeMap::new();
+
+    let weights = weight_logs(&mut rng, user_id, first_day, today);
+    let current_weight = weights.last().map_or(70.0, |w| w.weight_kg);
+
+    let user = User {
+        id: Some(user_id),
+        google_id: format!("{}{}", DEMO_GOOGLE_ID_PREFIX, email),
+        profile_image: None,
+        username: email.split('@').next().unwrap_or("demo").to_string(),
+        name: name.to_string(),
+        gmail: email.to_string(),
+        email_verification_status: true,
+        email_verification_token: None,
+        email_verification_sent_at: None,
+        email_verified_at: Some(at(first_day, 8, 0)),
+        created_at: at(first_day, 8, 0),
+        updated_at: Utc::now(),
+        health_profile: Some(health_profile(current_weight, at(first_day, 8, 5))),
+        has_completed_health_survey: Some(true),
+        linked_google_ids: Vec::new(),
+        role: UserRole::User,
+        chat_retention_months: None,
+        notification_preferences: None,
+        widget_token_id: None,
+        whatsapp: None,
+    };
+
+    users.insert_one(&user, None).await.map_err(|e| AppError::InternalError(e.into()))?;
+
+    let (meals, progress) = meal_logs(&mut rng, user_id, first_day, today);
+    seeded.insert("meal_logs", insert_all(state, "meal_logs", &meals).await?);
+    seeded.insert("daily_progress", insert_all(state, "daily_progress", &progress).await?);
+    seeded.insert("weight_logs", insert_all(state, "weight_logs", &weights).await?);
+
+    let (sessions, messages) = chats(user_id, today);
+    seeded.insert("chat_sessions", insert_all(state, "chat_sessions", &sessions).await?);
+    seeded.insert("chat_messages", insert_all(state, "chat_messages", &messages).await?);
+
+    let reports = reports(state, &user, first_day, today).await?;
+    seeded.insert("meal_reports", insert_all(state, "meal_reports", &reports).await?);
+
+    tracing::info!("Seeded demo account {} with {} days of data", email, days);
+
+    Ok(DemoAccount { user, seeded })
+}
+
+async fn insert_all<T: serde::Serialize>(state: &AppState, collection: &str, documents: &[T]) -> Result<usize> {
+    if documents.is_empty() {
+        return Ok(0);
+    }
+    state.db
+        .collection::<T>(collection)
+        .insert_many(documents, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    Ok(documents.len())
+}
+
+fn at(day: NaiveDate, hour: u32, minute: u32) -> DateTime<Utc> {
+    timezone::start_of_day(day, timezone::utc()) + Duration::hours(hour as i64) + Duration::minutes(minute as i64)
+}
+
+/// A 29-year-old woman losing weight, moderately active.
+fn health_profile(weight_kg: f64, created_at: DateTime<Utc>) -> HealthProfile {
+    let (age, height_cm, gender, activity_level, goal) = (
+        29,
+        165.0,
+        Gender::Female,
+        ActivityLevel::ModeratelyActive,
+        HealthGoal::LoseWeight,
+    );
+    let bmi = HealthProfile::calculate_bmi(weight_kg, height_cm);
+    let bmr = HealthProfile::calculate_bmr(weight_kg, height_cm, age, &gender);
+    let tdee = HealthProfile::calculate_tdee(bmr, &activity_level);
+    let daily_calories = HealthProfile::calculate_daily_calories(tdee, &goal);
+    let macro_strategy = MacroStrategy::default();
+    let (protein_g, carbs_g, fat_g) = macro_strategy.calculate(daily_calories, weight_kg, &goal, None);
+
+    HealthProfile {
+        age,
+        gender,
+        height_cm,
+        weight_kg,
+        activity_level,
+        goal,
+        macro_strategy,
+        medical_conditions: None,
+        blood_pressure: None,
+        fasting_blood_sugar: None,
+        allergies: None,
+        dietary_preferences: None,
+        bmi,
+        bmi_category: HealthProfile::bmi_category(bmi),
+        bmr,
+        tdee,
+        daily_calories,
+        daily_protein_g: protein_g,
+        daily_carbs_g: carbs_g,
+        daily_fat_g: fat_g,
+        ai_recommendations: None,
+        ai_recommendations_prompt_version: None,
+        recommended_foods: None,
+        foods_to_avoid: None,
+        condition_guidance: None,
+        goal_preset: None,
+        target_cycle: Vec::new(),
+        version: 1,
+        created_at,
+        updated_at: created_at,
+    }
+}
+
+/// A weigh-in every three days, losing about 0.3 kg a week with day-to-day noise.
+fn weight_logs(rng: &mut StdRng, user_id: ObjectId, first_day: NaiveDate, today: NaiveDate) -> Vec<WeightLog> {
+    let mut logs = Vec::new();
+    let mut day = first_day;
+    while day <= today {
+        let weeks = ((day - first_day).num_days() as f64) / 7.0;
+        let weight_kg = 71.5 - weeks * 0.3 + rng.gen_range(-0.3..0.3);
+        logs.push(WeightLog {
+            id: None,
+            user_id,
+            weight_kg: (weight_kg * 10.0).round() / 10.0,
+            date: at(day, 6, 30),
+            notes: None,
+            created_at: at(day, 6, 31),
+        });
+        day += Duration::days(3);
+    }
+    logs
+}
+
+/// Three meals and usually a snack a day, with a few missed days before the
+/// current streak, plus each day's totals and water.
+fn meal_logs(
+    rng: &mut StdRng,
+    user_id: ObjectId,
+    first_day: NaiveDate,
+    today: NaiveDate
+) -> (Vec<MealLog>, Vec<DailyProgress>) {
+    let mut meals = Vec::new();
+    let mut progress = Vec::new();
+
+    let mut day = first_day;
+    while day <= today {
+        let in_streak = (today - day).num_days() < STREAK_DAYS;
+        if !in_streak && rng.gen_bool(0.08) {
+            day += Duration::days(1);
+            continue;
+        }
+
+        let mut planned = vec![
+            (MealType::Breakfast, BREAKFASTS, (7, 0)),
+            (MealType::Lunch, LUNCHES, (12, 15)),
+            (MealType::Dinner, DINNERS, (19, 0))
+        ];
+        if rng.gen_bool(0.7) {
+            planned.push((MealType::Snack, SNACKS, (15, 30)));
+        }
+        // Later in the day there is nothing to log yet.
+        if day == today {
+            let now = Utc::now();
+            planned.retain(|(_, _, (hour, minute))| at(day, *hour, *minute) <= now);
+        }
+
+        let first_meal = meals.len();
+        for (meal_type, templates, (hour, minute)) in planned {
+            let template = &templates[rng.gen_range(0..templates.len())];
+            meals.push(meal(rng, user_id, day, meal_type, template, hour, minute));
+        }
+
+        let day_meals = &meals[first_meal..];
+        let created_at = at(day, 23, 59).min(Utc::now());
+        progress.push(DailyProgress {
+            id: None,
+            user_id,
+            date: at(day, 0, 0),
+            total_calories: day_meals.iter().map(|m| m.calories).sum(),
+            total_protein_g: day_meals.iter().map(|m| m.protein_g).sum(),
+            total_carbs_g: day_meals.iter().map(|m| m.carbs_g).sum(),
+            total_fat_g: day_meals.iter().map(|m| m.fat_g).sum(),
+            meal_count: day_meals.len() as i64,
+            water_ml: Some((rng.gen_range(1400.0..2600.0_f64) / 50.0).round() * 50.0),
+            weight_kg: None,
+            notes: None,
+            created_at,
+            updated_at: created_at,
+        });
+
+        day += Duration::days(1);
+    }
+
+    (meals, progress)
+}
+
+fn meal(
+    rng: &mut StdRng,
+    user_id: ObjectId,
+    day: NaiveDate,
+    meal_type: MealType,
+    template: &MealTemplate,
+    hour: u32,
+    minute: u32
+) -> MealLog {
+    // Portions vary a little from day to day.
+    let scale = rng.gen_range(0.85..1.2);
+    let (mut calories, mut protein_g, mut carbs_g, mut fat_g, mut grams) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (food, servings) in template.items {
+        if let Some(entry) = food_table::FOODS.iter().find(|entry| entry.name == *food) {
+            let serving = entry.standard_serving();
+            let factor = servings * scale;
+            calories += serving.calories * factor;
+            protein_g += serving.protein_g * factor;
+            carbs_g += serving.carbs_g * factor;
+            fat_g += serving.fat_g * factor;
+            grams += serving.grams * factor;
+        }
+    }
+
+    let source = match rng.gen_range(0..10) {
+        0..=4 => MealSource::Manual,
+        5..=7 => MealSource::AiText,
+        _ => MealSource::AiImage,
+    };
+    // About half the meals carry the optional mood notes.
+    let (hunger_level, mood, eating_context) = if rng.gen_bool(0.5) {
+        let moods = [MealMood::Happy, MealMood::Calm, MealMood::Neutral, MealMood::Tired, MealMood::Stressed, MealMood::Bored];
+        let contexts = match meal_type {
+            MealType::Lunch => [EatingContext::Work, EatingContext::Restaurant],
+            MealType::Snack => [EatingContext::Screen, EatingContext::OnTheGo],
+            _ => [EatingContext::Home, EatingContext::Social],
+        };
+        (
+            Some(rng.gen_range(2..=5)),
+            Some(moods[rng.gen_range(0..moods.len())]),
+            Some(contexts[rng.gen_range(0..contexts.len())]),
+        )
+    } else {
+        (None, None, None)
+    };
+
+    let date = at(day, hour, minute) + Duration::minutes(rng.gen_range(0..60));
+    let round1 = |value: f64| (value * 10.0).round() / 10.0;
+
+    MealLog {
+        id: None,
+        user_id,
+        date,
+        meal_type,
+        food_name: template.name.to_string(),
+        calories: calories.round(),
+        protein_g: round1(protein_g),
+        carbs_g: round1(carbs_g),
+        fat_g: round1(fat_g),
+        fiber_g: None,
+        sugar_g: None,
+        sodium_mg: None,
+        items: Vec::new(),
+        serving_size: None,
+        notes: None,
+        source,
+        verified: source.is_verified(),
+        nutrition_snapshot: None,
+        portion_weight_g: Some(grams.round()),
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level,
+        mood,
+        eating_context,
+        version: 0,
+        created_at: date,
+    }
+}
+
+fn chats(user_id: ObjectId, today: NaiveDate) -> (Vec<ChatSession>, Vec<ChatMessage>) {
+    let mut sessions = Vec::new();
+    let mut messages = Vec::new();
+    for chat in DEMO_CHATS {
+        let session_id = ObjectId::new();
+        let started = at(today - Duration::days(chat.days_ago), 9, 0);
+        for (i, (role, content)) in chat.turns.iter().enumerate() {
+            messages.push(ChatMessage {
+                id: None,
+                session_id,
+                user_id,
+                role: role.clone(),
+                content: content.to_string(),
+                image_url: None,
+                image_id: None,
+                tool_calls: None,
+                tool_results: None,
+                flagged: false,
+                prompt_version: None,
+                created_at: started + Duration::minutes(i as i64),
+            });
+        }
+        sessions.push(ChatSession {
+            id: Some(session_id),
+            user_id,
+            title: chat.title.to_string(),
+            created_at: started,
+            updated_at: started + Duration::minutes(chat.turns.len() as i64),
+            message_count: chat.turns.len() as i32,
+        });
+    }
+
+    (sessions, messages)
+}
+
+/// Weekly reports for the last four full weeks and monthly reports for the
+/// last two full months, as far as the seeded data reaches.
+async fn reports(state: &AppState, user: &User, first_day: NaiveDate, today: NaiveDate) -> Result<Vec<MealReport>> {
+    let mut periods = Vec::new();
+
+    let (mut start, mut end) = report_service::last_completed_period(ReportFrequency::Weekly, today);
+    for _ in 0..4 {
+        periods.push((ReportPeriod::Weekly, start, end));
+        start -= Duration::days(7);
+        end -= Duration::days(7);
+    }
+
+    let mut month_end = today.with_day(1).unwrap_or(today) - Duration::days(1);
+    for _ in 0..2 {
+        let month_start = month_end.with_day(1).unwrap_or(month_end);
+        periods.push((ReportPeriod::Monthly, month_start, month_end));
+        month_end = month_start - Duration::days(1);
+    }
+
+    let mut reports = Vec::new();
+    for (report_type, start, end) in periods {
+        if start < first_day {
+            continue;
+        }
+        let mut report = report_service::build_report(state, user, report_type, start, end, timezone::utc()).await?;
+        report.status = ReportStatus::Generated;
+        report.generated_at = at(end + Duration::days(1), 7, 0);
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
@@ -0,0 +1,298 @@
+use serde::Serialize;
+
+use crate::{
+    models::{ DietaryPreference, HealthProfile },
+    services::{ condition_guidance::{ self, contains_word }, mealdb_service::Meal },
+};
+
+/// Allergies this long or longer also match ingredients one typo away, e.g.
+/// "peanuts" and "penuts". Shorter words would match too much.
+const FUZZY_MIN_LEN: usize = 5;
+
+/// TheMealDB categories that are all meat.
+const MEAT_CATEGORIES: &[&str] = &["Beef", "Chicken", "Lamb", "Goat", "Pork"];
+const MEAT: &[&str] = &[
+    "beef",
+    "pork",
+    "chicken",
+    "lamb",
+    "mutton",
+    "goat",
+    "veal",
+    "venison",
+    "turkey",
+    "duck",
+    "bacon",
+    "ham",
+    "gammon",
+    "sausage",
+    "chorizo",
+    "salami",
+    "pancetta",
+    "prosciutto",
+    "mince",
+    "steak",
+    "lard",
+    "gelatine",
+];
+const SEAFOOD: &[&str] = &[
+    "fish",
+    "salmon",
+    "tuna",
+    "cod",
+    "haddock",
+    "mackerel",
+    "sardine",
+    "anchovy",
+    "anchovies",
+    "prawn",
+    "shrimp",
+    "crab",
+    "lobster",
+    "mussel",
+    "clam",
+    "oyster",
+    "scallop",
+    "squid",
+];
+const SHELLFISH: &[&str] = &["prawn", "shrimp", "crab", "lobster", "mussel", "clam", "oyster", "scallop", "squid"];
+const PORK: &[&str] = &["pork", "bacon", "ham", "gammon", "lard", "chorizo", "salami", "pancetta", "prosciutto", "gelatine"];
+const ALCOHOL: &[&str] = &[
+    "wine",
+    "beer",
+    "rum",
+    "brandy",
+    "vodka",
+    "whisky",
+    "whiskey",
+    "bourbon",
+    "cognac",
+    "sherry",
+    "sake",
+    "mirin",
+    "tequila",
+];
+const DAIRY: &[&str] = &[
+    "milk",
+    "cheese",
+    "butter",
+    "cream",
+    "yogurt",
+    "yoghurt",
+    "ghee",
+    "parmesan",
+    "mozzarella",
+    "cheddar",
+    "feta",
+    "ricotta",
+    "mascarpone",
+    "creme fraiche",
+];
+/// Plant-based stand-ins that would otherwise read as dairy, e.g. "coconut milk".
+const PLANT_BASED: &[&str] = &["coconut", "almond", "soy", "oat", "peanut", "vegan"];
+const OTHER_ANIMAL: &[&str] = &["egg", "honey"];
+const GLUTEN: &[&str] = &[
+    "flour",
+    "bread",
+    "breadcrumbs",
+    "pasta",
+    "spaghetti",
+    "penne",
+    "macaroni",
+    "lasagne",
+    "noodles",
+    "couscous",
+    "bulgur",
+    "wheat",
+    "barley",
+    "rye",
+    "tortilla",
+    "pastry",
+    "soy sauce",
+];
+const TREE_NUTS: &[&str] = &[
+    "almond",
+    "cashew",
+    "walnut",
+    "pecan",
+    "hazelnut",
+    "pistachio",
+    "macadamia",
+    "brazil nut",
+    "pine nut",
+    "praline",
+    "marzipan",
+];
+const EGG: &[&str] = &["egg", "mayonnaise", "meringue"];
+const SOY: &[&str] = &["soy", "soya", "soy sauce", "tofu", "tempeh", "edamame", "miso"];
+const SESAME: &[&str] = &["sesame", "tahini"];
+
+/// Why a recipe doesn't suit the user's profile.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecipeConflict {
+    DietaryPreference {
+        preference: DietaryPreference,
+        /// The ingredient, or the recipe's category such as "Pork".
+        reason: String,
+    },
+    Allergy {
+        allergy: String,
+        ingredient: String,
+    },
+    MedicalCondition {
+        title: String,
+        ingredient: String,
+    },
+}
+
+/// Everything in `meal` that goes against the profile's dietary preferences,
+/// allergies or condition guidance. Keto and low-carb are judged by the
+/// estimated carbs instead, so they never conflict here.
+pub fn recipe_conflicts(profile: &HealthProfile, meal: &Meal) -> Vec<RecipeConflict> {
+    let ingredients: Vec<String> = meal
+        .get_ingredients()
+        .into_iter()
+        .map(|(ingredient, _)| ingredient.to_lowercase())
+        .collect();
+    let category = meal.str_category.as_deref().unwrap_or_default();
+
+    let mut conflicts: Vec<RecipeConflict> = profile.dietary_preferences
+        .iter()
+        .flatten()
+        .filter_map(|preference| {
+            preference_conflict(category, &ingredients, preference).map(|reason| RecipeConflict::DietaryPreference {
+                preference: preference.clone(),
+                reason,
+            })
+        })
+        .collect();
+
+    for allergy in profile.allergies.iter().flatten() {
+        if let Some(ingredient) = allergy_conflict(allergy, &ingredients) {
+            conflicts.push(RecipeConflict::Allergy { allergy: allergy.trim().to_string(), ingredient });
+        }
+    }
+
+    let guidance = condition_guidance::for_profile(profile);
+    for (g, ingredient) in condition_guidance::conflicts(&guidance, &ingredients) {
+        conflicts.push(RecipeConflict::MedicalCondition { title: g.title.clone(), ingredient });
+    }
+
+    conflicts
+}
+
+/// The first lowercase ingredient in `ingredients`, or the TheMealDB
+/// `category`, that `preference` rules out.
+pub fn preference_conflict(category: &str, ingredients: &[String], preference: &DietaryPreference) -> Option<String> {
+    let mentioning = |words: &[&str]| {
+        ingredients
+            .iter()
+            .find(|i| words.iter().any(|w| contains_word(i, w)))
+            .cloned()
+    };
+    let in_category = |names: &[&str]| names.contains(&category).then(|| category.to_string());
+    let meat = || in_category(MEAT_CATEGORIES).or_else(|| mentioning(MEAT));
+    let seafood = || in_category(&["Seafood"]).or_else(|| mentioning(SEAFOOD));
+    let pork = || in_category(&["Pork"]).or_else(|| mentioning(PORK));
+    let dairy = || {
+        ingredients
+            .iter()
+            .find(|i| !PLANT_BASED.iter().any(|p| i.contains(p)) && DAIRY.iter().any(|w| contains_word(i, w)))
+            .cloned()
+    };
+
+    match preference {
+        DietaryPreference::Vegetarian => meat().or_else(seafood),
+        DietaryPreference::Vegan =>
+            meat()
+                .or_else(seafood)
+                .or_else(dairy)
+                .or_else(|| mentioning(OTHER_ANIMAL)),
+        DietaryPreference::Pescatarian => meat(),
+        DietaryPreference::Halal => pork().or_else(|| mentioning(ALCOHOL)),
+        DietaryPreference::Kosher =>
+            pork()
+                .or_else(|| mentioning(SHELLFISH))
+                .or_else(|| meat().and_then(|m| dairy().map(|d| format!("{} with {}", m, d)))),
+        DietaryPreference::GlutenFree =>
+            ingredients
+                .iter()
+                .find(|i| {
+                    !i.contains("gluten-free") &&
+                        !i.contains("rice") &&
+                        GLUTEN.iter().any(|w| contains_word(i, w))
+                })
+                .cloned(),
+        DietaryPreference::DairyFree => dairy(),
+        // Checked against the estimated carbs per serving instead.
+        DietaryPreference::LowCarb | DietaryPreference::Keto => None,
+    }
+}
+
+/// The first lowercase ingredient that contains the allergen. Matches the
+/// allergy as a whole word, its family for common allergens ("nuts" covers
+/// almonds, "dairy" covers cheese), and near misses of longer words.
+pub fn allergy_conflict(allergy: &str, ingredients: &[String]) -> Option<String> {
+    let allergy = allergy.trim().to_lowercase();
+    if allergy.is_empty() {
+        return None;
+    }
+    let singular = allergy.strip_suffix('s').unwrap_or(&allergy);
+    let family = allergen_family(singular);
+
+    ingredients
+        .iter()
+        .find(|ingredient| {
+            contains_word(ingredient, &allergy) ||
+                contains_word(ingredient, singular) ||
+                family.iter().any(|word| {
+                    contains_word(ingredient, word) &&
+                        // "Coconut milk" isn't dairy.
+                        !(family == DAIRY && PLANT_BASED.iter().any(|p| ingredient.contains(p)))
+                }) ||
+                (singular.len() >= FUZZY_MIN_LEN &&
+                    ingredient.split_whitespace().any(|word| within_one_edit(word, singular)))
+        })
+        .cloned()
+}
+
+/// Ingredients that contain a common allergen, by the allergy's usual names.
+fn allergen_family(allergy: &str) -> &'static [&'static str] {
+    match allergy {
+        "dairy" | "milk" | "lactose" | "casein" => DAIRY,
+        "gluten" | "wheat" | "celiac" | "coeliac" => GLUTEN,
+        "nut" | "tree nut" => TREE_NUTS,
+        "shellfish" | "crustacean" | "mollusc" => SHELLFISH,
+        "fish" | "seafood" => SEAFOOD,
+        "egg" => EGG,
+        "soy" | "soya" => SOY,
+        "sesame" => SESAME,
+        "pork" => PORK,
+        "alcohol" => ALCOHOL,
+        _ => &[],
+    }
+}
+
+/// Whether `word` becomes `target` with at most one insertion, deletion or
+/// substitution, ignoring a plural "s".
+fn within_one_edit(word: &str, target: &str) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    let word = word.strip_suffix('s').unwrap_or(word);
+    let (a, b): (Vec<char>, Vec<char>) = (word.chars().collect(), target.chars().collect());
+    let (shorter, longer) = if a.len() <= b.len() { (&a, &b) } else { (&b, &a) };
+    if longer.len() - shorter.len() > 1 {
+        return false;
+    }
+
+    let prefix = shorter
+        .iter()
+        .zip(longer.iter())
+        .take_while(|(x, y)| x == y)
+        .count();
+    if prefix == shorter.len() {
+        return true;
+    }
+    let skip = if shorter.len() == longer.len() { 1 } else { 0 };
+    shorter[prefix + skip..] == longer[prefix + 1..]
+}
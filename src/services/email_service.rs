@@ -1,13 +1,19 @@
 use lettre::{
-    message::header::ContentType,
+    message::{ header::ContentType, Mailbox },
     transport::smtp::authentication::Credentials,
     AsyncSmtpTransport,
     AsyncTransport,
     Message,
     Tokio1Executor,
 };
+use std::time::Instant;
 
-use crate::{ config::Config, error::Result, models::{User, MealReport} };
+use crate::{
+    config::{ BrandingConfig, Config },
+    error::Result,
+    models::{User, MealReport, Notification},
+    services::{ auth_service, provider_metrics_service::{ self, Provider } },
+};
 
 pub async fn send_verification_email(
     config: &Config,
@@ -20,6 +26,8 @@ pub async fn send_verification_email(
         config.security.allowed_origins.first().unwrap_or(&"http://localhost:3000".to_string()),
         token
     );
+    let branding = &config.branding;
+    let app_name = escape_html(&branding.app_name);
 
     let email_body = format!(
         r#"
@@ -32,13 +40,13 @@ pub async fn send_verification_email(
                     .webkit {{ max-width: 600px; margin: 0 auto; }}
                     .outer {{ margin: 0 auto; width: 100%; max-width: 600px; }}
                     .header {{ text-align: center; padding: 30px 0; }}
-                    .logo-circle {{ display: inline-block; width: 50px; height: 50px; background-color: #FAB12F; border-radius: 50%; margin-bottom: 10px; }}
-                    .card {{ background-color: #ffffff; border-radius: 32px; padding: 40px; box-shadow: 0 8px 32px rgba(250, 177, 47, 0.1); border: 1px solid rgba(255, 255, 255, 0.5); }}
+                    .logo-circle {{ display: inline-block; width: 50px; height: 50px; background-color: {primary}; border-radius: 50%; margin-bottom: 10px; }}
+                    .card {{ background-color: #ffffff; border-radius: 32px; padding: 40px; box-shadow: 0 8px 32px {shadow}; border: 1px solid rgba(255, 255, 255, 0.5); }}
                     h2 {{ color: #1a1a1a; margin-top: 0; font-size: 24px; font-weight: 800; letter-spacing: -0.5px; }}
                     p {{ color: #4a4a4a; font-size: 16px; line-height: 1.6; }}
                     .btn-container {{ text-align: center; margin: 35px 0; }}
-                    .btn {{ background: linear-gradient(to right, #FAB12F, #FA812F); color: white !important; padding: 16px 32px; text-decoration: none; border-radius: 50px; font-weight: bold; display: inline-block; box-shadow: 0 4px 15px rgba(250, 129, 47, 0.3); }}
-                    .link-text {{ color: #FA812F; word-break: break-all; font-size: 14px; }}
+                    .btn {{ background: linear-gradient(to right, {primary}, {secondary}); color: white !important; padding: 16px 32px; text-decoration: none; border-radius: 50px; font-weight: bold; display: inline-block; box-shadow: 0 4px 15px {shadow}; }}
+                    .link-text {{ color: {secondary}; word-break: break-all; font-size: 14px; }}
                     .footer {{ text-align: center; margin-top: 30px; color: #888888; font-size: 12px; }}
                 </style>
             </head>
@@ -47,28 +55,29 @@ pub async fn send_verification_email(
                     <div class="webkit">
                         <div class="outer">
                             <div class="header">
-                                <div class="logo-circle"></div>
-                                <h3 style="margin: 5px 0 0 0; color: #1a1a1a;">Alimentify</h3>
+                                {logo}
+                                <h3 style="margin: 5px 0 0 0; color: #1a1a1a;">{app_name}</h3>
                             </div>
                             <div class="card">
-                                <h2>Welcome to Alimentify! 👋</h2>
-                                <p>Hello <strong>{}</strong>,</p>
+                                <h2>Welcome to {app_name}! 👋</h2>
+                                <p>Hello <strong>{name}</strong>,</p>
                                 <p>Thank you for joining us! To get started with your nutrition journey, please verify your email address.</p>
                                 
                                 <div class="btn-container">
-                                    <a href="{}" class="btn">Verify Email Address</a>
+                                    <a href="{url}" class="btn">Verify Email Address</a>
                                 </div>
                                 
                                 <p style="font-size: 14px; color: #666;">Or copy and paste this link into your browser:</p>
-                                <p><a href="{}" class="link-text">{}</a></p>
+                                <p><a href="{url}" class="link-text">{url}</a></p>
                                 
                                 <hr style="border: none; border-top: 1px solid #eee; margin: 30px 0;">
                                 
-                                <p style="font-size: 13px; color: #888; margin-bottom: 0;">This link will expire in 24 hours.</p>
+                                <p style="font-size: 13px; color: #888; margin-bottom: 0;">This link will expire in {hours} hours.</p>
                                 <p style="font-size: 13px; color: #888; margin-top: 5px;">If you didn't create an account, please ignore this email.</p>
                             </div>
                             <div class="footer">
-                                <p>&copy; 2025 Alimentify. All rights reserved.</p>
+                                {support}
+                                <p>&copy; 2025 {app_name}. All rights reserved.</p>
                             </div>
                         </div>
                     </div>
@@ -76,16 +85,21 @@ pub async fn send_verification_email(
             </body>
         </html>
         "#,
-        to_name,
-        verification_url,
-        verification_url,
-        verification_url
+        name = to_name,
+        url = verification_url,
+        hours = auth_service::VERIFICATION_TOKEN_HOURS,
+        app_name = app_name,
+        primary = branding.primary_color,
+        secondary = branding.secondary_color,
+        shadow = branding.primary_rgba(0.1),
+        logo = logo_html(branding, 50),
+        support = support_html(branding)
     );
 
     let email = Message::builder()
         .from(format!("{} <{}>", config.brevo.from_name, config.brevo.from_email).parse().unwrap())
         .to(format!("{} <{}>", to_name, to_email).parse().unwrap())
-        .subject("Verify your Alimentify account")
+        .subject(format!("Verify your {} account", branding.app_name))
         .header(ContentType::TEXT_HTML)
         .body(email_body)
         .unwrap();
@@ -99,7 +113,10 @@ pub async fn send_verification_email(
         .credentials(creds)
         .build();
 
-    mailer.send(email).await.map_err(|e| {
+    let started = Instant::now();
+    let sent = mailer.send(email).await;
+    provider_metrics_service::record(Provider::Smtp, sent.is_ok(), started);
+    sent.map_err(|e| {
         tracing::error!("Failed to send email: {}", e);
         crate::error::AppError::InternalError(anyhow::anyhow!("Failed to send email"))
     })?;
@@ -116,6 +133,7 @@ pub struct EmailService {
     smtp_password: String,
     from_email: String,
     from_name: String,
+    branding: BrandingConfig,
 }
 
 impl EmailService {
@@ -126,6 +144,7 @@ impl EmailService {
         smtp_password: String,
         from_email: String,
         from_name: String,
+        branding: BrandingConfig,
     ) -> Self {
         Self {
             smtp_host,
@@ -134,6 +153,7 @@ impl EmailService {
             smtp_password,
             from_email,
             from_name,
+            branding,
         }
     }
 
@@ -200,6 +220,49 @@ impl EmailService {
             String::new()
         };
 
+        let coach_section = if let Some(commentary) = &report.coach_commentary {
+            format!(
+                r#"
+                <div style="background-color: #FFFBEB; padding: 20px; border-radius: 24px; margin: 20px 0; border: 1px solid #FDE68A;">
+                    <h3 style="color: #B45309; margin-top: 0; font-size: 18px;">
+                        <span style="background: #FEF3C7; width: 32px; height: 32px; border-radius: 50%; display: inline-block; text-align: center; line-height: 32px; margin-right: 10px;">🧑‍🍳</span> 
+                        Coach's Note
+                    </h3>
+                    <p style="margin: 0; color: #78350F; line-height: 1.6;">{}</p>
+                </div>
+                "#,
+                escape_html(commentary)
+            )
+        } else {
+            String::new()
+        };
+
+        let data_quality_section = if report.data_quality.is_empty() {
+            String::new()
+        } else {
+            let items: String = report.data_quality
+                .iter()
+                .map(|a| format!(
+                    r#"<li style="margin-bottom: 6px;"><strong>{}</strong> - {}</li>"#,
+                    a.date,
+                    escape_html(&a.detail)
+                ))
+                .collect();
+            format!(
+                r#"
+                <div style="background-color: #FEF2F2; padding: 20px; border-radius: 24px; margin: 20px 0; border: 1px solid #FECACA;">
+                    <h3 style="color: #B91C1C; margin-top: 0; font-size: 18px;">
+                        <span style="background: #FEE2E2; width: 32px; height: 32px; border-radius: 50%; display: inline-block; text-align: center; line-height: 32px; margin-right: 10px;">🔍</span> 
+                        Data Quality
+                    </h3>
+                    <p style="margin: 0 0 10px 0; color: #7F1D1D; font-size: 14px;">Some days look unusual, so the averages above may be skewed by logging errors:</p>
+                    <ul style="margin: 0; padding-left: 20px; color: #7F1D1D; font-size: 14px;">{}</ul>
+                </div>
+                "#,
+                items
+            )
+        };
+
         let email_body = format!(
             r#"
             <!DOCTYPE html>
@@ -211,13 +274,13 @@ impl EmailService {
                         .webkit {{ max-width: 600px; margin: 0 auto; }}
                         .outer {{ margin: 0 auto; width: 100%; max-width: 600px; }}
                         .header {{ text-align: center; padding: 30px 0; }}
-                        .logo-circle {{ display: inline-block; width: 40px; height: 40px; background-color: #FAB12F; border-radius: 50%; margin-bottom: 5px; }}
-                        .card {{ background-color: #ffffff; border-radius: 32px; padding: 40px; box-shadow: 0 8px 32px rgba(250, 177, 47, 0.1); border: 1px solid rgba(255, 255, 255, 0.5); }}
+                        .logo-circle {{ display: inline-block; width: 40px; height: 40px; background-color: {primary}; border-radius: 50%; margin-bottom: 5px; }}
+                        .card {{ background-color: #ffffff; border-radius: 32px; padding: 40px; box-shadow: 0 8px 32px {shadow}; border: 1px solid rgba(255, 255, 255, 0.5); }}
                         
                         h1 {{ color: #1a1a1a; font-size: 24px; font-weight: 800; margin-top: 0; letter-spacing: -0.5px; }}
                         h2 {{ color: #4a4a4a; font-size: 18px; margin-top: 30px; margin-bottom: 15px; font-weight: 700; }}
                         
-                        .status-banner {{ background: linear-gradient(to right, #FAB12F, #FA812F); padding: 24px; border-radius: 24px; margin: 25px 0; text-align: center; color: white; box-shadow: 0 4px 12px rgba(250, 129, 47, 0.2); }}
+                        .status-banner {{ background: linear-gradient(to right, {primary}, {secondary}); padding: 24px; border-radius: 24px; margin: 25px 0; text-align: center; color: white; box-shadow: 0 4px 12px {shadow}; }}
                         
                         .grid-2 {{ display: table; width: 100%; border-spacing: 10px; margin: 0 -10px; }}
                         .col {{ display: table-cell; width: 50%; vertical-align: top; }}
@@ -239,8 +302,8 @@ impl EmailService {
                         <div class="webkit">
                             <div class="outer">
                                 <div class="header">
-                                    <div class="logo-circle"></div>
-                                    <h3 style="margin: 5px 0 0 0; color: #1a1a1a; font-family: monospace;">Alimentify</h3>
+                                    {logo}
+                                    <h3 style="margin: 5px 0 0 0; color: #1a1a1a; font-family: monospace;">{app_name}</h3>
                                 </div>
                                 
                                 <div class="card">
@@ -328,6 +391,10 @@ impl EmailService {
 
                                     {}
 
+                                    {}
+
+                                    {}
+
                                     <div style="text-align: center; margin-top: 40px;">
                                         <p style="color: #475569; font-style: italic;">"{}"</p>
                                         <a href="https://alimentify.app/my/reports" class="btn">View Full Report</a>
@@ -336,7 +403,8 @@ impl EmailService {
 
                                 <div class="footer">
                                     <p>You received this email because you enabled nutrition reports in your settings.</p>
-                                    <p>&copy; 2025 Alimentify. All rights reserved.</p>
+                                    {support}
+                                    <p>&copy; 2025 {app_name}. All rights reserved.</p>
                                 </div>
                             </div>
                         </div>
@@ -365,16 +433,24 @@ impl EmailService {
             if report.carbs_compliance_percent > 100.0 { 100.0 } else { report.carbs_compliance_percent },
             weight_section,
             best_day_section,
+            data_quality_section,
+            coach_section,
             if report.goal_achieved {
                 "Congratulations! You've achieved your nutrition goals for this period. Keep up the excellent work! 🎉"
             } else {
                 "You're making progress! Keep tracking your meals consistently to reach your goals. 💪"
-            }
+            },
+            app_name = escape_html(&self.branding.app_name),
+            primary = self.branding.primary_color,
+            secondary = self.branding.secondary_color,
+            shadow = self.branding.primary_rgba(0.1),
+            logo = logo_html(&self.branding, 40),
+            support = support_html(&self.branding)
         );
 
         let email = Message::builder()
             .from(format!("{} <{}>", self.from_name, self.from_email).parse().unwrap())
-            .to(format!("{} <{}>", user.name, user.gmail).parse().unwrap())
+            .to(recipient(user)?)
             .subject(format!("{} {} Nutrition Report - {}", 
                 goal_status_emoji, 
                 report_period,
@@ -393,7 +469,10 @@ impl EmailService {
             .credentials(creds)
             .build();
 
-        mailer.send(email).await.map_err(|e| {
+        let started = Instant::now();
+        let sent = mailer.send(email).await;
+        provider_metrics_service::record(Provider::Smtp, sent.is_ok(), started);
+        sent.map_err(|e| {
             tracing::error!("Failed to send report email: {}", e);
             crate::error::AppError::InternalError(anyhow::anyhow!("Failed to send email"))
         })?;
@@ -402,4 +481,342 @@ impl EmailService {
 
         Ok(())
     }
+
+    /// One email listing the low-priority notifications held for the digest.
+    pub async fn send_digest_email(&self, user: &User, notifications: &[Notification]) -> Result<()> {
+        let items: String = notifications
+            .iter()
+            .map(|n| format!(
+                r#"<li style="margin-bottom: 12px;"><strong style="color: #1E293B;">{}</strong><br><span style="color: #64748B;">{}</span></li>"#,
+                escape_html(&n.title),
+                escape_html(&n.message)
+            ))
+            .collect();
+
+        let email_body = format!(
+            r#"
+            <!DOCTYPE html>
+            <html>
+                <head>
+                    <style>
+                        body {{ font-family: 'Segoe UI', Tahoma, Geneva, Verdana, sans-serif; margin: 0; padding: 0; background-color: #FEF3E2; }}
+                        .outer {{ margin: 0 auto; width: 100%; max-width: 600px; padding-bottom: 40px; }}
+                        .header {{ text-align: center; padding: 30px 0; }}
+                        .logo-circle {{ display: inline-block; width: 40px; height: 40px; background-color: {primary}; border-radius: 50%; margin-bottom: 5px; }}
+                        .card {{ background-color: #ffffff; border-radius: 32px; padding: 40px; box-shadow: 0 8px 32px {shadow}; }}
+                        h1 {{ color: #1a1a1a; font-size: 24px; font-weight: 800; margin-top: 0; }}
+                        .footer {{ text-align: center; margin-top: 30px; color: #888888; font-size: 12px; }}
+                    </style>
+                </head>
+                <body>
+                    <div class="outer">
+                        <div class="header">
+                            {logo}
+                            <h3 style="margin: 5px 0 0 0; color: #1a1a1a; font-family: monospace;">{app_name}</h3>
+                        </div>
+                        <div class="card">
+                            <h1>Your daily digest</h1>
+                            <p style="color: #64748B;">Hi {name}, here's what happened today:</p>
+                            <ul style="padding-left: 20px; font-size: 15px;">{items}</ul>
+                        </div>
+                        <div class="footer">
+                            <p>You received this email because you turned on email digests in your notification settings.</p>
+                            {support}
+                            <p>&copy; 2025 {app_name}. All rights reserved.</p>
+                        </div>
+                    </div>
+                </body>
+            </html>
+            "#,
+            name = escape_html(&user.name),
+            items = items,
+            app_name = escape_html(&self.branding.app_name),
+            primary = self.branding.primary_color,
+            shadow = self.branding.primary_rgba(0.1),
+            logo = logo_html(&self.branding, 40),
+            support = support_html(&self.branding)
+        );
+
+        let email = Message::builder()
+            .from(format!("{} <{}>", self.from_name, self.from_email).parse().unwrap())
+            .to(recipient(user)?)
+            .subject(format!("Your {} digest: {} update{}", self.branding.app_name, notifications.len(), if notifications.len() == 1 { "" } else { "s" }))
+            .header(ContentType::TEXT_HTML)
+            .body(email_body)
+            .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Failed to build email: {}", e)))?;
+
+        let creds = Credentials::new(self.smtp_username.clone(), self.smtp_password.clone());
+
+        let mailer: AsyncSmtpTransport<Tokio1Executor> = AsyncSmtpTransport::<Tokio1Executor>
+            ::starttls_relay(&self.smtp_host)
+            .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Failed to create mailer: {}", e)))?
+            .port(self.smtp_port)
+            .credentials(creds)
+            .build();
+
+        let started = Instant::now();
+        let sent = mailer.send(email).await;
+        provider_metrics_service::record(Provider::Smtp, sent.is_ok(), started);
+        sent.map_err(|e| {
+            tracing::error!("Failed to send digest email: {}", e);
+            crate::error::AppError::InternalError(anyhow::anyhow!("Failed to send email"))
+        })?;
+
+        tracing::info!("Digest email sent to {}", user.gmail);
+
+        Ok(())
+    }
+
+    /// A single notification sent straight to email, such as a reminder.
+    pub async fn send_notification_email(&self, user: &User, notification: &Notification) -> Result<()> {
+        let email_body = format!(
+            r#"
+            <!DOCTYPE html>
+            <html>
+                <head>
+                    <style>
+                        body {{ font-family: 'Segoe UI', Tahoma, Geneva, Verdana, sans-serif; margin: 0; padding: 0; background-color: #FEF3E2; }}
+                        .outer {{ margin: 0 auto; width: 100%; max-width: 600px; padding-bottom: 40px; }}
+                        .header {{ text-align: center; padding: 30px 0; }}
+                        .logo-circle {{ display: inline-block; width: 40px; height: 40px; background-color: {primary}; border-radius: 50%; margin-bottom: 5px; }}
+                        .card {{ background-color: #ffffff; border-radius: 32px; padding: 40px; box-shadow: 0 8px 32px {shadow}; }}
+                        h1 {{ color: #1a1a1a; font-size: 24px; font-weight: 800; margin-top: 0; }}
+                        p {{ color: #4a4a4a; font-size: 16px; line-height: 1.6; }}
+                        .footer {{ text-align: center; margin-top: 30px; color: #888888; font-size: 12px; }}
+                    </style>
+                </head>
+                <body>
+                    <div class="outer">
+                        <div class="header">
+                            {logo}
+                            <h3 style="margin: 5px 0 0 0; color: #1a1a1a; font-family: monospace;">{app_name}</h3>
+                        </div>
+                        <div class="card">
+                            <h1>{title}</h1>
+                            <p>Hi {name},</p>
+                            <p>{message}</p>
+                        </div>
+                        <div class="footer">
+                            <p>You received this email because you turned on email reminders in your notification settings.</p>
+                            {support}
+                            <p>&copy; 2025 {app_name}. All rights reserved.</p>
+                        </div>
+                    </div>
+                </body>
+            </html>
+            "#,
+            name = escape_html(&user.name),
+            title = escape_html(&notification.title),
+            message = escape_html(&notification.message),
+            app_name = escape_html(&self.branding.app_name),
+            primary = self.branding.primary_color,
+            shadow = self.branding.primary_rgba(0.1),
+            logo = logo_html(&self.branding, 40),
+            support = support_html(&self.branding)
+        );
+
+        let email = Message::builder()
+            .from(format!("{} <{}>", self.from_name, self.from_email).parse().unwrap())
+            .to(recipient(user)?)
+            .subject(notification.title.clone())
+            .header(ContentType::TEXT_HTML)
+            .body(email_body)
+            .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Failed to build email: {}", e)))?;
+
+        let creds = Credentials::new(self.smtp_username.clone(), self.smtp_password.clone());
+
+        let mailer: AsyncSmtpTransport<Tokio1Executor> = AsyncSmtpTransport::<Tokio1Executor>
+            ::starttls_relay(&self.smtp_host)
+            .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Failed to create mailer: {}", e)))?
+            .port(self.smtp_port)
+            .credentials(creds)
+            .build();
+
+        let started = Instant::now();
+        let sent = mailer.send(email).await;
+        provider_metrics_service::record(Provider::Smtp, sent.is_ok(), started);
+        sent.map_err(|e| {
+            tracing::error!("Failed to send notification email: {}", e);
+            crate::error::AppError::InternalError(anyhow::anyhow!("Failed to send email"))
+        })?;
+
+        tracing::info!("Notification email sent to {}", user.gmail);
+
+        Ok(())
+    }
+
+    /// Plain-text answer to a meal the user logged by email, threaded under
+    /// their message's subject.
+    pub async fn send_gateway_reply(&self, user: &User, subject: &str, reply: &str) -> Result<()> {
+        let subject = if subject.to_lowercase().starts_with("re:") {
+            subject.to_string()
+        } else {
+            format!("Re: {}", subject)
+        };
+
+        let email = Message::builder()
+            .from(format!("{} <{}>", self.from_name, self.from_email).parse().unwrap())
+            .to(recipient(user)?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(format!("Hi {},\n\n{}\n\n- {}", user.name, reply, self.branding.app_name))
+            .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Failed to build email: {}", e)))?;
+
+        let creds = Credentials::new(self.smtp_username.clone(), self.smtp_password.clone());
+
+        let mailer: AsyncSmtpTransport<Tokio1Executor> = AsyncSmtpTransport::<Tokio1Executor>
+            ::starttls_relay(&self.smtp_host)
+            .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Failed to create mailer: {}", e)))?
+            .port(self.smtp_port)
+            .credentials(creds)
+            .build();
+
+        let started = Instant::now();
+        let sent = mailer.send(email).await;
+        provider_metrics_service::record(Provider::Smtp, sent.is_ok(), started);
+        sent.map_err(|e| {
+            tracing::error!("Failed to send gateway reply email: {}", e);
+            crate::error::AppError::InternalError(anyhow::anyhow!("Failed to send email"))
+        })?;
+
+        tracing::info!("Gateway reply email sent to {}", user.gmail);
+
+        Ok(())
+    }
+
+    /// Asks the user to confirm deleting their account; nothing is deleted
+    /// until the link is followed.
+    pub async fn send_account_deletion_email(&self, user: &User, confirm_url: &str, expires_in_minutes: u64) -> Result<()> {
+        let email_body = format!(
+            r#"
+            <!DOCTYPE html>
+            <html>
+                <head>
+                    <style>
+                        body {{ font-family: 'Segoe UI', Tahoma, Geneva, Verdana, sans-serif; margin: 0; padding: 0; background-color: #FEF3E2; }}
+                        .outer {{ margin: 0 auto; width: 100%; max-width: 600px; padding-bottom: 40px; }}
+                        .header {{ text-align: center; padding: 30px 0; }}
+                        .logo-circle {{ display: inline-block; width: 40px; height: 40px; background-color: {primary}; border-radius: 50%; margin-bottom: 5px; }}
+                        .card {{ background-color: #ffffff; border-radius: 32px; padding: 40px; box-shadow: 0 8px 32px {shadow}; }}
+                        h1 {{ color: #1a1a1a; font-size: 24px; font-weight: 800; margin-top: 0; }}
+                        p {{ color: #4a4a4a; font-size: 16px; line-height: 1.6; }}
+                        .btn-container {{ text-align: center; margin: 35px 0; }}
+                        .btn {{ background-color: #DC2626; color: white !important; padding: 16px 32px; text-decoration: none; border-radius: 50px; font-weight: bold; display: inline-block; }}
+                        .link-text {{ color: {secondary}; word-break: break-all; font-size: 14px; }}
+                        .footer {{ text-align: center; margin-top: 30px; color: #888888; font-size: 12px; }}
+                    </style>
+                </head>
+                <body>
+                    <div class="outer">
+                        <div class="header">
+                            {logo}
+                            <h3 style="margin: 5px 0 0 0; color: #1a1a1a; font-family: monospace;">{app_name}</h3>
+                        </div>
+                        <div class="card">
+                            <h1>Confirm account deletion</h1>
+                            <p>Hi <strong>{name}</strong>,</p>
+                            <p>We received a request to delete your {app_name} account. This permanently removes your profile, meals, reports, chats, weigh-ins and all other data. It can't be undone.</p>
+
+                            <div class="btn-container">
+                                <a href="{url}" class="btn">Delete My Account</a>
+                            </div>
+
+                            <p style="font-size: 14px; color: #666;">Or copy and paste this link into your browser:</p>
+                            <p><a href="{url}" class="link-text">{url}</a></p>
+
+                            <hr style="border: none; border-top: 1px solid #eee; margin: 30px 0;">
+
+                            <p style="font-size: 13px; color: #888; margin-bottom: 0;">This link will expire in {expires} minutes.</p>
+                            <p style="font-size: 13px; color: #888; margin-top: 5px;">If you didn't ask to delete your account, ignore this email and nothing will change.</p>
+                        </div>
+                        <div class="footer">
+                            {support}
+                            <p>&copy; 2025 {app_name}. All rights reserved.</p>
+                        </div>
+                    </div>
+                </body>
+            </html>
+            "#,
+            name = escape_html(&user.name),
+            url = escape_html(confirm_url),
+            expires = expires_in_minutes,
+            app_name = escape_html(&self.branding.app_name),
+            primary = self.branding.primary_color,
+            secondary = self.branding.secondary_color,
+            shadow = self.branding.primary_rgba(0.1),
+            logo = logo_html(&self.branding, 40),
+            support = support_html(&self.branding)
+        );
+
+        let email = Message::builder()
+            .from(format!("{} <{}>", self.from_name, self.from_email).parse().unwrap())
+            .to(recipient(user)?)
+            .subject(format!("Confirm deleting your {} account", self.branding.app_name))
+            .header(ContentType::TEXT_HTML)
+            .body(email_body)
+            .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Failed to build email: {}", e)))?;
+
+        let creds = Credentials::new(self.smtp_username.clone(), self.smtp_password.clone());
+
+        let mailer: AsyncSmtpTransport<Tokio1Executor> = AsyncSmtpTransport::<Tokio1Executor>
+            ::starttls_relay(&self.smtp_host)
+            .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Failed to create mailer: {}", e)))?
+            .port(self.smtp_port)
+            .credentials(creds)
+            .build();
+
+        let started = Instant::now();
+        let sent = mailer.send(email).await;
+        provider_metrics_service::record(Provider::Smtp, sent.is_ok(), started);
+        sent.map_err(|e| {
+            tracing::error!("Failed to send account deletion email: {}", e);
+            crate::error::AppError::InternalError(anyhow::anyhow!("Failed to send email"))
+        })?;
+
+        tracing::info!("Account deletion email sent to {}", user.gmail);
+
+        Ok(())
+    }
+}
+
+/// The logo image, or a circle in the primary color without one.
+/// The user's name and address as a recipient. An address that doesn't parse
+/// fails the send instead of panicking.
+fn recipient(user: &User) -> Result<Mailbox> {
+    let email = user.gmail
+        .parse()
+        .map_err(|e| crate::error::AppError::InternalError(anyhow::anyhow!("Invalid recipient address: {}", e)))?;
+    Ok(Mailbox::new(Some(user.name.clone()), email))
+}
+
+fn logo_html(branding: &BrandingConfig, size: u32) -> String {
+    match &branding.logo_url {
+        Some(url) =>
+            format!(
+                r#"<img src="{}" alt="{}" width="{}" height="{}" style="margin-bottom: 5px;">"#,
+                escape_html(url),
+                escape_html(&branding.app_name),
+                size,
+                size
+            ),
+        None => r#"<div class="logo-circle"></div>"#.to_string(),
+    }
+}
+
+fn support_html(branding: &BrandingConfig) -> String {
+    match &branding.support_email {
+        Some(email) =>
+            format!(
+                r#"<p>Questions? Contact <a href="mailto:{0}" style="color: #888888;">{0}</a></p>"#,
+                escape_html(email)
+            ),
+        None => String::new(),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
@@ -0,0 +1,225 @@
+use anyhow::Result;
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId, Document }, options::{ FindOptions, UpdateOptions } };
+
+use crate::{
+    db::{ self, AppState },
+    models::{
+        ChatMessage,
+        ChatSession,
+        MealReport,
+        MessageRole,
+        Milestone,
+        Notification,
+        NotificationKind,
+        ReportPeriod,
+    },
+    services::{ achievement_service, notification_service },
+};
+
+const MILESTONE_CHAT_TITLE: &str = "🎉 Milestones";
+
+#[derive(Debug, Clone)]
+pub enum DomainEvent {
+    MilestoneReached {
+        user_id: ObjectId,
+        milestone: Milestone,
+    },
+    ReportReady {
+        user_id: ObjectId,
+        report_id: ObjectId,
+        report_type: ReportPeriod,
+    },
+}
+
+pub async fn publish(state: &AppState, event: DomainEvent) {
+    let result = match &event {
+        DomainEvent::MilestoneReached { user_id, milestone } => {
+            on_milestone_reached(state, *user_id, *milestone).await
+        }
+        DomainEvent::ReportReady { user_id, report_id, report_type } => {
+            on_report_ready(state, *user_id, *report_id, report_type).await
+        }
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Failed to handle domain event {:?}: {}", event, e);
+    }
+}
+
+/// Milestones detected from a freshly generated report.
+pub async fn check_report_milestones(state: &AppState, user_id: ObjectId, report: &MealReport) {
+    let mut milestones = Vec::new();
+
+    if report.total_days >= 7 && report.days_logged >= report.total_days {
+        milestones.push(Milestone::FirstPerfectWeek);
+    }
+    if report.streak_days >= 30 {
+        milestones.push(Milestone::ThirtyDayStreak);
+    }
+    if report.weight_goal_achieved == Some(true) {
+        milestones.push(Milestone::TargetWeightReached);
+    }
+
+    for milestone in milestones {
+        publish(state, DomainEvent::MilestoneReached { user_id, milestone }).await;
+    }
+}
+
+/// Run after a meal is logged: refreshes the user's achievements and emits
+/// milestones for the longest logging streak.
+pub async fn check_logging_milestones(state: &AppState, user_id: ObjectId) {
+    let longest_run = match achievement_service::refresh(state, user_id, None).await {
+        Ok((achievements, _)) => achievements.logging_streak.longest_days,
+        Err(e) => {
+            tracing::error!("Failed to refresh achievements for milestone check: {}", e);
+            return;
+        }
+    };
+
+    if longest_run >= 7 {
+        publish(state, DomainEvent::MilestoneReached {
+            user_id,
+            milestone: Milestone::FirstPerfectWeek,
+        }).await;
+    }
+    if longest_run >= 30 {
+        publish(state, DomainEvent::MilestoneReached {
+            user_id,
+            milestone: Milestone::ThirtyDayStreak,
+        }).await;
+    }
+}
+
+async fn on_milestone_reached(state: &AppState, user_id: ObjectId, milestone: Milestone) -> Result<()> {
+    let now = Utc::now();
+
+    // Each milestone is celebrated once per user; the upsert tells us whether this is the first time.
+    // Of two concurrent upserts the unique index lets one insert; the other
+    // fails with a duplicate key, meaning it was already recorded.
+    let recorded = state.db
+        .collection::<Document>("user_milestones")
+        .update_one(
+            doc! { "user_id": user_id, "milestone": milestone.as_str() },
+            doc! { "$setOnInsert": { "achieved_at": mongodb::bson::DateTime::from_chrono(now) } },
+            UpdateOptions::builder().upsert(true).build()
+        ).await;
+
+    match recorded {
+        Ok(recorded) if recorded.upserted_id.is_some() => {}
+        Ok(_) => {
+            return Ok(());
+        }
+        Err(e) if db::is_duplicate_key(&e) => {
+            return Ok(());
+        }
+        Err(e) => {
+            return Err(e.into());
+        }
+    }
+
+    tracing::info!("User {} reached milestone {}", user_id, milestone.as_str());
+
+    let notification = Notification {
+        id: None,
+        user_id,
+        kind: NotificationKind::Milestone,
+        title: milestone.title().to_string(),
+        message: milestone.message().to_string(),
+        milestone: Some(milestone),
+        report_id: None,
+        read: false,
+        created_at: now,
+    };
+
+    notification_service::dispatch(state, notification).await?;
+
+    queue_chat_message(state, user_id, milestone).await?;
+
+    Ok(())
+}
+
+async fn on_report_ready(
+    state: &AppState,
+    user_id: ObjectId,
+    report_id: ObjectId,
+    report_type: &ReportPeriod
+) -> Result<()> {
+    let notification = Notification {
+        id: None,
+        user_id,
+        kind: NotificationKind::ReportReady,
+        title: "Your report is ready".to_string(),
+        message: format!("Your {} nutrition report has finished generating. Tap to view it.", report_type.as_str()),
+        milestone: None,
+        report_id: Some(report_id),
+        read: false,
+        created_at: Utc::now(),
+    };
+
+    notification_service::dispatch(state, notification).await?;
+
+    Ok(())
+}
+
+async fn queue_chat_message(state: &AppState, user_id: ObjectId, milestone: Milestone) -> Result<()> {
+    let now = Utc::now();
+    let sessions = state.db.collection::<ChatSession>("chat_sessions");
+
+    let existing = sessions
+        .find(
+            doc! { "user_id": user_id },
+            FindOptions::builder()
+                .sort(doc! { "updated_at": -1 })
+                .limit(1)
+                .build()
+        ).await?
+        .try_next().await?;
+
+    let session_id = match existing.and_then(|s| s.id) {
+        Some(id) => id,
+        None => {
+            let session = ChatSession {
+                id: None,
+                user_id,
+                title: MILESTONE_CHAT_TITLE.to_string(),
+                created_at: now,
+                updated_at: now,
+                message_count: 0,
+            };
+            let result = sessions.insert_one(&session, None).await?;
+            result.inserted_id
+                .as_object_id()
+                .ok_or_else(|| anyhow::anyhow!("Chat session insert returned no ObjectId"))?
+        }
+    };
+
+    let message = ChatMessage {
+        id: None,
+        session_id,
+        user_id,
+        role: MessageRole::Assistant,
+        content: format!("**{}**\n\n{}", milestone.title(), milestone.message()),
+        image_url: None,
+        image_id: None,
+        tool_calls: None,
+        tool_results: None,
+        flagged: false,
+        prompt_version: None,
+        created_at: now,
+    };
+
+    state.db.collection::<ChatMessage>("chat_messages").insert_one(&message, None).await?;
+
+    sessions.update_one(
+        doc! { "_id": session_id },
+        doc! {
+            "$set": { "updated_at": mongodb::bson::DateTime::from_chrono(now) },
+            "$inc": { "message_count": 1 }
+        },
+        None
+    ).await?;
+
+    Ok(())
+}
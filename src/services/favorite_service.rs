@@ -0,0 +1,121 @@
+use chrono::{ DateTime, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::FindOptions };
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ DailyProgress, FavoriteFood, MealLog, MealType },
+    services::{ progress_service, report_service },
+};
+
+pub const MAX_SERVINGS: f64 = 20.0;
+
+/// The user's favorites, most logged first.
+pub async fn list_favorites(state: &AppState, user_id: ObjectId) -> Result<Vec<FavoriteFood>> {
+    state.db
+        .collection::<FavoriteFood>("favorite_foods")
+        .find(
+            doc! { "user_id": user_id },
+            FindOptions::builder().sort(doc! { "times_logged": -1, "food_name": 1 }).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+pub async fn find_favorite(state: &AppState, user_id: ObjectId, favorite_id: ObjectId) -> Result<FavoriteFood> {
+    state.db
+        .collection::<FavoriteFood>("favorite_foods")
+        .find_one(doc! { "_id": favorite_id, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Favorite food not found".to_string()))
+}
+
+/// Exact name match first, ignoring case, then the most logged favorite whose
+/// name contains `name`.
+pub fn match_by_name<'a>(favorites: &'a [FavoriteFood], name: &str) -> Option<&'a FavoriteFood> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+
+    favorites
+        .iter()
+        .find(|f| f.food_name.to_lowercase() == name)
+        .or_else(|| favorites.iter().find(|f| f.food_name.to_lowercase().contains(&name)))
+}
+
+/// Logs `servings` of the favorite at `date` and bumps its usage count.
+/// Returns the meal and the day's updated progress.
+pub async fn log_favorite(
+    state: &AppState,
+    favorite: &FavoriteFood,
+    meal_type: Option<MealType>,
+    date: DateTime<Utc>,
+    servings: f64
+) -> Result<(MealLog, DailyProgress)> {
+    let favorite_id = favorite.id.ok_or_else(|| AppError::BadRequest("Favorite has no ID".to_string()))?;
+
+    if !servings.is_finite() || servings <= 0.0 || servings > MAX_SERVINGS {
+        return Err(AppError::BadRequest(format!("servings must be between 0 and {}", MAX_SERVINGS)));
+    }
+
+    let serving_size = match (&favorite.serving_size, servings == 1.0) {
+        (serving, true) => serving.clone(),
+        (Some(serving), false) => Some(format!("{} x {}", servings, serving)),
+        (None, false) => Some(format!("{} servings", servings)),
+    };
+
+    let mut meal_log = MealLog {
+        id: None,
+        user_id: favorite.user_id,
+        date,
+        meal_type: meal_type.unwrap_or_else(|| favorite.meal_type.clone()),
+        food_name: favorite.food_name.clone(),
+        calories: favorite.calories * servings,
+        protein_g: favorite.protein_g * servings,
+        carbs_g: favorite.carbs_g * servings,
+        fat_g: favorite.fat_g * servings,
+        fiber_g: None,
+        sugar_g: None,
+        sodium_mg: None,
+        items: Vec::new(),
+        serving_size,
+        notes: favorite.notes.clone(),
+        source: favorite.source.unverified(),
+        verified: false,
+        nutrition_snapshot: None,
+        portion_weight_g: favorite.portion_weight_g.map(|g| g * servings),
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: None,
+        mood: None,
+        eating_context: None,
+        version: 0,
+        created_at: Utc::now(),
+    };
+
+    let result = state.db
+        .collection::<MealLog>("meal_logs")
+        .insert_one(&meal_log, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    meal_log.id = result.inserted_id.as_object_id();
+
+    state.db
+        .collection::<FavoriteFood>("favorite_foods")
+        .update_one(
+            doc! { "_id": favorite_id },
+            doc! {
+                "$inc": { "times_logged": 1 },
+                "$set": { "last_logged_at": Utc::now().to_rfc3339() },
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    report_service::invalidate_compliance_score(state, favorite.user_id).await;
+    let progress = progress_service::record_meal_added(state, &meal_log).await?;
+
+    Ok((meal_log, progress))
+}
@@ -1,7 +1,9 @@
 use anyhow::{ Context, Result };
 use reqwest::Client;
 use serde::{ Deserialize, Serialize };
-use std::sync::Arc;
+use std::{ sync::Arc, time::Instant };
+
+use crate::services::provider_metrics_service::{ self, Provider };
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FoodSearchResult {
@@ -98,12 +100,22 @@ pub struct FoodPortion {
     pub id: Option<i32>,
     pub amount: Option<f64>,
     pub modifier: Option<String>,
+    /// Set on Foundation foods, e.g. "1 cup, chopped".
+    #[serde(rename = "portionDescription")]
+    pub portion_description: Option<String>,
+    #[serde(rename = "measureUnit")]
+    pub measure_unit: Option<MeasureUnit>,
     #[serde(rename = "gramWeight")]
     pub gram_weight: Option<f64>,
     #[serde(rename = "sequenceNumber")]
     pub sequence_number: Option<i32>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MeasureUnit {
+    pub name: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct FdcService {
     client: Arc<Client>,
@@ -144,11 +156,13 @@ impl FdcService {
             params.push(("dataType", types_str));
         }
 
+        let started = Instant::now();
         let response = self.client
             .get(&url)
             .query(&params)
-            .send().await
-            .context("Failed to send request to FDC API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::Fdc, started, &response);
+        let response = response.context("Failed to send request to FDC API")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -166,11 +180,13 @@ impl FdcService {
     pub async fn get_food_details(&self, fdc_id: i32) -> Result<FoodDetails> {
         let url = format!("{}/food/{}", self.base_url, fdc_id);
 
+        let started = Instant::now();
         let response = self.client
             .get(&url)
             .query(&[("api_key", &self.api_key)])
-            .send().await
-            .context("Failed to send request to FDC API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::Fdc, started, &response);
+        let response = response.context("Failed to send request to FDC API")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -188,12 +204,14 @@ impl FdcService {
     pub async fn get_foods(&self, fdc_ids: Vec<i32>) -> Result<Vec<FoodDetails>> {
         let url = format!("{}/foods", self.base_url);
 
+        let started = Instant::now();
         let response = self.client
             .post(&url)
             .query(&[("api_key", &self.api_key)])
             .json(&fdc_ids)
-            .send().await
-            .context("Failed to send request to FDC API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::Fdc, started, &response);
+        let response = response.context("Failed to send request to FDC API")?;
 
         if !response.status().is_success() {
             let status = response.status();
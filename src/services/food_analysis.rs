@@ -0,0 +1,200 @@
+use serde::{ Deserialize, Deserializer, Serialize };
+use serde_json::{ json, Value };
+
+use crate::error::AiMalformedResponse;
+
+/// What Gemini made of a food photo: an analysis, or the reason it isn't one.
+#[derive(Debug, Clone)]
+pub enum FoodImageAnalysis {
+    Food(Box<FoodAnalysis>),
+    Rejected(FoodRejection),
+}
+
+impl FoodImageAnalysis {
+    /// The analysis in the prompt's JSON shape, `is_valid_food` included, for
+    /// handing on to the chat agent.
+    pub fn to_json(&self) -> Value {
+        match self {
+            FoodImageAnalysis::Food(analysis) => {
+                let mut value = serde_json::to_value(analysis).unwrap_or_default();
+                if let Some(object) = value.as_object_mut() {
+                    object.insert("is_valid_food".to_string(), Value::Bool(true));
+                }
+                value
+            }
+            FoodImageAnalysis::Rejected(rejection) =>
+                json!({
+                    "is_valid_food": false,
+                    "error_type": rejection.kind,
+                    "message": rejection.message,
+                }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionKind {
+    NotEdible,
+    Inappropriate,
+    /// Gemini's safety filter blocked the image.
+    AiRefusal,
+    /// Also used for any `error_type` the model makes up.
+    #[serde(other)]
+    NotFood,
+}
+
+impl RejectionKind {
+    pub fn default_message(self) -> &'static str {
+        match self {
+            RejectionKind::NotFood =>
+                "This image does not appear to contain food. Please upload a clear photo of a meal or food item.",
+            RejectionKind::NotEdible =>
+                "This item is not typically consumed as human food. Please upload a photo of an edible meal or food item.",
+            RejectionKind::Inappropriate =>
+                "This image cannot be processed. Please upload an appropriate photo of food.",
+            RejectionKind::AiRefusal => "This image could not be processed. Please upload a clear photo of food.",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FoodRejection {
+    pub kind: RejectionKind,
+    pub message: String,
+}
+
+impl FoodRejection {
+    pub fn new(kind: RejectionKind) -> Self {
+        Self { kind, message: kind.default_message().to_string() }
+    }
+}
+
+/// Nutrition estimate for the portion shown in a photo. Field aliases accept
+/// the older prompt versions' names, which reported numbers as strings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoodAnalysis {
+    pub food_name: String,
+    #[serde(default)]
+    pub serving_size: Option<String>,
+    #[serde(default, deserialize_with = "optional_number")]
+    pub estimated_weight_g: Option<f64>,
+    #[serde(default)]
+    pub portion_reference: Option<String>,
+    #[serde(deserialize_with = "number")]
+    pub calories: f64,
+    pub macronutrients: Macronutrients,
+    #[serde(default)]
+    pub micronutrients: Micronutrients,
+    /// 1-10, higher is more nutritious.
+    #[serde(default, deserialize_with = "optional_number")]
+    pub health_score: Option<f64>,
+    #[serde(default)]
+    pub health_notes: Option<String>,
+    #[serde(default)]
+    pub dietary_info: DietaryInfo,
+    #[serde(default)]
+    pub recommendations: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Macronutrients {
+    #[serde(alias = "protein", deserialize_with = "number")]
+    pub protein_g: f64,
+    #[serde(alias = "carbohydrates", deserialize_with = "number")]
+    pub carbs_g: f64,
+    #[serde(alias = "fat", deserialize_with = "number")]
+    pub fat_g: f64,
+    #[serde(alias = "fiber", default, deserialize_with = "optional_number")]
+    pub fiber_g: Option<f64>,
+    /// Total sugars; asked for since prompt v4.
+    #[serde(alias = "sugar", default, deserialize_with = "optional_number")]
+    pub sugar_g: Option<f64>,
+    #[serde(alias = "sodium", default, deserialize_with = "optional_number")]
+    pub sodium_mg: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Micronutrients {
+    #[serde(default)]
+    pub vitamins: Vec<String>,
+    #[serde(default)]
+    pub minerals: Vec<String>,
+}
+
+/// `None` where the model couldn't tell.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DietaryInfo {
+    #[serde(default)]
+    pub is_vegetarian: Option<bool>,
+    #[serde(default)]
+    pub is_vegan: Option<bool>,
+    #[serde(default)]
+    pub is_gluten_free: Option<bool>,
+    #[serde(default)]
+    pub allergens: Vec<String>,
+}
+
+/// Reads the JSON object out of the model's reply, which may be wrapped in
+/// prose or a code fence.
+pub fn parse(text: &str) -> Result<FoodImageAnalysis, AiMalformedResponse> {
+    let malformed = |reason: String| AiMalformedResponse { reason };
+
+    let json = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => {
+            return Err(malformed("no JSON object in food analysis".to_string()));
+        }
+    };
+    let value: Value = serde_json::from_str(json).map_err(|e| malformed(format!("invalid JSON: {}", e)))?;
+
+    if value.get("is_valid_food").and_then(Value::as_bool) == Some(false) {
+        let kind = value
+            .get("error_type")
+            .and_then(|v| RejectionKind::deserialize(v).ok())
+            .unwrap_or(RejectionKind::NotFood);
+        let message = value
+            .get("message")
+            .and_then(Value::as_str)
+            .filter(|m| !m.trim().is_empty())
+            .map_or_else(|| kind.default_message().to_string(), str::to_string);
+        return Ok(FoodImageAnalysis::Rejected(FoodRejection { kind, message }));
+    }
+
+    let mut analysis: FoodAnalysis = serde_json
+        ::from_value(value)
+        .map_err(|e| malformed(format!("unusable food analysis: {}", e)))?;
+
+    let macros = &analysis.macronutrients;
+    let values = [analysis.calories, macros.protein_g, macros.carbs_g, macros.fat_g];
+    if analysis.food_name.trim().is_empty() || values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+        return Err(malformed(format!("unusable values for {:?}", analysis.food_name)));
+    }
+    analysis.health_score = analysis.health_score.map(|score| score.clamp(1.0, 10.0));
+    analysis.estimated_weight_g = analysis.estimated_weight_g.filter(|g| g.is_finite() && *g > 0.0);
+
+    Ok(FoodImageAnalysis::Food(Box::new(analysis)))
+}
+
+/// The number a value starts with: `350`, `"350"`, `"350 kcal"`, `"~12.5g"`.
+fn leading_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => {
+            let s = s.trim_start_matches(|c: char| !c.is_ascii_digit() && c != '.');
+            let end = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+            s[..end].parse().ok()
+        }
+        _ => None,
+    }
+}
+
+fn number<'de, D>(deserializer: D) -> Result<f64, D::Error> where D: Deserializer<'de> {
+    let value = Value::deserialize(deserializer)?;
+    leading_number(&value).ok_or_else(|| serde::de::Error::custom(format!("expected a number, got {}", value)))
+}
+
+fn optional_number<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error> where D: Deserializer<'de> {
+    let value = Value::deserialize(deserializer)?;
+    Ok(leading_number(&value))
+}
@@ -0,0 +1,318 @@
+use serde::Serialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    services::{ cache_service, fdc_service::{ FoodDetails, FoodItem, FoodPortion }, food_table },
+};
+
+/// FoodData Central search results are per 100 g.
+const FDC_BASIS_G: f64 = 100.0;
+/// Generic foods rather than branded products, whose names rarely match how
+/// people describe what they ate.
+const FDC_DATA_TYPES: [&str; 2] = ["Foundation", "SR Legacy"];
+const FDC_ENERGY_IDS: [i32; 3] = [1008, 2047, 2048];
+const FDC_PROTEIN_ID: i32 = 1003;
+const FDC_FAT_ID: i32 = 1004;
+const FDC_CARBS_ID: i32 = 1005;
+const FDC_FIBER_ID: i32 = 1079;
+/// Total sugars, then the older "Sugars, total including NLEA".
+const FDC_SUGAR_IDS: [i32; 2] = [2000, 1063];
+const FDC_SODIUM_ID: i32 = 1093;
+/// FDC's placeholder for portions without a household measure.
+const UNSPECIFIED_PORTION: &str = "Quantity not specified";
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupSource {
+    FoodTable,
+    ApiNinjas,
+    Fdc,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FoodNutrition {
+    pub food_name: String,
+    pub serving_size: String,
+    pub grams: Option<f64>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fdc_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FoodLookup {
+    pub source: LookupSource,
+    pub foods: Vec<FoodNutrition>,
+}
+
+/// Nutrition for a description such as "200g salmon" or "2 eggs and toast",
+/// from the built-in food table, then API Ninjas, then FoodData Central.
+/// Returns `None` when none of them know the food.
+pub async fn lookup(state: &AppState, query: &str) -> Result<Option<FoodLookup>> {
+    if let Some(food) = food_table::lookup(query) {
+        return Ok(
+            Some(FoodLookup {
+                source: LookupSource::FoodTable,
+                foods: vec![FoodNutrition {
+                    food_name: food.food_name,
+                    serving_size: food.serving_size,
+                    grams: Some(food.grams),
+                    calories: food.calories,
+                    protein_g: food.protein_g,
+                    carbs_g: food.carbs_g,
+                    fat_g: food.fat_g,
+                    fdc_id: None,
+                }],
+            })
+        );
+    }
+
+    // API Ninjas understands quantities and lists of foods itself.
+    match
+        state.cache.get_or_fetch(cache_service::NINJA_NUTRITION, query, false, || {
+            state.ninja_service.get_nutrition(query)
+        }).await
+    {
+        Ok(items) if !items.is_empty() => {
+            let foods = items
+                .into_iter()
+                .map(|item| FoodNutrition {
+                    serving_size: format!("{} g", round1(item.serving_size_g)),
+                    grams: Some(item.serving_size_g).filter(|g| *g > 0.0),
+                    // Calories are a premium field on the free plan and come back as 0.
+                    calories: (if item.calories > 0.0 {
+                        item.calories
+                    } else {
+                        item.protein_g * 4.0 + item.carbohydrates_total_g * 4.0 + item.fat_total_g * 9.0
+                    }).round(),
+                    protein_g: round1(item.protein_g),
+                    carbs_g: round1(item.carbohydrates_total_g),
+                    fat_g: round1(item.fat_total_g),
+                    food_name: item.name,
+                    fdc_id: None,
+                })
+                .collect();
+            return Ok(Some(FoodLookup { source: LookupSource::ApiNinjas, foods }));
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("API Ninjas lookup failed for {:?}, trying FDC: {}", query, e),
+    }
+
+    let (grams, food) = food_table::split_grams(query);
+    let data_types: Vec<String> = FDC_DATA_TYPES.iter()
+        .map(|t| t.to_string())
+        .collect();
+    // Same key format as the food wiki search, so the two share entries.
+    let cache_key = format!("{}|{}|{}|{}", food, 1, 5, data_types.join(","));
+    let result = state.cache
+        .get_or_fetch(cache_service::FDC_SEARCH, &cache_key, false, || {
+            state.fdc_service.search_foods(&food, Some(1), Some(5), Some(data_types))
+        }).await
+        .map_err(|e| AppError::ExternalApiError(format!("Nutrition lookup failed: {}", e)))?;
+
+    let Some(item) = result.foods.iter().find(|item| fdc_value(item, &FDC_ENERGY_IDS).is_some()) else {
+        return Ok(None);
+    };
+
+    let grams = grams.unwrap_or(FDC_BASIS_G);
+    let scale = grams / FDC_BASIS_G;
+    let value = |ids: &[i32]| fdc_value(item, ids).unwrap_or(0.0) * scale;
+
+    Ok(
+        Some(FoodLookup {
+            source: LookupSource::Fdc,
+            foods: vec![FoodNutrition {
+                food_name: item.description.clone(),
+                serving_size: format!("{} g", round1(grams)),
+                grams: Some(grams),
+                calories: value(&FDC_ENERGY_IDS).round(),
+                protein_g: round1(value(&[FDC_PROTEIN_ID])),
+                carbs_g: round1(value(&[FDC_CARBS_ID])),
+                fat_g: round1(value(&[FDC_FAT_ID])),
+                fdc_id: Some(item.fdc_id),
+            }],
+        })
+    )
+}
+
+/// One of an FDC food's household measures, e.g. "1 cup, chopped" = 128 g.
+#[derive(Debug, Clone, Serialize)]
+pub struct FdcPortion {
+    pub id: i32,
+    pub description: String,
+    pub gram_weight: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Serving {
+    Grams(f64),
+    /// `servings` of the portion with this FDC ID.
+    Portion { id: i32, servings: f64 },
+}
+
+/// An FDC food's nutrition for one serving.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScaledFood {
+    pub fdc_id: i32,
+    pub description: String,
+    pub grams: f64,
+    /// The portion the serving was given in, if any.
+    pub portion: Option<FdcPortion>,
+    pub servings: Option<f64>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub fiber_g: Option<f64>,
+    pub sugar_g: Option<f64>,
+    pub sodium_mg: Option<f64>,
+    /// Every portion the food has, for a serving picker.
+    pub portions: Vec<FdcPortion>,
+}
+
+/// A serving from either `grams`, or a `portion_id` and how many `servings`
+/// of it (default 1).
+pub fn serving(grams: Option<f64>, portion_id: Option<i32>, servings: Option<f64>) -> Result<Serving> {
+    let positive = |value: f64, name: &str| {
+        if value.is_finite() && value > 0.0 {
+            Ok(value)
+        } else {
+            Err(AppError::BadRequest(format!("{} must be greater than 0", name)))
+        }
+    };
+
+    match (grams, portion_id) {
+        (Some(grams), None) => Ok(Serving::Grams(positive(grams, "grams")?)),
+        (None, Some(id)) =>
+            Ok(Serving::Portion {
+                id,
+                servings: positive(servings.unwrap_or(1.0), "servings")?,
+            }),
+        _ => Err(AppError::BadRequest("Send either grams or portion_id".to_string())),
+    }
+}
+
+/// The FDC ID of the branded food with this UPC/EAN barcode, `None` when
+/// FDC has no such product. Leading zeros are ignored, as GTINs pad them.
+pub async fn find_barcode(state: &AppState, barcode: &str) -> Result<Option<i32>> {
+    let data_types = vec!["Branded".to_string()];
+    // Same key format as the food wiki search, so the two share entries.
+    let cache_key = format!("{}|{}|{}|{}", barcode, 1, 10, data_types.join(","));
+    let result = state.cache
+        .get_or_fetch(cache_service::FDC_SEARCH, &cache_key, false, || {
+            state.fdc_service.search_foods(barcode, Some(1), Some(10), Some(data_types))
+        }).await
+        .map_err(|e| AppError::ExternalApiError(format!("Barcode lookup failed: {}", e)))?;
+
+    let code = barcode.trim_start_matches('0');
+    Ok(
+        result.foods
+            .iter()
+            .find(|item| item.gtin_upc.as_deref().is_some_and(|gtin| gtin.trim().trim_start_matches('0') == code))
+            .map(|item| item.fdc_id)
+    )
+}
+
+/// The food's household measures that have a gram weight, in FDC's order.
+pub fn fdc_portions(details: &FoodDetails) -> Vec<FdcPortion> {
+    let mut portions: Vec<&FoodPortion> = details.food_portions.iter().flatten().collect();
+    portions.sort_by_key(|portion| portion.sequence_number.unwrap_or(i32::MAX));
+
+    portions
+        .into_iter()
+        .filter_map(|portion| {
+            Some(FdcPortion {
+                id: portion.id?,
+                description: portion_description(portion),
+                gram_weight: portion.gram_weight.filter(|g| *g > 0.0)?,
+            })
+        })
+        .collect()
+}
+
+fn portion_description(portion: &FoodPortion) -> String {
+    if let Some(description) = portion.portion_description
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty() && *d != UNSPECIFIED_PORTION)
+    {
+        return description.to_string();
+    }
+
+    let unit = portion.modifier
+        .as_deref()
+        .filter(|m| !m.trim().is_empty())
+        .or_else(|| portion.measure_unit.as_ref().and_then(|unit| unit.name.as_deref()))
+        .filter(|unit| *unit != "undetermined")
+        .unwrap_or("serving");
+    format!("{} {}", round1(portion.amount.unwrap_or(1.0)), unit)
+}
+
+/// The food's nutrition for `serving`. FDC reports nutrients per 100 g;
+/// portions give the grams. Foods without energy get it from the macros.
+pub fn scale_fdc_food(details: &FoodDetails, serving: Serving) -> Result<ScaledFood> {
+    let portions = fdc_portions(details);
+
+    let (grams, portion, servings) = match serving {
+        Serving::Grams(grams) => (grams, None, None),
+        Serving::Portion { id, servings } => {
+            let portion = portions
+                .iter()
+                .find(|portion| portion.id == id)
+                .cloned()
+                .ok_or_else(|| AppError::NotFound("This food has no portion with that ID".to_string()))?;
+            (portion.gram_weight * servings, Some(portion), Some(servings))
+        }
+    };
+
+    let scale = grams / FDC_BASIS_G;
+    let value = |ids: &[i32]| {
+        ids.iter().find_map(|id| {
+            details.food_nutrients
+                .iter()
+                .find(|n| n.nutrient.id == *id)
+                .and_then(|n| n.amount)
+                .map(|amount| amount * scale)
+        })
+    };
+
+    let protein_g = value(&[FDC_PROTEIN_ID]).unwrap_or(0.0);
+    let carbs_g = value(&[FDC_CARBS_ID]).unwrap_or(0.0);
+    let fat_g = value(&[FDC_FAT_ID]).unwrap_or(0.0);
+    let calories = value(&FDC_ENERGY_IDS).unwrap_or(protein_g * 4.0 + carbs_g * 4.0 + fat_g * 9.0);
+
+    Ok(ScaledFood {
+        fdc_id: details.fdc_id,
+        description: details.description.clone(),
+        grams: round1(grams),
+        portion,
+        servings,
+        calories: calories.round(),
+        protein_g: round1(protein_g),
+        carbs_g: round1(carbs_g),
+        fat_g: round1(fat_g),
+        fiber_g: value(&[FDC_FIBER_ID]).map(round1),
+        sugar_g: value(&FDC_SUGAR_IDS).map(round1),
+        sodium_mg: value(&[FDC_SODIUM_ID]).map(f64::round),
+        portions,
+    })
+}
+
+/// The first of `ids` the search result reports, per 100 g.
+fn fdc_value(item: &FoodItem, ids: &[i32]) -> Option<f64> {
+    let nutrients = item.food_nutrients.as_ref()?;
+    ids.iter().find_map(|id| {
+        nutrients
+            .iter()
+            .find(|n| n.nutrient_id == *id)
+            .map(|n| n.value)
+    })
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
@@ -0,0 +1,230 @@
+use chrono::{ DateTime, FixedOffset, NaiveDate, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::{ Deserialize, Serialize };
+
+use crate::{
+    db::AppState,
+    error::Result,
+    models::HealthProfile,
+    services::{ report_service, timezone },
+};
+
+/// Points for eating the protein target; the other half is fiber.
+const PROTEIN_POINTS: f64 = 50.0;
+const FIBER_POINTS: f64 = 50.0;
+/// Most points sugar or sodium can each take off.
+const MAX_PENALTY_POINTS: f64 = 25.0;
+
+/// FDA daily value, for users without a health profile.
+const DEFAULT_PROTEIN_TARGET_G: f64 = 50.0;
+const DEFAULT_CALORIE_TARGET: f64 = 2000.0;
+/// Dietary Guidelines for Americans: 14 g of fiber per 1000 kcal.
+const FIBER_G_PER_1000_KCAL: f64 = 14.0;
+/// Share of calories from sugar where the penalty starts, and where it is
+/// full. Meals report total rather than added sugars, hence the leeway.
+const SUGAR_SHARE_FREE: f64 = 0.15;
+const SUGAR_SHARE_FULL: f64 = 0.25;
+const SODIUM_FREE_MG: f64 = 2300.0;
+const SODIUM_FULL_MG: f64 = 4600.0;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityFactor {
+    LowProtein,
+    LowFiber,
+    HighSugar,
+    HighSodium,
+}
+
+/// Points a factor cost the day, with the numbers behind it.
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityDeduction {
+    pub factor: QualityFactor,
+    pub points: f64,
+    pub detail: String,
+}
+
+/// 0-100 nutrient density score for one day: up to 50 points for reaching
+/// the protein target and 50 for the fiber target, minus up to 25 each for
+/// sugar and sodium. Without fiber data, protein is worth all 100.
+#[derive(Debug, Clone, Serialize)]
+pub struct FoodQuality {
+    pub score: f64,
+    pub protein_g: f64,
+    pub protein_target_g: f64,
+    /// Only set when every meal of the day reports fiber.
+    pub fiber_g: Option<f64>,
+    pub fiber_target_g: f64,
+    /// Sums over the meals that report them, so at least the real amount.
+    pub sugar_g: Option<f64>,
+    pub sodium_mg: Option<f64>,
+    pub meal_count: usize,
+    /// Biggest first; empty for a perfect day.
+    pub deductions: Vec<QualityDeduction>,
+}
+
+/// Per-day sums from `meal_logs`, with how many meals reported each of the
+/// optional nutrients.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QualityTotals {
+    pub calories: f64,
+    pub protein_g: f64,
+    pub fiber_g: f64,
+    pub sugar_g: f64,
+    pub sodium_mg: f64,
+    pub meal_count: usize,
+    pub fiber_meals: usize,
+    pub sugar_meals: usize,
+    pub sodium_meals: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DailyQualityTotals {
+    #[serde(rename = "_id")]
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub totals: QualityTotals,
+}
+
+/// Per-day quality totals for `[start, end)`, days running midnight to
+/// midnight at `offset`, oldest first. Days without meals are absent.
+pub async fn daily_quality_totals(
+    state: &AppState,
+    user_id: ObjectId,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    offset: FixedOffset
+) -> Result<Vec<DailyQualityTotals>> {
+    let reported = |field: &str| doc! { "$sum": { "$cond": [{ "$gt": [format!("${}", field), null] }, 1, 0] } };
+    let summed = |field: &str| doc! { "$sum": { "$ifNull": [format!("${}", field), 0.0] } };
+
+    let pipeline = vec![
+        report_service::meal_range_stage(user_id, start, end),
+        doc! {
+            "$group": {
+                "_id": {
+                    "$dateToString": {
+                        "format": "%Y-%m-%d",
+                        "date": "$date",
+                        "timezone": timezone::mongo_timezone(offset),
+                    }
+                },
+                "calories": { "$sum": "$calories" },
+                "protein_g": { "$sum": "$protein_g" },
+                "fiber_g": summed("fiber_g"),
+                "sugar_g": summed("sugar_g"),
+                "sodium_mg": summed("sodium_mg"),
+                "meal_count": { "$sum": 1 },
+                "fiber_meals": reported("fiber_g"),
+                "sugar_meals": reported("sugar_g"),
+                "sodium_meals": reported("sodium_mg"),
+            }
+        },
+        doc! { "$sort": { "_id": 1 } }
+    ];
+    report_service::aggregate_meals(state, pipeline).await
+}
+
+/// The score for one day at `offset`, or `None` if nothing was logged.
+pub async fn day_quality(
+    state: &AppState,
+    user_id: ObjectId,
+    profile: Option<&HealthProfile>,
+    day: NaiveDate,
+    offset: FixedOffset
+) -> Result<Option<FoodQuality>> {
+    let start = timezone::start_of_day(day, offset);
+    let totals = daily_quality_totals(state, user_id, start, start + chrono::Duration::days(1), offset).await?;
+
+    Ok(totals.first().and_then(|day_totals| score_day(&day_totals.totals, profile, day)))
+}
+
+pub fn score_day(totals: &QualityTotals, profile: Option<&HealthProfile>, day: NaiveDate) -> Option<FoodQuality> {
+    if totals.meal_count == 0 {
+        return None;
+    }
+
+    let (calorie_target, protein_target_g) = profile
+        .map(|profile| {
+            let (calories, protein_g, _, _) = profile.targets_on(day);
+            (calories, protein_g)
+        })
+        .filter(|(calories, protein_g)| *calories > 0.0 && *protein_g > 0.0)
+        .unwrap_or((DEFAULT_CALORIE_TARGET, DEFAULT_PROTEIN_TARGET_G));
+    let fiber_target_g = (calorie_target / 1000.0) * FIBER_G_PER_1000_KCAL;
+
+    let fiber_g = (totals.fiber_meals == totals.meal_count).then_some(totals.fiber_g);
+    let sugar_g = (totals.sugar_meals > 0).then_some(totals.sugar_g);
+    let sodium_mg = (totals.sodium_meals > 0).then_some(totals.sodium_mg);
+
+    let mut deductions = Vec::new();
+    let mut deduct = |factor, points: f64, detail: String| {
+        if points >= 0.5 {
+            deductions.push(QualityDeduction { factor, points: round1(points), detail });
+        }
+    };
+
+    let protein_points = if fiber_g.is_some() { PROTEIN_POINTS } else { PROTEIN_POINTS + FIBER_POINTS };
+    let protein_share = (totals.protein_g / protein_target_g).min(1.0);
+    deduct(
+        QualityFactor::LowProtein,
+        protein_points * (1.0 - protein_share),
+        format!("{} g of the {} g protein target", round1(totals.protein_g), round1(protein_target_g))
+    );
+
+    let mut score = protein_points * protein_share;
+
+    if let Some(fiber_g) = fiber_g {
+        let fiber_share = (fiber_g / fiber_target_g).min(1.0);
+        score += FIBER_POINTS * fiber_share;
+        deduct(
+            QualityFactor::LowFiber,
+            FIBER_POINTS * (1.0 - fiber_share),
+            format!("{} g of the {} g fiber target", round1(fiber_g), round1(fiber_target_g))
+        );
+    }
+
+    if let Some(sugar_g) = sugar_g.filter(|_| totals.calories > 0.0) {
+        let sugar_share = (sugar_g * 4.0) / totals.calories;
+        let penalty = penalty(sugar_share, SUGAR_SHARE_FREE, SUGAR_SHARE_FULL);
+        score -= penalty;
+        deduct(
+            QualityFactor::HighSugar,
+            penalty,
+            format!("{} g of sugar, {}% of calories", round1(sugar_g), (sugar_share * 100.0).round())
+        );
+    }
+
+    if let Some(sodium_mg) = sodium_mg {
+        let penalty = penalty(sodium_mg, SODIUM_FREE_MG, SODIUM_FULL_MG);
+        score -= penalty;
+        deduct(
+            QualityFactor::HighSodium,
+            penalty,
+            format!("{} mg of sodium, over the {} mg limit", sodium_mg.round(), SODIUM_FREE_MG)
+        );
+    }
+
+    deductions.sort_by(|a, b| b.points.total_cmp(&a.points));
+
+    Some(FoodQuality {
+        score: score.clamp(0.0, 100.0).round(),
+        protein_g: round1(totals.protein_g),
+        protein_target_g: round1(protein_target_g),
+        fiber_g: fiber_g.map(round1),
+        fiber_target_g: round1(fiber_target_g),
+        sugar_g: sugar_g.map(round1),
+        sodium_mg: sodium_mg.map(f64::round),
+        meal_count: totals.meal_count,
+        deductions,
+    })
+}
+
+/// Linear from 0 at `free` to the full penalty at `full`.
+fn penalty(value: f64, free: f64, full: f64) -> f64 {
+    (((value - free) / (full - free)).clamp(0.0, 1.0)) * MAX_PENALTY_POINTS
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
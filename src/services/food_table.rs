@@ -0,0 +1,388 @@
+use serde::Serialize;
+
+/// Nutrition for a frequently logged food, per 100 g of the food as eaten.
+pub struct FoodEntry {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    /// Weight of one standard serving, used when the description gives a count.
+    pub serving_g: f64,
+    pub serving_label: &'static str,
+    /// Offered by the snack suggestions endpoint.
+    pub snack: bool,
+}
+
+/// Values follow USDA FoodData Central / the Indonesian food composition table
+/// (TKPI) for the cooked or ready-to-eat form.
+pub const FOODS: &[FoodEntry] = &[
+    FoodEntry {
+        name: "White rice (cooked)",
+        aliases: &["rice", "white rice", "steamed rice", "cooked rice", "nasi", "nasi putih"],
+        calories: 130.0,
+        protein_g: 2.7,
+        carbs_g: 28.2,
+        fat_g: 0.3,
+        serving_g: 158.0,
+        serving_label: "1 cup",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Brown rice (cooked)",
+        aliases: &["brown rice", "nasi merah"],
+        calories: 123.0,
+        protein_g: 2.7,
+        carbs_g: 25.6,
+        fat_g: 1.0,
+        serving_g: 195.0,
+        serving_label: "1 cup",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Fried rice",
+        aliases: &["fried rice", "nasi goreng"],
+        calories: 168.0,
+        protein_g: 6.3,
+        carbs_g: 21.1,
+        fat_g: 6.2,
+        serving_g: 250.0,
+        serving_label: "1 plate",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Egg (boiled)",
+        aliases: &["egg", "eggs", "boiled egg", "boiled eggs", "hard boiled egg", "telur", "telur rebus"],
+        calories: 155.0,
+        protein_g: 12.6,
+        carbs_g: 1.1,
+        fat_g: 10.6,
+        serving_g: 50.0,
+        serving_label: "1 large egg",
+        snack: true,
+    },
+    FoodEntry {
+        name: "Fried egg",
+        aliases: &["fried egg", "fried eggs", "telur ceplok", "telur mata sapi", "telur goreng"],
+        calories: 196.0,
+        protein_g: 13.6,
+        carbs_g: 0.8,
+        fat_g: 14.8,
+        serving_g: 46.0,
+        serving_label: "1 large egg",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Chicken breast (cooked, skinless)",
+        aliases: &[
+            "chicken breast",
+            "chicken breasts",
+            "grilled chicken breast",
+            "boiled chicken breast",
+            "dada ayam",
+        ],
+        calories: 165.0,
+        protein_g: 31.0,
+        carbs_g: 0.0,
+        fat_g: 3.6,
+        serving_g: 120.0,
+        serving_label: "1 breast fillet",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Fried chicken",
+        aliases: &["fried chicken", "ayam goreng"],
+        calories: 260.0,
+        protein_g: 25.0,
+        carbs_g: 9.0,
+        fat_g: 13.5,
+        serving_g: 120.0,
+        serving_label: "1 piece",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Tempeh",
+        aliases: &["tempeh", "tempe"],
+        calories: 192.0,
+        protein_g: 20.3,
+        carbs_g: 7.6,
+        fat_g: 10.8,
+        serving_g: 50.0,
+        serving_label: "1 slice",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Fried tempeh",
+        aliases: &["fried tempeh", "tempe goreng"],
+        calories: 225.0,
+        protein_g: 17.1,
+        carbs_g: 8.4,
+        fat_g: 14.5,
+        serving_g: 35.0,
+        serving_label: "1 slice",
+        snack: true,
+    },
+    FoodEntry {
+        name: "Tofu",
+        aliases: &["tofu", "tahu"],
+        calories: 76.0,
+        protein_g: 8.1,
+        carbs_g: 1.9,
+        fat_g: 4.8,
+        serving_g: 100.0,
+        serving_label: "1 piece",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Fried tofu",
+        aliases: &["fried tofu", "tahu goreng"],
+        calories: 271.0,
+        protein_g: 17.2,
+        carbs_g: 10.5,
+        fat_g: 20.2,
+        serving_g: 40.0,
+        serving_label: "1 piece",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Banana",
+        aliases: &["banana", "bananas", "pisang"],
+        calories: 89.0,
+        protein_g: 1.1,
+        carbs_g: 22.8,
+        fat_g: 0.3,
+        serving_g: 118.0,
+        serving_label: "1 medium banana",
+        snack: true,
+    },
+    FoodEntry {
+        name: "Apple",
+        aliases: &["apple", "apples", "apel"],
+        calories: 52.0,
+        protein_g: 0.3,
+        carbs_g: 13.8,
+        fat_g: 0.2,
+        serving_g: 182.0,
+        serving_label: "1 medium apple",
+        snack: true,
+    },
+    FoodEntry {
+        name: "White bread",
+        aliases: &["white bread", "bread", "roti tawar", "roti"],
+        calories: 265.0,
+        protein_g: 9.0,
+        carbs_g: 49.0,
+        fat_g: 3.2,
+        serving_g: 25.0,
+        serving_label: "1 slice",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Oatmeal (cooked with water)",
+        aliases: &["oatmeal", "porridge oats", "oat"],
+        calories: 71.0,
+        protein_g: 2.5,
+        carbs_g: 12.0,
+        fat_g: 1.5,
+        serving_g: 234.0,
+        serving_label: "1 cup",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Whole milk",
+        aliases: &["milk", "whole milk", "susu", "susu sapi"],
+        calories: 61.0,
+        protein_g: 3.2,
+        carbs_g: 4.8,
+        fat_g: 3.3,
+        serving_g: 244.0,
+        serving_label: "1 cup",
+        snack: true,
+    },
+    FoodEntry {
+        name: "Potato (boiled)",
+        aliases: &["potato", "potatoes", "boiled potato", "kentang", "kentang rebus"],
+        calories: 87.0,
+        protein_g: 1.9,
+        carbs_g: 20.1,
+        fat_g: 0.1,
+        serving_g: 173.0,
+        serving_label: "1 medium potato",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Sweet potato (boiled)",
+        aliases: &["sweet potato", "sweet potatoes", "ubi", "ubi rebus"],
+        calories: 76.0,
+        protein_g: 1.4,
+        carbs_g: 17.7,
+        fat_g: 0.1,
+        serving_g: 150.0,
+        serving_label: "1 medium sweet potato",
+        snack: true,
+    },
+    FoodEntry {
+        name: "Salmon (cooked)",
+        aliases: &["salmon", "grilled salmon", "baked salmon"],
+        calories: 206.0,
+        protein_g: 22.1,
+        carbs_g: 0.0,
+        fat_g: 12.4,
+        serving_g: 100.0,
+        serving_label: "1 fillet",
+        snack: false,
+    },
+    FoodEntry {
+        name: "Greek yogurt (plain, nonfat)",
+        aliases: &["greek yogurt", "yogurt", "yoghurt"],
+        calories: 59.0,
+        protein_g: 10.2,
+        carbs_g: 3.6,
+        fat_g: 0.4,
+        serving_g: 170.0,
+        serving_label: "1 container",
+        snack: true,
+    },
+    FoodEntry {
+        name: "Almonds",
+        aliases: &["almonds", "almond", "kacang almond"],
+        calories: 579.0,
+        protein_g: 21.2,
+        carbs_g: 21.6,
+        fat_g: 49.9,
+        serving_g: 28.0,
+        serving_label: "1 handful",
+        snack: true,
+    },
+    FoodEntry {
+        name: "Edamame",
+        aliases: &["edamame"],
+        calories: 121.0,
+        protein_g: 11.9,
+        carbs_g: 8.9,
+        fat_g: 5.2,
+        serving_g: 155.0,
+        serving_label: "1 cup shelled",
+        snack: true,
+    },
+    FoodEntry {
+        name: "Roasted peanuts",
+        aliases: &["peanuts", "roasted peanuts", "kacang tanah", "kacang goreng"],
+        calories: 585.0,
+        protein_g: 23.7,
+        carbs_g: 21.5,
+        fat_g: 49.7,
+        serving_g: 28.0,
+        serving_label: "1 handful",
+        snack: true,
+    },
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FoodMatch {
+    pub food_name: String,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub serving_size: String,
+    pub grams: f64,
+}
+
+/// Looks up a description such as "rice", "2 eggs" or "150g chicken breast".
+/// Only exact (alias) matches count — anything else goes to the LLM.
+pub fn lookup(description: &str) -> Option<FoodMatch> {
+    let normalized = description
+        .trim()
+        .trim_end_matches('.')
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let (amount, food) = split_amount(&normalized);
+    let entry = FOODS.iter().find(|f| f.aliases.contains(&food))?;
+
+    let (grams, serving_size) = match amount {
+        Amount::Default => {
+            return Some(entry.standard_serving());
+        }
+        Amount::Grams(g) => (g, format!("{} g", g)),
+        Amount::Count(n) => {
+            let grams = n * entry.serving_g;
+            (grams, format!("{} x {} ({} g)", n, entry.serving_label, grams))
+        }
+    };
+
+    if grams <= 0.0 {
+        return None;
+    }
+
+    Some(entry.portion(grams, serving_size))
+}
+
+/// Splits a weight off a description: "200g salmon" gives `(Some(200.0), "salmon")`.
+/// Counts and descriptions without an amount give `None` and the whole text.
+pub fn split_grams(description: &str) -> (Option<f64>, String) {
+    let normalized = description.trim().to_lowercase();
+    match split_amount(&normalized) {
+        (Amount::Grams(grams), food) => (Some(grams), food.to_string()),
+        _ => (None, normalized.clone()),
+    }
+}
+
+impl FoodEntry {
+    pub fn standard_serving(&self) -> FoodMatch {
+        self.portion(self.serving_g, format!("{} ({} g)", self.serving_label, self.serving_g))
+    }
+
+    fn portion(&self, grams: f64, serving_size: String) -> FoodMatch {
+        let factor = grams / 100.0;
+
+        FoodMatch {
+            food_name: self.name.to_string(),
+            calories: (self.calories * factor).round(),
+            protein_g: round1(self.protein_g * factor),
+            carbs_g: round1(self.carbs_g * factor),
+            fat_g: round1(self.fat_g * factor),
+            serving_size,
+            grams,
+        }
+    }
+}
+
+enum Amount {
+    Default,
+    Grams(f64),
+    Count(f64),
+}
+
+fn split_amount(text: &str) -> (Amount, &str) {
+    let Some((first, rest)) = text.split_once(' ') else {
+        return (Amount::Default, text);
+    };
+
+    for suffix in ["grams", "gram", "gr", "g"] {
+        if let Some(number) = first.strip_suffix(suffix) {
+            if let Ok(grams) = number.parse::<f64>() {
+                return (Amount::Grams(grams), rest.strip_prefix("of ").unwrap_or(rest));
+            }
+        }
+    }
+
+    if let Ok(number) = first.parse::<f64>() {
+        if let Some((unit, food)) = rest.split_once(' ') {
+            if matches!(unit, "g" | "gr" | "gram" | "grams") {
+                return (Amount::Grams(number), food.strip_prefix("of ").unwrap_or(food));
+            }
+        }
+        return (Amount::Count(number), rest);
+    }
+
+    (Amount::Default, text)
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
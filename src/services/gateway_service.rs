@@ -0,0 +1,511 @@
+//! Inbound message gateway: meals sent by email or WhatsApp from an address
+//! or number verified for an account are analyzed like `/api/nutrition/analyze-text`,
+//! logged like the chat agent's `LOG_MEAL`, and answered with the day's totals.
+
+use chrono::{ Duration, Timelike, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
+use rand::Rng;
+use ring::hmac;
+use serde::{ Deserialize, Serialize };
+
+use crate::{
+    config::Config,
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ MealLog, MealSource, MealType, User, WhatsAppLink },
+    services::{
+        custom_food_service,
+        email_service::EmailService,
+        event_service,
+        progress_service,
+        quick_action_service,
+        report_service,
+        timezone,
+    },
+};
+
+/// Longer messages are cut off before analysis.
+pub const MAX_MESSAGE_CHARS: usize = 500;
+pub const WHATSAPP_CODE_MINUTES: i64 = 15;
+/// Providers retry deliveries; a message ID seen within this window is ignored.
+const MESSAGE_ID_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+const HELP_REPLY: &str =
+    "Send what you ate, e.g. \"lunch: nasi goreng with a fried egg\", and I'll log it. Start with breakfast, lunch, dinner or snack to pick the meal; otherwise it's guessed from the time of day.";
+
+/// Leading words that pick the meal type, English and Indonesian.
+const MEAL_TYPE_WORDS: &[(&str, MealType)] = &[
+    ("breakfast", MealType::Breakfast),
+    ("sarapan", MealType::Breakfast),
+    ("lunch", MealType::Lunch),
+    ("makan siang", MealType::Lunch),
+    ("dinner", MealType::Dinner),
+    ("makan malam", MealType::Dinner),
+    ("snack", MealType::Snack),
+    ("camilan", MealType::Snack),
+    ("cemilan", MealType::Snack),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Email,
+    Whatsapp,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Email => "email",
+            Channel::Whatsapp => "whatsapp",
+        }
+    }
+}
+
+/// A message as the provider forwards it.
+#[derive(Debug, Deserialize)]
+pub struct InboundMessage {
+    /// Sender email address (`Name <address>` is accepted) or WhatsApp number.
+    pub from: String,
+    #[serde(default)]
+    pub text: String,
+    /// Email subject, used when the body is empty.
+    #[serde(default)]
+    pub subject: Option<String>,
+    /// The provider's message ID, to drop redelivered messages.
+    #[serde(default)]
+    pub message_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InboundOutcome {
+    /// False for unknown senders and redelivered messages, which get no reply.
+    pub handled: bool,
+    pub meal_id: Option<String>,
+    /// Text for the provider to send back. Email replies are also sent by us.
+    pub reply: Option<String>,
+}
+
+impl InboundOutcome {
+    fn ignored() -> Self {
+        Self { handled: false, meal_id: None, reply: None }
+    }
+
+    fn reply(reply: impl Into<String>) -> Self {
+        Self { handled: true, meal_id: None, reply: Some(reply.into()) }
+    }
+}
+
+/// Checks the `sha256=<hex>` HMAC of `body` made with the gateway secret.
+pub fn verify_signature(config: &Config, body: &[u8], signature: Option<&str>) -> Result<()> {
+    let Some(secret) = &config.gateway.inbound_secret else {
+        return Err(AppError::NotFound("The inbound gateway is not enabled".to_string()));
+    };
+    let invalid = || AppError::BadRequest("Invalid gateway signature".to_string());
+
+    let signature = signature.ok_or_else(invalid)?;
+    let tag = decode_hex(signature.strip_prefix("sha256=").unwrap_or(signature)).ok_or_else(invalid)?;
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, body, &tag).map_err(|_| invalid())
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+/// `+` and 8 to 15 digits, from a number with spaces, dashes, brackets, a
+/// `00` international prefix or a `whatsapp:` scheme. Numbers without a
+/// country code are rejected.
+pub fn normalize_phone(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    let raw = raw.strip_prefix("whatsapp:").unwrap_or(raw).trim();
+    let digits: String = raw.chars().filter(|c| !matches!(c, ' ' | '-' | '(' | ')' | '.')).collect();
+    let digits = digits.strip_prefix('+').or_else(|| digits.strip_prefix("00"))?;
+
+    ((8..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit())).then(|| format!("+{}", digits))
+}
+
+/// Starts linking `number` to the user: saves it with a fresh verification
+/// code, replacing any number linked before. Messages from it are logged
+/// once the code is sent from it.
+pub async fn link_whatsapp(state: &AppState, user_id: ObjectId, number: &str) -> Result<WhatsAppLink> {
+    let users = state.db.collection::<User>("users");
+
+    let taken = users
+        .find_one(
+            doc! {
+                "_id": { "$ne": user_id },
+                "whatsapp.number": number,
+                "whatsapp.verified_at": { "$exists": true },
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    if taken.is_some() {
+        return Err(AppError::Conflict("This WhatsApp number is linked to another account".to_string()));
+    }
+
+    let link = WhatsAppLink {
+        number: number.to_string(),
+        verified_at: None,
+        verification_code: Some(format!("{:06}", rand::thread_rng().gen_range(0..1_000_000))),
+        code_expires_at: Some(Utc::now() + Duration::minutes(WHATSAPP_CODE_MINUTES)),
+    };
+
+    let result = users
+        .update_one(
+            doc! { "_id": user_id },
+            doc! {
+                "$set": {
+                    "whatsapp": mongodb::bson::to_bson(&link).map_err(|e| AppError::InternalError(e.into()))?,
+                    "updated_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    Ok(link)
+}
+
+/// Unlinks the user's WhatsApp number. `false` if they had none.
+pub async fn unlink_whatsapp(state: &AppState, user_id: ObjectId) -> Result<bool> {
+    let result = state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! { "_id": user_id, "whatsapp": { "$exists": true } },
+            doc! {
+                "$unset": { "whatsapp": "" },
+                "$set": { "updated_at": mongodb::bson::DateTime::from_chrono(Utc::now()) },
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(result.modified_count > 0)
+}
+
+/// Handles one inbound message: verifies a pending WhatsApp number, answers
+/// `help`, or logs the meal described and replies with the day's totals.
+pub async fn handle_inbound(state: &AppState, channel: Channel, message: InboundMessage) -> Result<InboundOutcome> {
+    if let Some(message_id) = &message.message_id {
+        if !claim_message(state, channel, message_id).await {
+            tracing::info!("Ignoring redelivered {} message {}", channel.as_str(), message_id);
+            return Ok(InboundOutcome::ignored());
+        }
+    }
+
+    let text = match channel {
+        Channel::Email => strip_quoted_reply(&message.text),
+        Channel::Whatsapp => message.text.trim().to_string(),
+    };
+    let text = if text.is_empty() { message.subject.clone().unwrap_or_default().trim().to_string() } else { text };
+    let text: String = text.chars().take(MAX_MESSAGE_CHARS).collect();
+
+    let user = match channel {
+        Channel::Email => email_sender(state, &message.from).await?,
+        Channel::Whatsapp => {
+            let Some(number) = normalize_phone(&message.from) else {
+                return Ok(InboundOutcome::ignored());
+            };
+            match whatsapp_sender(state, &number).await? {
+                Some(user) => Some(user),
+                None => {
+                    return Ok(
+                        if verify_whatsapp(state, &number, &text).await? {
+                            InboundOutcome::reply("Your WhatsApp number is linked. Send me what you eat and I'll log it.")
+                        } else {
+                            InboundOutcome::ignored()
+                        }
+                    );
+                }
+            }
+        }
+    };
+    let Some(user) = user else {
+        tracing::info!("Ignoring {} message from an unknown sender", channel.as_str());
+        return Ok(InboundOutcome::ignored());
+    };
+
+    let outcome = log_message(state, &user, &text).await?;
+
+    if channel == Channel::Email {
+        if let Some(reply) = &outcome.reply {
+            let subject = message.subject.as_deref().filter(|s| !s.trim().is_empty()).unwrap_or("Your meal");
+            if let Err(e) = email_service(state).send_gateway_reply(&user, subject, reply).await {
+                tracing::warn!("Failed to email gateway reply to {}: {}", user.gmail, e);
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+async fn log_message(state: &AppState, user: &User, text: &str) -> Result<InboundOutcome> {
+    let user_id = user.id.ok_or_else(|| AppError::InternalError(anyhow::anyhow!("User has no ID")))?;
+
+    if text.is_empty() || ["help", "bantuan", "?"].contains(&text.to_lowercase().as_str()) {
+        return Ok(InboundOutcome::reply(HELP_REPLY));
+    }
+
+    let offset = user.notification_preferences
+        .as_ref()
+        .and_then(|preferences| timezone::from_minutes(preferences.utc_offset_minutes))
+        .unwrap_or_else(timezone::utc);
+    let now = Utc::now();
+    let (meal_type, description) = split_meal_type(text);
+    let meal_type = meal_type
+        .or_else(|| quick_action_service::meal_due(now.with_timezone(&offset).hour()))
+        .unwrap_or(MealType::Snack);
+
+    if description.is_empty() {
+        return Ok(InboundOutcome::reply(HELP_REPLY));
+    }
+
+    let analysis = state.gemini_service.analyze_food_from_text(description).await.map_err(AppError::from_ai)?;
+    if analysis["is_valid_food"].as_bool() != Some(true) {
+        let message = analysis["message"].as_str().unwrap_or("I couldn't recognize a food in that message.");
+        return Ok(InboundOutcome::reply(format!("{} Nothing was logged.", message)));
+    }
+
+    let number = |key: &str| analysis[key].as_f64().filter(|value| value.is_finite() && *value >= 0.0).unwrap_or(0.0);
+    let calories = number("calories");
+    if calories <= 0.0 {
+        return Ok(InboundOutcome::reply("I couldn't estimate the calories for that. Nothing was logged."));
+    }
+
+    let mut meal_log = MealLog {
+        id: None,
+        user_id,
+        date: now,
+        meal_type,
+        food_name: analysis["food_name"].as_str().unwrap_or(description).to_string(),
+        calories,
+        protein_g: number("protein_g"),
+        carbs_g: number("carbs_g"),
+        fat_g: number("fat_g"),
+        fiber_g: None,
+        sugar_g: None,
+        sodium_mg: None,
+        items: Vec::new(),
+        serving_size: analysis["serving_size"].as_str().map(str::to_string),
+        notes: None,
+        source: MealSource::AiText,
+        verified: MealSource::AiText.is_verified(),
+        nutrition_snapshot: None,
+        portion_weight_g: None,
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: None,
+        mood: None,
+        eating_context: None,
+        version: 0,
+        created_at: now,
+    };
+
+    let result = state.db
+        .collection::<MealLog>("meal_logs")
+        .insert_one(&meal_log, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    meal_log.id = result.inserted_id.as_object_id();
+
+    report_service::invalidate_compliance_score(state, user_id).await;
+    progress_service::record_meal_added(state, &meal_log).await?;
+
+    let event_state = state.clone();
+    tokio::spawn(async move {
+        event_service::check_logging_milestones(&event_state, user_id).await;
+    });
+
+    let today = timezone::today(offset);
+    let totals = report_service::daily_meal_totals_in(
+        state,
+        user_id,
+        timezone::start_of_day(today, offset),
+        timezone::start_of_day(today + Duration::days(1), offset),
+        offset
+    ).await?
+        .into_iter()
+        .find(|day| day.date == today)
+        .map(|day| day.totals)
+        .unwrap_or_default();
+    let target = user.health_profile.as_ref().map(|profile| profile.targets_on(today).0);
+
+    tracing::info!("Logged gateway meal {:?} for user {}", meal_log.id, user_id);
+
+    Ok(InboundOutcome {
+        handled: true,
+        meal_id: meal_log.id.map(|id| id.to_hex()),
+        reply: Some(logged_reply(&meal_log, totals.calories, totals.meal_count, target)),
+    })
+}
+
+fn logged_reply(meal: &MealLog, day_calories: f64, day_meals: usize, target: Option<f64>) -> String {
+    let serving = meal.serving_size.as_deref().map(|serving| format!(" ({})", serving)).unwrap_or_default();
+    let today = match target.filter(|target| *target > 0.0) {
+        Some(target) => format!("{:.0} of {:.0} kcal", day_calories, target),
+        None => format!("{:.0} kcal", day_calories),
+    };
+    format!(
+        "Logged {}{} as {}: {:.0} kcal, {:.0} g protein, {:.0} g carbs, {:.0} g fat.\nToday so far: {} across {} meal{}.",
+        meal.food_name,
+        serving,
+        meal.meal_type.as_str(),
+        meal.calories,
+        meal.protein_g,
+        meal.carbs_g,
+        meal.fat_g,
+        today,
+        day_meals,
+        if day_meals == 1 { "" } else { "s" }
+    )
+}
+
+/// The meal type named at the start of `text`, and the rest of it.
+fn split_meal_type(text: &str) -> (Option<MealType>, &str) {
+    for (word, meal_type) in MEAL_TYPE_WORDS {
+        let Some(head) = text.get(..word.len()) else {
+            continue;
+        };
+        let rest = &text[word.len()..];
+        if head.eq_ignore_ascii_case(word) && !rest.starts_with(|c: char| c.is_alphanumeric()) {
+            let rest = rest.trim_start_matches(|c: char| c == ':' || c == '-' || c == ',' || c.is_whitespace());
+            return (Some(meal_type.clone()), rest.trim_end());
+        }
+    }
+    (None, text)
+}
+
+/// The new text of an email reply: lines up to the quoted message or the
+/// signature, joined into one line.
+fn strip_quoted_reply(text: &str) -> String {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let quote_header = line.starts_with("On ") && line.ends_with("wrote:");
+        if line.starts_with('>') || line == "--" || quote_header {
+            break;
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines.join(" ")
+}
+
+/// The verified account whose email sent the message.
+async fn email_sender(state: &AppState, from: &str) -> Result<Option<User>> {
+    let address = match (from.rfind('<'), from.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &from[start + 1..end],
+        _ => from,
+    };
+    let address = address.trim().to_lowercase();
+    if !address.contains('@') {
+        return Ok(None);
+    }
+
+    state.db
+        .collection::<User>("users")
+        .find_one(
+            doc! {
+                "gmail": { "$regex": format!("^{}$", custom_food_service::escape_regex(&address)), "$options": "i" },
+                "email_verification_status": true,
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+async fn whatsapp_sender(state: &AppState, number: &str) -> Result<Option<User>> {
+    state.db
+        .collection::<User>("users")
+        .find_one(doc! { "whatsapp.number": number, "whatsapp.verified_at": { "$exists": true } }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+/// Verifies the pending link of `number` whose code is `text`. False if no
+/// unexpired code matches.
+async fn verify_whatsapp(state: &AppState, number: &str, text: &str) -> Result<bool> {
+    let code = text.trim();
+    if code.is_empty() {
+        return Ok(false);
+    }
+
+    let users = state.db.collection::<User>("users");
+    let Some(user) = users
+        .find_one(doc! { "whatsapp.number": number, "whatsapp.verification_code": code }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))? else {
+        return Ok(false);
+    };
+    let now = Utc::now();
+    let unexpired = user.whatsapp
+        .and_then(|link| link.code_expires_at)
+        .is_some_and(|expires_at| expires_at > now);
+    if !unexpired {
+        return Ok(false);
+    }
+
+    // The code is in the filter so a code replaced meanwhile doesn't verify.
+    let result = users
+        .update_one(
+            doc! { "_id": user.id, "whatsapp.number": number, "whatsapp.verification_code": code },
+            doc! {
+                "$set": {
+                    "whatsapp.verified_at": now.to_rfc3339(),
+                    "updated_at": mongodb::bson::DateTime::from_chrono(now),
+                },
+                "$unset": { "whatsapp.verification_code": "", "whatsapp.code_expires_at": "" },
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.modified_count > 0 {
+        tracing::info!("Verified WhatsApp number for the meal gateway");
+    }
+    Ok(result.modified_count > 0)
+}
+
+/// Records `message_id` as handled; false if it already was. A Redis outage
+/// lets the message through, since a rare duplicate meal beats a lost one.
+async fn claim_message(state: &AppState, channel: Channel, message_id: &str) -> bool {
+    let mut conn = state.redis.clone();
+    let claimed: redis::RedisResult<Option<String>> = redis
+        ::cmd("SET")
+        .arg(format!("gateway_message:{}:{}", channel.as_str(), message_id))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(MESSAGE_ID_TTL_SECONDS)
+        .query_async(&mut conn).await;
+
+    match claimed {
+        Ok(claimed) => claimed.is_some(),
+        Err(e) => {
+            tracing::warn!("Failed to check gateway message ID, handling it anyway: {}", e);
+            true
+        }
+    }
+}
+
+fn email_service(state: &AppState) -> EmailService {
+    EmailService::new(
+        state.config.brevo.smtp_host.clone(),
+        state.config.brevo.smtp_port,
+        state.config.brevo.smtp_user.clone(),
+        state.config.brevo.smtp_pass.clone(),
+        state.config.brevo.from_email.clone(),
+        state.config.brevo.from_name.clone(),
+        state.config.branding.clone()
+    )
+}
+
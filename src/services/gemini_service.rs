@@ -1,19 +1,61 @@
 use anyhow::Result;
 use base64::{ engine::general_purpose, Engine as _ };
 use serde::{ Deserialize, Serialize };
-use std::sync::Arc;
+use serde_json::Value;
+use rand::Rng;
+use reqwest::StatusCode;
+use std::{ sync::{ Arc, Mutex }, time::{ Duration, Instant } };
+
+use crate::{
+    config::GeminiConfig,
+    error::{ AiRefusal, AiUnavailable },
+    services::{
+        food_analysis::{ self, FoodImageAnalysis },
+        food_table,
+        prompt_service::PromptRegistry,
+        provider_metrics_service::{ self, Provider },
+    },
+};
 
-#[derive(Debug, Serialize)]
+
+#[derive(Debug, Serialize, Default)]
 struct GeminiRequest {
     contents: Vec<Content>,
     #[serde(skip_serializing_if = "Option::is_none")]
     generation_config: Option<GenerationConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<ToolConfig>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    function_declarations: Vec<FunctionDeclaration>,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolConfig {
+    function_calling_config: FunctionCallingConfig,
+}
+
+#[derive(Debug, Serialize)]
+struct FunctionCallingConfig {
+    /// `AUTO` lets the model choose between answering and calling functions;
+    /// `NONE` makes it answer in text.
+    mode: &'static str,
 }
 
 #[derive(Debug, Serialize)]
 struct GenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     thinking_config: Option<ThinkingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -23,6 +65,8 @@ struct ThinkingConfig {
 
 #[derive(Debug, Serialize)]
 struct Content {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'static str>,
     parts: Vec<Part>,
 }
 
@@ -35,6 +79,10 @@ enum Part {
     InlineData {
         inline_data: InlineData,
     },
+    Model(ModelPart),
+    FunctionResponse {
+        function_response: FunctionResponse,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -45,103 +93,331 @@ struct InlineData {
 
 #[derive(Debug, Deserialize)]
 struct GeminiResponse {
+    #[serde(default)]
     candidates: Vec<Candidate>,
+    #[serde(rename = "promptFeedback")]
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PromptFeedback {
+    #[serde(rename = "blockReason")]
+    block_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Candidate {
-    content: ResponseContent,
+    content: Option<ResponseContent>,
+    #[serde(rename = "finishReason")]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ResponseContent {
-    parts: Vec<ResponsePart>,
+    #[serde(default)]
+    parts: Vec<ModelPart>,
 }
 
-#[derive(Debug, Deserialize)]
-struct ResponsePart {
-    text: String,
+/// A function the chat model may call. `parameters` is an OpenAPI-style
+/// schema object; `None` for functions without parameters.
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionDeclaration {
+    pub name: &'static str,
+    pub description: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionResponse {
+    pub name: String,
+    pub response: Value,
+}
+
+/// One part of a model reply: text or a function call. Replies are sent back
+/// unchanged in later requests, because Gemini 3 rejects function calls in
+/// the history without their thought signatures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPart {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "functionCall")]
+    pub function_call: Option<FunctionCall>,
+    #[serde(skip_serializing_if = "Option::is_none", default, alias = "thoughtSignature")]
+    pub thought_signature: Option<String>,
+}
+
+/// One entry in a function-calling conversation.
+#[derive(Debug, Clone)]
+pub enum Turn {
+    User(String),
+    Model(Vec<ModelPart>),
+    FunctionResponses(Vec<FunctionResponse>),
+}
+
+impl From<&Turn> for Content {
+    fn from(turn: &Turn) -> Self {
+        match turn {
+            Turn::User(text) =>
+                Content {
+                    role: Some("user"),
+                    parts: vec![Part::Text { text: text.clone() }],
+                },
+            Turn::Model(parts) =>
+                Content {
+                    role: Some("model"),
+                    parts: parts.iter().cloned().map(Part::Model).collect(),
+                },
+            Turn::FunctionResponses(responses) =>
+                Content {
+                    role: Some("user"),
+                    parts: responses
+                        .iter()
+                        .map(|response| Part::FunctionResponse { function_response: response.clone() })
+                        .collect(),
+                },
+        }
+    }
+}
+
+/// Finish reasons that mean the model withheld its answer rather than failed.
+const REFUSAL_FINISH_REASONS: [&str; 6] = [
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+    "IMAGE_SAFETY",
+];
+
+/// Worth retrying: rate limits and server-side failures.
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+impl GeminiResponse {
+    /// First candidate's text, or an [`AiRefusal`] when the prompt or answer was blocked.
+    fn into_text(self) -> Result<String> {
+        self.into_parts()?
+            .into_iter()
+            .find_map(|p| p.text)
+            .ok_or_else(|| anyhow::anyhow!("No response from Gemini API"))
+    }
+
+    /// First candidate's parts, or an [`AiRefusal`] when the prompt or answer was blocked.
+    fn into_parts(self) -> Result<Vec<ModelPart>> {
+        if let Some(reason) = self.prompt_feedback.and_then(|f| f.block_reason) {
+            tracing::warn!("Gemini blocked the prompt: {}", reason);
+            return Err(AiRefusal { reason }.into());
+        }
+
+        let Some(candidate) = self.candidates.into_iter().next() else {
+            tracing::warn!("Gemini returned no candidates");
+            return Err(AiRefusal { reason: "NO_CANDIDATES".to_string() }.into());
+        };
+
+        if let Some(reason) = &candidate.finish_reason {
+            if REFUSAL_FINISH_REASONS.contains(&reason.as_str()) {
+                tracing::warn!("Gemini stopped with finish reason {}", reason);
+                return Err(AiRefusal { reason: reason.clone() }.into());
+            }
+        }
+
+        candidate.content
+            .map(|c| c.parts)
+            .filter(|parts| !parts.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("No response from Gemini API"))
+    }
+}
+
+/// Stops calling Gemini for a while after too many failed requests in a row,
+/// so users get a quick "unavailable" instead of waiting through retries.
+/// Once the cooldown is over, one request at a time is let through: a success
+/// closes the breaker and a failure opens it again.
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
 }
 
 #[derive(Clone)]
 pub struct GeminiService {
     api_key: String,
     client: Arc<reqwest::Client>,
+    prompts: Arc<PromptRegistry>,
+    config: GeminiConfig,
+    breaker: Arc<Mutex<CircuitBreaker>>,
 }
 
 impl GeminiService {
-    pub fn new(api_key: String) -> Self {
+    pub fn new(api_key: String, prompts: Arc<PromptRegistry>, config: GeminiConfig) -> Self {
         Self {
             api_key,
             client: Arc::new(reqwest::Client::new()),
+            prompts,
+            config,
+            breaker: Arc::new(Mutex::new(CircuitBreaker::default())),
         }
     }
 
-    pub async fn analyze_food_image(&self, image_data: &[u8], mime_type: &str) -> Result<String> {
-        let base64_image = general_purpose::STANDARD.encode(image_data);
+    /// Generation settings from the config, the same for every request.
+    fn generation_config(&self) -> Option<GenerationConfig> {
+        Some(GenerationConfig {
+            thinking_config: self.config.thinking_level.clone().map(|thinking_level| ThinkingConfig { thinking_level }),
+            temperature: self.config.temperature,
+            max_output_tokens: self.config.max_output_tokens,
+        })
+    }
 
-        let prompt =
-            r#"Analyze this image for food content. Follow these steps:
+    /// Sends one generateContent request, retrying timeouts, connection
+    /// errors, 429s and 5xx with exponential backoff. Fails with
+    /// [`AiUnavailable`] when they keep failing or the breaker is open.
+    async fn send(&self, request_body: &GeminiRequest) -> Result<GeminiResponse> {
+        self.acquire()?;
+        let started = Instant::now();
 
-STEP 1 - VALIDATION:
-First, determine if the image contains actual human-edible food. 
-- If the image shows non-food items (objects, animals, people, text, memes, inappropriate content, etc.), respond ONLY with this JSON:
-{
-  "is_valid_food": false,
-  "error_type": "not_food",
-  "message": "This image does not appear to contain food. Please upload a clear photo of a meal or food item."
-}
+        // The key goes in a header: reqwest errors print the URL, and those
+        // end up in the logs and in `AiUnavailable`.
+        let url = format!(
+            "{}/models/{}:generateContent",
+            self.config.base_url.trim_end_matches('/'),
+            self.config.model
+        );
+        let mut attempt = 0;
+        loop {
+            let request = self.client
+                .post(&url)
+                .header("x-goog-api-key", &self.api_key)
+                .timeout(Duration::from_secs(self.config.timeout_seconds))
+                .json(request_body);
+
+            let failure = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    match response.json::<GeminiResponse>().await {
+                        Ok(gemini_response) => {
+                            self.record_success(started);
+                            return Ok(gemini_response);
+                        }
+                        Err(e) if e.is_timeout() => e.to_string(),
+                        Err(e) => {
+                            self.record_success(started);
+                            return Err(e.into());
+                        }
+                    }
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let error_text = response.text().await.unwrap_or_default();
+                    tracing::error!("Gemini API error: {} - {}", status, error_text);
+                    if !is_transient(status) {
+                        // Gemini answered, so it's up; the request was at fault.
+                        self.record_success(started);
+                        anyhow::bail!("Gemini API request failed: {} - {}", status, error_text);
+                    }
+                    format!("{} - {}", status, error_text)
+                }
+                Err(e) => e.to_string(),
+            };
+
+            if attempt >= self.config.max_retries {
+                self.record_failure(started);
+                return Err(AiUnavailable { reason: failure }.into());
+            }
 
-- If the image shows something that is NOT typically consumed by humans (pet food, raw inedible items, toxic substances, etc.), respond ONLY with this JSON:
-{
-  "is_valid_food": false,
-  "error_type": "not_edible",
-  "message": "This item is not typically consumed as human food. Please upload a photo of an edible meal or food item."
-}
+            let delay = self.config.retry_base_ms.saturating_mul(1 << attempt.min(16));
+            let jitter = rand::thread_rng().gen_range(0..=delay / 4);
+            tracing::warn!(
+                "Gemini request failed ({}), retrying in {} ms (attempt {} of {})",
+                failure,
+                delay + jitter,
+                attempt + 1,
+                self.config.max_retries
+            );
+            tokio::time::sleep(Duration::from_millis(delay + jitter)).await;
+            attempt += 1;
+        }
+    }
 
-- If the image is inappropriate, offensive, or contains sensitive content, respond ONLY with this JSON:
-{
-  "is_valid_food": false,
-  "error_type": "inappropriate",
-  "message": "This image cannot be processed. Please upload an appropriate photo of food."
-}
+    /// Fails fast while the breaker is open. After the cooldown, lets this
+    /// request through as the trial and keeps others out until it finishes.
+    fn acquire(&self) -> Result<()> {
+        let mut breaker = self.breaker.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(open_until) = breaker.open_until else {
+            return Ok(());
+        };
 
-STEP 2 - ANALYSIS (only if validation passes):
-If the image contains valid, human-edible food, provide detailed nutritional information in this JSON format:
-
-{
-  "is_valid_food": true,
-  "food_name": "name of the food item",
-  "serving_size": "typical serving size",
-  "calories": "estimated calories per serving",
-  "macronutrients": {
-    "protein": "grams of protein",
-    "carbohydrates": "grams of carbohydrates",
-    "fat": "grams of fat",
-    "fiber": "grams of fiber"
-  },
-  "micronutrients": {
-    "vitamins": ["list of significant vitamins"],
-    "minerals": ["list of significant minerals"]
-  },
-  "health_score": "score from 1-10 based on nutritional value",
-  "health_notes": "brief notes about health benefits or concerns",
-  "dietary_info": {
-    "is_vegetarian": true/false,
-    "is_vegan": true/false,
-    "is_gluten_free": true/false,
-    "allergens": ["list of common allergens present"]
-  },
-  "recommendations": "suggestions for healthier alternatives or complementary foods"
-}
+        let now = Instant::now();
+        if now < open_until {
+            return Err(AiUnavailable { reason: "circuit breaker is open".to_string() }.into());
+        }
+        // Covers the trial request's own retries.
+        breaker.open_until = Some(now + self.trial_window());
+        Ok(())
+    }
+
+    fn trial_window(&self) -> Duration {
+        let retries = u64::from(self.config.max_retries) + 1;
+        Duration::from_secs(self.config.timeout_seconds * retries) +
+            Duration::from_millis(self.config.retry_base_ms.saturating_mul(1 << retries.min(16)))
+    }
+
+    /// Whether calls are currently paused by the open breaker.
+    pub fn is_paused(&self) -> bool {
+        let breaker = self.breaker.lock().unwrap_or_else(|e| e.into_inner());
+        breaker.open_until.is_some_and(|open_until| Instant::now() < open_until)
+    }
+
+    fn record_success(&self, started: Instant) {
+        provider_metrics_service::record(Provider::Gemini, true, started);
+        let mut breaker = self.breaker.lock().unwrap_or_else(|e| e.into_inner());
+        if breaker.open_until.is_some() {
+            tracing::info!("Gemini is responding again; closing the circuit breaker");
+        }
+        *breaker = CircuitBreaker::default();
+    }
+
+    fn record_failure(&self, started: Instant) {
+        provider_metrics_service::record(Provider::Gemini, false, started);
+        let mut breaker = self.breaker.lock().unwrap_or_else(|e| e.into_inner());
+        breaker.consecutive_failures += 1;
+        if breaker.consecutive_failures >= self.config.breaker_threshold {
+            tracing::error!(
+                "Gemini failed {} requests in a row; pausing calls for {} s",
+                breaker.consecutive_failures,
+                self.config.breaker_cooldown_seconds
+            );
+            breaker.open_until = Some(Instant::now() + Duration::from_secs(self.config.breaker_cooldown_seconds));
+        }
+    }
 
-Be accurate based on visual analysis. If you cannot clearly identify the food, indicate uncertainty in your response but still provide estimates if it appears to be food."#;
+    pub fn prompts(&self) -> &PromptRegistry {
+        &self.prompts
+    }
+
+    /// Returns the parsed analysis together with the prompt version used.
+    /// A reply that isn't a usable analysis fails with [`AiMalformedResponse`](crate::error::AiMalformedResponse).
+    pub async fn analyze_food_image(
+        &self,
+        image_data: &[u8],
+        mime_type: &str
+    ) -> Result<(FoodImageAnalysis, String)> {
+        let base64_image = general_purpose::STANDARD.encode(image_data);
+
+        let prompt = self.prompts.render("food_image_analysis", &[])?;
 
         let request_body = GeminiRequest {
             contents: vec![Content {
+                role: None,
                 parts: vec![
                     Part::Text {
-                        text: prompt.to_string(),
+                        text: prompt.text,
                     },
                     Part::InlineData {
                         inline_data: InlineData {
@@ -151,53 +427,40 @@ Be accurate based on visual analysis. If you cannot clearly identify the food, i
                     }
                 ],
             }],
-            generation_config: Some(GenerationConfig {
-                thinking_config: Some(ThinkingConfig {
-                    thinking_level: "low".to_string(),
-                }),
-            }),
+            generation_config: self.generation_config(),
+            ..Default::default()
         };
 
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-3-pro-preview:generateContent?key={}",
-            self.api_key
-        );
+        tracing::info!("Sending request to Gemini ({}) for food analysis", self.config.model);
 
-        tracing::info!("Sending request to Gemini 3 Pro Preview API for food analysis");
-
-        let response = self.client.post(&url).json(&request_body).send().await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            tracing::error!("Gemini API error: {} - {}", status, error_text);
-            anyhow::bail!("Gemini API request failed: {} - {}", status, error_text);
-        }
+        let gemini_response = self.send(&request_body).await?;
 
-        let gemini_response: GeminiResponse = response.json().await?;
-
-        let analysis_text = gemini_response.candidates
-            .first()
-            .and_then(|c| c.content.parts.first())
-            .map(|p| p.text.clone())
-            .ok_or_else(|| anyhow::anyhow!("No response from Gemini API"))?;
+        let analysis_text = gemini_response.into_text()?;
 
         tracing::info!("Successfully received analysis from Gemini API");
 
-        Ok(analysis_text)
+        let analysis = food_analysis::parse(&analysis_text).inspect_err(|e| {
+            tracing::warn!("{} ({}). Response was: {}", e, prompt.version, analysis_text);
+        })?;
+
+        Ok((analysis, prompt.version))
     }
 
-    pub async fn quick_food_check(&self, image_data: &[u8], mime_type: &str) -> Result<String> {
+    pub async fn quick_food_check(
+        &self,
+        image_data: &[u8],
+        mime_type: &str
+    ) -> Result<(String, String)> {
         let base64_image = general_purpose::STANDARD.encode(image_data);
 
-        let prompt =
-            "Identify this food and provide a brief health assessment (1-2 sentences) including estimated calories and whether it's generally healthy or not.";
+        let prompt = self.prompts.render("food_quick_check", &[])?;
 
         let request_body = GeminiRequest {
             contents: vec![Content {
+                role: None,
                 parts: vec![
                     Part::Text {
-                        text: prompt.to_string(),
+                        text: prompt.text,
                     },
                     Part::InlineData {
                         inline_data: InlineData {
@@ -207,73 +470,98 @@ Be accurate based on visual analysis. If you cannot clearly identify the food, i
                     }
                 ],
             }],
-            generation_config: Some(GenerationConfig {
-                thinking_config: Some(ThinkingConfig {
-                    thinking_level: "low".to_string(),
-                }),
-            }),
+            generation_config: self.generation_config(),
+            ..Default::default()
         };
 
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-3-pro-preview:generateContent?key={}",
-            self.api_key
-        );
+        let gemini_response = self.send(&request_body).await?;
 
-        let response = self.client.post(&url).json(&request_body).send().await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            anyhow::bail!("Gemini API request failed: {} - {}", status, error_text);
-        }
+        let analysis_text = gemini_response.into_text()?;
 
-        let gemini_response: GeminiResponse = response.json().await?;
-
-        let analysis_text = gemini_response.candidates
-            .first()
-            .and_then(|c| c.content.parts.first())
-            .map(|p| p.text.clone())
-            .ok_or_else(|| anyhow::anyhow!("No response from Gemini API"))?;
-
-        Ok(analysis_text)
+        Ok((analysis_text, prompt.version))
     }
 
     pub async fn get_text_response(&self, prompt: &str) -> Result<String> {
         let request_body = GeminiRequest {
             contents: vec![Content {
+                role: None,
                 parts: vec![Part::Text {
                     text: prompt.to_string(),
                 }],
             }],
-            generation_config: Some(GenerationConfig {
-                thinking_config: Some(ThinkingConfig {
-                    thinking_level: "low".to_string(),
-                }),
-            }),
+            generation_config: self.generation_config(),
+            ..Default::default()
         };
 
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-3-pro-preview:generateContent?key={}",
-            self.api_key
-        );
+        let gemini_response = self.send(&request_body).await?;
 
-        let response = self.client.post(&url).json(&request_body).send().await?;
+        gemini_response.into_text()
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            anyhow::bail!("Gemini API request failed: {} - {}", status, error_text);
-        }
+    /// One step of a function-calling conversation: the model either answers
+    /// in text or asks for function calls, which the caller runs and sends back
+    /// as a [`Turn::FunctionResponses`] after the reply. With `allow_calls`
+    /// false, the model has to answer in text.
+    pub async fn generate_with_tools(
+        &self,
+        system_instruction: &str,
+        turns: &[Turn],
+        functions: &[FunctionDeclaration],
+        allow_calls: bool
+    ) -> Result<Vec<ModelPart>> {
+        let request_body = GeminiRequest {
+            contents: turns.iter().map(Content::from).collect(),
+            generation_config: self.generation_config(),
+            system_instruction: Some(Content {
+                role: None,
+                parts: vec![Part::Text { text: system_instruction.to_string() }],
+            }),
+            tools: Some(vec![Tool { function_declarations: functions.to_vec() }]),
+            tool_config: Some(ToolConfig {
+                function_calling_config: FunctionCallingConfig {
+                    mode: if allow_calls { "AUTO" } else { "NONE" },
+                },
+            }),
+        };
 
-        let gemini_response: GeminiResponse = response.json().await?;
+        let gemini_response = self.send(&request_body).await?;
 
-        let text = gemini_response.candidates
-            .first()
-            .and_then(|c| c.content.parts.first())
-            .map(|p| p.text.clone())
-            .ok_or_else(|| anyhow::anyhow!("No response from Gemini API"))?;
+        gemini_response.into_parts()
+    }
 
-        Ok(text)
+    pub async fn generate_coach_commentary(
+        &self,
+        report: &crate::models::MealReport,
+        language: &str
+    ) -> Result<(String, String)> {
+        let prompt = self.prompts.render("report_coach_commentary", &[
+            ("report_type", format!("{:?}", report.report_type)),
+            ("start_date", report.start_date.clone()),
+            ("end_date", report.end_date.clone()),
+            ("days_logged", report.days_logged.to_string()),
+            ("total_days", report.total_days.to_string()),
+            ("streak_days", report.streak_days.to_string()),
+            ("avg_calories", format!("{:.0}", report.avg_calories)),
+            ("avg_protein_g", format!("{:.1}", report.avg_protein_g)),
+            ("avg_carbs_g", format!("{:.1}", report.avg_carbs_g)),
+            ("avg_fat_g", format!("{:.1}", report.avg_fat_g)),
+            ("goal_type", report.goal_type.clone()),
+            (
+                "goal_status",
+                (if report.goal_achieved { "achieved" } else { "in progress" }).to_string(),
+            ),
+            ("calories_compliance", format!("{:.0}", report.calories_compliance_percent)),
+            ("protein_compliance", format!("{:.0}", report.protein_compliance_percent)),
+            ("carbs_compliance", format!("{:.0}", report.carbs_compliance_percent)),
+            ("fat_compliance", format!("{:.0}", report.fat_compliance_percent)),
+            ("days_on_target", report.days_on_target.to_string()),
+            ("data_quality_issues", report.data_quality.len().to_string()),
+            ("language", language.to_string()),
+        ])?;
+
+        let text = self.get_text_response(&prompt.text).await?;
+
+        Ok((text.trim().to_string(), prompt.version))
     }
 
     pub async fn analyze_food_from_text(
@@ -300,42 +588,42 @@ Be accurate based on visual analysis. If you cannot clearly identify the food, i
             }
         }
         
-        let prompt =
-            format!(r#"Analyze the following food description and provide detailed nutrition information.
-
-Food Description: {}
-
-IMPORTANT: First, determine if this is a valid, human-edible food item.
-
-If the description is NOT a valid food (e.g., non-food objects, inappropriate content, inedible items, or anything that shouldn't be consumed), respond ONLY with this JSON:
-{{
-    "is_valid_food": false,
-    "error_type": "not_food",
-    "message": "This doesn't appear to be a valid food item. Please enter an actual food or meal."
-}}
-
-If it IS a valid food, provide the response as a valid JSON object with this exact structure:
-{{
-    "is_valid_food": true,
-    "food_name": "the food name",
-    "calories": <number>,
-    "protein_g": <number>,
-    "carbs_g": <number>,
-    "fat_g": <number>,
-    "serving_size": "serving description"
-}}
-
-Guidelines:
-1. Use reasonable estimates for nutrition values based on standard servings
-2. If a portion size is mentioned (e.g., "200g", "2 slices"), use that for calculations
-3. If no portion is specified, assume a standard serving size
-4. All numeric values should be numbers (not strings)
-5. serving_size should describe what the nutrition values represent
-6. Be accurate but reasonable with estimates
-
-Return ONLY the JSON object, nothing else."#, food_description);
-
-        let response_text = self.get_text_response(&prompt).await?;
+        if let Some(food) = food_table::lookup(food_description) {
+            tracing::info!("Matched '{}' in the built-in food table", food_description);
+            return Ok(
+                serde_json::json!({
+                "is_valid_food": true,
+                "food_name": food.food_name,
+                "calories": food.calories,
+                "protein_g": food.protein_g,
+                "carbs_g": food.carbs_g,
+                "fat_g": food.fat_g,
+                "serving_size": food.serving_size,
+                "source": "food_table",
+            })
+            );
+        }
+
+        let prompt = self.prompts.render("food_text_analysis", &[
+            ("food_description", food_description.to_string()),
+        ])?;
+
+        let response_text = match self.get_text_response(&prompt.text).await {
+            Ok(text) => text,
+            Err(e) => {
+                let Some(refusal) = e.downcast_ref::<AiRefusal>() else {
+                    return Err(e);
+                };
+                return Ok(
+                    serde_json::json!({
+                    "is_valid_food": false,
+                    "error_type": "ai_refusal",
+                    "message": refusal.user_message(),
+                    "prompt_version": prompt.version,
+                })
+                );
+            }
+        };
         
         let response_lower = response_text.to_lowercase();
         let safety_indicators = [
@@ -371,7 +659,7 @@ Return ONLY the JSON object, nothing else."#, food_description);
             }));
         };
 
-        let nutrition_data: serde_json::Value = serde_json
+        let mut nutrition_data: serde_json::Value = serde_json
             ::from_str(json_str)
             .map_err(|e| {
                 tracing::warn!("Failed to parse JSON: {}. Response was: {}", e, response_text);
@@ -382,6 +670,11 @@ Return ONLY the JSON object, nothing else."#, food_description);
                 )
             })?;
 
+        if let Some(obj) = nutrition_data.as_object_mut() {
+            obj.insert("source".to_string(), serde_json::Value::String("ai".to_string()));
+            obj.insert("prompt_version".to_string(), serde_json::Value::String(prompt.version));
+        }
+
         Ok(nutrition_data)
     }
 }
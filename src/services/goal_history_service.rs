@@ -0,0 +1,179 @@
+use chrono::{ DateTime, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::Serialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ GoalEra, HealthProfile, WeightLog },
+    services::weight_service::{ self, WeightTrend },
+};
+
+/// Progress of the weigh-ins within one goal era towards its target.
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalEraProgress {
+    pub days: i64,
+    /// Latest weigh-in in the era.
+    pub current_weight_kg: Option<f64>,
+    pub weight_change_kg: Option<f64>,
+    /// Share of the way from the start weight to the target, 0 to 100. `None`
+    /// for maintenance, whose target is the start weight.
+    pub progress_percent: Option<f64>,
+    pub achieved: bool,
+    pub trend: Option<WeightTrend>,
+}
+
+/// The user's goal eras, oldest first. A profile saved before goal history
+/// was kept gets one era from its creation.
+pub async fn eras(
+    state: &AppState,
+    user_id: ObjectId,
+    profile: Option<&HealthProfile>
+) -> Result<Vec<GoalEra>> {
+    let mut eras: Vec<GoalEra> = state.db
+        .collection::<GoalEra>("goal_history")
+        .find(doc! { "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if eras.is_empty() {
+        if let Some(profile) = profile {
+            eras.push(insert_era(state, new_era(user_id, profile, profile.weight_kg, profile.created_at)).await?);
+        }
+    }
+
+    // Dates are stored as RFC 3339 strings, so they're ordered here.
+    eras.sort_by_key(|era| era.started_at);
+    Ok(eras)
+}
+
+/// Starts a new era when a save changed the goal or goal preset and closes
+/// the previous one. `previous` is the profile before the save. Failures are
+/// logged, since the profile itself is already saved.
+pub async fn record_change(
+    state: &AppState,
+    user_id: ObjectId,
+    previous: Option<&HealthProfile>,
+    profile: &HealthProfile
+) {
+    if let Some(previous) = previous {
+        if previous.goal == profile.goal && preset_id(previous) == preset_id(profile) {
+            return;
+        }
+    }
+
+    if let Err(e) = start_era(state, user_id, previous, profile).await {
+        tracing::warn!("Failed to record goal change for user {}: {}", user_id, e);
+    }
+}
+
+async fn start_era(
+    state: &AppState,
+    user_id: ObjectId,
+    previous: Option<&HealthProfile>,
+    profile: &HealthProfile
+) -> Result<()> {
+    let now = Utc::now();
+
+    if previous.is_some() {
+        // Backfills the previous goal's era if it was never recorded.
+        eras(state, user_id, previous).await?;
+    }
+    state.db
+        .collection::<GoalEra>("goal_history")
+        .update_many(
+            doc! { "user_id": user_id, "ended_at": { "$exists": false } },
+            doc! { "$set": { "ended_at": now.to_rfc3339() } },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    // A preset applied to an unchanged profile starts from the latest
+    // weigh-in rather than the weight entered with the survey.
+    let mut start_weight = profile.weight_kg;
+    if let Some(previous) = previous.filter(|p| p.weight_kg == profile.weight_kg) {
+        if let Some(log) = weight_service::latest_weight_before(state, user_id, now).await? {
+            if log.date > previous.updated_at {
+                start_weight = log.weight_kg;
+            }
+        }
+    }
+
+    insert_era(state, new_era(user_id, profile, start_weight, now)).await?;
+    Ok(())
+}
+
+/// The era in effect at `at`: the latest one started by then, else the first.
+pub fn era_at(eras: &[GoalEra], at: DateTime<Utc>) -> Option<&GoalEra> {
+    eras.iter()
+        .rev()
+        .find(|era| era.started_at <= at)
+        .or_else(|| eras.first())
+}
+
+/// Weigh-ins from the start of the era to its end, or to now for the current
+/// one, oldest first.
+pub async fn weigh_ins(state: &AppState, era: &GoalEra) -> Result<Vec<WeightLog>> {
+    weight_service::fetch_weight_logs_between(
+        state,
+        era.user_id,
+        era.started_at,
+        era.ended_at.unwrap_or_else(Utc::now)
+    ).await
+}
+
+pub async fn progress(state: &AppState, era: &GoalEra) -> Result<GoalEraProgress> {
+    Ok(progress_from(era, &weigh_ins(state, era).await?))
+}
+
+pub fn progress_from(era: &GoalEra, weigh_ins: &[WeightLog]) -> GoalEraProgress {
+    let trend = weight_service::trend(weigh_ins);
+    let current = trend.as_ref().map(|t| t.ending_weight);
+    let span = era.target_weight_kg - era.start_weight_kg;
+
+    GoalEraProgress {
+        days: (era.ended_at.unwrap_or_else(Utc::now) - era.started_at).num_days(),
+        current_weight_kg: current,
+        weight_change_kg: current.map(|weight| round2(weight - era.start_weight_kg)),
+        progress_percent: current
+            .filter(|_| span.abs() > f64::EPSILON)
+            .map(|weight| round2((((weight - era.start_weight_kg) / span) * 100.0).clamp(0.0, 100.0))),
+        achieved: current.is_some_and(|weight|
+            weight_service::goal_achieved(&era.goal, weight, era.target_weight_kg)
+        ),
+        trend,
+    }
+}
+
+fn new_era(user_id: ObjectId, profile: &HealthProfile, start_weight_kg: f64, started_at: DateTime<Utc>) -> GoalEra {
+    GoalEra {
+        id: None,
+        user_id,
+        goal: profile.goal.clone(),
+        goal_preset_id: preset_id(profile),
+        start_weight_kg,
+        target_weight_kg: round2(weight_service::target_weight_from(&profile.goal, start_weight_kg)),
+        daily_calories: profile.daily_calories,
+        started_at,
+        ended_at: None,
+    }
+}
+
+async fn insert_era(state: &AppState, mut era: GoalEra) -> Result<GoalEra> {
+    let result = state.db
+        .collection::<GoalEra>("goal_history")
+        .insert_one(&era, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    era.id = result.inserted_id.as_object_id();
+    Ok(era)
+}
+
+fn preset_id(profile: &HealthProfile) -> Option<String> {
+    profile.goal_preset.as_ref().map(|preset| preset.id.clone())
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,210 @@
+use chrono::Utc;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::Serialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    field_encryption,
+    models::{ AppliedGoalPreset, HealthGoal, HealthProfile, User },
+    services::{ condition_guidance, goal_history_service, response_cache_service, versioning },
+};
+
+/// Curated goals with their own calorie adjustment and macro split, for users
+/// who want more than the four basic `HealthGoal`s.
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalPreset {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Stored as the profile's goal, for everything that only knows goals.
+    pub goal: HealthGoal,
+    /// kcal added to TDEE.
+    pub calorie_adjustment: f64,
+    pub protein_share: f64,
+    pub carbs_share: f64,
+    pub fat_share: f64,
+    pub guidelines: &'static [&'static str],
+    pub recommended_foods: &'static [&'static str],
+    pub foods_to_avoid: &'static [&'static str],
+}
+
+pub const PRESETS: &[GoalPreset] = &[
+    GoalPreset {
+        id: "cutting",
+        name: "Cutting",
+        description: "Lose fat while keeping muscle: a moderate deficit with high protein.",
+        goal: HealthGoal::LoseWeight,
+        calorie_adjustment: -500.0,
+        protein_share: 0.4,
+        carbs_share: 0.3,
+        fat_share: 0.3,
+        guidelines: &[
+            "Eat a palm-sized portion of lean protein at every meal",
+            "Fill half the plate with vegetables to stay full on fewer calories",
+            "Keep strength training so the deficit comes from fat, not muscle",
+        ],
+        recommended_foods: &["chicken breast", "egg whites", "greek yogurt", "tuna", "broccoli", "leafy greens"],
+        foods_to_avoid: &["sugary drinks", "fried food", "pastries", "alcohol"],
+    },
+    GoalPreset {
+        id: "lean_bulk",
+        name: "Lean bulk",
+        description: "Build muscle with a small surplus to limit fat gain.",
+        goal: HealthGoal::GainWeight,
+        calorie_adjustment: 250.0,
+        protein_share: 0.3,
+        carbs_share: 0.45,
+        fat_share: 0.25,
+        guidelines: &[
+            "Spread protein over 4 or more meals",
+            "Eat most carbs around training",
+            "Aim to gain 0.25-0.5% of body weight per week",
+        ],
+        recommended_foods: &["oats", "rice", "potatoes", "lean beef", "eggs", "milk", "bananas"],
+        foods_to_avoid: &["fast food", "sugary snacks"],
+    },
+    GoalPreset {
+        id: "low_carb",
+        name: "Low carb",
+        description: "Fewer carbohydrates, more fat and protein, at maintenance calories.",
+        goal: HealthGoal::MaintainWeight,
+        calorie_adjustment: 0.0,
+        protein_share: 0.3,
+        carbs_share: 0.2,
+        fat_share: 0.5,
+        guidelines: &[
+            "Get most carbs from non-starchy vegetables",
+            "Prefer unsaturated fats such as olive oil, nuts and avocado",
+        ],
+        recommended_foods: &["eggs", "salmon", "avocado", "nuts", "cauliflower", "spinach"],
+        foods_to_avoid: &["bread", "pasta", "white rice", "sugary drinks", "sweets"],
+    },
+    GoalPreset {
+        id: "mediterranean",
+        name: "Mediterranean",
+        description: "Plant-forward eating with olive oil, fish and whole grains.",
+        goal: HealthGoal::MaintainWeight,
+        calorie_adjustment: 0.0,
+        protein_share: 0.2,
+        carbs_share: 0.45,
+        fat_share: 0.35,
+        guidelines: &[
+            "Use olive oil as the main cooking fat",
+            "Eat fish at least twice a week and limit red meat",
+            "Build meals around vegetables, legumes and whole grains",
+        ],
+        recommended_foods: &["olive oil", "fish", "lentils", "chickpeas", "whole grains", "tomatoes", "nuts"],
+        foods_to_avoid: &["processed meat", "refined grains", "sugary drinks"],
+    },
+    GoalPreset {
+        id: "diabetic_friendly",
+        name: "Diabetic-friendly",
+        description: "Moderate, evenly spread carbs from low-GI sources to keep blood sugar steady.",
+        goal: HealthGoal::MaintainWeight,
+        calorie_adjustment: 0.0,
+        protein_share: 0.25,
+        carbs_share: 0.35,
+        fat_share: 0.4,
+        guidelines: &[
+            "Spread carbs evenly across meals instead of one large serving",
+            "Choose low-GI carbs and pair them with protein or fat",
+            "Check with your doctor before changing diet alongside medication",
+        ],
+        recommended_foods: &["legumes", "brown rice", "oats", "non-starchy vegetables", "fish", "nuts"],
+        foods_to_avoid: &["white bread", "sugary drinks", "sweets", "fruit juice"],
+    },
+];
+
+pub fn find(id: &str) -> Option<&'static GoalPreset> {
+    PRESETS.iter().find(|preset| preset.id == id)
+}
+
+/// Guidelines as a block for AI prompts; empty without a preset.
+pub fn prompt_block(preset: Option<&AppliedGoalPreset>) -> String {
+    let Some(preset) = preset else {
+        return String::new();
+    };
+
+    let mut block = format!("Goal preset: {}\n", preset.name);
+    for line in &preset.guidelines {
+        block.push_str(&format!("  - {}\n", line));
+    }
+    block
+}
+
+/// Applies `preset` to the profile, recalculates its targets and saves it.
+/// `expected_version` comes from `If-Match`.
+pub async fn apply(
+    state: &AppState,
+    user_id: ObjectId,
+    profile: &HealthProfile,
+    preset: &GoalPreset,
+    expected_version: Option<i64>
+) -> Result<HealthProfile> {
+    if expected_version.is_some_and(|v| v != profile.version) {
+        return Err(versioning::conflict("health profile"));
+    }
+
+    let previous = profile;
+    let mut profile = profile.clone();
+    profile.goal = preset.goal.clone();
+    profile.goal_preset = Some(AppliedGoalPreset {
+        id: preset.id.to_string(),
+        name: preset.name.to_string(),
+        calorie_adjustment: preset.calorie_adjustment,
+        protein_share: preset.protein_share,
+        carbs_share: preset.carbs_share,
+        fat_share: preset.fat_share,
+        guidelines: to_strings(preset.guidelines),
+        recommended_foods: to_strings(preset.recommended_foods),
+        foods_to_avoid: to_strings(preset.foods_to_avoid),
+        applied_at: Utc::now(),
+    });
+
+    let (daily_calories, (protein_g, carbs_g, fat_g)) = profile.daily_targets(profile.tdee);
+    profile.daily_calories = daily_calories;
+    profile.daily_protein_g = protein_g;
+    profile.daily_carbs_g = carbs_g;
+    profile.daily_fat_g = fat_g;
+    // Carb limits in the guidance scale with the daily target.
+    profile.condition_guidance = Some(
+        condition_guidance::build(profile.medical_conditions.as_deref(), carbs_g)
+    );
+    profile.version += 1;
+    profile.updated_at = Utc::now();
+
+    let profile_bson = field_encryption::profile_to_bson(&profile).map_err(AppError::InternalError)?;
+
+    let result = state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! {
+                "_id": user_id,
+                "health_profile.version": versioning::version_filter(profile.version - 1),
+            },
+            doc! {
+                "$set": {
+                    "health_profile": profile_bson,
+                    "updated_at": Utc::now(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.matched_count == 0 {
+        return Err(versioning::conflict("health profile"));
+    }
+    response_cache_service::invalidate_profile(state, user_id).await;
+    goal_history_service::record_change(state, user_id, Some(previous), &profile).await;
+
+    Ok(profile)
+}
+
+fn to_strings(items: &[&str]) -> Vec<String> {
+    items
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
@@ -0,0 +1,182 @@
+use chrono::{ Duration, NaiveDate, TimeZone, Utc };
+use serde::Serialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ ActivityLevel, DietaryPreference, Gender, HealthGoal, User },
+    services::{ condition_guidance, report_service, weight_service::{ self, WeightTrend } },
+};
+
+pub const DEFAULT_SUMMARY_DAYS: i64 = 30;
+pub const MAX_SUMMARY_DAYS: i64 = 365;
+
+/// Everything a clinician needs at an appointment, in one document.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSummary {
+    pub patient_name: String,
+    pub patient_email: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub days: i64,
+    pub generated_at: String,
+    pub profile: SummaryProfile,
+    pub vitals: SummaryVitals,
+    /// `None` when nothing was weighed in the period.
+    pub weight_trend: Option<WeightTrend>,
+    pub weight_readings: Vec<WeightReading>,
+    pub intake: IntakeSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SummaryProfile {
+    pub age: i32,
+    pub gender: Gender,
+    pub height_cm: f64,
+    pub weight_kg: f64,
+    pub bmi: f64,
+    pub bmi_category: String,
+    pub activity_level: ActivityLevel,
+    pub goal: HealthGoal,
+    pub medical_conditions: Vec<String>,
+    pub allergies: Vec<String>,
+    pub dietary_preferences: Vec<DietaryPreference>,
+    /// Titles of the condition guidance the app applies to suggestions.
+    pub dietary_guidance: Vec<String>,
+    pub daily_calories: f64,
+    pub daily_protein_g: f64,
+    pub daily_carbs_g: f64,
+    pub daily_fat_g: f64,
+    pub updated_at: String,
+}
+
+/// Self-reported readings from the health profile.
+#[derive(Debug, Clone, Serialize)]
+pub struct SummaryVitals {
+    pub blood_pressure: Option<BloodPressureReading>,
+    /// mg/dL, as entered in the health survey.
+    pub fasting_blood_sugar: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BloodPressureReading {
+    pub systolic: i32,
+    pub diastolic: i32,
+    pub measured_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WeightReading {
+    pub date: String,
+    pub weight_kg: f64,
+}
+
+/// Averages over the days that have meals logged, so missed days don't read
+/// as fasting.
+#[derive(Debug, Clone, Serialize)]
+pub struct IntakeSummary {
+    pub days_logged: usize,
+    pub meals_logged: usize,
+    pub average_calories: f64,
+    pub average_protein_g: f64,
+    pub average_carbs_g: f64,
+    pub average_fat_g: f64,
+    /// Average calories as a percentage of the daily target.
+    pub calories_percent_of_target: Option<f64>,
+}
+
+/// Summary of the `days` days up to and including `end`.
+pub async fn build_summary(state: &AppState, user: &User, end: NaiveDate, days: i64) -> Result<HealthSummary> {
+    let user_id = user.id.ok_or_else(|| AppError::BadRequest("User has no ID".to_string()))?;
+    let profile = user.health_profile
+        .as_ref()
+        .ok_or_else(||
+            AppError::NotFound("Health profile not found. Please complete the health survey.".to_string())
+        )?;
+
+    let start = end - Duration::days(days - 1);
+    let start_datetime = Utc.from_utc_datetime(&start.and_hms_opt(0, 0, 0).unwrap());
+    let end_datetime = Utc.from_utc_datetime(&(end + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap());
+
+    let daily = report_service::daily_meal_totals(state, user_id, start_datetime, end_datetime).await?;
+    let days_logged = daily.len();
+    let average = |value: fn(&report_service::MealTotals) -> f64| {
+        if days_logged == 0 {
+            return 0.0;
+        }
+        round1(daily.iter().map(|d| value(&d.totals)).sum::<f64>() / (days_logged as f64))
+    };
+    let average_calories = average(|t| t.calories);
+
+    let intake = IntakeSummary {
+        days_logged,
+        meals_logged: daily.iter().map(|d| d.totals.meal_count).sum(),
+        average_calories,
+        average_protein_g: average(|t| t.protein_g),
+        average_carbs_g: average(|t| t.carbs_g),
+        average_fat_g: average(|t| t.fat_g),
+        calories_percent_of_target: (days_logged > 0 && profile.daily_calories > 0.0).then(||
+            round1((average_calories / profile.daily_calories) * 100.0)
+        ),
+    };
+
+    let weight_logs = weight_service::fetch_weight_logs_between(
+        state,
+        user_id,
+        start_datetime,
+        end_datetime - Duration::seconds(1)
+    ).await?;
+
+    Ok(HealthSummary {
+        patient_name: user.name.clone(),
+        patient_email: user.gmail.clone(),
+        period_start: start.format("%Y-%m-%d").to_string(),
+        period_end: end.format("%Y-%m-%d").to_string(),
+        days,
+        generated_at: Utc::now().to_rfc3339(),
+        profile: SummaryProfile {
+            age: profile.age,
+            gender: profile.gender.clone(),
+            height_cm: profile.height_cm,
+            weight_kg: profile.weight_kg,
+            bmi: profile.bmi,
+            bmi_category: profile.bmi_category.clone(),
+            activity_level: profile.activity_level,
+            goal: profile.goal.clone(),
+            medical_conditions: profile.medical_conditions.clone().unwrap_or_default(),
+            allergies: profile.allergies.clone().unwrap_or_default(),
+            dietary_preferences: profile.dietary_preferences.clone().unwrap_or_default(),
+            dietary_guidance: condition_guidance
+                ::for_profile(profile)
+                .into_iter()
+                .map(|g| g.title)
+                .collect(),
+            daily_calories: profile.daily_calories,
+            daily_protein_g: profile.daily_protein_g,
+            daily_carbs_g: profile.daily_carbs_g,
+            daily_fat_g: profile.daily_fat_g,
+            updated_at: profile.updated_at.to_rfc3339(),
+        },
+        vitals: SummaryVitals {
+            blood_pressure: profile.blood_pressure.as_ref().map(|bp| BloodPressureReading {
+                systolic: bp.systolic,
+                diastolic: bp.diastolic,
+                measured_at: bp.measured_at.to_rfc3339(),
+            }),
+            fasting_blood_sugar: profile.fasting_blood_sugar,
+        },
+        weight_trend: weight_service::trend(&weight_logs),
+        weight_readings: weight_logs
+            .iter()
+            .map(|log| WeightReading {
+                date: log.date.to_rfc3339(),
+                weight_kg: log.weight_kg,
+            })
+            .collect(),
+        intake,
+    })
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
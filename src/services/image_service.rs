@@ -0,0 +1,182 @@
+use base64::{ engine::general_purpose, Engine as _ };
+use chrono::{ Duration, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{ doc, oid::ObjectId, Bson, Document },
+    options::{ GridFsBucketOptions, GridFsUploadOptions },
+    gridfs::GridFsBucket,
+    Database,
+};
+use ring::hmac;
+
+use crate::{ config::Config, error::{ AppError, Result } };
+
+/// GridFS bucket holding the photos sent in chat messages.
+const BUCKET: &str = "chat_images";
+/// Image URLs stay valid at least this long after they are handed out.
+const SIGNED_URL_MINUTES: i64 = 60;
+/// Expiries are rounded up to this many seconds, so a message reread within
+/// the window gets the same URL and the browser's cached copy.
+const EXPIRY_GRANULARITY_SECS: i64 = 3600;
+
+/// Served back with their own content type, so anything that isn't plainly
+/// an image is refused.
+pub const ALLOWED_IMAGE_TYPES: [&str; 6] = [
+    "image/jpeg",
+    "image/png",
+    "image/webp",
+    "image/gif",
+    "image/heic",
+    "image/heif",
+];
+
+pub struct StoredImage {
+    pub bytes: Vec<u8>,
+    pub content_type: String,
+}
+
+fn bucket(db: &Database) -> GridFsBucket {
+    db.gridfs_bucket(GridFsBucketOptions::builder().bucket_name(BUCKET.to_string()).build())
+}
+
+pub fn is_allowed_type(mime_type: &str) -> bool {
+    ALLOWED_IMAGE_TYPES.contains(&mime_type)
+}
+
+/// Stores an image and returns its ID.
+pub async fn store_image(db: &Database, bytes: &[u8], mime_type: &str) -> Result<ObjectId> {
+    let options = GridFsUploadOptions::builder()
+        .metadata(doc! { "content_type": mime_type })
+        .build();
+
+    let id = bucket(db)
+        .upload_from_futures_0_3_reader(format!("{}.img", ObjectId::new().to_hex()), bytes, options).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(id)
+}
+
+pub async fn load_image(db: &Database, id: ObjectId) -> Result<StoredImage> {
+    let bucket = bucket(db);
+    let file = bucket
+        .find(doc! { "_id": id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_next().await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Image not found".to_string()))?;
+
+    let content_type = file.metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get_str("content_type").ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    let mut bytes = Vec::with_capacity(file.length as usize);
+    bucket
+        .download_to_futures_0_3_writer(Bson::ObjectId(id), &mut bytes).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(StoredImage { bytes, content_type })
+}
+
+/// Deletes the images attached to the chat messages matching `filter`. Call
+/// before deleting the messages themselves. Returns the images deleted.
+pub async fn delete_message_images(db: &Database, filter: Document) -> Result<u64> {
+    let mut filter = filter;
+    filter.insert("image_id", doc! { "$exists": true });
+
+    let image_ids = db
+        .collection::<Document>("chat_messages")
+        .distinct("image_id", filter, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let bucket = bucket(db);
+    let mut deleted = 0;
+    for id in image_ids.into_iter().filter(|id| matches!(id, Bson::ObjectId(_))) {
+        match bucket.delete(id.clone()).await {
+            Ok(()) => {
+                deleted += 1;
+            }
+            Err(e) => tracing::warn!("Failed to delete chat image {}: {}", id, e),
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Moves images still inlined as `data:` URLs in chat messages into GridFS.
+/// Only touches messages that still hold one, so after the first run this is
+/// a cheap no-op.
+pub async fn migrate_inline_images(db: &Database) -> Result<u64> {
+    let messages = db.collection::<Document>("chat_messages");
+    let mut cursor = messages
+        .find(doc! { "image_url": { "$regex": "^data:" } }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let mut migrated = 0;
+    while let Some(message) = cursor.try_next().await.map_err(|e| AppError::InternalError(e.into()))? {
+        let Ok(id) = message.get_object_id("_id") else {
+            continue;
+        };
+        let Some((mime_type, bytes)) = message.get_str("image_url").ok().and_then(decode_data_url) else {
+            tracing::warn!("Chat message {} has an unreadable inline image; leaving it", id);
+            continue;
+        };
+
+        let image_id = store_image(db, &bytes, &mime_type).await?;
+        messages
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": { "image_id": image_id }, "$unset": { "image_url": "" } },
+                None
+            ).await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+        migrated += 1;
+    }
+
+    Ok(migrated)
+}
+
+/// `data:<mime>;base64,<data>` into its MIME type and bytes.
+fn decode_data_url(url: &str) -> Option<(String, Vec<u8>)> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    let bytes = general_purpose::STANDARD.decode(data).ok()?;
+    Some((mime_type.to_string(), bytes))
+}
+
+/// Path of the image, signed so it can be loaded by an `<img>` tag without
+/// an access token. The signature covers the tenant, so a URL from one
+/// tenant can't read another's bucket.
+pub fn signed_url(config: &Config, id: ObjectId) -> String {
+    let earliest = Utc::now() + Duration::minutes(SIGNED_URL_MINUTES);
+    let expires = (earliest.timestamp() + EXPIRY_GRANULARITY_SECS - 1) / EXPIRY_GRANULARITY_SECS *
+        EXPIRY_GRANULARITY_SECS;
+
+    format!("/api/images/{}?expires={}&signature={}", id.to_hex(), expires, signature(config, id, expires))
+}
+
+/// Whether `signature` was issued for this image by [`signed_url`] and has
+/// not expired.
+pub fn verify_signature(config: &Config, id: ObjectId, expires: i64, signature: &str) -> bool {
+    if expires < Utc::now().timestamp() {
+        return false;
+    }
+    let Ok(tag) = general_purpose::URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+    hmac::verify(&signing_key(config), signed_message(config, id, expires).as_bytes(), &tag).is_ok()
+}
+
+fn signature(config: &Config, id: ObjectId, expires: i64) -> String {
+    let tag = hmac::sign(&signing_key(config), signed_message(config, id, expires).as_bytes());
+    general_purpose::URL_SAFE_NO_PAD.encode(tag.as_ref())
+}
+
+fn signing_key(config: &Config) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, config.jwt.secret.as_bytes())
+}
+
+fn signed_message(config: &Config, id: ObjectId, expires: i64) -> String {
+    format!("image:{}:{}:{}", config.tenant_database().unwrap_or(""), id.to_hex(), expires)
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use chrono::Utc;
+use redis::{ aio::ConnectionManager, AsyncCommands };
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Upper edges of the histogram buckets, in milliseconds. Slower requests
+/// land in the last bucket.
+const BUCKETS_MS: &[u64] = &[10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000, 60000];
+/// Minute buckets outlive the reporting window a little so a slow read
+/// doesn't race their expiry.
+const BUCKET_TTL_SECONDS: i64 = 2 * 60 * 60;
+const ROUTES_KEY: &str = "latency:routes";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteLatency {
+    /// Method and path pattern, e.g. `GET /api/meals/daily`.
+    pub route: String,
+    pub requests: u64,
+    /// Upper edge of the bucket holding the 95th percentile.
+    pub p95_ms: u64,
+    pub avg_ms: u64,
+}
+
+/// Adds one request to the route's histogram for the current minute. Shared
+/// by every instance through Redis.
+pub async fn record(redis: &ConnectionManager, route: &str, elapsed_ms: u64) -> Result<()> {
+    let key = bucket_key(Utc::now().timestamp() / 60, route);
+    let bucket = bucket_for(elapsed_ms);
+
+    let mut conn = redis.clone();
+    redis
+        ::pipe()
+        .hincr(&key, bucket.to_string(), 1)
+        .ignore()
+        .hincr(&key, "count", 1)
+        .ignore()
+        .hincr(&key, "sum_ms", elapsed_ms)
+        .ignore()
+        .expire(&key, BUCKET_TTL_SECONDS)
+        .ignore()
+        .sadd(ROUTES_KEY, route)
+        .ignore()
+        .expire(ROUTES_KEY, BUCKET_TTL_SECONDS)
+        .ignore()
+        .query_async::<_, ()>(&mut conn).await?;
+
+    Ok(())
+}
+
+/// Per-route statistics over the last `window_minutes`, slowest p95 first.
+pub async fn route_stats(redis: &ConnectionManager, window_minutes: i64) -> Result<Vec<RouteLatency>> {
+    let mut conn = redis.clone();
+    let routes: Vec<String> = conn.smembers(ROUTES_KEY).await?;
+    let current_minute = Utc::now().timestamp() / 60;
+
+    let mut stats = Vec::new();
+    for route in routes {
+        let mut pipe = redis::pipe();
+        for minute in current_minute - window_minutes + 1..=current_minute {
+            pipe.hgetall(bucket_key(minute, &route));
+        }
+        let minutes: Vec<HashMap<String, u64>> = pipe.query_async(&mut conn).await?;
+
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for minute in minutes {
+            for (field, value) in minute {
+                *counts.entry(field).or_default() += value;
+            }
+        }
+
+        let requests = counts.get("count").copied().unwrap_or(0);
+        if requests == 0 {
+            continue;
+        }
+        let sum_ms = counts.get("sum_ms").copied().unwrap_or(0);
+
+        stats.push(RouteLatency {
+            route,
+            requests,
+            p95_ms: percentile(&counts, requests, 0.95),
+            avg_ms: sum_ms / requests,
+        });
+    }
+
+    stats.sort_by_key(|s| std::cmp::Reverse(s.p95_ms));
+    Ok(stats)
+}
+
+/// Upper edge of the histogram bucket `elapsed_ms` falls in.
+pub(crate) fn bucket_for(elapsed_ms: u64) -> u64 {
+    BUCKETS_MS
+        .iter()
+        .copied()
+        .find(|edge| elapsed_ms <= *edge)
+        .unwrap_or(*BUCKETS_MS.last().unwrap())
+}
+
+/// Upper edge of the bucket holding `quantile` of the `total` counts, which
+/// are keyed by bucket edge.
+pub(crate) fn percentile(counts: &HashMap<String, u64>, total: u64, quantile: f64) -> u64 {
+    let target = ((total as f64) * quantile).ceil() as u64;
+    let mut seen = 0;
+    for edge in BUCKETS_MS {
+        seen += counts.get(&edge.to_string()).copied().unwrap_or(0);
+        if seen >= target {
+            return *edge;
+        }
+    }
+    *BUCKETS_MS.last().unwrap()
+}
+
+fn bucket_key(minute: i64, route: &str) -> String {
+    format!("latency:{}:{}", minute, route)
+}
@@ -0,0 +1,45 @@
+use chrono::NaiveDate;
+use mongodb::bson::oid::ObjectId;
+use std::{ collections::HashMap, sync::Mutex };
+use tokio::sync::broadcast;
+
+/// Changes a connection may fall behind by before it misses some; the next
+/// change brings it up to date again, since every push is the whole day.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Per-user channels announcing which day's totals changed, feeding the
+/// WebSocket at `/api/ws`. A channel exists only while its user has a
+/// connection open. In-process only: a change made through another instance
+/// isn't seen here.
+#[derive(Default)]
+pub struct LiveUpdates {
+    channels: Mutex<HashMap<ObjectId, broadcast::Sender<NaiveDate>>>,
+}
+
+impl LiveUpdates {
+    pub fn subscribe(&self, user_id: ObjectId) -> broadcast::Receiver<NaiveDate> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels
+            .entry(user_id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Announces that the user's totals for the UTC `day` changed. Costs a map
+    /// lookup when nobody is listening.
+    pub fn publish(&self, user_id: ObjectId, day: NaiveDate) {
+        let channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sender) = channels.get(&user_id) {
+            // Fails only without receivers, which `release` cleans up.
+            let _ = sender.send(day);
+        }
+    }
+
+    /// Drops the user's channel once their last connection has closed.
+    pub fn release(&self, user_id: ObjectId) {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        if channels.get(&user_id).is_some_and(|sender| sender.receiver_count() == 0) {
+            channels.remove(&user_id);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::FindOptions };
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ MealChangeAction, MealChangeActor, MealHistoryEntry, MealLog, MealValues },
+};
+
+/// Who is changing a meal, and for the assistant, in which chat session.
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeSource {
+    pub actor: MealChangeActor,
+    pub chat_session_id: Option<ObjectId>,
+}
+
+impl ChangeSource {
+    pub fn user() -> Self {
+        ChangeSource { actor: MealChangeActor::User, chat_session_id: None }
+    }
+
+    pub fn assistant(chat_session_id: ObjectId) -> Self {
+        ChangeSource { actor: MealChangeActor::Assistant, chat_session_id: Some(chat_session_id) }
+    }
+}
+
+/// Records the fields an edit changed, with their old and new values. An
+/// edit that changed nothing leaves no entry.
+pub async fn record_update(state: &AppState, previous: &MealLog, updated: &MealLog, source: ChangeSource) {
+    let (old_values, new_values) = changed_values(MealValues::from(previous), MealValues::from(updated));
+    if old_values == MealValues::default() {
+        return;
+    }
+
+    append(state, previous, MealChangeAction::Updated, source, old_values, Some(new_values), updated.version).await;
+}
+
+/// Records every value of a deleted meal, so it can be logged again.
+pub async fn record_delete(state: &AppState, meal: &MealLog, source: ChangeSource) {
+    append(state, meal, MealChangeAction::Deleted, source, MealValues::from(meal), None, meal.version).await;
+}
+
+/// Failures are logged rather than returned, like audit entries, so that
+/// history never blocks the change itself.
+async fn append(
+    state: &AppState,
+    meal: &MealLog,
+    action: MealChangeAction,
+    source: ChangeSource,
+    old_values: MealValues,
+    new_values: Option<MealValues>,
+    version: i64
+) {
+    let Some(meal_id) = meal.id else {
+        return;
+    };
+
+    let entry = MealHistoryEntry {
+        id: None,
+        meal_id,
+        user_id: meal.user_id,
+        action,
+        actor: source.actor,
+        chat_session_id: source.chat_session_id,
+        old_values,
+        new_values,
+        version,
+        created_at: Utc::now(),
+    };
+
+    if let Err(e) = state.db.collection::<MealHistoryEntry>("meal_history").insert_one(&entry, None).await {
+        tracing::error!("Failed to record history for meal {}: {}", meal_id, e);
+    }
+}
+
+/// The meal's history, oldest first. Also works for deleted meals.
+pub async fn history(state: &AppState, user_id: ObjectId, meal_id: ObjectId) -> Result<Vec<MealHistoryEntry>> {
+    state.db
+        .collection::<MealHistoryEntry>("meal_history")
+        .find(
+            doc! { "user_id": user_id, "meal_id": meal_id },
+            FindOptions::builder().sort(doc! { "created_at": 1, "_id": 1 }).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+/// Clears the fields that are the same on both sides.
+fn changed_values(mut old: MealValues, mut new: MealValues) -> (MealValues, MealValues) {
+    fn keep_changed<T: PartialEq>(old: &mut Option<T>, new: &mut Option<T>) {
+        if old == new {
+            *old = None;
+            *new = None;
+        }
+    }
+
+    keep_changed(&mut old.date, &mut new.date);
+    keep_changed(&mut old.meal_type, &mut new.meal_type);
+    keep_changed(&mut old.food_name, &mut new.food_name);
+    keep_changed(&mut old.calories, &mut new.calories);
+    keep_changed(&mut old.protein_g, &mut new.protein_g);
+    keep_changed(&mut old.carbs_g, &mut new.carbs_g);
+    keep_changed(&mut old.fat_g, &mut new.fat_g);
+    keep_changed(&mut old.items, &mut new.items);
+    keep_changed(&mut old.serving_size, &mut new.serving_size);
+    keep_changed(&mut old.notes, &mut new.notes);
+    keep_changed(&mut old.portion_weight_g, &mut new.portion_weight_g);
+    keep_changed(&mut old.source, &mut new.source);
+    keep_changed(&mut old.verified, &mut new.verified);
+    keep_changed(&mut old.hunger_level, &mut new.hunger_level);
+    keep_changed(&mut old.mood, &mut new.mood);
+    keep_changed(&mut old.eating_context, &mut new.eating_context);
+
+    (old, new)
+}
@@ -0,0 +1,425 @@
+use chrono::{ DateTime, Duration, NaiveDate, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::FindOptions };
+use serde::Deserialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{
+        DailyProgress,
+        HealthProfile,
+        MealLog,
+        MealPlan,
+        MealPlanDay,
+        MealSource,
+        MealType,
+        PlannedIngredient,
+        PlannedMeal,
+        User,
+    },
+    services::{ condition_guidance, goal_presets, progress_service, report_service },
+};
+
+pub const PLAN_DAYS: usize = 7;
+/// The AI occasionally ignores an allergy or returns the wrong number of days;
+/// those answers are retried once before giving up.
+const MAX_ATTEMPTS: usize = 2;
+
+#[derive(Debug, Deserialize)]
+struct GeneratedPlan {
+    days: Vec<GeneratedDay>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneratedDay {
+    meals: Vec<GeneratedMeal>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneratedMeal {
+    meal_type: MealType,
+    food_name: String,
+    description: Option<String>,
+    serving_size: Option<String>,
+    calories: f64,
+    protein_g: f64,
+    carbs_g: f64,
+    fat_g: f64,
+    #[serde(default)]
+    ingredients: Vec<PlannedIngredient>,
+}
+
+/// Generates and stores a plan for the 7 days starting at `start_date`.
+pub async fn generate(state: &AppState, user_id: ObjectId, start_date: NaiveDate) -> Result<MealPlan> {
+    let profile = load_profile(state, user_id).await?;
+
+    let (days, prompt_version) = generate_days(state, &profile, start_date, PLAN_DAYS, &[]).await?;
+
+    let now = Utc::now();
+    let mut plan = MealPlan {
+        id: None,
+        user_id,
+        start_date: start_date.format("%Y-%m-%d").to_string(),
+        days,
+        target_calories: profile.daily_calories.round(),
+        prompt_version,
+        created_at: now,
+        updated_at: now,
+    };
+
+    let result = state.db
+        .collection::<MealPlan>("meal_plans")
+        .insert_one(&plan, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    plan.id = result.inserted_id.as_object_id();
+
+    Ok(plan)
+}
+
+/// Replaces one day's meals, steering away from meals on the plan's other days.
+/// Days with a meal already marked as eaten are left alone.
+pub async fn regenerate_day(state: &AppState, user_id: ObjectId, plan_id: ObjectId, date: &str) -> Result<MealPlan> {
+    let plan = find_plan(state, user_id, plan_id).await?;
+    let day_index = day_index(&plan, date)?;
+
+    if plan.days[day_index].meals.iter().any(|m| m.eaten_at.is_some()) {
+        return Err(AppError::Conflict("Meals on this day were already eaten; it can't be regenerated".to_string()));
+    }
+
+    let profile = load_profile(state, user_id).await?;
+    let other_meals: Vec<String> = plan.days
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != day_index)
+        .flat_map(|(_, day)| day.meals.iter().map(|m| m.food_name.clone()))
+        .collect();
+
+    let day_date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_|
+        AppError::BadRequest("Invalid date format. Use YYYY-MM-DD".to_string())
+    )?;
+    let (mut days, prompt_version) = generate_days(state, &profile, day_date, 1, &other_meals).await?;
+    let day = days.remove(0);
+
+    let meals = mongodb::bson
+        ::to_bson(&day.meals)
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    // Guarded so a meal marked as eaten while the AI was answering isn't lost.
+    let result = state.db
+        .collection::<MealPlan>("meal_plans")
+        .update_one(
+            doc! {
+                "_id": plan_id,
+                "user_id": user_id,
+                format!("days.{}.date", day_index): date,
+                format!("days.{}.meals.eaten_at", day_index): { "$exists": false },
+            },
+            doc! {
+                "$set": {
+                    format!("days.{}.meals", day_index): meals,
+                    "prompt_version": prompt_version,
+                    "updated_at": Utc::now().to_rfc3339(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if result.matched_count == 0 {
+        return Err(AppError::Conflict("The plan changed while regenerating; please try again".to_string()));
+    }
+
+    find_plan(state, user_id, plan_id).await
+}
+
+/// Logs a planned meal as eaten at `date`. Each planned meal can be logged once.
+pub async fn mark_eaten(
+    state: &AppState,
+    user_id: ObjectId,
+    plan_id: ObjectId,
+    date: &str,
+    meal_index: usize,
+    eaten_at: DateTime<Utc>
+) -> Result<(MealLog, DailyProgress)> {
+    let plan = find_plan(state, user_id, plan_id).await?;
+    let day_index = day_index(&plan, date)?;
+    let planned = plan.days[day_index].meals
+        .get(meal_index)
+        .ok_or_else(|| AppError::NotFound("Planned meal not found".to_string()))?;
+
+    let meal_log_id = ObjectId::new();
+    let meal_path = format!("days.{}.meals.{}", day_index, meal_index);
+
+    // Claim the planned meal first so a double tap can't log it twice.
+    let claimed = state.db
+        .collection::<MealPlan>("meal_plans")
+        .update_one(
+            doc! {
+                "_id": plan_id,
+                "user_id": user_id,
+                format!("days.{}.date", day_index): date,
+                format!("{}.food_name", meal_path): &planned.food_name,
+                format!("{}.eaten_at", meal_path): { "$exists": false },
+            },
+            doc! {
+                "$set": {
+                    format!("{}.eaten_at", meal_path): eaten_at.to_rfc3339(),
+                    format!("{}.meal_log_id", meal_path): meal_log_id,
+                    "updated_at": Utc::now().to_rfc3339(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    if claimed.matched_count == 0 {
+        return Err(AppError::Conflict("This planned meal was already marked as eaten".to_string()));
+    }
+
+    let meal_log = MealLog {
+        id: Some(meal_log_id),
+        user_id,
+        date: eaten_at,
+        meal_type: planned.meal_type.clone(),
+        food_name: planned.food_name.clone(),
+        calories: planned.calories,
+        protein_g: planned.protein_g,
+        carbs_g: planned.carbs_g,
+        fat_g: planned.fat_g,
+        fiber_g: None,
+        sugar_g: None,
+        sodium_mg: None,
+        items: Vec::new(),
+        serving_size: planned.serving_size.clone(),
+        notes: planned.description.clone(),
+        source: MealSource::MealPlan,
+        verified: false,
+        nutrition_snapshot: None,
+        portion_weight_g: None,
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: None,
+        mood: None,
+        eating_context: None,
+        version: 0,
+        created_at: Utc::now(),
+    };
+
+    if let Err(e) = state.db.collection::<MealLog>("meal_logs").insert_one(&meal_log, None).await {
+        // Release the claim so the user can try again.
+        let _ = state.db
+            .collection::<MealPlan>("meal_plans")
+            .update_one(
+                doc! { "_id": plan_id },
+                doc! {
+                    "$unset": {
+                        format!("{}.eaten_at", meal_path): "",
+                        format!("{}.meal_log_id", meal_path): "",
+                    }
+                },
+                None
+            ).await;
+        return Err(AppError::InternalError(e.into()));
+    }
+
+    report_service::invalidate_compliance_score(state, user_id).await;
+    let progress = progress_service::record_meal_added(state, &meal_log).await?;
+
+    Ok((meal_log, progress))
+}
+
+pub async fn find_plan(state: &AppState, user_id: ObjectId, plan_id: ObjectId) -> Result<MealPlan> {
+    state.db
+        .collection::<MealPlan>("meal_plans")
+        .find_one(doc! { "_id": plan_id, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Meal plan not found".to_string()))
+}
+
+/// Newest first.
+pub async fn list_plans(state: &AppState, user_id: ObjectId, limit: i64) -> Result<Vec<MealPlan>> {
+    state.db
+        .collection::<MealPlan>("meal_plans")
+        .find(
+            doc! { "user_id": user_id },
+            FindOptions::builder()
+                .sort(doc! { "created_at": -1 })
+                .limit(limit)
+                .build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+async fn load_profile(state: &AppState, user_id: ObjectId) -> Result<HealthProfile> {
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    user.health_profile.ok_or_else(||
+        AppError::BadRequest("Complete your health profile to generate a meal plan".to_string())
+    )
+}
+
+fn day_index(plan: &MealPlan, date: &str) -> Result<usize> {
+    plan.days
+        .iter()
+        .position(|day| day.date == date)
+        .ok_or_else(|| AppError::NotFound(format!("Meal plan has no day {}", date)))
+}
+
+/// Asks the AI for `day_count` days starting at `start_date`. Returns the days
+/// and the prompt version that produced them.
+async fn generate_days(
+    state: &AppState,
+    profile: &HealthProfile,
+    start_date: NaiveDate,
+    day_count: usize,
+    avoid_meals: &[String]
+) -> Result<(Vec<MealPlanDay>, String)> {
+    let guidance = condition_guidance::for_profile(profile);
+    let allergies: Vec<String> = profile.allergies
+        .iter()
+        .flatten()
+        .map(|a| a.trim().to_lowercase())
+        .filter(|a| !a.is_empty())
+        .collect();
+
+    let prompt = state.gemini_service
+        .prompts()
+        .render("meal_plan", &[
+            ("day_count", day_count.to_string()),
+            ("daily_calories", format!("{:.0}", profile.daily_calories)),
+            ("protein_g", format!("{:.0}", profile.daily_protein_g)),
+            ("carbs_g", format!("{:.0}", profile.daily_carbs_g)),
+            ("fat_g", format!("{:.0}", profile.daily_fat_g)),
+            ("goal", format!("{:?}", profile.goal)),
+            (
+                "allergies",
+                if allergies.is_empty() {
+                    String::new()
+                } else {
+                    format!("- Allergies: {}", allergies.join(", "))
+                },
+            ),
+            (
+                "dietary_preferences",
+                match &profile.dietary_preferences {
+                    Some(prefs) if !prefs.is_empty() => format!("- Dietary preferences: {:?}", prefs),
+                    _ => String::new(),
+                },
+            ),
+            (
+                "medical_conditions",
+                match &profile.medical_conditions {
+                    Some(conditions) if !conditions.is_empty() =>
+                        format!("- Medical conditions: {}", conditions.join(", ")),
+                    _ => String::new(),
+                },
+            ),
+            (
+                "condition_guidance",
+                format!(
+                    "{}{}",
+                    condition_guidance::prompt_block(&guidance),
+                    goal_presets::prompt_block(profile.goal_preset.as_ref())
+                ),
+            ),
+            (
+                "avoid_meals",
+                if avoid_meals.is_empty() {
+                    String::new()
+                } else {
+                    format!("- Already planned this week, don't repeat: {}", avoid_meals.join(", "))
+                },
+            ),
+        ])
+        .map_err(AppError::InternalError)?;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let response = state.gemini_service.get_text_response(&prompt.text).await.map_err(AppError::from_ai)?;
+
+        match parse_days(&response, day_count, &allergies) {
+            Ok(generated) => {
+                let days = generated
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, day)| MealPlanDay {
+                        date: (start_date + Duration::days(i as i64)).format("%Y-%m-%d").to_string(),
+                        meals: day.meals.into_iter().map(planned_meal).collect(),
+                    })
+                    .collect();
+                return Ok((days, prompt.version));
+            }
+            Err(reason) => {
+                tracing::warn!("Meal plan attempt {}/{} rejected: {}", attempt, MAX_ATTEMPTS, reason);
+            }
+        }
+    }
+
+    Err(AppError::ExternalApiError("Could not generate a meal plan right now, please try again".to_string()))
+}
+
+fn parse_days(response: &str, day_count: usize, allergies: &[String]) -> std::result::Result<Vec<GeneratedDay>, String> {
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => {
+            return Err("no JSON object in response".to_string());
+        }
+    };
+    let plan: GeneratedPlan = serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+
+    if plan.days.len() != day_count {
+        return Err(format!("expected {} days, got {}", day_count, plan.days.len()));
+    }
+
+    for meal in plan.days.iter().flat_map(|day| &day.meals) {
+        let values = [meal.calories, meal.protein_g, meal.carbs_g, meal.fat_g];
+        if meal.food_name.trim().is_empty() || values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return Err(format!("unusable meal {:?}", meal.food_name));
+        }
+
+        let text = format!("{} {}", meal.food_name, meal.description.as_deref().unwrap_or("")).to_lowercase();
+        if let Some(allergen) = allergies.iter().find(|a| text.contains(a.as_str())) {
+            return Err(format!("{:?} mentions allergen {:?}", meal.food_name, allergen));
+        }
+    }
+
+    if plan.days.iter().any(|day| day.meals.is_empty()) {
+        return Err("a day has no meals".to_string());
+    }
+
+    Ok(plan.days)
+}
+
+fn planned_meal(meal: GeneratedMeal) -> PlannedMeal {
+    PlannedMeal {
+        meal_type: meal.meal_type,
+        food_name: meal.food_name.trim().to_string(),
+        description: meal.description,
+        calories: meal.calories.round(),
+        protein_g: round1(meal.protein_g),
+        carbs_g: round1(meal.carbs_g),
+        fat_g: round1(meal.fat_g),
+        serving_size: meal.serving_size,
+        ingredients: meal.ingredients
+            .into_iter()
+            .filter(|i| !i.name.trim().is_empty() && i.quantity.is_finite() && i.quantity > 0.0)
+            .map(|i| PlannedIngredient {
+                name: i.name.trim().to_string(),
+                quantity: i.quantity,
+                unit: i.unit.trim().to_lowercase(),
+            })
+            .collect(),
+        meal_log_id: None,
+        eaten_at: None,
+    }
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
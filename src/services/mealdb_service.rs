@@ -1,7 +1,9 @@
 use anyhow::{ Context, Result };
 use reqwest::Client;
 use serde::{ Deserialize, Serialize };
-use std::sync::Arc;
+use std::{ sync::Arc, time::Instant };
+
+use crate::services::provider_metrics_service::{ self, Provider };
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MealsResponse {
@@ -176,11 +178,13 @@ impl MealDbService {
     pub async fn search_meals(&self, query: &str) -> Result<Vec<Meal>> {
         let url = format!("{}/search.php", self.base_url);
 
+        let started = Instant::now();
         let response = self.client
             .get(&url)
             .query(&[("s", query)])
-            .send().await
-            .context("Failed to send request to MealDB API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::MealDb, started, &response);
+        let response = response.context("Failed to send request to MealDB API")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -198,11 +202,13 @@ impl MealDbService {
     pub async fn get_meal_by_id(&self, id: &str) -> Result<Option<Meal>> {
         let url = format!("{}/lookup.php", self.base_url);
 
+        let started = Instant::now();
         let response = self.client
             .get(&url)
             .query(&[("i", id)])
-            .send().await
-            .context("Failed to send request to MealDB API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::MealDb, started, &response);
+        let response = response.context("Failed to send request to MealDB API")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -220,10 +226,12 @@ impl MealDbService {
     pub async fn get_random_meal(&self) -> Result<Option<Meal>> {
         let url = format!("{}/random.php", self.base_url);
 
+        let started = Instant::now();
         let response = self.client
             .get(&url)
-            .send().await
-            .context("Failed to send request to MealDB API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::MealDb, started, &response);
+        let response = response.context("Failed to send request to MealDB API")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -253,11 +261,13 @@ impl MealDbService {
     pub async fn filter_by_category(&self, category: &str) -> Result<Vec<Meal>> {
         let url = format!("{}/filter.php", self.base_url);
 
+        let started = Instant::now();
         let response = self.client
             .get(&url)
             .query(&[("c", category)])
-            .send().await
-            .context("Failed to send request to MealDB API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::MealDb, started, &response);
+        let response = response.context("Failed to send request to MealDB API")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -275,11 +285,13 @@ impl MealDbService {
     pub async fn filter_by_area(&self, area: &str) -> Result<Vec<Meal>> {
         let url = format!("{}/filter.php", self.base_url);
 
+        let started = Instant::now();
         let response = self.client
             .get(&url)
             .query(&[("a", area)])
-            .send().await
-            .context("Failed to send request to MealDB API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::MealDb, started, &response);
+        let response = response.context("Failed to send request to MealDB API")?;
 
         if !response.status().is_success() {
             let status = response.status();
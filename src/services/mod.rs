@@ -5,3 +5,53 @@ pub mod fdc_service;
 pub mod ninja_service;
 pub mod mealdb_service;
 pub mod chat_agent_service;
+pub mod event_service;
+pub mod report_service;
+pub mod account_merge_service;
+pub mod account_data_service;
+pub mod prompt_service;
+pub mod food_table;
+pub mod pdf_service;
+pub mod audit_service;
+pub mod research_service;
+pub mod scheduler;
+pub mod suggestion_service;
+pub mod weight_service;
+pub mod water_service;
+pub mod activity_service;
+pub mod condition_guidance;
+pub mod goal_presets;
+pub mod health_summary_service;
+pub mod cache_service;
+pub mod favorite_service;
+pub mod progress_service;
+pub mod custom_food_service;
+pub mod versioning;
+pub mod retention_service;
+pub mod recipe_nutrition_service;
+pub mod meal_plan_service;
+pub mod shopping_list_service;
+pub mod response_cache_service;
+pub mod notification_service;
+pub mod reminder_service;
+pub mod latency_service;
+pub mod monthly_stats_service;
+pub mod timezone;
+pub mod demo_service;
+pub mod meal_history_service;
+pub mod food_lookup_service;
+pub mod chat_tools;
+pub mod widget_service;
+pub mod food_analysis;
+pub mod recompute_service;
+pub mod image_service;
+pub mod food_quality_service;
+pub mod provider_metrics_service;
+pub mod redis_health_service;
+pub mod dietary_filter;
+pub mod quick_action_service;
+pub mod achievement_service;
+pub mod gateway_service;
+pub mod goal_history_service;
+pub mod api_key_service;
+pub mod live_update_service;
@@ -0,0 +1,366 @@
+use chrono::{ Datelike, Duration, FixedOffset, NaiveDate, TimeZone, Utc };
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::ReplaceOptions };
+use serde::Deserialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ DayTotals, MealLog, MonthlyStats, User },
+    services::{ report_service::{ self, DailyMealTotals, MealTotals }, timezone },
+};
+
+/// Targets used when the user has no health profile, as in reports.
+const DEFAULT_TARGETS: (f64, f64, f64, f64) = (2000.0, 150.0, 250.0, 67.0);
+
+/// First day of the month holding `day`.
+pub fn month_start(day: NaiveDate) -> NaiveDate {
+    day.with_day(1).unwrap()
+}
+
+/// Last day of the month starting on `first`.
+pub fn month_end(first: NaiveDate) -> NaiveDate {
+    next_month(first) - Duration::days(1)
+}
+
+fn next_month(first: NaiveDate) -> NaiveDate {
+    if first.month() == 12 {
+        NaiveDate::from_ymd_opt(first.year() + 1, 1, 1).unwrap()
+    } else {
+        NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1).unwrap()
+    }
+}
+
+pub fn month_key(first: NaiveDate) -> String {
+    first.format("%Y-%m").to_string()
+}
+
+/// Only months that have ended are stored; the current one is still changing.
+fn is_completed(first: NaiveDate) -> bool {
+    first < month_start(Utc::now().date_naive())
+}
+
+/// Stats for the month starting on `first`. Completed months come from
+/// `monthly_stats`, and are computed and stored when missing; the current
+/// month is computed on every call.
+pub async fn get_month(state: &AppState, user_id: ObjectId, first: NaiveDate) -> Result<MonthlyStats> {
+    if is_completed(first) {
+        if let Some(stats) = find_month(state, user_id, first).await? {
+            return Ok(stats);
+        }
+    }
+
+    let user = find_user(state, user_id).await?;
+    let stats = compute(state, &user, first).await?;
+    if is_completed(first) {
+        store(state, &stats).await?;
+    }
+    Ok(stats)
+}
+
+/// Per-day totals for `[start_date, end_date]`, oldest first; days without
+/// meals are absent. Completed months are read from their stored stats, so
+/// long ranges don't re-aggregate every meal. Stored stats use UTC days, so
+/// other offsets are always aggregated live.
+pub async fn daily_totals(
+    state: &AppState,
+    user_id: ObjectId,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    offset: FixedOffset
+) -> Result<Vec<DailyMealTotals>> {
+    if !timezone::is_utc(offset) {
+        return report_service::daily_meal_totals_in(
+            state,
+            user_id,
+            timezone::start_of_day(start_date, offset),
+            timezone::start_of_day(end_date + Duration::days(1), offset),
+            offset
+        ).await;
+    }
+
+    let mut days = Vec::new();
+    let mut first = month_start(start_date);
+
+    while first <= end_date {
+        let last = month_end(first);
+        let from = first.max(start_date);
+        let to = last.min(end_date);
+
+        if is_completed(first) {
+            let stats = get_month(state, user_id, first).await?;
+            for day in stats.days {
+                let Ok(date) = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d") else {
+                    continue;
+                };
+                if date >= from && date <= to {
+                    days.push(DailyMealTotals {
+                        date,
+                        totals: MealTotals {
+                            calories: day.calories,
+                            protein_g: day.protein_g,
+                            carbs_g: day.carbs_g,
+                            fat_g: day.fat_g,
+                            meal_count: day.meal_count,
+                        },
+                    });
+                }
+            }
+        } else {
+            days.extend(
+                report_service::daily_meal_totals(state, user_id, day_start(from), day_start(to) + Duration::days(1)).await?
+            );
+        }
+
+        first = next_month(first);
+    }
+
+    Ok(days)
+}
+
+/// Drops the stored stats for the month holding `day`, after its meals changed.
+pub async fn invalidate(state: &AppState, user_id: ObjectId, day: NaiveDate) {
+    let result = state.db
+        .collection::<MonthlyStats>("monthly_stats")
+        .delete_one(doc! { "user_id": user_id, "month": month_key(month_start(day)) }, None).await;
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to invalidate monthly stats: {}", e);
+    }
+}
+
+pub async fn invalidate_all(state: &AppState, user_id: ObjectId) {
+    let result = state.db
+        .collection::<MonthlyStats>("monthly_stats")
+        .delete_many(doc! { "user_id": user_id }, None).await;
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to invalidate monthly stats: {}", e);
+    }
+}
+
+/// Computes last month's stats for every user who logged meals in it and
+/// doesn't have them yet. Run by the scheduler.
+pub async fn aggregate_previous_month(state: &AppState) -> anyhow::Result<()> {
+    let first = month_start(month_start(Utc::now().date_naive()) - Duration::days(1));
+    let key = month_key(first);
+
+    let user_ids = state.db
+        .collection::<MealLog>("meal_logs")
+        .distinct(
+            "user_id",
+            doc! {
+                "date": {
+                    "$gte": mongodb::bson::DateTime::from_chrono(day_start(first)),
+                    "$lt": mongodb::bson::DateTime::from_chrono(day_start(next_month(first))),
+                }
+            },
+            None
+        ).await?;
+
+    let collection = state.db.collection::<MonthlyStats>("monthly_stats");
+    let mut computed = 0;
+    for user_id in user_ids.into_iter().filter_map(|id| id.as_object_id()) {
+        if collection.find_one(doc! { "user_id": user_id, "month": &key }, None).await?.is_some() {
+            continue;
+        }
+        let Some(user) = state.db.collection::<User>("users").find_one(doc! { "_id": user_id }, None).await? else {
+            continue;
+        };
+
+        let stats = compute(state, &user, first).await?;
+        store(state, &stats).await?;
+        computed += 1;
+    }
+
+    if computed > 0 {
+        tracing::info!("Computed {} monthly stats for {}", computed, key);
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct LoggedMonth {
+    /// First day of the month.
+    #[serde(rename = "_id")]
+    first: NaiveDate,
+}
+
+/// Recomputes the stored stats of every completed month the user logged
+/// meals in, and drops stored months that no longer have any. Returns how
+/// many months were stored.
+pub async fn rebuild_user(state: &AppState, user: &User) -> Result<i64> {
+    let user_id = user.id.ok_or_else(|| AppError::BadRequest("User has no ID".to_string()))?;
+
+    let pipeline = vec![
+        doc! { "$match": { "user_id": user_id } },
+        doc! { "$group": { "_id": { "$dateToString": { "format": "%Y-%m-01", "date": "$date" } } } }
+    ];
+    let months: Vec<NaiveDate> = report_service
+        ::aggregate_meals::<LoggedMonth>(state, pipeline).await?
+        .into_iter()
+        .map(|month| month.first)
+        .filter(|first| is_completed(*first))
+        .collect();
+
+    let keys: Vec<String> = months
+        .iter()
+        .map(|first| month_key(*first))
+        .collect();
+    state.db
+        .collection::<MonthlyStats>("monthly_stats")
+        .delete_many(doc! { "user_id": user_id, "month": { "$nin": &keys } }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    for &first in &months {
+        let stats = compute(state, user, first).await?;
+        store(state, &stats).await?;
+    }
+
+    Ok(months.len() as i64)
+}
+
+async fn compute(state: &AppState, user: &User, first: NaiveDate) -> Result<MonthlyStats> {
+    let user_id = user.id.ok_or_else(|| AppError::BadRequest("User has no ID".to_string()))?;
+    let last = month_end(first);
+    let daily = report_service::daily_meal_totals(
+        state,
+        user_id,
+        day_start(first),
+        day_start(next_month(first))
+    ).await?;
+
+    let (target_calories, target_protein, target_carbs, target_fat) = match &user.health_profile {
+        Some(profile) => profile.average_targets(daily.iter().map(|day| day.date)),
+        None => DEFAULT_TARGETS,
+    };
+    let day_calories = |date: NaiveDate| match &user.health_profile {
+        Some(profile) => profile.targets_on(date).0,
+        None => DEFAULT_TARGETS.0,
+    };
+
+    let days_logged = daily.len();
+    let sum = |field: fn(&MealTotals) -> f64| -> f64 {
+        daily
+            .iter()
+            .map(|day| field(&day.totals))
+            .sum()
+    };
+    let total_calories = sum(|t| t.calories);
+    let total_protein = sum(|t| t.protein_g);
+    let total_carbs = sum(|t| t.carbs_g);
+    let total_fat = sum(|t| t.fat_g);
+    let average = |total: f64| if days_logged > 0 { total / (days_logged as f64) } else { 0.0 };
+    let compliance = |avg: f64, target: f64| {
+        if target > 0.0 { ((avg / target) * 100.0).min(100.0) } else { 0.0 }
+    };
+
+    let days_on_target = daily
+        .iter()
+        .filter(|day| (day.totals.calories - day_calories(day.date)).abs() / day_calories(day.date) <= 0.1)
+        .count();
+
+    let dates: Vec<NaiveDate> = daily
+        .iter()
+        .map(|day| day.date)
+        .collect();
+    let (longest_streak, streak_at_start, streak_at_end) = streaks(&dates, first, last);
+
+    Ok(MonthlyStats {
+        id: None,
+        user_id,
+        month: month_key(first),
+        total_days: ((last - first).num_days() as usize) + 1,
+        days_logged,
+        total_meals: daily
+            .iter()
+            .map(|day| day.totals.meal_count)
+            .sum(),
+        total_calories,
+        total_protein_g: total_protein,
+        total_carbs_g: total_carbs,
+        total_fat_g: total_fat,
+        avg_calories: average(total_calories),
+        avg_protein_g: average(total_protein),
+        avg_carbs_g: average(total_carbs),
+        avg_fat_g: average(total_fat),
+        target_calories,
+        target_protein_g: target_protein,
+        target_carbs_g: target_carbs,
+        target_fat_g: target_fat,
+        calories_compliance_percent: compliance(average(total_calories), target_calories),
+        protein_compliance_percent: compliance(average(total_protein), target_protein),
+        carbs_compliance_percent: compliance(average(total_carbs), target_carbs),
+        fat_compliance_percent: compliance(average(total_fat), target_fat),
+        days_on_target,
+        longest_streak,
+        streak_at_start,
+        streak_at_end,
+        days: daily
+            .iter()
+            .map(|day| DayTotals {
+                date: day.date.format("%Y-%m-%d").to_string(),
+                calories: day.totals.calories,
+                protein_g: day.totals.protein_g,
+                carbs_g: day.totals.carbs_g,
+                fat_g: day.totals.fat_g,
+                meal_count: day.totals.meal_count,
+            })
+            .collect(),
+        computed_at: Utc::now(),
+    })
+}
+
+/// Longest run of consecutive dates, and the runs touching `first` and `last`.
+fn streaks(dates: &[NaiveDate], first: NaiveDate, last: NaiveDate) -> (usize, usize, usize) {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    let mut at_start = 0;
+
+    for &date in dates {
+        current = match previous {
+            Some(prev) if (date - prev).num_days() == 1 => current + 1,
+            _ => 1,
+        };
+        if date - Duration::days((current as i64) - 1) == first {
+            at_start = current;
+        }
+        longest = longest.max(current);
+        previous = Some(date);
+    }
+
+    let at_end = if previous == Some(last) { current } else { 0 };
+    (longest, at_start, at_end)
+}
+
+async fn find_month(state: &AppState, user_id: ObjectId, first: NaiveDate) -> Result<Option<MonthlyStats>> {
+    state.db
+        .collection::<MonthlyStats>("monthly_stats")
+        .find_one(doc! { "user_id": user_id, "month": month_key(first) }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+async fn store(state: &AppState, stats: &MonthlyStats) -> Result<()> {
+    state.db
+        .collection::<MonthlyStats>("monthly_stats")
+        .replace_one(
+            doc! { "user_id": stats.user_id, "month": &stats.month },
+            stats,
+            ReplaceOptions::builder().upsert(true).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    Ok(())
+}
+
+async fn find_user(state: &AppState, user_id: ObjectId) -> Result<User> {
+    state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+fn day_start(day: NaiveDate) -> chrono::DateTime<Utc> {
+    Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap())
+}
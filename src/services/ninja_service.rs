@@ -2,7 +2,9 @@ use anyhow::{ Context, Result };
 use reqwest::Client;
 use serde::{ Deserialize, Serialize };
 use serde_json::Value;
-use std::sync::Arc;
+use std::{ sync::Arc, time::Instant };
+
+use crate::services::provider_metrics_service::{ self, Provider };
 
 fn parse_flexible_number(value: &Value) -> f64 {
     match value {
@@ -69,12 +71,14 @@ impl NinjaService {
 
         tracing::debug!("Calling Ninja API with query: {}", query);
 
+        let started = Instant::now();
         let response = self.client
             .get(&url)
             .header("X-Api-Key", &self.api_key)
             .query(&[("query", query)])
-            .send().await
-            .context("Failed to send request to Ninja API")?;
+            .send().await;
+        provider_metrics_service::record_response(Provider::Ninja, started, &response);
+        let response = response.context("Failed to send request to Ninja API")?;
 
         let status = response.status();
         tracing::debug!("Ninja API response status: {}", status);
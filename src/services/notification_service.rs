@@ -0,0 +1,315 @@
+use anyhow::Result;
+use chrono::{ DateTime, Duration, FixedOffset, NaiveTime, TimeZone, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId, Bson }, options::FindOptions };
+use std::collections::HashMap;
+
+use crate::{
+    db::AppState,
+    models::{
+        Notification,
+        NotificationChannel,
+        NotificationPreferences,
+        NotificationPriority,
+        QueuedNotification,
+        User,
+    },
+    services::email_service::EmailService,
+};
+
+/// Every outbound notification goes through here. It is stored in the in-app
+/// inbox, then sent on push right away, held until quiet hours end, or added
+/// to the next digest, depending on its priority and the user's preferences.
+pub async fn dispatch(state: &AppState, mut notification: Notification) -> Result<()> {
+    let result = state.db.collection::<Notification>("notifications").insert_one(&notification, None).await?;
+    notification.id = result.inserted_id.as_object_id();
+    let Some(notification_id) = notification.id else {
+        anyhow::bail!("Notification insert returned no ObjectId");
+    };
+
+    let preferences = load_preferences(state, notification.user_id).await?;
+    let now = Utc::now();
+
+    match notification.kind.priority() {
+        NotificationPriority::High => {
+            if !preferences.push_enabled {
+                return Ok(());
+            }
+            match quiet_until(&preferences, now) {
+                None => {
+                    if let Err(e) = send_push(state, &notification).await {
+                        tracing::warn!("Failed to send push notification: {}", e);
+                    }
+                }
+                Some(until) => {
+                    queue(state, &notification, notification_id, NotificationChannel::Push, false, until).await?;
+                }
+            }
+        }
+        NotificationPriority::Low => {
+            let digest_at = next_digest_at(&preferences, now);
+            if preferences.push_enabled {
+                queue(state, &notification, notification_id, NotificationChannel::Push, true, digest_at).await?;
+            }
+            if preferences.email_digest_enabled {
+                queue(state, &notification, notification_id, NotificationChannel::Email, true, digest_at).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores the notification in the inbox and sends it on `channels` right away,
+/// bypassing priorities and digests. Used for reminders, which are only
+/// useful on time and whose channels the user picked.
+pub async fn send_now(state: &AppState, mut notification: Notification, channels: &[NotificationChannel]) -> Result<()> {
+    let result = state.db.collection::<Notification>("notifications").insert_one(&notification, None).await?;
+    notification.id = result.inserted_id.as_object_id();
+
+    for channel in channels {
+        let sent = match channel {
+            NotificationChannel::Push => send_push(state, &notification).await,
+            NotificationChannel::Email => send_email(state, &notification).await,
+        };
+        if let Err(e) = sent {
+            tracing::warn!("Failed to send {:?} notification to user {}: {}", channel, notification.user_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn load_preferences(state: &AppState, user_id: ObjectId) -> Result<NotificationPreferences> {
+    let user = state.db.collection::<User>("users").find_one(doc! { "_id": user_id }, None).await?;
+    Ok(user.and_then(|u| u.notification_preferences).unwrap_or_default())
+}
+
+/// Sends everything whose hold has run out: held notifications one by one,
+/// digests as one message per user and channel.
+pub async fn deliver_due(state: &AppState) -> Result<()> {
+    let outbox = state.db.collection::<QueuedNotification>("notification_outbox");
+    let now = Utc::now();
+
+    // Claim the due entries first so concurrent instances can't send them twice.
+    let claim_id = ObjectId::new();
+    let claimed = outbox.update_many(
+        doc! {
+            "deliver_after": { "$lte": mongodb::bson::DateTime::from_chrono(now) },
+            "claim_id": Bson::Null,
+        },
+        doc! { "$set": { "claim_id": claim_id } },
+        None
+    ).await?;
+    if claimed.modified_count == 0 {
+        return Ok(());
+    }
+
+    let entries: Vec<QueuedNotification> = outbox
+        .find(doc! { "claim_id": claim_id }, None).await?
+        .try_collect().await?;
+
+    let mut groups: HashMap<(ObjectId, NotificationChannel, bool), Vec<ObjectId>> = HashMap::new();
+    for entry in entries {
+        groups.entry((entry.user_id, entry.channel, entry.digest)).or_default().push(entry.notification_id);
+    }
+
+    let mut sent = 0;
+    for ((user_id, channel, digest), notification_ids) in groups {
+        let notifications: Vec<Notification> = state.db
+            .collection::<Notification>("notifications")
+            .find(
+                doc! { "_id": { "$in": notification_ids } },
+                FindOptions::builder().sort(doc! { "_id": 1 }).build()
+            ).await?
+            .try_collect().await?;
+        if notifications.is_empty() {
+            continue;
+        }
+
+        let result = match (channel, digest) {
+            (NotificationChannel::Push, false) => send_each_push(state, &notifications).await,
+            (NotificationChannel::Push, true) => send_digest_push(state, user_id, &notifications).await,
+            (NotificationChannel::Email, _) => send_digest_email(state, user_id, &notifications).await,
+        };
+        match result {
+            Ok(()) => {
+                sent += 1;
+            }
+            Err(e) => tracing::warn!("Failed to deliver {:?} notifications to user {}: {}", channel, user_id, e),
+        }
+    }
+
+    outbox.delete_many(doc! { "claim_id": claim_id }, None).await?;
+
+    if sent > 0 {
+        tracing::info!("Delivered {} held notification batches", sent);
+    }
+
+    Ok(())
+}
+
+async fn queue(
+    state: &AppState,
+    notification: &Notification,
+    notification_id: ObjectId,
+    channel: NotificationChannel,
+    digest: bool,
+    deliver_after: DateTime<Utc>
+) -> Result<()> {
+    let entry = QueuedNotification {
+        id: None,
+        user_id: notification.user_id,
+        notification_id,
+        channel,
+        digest,
+        deliver_after,
+        claim_id: None,
+        created_at: Utc::now(),
+    };
+    state.db.collection::<QueuedNotification>("notification_outbox").insert_one(&entry, None).await?;
+    Ok(())
+}
+
+/// When quiet hours end, if `now` falls inside them.
+pub fn quiet_until(preferences: &NotificationPreferences, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let offset = user_offset(preferences);
+    let start = parse_time(preferences.quiet_hours_start.as_deref()?)?;
+    let end = parse_time(preferences.quiet_hours_end.as_deref()?)?;
+    let local = now.with_timezone(&offset);
+    let time = local.time();
+
+    let end_date = if start <= end {
+        // Same-day window, e.g. 13:00-15:00.
+        if time < start || time >= end {
+            return None;
+        }
+        local.date_naive()
+    } else if time >= start {
+        // Overnight window, e.g. 22:00-07:00, before midnight.
+        local.date_naive() + Duration::days(1)
+    } else if time < end {
+        local.date_naive()
+    } else {
+        return None;
+    };
+
+    offset
+        .from_local_datetime(&end_date.and_time(end))
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// The next digest time, pushed past quiet hours if it falls inside them.
+pub fn next_digest_at(preferences: &NotificationPreferences, now: DateTime<Utc>) -> DateTime<Utc> {
+    let offset = user_offset(preferences);
+    let digest_time = parse_time(&preferences.digest_time).unwrap_or(NaiveTime::MIN);
+    let local = now.with_timezone(&offset);
+
+    let mut date = local.date_naive();
+    if local.time() >= digest_time {
+        date += Duration::days(1);
+    }
+    let digest_at = offset
+        .from_local_datetime(&date.and_time(digest_time))
+        .single()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or(now);
+
+    quiet_until(preferences, digest_at).unwrap_or(digest_at)
+}
+
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+fn user_offset(preferences: &NotificationPreferences) -> FixedOffset {
+    FixedOffset::east_opt(preferences.utc_offset_minutes * 60).unwrap_or(FixedOffset::east_opt(0).unwrap())
+}
+
+async fn send_each_push(state: &AppState, notifications: &[Notification]) -> Result<()> {
+    for notification in notifications {
+        send_push(state, notification).await?;
+    }
+    Ok(())
+}
+
+async fn send_digest_push(state: &AppState, user_id: ObjectId, notifications: &[Notification]) -> Result<()> {
+    let titles: Vec<&str> = notifications
+        .iter()
+        .map(|n| n.title.as_str())
+        .collect();
+
+    post_push(
+        state,
+        &serde_json::json!({
+            "user_id": user_id.to_hex(),
+            "title": format!("{} update{}", notifications.len(), if notifications.len() == 1 { "" } else { "s" }),
+            "message": titles.join(" · "),
+            "kind": "digest",
+            "notification_ids": notifications
+                .iter()
+                .filter_map(|n| n.id.map(|id| id.to_hex()))
+                .collect::<Vec<_>>(),
+        })
+    ).await
+}
+
+async fn send_digest_email(state: &AppState, user_id: ObjectId, notifications: &[Notification]) -> Result<()> {
+    let Some(user) = state.db.collection::<User>("users").find_one(doc! { "_id": user_id }, None).await? else {
+        return Ok(());
+    };
+
+    email_service(state).send_digest_email(&user, notifications).await?;
+    Ok(())
+}
+
+async fn send_email(state: &AppState, notification: &Notification) -> Result<()> {
+    let Some(user) = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": notification.user_id }, None).await? else {
+        return Ok(());
+    };
+
+    email_service(state).send_notification_email(&user, notification).await?;
+    Ok(())
+}
+
+fn email_service(state: &AppState) -> EmailService {
+    EmailService::new(
+        state.config.brevo.smtp_host.clone(),
+        state.config.brevo.smtp_port,
+        state.config.brevo.smtp_user.clone(),
+        state.config.brevo.smtp_pass.clone(),
+        state.config.brevo.from_email.clone(),
+        state.config.brevo.from_name.clone(),
+        state.config.branding.clone()
+    )
+}
+
+async fn send_push(state: &AppState, notification: &Notification) -> Result<()> {
+    post_push(
+        state,
+        &serde_json::json!({
+            "user_id": notification.user_id.to_hex(),
+            "title": notification.title,
+            "message": notification.message,
+            "kind": notification.kind,
+            "report_id": notification.report_id.map(|id| id.to_hex()),
+        })
+    ).await
+}
+
+async fn post_push(state: &AppState, body: &serde_json::Value) -> Result<()> {
+    let Some(url) = &state.config.notifications.push_webhook_url else {
+        return Ok(());
+    };
+
+    let response = reqwest::Client::new().post(url).json(body).send().await?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("Push webhook returned {}", response.status());
+    }
+
+    Ok(())
+}
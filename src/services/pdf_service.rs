@@ -0,0 +1,635 @@
+use chrono::{ NaiveDate, Utc };
+use std::collections::BTreeMap;
+
+use crate::{
+    models::{ HealthProfile, MealLog, MealType, User },
+    services::health_summary_service::HealthSummary,
+};
+
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 40.0;
+const FOOTER_Y: f64 = 24.0;
+
+/// Minimal single-font PDF writer (Helvetica / Helvetica-Bold, WinAnsi text).
+/// Enough for tabular exports without pulling in a layout engine.
+pub struct PdfDocument {
+    pages: Vec<String>,
+    y: f64,
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        let mut doc = Self { pages: Vec::new(), y: 0.0 };
+        doc.new_page();
+        doc
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(String::new());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    /// Starts a new page when fewer than `height` points remain above the footer.
+    pub fn ensure_space(&mut self, height: f64) {
+        if self.y - height < MARGIN + 10.0 {
+            self.new_page();
+        }
+    }
+
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+
+    pub fn advance(&mut self, height: f64) {
+        self.y -= height;
+    }
+
+    pub fn text(&mut self, x: f64, y: f64, size: f64, bold: bool, text: &str) {
+        let font = if bold { "F2" } else { "F1" };
+        let page = self.pages.last_mut().expect("document always has a page");
+        page.push_str(
+            &format!("BT /{} {:.1} Tf {:.2} {:.2} Td ({}) Tj ET\n", font, size, x, y, escape(text))
+        );
+    }
+
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        let page = self.pages.last_mut().expect("document always has a page");
+        page.push_str(&format!("0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n", x1, y1, x2, y2));
+    }
+
+    /// Writes a paragraph at the cursor, wrapping on words to `max_width`.
+    pub fn paragraph(&mut self, x: f64, size: f64, max_width: f64, text: &str) {
+        for line in wrap(text, size, max_width) {
+            self.ensure_space(size + 3.0);
+            let y = self.y;
+            self.text(x, y, size, false, &line);
+            self.advance(size + 3.0);
+        }
+    }
+
+    pub fn finish(mut self, footer: &str) -> Vec<u8> {
+        let total = self.pages.len();
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            page.push_str(
+                &format!(
+                    "BT /F1 7.0 Tf {:.2} {:.2} Td ({}) Tj ET\n",
+                    MARGIN,
+                    FOOTER_Y,
+                    escape(&format!("{} - page {} of {}", footer, index + 1, total))
+                )
+            );
+        }
+
+        // Objects: 1 catalog, 2 pages, 3-4 fonts, then a (page, content) pair per page.
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        let page_ids: Vec<usize> = (0..total).map(|i| 5 + i * 2).collect();
+
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids
+                    .iter()
+                    .map(|id| format!("{} 0 R", id))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                total
+            ).into_bytes()
+        );
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec()
+        );
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec()
+        );
+
+        for (index, content) in self.pages.iter().enumerate() {
+            let content_id = page_ids[index] + 1;
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                    PAGE_WIDTH,
+                    PAGE_HEIGHT,
+                    content_id
+                ).into_bytes()
+            );
+
+            let stream = to_latin1(content);
+            let mut object = format!("<< /Length {} >>\nstream\n", stream.len()).into_bytes();
+            object.extend_from_slice(&stream);
+            object.extend_from_slice(b"\nendstream");
+            objects.push(object);
+        }
+
+        let mut out: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            out.extend_from_slice(object);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref_offset = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref_offset
+            ).as_bytes()
+        );
+
+        out
+    }
+}
+
+/// Clinician-facing food diary: profile summary, then one table of meals per
+/// logged day with daily totals against the user's targets.
+pub fn render_food_diary(user: &User, meals: &[MealLog], start: NaiveDate, end: NaiveDate, app_name: &str) -> Vec<u8> {
+    let mut pdf = PdfDocument::new();
+    let width = PAGE_WIDTH - MARGIN * 2.0;
+
+    let y = pdf.y();
+    pdf.text(MARGIN, y, 18.0, true, "Food Diary");
+    pdf.advance(22.0);
+
+    let y = pdf.y();
+    pdf.text(MARGIN, y, 10.0, false, &format!("Patient: {} ({})", user.name, user.gmail));
+    pdf.advance(13.0);
+    let y = pdf.y();
+    pdf.text(
+        MARGIN,
+        y,
+        10.0,
+        false,
+        &format!(
+            "Period: {} to {}   |   Generated: {}",
+            start.format("%d %b %Y"),
+            end.format("%d %b %Y"),
+            Utc::now().format("%d %b %Y %H:%M UTC")
+        )
+    );
+    pdf.advance(16.0);
+
+    if let Some(profile) = &user.health_profile {
+        profile_block(&mut pdf, profile);
+    }
+
+    let mut days: BTreeMap<NaiveDate, Vec<&MealLog>> = BTreeMap::new();
+    for meal in meals {
+        days.entry(meal.date.date_naive()).or_default().push(meal);
+    }
+
+    let y = pdf.y();
+    pdf.line(MARGIN, y, MARGIN + width, y);
+    pdf.advance(14.0);
+
+    if days.is_empty() {
+        let y = pdf.y();
+        pdf.text(MARGIN, y, 10.0, false, "No meals were logged in this period.");
+        pdf.advance(14.0);
+    }
+
+    let targets = user.health_profile.as_ref();
+
+    for (day, mut day_meals) in days {
+        day_meals.sort_by_key(|m| m.date);
+
+        pdf.ensure_space(60.0);
+        let y = pdf.y();
+        pdf.text(MARGIN, y, 11.0, true, &day.format("%A, %d %B %Y").to_string());
+        pdf.advance(14.0);
+        table_header(&mut pdf);
+
+        let (mut calories, mut protein, mut carbs, mut fat) = (0.0, 0.0, 0.0, 0.0);
+        for meal in &day_meals {
+            pdf.ensure_space(12.0);
+            if pdf.y() > PAGE_HEIGHT - MARGIN - 1.0 {
+                table_header(&mut pdf);
+            }
+
+            let serving = meal.serving_size
+                .clone()
+                .or_else(|| meal.portion_weight_g.map(|g| format!("{:.0} g", g)))
+                .unwrap_or_default();
+
+            table_row(
+                &mut pdf,
+                false,
+                [
+                    meal.date.format("%H:%M").to_string(),
+                    meal_type_label(&meal.meal_type).to_string(),
+                    truncate(&meal.food_name, 46),
+                    truncate(&serving, 18),
+                    format!("{:.0}", meal.calories),
+                    format!("{:.1}", meal.protein_g),
+                    format!("{:.1}", meal.carbs_g),
+                    format!("{:.1}", meal.fat_g),
+                ]
+            );
+
+            if let Some(notes) = meal.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+                pdf.paragraph(COLUMNS[2], 7.5, width - (COLUMNS[2] - MARGIN), &format!("Note: {}", notes));
+            }
+
+            calories += meal.calories;
+            protein += meal.protein_g;
+            carbs += meal.carbs_g;
+            fat += meal.fat_g;
+        }
+
+        pdf.ensure_space(24.0);
+        let y = pdf.y() + 8.0;
+        pdf.line(MARGIN, y, MARGIN + width, y);
+        table_row(
+            &mut pdf,
+            true,
+            [
+                String::new(),
+                String::new(),
+                "Daily total".to_string(),
+                String::new(),
+                format!("{:.0}", calories),
+                format!("{:.1}", protein),
+                format!("{:.1}", carbs),
+                format!("{:.1}", fat),
+            ]
+        );
+
+        if let Some(profile) = targets {
+            let (target_calories, target_protein, target_carbs, target_fat) = profile.targets_on(day);
+            table_row(
+                &mut pdf,
+                false,
+                [
+                    String::new(),
+                    String::new(),
+                    "Daily target".to_string(),
+                    String::new(),
+                    format!("{:.0}", target_calories),
+                    format!("{:.1}", target_protein),
+                    format!("{:.1}", target_carbs),
+                    format!("{:.1}", target_fat),
+                ]
+            );
+        }
+
+        pdf.advance(10.0);
+    }
+
+    pdf.ensure_space(30.0);
+    pdf.advance(6.0);
+    pdf.paragraph(
+        MARGIN,
+        7.5,
+        width,
+        "Times are in UTC. Nutrition values are self-reported or estimated (AI image/text analysis, FoodData Central) and have not been clinically verified."
+    );
+
+    pdf.finish(&format!("{} food diary", app_name))
+}
+
+/// One-page-ish appointment summary: profile, self-reported vitals, weight
+/// trend with the weigh-ins behind it, and average intake against targets.
+pub fn render_health_summary(summary: &HealthSummary, app_name: &str) -> Vec<u8> {
+    let mut pdf = PdfDocument::new();
+    let width = PAGE_WIDTH - MARGIN * 2.0;
+    let profile = &summary.profile;
+
+    let y = pdf.y();
+    pdf.text(MARGIN, y, 18.0, true, "Health Summary");
+    pdf.advance(22.0);
+
+    let y = pdf.y();
+    pdf.text(
+        MARGIN,
+        y,
+        10.0,
+        false,
+        &format!("Patient: {} ({})", summary.patient_name, summary.patient_email)
+    );
+    pdf.advance(13.0);
+    let y = pdf.y();
+    pdf.text(
+        MARGIN,
+        y,
+        10.0,
+        false,
+        &format!(
+            "Period: {} to {} ({} days)   |   Generated: {}",
+            format_day(&summary.period_start),
+            format_day(&summary.period_end),
+            summary.days,
+            Utc::now().format("%d %b %Y %H:%M UTC")
+        )
+    );
+    pdf.advance(16.0);
+
+    section_heading(&mut pdf, "Profile");
+    let mut lines = vec![
+        format!(
+            "Age {} | {:?} | Height {:.1} cm | Weight {:.1} kg | BMI {:.1} ({})",
+            profile.age,
+            profile.gender,
+            profile.height_cm,
+            profile.weight_kg,
+            profile.bmi,
+            profile.bmi_category
+        ),
+        format!("Activity level: {:?} | Goal: {:?}", profile.activity_level, profile.goal),
+        format!(
+            "Daily targets: {:.0} kcal, {:.0} g protein, {:.0} g carbs, {:.0} g fat",
+            profile.daily_calories,
+            profile.daily_protein_g,
+            profile.daily_carbs_g,
+            profile.daily_fat_g
+        ),
+    ];
+    if !profile.medical_conditions.is_empty() {
+        lines.push(format!("Medical conditions: {}", profile.medical_conditions.join(", ")));
+    }
+    if !profile.allergies.is_empty() {
+        lines.push(format!("Allergies: {}", profile.allergies.join(", ")));
+    }
+    if !profile.dietary_preferences.is_empty() {
+        let preferences: Vec<String> = profile.dietary_preferences
+            .iter()
+            .map(|p| format!("{:?}", p))
+            .collect();
+        lines.push(format!("Dietary preferences: {}", preferences.join(", ")));
+    }
+    if !profile.dietary_guidance.is_empty() {
+        lines.push(format!("Dietary guidance applied: {}", profile.dietary_guidance.join("; ")));
+    }
+    for line in lines {
+        pdf.paragraph(MARGIN, 9.0, width, &line);
+    }
+
+    section_heading(&mut pdf, "Vitals (self-reported)");
+    let blood_pressure = match &summary.vitals.blood_pressure {
+        Some(bp) =>
+            format!(
+                "Blood pressure: {}/{} mmHg (measured {})",
+                bp.systolic,
+                bp.diastolic,
+                format_day(&bp.measured_at)
+            ),
+        None => "Blood pressure: not recorded".to_string(),
+    };
+    let blood_sugar = match summary.vitals.fasting_blood_sugar {
+        Some(value) => format!("Fasting blood sugar: {:.0} mg/dL", value),
+        None => "Fasting blood sugar: not recorded".to_string(),
+    };
+    for line in [blood_pressure, blood_sugar] {
+        pdf.paragraph(MARGIN, 9.0, width, &line);
+    }
+
+    section_heading(&mut pdf, "Weight");
+    match &summary.weight_trend {
+        Some(trend) => {
+            let rate = trend.weekly_rate_kg
+                .map(|rate| format!("{:+.2} kg/week", rate))
+                .unwrap_or_else(|| "n/a".to_string());
+            pdf.paragraph(
+                MARGIN,
+                9.0,
+                width,
+                &format!(
+                    "{:.1} kg to {:.1} kg ({:+.1} kg) over {} weigh-ins | Trend: {} | 7-day average: {:.1} kg",
+                    trend.starting_weight,
+                    trend.ending_weight,
+                    trend.weight_change,
+                    trend.entries,
+                    rate,
+                    trend.moving_average_kg
+                )
+            );
+            pdf.advance(4.0);
+            for reading in &summary.weight_readings {
+                pdf.ensure_space(12.0);
+                let y = pdf.y();
+                pdf.text(MARGIN, y, 8.0, false, &format_day(&reading.date));
+                pdf.text(MARGIN + 90.0, y, 8.0, false, &format!("{:.1} kg", reading.weight_kg));
+                pdf.advance(11.0);
+            }
+        }
+        None => pdf.paragraph(MARGIN, 9.0, width, "No weigh-ins were logged in this period."),
+    }
+
+    section_heading(&mut pdf, "Average daily intake");
+    let intake = &summary.intake;
+    if intake.days_logged == 0 {
+        pdf.paragraph(MARGIN, 9.0, width, "No meals were logged in this period.");
+    } else {
+        pdf.paragraph(
+            MARGIN,
+            9.0,
+            width,
+            &format!(
+                "{} of {} days logged ({} meals). Averages are over logged days only.",
+                intake.days_logged,
+                summary.days,
+                intake.meals_logged
+            )
+        );
+        pdf.advance(4.0);
+        intake_row(&mut pdf, true, ["", "kcal", "Protein g", "Carbs g", "Fat g"].map(String::from));
+        intake_row(
+            &mut pdf,
+            false,
+            [
+                "Average".to_string(),
+                format!("{:.0}", intake.average_calories),
+                format!("{:.1}", intake.average_protein_g),
+                format!("{:.1}", intake.average_carbs_g),
+                format!("{:.1}", intake.average_fat_g),
+            ]
+        );
+        intake_row(
+            &mut pdf,
+            false,
+            [
+                "Target".to_string(),
+                format!("{:.0}", profile.daily_calories),
+                format!("{:.1}", profile.daily_protein_g),
+                format!("{:.1}", profile.daily_carbs_g),
+                format!("{:.1}", profile.daily_fat_g),
+            ]
+        );
+        if let Some(percent) = intake.calories_percent_of_target {
+            pdf.advance(2.0);
+            pdf.paragraph(MARGIN, 9.0, width, &format!("Average calories are {:.0}% of target.", percent));
+        }
+    }
+
+    pdf.ensure_space(30.0);
+    pdf.advance(10.0);
+    pdf.paragraph(
+        MARGIN,
+        7.5,
+        width,
+        &format!(
+            "All values are self-reported by the patient in the {} app and have not been clinically verified. Nutrition values may be estimated (AI image/text analysis, FoodData Central).",
+            app_name
+        )
+    );
+
+    pdf.finish(&format!("{} health summary", app_name))
+}
+
+fn section_heading(pdf: &mut PdfDocument, title: &str) {
+    pdf.ensure_space(40.0);
+    pdf.advance(6.0);
+    let y = pdf.y();
+    pdf.text(MARGIN, y, 11.0, true, title);
+    pdf.advance(4.0);
+    let y = pdf.y();
+    pdf.line(MARGIN, y, PAGE_WIDTH - MARGIN, y);
+    pdf.advance(12.0);
+}
+
+/// Left edge of each intake column: label, kcal, protein, carbs, fat.
+const INTAKE_COLUMNS: [f64; 5] = [MARGIN, 120.0, 190.0, 260.0, 330.0];
+
+fn intake_row(pdf: &mut PdfDocument, bold: bool, cells: [String; 5]) {
+    let y = pdf.y();
+    for (x, cell) in INTAKE_COLUMNS.iter().zip(cells.iter()) {
+        pdf.text(*x, y, 8.0, bold, cell);
+    }
+    pdf.advance(11.0);
+}
+
+/// `YYYY-MM-DD` or RFC 3339 as `15 Jan 2025`; anything else is shown as-is.
+fn format_day(value: &str) -> String {
+    NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d")
+        .map(|day| day.format("%d %b %Y").to_string())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+/// Left edge of each table column: time, meal, food, serving, kcal, protein, carbs, fat.
+const COLUMNS: [f64; 8] = [MARGIN, 72.0, 120.0, 330.0, 410.0, 450.0, 495.0, 540.0];
+
+fn table_header(pdf: &mut PdfDocument) {
+    table_row(
+        pdf,
+        true,
+        [
+            "Time".to_string(),
+            "Meal".to_string(),
+            "Food".to_string(),
+            "Serving".to_string(),
+            "kcal".to_string(),
+            "Protein g".to_string(),
+            "Carbs g".to_string(),
+            "Fat g".to_string(),
+        ]
+    );
+}
+
+fn table_row(pdf: &mut PdfDocument, bold: bool, cells: [String; 8]) {
+    let y = pdf.y();
+    for (x, cell) in COLUMNS.iter().zip(cells.iter()) {
+        if !cell.is_empty() {
+            pdf.text(*x, y, 8.0, bold, cell);
+        }
+    }
+    pdf.advance(11.0);
+}
+
+fn profile_block(pdf: &mut PdfDocument, profile: &HealthProfile) {
+    let lines = [
+        format!(
+            "Age {} | {:?} | Height {:.1} cm | Weight {:.1} kg (as of {}) | BMI {:.1} ({})",
+            profile.age,
+            profile.gender,
+            profile.height_cm,
+            profile.weight_kg,
+            profile.updated_at.format("%d %b %Y"),
+            profile.bmi,
+            profile.bmi_category
+        ),
+        format!(
+            "Goal: {:?} | Daily targets: {:.0} kcal, {:.0} g protein, {:.0} g carbs, {:.0} g fat",
+            profile.goal,
+            profile.daily_calories,
+            profile.daily_protein_g,
+            profile.daily_carbs_g,
+            profile.daily_fat_g
+        ),
+    ];
+
+    for line in lines {
+        let y = pdf.y();
+        pdf.text(MARGIN, y, 9.0, false, &line);
+        pdf.advance(12.0);
+    }
+
+    let mut extra = Vec::new();
+    if let Some(conditions) = profile.medical_conditions.as_ref().filter(|c| !c.is_empty()) {
+        extra.push(format!("Medical conditions: {}", conditions.join(", ")));
+    }
+    if let Some(allergies) = profile.allergies.as_ref().filter(|a| !a.is_empty()) {
+        extra.push(format!("Allergies: {}", allergies.join(", ")));
+    }
+    for line in extra {
+        pdf.paragraph(MARGIN, 9.0, PAGE_WIDTH - MARGIN * 2.0, &line);
+    }
+
+    pdf.advance(4.0);
+}
+
+fn meal_type_label(meal_type: &MealType) -> &'static str {
+    match meal_type {
+        MealType::Breakfast => "Breakfast",
+        MealType::Lunch => "Lunch",
+        MealType::Dinner => "Dinner",
+        MealType::Snack => "Snack",
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    out.push_str("...");
+    out
+}
+
+/// Word wrap using an average Helvetica glyph width of half the font size.
+fn wrap(text: &str, size: f64, max_width: f64) -> Vec<String> {
+    let max_chars = ((max_width / (size * 0.5)) as usize).max(10);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > max_chars {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)").replace(['\r', '\n'], " ")
+}
+
+/// WinAnsi covers Latin-1; anything outside it (emoji, CJK) is replaced.
+fn to_latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+        .collect()
+}
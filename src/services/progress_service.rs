@@ -0,0 +1,317 @@
+use chrono::{ FixedOffset, NaiveDate, TimeZone, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{ doc, oid::ObjectId, Bson, Document },
+    options::{ FindOneAndUpdateOptions, FindOneOptions, ReturnDocument },
+};
+
+use crate::{
+    db::{ self, AppState },
+    error::{ AppError, Result },
+    models::{ DailyProgress, MealLog },
+    services::{ monthly_stats_service, response_cache_service::{ self, CachedRoute }, timezone },
+};
+
+/// `DailyProgress.date` of the document holding a day's running totals: the
+/// user's local date, stored as midnight UTC of that date.
+pub fn day_key(day: NaiveDate) -> mongodb::bson::DateTime {
+    mongodb::bson::DateTime::from_chrono(Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap()))
+}
+
+/// The user's UTC offset from their notification preferences; UTC without
+/// them. Days in `daily_progress` run midnight to midnight at this offset.
+pub async fn user_offset(state: &AppState, user_id: ObjectId) -> Result<FixedOffset> {
+    let user = state.db
+        .collection::<Document>("users")
+        .find_one(
+            doc! { "_id": user_id },
+            FindOneOptions::builder().projection(doc! { "notification_preferences.utc_offset_minutes": 1 }).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let minutes = user
+        .as_ref()
+        .and_then(|user| user.get_document("notification_preferences").ok())
+        .and_then(|preferences| preferences.get("utc_offset_minutes"))
+        .and_then(|minutes| minutes.as_i32().or_else(|| minutes.as_i64().map(|m| m as i32)));
+    Ok(minutes.and_then(timezone::from_minutes).unwrap_or_else(timezone::utc))
+}
+
+/// The owner's local date the meal was eaten on, which keys its totals.
+pub async fn meal_day(state: &AppState, meal: &MealLog) -> Result<NaiveDate> {
+    let offset = user_offset(state, meal.user_id).await?;
+    Ok(meal.date.with_timezone(&offset).date_naive())
+}
+
+/// Stages setting `local_date` on each meal: its owner's local date, as
+/// midnight UTC like [`day_key`]. The offset is looked up like [`user_offset`].
+fn local_date_stages() -> Vec<Document> {
+    vec![
+        doc! {
+            "$lookup": {
+                "from": "users",
+                "let": { "user_id": "$user_id" },
+                "pipeline": [
+                    doc! { "$match": { "$expr": { "$eq": ["$_id", "$$user_id"] } } },
+                    doc! { "$project": { "_id": 0, "offset": "$notification_preferences.utc_offset_minutes" } },
+                ],
+                "as": "owner",
+            }
+        },
+        doc! {
+            "$set": {
+                "local_date": {
+                    "$dateFromString": {
+                        "dateString": {
+                            "$dateToString": {
+                                "format": "%Y-%m-%d",
+                                "date": {
+                                    "$add": [
+                                        "$date",
+                                        {
+                                            "$multiply": [
+                                                { "$ifNull": [{ "$arrayElemAt": ["$owner.offset", 0] }, 0] },
+                                                60_000,
+                                            ],
+                                        },
+                                    ],
+                                },
+                            },
+                        },
+                    },
+                },
+            }
+        }
+    ]
+}
+
+/// Aggregation over `meal_logs` that recomputes the meal totals of every
+/// (user, local day) with meals matching `filter` and `$merge`s them into
+/// `daily_progress`, leaving water, weight and notes alone. Setting rather
+/// than adding makes it safe to run again. Needs the unique (user_id, date)
+/// index.
+pub fn rebuild_totals_pipeline(filter: Document, now: &str) -> Vec<Document> {
+    let mut pipeline = vec![doc! { "$match": filter }];
+    pipeline.extend(local_date_stages());
+    pipeline.extend([
+        doc! {
+            "$group": {
+                "_id": {
+                    "user_id": "$user_id",
+                    "date": "$local_date",
+                },
+                "total_calories": { "$sum": "$calories" },
+                "total_protein_g": { "$sum": "$protein_g" },
+                "total_carbs_g": { "$sum": "$carbs_g" },
+                "total_fat_g": { "$sum": "$fat_g" },
+                "meal_count": { "$sum": 1_i64 },
+            }
+        },
+        doc! {
+            "$project": {
+                "_id": 0,
+                "user_id": "$_id.user_id",
+                "date": "$_id.date",
+                "total_calories": 1,
+                "total_protein_g": 1,
+                "total_carbs_g": 1,
+                "total_fat_g": 1,
+                "meal_count": 1,
+                "created_at": now,
+                "updated_at": now,
+            }
+        },
+        doc! {
+            "$merge": {
+                "into": "daily_progress",
+                "on": ["user_id", "date"],
+                "whenMatched": [
+                    doc! {
+                        "$set": {
+                            "total_calories": "$$new.total_calories",
+                            "total_protein_g": "$$new.total_protein_g",
+                            "total_carbs_g": "$$new.total_carbs_g",
+                            "total_fat_g": "$$new.total_fat_g",
+                            "meal_count": "$$new.meal_count",
+                            "updated_at": "$$new.updated_at",
+                        }
+                    },
+                ],
+                "whenNotMatched": "insert",
+            }
+        }
+    ]);
+    pipeline
+}
+
+/// Change to a day's meal totals.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MealDelta {
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub meal_count: i64,
+}
+
+impl MealDelta {
+    pub fn added(meal: &MealLog) -> Self {
+        Self {
+            calories: meal.calories,
+            protein_g: meal.protein_g,
+            carbs_g: meal.carbs_g,
+            fat_g: meal.fat_g,
+            meal_count: 1,
+        }
+    }
+
+    pub fn removed(meal: &MealLog) -> Self {
+        let added = Self::added(meal);
+        Self {
+            calories: -added.calories,
+            protein_g: -added.protein_g,
+            carbs_g: -added.carbs_g,
+            fat_g: -added.fat_g,
+            meal_count: -1,
+        }
+    }
+}
+
+/// Applies `delta` to the day's document with a single `$inc` upsert and
+/// returns the document as written, so concurrent logs never lose an update.
+pub async fn apply_meal_delta(
+    state: &AppState,
+    user_id: ObjectId,
+    day: NaiveDate,
+    delta: MealDelta
+) -> Result<DailyProgress> {
+    let now = Utc::now().to_rfc3339();
+    let filter = doc! { "user_id": user_id, "date": day_key(day) };
+    let update =
+        doc! {
+        "$inc": {
+            "total_calories": delta.calories,
+            "total_protein_g": delta.protein_g,
+            "total_carbs_g": delta.carbs_g,
+            "total_fat_g": delta.fat_g,
+            "meal_count": delta.meal_count,
+        },
+        "$set": { "updated_at": &now },
+        "$setOnInsert": { "created_at": &now },
+    };
+
+    let progress = upsert(state, filter.clone(), update).await?;
+    monthly_stats_service::invalidate(state, user_id, day).await;
+
+    // Repeated float adds and subtracts leave residue; a day with no meals is exactly zero.
+    if progress.meal_count == 0 && delta.meal_count < 0 {
+        let cleared = state.db
+            .collection::<DailyProgress>("daily_progress")
+            .find_one_and_update(
+                doc! { "user_id": user_id, "date": day_key(day), "meal_count": 0 },
+                doc! {
+                    "$set": {
+                        "total_calories": 0.0,
+                        "total_protein_g": 0.0,
+                        "total_carbs_g": 0.0,
+                        "total_fat_g": 0.0,
+                    }
+                },
+                FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build()
+            ).await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+        return Ok(cleared.unwrap_or(progress));
+    }
+
+    Ok(progress)
+}
+
+pub async fn record_meal_added(state: &AppState, meal: &MealLog) -> Result<DailyProgress> {
+    let day = meal_day(state, meal).await?;
+    apply_meal_delta(state, meal.user_id, day, MealDelta::added(meal)).await
+}
+
+pub async fn record_meal_removed(state: &AppState, meal: &MealLog) -> Result<DailyProgress> {
+    let day = meal_day(state, meal).await?;
+    apply_meal_delta(state, meal.user_id, day, MealDelta::removed(meal)).await
+}
+
+/// Resets the user's meal totals in `daily_progress` from `meal_logs`,
+/// zeroing days whose meals are all gone, e.g. after their UTC offset
+/// changed and meals moved to other local days. Returns the days rewritten.
+pub async fn rebuild_user_totals(state: &AppState, user_id: ObjectId) -> Result<u64> {
+    let now = Utc::now().to_rfc3339();
+    let meal_logs = state.db.collection::<Document>("meal_logs");
+
+    let mut days_pipeline = vec![doc! { "$match": { "user_id": user_id } }];
+    days_pipeline.extend(local_date_stages());
+    days_pipeline.push(doc! { "$group": { "_id": "$local_date" } });
+    let days: Vec<Bson> = meal_logs
+        .aggregate(days_pipeline, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect::<Vec<Document>>().await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .into_iter()
+        .filter_map(|day| day.get("_id").cloned())
+        .collect();
+
+    meal_logs
+        .aggregate(rebuild_totals_pipeline(doc! { "user_id": user_id }, &now), None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let zeroed = state.db
+        .collection::<Document>("daily_progress")
+        .update_many(
+            doc! { "user_id": user_id, "date": { "$nin": &days }, "meal_count": { "$ne": 0_i64 } },
+            doc! {
+                "$set": {
+                    "total_calories": 0.0,
+                    "total_protein_g": 0.0,
+                    "total_carbs_g": 0.0,
+                    "total_fat_g": 0.0,
+                    "meal_count": 0_i64,
+                    "updated_at": &now,
+                },
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?.modified_count;
+
+    // `$merge` bypasses `upsert`, which would have done this per day.
+    response_cache_service::invalidate(state, user_id, CachedRoute::DailyMeals).await;
+    monthly_stats_service::invalidate_all(state, user_id).await;
+
+    Ok((days.len() as u64) + zeroed)
+}
+
+pub async fn progress_for_day(state: &AppState, user_id: ObjectId, day: NaiveDate) -> Result<Option<DailyProgress>> {
+    state.db
+        .collection::<DailyProgress>("daily_progress")
+        .find_one(doc! { "user_id": user_id, "date": day_key(day) }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+/// `find_one_and_update` with upsert on the unique (user_id, date) index. Two
+/// concurrent upserts can both try the insert; the loser retries as an update.
+pub async fn upsert(state: &AppState, filter: Document, update: Document) -> Result<DailyProgress> {
+    let collection = state.db.collection::<DailyProgress>("daily_progress");
+    let options = FindOneAndUpdateOptions::builder()
+        .upsert(true)
+        .return_document(ReturnDocument::After)
+        .build();
+
+    let result = match collection.find_one_and_update(filter.clone(), update.clone(), options.clone()).await {
+        Err(e) if db::is_duplicate_key(&e) => collection.find_one_and_update(filter, update, options).await,
+        other => other,
+    };
+
+    let progress = result
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::InternalError(anyhow::anyhow!("Upsert returned no daily progress document")))?;
+
+    // Every meal and water change goes through here.
+    response_cache_service::invalidate(state, progress.user_id, CachedRoute::DailyMeals).await;
+    state.live.publish(progress.user_id, progress.date.date_naive());
+
+    Ok(progress)
+}
@@ -0,0 +1,140 @@
+use anyhow::Result;
+use std::{ collections::HashMap, path::PathBuf, sync::RwLock };
+
+/// Templates compiled into the binary so deployments that only ship the
+/// executable still work. Files in the prompts directory override these; when
+/// adding a new version, add the file and bump the entry here.
+const BUILTIN_TEMPLATES: &[(&str, u32, &str)] = &[
+    ("chat_system", 14, include_str!("../../prompts/chat_system.v14.txt")),
+    ("chat_title", 1, include_str!("../../prompts/chat_title.v1.txt")),
+    ("food_image_analysis", 4, include_str!("../../prompts/food_image_analysis.v4.txt")),
+    ("food_quick_check", 1, include_str!("../../prompts/food_quick_check.v1.txt")),
+    ("food_text_analysis", 1, include_str!("../../prompts/food_text_analysis.v1.txt")),
+    ("health_recommendations", 2, include_str!("../../prompts/health_recommendations.v2.txt")),
+    ("meal_plan", 2, include_str!("../../prompts/meal_plan.v2.txt")),
+    ("report_coach_commentary", 1, include_str!("../../prompts/report_coach_commentary.v1.txt")),
+];
+
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    pub version: u32,
+    pub body: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedPrompt {
+    pub text: String,
+    /// `name@vN`, stored alongside generated content.
+    pub version: String,
+}
+
+pub struct PromptRegistry {
+    dir: PathBuf,
+    hot_reload: bool,
+    templates: RwLock<HashMap<String, PromptTemplate>>,
+}
+
+impl PromptRegistry {
+    pub fn load(dir: impl Into<PathBuf>, hot_reload: bool) -> Self {
+        let registry = Self {
+            dir: dir.into(),
+            hot_reload,
+            templates: RwLock::new(HashMap::new()),
+        };
+        registry.reload();
+        registry
+    }
+
+    /// Rebuilds the template set from the built-ins plus the prompts directory,
+    /// keeping the highest `name.vN.txt` version of each template.
+    pub fn reload(&self) {
+        let mut templates: HashMap<String, PromptTemplate> = BUILTIN_TEMPLATES.iter()
+            .map(|(name, version, body)| {
+                (
+                    name.to_string(),
+                    PromptTemplate {
+                        version: *version,
+                        body: body.to_string(),
+                    },
+                )
+            })
+            .collect();
+
+        match std::fs::read_dir(&self.dir) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let Some((name, version)) = parse_file_name(&path) else {
+                        continue;
+                    };
+
+                    let body = match std::fs::read_to_string(&path) {
+                        Ok(body) => body,
+                        Err(e) => {
+                            tracing::warn!("Failed to read prompt template {:?}: {}", path, e);
+                            continue;
+                        }
+                    };
+
+                    let newer = templates.get(&name).is_none_or(|t| version >= t.version);
+                    if newer {
+                        templates.insert(name, PromptTemplate { version, body });
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::debug!("Prompt directory {:?} not readable, using built-in templates: {}", self.dir, e);
+            }
+        }
+
+        if let Ok(mut guard) = self.templates.write() {
+            *guard = templates;
+        }
+    }
+
+    /// Currently active `name@vN` for every template, sorted by name.
+    pub fn versions(&self) -> Vec<String> {
+        let Ok(templates) = self.templates.read() else {
+            return Vec::new();
+        };
+        let mut versions: Vec<String> = templates
+            .iter()
+            .map(|(name, t)| format!("{}@v{}", name, t.version))
+            .collect();
+        versions.sort();
+        versions
+    }
+
+    pub fn render(&self, name: &str, vars: &[(&str, String)]) -> Result<RenderedPrompt> {
+        if self.hot_reload {
+            self.reload();
+        }
+
+        let template = self.templates
+            .read()
+            .map_err(|_| anyhow::anyhow!("Prompt registry lock poisoned"))?
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown prompt template: {}", name))?;
+
+        let mut text = template.body;
+        for (key, value) in vars {
+            text = text.replace(&format!("{{{{{}}}}}", key), value);
+        }
+
+        if text.contains("{{") {
+            tracing::warn!("Prompt {} rendered with unresolved placeholders", name);
+        }
+
+        Ok(RenderedPrompt {
+            text,
+            version: format!("{}@v{}", name, template.version),
+        })
+    }
+}
+
+fn parse_file_name(path: &std::path::Path) -> Option<(String, u32)> {
+    let file_name = path.file_name()?.to_str()?.strip_suffix(".txt")?;
+    let (name, version) = file_name.rsplit_once(".v")?;
+    Some((name.to_string(), version.parse().ok()?))
+}
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use serde::Serialize;
+use std::{ collections::HashMap, sync::OnceLock, time::Instant };
+
+use crate::services::latency_service;
+
+/// Minute buckets outlive the reporting window a little so a slow read
+/// doesn't race their expiry.
+const BUCKET_TTL_SECONDS: i64 = 2 * 60 * 60;
+/// A provider answering less often than this is degraded.
+const DEGRADED_SUCCESS_RATE: f64 = 0.95;
+/// Below this it is down.
+const DOWN_SUCCESS_RATE: f64 = 0.5;
+
+/// Set once at startup. Providers are shared by every tenant, and so are
+/// their metrics.
+static REDIS: OnceLock<ConnectionManager> = OnceLock::new();
+
+/// External dependencies whose calls are measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    Gemini,
+    Fdc,
+    Ninja,
+    MealDb,
+    Smtp,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 5] = [Provider::Gemini, Provider::Fdc, Provider::Ninja, Provider::MealDb, Provider::Smtp];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Gemini => "gemini",
+            Provider::Fdc => "fdc",
+            Provider::Ninja => "ninja",
+            Provider::MealDb => "meal_db",
+            Provider::Smtp => "smtp",
+        }
+    }
+
+    /// p95 above which the provider is degraded even if calls succeed. Gemini
+    /// analyses take seconds when all is well.
+    fn slow_p95_ms(&self) -> u64 {
+        match self {
+            Provider::Gemini => 30000,
+            Provider::Smtp => 10000,
+            Provider::Fdc | Provider::Ninja | Provider::MealDb => 5000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderState {
+    Operational,
+    Degraded,
+    Down,
+    /// No calls in the window to judge by.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+    pub provider: Provider,
+    pub status: ProviderState,
+    pub calls: u64,
+    /// Share of calls the provider answered, from 0 to 1.
+    pub success_rate: Option<f64>,
+    pub avg_ms: Option<u64>,
+    /// Upper edge of the latency bucket holding the 95th percentile.
+    pub p95_ms: Option<u64>,
+}
+
+pub fn init(redis: ConnectionManager) {
+    if REDIS.set(redis).is_err() {
+        tracing::warn!("Provider metrics are already initialized");
+    }
+}
+
+/// Records one call to `provider` that took since `started`. `answered` is
+/// whether the provider handled it, even with an error the request caused;
+/// timeouts, connection errors, 429s and 5xx are failures. Written off the
+/// caller's path and dropped if Redis is unavailable.
+pub fn record(provider: Provider, answered: bool, started: Instant) {
+    let Some(redis) = REDIS.get() else {
+        return;
+    };
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    let mut conn = redis.clone();
+
+    tokio::spawn(async move {
+        let key = bucket_key(Utc::now().timestamp() / 60, provider);
+        let result = redis
+            ::pipe()
+            .hincr(&key, latency_service::bucket_for(elapsed_ms).to_string(), 1)
+            .ignore()
+            .hincr(&key, "count", 1)
+            .ignore()
+            .hincr(&key, "failures", u64::from(!answered))
+            .ignore()
+            .hincr(&key, "sum_ms", elapsed_ms)
+            .ignore()
+            .expire(&key, BUCKET_TTL_SECONDS)
+            .ignore()
+            .query_async::<_, ()>(&mut conn).await;
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to record a {} call: {}", provider.as_str(), e);
+        }
+    });
+}
+
+/// [`record`] for an HTTP call.
+pub fn record_response(provider: Provider, started: Instant, response: &reqwest::Result<reqwest::Response>) {
+    let answered = match response {
+        Ok(response) => {
+            let status = response.status();
+            !status.is_server_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        }
+        Err(_) => false,
+    };
+    record(provider, answered, started);
+}
+
+/// Every provider's calls over the last `window_minutes`, in [`Provider::ALL`] order.
+pub async fn provider_stats(redis: &ConnectionManager, window_minutes: i64) -> Result<Vec<ProviderStatus>> {
+    let mut conn = redis.clone();
+    let current_minute = Utc::now().timestamp() / 60;
+
+    let mut stats = Vec::new();
+    for provider in Provider::ALL {
+        let mut pipe = redis::pipe();
+        for minute in current_minute - window_minutes + 1..=current_minute {
+            pipe.hgetall(bucket_key(minute, provider));
+        }
+        let minutes: Vec<HashMap<String, u64>> = pipe.query_async(&mut conn).await?;
+
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for minute in minutes {
+            for (field, value) in minute {
+                *counts.entry(field).or_default() += value;
+            }
+        }
+
+        stats.push(summarize(provider, &counts));
+    }
+
+    Ok(stats)
+}
+
+fn summarize(provider: Provider, counts: &HashMap<String, u64>) -> ProviderStatus {
+    let calls = counts.get("count").copied().unwrap_or(0);
+    if calls == 0 {
+        return ProviderStatus {
+            provider,
+            status: ProviderState::Unknown,
+            calls,
+            success_rate: None,
+            avg_ms: None,
+            p95_ms: None,
+        };
+    }
+
+    let failures = counts.get("failures").copied().unwrap_or(0).min(calls);
+    let success_rate = ((calls - failures) as f64) / (calls as f64);
+    let p95_ms = latency_service::percentile(counts, calls, 0.95);
+
+    let status = if success_rate < DOWN_SUCCESS_RATE {
+        ProviderState::Down
+    } else if success_rate < DEGRADED_SUCCESS_RATE || p95_ms > provider.slow_p95_ms() {
+        ProviderState::Degraded
+    } else {
+        ProviderState::Operational
+    };
+
+    ProviderStatus {
+        provider,
+        status,
+        calls,
+        success_rate: Some((success_rate * 1000.0).round() / 1000.0),
+        avg_ms: Some(counts.get("sum_ms").copied().unwrap_or(0) / calls),
+        p95_ms: Some(p95_ms),
+    }
+}
+
+fn bucket_key(minute: i64, provider: Provider) -> String {
+    format!("provider_calls:{}:{}", minute, provider.as_str())
+}
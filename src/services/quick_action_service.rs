@@ -0,0 +1,130 @@
+use chrono::{ Datelike, Duration, FixedOffset, Timelike, Utc, Weekday };
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::Serialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ MealType, User },
+    services::{ report_service, timezone, water_service },
+};
+
+/// Chips beyond this crowd the input on small screens.
+const MAX_QUICK_ACTIONS: usize = 4;
+/// Below this share of the water target by mid-afternoon, water is suggested.
+const WATER_BEHIND_SHARE: f64 = 0.5;
+
+/// A quick-reply chip: `label` is shown, `message` is sent to the assistant
+/// as if the user typed it.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickAction {
+    /// Stable, for analytics and icons.
+    pub id: &'static str,
+    pub label: String,
+    pub message: String,
+}
+
+impl QuickAction {
+    fn new(id: &'static str, label: &str, message: &str) -> Self {
+        Self { id, label: label.to_string(), message: message.to_string() }
+    }
+}
+
+/// Quick actions for the user right now, most relevant first: the meal due
+/// at this time of day if it isn't logged yet, progress once something is,
+/// water when behind, and the weekly report at the start of the week.
+/// `offset` is the client's time zone; without one, the offset saved with
+/// the notification preferences, else UTC.
+pub async fn quick_actions(
+    state: &AppState,
+    user_id: ObjectId,
+    offset: Option<FixedOffset>
+) -> Result<Vec<QuickAction>> {
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let offset = offset
+        .or_else(|| {
+            user.notification_preferences
+                .as_ref()
+                .and_then(|preferences| timezone::from_minutes(preferences.utc_offset_minutes))
+        })
+        .unwrap_or_else(timezone::utc);
+    let now = Utc::now().with_timezone(&offset);
+    let today = now.date_naive();
+    let hour = now.hour();
+
+    let meals = report_service::fetch_meals_between(
+        state,
+        user_id,
+        timezone::start_of_day(today, offset),
+        timezone::start_of_day(today + Duration::days(1), offset) - Duration::seconds(1)
+    ).await?;
+    let logged = |meal_type: &MealType| meals.iter().any(|meal| &meal.meal_type == meal_type);
+
+    let mut actions = Vec::new();
+
+    if user.health_profile.is_none() {
+        actions.push(
+            QuickAction::new("set_up_profile", "Set up my health profile", "Help me set up my health profile")
+        );
+    }
+
+    if let Some(meal_type) = meal_due(hour).filter(|meal_type| !logged(meal_type)) {
+        let name = meal_type.as_str();
+        actions.push(
+            QuickAction {
+                id: "log_meal",
+                label: format!("Log my {}", name),
+                message: format!("I want to log my {}", name),
+            }
+        );
+    }
+
+    if meals.is_empty() {
+        if (5..11).contains(&hour) {
+            actions.push(
+                QuickAction::new("suggest_breakfast", "Breakfast ideas", "What should I have for breakfast?")
+            );
+        }
+    } else {
+        actions.push(QuickAction::new("daily_progress", "How am I doing today?", "How am I doing today?"));
+        if hour >= 15 {
+            actions.push(
+                QuickAction::new("dinner_idea", "What can I still eat?", "Recommend a recipe that fits what I have left today")
+            );
+        }
+    }
+
+    if hour >= 14 {
+        let water_ml = water_service::water_for_day(state, user_id, today).await?;
+        if water_ml < water_service::water_target_ml(&user) * WATER_BEHIND_SHARE {
+            actions.push(QuickAction::new("log_water", "Log a glass of water", "Log a glass of water (250 ml)"));
+        }
+    }
+
+    if matches!(today.weekday(), Weekday::Sun | Weekday::Mon) {
+        actions.push(
+            QuickAction::new("weekly_report", "Generate weekly report", "Generate my weekly report")
+        );
+    }
+
+    actions.push(QuickAction::new("suggest_snack", "Suggest a snack", "Suggest a healthy snack for me"));
+    actions.push(QuickAction::new("compliance_score", "My compliance score", "What's my compliance score?"));
+
+    actions.truncate(MAX_QUICK_ACTIONS);
+    Ok(actions)
+}
+
+/// The meal usually eaten around `hour`, local time.
+pub fn meal_due(hour: u32) -> Option<MealType> {
+    match hour {
+        5..=10 => Some(MealType::Breakfast),
+        11..=14 => Some(MealType::Lunch),
+        17..=21 => Some(MealType::Dinner),
+        _ => None,
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::{ DateTime, Utc };
+use mongodb::bson::oid::ObjectId;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ DailyProgress, MealLog, MealSource, MealType },
+    services::{ cache_service, food_table, mealdb_service::Meal, progress_service, report_service },
+};
+
+/// Largest share of a recipe one meal can log; more than twice the recipe is
+/// almost certainly a typo.
+pub const MAX_SERVING_FRACTION: f64 = 2.0;
+
+/// Estimated nutrition for a whole recipe, summed over its ingredients.
+#[derive(Debug, Clone)]
+pub struct RecipeNutrition {
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    /// Ingredients matched in the built-in food table.
+    pub table_matches: usize,
+    /// Ingredients sent to API Ninjas because the table had no exact match.
+    pub ninja_ingredients: usize,
+}
+
+/// Resolves each ingredient the same way free-text meal descriptions are:
+/// the built-in food table first, then API Ninjas for the rest in one query.
+pub async fn estimate(state: &AppState, meal: &Meal) -> Result<RecipeNutrition> {
+    let mut nutrition = RecipeNutrition {
+        calories: 0.0,
+        protein_g: 0.0,
+        carbs_g: 0.0,
+        fat_g: 0.0,
+        table_matches: 0,
+        ninja_ingredients: 0,
+    };
+    let mut unresolved = Vec::new();
+
+    for (ingredient, measure) in meal.get_ingredients() {
+        let description = format!("{} {}", measure.trim(), ingredient.trim());
+        match food_table::lookup(&description) {
+            Some(food) => {
+                nutrition.calories += food.calories;
+                nutrition.protein_g += food.protein_g;
+                nutrition.carbs_g += food.carbs_g;
+                nutrition.fat_g += food.fat_g;
+                nutrition.table_matches += 1;
+            }
+            None => unresolved.push(description),
+        }
+    }
+
+    if !unresolved.is_empty() {
+        let query = unresolved.join(", ");
+        let items = state.cache
+            .get_or_fetch(cache_service::NINJA_NUTRITION, &query, false, || {
+                state.ninja_service.get_nutrition(&query)
+            }).await
+            .map_err(|e| AppError::ExternalApiError(format!("Nutrition lookup failed: {}", e)))?;
+
+        for item in items {
+            nutrition.protein_g += item.protein_g;
+            nutrition.carbs_g += item.carbohydrates_total_g;
+            nutrition.fat_g += item.fat_total_g;
+            // Calories are a premium field on the free plan and come back as 0.
+            nutrition.calories += if item.calories > 0.0 {
+                item.calories
+            } else {
+                item.protein_g * 4.0 + item.carbohydrates_total_g * 4.0 + item.fat_total_g * 9.0
+            };
+        }
+        nutrition.ninja_ingredients = unresolved.len();
+    }
+
+    if nutrition.calories <= 0.0 {
+        return Err(
+            AppError::ValidationError("Could not estimate nutrition for this recipe's ingredients".to_string())
+        );
+    }
+
+    Ok(nutrition)
+}
+
+/// Logs `serving_fraction` of the recipe (0.25 = a quarter of it) as a meal.
+/// Returns the meal and the day's updated progress.
+pub async fn log_recipe(
+    state: &AppState,
+    user_id: ObjectId,
+    meal: &Meal,
+    meal_type: MealType,
+    date: DateTime<Utc>,
+    serving_fraction: f64
+) -> Result<(MealLog, DailyProgress)> {
+    if !serving_fraction.is_finite() || serving_fraction <= 0.0 || serving_fraction > MAX_SERVING_FRACTION {
+        return Err(
+            AppError::BadRequest(format!("serving_fraction must be between 0 and {}", MAX_SERVING_FRACTION))
+        );
+    }
+
+    let nutrition = estimate(state, meal).await?;
+    tracing::debug!(
+        "Estimated recipe {}: {} ingredients from the food table, {} from API Ninjas",
+        meal.id_meal,
+        nutrition.table_matches,
+        nutrition.ninja_ingredients
+    );
+
+    let serving_size = if serving_fraction == 1.0 {
+        "Whole recipe".to_string()
+    } else {
+        format!("{} of recipe", round2(serving_fraction))
+    };
+
+    let mut meal_log = MealLog {
+        id: None,
+        user_id,
+        date,
+        meal_type,
+        food_name: meal.str_meal.clone(),
+        calories: (nutrition.calories * serving_fraction).round(),
+        protein_g: round1(nutrition.protein_g * serving_fraction),
+        carbs_g: round1(nutrition.carbs_g * serving_fraction),
+        fat_g: round1(nutrition.fat_g * serving_fraction),
+        fiber_g: None,
+        sugar_g: None,
+        sodium_mg: None,
+        items: Vec::new(),
+        serving_size: Some(serving_size),
+        notes: Some(format!("TheMealDB recipe {}", meal.id_meal)),
+        source: MealSource::Recipe,
+        verified: false,
+        nutrition_snapshot: None,
+        portion_weight_g: None,
+        auto_logged: false,
+        recurring_meal_id: None,
+        hunger_level: None,
+        mood: None,
+        eating_context: None,
+        version: 0,
+        created_at: Utc::now(),
+    };
+
+    let result = state.db
+        .collection::<MealLog>("meal_logs")
+        .insert_one(&meal_log, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    meal_log.id = result.inserted_id.as_object_id();
+
+    report_service::invalidate_compliance_score(state, user_id).await;
+    let progress = progress_service::record_meal_added(state, &meal_log).await?;
+
+    Ok((meal_log, progress))
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
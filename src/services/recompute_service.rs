@@ -0,0 +1,196 @@
+use anyhow::Result;
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId, Bson, Document }, options::FindOptions };
+use std::collections::BTreeMap;
+
+use crate::{
+    db::AppState,
+    models::{ RecomputeJob, RecomputeStatus, RecomputeStep, User },
+    services::{
+        event_service,
+        monthly_stats_service,
+        progress_service,
+        report_service,
+        response_cache_service,
+    },
+};
+
+/// Users per batch; progress is saved after each one.
+const BATCH_SIZE: i64 = 50;
+const MAX_RECORDED_FAILURES: usize = 100;
+
+pub async fn run_job(state: AppState, job_id: ObjectId) {
+    if let Err(e) = execute_job(&state, job_id).await {
+        tracing::error!("Recompute job {} failed: {}", job_id, e);
+        let _ = state.db
+            .collection::<RecomputeJob>("recompute_jobs")
+            .update_one(
+                doc! { "_id": job_id },
+                doc! {
+                    "$set": {
+                        "status": RecomputeStatus::Failed.as_str(),
+                        "error": e.to_string(),
+                        "updated_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+                    }
+                },
+                None
+            ).await;
+    }
+}
+
+async fn execute_job(state: &AppState, job_id: ObjectId) -> Result<()> {
+    let jobs = state.db.collection::<RecomputeJob>("recompute_jobs");
+    let job = jobs
+        .find_one(doc! { "_id": job_id }, None).await?
+        .ok_or_else(|| anyhow::anyhow!("Recompute job not found"))?;
+
+    let users = state.db.collection::<Document>("users");
+    let scope = match job.user_id {
+        Some(user_id) => doc! { "_id": user_id },
+        None => doc! {},
+    };
+    let total_users = users.count_documents(scope.clone(), None).await? as i64;
+
+    tracing::info!(
+        "Recompute job {} started: {} users, steps {:?}",
+        job_id,
+        total_users,
+        job.steps.iter().map(RecomputeStep::as_str).collect::<Vec<_>>()
+    );
+
+    jobs.update_one(
+        doc! { "_id": job_id },
+        doc! {
+            "$set": {
+                "status": RecomputeStatus::Running.as_str(),
+                "total_users": total_users,
+                "updated_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+            }
+        },
+        None
+    ).await?;
+
+    let mut updated: BTreeMap<String, i64> = BTreeMap::new();
+    let mut failed_user_ids: Vec<ObjectId> = Vec::new();
+    let mut failed_users = 0;
+    let mut processed_users = 0;
+    let mut last_user_id: Option<ObjectId> = None;
+
+    loop {
+        let filter = match last_user_id {
+            Some(last) => doc! { "$and": [scope.clone(), { "_id": { "$gt": last } }] },
+            None => scope.clone(),
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "_id": 1 })
+            .limit(BATCH_SIZE)
+            .projection(doc! { "_id": 1 })
+            .build();
+        let batch: Vec<ObjectId> = users
+            .find(filter, options).await?
+            .try_collect::<Vec<Document>>().await?
+            .iter()
+            .filter_map(|user| user.get_object_id("_id").ok())
+            .collect();
+
+        let Some(&last) = batch.last() else {
+            break;
+        };
+
+        for user_id in batch {
+            match recompute_user(state, user_id, &job.steps).await {
+                Ok(counts) => {
+                    for (step, count) in counts {
+                        *updated.entry(step.as_str().to_string()).or_default() += count;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Recompute job {}: user {} failed: {}", job_id, user_id, e);
+                    failed_users += 1;
+                    if failed_user_ids.len() < MAX_RECORDED_FAILURES {
+                        failed_user_ids.push(user_id);
+                    }
+                }
+            }
+            processed_users += 1;
+        }
+        last_user_id = Some(last);
+
+        jobs.update_one(
+            doc! { "_id": job_id },
+            doc! {
+                "$set": {
+                    "processed_users": processed_users,
+                    "last_user_id": last,
+                    "updated": mongodb::bson::to_bson(&updated)?,
+                    "failed_users": failed_users,
+                    "failed_user_ids": failed_user_ids.iter().map(|id| Bson::ObjectId(*id)).collect::<Vec<_>>(),
+                    "updated_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+                }
+            },
+            None
+        ).await?;
+    }
+
+    let now = mongodb::bson::DateTime::from_chrono(Utc::now());
+    jobs.update_one(
+        doc! { "_id": job_id },
+        doc! {
+            "$set": {
+                "status": RecomputeStatus::Completed.as_str(),
+                "updated_at": now,
+                "completed_at": now,
+            }
+        },
+        None
+    ).await?;
+
+    tracing::info!(
+        "Recompute job {} completed: {} users, {} failed, updated {:?}",
+        job_id,
+        processed_users,
+        failed_users,
+        updated
+    );
+
+    Ok(())
+}
+
+/// Runs `steps` for one user. Returns the work done per step: days, months
+/// or reports rewritten, or 1 for a user checked for milestones.
+async fn recompute_user(
+    state: &AppState,
+    user_id: ObjectId,
+    steps: &[RecomputeStep]
+) -> Result<BTreeMap<RecomputeStep, i64>> {
+    let Some(user) = state.db.collection::<User>("users").find_one(doc! { "_id": user_id }, None).await? else {
+        // Deleted since the batch was read.
+        return Ok(BTreeMap::new());
+    };
+
+    let mut counts = BTreeMap::new();
+    for &step in steps {
+        let count = match step {
+            RecomputeStep::DailyProgress => rebuild_daily_progress(state, user_id).await?,
+            RecomputeStep::MonthlyStats => monthly_stats_service::rebuild_user(state, &user).await?,
+            RecomputeStep::Milestones => {
+                event_service::check_logging_milestones(state, user_id).await;
+                1
+            }
+            RecomputeStep::Reports => report_service::rebuild_reports(state, &user).await?,
+        };
+        counts.insert(step, count);
+    }
+
+    report_service::invalidate_compliance_score(state, user_id).await;
+    response_cache_service::invalidate_all(state, user_id).await;
+
+    Ok(counts)
+}
+
+/// Resets the meal totals of every `daily_progress` day from `meal_logs`,
+/// zeroing days whose meals are all gone. Returns the days rewritten.
+async fn rebuild_daily_progress(state: &AppState, user_id: ObjectId) -> Result<i64> {
+    Ok(progress_service::rebuild_user_totals(state, user_id).await? as i64)
+}
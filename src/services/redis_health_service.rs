@@ -0,0 +1,116 @@
+use chrono::{ DateTime, TimeZone, Utc };
+use redis::aio::ConnectionManager;
+use serde::Serialize;
+use std::{ sync::atomic::{ AtomicI64, Ordering }, time::{ Duration, Instant } };
+
+use crate::{ config::{ Config, RedisPolicy }, error::AppError };
+
+/// A health check waiting longer than this reports Redis as down.
+const PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub const SESSIONS_UNAVAILABLE_MESSAGE: &str = "Sign-in is temporarily unavailable. Please try again shortly.";
+pub const CACHE_UNAVAILABLE_MESSAGE: &str = "This page is temporarily unavailable. Please try again shortly.";
+
+/// Unix seconds of each feature's last Redis failure, 0 for none since startup.
+static LAST_FAILURE: [AtomicI64; 2] = [AtomicI64::new(0), AtomicI64::new(0)];
+
+/// Features that keep their state in Redis and follow a [`RedisPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisFeature {
+    Sessions,
+    ResponseCache,
+}
+
+impl RedisFeature {
+    pub const ALL: [RedisFeature; 2] = [RedisFeature::Sessions, RedisFeature::ResponseCache];
+
+    pub fn policy(&self, config: &Config) -> RedisPolicy {
+        match self {
+            RedisFeature::Sessions => config.redis.session_policy,
+            RedisFeature::ResponseCache => config.redis.response_cache_policy,
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            RedisFeature::Sessions => 0,
+            RedisFeature::ResponseCache => 1,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            RedisFeature::Sessions => "sessions",
+            RedisFeature::ResponseCache => "response cache",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureHealth {
+    pub feature: RedisFeature,
+    pub policy: RedisPolicy,
+    pub last_failure_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RedisHealth {
+    /// Whether Redis answered a PING in time.
+    pub available: bool,
+    pub latency_ms: Option<u64>,
+    pub features: Vec<FeatureHealth>,
+}
+
+/// Logs a Redis error hit by `feature` and remembers when it happened.
+pub fn note_failure(feature: RedisFeature, error: &redis::RedisError) {
+    tracing::warn!("Redis unavailable for {}: {}", feature.name(), error);
+    LAST_FAILURE[feature.index()].store(Utc::now().timestamp(), Ordering::Relaxed);
+}
+
+/// The 503 a fail-closed `feature` answers with while Redis is down.
+pub fn unavailable(feature: RedisFeature) -> AppError {
+    let message = match feature {
+        RedisFeature::Sessions => SESSIONS_UNAVAILABLE_MESSAGE,
+        RedisFeature::ResponseCache => CACHE_UNAVAILABLE_MESSAGE,
+    };
+    AppError::ExternalApiError(message.to_string())
+}
+
+/// Pings Redis and reports each feature's policy and last failure.
+pub async fn check(redis: &ConnectionManager, config: &Config) -> RedisHealth {
+    let mut conn = redis.clone();
+    let started = Instant::now();
+    let ping = tokio::time::timeout(PING_TIMEOUT, redis::cmd("PING").query_async::<_, String>(&mut conn)).await;
+
+    let available = match ping {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            tracing::warn!("Redis health check failed: {}", e);
+            false
+        }
+        Err(_) => {
+            tracing::warn!("Redis health check timed out");
+            false
+        }
+    };
+
+    let features = RedisFeature::ALL.iter()
+        .map(|feature| {
+            let last_failure = LAST_FAILURE[feature.index()].load(Ordering::Relaxed);
+            FeatureHealth {
+                feature: *feature,
+                policy: feature.policy(config),
+                last_failure_at: (last_failure > 0)
+                    .then(|| Utc.timestamp_opt(last_failure, 0).single())
+                    .flatten(),
+            }
+        })
+        .collect();
+
+    RedisHealth {
+        available,
+        latency_ms: available.then(|| started.elapsed().as_millis() as u64),
+        features,
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::Result;
+use chrono::{ DateTime, Duration, FixedOffset, NaiveDate, NaiveTime, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId };
+
+use crate::{
+    db::AppState,
+    models::{
+        MealLog,
+        MealType,
+        Notification,
+        NotificationChannel,
+        NotificationKind,
+        NotificationPreferences,
+        User,
+    },
+    services::{ notification_service, report_service, timezone, water_service },
+};
+
+/// A reminder is still sent this long after its time, so a slow or restarted
+/// scheduler doesn't drop it, but one set for earlier today doesn't fire late.
+const SEND_WINDOW_MINUTES: i64 = 30;
+/// Streaks shorter than this aren't worth an alert.
+const MIN_STREAK_DAYS: usize = 2;
+/// How far back a streak is counted.
+const STREAK_LOOKBACK_DAYS: i64 = 90;
+const SENT_KEY_TTL_SECONDS: u64 = 2 * 24 * 60 * 60;
+
+/// Sends every reminder that is due for every user who set one. Run by the
+/// scheduler every few minutes.
+pub async fn send_due_reminders(state: &AppState) -> Result<()> {
+    let users: Vec<User> = state.db
+        .collection::<User>("users")
+        .find(
+            doc! {
+                "$or": [
+                    { "notification_preferences.reminders.meals.0": { "$exists": true } },
+                    { "notification_preferences.reminders.streak_alert_time": { "$type": "string" } },
+                    { "notification_preferences.reminders.hydration_times.0": { "$exists": true } },
+                ]
+            },
+            None
+        ).await?
+        .try_collect().await?;
+
+    let now = Utc::now();
+    let mut sent = 0;
+    for user in users {
+        let Some(user_id) = user.id else {
+            continue;
+        };
+        match remind_user(state, &user, user_id, now).await {
+            Ok(count) => {
+                sent += count;
+            }
+            Err(e) => tracing::warn!("Reminders failed for user {}: {}", user_id, e),
+        }
+    }
+
+    if sent > 0 {
+        tracing::info!("Sent {} reminders", sent);
+    }
+
+    Ok(())
+}
+
+async fn remind_user(state: &AppState, user: &User, user_id: ObjectId, now: DateTime<Utc>) -> Result<usize> {
+    let preferences = user.notification_preferences.clone().unwrap_or_default();
+    if !preferences.reminders.any_enabled() || notification_service::quiet_until(&preferences, now).is_some() {
+        return Ok(0);
+    }
+
+    let offset = timezone::from_minutes(preferences.utc_offset_minutes).unwrap_or_else(timezone::utc);
+    let local = now.with_timezone(&offset);
+    let today = local.date_naive();
+    let due = |time: &str| {
+        notification_service::parse_time(time).is_some_and(|time| is_due(local.time(), time))
+    };
+
+    let mut sent = 0;
+
+    for reminder in &preferences.reminders.meals {
+        if !due(&reminder.time) || logged_meal_type(state, user_id, &reminder.meal_type, today, offset).await? {
+            continue;
+        }
+        let key = format!("meal:{}:{}", reminder.meal_type.as_str(), reminder.time);
+        if !claim(state, user_id, &key, today).await? {
+            continue;
+        }
+        let meal = reminder.meal_type.as_str();
+        send(
+            state,
+            &preferences,
+            reminder_notification(
+                user_id,
+                NotificationKind::MealReminder,
+                format!("Time to log {}", meal),
+                format!("You haven't logged {} yet today. It only takes a moment.", meal)
+            )
+        ).await?;
+        sent += 1;
+    }
+
+    if let Some(time) = preferences.reminders.streak_alert_time.as_deref().filter(|time| due(time)) {
+        let streak = streak_at_risk(state, user_id, today, offset).await?;
+        if streak >= MIN_STREAK_DAYS && claim(state, user_id, &format!("streak:{}", time), today).await? {
+            send(
+                state,
+                &preferences,
+                reminder_notification(
+                    user_id,
+                    NotificationKind::StreakAtRisk,
+                    format!("Your {}-day streak ends today", streak),
+                    "Log anything you've eaten today to keep your logging streak going.".to_string()
+                )
+            ).await?;
+            sent += 1;
+        }
+    }
+
+    for time in preferences.reminders.hydration_times.iter().filter(|time| due(time)) {
+        let water_ml = water_service::water_for_day(state, user_id, today).await?;
+        let target_ml = water_service::water_target_ml(user);
+        if water_ml >= target_ml || !claim(state, user_id, &format!("hydration:{}", time), today).await? {
+            continue;
+        }
+        send(
+            state,
+            &preferences,
+            reminder_notification(
+                user_id,
+                NotificationKind::HydrationNudge,
+                "Time for some water".to_string(),
+                format!("You've had {:.0} of your {:.0} ml today.", water_ml, target_ml)
+            )
+        ).await?;
+        sent += 1;
+    }
+
+    Ok(sent)
+}
+
+fn is_due(now: NaiveTime, time: NaiveTime) -> bool {
+    now >= time && now - time < Duration::minutes(SEND_WINDOW_MINUTES)
+}
+
+/// Marks the reminder as sent for `day`; false if it already was, e.g. by
+/// another instance.
+async fn claim(state: &AppState, user_id: ObjectId, reminder: &str, day: NaiveDate) -> Result<bool> {
+    let mut conn = state.redis.clone();
+    let claimed: Option<String> = redis
+        ::cmd("SET")
+        .arg(format!("reminder_sent:{}:{}:{}", user_id.to_hex(), reminder, day))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(SENT_KEY_TTL_SECONDS)
+        .query_async(&mut conn).await?;
+    Ok(claimed.is_some())
+}
+
+async fn logged_meal_type(
+    state: &AppState,
+    user_id: ObjectId,
+    meal_type: &MealType,
+    day: NaiveDate,
+    offset: FixedOffset
+) -> Result<bool> {
+    let count = state.db.collection::<MealLog>("meal_logs").count_documents(
+        doc! {
+            "user_id": user_id,
+            "meal_type": meal_type.as_str(),
+            "date": {
+                "$gte": mongodb::bson::DateTime::from_chrono(timezone::start_of_day(day, offset)),
+                "$lt": mongodb::bson::DateTime::from_chrono(timezone::start_of_day(day + Duration::days(1), offset)),
+            },
+        },
+        None
+    ).await?;
+    Ok(count > 0)
+}
+
+/// Length of the logging streak that ends today unless something is logged:
+/// the run of logged days up to yesterday, or 0 if today is already logged.
+async fn streak_at_risk(state: &AppState, user_id: ObjectId, today: NaiveDate, offset: FixedOffset) -> Result<usize> {
+    let days = report_service::daily_meal_totals_in(
+        state,
+        user_id,
+        timezone::start_of_day(today - Duration::days(STREAK_LOOKBACK_DAYS), offset),
+        timezone::start_of_day(today + Duration::days(1), offset),
+        offset
+    ).await?;
+
+    let mut expected = today - Duration::days(1);
+    let mut streak = 0;
+    for day in days.iter().rev() {
+        if day.date == today {
+            return Ok(0);
+        }
+        if day.date != expected {
+            break;
+        }
+        streak += 1;
+        expected -= Duration::days(1);
+    }
+
+    Ok(streak)
+}
+
+fn reminder_notification(user_id: ObjectId, kind: NotificationKind, title: String, message: String) -> Notification {
+    Notification {
+        id: None,
+        user_id,
+        kind,
+        title,
+        message,
+        milestone: None,
+        report_id: None,
+        read: false,
+        created_at: Utc::now(),
+    }
+}
+
+/// Turning push off in the general preferences also silences pushed reminders.
+async fn send(state: &AppState, preferences: &NotificationPreferences, notification: Notification) -> Result<()> {
+    let channels: Vec<NotificationChannel> = preferences.reminders.channels
+        .iter()
+        .copied()
+        .filter(|channel| preferences.push_enabled || *channel != NotificationChannel::Push)
+        .collect();
+    notification_service::send_now(state, notification, &channels).await
+}
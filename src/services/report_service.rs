@@ -0,0 +1,766 @@
+use chrono::{ DateTime, Datelike, Duration, FixedOffset, NaiveDate, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId, Bson, Document };
+use redis::AsyncCommands;
+use serde::{ de::DeserializeOwned, Deserialize, Serialize };
+use std::collections::BTreeMap;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{
+        AnomalyKind,
+        HealthGoal,
+        MealLog,
+        MealReport,
+        ReportAnomaly,
+        ReportFrequency,
+        ReportPeriod,
+        ReportStatus,
+        User,
+    },
+    services::{
+        email_service::EmailService,
+        event_service::{ self, DomainEvent },
+        goal_history_service,
+        monthly_stats_service,
+        timezone,
+        response_cache_service::{ self, CachedRoute },
+        weight_service,
+    },
+};
+
+const VERY_LOW_INTAKE_KCAL: f64 = 500.0;
+const IMPLAUSIBLE_INTAKE_KCAL: f64 = 6000.0;
+const MIN_GAP_DAYS: i64 = 3;
+
+const SCORE_WINDOW_DAYS: i64 = 7;
+const SCORE_CACHE_TTL_SECONDS: u64 = 900;
+const LOGGING_WEIGHT: f64 = 0.4;
+const MACRO_WEIGHT: f64 = 0.6;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ComplianceScore {
+    pub score: f64,
+    pub logging_score: f64,
+    pub macro_score: f64,
+    pub days_logged: usize,
+    pub window_days: usize,
+    pub start_date: String,
+    pub end_date: String,
+    pub computed_at: DateTime<Utc>,
+}
+
+/// Meal totals summed server-side by [`daily_meal_totals`].
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MealTotals {
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub meal_count: usize,
+}
+
+/// Totals for one UTC day.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DailyMealTotals {
+    #[serde(rename = "_id")]
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub totals: MealTotals,
+}
+
+/// Meals logged in `[start, end]`, in no particular order.
+pub async fn fetch_meals_between(
+    state: &AppState,
+    user_id: ObjectId,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>
+) -> Result<Vec<MealLog>> {
+    let filter =
+        doc! {
+        "user_id": user_id,
+        "date": {
+            "$gte": mongodb::bson::DateTime::from_chrono(start),
+            "$lte": mongodb::bson::DateTime::from_chrono(end)
+        }
+    };
+
+    state.db
+        .collection::<MealLog>("meal_logs")
+        .find(filter, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+/// `$match` stage for a user's meals in `[start, end)`.
+pub fn meal_range_stage(user_id: ObjectId, start: DateTime<Utc>, end: DateTime<Utc>) -> Document {
+    doc! {
+        "$match": {
+            "user_id": user_id,
+            "date": {
+                "$gte": mongodb::bson::DateTime::from_chrono(start),
+                "$lt": mongodb::bson::DateTime::from_chrono(end)
+            }
+        }
+    }
+}
+
+/// `$group` stage summing macros into the [`MealTotals`] fields.
+fn totals_group_stage(id: impl Into<Bson>) -> Document {
+    doc! {
+        "$group": {
+            "_id": id.into(),
+            "calories": { "$sum": "$calories" },
+            "protein_g": { "$sum": "$protein_g" },
+            "carbs_g": { "$sum": "$carbs_g" },
+            "fat_g": { "$sum": "$fat_g" },
+            "meal_count": { "$sum": 1 },
+        }
+    }
+}
+
+/// Runs an aggregation over `meal_logs` and deserializes each output document.
+pub async fn aggregate_meals<T: DeserializeOwned>(state: &AppState, pipeline: Vec<Document>) -> Result<Vec<T>> {
+    let documents: Vec<Document> = state.db
+        .collection::<MealLog>("meal_logs")
+        .aggregate(pipeline, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    documents
+        .into_iter()
+        .map(|document| mongodb::bson::from_document(document).map_err(|e| AppError::InternalError(e.into())))
+        .collect()
+}
+
+/// Per-day totals for `[start, end)`, oldest first. Days without meals are absent.
+pub async fn daily_meal_totals(
+    state: &AppState,
+    user_id: ObjectId,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>
+) -> Result<Vec<DailyMealTotals>> {
+    daily_meal_totals_in(state, user_id, start, end, timezone::utc()).await
+}
+
+/// Like [`daily_meal_totals`], with days running midnight to midnight at `offset`.
+pub async fn daily_meal_totals_in(
+    state: &AppState,
+    user_id: ObjectId,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    offset: FixedOffset
+) -> Result<Vec<DailyMealTotals>> {
+    let pipeline = vec![
+        meal_range_stage(user_id, start, end),
+        totals_group_stage(
+            doc! {
+                "$dateToString": {
+                    "format": "%Y-%m-%d",
+                    "date": "$date",
+                    "timezone": timezone::mongo_timezone(offset),
+                }
+            }
+        ),
+        doc! { "$sort": { "_id": 1 } }
+    ];
+    aggregate_meals(state, pipeline).await
+}
+
+fn compliance_cache_key(user_id: ObjectId) -> String {
+    format!("compliance_score:{}", user_id.to_hex())
+}
+
+/// Rolling 0-100 consistency score over the trailing 7 days: 40% how many days
+/// were logged, 60% how close logged days were to the macro targets.
+pub async fn get_compliance_score(state: &AppState, user_id: ObjectId) -> Result<ComplianceScore> {
+    let mut conn = state.redis.clone();
+    let key = compliance_cache_key(user_id);
+
+    let cached: Option<String> = conn.get(&key).await.unwrap_or(None);
+    if let Some(score) = cached.and_then(|json| serde_json::from_str(&json).ok()) {
+        return Ok(score);
+    }
+
+    let score = compute_compliance_score(state, user_id).await?;
+
+    if let Ok(json) = serde_json::to_string(&score) {
+        if let Err(e) = conn.set_ex::<_, _, ()>(&key, json, SCORE_CACHE_TTL_SECONDS).await {
+            tracing::warn!("Failed to cache compliance score: {}", e);
+        }
+    }
+
+    Ok(score)
+}
+
+pub async fn invalidate_compliance_score(state: &AppState, user_id: ObjectId) {
+    let mut conn = state.redis.clone();
+    if let Err(e) = conn.del::<_, ()>(compliance_cache_key(user_id)).await {
+        tracing::warn!("Failed to invalidate compliance score: {}", e);
+    }
+}
+
+async fn compute_compliance_score(state: &AppState, user_id: ObjectId) -> Result<ComplianceScore> {
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    // Each day is scored against its own targets when the profile has a target cycle.
+    let targets_on = |date: NaiveDate| match &user.health_profile {
+        Some(profile) => profile.targets_on(date),
+        None => (2000.0, 150.0, 250.0, 67.0),
+    };
+
+    let end_date = Utc::now().date_naive();
+    let start_date = end_date - Duration::days(SCORE_WINDOW_DAYS - 1);
+
+    let daily = monthly_stats_service::daily_totals(state, user_id, start_date, end_date, timezone::utc()).await?;
+
+    let closeness = |actual: f64, target: f64| -> f64 {
+        if target <= 0.0 {
+            return 0.0;
+        }
+        (100.0 - ((actual - target).abs() / target) * 100.0).max(0.0)
+    };
+
+    let days_logged = daily.len();
+    let logging_score = ((days_logged as f64) / (SCORE_WINDOW_DAYS as f64)) * 100.0;
+    let macro_score = if days_logged > 0 {
+        daily
+            .iter()
+            .map(|day| {
+                let (target_calories, target_protein, target_carbs, target_fat) = targets_on(day.date);
+                (closeness(day.totals.calories, target_calories) +
+                    closeness(day.totals.protein_g, target_protein) +
+                    closeness(day.totals.carbs_g, target_carbs) +
+                    closeness(day.totals.fat_g, target_fat)) /
+                    4.0
+            })
+            .sum::<f64>() / (days_logged as f64)
+    } else {
+        0.0
+    };
+
+    let score = logging_score * LOGGING_WEIGHT + macro_score * MACRO_WEIGHT;
+
+    Ok(ComplianceScore {
+        score: score.round(),
+        logging_score: logging_score.round(),
+        macro_score: macro_score.round(),
+        days_logged,
+        window_days: SCORE_WINDOW_DAYS as usize,
+        start_date: start_date.format("%Y-%m-%d").to_string(),
+        end_date: end_date.format("%Y-%m-%d").to_string(),
+        computed_at: Utc::now(),
+    })
+}
+
+/// Flags days that are likely logging mistakes rather than real intake, so the
+/// report can warn that its averages may be skewed.
+pub fn detect_anomalies(
+    daily: &[DailyMealTotals],
+    start_date: NaiveDate,
+    end_date: NaiveDate
+) -> Vec<ReportAnomaly> {
+    let daily_calories: BTreeMap<NaiveDate, f64> = daily
+        .iter()
+        .map(|day| (day.date, day.totals.calories))
+        .collect();
+
+    let mut anomalies = Vec::new();
+
+    for (date, calories) in &daily_calories {
+        if *calories > IMPLAUSIBLE_INTAKE_KCAL {
+            anomalies.push(ReportAnomaly {
+                kind: AnomalyKind::ImplausibleIntake,
+                date: date.format("%Y-%m-%d").to_string(),
+                detail: format!(
+                    "{:.0} kcal logged - this is unusually high and may include a duplicate or mistyped entry",
+                    calories
+                ),
+            });
+        } else if *calories < VERY_LOW_INTAKE_KCAL {
+            anomalies.push(ReportAnomaly {
+                kind: AnomalyKind::VeryLowIntake,
+                date: date.format("%Y-%m-%d").to_string(),
+                detail: format!(
+                    "Only {:.0} kcal logged - some meals may not have been recorded",
+                    calories
+                ),
+            });
+        }
+    }
+
+    // Days that haven't happened yet can't be missing.
+    let last_day = end_date.min(Utc::now().date_naive());
+    let mut gap_start: Option<NaiveDate> = None;
+    let mut day = start_date;
+    while day <= last_day {
+        if daily_calories.contains_key(&day) {
+            if let Some(start) = gap_start.take() {
+                push_gap(&mut anomalies, start, day.pred_opt().unwrap_or(day));
+            }
+        } else if gap_start.is_none() {
+            gap_start = Some(day);
+        }
+        day = match day.succ_opt() {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    if let Some(start) = gap_start {
+        push_gap(&mut anomalies, start, last_day);
+    }
+
+    anomalies
+}
+
+fn push_gap(anomalies: &mut Vec<ReportAnomaly>, start: NaiveDate, end: NaiveDate) {
+    let days = (end - start).num_days() + 1;
+    if days < MIN_GAP_DAYS {
+        return;
+    }
+
+    anomalies.push(ReportAnomaly {
+        kind: AnomalyKind::LoggingGap,
+        date: start.format("%Y-%m-%d").to_string(),
+        detail: format!(
+            "No meals logged for {} days ({} to {})",
+            days,
+            start.format("%Y-%m-%d"),
+            end.format("%Y-%m-%d")
+        ),
+    });
+}
+
+/// The most recent fully elapsed Monday-Sunday week or calendar month before `today`.
+pub fn last_completed_period(frequency: ReportFrequency, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match frequency {
+        ReportFrequency::Weekly => {
+            let this_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+            (this_monday - Duration::days(7), this_monday - Duration::days(1))
+        }
+        ReportFrequency::Monthly => {
+            let last_month_end = today.with_day(1).unwrap() - Duration::days(1);
+            (last_month_end.with_day(1).unwrap(), last_month_end)
+        }
+    }
+}
+
+/// First day a scheduled report can go out for the period in progress on `today`.
+pub fn next_delivery_date(frequency: ReportFrequency, today: NaiveDate) -> NaiveDate {
+    match frequency {
+        ReportFrequency::Weekly =>
+            today + Duration::days(7 - (today.weekday().num_days_from_monday() as i64)),
+        ReportFrequency::Monthly => (today.with_day(28).unwrap() + Duration::days(4)).with_day(1).unwrap(),
+    }
+}
+
+/// Placeholder stored when a report is queued; `run_report_job` fills it in.
+pub fn pending_report(
+    user_id: ObjectId,
+    report_type: ReportPeriod,
+    start_date: NaiveDate,
+    end_date: NaiveDate
+) -> MealReport {
+    MealReport {
+        id: None,
+        user_id,
+        report_type,
+        start_date: start_date.format("%Y-%m-%d").to_string(),
+        end_date: end_date.format("%Y-%m-%d").to_string(),
+        generated_at: Utc::now(),
+        status: ReportStatus::Pending,
+        total_days: ((end_date - start_date).num_days() as usize) + 1,
+        days_logged: 0,
+        total_meals: 0,
+        avg_calories: 0.0,
+        avg_protein_g: 0.0,
+        avg_carbs_g: 0.0,
+        avg_fat_g: 0.0,
+        goal_type: String::new(),
+        goal_achieved: false,
+        calories_compliance_percent: 0.0,
+        protein_compliance_percent: 0.0,
+        carbs_compliance_percent: 0.0,
+        fat_compliance_percent: 0.0,
+        days_on_target: 0,
+        starting_weight: None,
+        ending_weight: None,
+        weight_change: None,
+        target_weight: None,
+        weight_goal_achieved: None,
+        goal_started_on: None,
+        best_day_date: None,
+        best_day_compliance: None,
+        streak_days: 0,
+        notes: None,
+        coach_commentary: None,
+        coach_prompt_version: None,
+        data_quality: Vec::new(),
+        error: None,
+        utc_offset_minutes: None,
+    }
+}
+
+/// Background job behind `POST /api/reports/generate` and the chat agent's
+/// GENERATE_REPORT tool. Long ranges (yearly) take too long to build inline.
+pub async fn run_report_job(state: AppState, report_id: ObjectId, send_email: bool, language: String) {
+    let reports = state.db.collection::<MealReport>("meal_reports");
+    let user_id = match reports.find_one(doc! { "_id": report_id }, None).await {
+        Ok(report) => report.map(|r| r.user_id),
+        Err(_) => None,
+    };
+    // The pending report was just inserted; listings must show it.
+    if let Some(user_id) = user_id {
+        response_cache_service::invalidate(&state, user_id, CachedRoute::Reports).await;
+    }
+
+    if let Err(e) = execute_report_job(&state, report_id, send_email, &language).await {
+        tracing::error!("Report job {} failed: {}", report_id, e);
+        let _ = reports.update_one(
+            doc! { "_id": report_id },
+            doc! { "$set": { "status": "Failed", "error": e.to_string() } },
+            None
+        ).await;
+    }
+
+    if let Some(user_id) = user_id {
+        response_cache_service::invalidate(&state, user_id, CachedRoute::Reports).await;
+    }
+}
+
+async fn execute_report_job(
+    state: &AppState,
+    report_id: ObjectId,
+    send_email: bool,
+    language: &str
+) -> Result<()> {
+    let reports = state.db.collection::<MealReport>("meal_reports");
+
+    let pending = reports
+        .find_one(doc! { "_id": report_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Report not found".to_string()))?;
+
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": pending.user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let start_date = NaiveDate::parse_from_str(&pending.start_date, "%Y-%m-%d").map_err(|_|
+        AppError::BadRequest("Invalid start date format".to_string())
+    )?;
+    let end_date = NaiveDate::parse_from_str(&pending.end_date, "%Y-%m-%d").map_err(|_|
+        AppError::BadRequest("Invalid end date format".to_string())
+    )?;
+
+    let offset = pending.utc_offset_minutes.and_then(timezone::from_minutes).unwrap_or_else(timezone::utc);
+    let mut report = build_report(state, &user, pending.report_type, start_date, end_date, offset).await?;
+    report.id = Some(report_id);
+
+    if send_email {
+        match state.gemini_service.generate_coach_commentary(&report, language).await {
+            Ok((commentary, prompt_version)) => {
+                report.coach_commentary = Some(commentary);
+                report.coach_prompt_version = Some(prompt_version);
+            }
+            Err(e) => tracing::warn!("Failed to generate coach commentary: {}", e),
+        }
+    }
+
+    let mut fields = mongodb::bson
+        ::to_document(&report)
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    fields.remove("_id");
+
+    reports
+        .update_one(doc! { "_id": report_id }, doc! { "$set": fields }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    event_service::check_report_milestones(state, pending.user_id, &report).await;
+
+    if send_email {
+        let email_service = EmailService::new(
+            state.config.brevo.smtp_host.clone(),
+            state.config.brevo.smtp_port,
+            state.config.brevo.smtp_user.clone(),
+            state.config.brevo.smtp_pass.clone(),
+            state.config.brevo.from_email.clone(),
+            state.config.brevo.from_name.clone(),
+            state.config.branding.clone()
+        );
+
+        let status = match email_service.send_report_email(&user, &report).await {
+            Ok(()) => "Sent",
+            Err(e) => {
+                tracing::error!("Failed to send report email: {}", e);
+                "Failed"
+            }
+        };
+
+        reports
+            .update_one(doc! { "_id": report_id }, doc! { "$set": { "status": status } }, None).await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+    }
+
+    tracing::info!("Report {} generated for user {}", report_id, pending.user_id);
+
+    event_service::publish(state, DomainEvent::ReportReady {
+        user_id: pending.user_id,
+        report_id,
+        report_type: report.report_type,
+    }).await;
+
+    Ok(())
+}
+
+/// Fields of a stored report that [`rebuild_reports`] leaves alone: its
+/// identity, delivery state and the commentary written from the old numbers.
+const PRESERVED_REPORT_FIELDS: [&str; 12] = [
+    "_id",
+    "user_id",
+    "report_type",
+    "start_date",
+    "end_date",
+    "generated_at",
+    "status",
+    "notes",
+    "coach_commentary",
+    "coach_prompt_version",
+    "error",
+    "utc_offset_minutes",
+];
+
+/// Recomputes the statistics of the user's finished reports from their meals,
+/// with the health profile's current targets. Returns how many were updated.
+pub async fn rebuild_reports(state: &AppState, user: &User) -> Result<i64> {
+    let user_id = user.id.ok_or_else(|| AppError::BadRequest("User has no ID".to_string()))?;
+    let reports = state.db.collection::<MealReport>("meal_reports");
+
+    let stored: Vec<MealReport> = reports
+        .find(doc! { "user_id": user_id, "status": { "$in": ["Generated", "Sent"] } }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    let mut rebuilt = 0;
+    for report in stored {
+        let (Some(report_id), Ok(start_date), Ok(end_date)) = (
+            report.id,
+            NaiveDate::parse_from_str(&report.start_date, "%Y-%m-%d"),
+            NaiveDate::parse_from_str(&report.end_date, "%Y-%m-%d"),
+        ) else {
+            continue;
+        };
+        let offset = report.utc_offset_minutes.and_then(timezone::from_minutes).unwrap_or_else(timezone::utc);
+
+        let fresh = build_report(state, user, report.report_type, start_date, end_date, offset).await?;
+        let mut fields = mongodb::bson::to_document(&fresh).map_err(|e| AppError::InternalError(e.into()))?;
+        for field in PRESERVED_REPORT_FIELDS {
+            fields.remove(field);
+        }
+
+        reports
+            .update_one(doc! { "_id": report_id }, doc! { "$set": fields }, None).await
+            .map_err(|e| AppError::InternalError(e.into()))?;
+        rebuilt += 1;
+    }
+
+    if rebuilt > 0 {
+        response_cache_service::invalidate(state, user_id, CachedRoute::Reports).await;
+    }
+
+    Ok(rebuilt)
+}
+
+/// Computes the report statistics for `user` over the inclusive date range,
+/// with days running midnight to midnight at `offset`.
+pub async fn build_report(
+    state: &AppState,
+    user: &User,
+    report_type: ReportPeriod,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    offset: FixedOffset
+) -> Result<MealReport> {
+    let user_id = user.id.ok_or_else(|| AppError::BadRequest("User has no ID".to_string()))?;
+
+    let start_datetime = timezone::start_of_day(start_date, offset);
+    let end_datetime = timezone::start_of_day(end_date + Duration::days(1), offset) - Duration::seconds(1);
+
+    let daily = monthly_stats_service::daily_totals(state, user_id, start_date, end_date, offset).await?;
+
+    let total_meals: usize = daily
+        .iter()
+        .map(|day| day.totals.meal_count)
+        .sum();
+
+    tracing::info!("Building report for user {} from {} meals", user_id, total_meals);
+
+    let total_days = ((end_date - start_date).num_days() as usize) + 1;
+    let total_calories: f64 = daily
+        .iter()
+        .map(|day| day.totals.calories)
+        .sum();
+    let total_protein: f64 = daily
+        .iter()
+        .map(|day| day.totals.protein_g)
+        .sum();
+    let total_carbs: f64 = daily
+        .iter()
+        .map(|day| day.totals.carbs_g)
+        .sum();
+    let total_fat: f64 = daily
+        .iter()
+        .map(|day| day.totals.fat_g)
+        .sum();
+
+    let days_logged = daily.len();
+    let avg_calories = if days_logged > 0 { total_calories / (days_logged as f64) } else { 0.0 };
+    let avg_protein = if days_logged > 0 { total_protein / (days_logged as f64) } else { 0.0 };
+    let avg_carbs = if days_logged > 0 { total_carbs / (days_logged as f64) } else { 0.0 };
+    let avg_fat = if days_logged > 0 { total_fat / (days_logged as f64) } else { 0.0 };
+
+    // Weight progress is measured against the goal in effect at the end of the
+    // period, and only from when that goal was set.
+    let eras = goal_history_service::eras(state, user_id, user.health_profile.as_ref()).await?;
+    let era = goal_history_service::era_at(&eras, end_datetime);
+
+    let (target_calories, target_protein, target_carbs, target_fat, goal_type) = if
+        let Some(profile) = &user.health_profile
+    {
+        let goal = match era.map_or(&profile.goal, |era| &era.goal) {
+            HealthGoal::LoseWeight => "lose_weight".to_string(),
+            HealthGoal::MaintainWeight => "maintain_weight".to_string(),
+            HealthGoal::GainWeight => "gain_weight".to_string(),
+            HealthGoal::BuildMuscle => "build_muscle".to_string(),
+        };
+        let (calories, protein, carbs, fat) = profile.average_targets(daily.iter().map(|day| day.date));
+        (calories, protein, carbs, fat, goal)
+    } else {
+        (2000.0, 150.0, 250.0, 67.0, "maintain_weight".to_string())
+    };
+
+    let compliance = |avg: f64, target: f64| {
+        if target > 0.0 { ((avg / target) * 100.0).min(100.0) } else { 0.0 }
+    };
+    let calories_compliance = compliance(avg_calories, target_calories);
+    let protein_compliance = compliance(avg_protein, target_protein);
+    let carbs_compliance = compliance(avg_carbs, target_carbs);
+    let fat_compliance = compliance(avg_fat, target_fat);
+
+    // Single days are compared with their own targets when the profile has a target cycle.
+    let day_targets = |date: NaiveDate| match &user.health_profile {
+        Some(profile) => profile.targets_on(date),
+        None => (2000.0, 150.0, 250.0, 67.0),
+    };
+
+    let days_on_target = daily
+        .iter()
+        .filter(|day| {
+            let (day_calories, ..) = day_targets(day.date);
+            (day.totals.calories - day_calories).abs() / day_calories <= 0.1
+        })
+        .count();
+
+    let avg_compliance =
+        (calories_compliance + protein_compliance + carbs_compliance + fat_compliance) / 4.0;
+    let goal_achieved =
+        avg_compliance >= 80.0 && (days_logged as f64) / (total_days as f64) >= 0.7;
+
+    let mut best_day_date = None;
+    let mut best_day_compliance = 0.0;
+    for day in &daily {
+        let (day_calories, day_protein, day_carbs, day_fat) = day_targets(day.date);
+        let day_avg_comp =
+            (((day.totals.calories / day_calories) * 100.0).min(100.0) +
+                ((day.totals.protein_g / day_protein) * 100.0).min(100.0) +
+                ((day.totals.carbs_g / day_carbs) * 100.0).min(100.0) +
+                ((day.totals.fat_g / day_fat) * 100.0).min(100.0)) /
+            4.0;
+
+        if day_avg_comp > best_day_compliance {
+            best_day_compliance = day_avg_comp;
+            best_day_date = Some(day.date.format("%Y-%m-%d").to_string());
+        }
+    }
+
+    let mut streak = 0;
+    let mut current_streak = 0;
+    let mut last_date: Option<NaiveDate> = None;
+    for date in daily.iter().map(|day| day.date) {
+        current_streak = match last_date {
+            Some(last) if (date - last).num_days() == 1 => current_streak + 1,
+            _ => 1,
+        };
+        streak = streak.max(current_streak);
+        last_date = Some(date);
+    }
+
+    let goal_started_at = era
+        .map(|era| era.started_at)
+        .filter(|started_at| *started_at > start_datetime && *started_at <= end_datetime);
+    let weigh_ins = weight_service::fetch_weight_logs_between(
+        state,
+        user_id,
+        goal_started_at.unwrap_or(start_datetime),
+        end_datetime
+    ).await?;
+    let weight_trend = weight_service::trend(&weigh_ins);
+    let target_weight = match era {
+        Some(era) => Some(era.target_weight_kg),
+        None => user.health_profile.as_ref().map(weight_service::target_weight),
+    };
+    let weight_goal = era.map(|era| &era.goal).or(user.health_profile.as_ref().map(|profile| &profile.goal));
+    let weight_goal_achieved = match (weight_goal, &weight_trend, target_weight) {
+        (Some(goal), Some(trend), Some(target)) =>
+            Some(weight_service::goal_achieved(goal, trend.ending_weight, target)),
+        _ => None,
+    };
+
+    Ok(MealReport {
+        id: None,
+        user_id,
+        report_type,
+        start_date: start_date.format("%Y-%m-%d").to_string(),
+        end_date: end_date.format("%Y-%m-%d").to_string(),
+        generated_at: Utc::now(),
+        status: ReportStatus::Generated,
+        total_days,
+        days_logged,
+        total_meals,
+        avg_calories,
+        avg_protein_g: avg_protein,
+        avg_carbs_g: avg_carbs,
+        avg_fat_g: avg_fat,
+        goal_type,
+        goal_achieved,
+        calories_compliance_percent: calories_compliance,
+        protein_compliance_percent: protein_compliance,
+        carbs_compliance_percent: carbs_compliance,
+        fat_compliance_percent: fat_compliance,
+        days_on_target,
+        starting_weight: weight_trend.as_ref().map(|t| t.starting_weight),
+        ending_weight: weight_trend.as_ref().map(|t| t.ending_weight),
+        weight_change: weight_trend.as_ref().map(|t| t.weight_change),
+        target_weight,
+        weight_goal_achieved,
+        goal_started_on: goal_started_at.map(|at| at.with_timezone(&offset).format("%Y-%m-%d").to_string()),
+        best_day_date,
+        best_day_compliance: if best_day_compliance > 0.0 { Some(best_day_compliance) } else { None },
+        streak_days: streak,
+        notes: None,
+        coach_commentary: None,
+        coach_prompt_version: None,
+        data_quality: detect_anomalies(&daily, start_date, end_date),
+        error: None,
+        utc_offset_minutes: (!timezone::is_utc(offset)).then(|| timezone::minutes(offset)),
+    })
+}
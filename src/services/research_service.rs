@@ -0,0 +1,224 @@
+use anyhow::Result;
+use chrono::{ NaiveDate, TimeZone, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::Deserialize;
+use std::collections::{ BTreeMap, HashMap };
+
+use crate::{
+    db::AppState,
+    models::{ ExportStatus, HealthProfile, ResearchCohort, ResearchExport, User },
+    services::report_service,
+};
+
+pub const DEFAULT_K_THRESHOLD: i32 = 10;
+/// Requests below this are rejected; smaller cohorts are too easy to re-identify.
+pub const MIN_K_THRESHOLD: i32 = 5;
+
+/// A user's average daily intake over the days they logged in the export range.
+#[derive(Deserialize)]
+struct UserAverages {
+    #[serde(rename = "_id")]
+    user_id: ObjectId,
+    days_logged: f64,
+    calories: f64,
+    protein_g: f64,
+    carbs_g: f64,
+    fat_g: f64,
+}
+
+#[derive(Default)]
+struct CohortAccumulator {
+    users: i32,
+    days_logged: f64,
+    calories: f64,
+    protein_g: f64,
+    carbs_g: f64,
+    fat_g: f64,
+    calorie_target_ratio: f64,
+}
+
+pub async fn run_export(state: AppState, export_id: ObjectId) {
+    if let Err(e) = execute_export(&state, export_id).await {
+        tracing::error!("Research export {} failed: {}", export_id, e);
+        let _ = state.db
+            .collection::<ResearchExport>("research_exports")
+            .update_one(
+                doc! { "_id": export_id },
+                doc! {
+                    "$set": {
+                        "status": ExportStatus::Failed.as_str(),
+                        "error": e.to_string(),
+                        "completed_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+                    }
+                },
+                None
+            ).await;
+    }
+}
+
+async fn execute_export(state: &AppState, export_id: ObjectId) -> Result<()> {
+    let exports = state.db.collection::<ResearchExport>("research_exports");
+
+    let export = exports
+        .find_one(doc! { "_id": export_id }, None).await?
+        .ok_or_else(|| anyhow::anyhow!("Research export not found"))?;
+
+    exports.update_one(
+        doc! { "_id": export_id },
+        doc! { "$set": { "status": ExportStatus::Running.as_str() } },
+        None
+    ).await?;
+
+    let start = NaiveDate::parse_from_str(&export.start_date, "%Y-%m-%d")?;
+    let end = NaiveDate::parse_from_str(&export.end_date, "%Y-%m-%d")?;
+
+    // Per-day totals first, then each user's average over the days they logged.
+    let range_start = Utc.from_utc_datetime(&start.and_hms_opt(0, 0, 0).unwrap());
+    let range_end = Utc.from_utc_datetime(&end.succ_opt().unwrap_or(end).and_hms_opt(0, 0, 0).unwrap());
+    let pipeline = vec![
+        doc! {
+            "$match": {
+                "date": {
+                    "$gte": mongodb::bson::DateTime::from_chrono(range_start),
+                    "$lt": mongodb::bson::DateTime::from_chrono(range_end)
+                }
+            }
+        },
+        doc! {
+            "$group": {
+                "_id": {
+                    "user_id": "$user_id",
+                    "day": { "$dateToString": { "format": "%Y-%m-%d", "date": "$date" } }
+                },
+                "calories": { "$sum": "$calories" },
+                "protein_g": { "$sum": "$protein_g" },
+                "carbs_g": { "$sum": "$carbs_g" },
+                "fat_g": { "$sum": "$fat_g" },
+            }
+        },
+        doc! {
+            "$group": {
+                "_id": "$_id.user_id",
+                "days_logged": { "$sum": 1 },
+                "calories": { "$avg": "$calories" },
+                "protein_g": { "$avg": "$protein_g" },
+                "carbs_g": { "$avg": "$carbs_g" },
+                "fat_g": { "$avg": "$fat_g" },
+            }
+        }
+    ];
+    let per_user: HashMap<ObjectId, UserAverages> = report_service
+        ::aggregate_meals::<UserAverages>(state, pipeline).await?
+        .into_iter()
+        .map(|averages| (averages.user_id, averages))
+        .collect();
+
+    let users: Vec<User> = state.db
+        .collection::<User>("users")
+        .find(doc! { "health_profile": { "$exists": true, "$ne": null } }, None).await?
+        .try_collect().await?;
+
+    let mut cohorts: BTreeMap<(String, String, String, String), CohortAccumulator> = BTreeMap::new();
+    for user in users {
+        let (Some(user_id), Some(profile)) = (user.id, user.health_profile) else {
+            continue;
+        };
+        let Some(averages) = per_user.get(&user_id) else {
+            continue;
+        };
+
+        let acc = cohorts.entry(cohort_key(&profile)).or_default();
+        acc.users += 1;
+        acc.days_logged += averages.days_logged;
+        acc.calories += averages.calories;
+        acc.protein_g += averages.protein_g;
+        acc.carbs_g += averages.carbs_g;
+        acc.fat_g += averages.fat_g;
+        if profile.daily_calories > 0.0 {
+            acc.calorie_target_ratio += averages.calories / profile.daily_calories;
+        }
+    }
+
+    let mut included_users = 0;
+    let mut suppressed_users = 0;
+    let mut results = Vec::new();
+    for ((age_band, gender, goal, bmi_category), acc) in cohorts {
+        if acc.users < export.k_threshold {
+            suppressed_users += acc.users;
+            continue;
+        }
+
+        included_users += acc.users;
+        let n = acc.users as f64;
+        results.push(ResearchCohort {
+            age_band,
+            gender,
+            goal,
+            bmi_category,
+            user_count: acc.users,
+            avg_days_logged: round1(acc.days_logged / n),
+            avg_daily_calories: round1(acc.calories / n),
+            avg_daily_protein_g: round1(acc.protein_g / n),
+            avg_daily_carbs_g: round1(acc.carbs_g / n),
+            avg_daily_fat_g: round1(acc.fat_g / n),
+            avg_calorie_target_ratio: (acc.calorie_target_ratio / n * 100.0).round() / 100.0,
+        });
+    }
+
+    exports.update_one(
+        doc! { "_id": export_id },
+        doc! {
+            "$set": {
+                "status": ExportStatus::Completed.as_str(),
+                "cohorts": mongodb::bson::to_bson(&results)?,
+                "included_users": included_users,
+                "suppressed_users": suppressed_users,
+                "completed_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+            }
+        },
+        None
+    ).await?;
+
+    tracing::info!(
+        "Research export {} completed: {} cohorts, {} users included, {} suppressed",
+        export_id,
+        results.len(),
+        included_users,
+        suppressed_users
+    );
+
+    Ok(())
+}
+
+/// Generalized quasi-identifiers: ten-year age band, gender, goal and BMI category.
+fn cohort_key(profile: &HealthProfile) -> (String, String, String, String) {
+    let age_band = match profile.age {
+        age if age < 18 => "under 18".to_string(),
+        age if age < 30 => "18-29".to_string(),
+        age if age >= 60 => "60+".to_string(),
+        age => {
+            let lower = (age / 10) * 10;
+            format!("{}-{}", lower, lower + 9)
+        }
+    };
+
+    (
+        age_band,
+        enum_label(&profile.gender),
+        enum_label(&profile.goal),
+        profile.bmi_category.clone(),
+    )
+}
+
+fn enum_label<T: serde::Serialize>(value: &T) -> String {
+    serde_json
+        ::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
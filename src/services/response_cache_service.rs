@@ -0,0 +1,123 @@
+use mongodb::bson::oid::ObjectId;
+use redis::AsyncCommands;
+use serde::{ Deserialize, Serialize };
+
+use crate::{ db::AppState, services::redis_health_service::{ self, RedisFeature } };
+
+/// Read-heavy routes whose responses may be cached per user. Each has a
+/// per-user generation counter; writes bump it, which orphans every cached
+/// response of that route for the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachedRoute {
+    DailyMeals,
+    HealthProfile,
+    Reports,
+}
+
+impl CachedRoute {
+    pub const ALL: [CachedRoute; 3] = [CachedRoute::DailyMeals, CachedRoute::HealthProfile, CachedRoute::Reports];
+
+    /// The allowlist: only these exact paths are cached.
+    pub fn for_path(path: &str) -> Option<Self> {
+        match path {
+            "/api/meals/daily" => Some(CachedRoute::DailyMeals),
+            "/api/health/profile" => Some(CachedRoute::HealthProfile),
+            "/api/reports" => Some(CachedRoute::Reports),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CachedRoute::DailyMeals => "meals_daily",
+            CachedRoute::HealthProfile => "health_profile",
+            CachedRoute::Reports => "reports",
+        }
+    }
+}
+
+/// Generation counters outlive every cached response, so one expiring can't
+/// bring back a stale entry.
+const GENERATION_TTL_SECONDS: i64 = 7 * 24 * 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub body: String,
+}
+
+/// The cached response under `key`, if any. An error means Redis is
+/// unavailable; the caller decides by the response cache policy whether to
+/// serve uncached.
+pub async fn get(state: &AppState, key: &str) -> redis::RedisResult<Option<CachedResponse>> {
+    let mut conn = state.redis.clone();
+    let json = conn.get::<_, Option<String>>(key).await.inspect_err(|e| {
+        redis_health_service::note_failure(RedisFeature::ResponseCache, e);
+    })?;
+    Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+pub async fn put(state: &AppState, key: &str, response: &CachedResponse) {
+    let mut conn = state.redis.clone();
+    let Ok(json) = serde_json::to_string(response) else {
+        return;
+    };
+
+    if let Err(e) = conn.set_ex::<_, _, ()>(key, json, state.config.response_cache.ttl_seconds).await {
+        redis_health_service::note_failure(RedisFeature::ResponseCache, &e);
+    }
+}
+
+/// Drops the user's cached responses for `route`. Call after any write that
+/// changes what the route returns.
+pub async fn invalidate(state: &AppState, user_id: ObjectId, route: CachedRoute) {
+    if !state.config.response_cache.enabled {
+        return;
+    }
+
+    let mut conn = state.redis.clone();
+    let key = generation_key(&user_id.to_hex(), route);
+    let result: redis::RedisResult<()> = redis
+        ::pipe()
+        .incr(&key, 1)
+        .ignore()
+        .expire(&key, GENERATION_TTL_SECONDS)
+        .ignore()
+        .query_async(&mut conn).await;
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to invalidate {} responses for user {}", route.name(), user_id);
+        redis_health_service::note_failure(RedisFeature::ResponseCache, &e);
+    }
+}
+
+/// The daily meals response includes the profile's targets, so it goes stale
+/// with the profile.
+pub async fn invalidate_profile(state: &AppState, user_id: ObjectId) {
+    invalidate(state, user_id, CachedRoute::HealthProfile).await;
+    invalidate(state, user_id, CachedRoute::DailyMeals).await;
+}
+
+pub async fn invalidate_all(state: &AppState, user_id: ObjectId) {
+    for route in CachedRoute::ALL {
+        invalidate(state, user_id, route).await;
+    }
+}
+
+/// Key for the response under the route's current generation. Take it before
+/// running the handler and store under it, so a write while the handler runs
+/// orphans the response instead of filing it under the new generation.
+pub async fn response_key(state: &AppState, user_id: &str, route: CachedRoute, query: &str) -> redis::RedisResult<String> {
+    let mut conn = state.redis.clone();
+    let generation = conn
+        .get::<_, Option<u64>>(generation_key(user_id, route)).await
+        .inspect_err(|e| redis_health_service::note_failure(RedisFeature::ResponseCache, e))?
+        .unwrap_or(0);
+
+    Ok(format!("resp:{}:{}:{}:{}", route.name(), user_id, generation, query))
+}
+
+fn generation_key(user_id: &str, route: CachedRoute) -> String {
+    format!("resp_gen:{}:{}", route.name(), user_id)
+}
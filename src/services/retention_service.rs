@@ -0,0 +1,71 @@
+use anyhow::Result;
+use chrono::{ Months, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId, Bson };
+
+use crate::{ db::AppState, models::{ ChatMessage, ChatSession, User }, services::image_service };
+
+/// Retention windows a user can choose, in months.
+pub const ALLOWED_RETENTION_MONTHS: [i32; 5] = [1, 3, 6, 12, 24];
+
+/// Applies every user's chat retention preference.
+pub async fn purge_expired_chat_history(state: &AppState) -> Result<()> {
+    let users: Vec<User> = state.db
+        .collection::<User>("users")
+        .find(doc! { "chat_retention_months": { "$ne": Bson::Null } }, None).await?
+        .try_collect().await?;
+
+    let mut deleted = 0;
+    for user in users {
+        let (Some(user_id), Some(months)) = (user.id, user.chat_retention_months) else {
+            continue;
+        };
+        match purge_user_chat_history(state, user_id, months).await {
+            Ok(count) => {
+                deleted += count;
+            }
+            Err(e) => tracing::error!("Chat retention cleanup failed for user {}: {}", user_id, e),
+        }
+    }
+
+    if deleted > 0 {
+        tracing::info!("Deleted {} chat messages past their retention window", deleted);
+    }
+
+    Ok(())
+}
+
+/// Deletes the user's chat messages older than `months` months, then drops
+/// sessions left empty and recounts the rest. Returns the messages deleted.
+pub async fn purge_user_chat_history(state: &AppState, user_id: ObjectId, months: i32) -> Result<u64> {
+    let cutoff = Utc::now()
+        .checked_sub_months(Months::new(months.max(1) as u32))
+        .ok_or_else(|| anyhow::anyhow!("Invalid retention window: {} months", months))?;
+    // `created_at` is stored as RFC 3339 in UTC, which sorts chronologically.
+    let expired = doc! { "user_id": user_id, "created_at": { "$lt": cutoff.to_rfc3339() } };
+
+    let messages = state.db.collection::<ChatMessage>("chat_messages");
+    let session_ids = messages.distinct("session_id", expired.clone(), None).await?;
+    if session_ids.is_empty() {
+        return Ok(0);
+    }
+
+    image_service::delete_message_images(&state.db, expired.clone()).await?;
+    let deleted = messages.delete_many(expired, None).await?.deleted_count;
+
+    let sessions = state.db.collection::<ChatSession>("chat_sessions");
+    for session_id in session_ids.iter().filter_map(Bson::as_object_id) {
+        let remaining = messages.count_documents(doc! { "session_id": session_id }, None).await?;
+        if remaining == 0 {
+            sessions.delete_one(doc! { "_id": session_id, "user_id": user_id }, None).await?;
+        } else {
+            sessions.update_one(
+                doc! { "_id": session_id, "user_id": user_id },
+                doc! { "$set": { "message_count": remaining as i32 } },
+                None
+            ).await?;
+        }
+    }
+
+    Ok(deleted)
+}
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use chrono::{ Datelike, NaiveTime, TimeZone, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId, Document }, options::FindOptions };
+use std::{ collections::HashSet, time::Duration };
+
+use crate::{
+    db::AppState,
+    models::{ MealLog, MealReport, RecurringMeal, ReportSchedule },
+    services::{
+        monthly_stats_service,
+        notification_service,
+        progress_service,
+        reminder_service,
+        report_service,
+        retention_service,
+    },
+};
+
+const RECURRING_MEALS_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const SCHEDULED_REPORTS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const NOTIFICATION_DELIVERY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const REMINDERS_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const MONTHLY_STATS_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const CHAT_RETENTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Starts the background jobs. Each job runs on its own interval and logs
+/// (rather than propagates) failures so one bad run doesn't stop the loop.
+pub fn start(state: AppState) {
+    let recurring_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECURRING_MEALS_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = log_recurring_meals(&recurring_state).await {
+                tracing::error!("Recurring meal job failed: {}", e);
+            }
+        }
+    });
+
+    let retention_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHAT_RETENTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = retention_service::purge_expired_chat_history(&retention_state).await {
+                tracing::error!("Chat retention job failed: {}", e);
+            }
+        }
+    });
+
+    let monthly_stats_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MONTHLY_STATS_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = monthly_stats_service::aggregate_previous_month(&monthly_stats_state).await {
+                tracing::error!("Monthly stats job failed: {}", e);
+            }
+        }
+    });
+
+    let notification_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(NOTIFICATION_DELIVERY_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = notification_service::deliver_due(&notification_state).await {
+                tracing::error!("Notification delivery job failed: {}", e);
+            }
+        }
+    });
+
+    let reminders_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REMINDERS_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = reminder_service::send_due_reminders(&reminders_state).await {
+                tracing::error!("Reminder job failed: {}", e);
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULED_REPORTS_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = deliver_scheduled_reports(&state).await {
+                tracing::error!("Scheduled report job failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Logs every active recurring meal that is due today and hasn't been logged yet.
+pub async fn log_recurring_meals(state: &AppState) -> Result<()> {
+    let now = Utc::now();
+    let today = now.date_naive();
+    let today_str = today.to_string();
+    let weekday = today.weekday().number_from_monday();
+
+    let recurring = state.db.collection::<RecurringMeal>("recurring_meals");
+    let due: Vec<RecurringMeal> = recurring
+        .find(doc! { "active": true, "last_logged_date": { "$ne": &today_str } }, None).await?
+        .try_collect().await?;
+
+    // Meals of deleted or merged-away accounts are switched off instead of
+    // being logged for a user that no longer exists.
+    let owners: Vec<ObjectId> = due.iter().map(|meal| meal.user_id).collect();
+    let existing: HashSet<ObjectId> = state.db
+        .collection::<Document>("users")
+        .find(
+            doc! { "_id": { "$in": &owners } },
+            FindOptions::builder().projection(doc! { "_id": 1 }).build()
+        ).await?
+        .try_collect::<Vec<Document>>().await?
+        .iter()
+        .filter_map(|user| user.get_object_id("_id").ok())
+        .collect();
+    let orphaned: Vec<ObjectId> = owners
+        .iter()
+        .filter(|user_id| !existing.contains(user_id))
+        .copied()
+        .collect();
+    if !orphaned.is_empty() {
+        let deactivated = recurring.update_many(
+            doc! { "user_id": { "$in": &orphaned }, "active": true },
+            doc! { "$set": { "active": false } },
+            None
+        ).await?;
+        tracing::info!("Deactivated {} recurring meals of deleted accounts", deactivated.modified_count);
+    }
+
+    let mut logged = 0;
+    for meal in due {
+        let Some(recurring_id) = meal.id else {
+            continue;
+        };
+        if !existing.contains(&meal.user_id) {
+            continue;
+        }
+        if !meal.days_of_week.is_empty() && !meal.days_of_week.contains(&weekday) {
+            continue;
+        }
+        let Ok(time) = NaiveTime::parse_from_str(&meal.time_of_day, "%H:%M") else {
+            tracing::warn!("Recurring meal {} has invalid time_of_day {}", recurring_id, meal.time_of_day);
+            continue;
+        };
+        if now.time() < time {
+            continue;
+        }
+
+        // Claim today's run first so concurrent instances can't log it twice.
+        let claimed = recurring.update_one(
+            doc! { "_id": recurring_id, "last_logged_date": { "$ne": &today_str } },
+            doc! { "$set": { "last_logged_date": &today_str } },
+            None
+        ).await?;
+        if claimed.modified_count == 0 {
+            continue;
+        }
+
+        let logged_at = Utc.from_utc_datetime(&today.and_time(time));
+        let meal_log = MealLog {
+            id: None,
+            user_id: meal.user_id,
+            date: logged_at,
+            meal_type: meal.meal_type,
+            food_name: meal.food_name,
+            calories: meal.calories,
+            protein_g: meal.protein_g,
+            carbs_g: meal.carbs_g,
+            fat_g: meal.fat_g,
+            fiber_g: None,
+            sugar_g: None,
+            sodium_mg: None,
+            items: Vec::new(),
+            serving_size: meal.serving_size,
+            notes: meal.notes,
+            source: meal.source.unverified(),
+            verified: false,
+            nutrition_snapshot: None,
+            portion_weight_g: None,
+            auto_logged: true,
+            recurring_meal_id: Some(recurring_id),
+            hunger_level: None,
+            mood: None,
+            eating_context: None,
+            version: 0,
+            created_at: now,
+        };
+
+        state.db.collection::<MealLog>("meal_logs").insert_one(&meal_log, None).await?;
+        report_service::invalidate_compliance_score(state, meal.user_id).await;
+        progress_service::record_meal_added(state, &meal_log).await?;
+        logged += 1;
+    }
+
+    if logged > 0 {
+        tracing::info!("Auto-logged {} recurring meals for {}", logged, today_str);
+    }
+
+    Ok(())
+}
+
+/// Generates and emails a report for every active schedule whose last
+/// completed week or month hasn't been sent yet. Reports are built one at a
+/// time so a Monday morning doesn't fire every user's Gemini call at once.
+pub async fn deliver_scheduled_reports(state: &AppState) -> Result<()> {
+    let today = Utc::now().date_naive();
+
+    let schedules_collection = state.db.collection::<ReportSchedule>("report_schedules");
+    let schedules: Vec<ReportSchedule> = schedules_collection
+        .find(doc! { "active": true }, None).await?
+        .try_collect().await?;
+
+    let mut sent = 0;
+    for schedule in schedules {
+        let Some(schedule_id) = schedule.id else {
+            continue;
+        };
+        let (start, end) = report_service::last_completed_period(schedule.frequency, today);
+        let end_str = end.format("%Y-%m-%d").to_string();
+        if schedule.last_period_end.as_deref() == Some(end_str.as_str()) {
+            continue;
+        }
+
+        // Claim the period first so concurrent instances can't send it twice.
+        let claimed = schedules_collection.update_one(
+            doc! { "_id": schedule_id, "last_period_end": { "$ne": &end_str } },
+            doc! { "$set": { "last_period_end": &end_str } },
+            None
+        ).await?;
+        if claimed.modified_count == 0 {
+            continue;
+        }
+
+        let report = report_service::pending_report(schedule.user_id, schedule.frequency.period(), start, end);
+        let result = state.db.collection::<MealReport>("meal_reports").insert_one(&report, None).await?;
+        let Some(report_id) = result.inserted_id.as_object_id() else {
+            continue;
+        };
+
+        report_service::run_report_job(state.clone(), report_id, true, schedule.language).await;
+        sent += 1;
+    }
+
+    if sent > 0 {
+        tracing::info!("Delivered {} scheduled reports", sent);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::FindOptions };
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ ItemQuantity, MealPlan, ShoppingList, ShoppingListItem },
+    services::{ cache_service, mealdb_service::Meal },
+};
+
+pub const MAX_RECIPES: usize = 20;
+
+/// Collects ingredients before merging, in the order they were first seen.
+#[derive(Default)]
+struct ListBuilder {
+    items: Vec<ShoppingListItem>,
+}
+
+impl ListBuilder {
+    /// Adds `amount` of `unit` to the item named `name`, ignoring case. Amounts
+    /// in the same unit are summed; other units are listed next to them.
+    fn add(&mut self, name: &str, amount: Option<f64>, unit: &str, note: Option<String>, used_in: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+
+        let index = match self.items.iter().position(|item| item.name.eq_ignore_ascii_case(name)) {
+            Some(index) => index,
+            None => {
+                self.items.push(ShoppingListItem {
+                    name: name.to_string(),
+                    quantities: Vec::new(),
+                    notes: Vec::new(),
+                    used_in: Vec::new(),
+                    checked: false,
+                });
+                self.items.len() - 1
+            }
+        };
+        let item = &mut self.items[index];
+
+        if let Some(amount) = amount {
+            match item.quantities.iter_mut().find(|q| q.unit == unit) {
+                Some(quantity) => {
+                    quantity.amount += amount;
+                }
+                None =>
+                    item.quantities.push(ItemQuantity {
+                        amount,
+                        unit: unit.to_string(),
+                    }),
+            }
+        }
+        if let Some(note) = note {
+            if !item.notes.contains(&note) {
+                item.notes.push(note);
+            }
+        }
+        if !item.used_in.iter().any(|u| u == used_in) {
+            item.used_in.push(used_in.to_string());
+        }
+    }
+
+    fn build(mut self) -> Vec<ShoppingListItem> {
+        for item in &mut self.items {
+            for quantity in &mut item.quantities {
+                quantity.amount = (quantity.amount * 100.0).round() / 100.0;
+            }
+        }
+        self.items.sort_by_key(|item| item.name.to_lowercase());
+        self.items
+    }
+}
+
+/// Every ingredient in the plan's meals that haven't been eaten yet, from
+/// `from_date` (`YYYY-MM-DD`) on when given.
+pub fn items_from_meal_plan(plan: &MealPlan, from_date: Option<&str>) -> Result<Vec<ShoppingListItem>> {
+    let mut builder = ListBuilder::default();
+    let mut meals = 0;
+
+    for day in plan.days.iter().filter(|day| from_date.is_none_or(|from| day.date.as_str() >= from)) {
+        for meal in day.meals.iter().filter(|meal| meal.eaten_at.is_none()) {
+            meals += 1;
+            for ingredient in &meal.ingredients {
+                let (unit, factor) = normalize_unit(&ingredient.unit);
+                // Units the plan made up ("slice", "clove") are kept as they are.
+                let (amount, unit) = if unit.is_empty() {
+                    (ingredient.quantity, ingredient.unit.trim().to_lowercase())
+                } else {
+                    (ingredient.quantity * factor, unit)
+                };
+                builder.add(&ingredient.name, Some(amount), &unit, None, &meal.food_name);
+            }
+        }
+    }
+
+    if meals > 0 && builder.items.is_empty() {
+        return Err(
+            AppError::ValidationError(
+                "This meal plan has no ingredient lists; regenerate it to build a shopping list".to_string()
+            )
+        );
+    }
+
+    Ok(builder.build())
+}
+
+/// Fetches the recipes from TheMealDB and merges their ingredients.
+pub async fn items_from_recipes(state: &AppState, recipe_ids: &[String]) -> Result<Vec<ShoppingListItem>> {
+    let mut builder = ListBuilder::default();
+
+    for recipe_id in recipe_ids {
+        let recipe: Meal = state.cache
+            .get_or_fetch(cache_service::MEALDB_MEAL, recipe_id, false, || {
+                state.mealdb_service.get_meal_by_id(recipe_id)
+            }).await
+            .map_err(AppError::InternalError)?
+            .ok_or_else(|| AppError::NotFound(format!("Recipe {} not found", recipe_id)))?;
+
+        for (ingredient, measure) in recipe.get_ingredients() {
+            let (amount, unit, note) = parse_measure(&measure);
+            builder.add(&ingredient, amount, &unit, note, &recipe.str_meal);
+        }
+    }
+
+    Ok(builder.build())
+}
+
+pub async fn find_list(state: &AppState, user_id: ObjectId, list_id: ObjectId) -> Result<ShoppingList> {
+    state.db
+        .collection::<ShoppingList>("shopping_lists")
+        .find_one(doc! { "_id": list_id, "user_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("Shopping list not found".to_string()))
+}
+
+/// Newest first.
+pub async fn list_lists(state: &AppState, user_id: ObjectId, limit: i64) -> Result<Vec<ShoppingList>> {
+    state.db
+        .collection::<ShoppingList>("shopping_lists")
+        .find(
+            doc! { "user_id": user_id },
+            FindOptions::builder()
+                .sort(doc! { "created_at": -1 })
+                .limit(limit)
+                .build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+pub async fn set_checked(
+    state: &AppState,
+    user_id: ObjectId,
+    list_id: ObjectId,
+    item_index: usize,
+    checked: bool
+) -> Result<ShoppingList> {
+    let list = find_list(state, user_id, list_id).await?;
+    if item_index >= list.items.len() {
+        return Err(AppError::NotFound("Shopping list item not found".to_string()));
+    }
+
+    state.db
+        .collection::<ShoppingList>("shopping_lists")
+        .update_one(
+            doc! { "_id": list_id, "user_id": user_id },
+            doc! {
+                "$set": {
+                    format!("items.{}.checked", item_index): checked,
+                    "updated_at": Utc::now().to_rfc3339(),
+                }
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    find_list(state, user_id, list_id).await
+}
+
+/// Splits a TheMealDB measure such as "1 1/2 cups", "200g" or "2 large" into an
+/// amount and a unit. Measures without a leading number ("Pinch", "to taste")
+/// come back as a note instead.
+fn parse_measure(measure: &str) -> (Option<f64>, String, Option<String>) {
+    let measure = measure.trim();
+    if measure.is_empty() {
+        return (None, String::new(), None);
+    }
+
+    let (number, rest) = leading_number(measure);
+    let Some(number) = number else {
+        return (None, String::new(), Some(measure.to_string()));
+    };
+
+    let unit_word = rest.split_whitespace().next().unwrap_or("");
+    let (unit, factor) = normalize_unit(unit_word);
+    if unit.is_empty() && !unit_word.is_empty() {
+        // "2 large" and "3 cloves" count whole items; keep the word as a note.
+        return (Some(number), String::new(), Some(measure.to_string()));
+    }
+
+    (Some(number * factor), unit, None)
+}
+
+/// The number at the start of `text`, including fractions ("1/2", "1 1/2",
+/// "½"), and the rest of the text.
+fn leading_number(text: &str) -> (Option<f64>, &str) {
+    let mut total: Option<f64> = None;
+    let mut rest = text;
+
+    loop {
+        let trimmed = rest.trim_start();
+        let end = trimmed
+            .char_indices()
+            .find(|(_, c)| !(c.is_ascii_digit() || *c == '.' || *c == '/' || is_vulgar_fraction(*c)))
+            .map(|(i, _)| i)
+            .unwrap_or(trimmed.len());
+        if end == 0 {
+            break;
+        }
+
+        let Some(value) = parse_number(&trimmed[..end]) else {
+            break;
+        };
+        total = Some(total.unwrap_or(0.0) + value);
+        rest = &trimmed[end..];
+    }
+
+    (total.filter(|n| *n > 0.0), rest.trim_start())
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    if let Some((numerator, denominator)) = token.split_once('/') {
+        let denominator: f64 = denominator.parse().ok()?;
+        return (denominator != 0.0).then_some(numerator.parse::<f64>().ok()? / denominator);
+    }
+
+    let mut value = 0.0;
+    let mut digits = String::new();
+    for c in token.chars() {
+        if is_vulgar_fraction(c) {
+            value += vulgar_fraction(c);
+        } else {
+            digits.push(c);
+        }
+    }
+    if !digits.is_empty() {
+        value += digits.parse::<f64>().ok()?;
+    }
+    Some(value)
+}
+
+fn is_vulgar_fraction(c: char) -> bool {
+    matches!(c, '¼' | '½' | '¾' | '⅓' | '⅔' | '⅛')
+}
+
+fn vulgar_fraction(c: char) -> f64 {
+    match c {
+        '¼' => 0.25,
+        '½' => 0.5,
+        '¾' => 0.75,
+        '⅓' => 1.0 / 3.0,
+        '⅔' => 2.0 / 3.0,
+        '⅛' => 0.125,
+        _ => 0.0,
+    }
+}
+
+/// Canonical unit and the factor converting to it; grams and millilitres
+/// absorb kilograms and litres so they sum together. Unknown words return an
+/// empty unit.
+fn normalize_unit(unit: &str) -> (String, f64) {
+    let unit = unit.trim().trim_end_matches('.').to_lowercase();
+    let (canonical, factor) = match unit.as_str() {
+        "g" | "gr" | "gram" | "grams" => ("g", 1.0),
+        "kg" | "kilogram" | "kilograms" => ("g", 1000.0),
+        "ml" | "millilitre" | "millilitres" | "milliliter" | "milliliters" => ("ml", 1.0),
+        "l" | "litre" | "litres" | "liter" | "liters" => ("ml", 1000.0),
+        "tbsp" | "tbs" | "tblsp" | "tablespoon" | "tablespoons" => ("tbsp", 1.0),
+        "tsp" | "teaspoon" | "teaspoons" => ("tsp", 1.0),
+        "cup" | "cups" => ("cup", 1.0),
+        "oz" | "ounce" | "ounces" => ("oz", 1.0),
+        "lb" | "lbs" | "pound" | "pounds" => ("lb", 1.0),
+        _ => ("", 1.0),
+    };
+    (canonical.to_string(), factor)
+}
@@ -0,0 +1,385 @@
+use chrono::{ Duration, NaiveDate, TimeZone, Utc };
+use mongodb::bson::{ doc, oid::ObjectId };
+use rand::seq::SliceRandom;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ DietaryPreference, HealthProfile, MealLog, MealType, User },
+    services::{
+        cache_service,
+        condition_guidance,
+        dietary_filter,
+        food_table,
+        mealdb_service::Meal,
+        recipe_nutrition_service,
+        report_service,
+    },
+};
+
+const MAX_SUGGESTIONS: usize = 5;
+const FAVORITES_LOOKBACK_DAYS: i64 = 30;
+/// A snack has to be logged at least this often in the lookback window to count as a favorite.
+const FAVORITE_MIN_COUNT: usize = 2;
+/// Below this there's no meaningful snack budget left.
+const MIN_SNACK_KCAL: f64 = 50.0;
+
+const MAX_RECIPE_SUGGESTIONS: usize = 3;
+/// Each estimate can cost an API Ninjas call, so only this many recipes that
+/// pass the ingredient filters are estimated per request.
+const MAX_RECIPE_ESTIMATES: usize = 6;
+/// TheMealDB doesn't list servings; most of its recipes feed about four.
+const RECIPE_SERVINGS: f64 = 4.0;
+/// Carb ceilings per serving for the carb-restricted diets.
+const KETO_MAX_CARBS_G: f64 = 15.0;
+const LOW_CARB_MAX_CARBS_G: f64 = 30.0;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    Favorite,
+    FoodTable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnackSuggestion {
+    pub food_name: String,
+    pub serving_size: Option<String>,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    pub source: SuggestionSource,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemainingBudget {
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnackSuggestions {
+    pub remaining: RemainingBudget,
+    pub suggestions: Vec<SnackSuggestion>,
+}
+
+/// Snack ideas that fit today's remaining calories and the profile's condition
+/// guidance, preferring the user's own frequently logged snacks and, among
+/// equals, the most protein per calorie.
+pub async fn suggest_snacks(state: &AppState, user_id: ObjectId) -> Result<SnackSuggestions> {
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let profile = user.health_profile.ok_or_else(||
+        AppError::BadRequest("Complete your health profile to get snack suggestions".to_string())
+    )?;
+
+    let now = Utc::now();
+    let today_start = Utc.from_utc_datetime(&now.date_naive().and_hms_opt(0, 0, 0).unwrap());
+    let lookback_start = today_start - Duration::days(FAVORITES_LOOKBACK_DAYS);
+
+    let meals = report_service::fetch_meals_between(state, user_id, lookback_start, now).await?;
+
+    let remaining = remaining_budget(
+        &profile,
+        today_start.date_naive(),
+        meals.iter().filter(|m| m.date >= today_start)
+    );
+
+    if remaining.calories < MIN_SNACK_KCAL {
+        return Ok(SnackSuggestions { remaining, suggestions: Vec::new() });
+    }
+
+    // Snacks logged repeatedly, keyed by name, keeping the most recent entry's values.
+    let mut favorites: HashMap<String, (usize, &MealLog)> = HashMap::new();
+    for meal in meals.iter().filter(|m| matches!(m.meal_type, MealType::Snack)) {
+        let entry = favorites.entry(meal.food_name.trim().to_lowercase()).or_insert((0, meal));
+        entry.0 += 1;
+        if meal.date > entry.1.date {
+            entry.1 = meal;
+        }
+    }
+
+    let mut candidates: Vec<SnackSuggestion> = favorites
+        .into_values()
+        .filter(|(count, _)| *count >= FAVORITE_MIN_COUNT)
+        .map(|(_, meal)| SnackSuggestion {
+            food_name: meal.food_name.clone(),
+            serving_size: meal.serving_size.clone(),
+            calories: meal.calories,
+            protein_g: meal.protein_g,
+            carbs_g: meal.carbs_g,
+            fat_g: meal.fat_g,
+            source: SuggestionSource::Favorite,
+        })
+        .collect();
+
+    for entry in food_table::FOODS.iter().filter(|f| f.snack) {
+        let serving = entry.standard_serving();
+        if candidates.iter().any(|c| c.food_name.eq_ignore_ascii_case(&serving.food_name)) {
+            continue;
+        }
+        candidates.push(SnackSuggestion {
+            food_name: serving.food_name,
+            serving_size: Some(serving.serving_size),
+            calories: serving.calories,
+            protein_g: serving.protein_g,
+            carbs_g: serving.carbs_g,
+            fat_g: serving.fat_g,
+            source: SuggestionSource::FoodTable,
+        });
+    }
+
+    candidates.retain(|c| c.calories > 0.0 && c.calories <= remaining.calories);
+
+    let guidance = condition_guidance::for_profile(&profile);
+    let max_snack_carbs = guidance
+        .iter()
+        .filter_map(|g| g.max_carbs_per_snack_g)
+        .reduce(f64::min);
+    candidates.retain(|c| {
+        max_snack_carbs.is_none_or(|max| c.carbs_g <= max) &&
+            condition_guidance::conflicts(&guidance, std::slice::from_ref(&c.food_name)).is_empty()
+    });
+
+    let needs_protein = remaining.protein_g > 0.0;
+    candidates.sort_by(|a, b| {
+        let favorite_first = (b.source == SuggestionSource::Favorite).cmp(
+            &(a.source == SuggestionSource::Favorite)
+        );
+        favorite_first.then_with(|| {
+            if needs_protein {
+                (b.protein_g / b.calories).total_cmp(&(a.protein_g / a.calories))
+            } else {
+                a.calories.total_cmp(&b.calories)
+            }
+        })
+    });
+    candidates.truncate(MAX_SUGGESTIONS);
+
+    Ok(SnackSuggestions { remaining, suggestions: candidates })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeSuggestion {
+    pub recipe_id: String,
+    pub name: String,
+    pub category: Option<String>,
+    pub area: Option<String>,
+    pub url: String,
+    pub source_url: Option<String>,
+    pub youtube_url: Option<String>,
+    pub thumbnail: Option<String>,
+    /// Servings the estimate below assumes the recipe makes.
+    pub servings: f64,
+    pub calories: f64,
+    pub protein_g: f64,
+    pub carbs_g: f64,
+    pub fat_g: f64,
+    /// Whether one serving fits today's remaining calories.
+    pub fits_remaining: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecipeSuggestions {
+    pub remaining: RemainingBudget,
+    pub recipes: Vec<RecipeSuggestion>,
+    /// Recipes dropped for the user's dietary preferences, allergies or
+    /// medical conditions.
+    pub hidden: usize,
+}
+
+/// TheMealDB recipes for the user: `query` searches by name, otherwise a
+/// sample is drawn from a category that suits their preferences. Recipes that
+/// conflict with dietary preferences, allergies or condition guidance are
+/// dropped, and the rest get estimated per-serving macros. With
+/// `only_fitting`, recipes whose serving doesn't fit today's remaining
+/// calories are left out too.
+pub async fn suggest_recipes(
+    state: &AppState,
+    user_id: ObjectId,
+    query: Option<&str>,
+    meal_type: Option<&MealType>,
+    only_fitting: bool
+) -> Result<RecipeSuggestions> {
+    let user = state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let profile = user.health_profile.ok_or_else(||
+        AppError::BadRequest("Complete your health profile to get recipe suggestions".to_string())
+    )?;
+
+    let now = Utc::now();
+    let today_start = Utc.from_utc_datetime(&now.date_naive().and_hms_opt(0, 0, 0).unwrap());
+    let meals = report_service::fetch_meals_between(state, user_id, today_start, now).await?;
+    let remaining = remaining_budget(&profile, today_start.date_naive(), meals.iter());
+
+    let candidates = match query.map(str::trim).filter(|q| !q.is_empty()) {
+        Some(query) =>
+            state.cache
+                .get_or_fetch(cache_service::MEALDB_SEARCH, query, false, || {
+                    state.mealdb_service.search_meals(query)
+                }).await
+                .map_err(AppError::InternalError)?,
+        None => sample_recipes(state, &profile, meal_type).await?,
+    };
+
+    let preferences = profile.dietary_preferences.clone().unwrap_or_default();
+    let carb_limit = preferences
+        .iter()
+        .filter_map(|p| {
+            match p {
+                DietaryPreference::Keto => Some(KETO_MAX_CARBS_G),
+                DietaryPreference::LowCarb => Some(LOW_CARB_MAX_CARBS_G),
+                _ => None,
+            }
+        })
+        .reduce(f64::min);
+
+    let total = candidates.len();
+    let mut allowed: Vec<Meal> = candidates
+        .into_iter()
+        .filter(|meal| dietary_filter::recipe_conflicts(&profile, meal).is_empty() && !is_off_menu(meal, meal_type))
+        .collect();
+    let mut hidden = total - allowed.len();
+    allowed.truncate(MAX_RECIPE_ESTIMATES);
+
+    let mut recipes = Vec::new();
+    for meal in allowed {
+        let nutrition = match recipe_nutrition_service::estimate(state, &meal).await {
+            Ok(nutrition) => nutrition,
+            Err(e) => {
+                tracing::debug!("Skipping recipe {} without a nutrition estimate: {}", meal.id_meal, e);
+                continue;
+            }
+        };
+
+        let carbs_g = round1(nutrition.carbs_g / RECIPE_SERVINGS);
+        if carb_limit.is_some_and(|limit| carbs_g > limit) {
+            hidden += 1;
+            continue;
+        }
+
+        let calories = (nutrition.calories / RECIPE_SERVINGS).round();
+        recipes.push(RecipeSuggestion {
+            url: format!("https://www.themealdb.com/meal/{}", meal.id_meal),
+            recipe_id: meal.id_meal,
+            name: meal.str_meal,
+            category: meal.str_category,
+            area: meal.str_area,
+            source_url: meal.str_source.filter(|s| !s.trim().is_empty()),
+            youtube_url: meal.str_youtube.filter(|s| !s.trim().is_empty()),
+            thumbnail: meal.str_meal_thumb,
+            servings: RECIPE_SERVINGS,
+            calories,
+            protein_g: round1(nutrition.protein_g / RECIPE_SERVINGS),
+            carbs_g,
+            fat_g: round1(nutrition.fat_g / RECIPE_SERVINGS),
+            fits_remaining: calories <= remaining.calories,
+        });
+    }
+
+    if only_fitting {
+        recipes.retain(|r| r.fits_remaining);
+    }
+
+    // Fitting recipes first; then, while protein is still needed, the most
+    // protein per calorie, otherwise the lightest.
+    let needs_protein = remaining.protein_g > 0.0;
+    recipes.sort_by(|a, b| {
+        b.fits_remaining.cmp(&a.fits_remaining).then_with(|| {
+            if needs_protein {
+                (b.protein_g / b.calories.max(1.0)).total_cmp(&(a.protein_g / a.calories.max(1.0)))
+            } else {
+                a.calories.total_cmp(&b.calories)
+            }
+        })
+    });
+    recipes.truncate(MAX_RECIPE_SUGGESTIONS);
+
+    Ok(RecipeSuggestions { remaining, recipes, hidden })
+}
+
+/// Recipes to recommend from when the user didn't ask for anything specific:
+/// a category matching a meat-free diet or breakfast, else random recipes.
+async fn sample_recipes(state: &AppState, profile: &HealthProfile, meal_type: Option<&MealType>) -> Result<Vec<Meal>> {
+    let preferences = profile.dietary_preferences.as_deref().unwrap_or_default();
+    let category = if preferences.iter().any(|p| matches!(p, DietaryPreference::Vegan)) {
+        Some("Vegan")
+    } else if preferences.iter().any(|p| matches!(p, DietaryPreference::Vegetarian)) {
+        Some("Vegetarian")
+    } else if preferences.iter().any(|p| matches!(p, DietaryPreference::Pescatarian)) {
+        Some("Seafood")
+    } else if matches!(meal_type, Some(MealType::Breakfast)) {
+        Some("Breakfast")
+    } else {
+        None
+    };
+
+    let Some(category) = category else {
+        return state.mealdb_service.get_random_meals(MAX_RECIPE_ESTIMATES).await.map_err(AppError::InternalError);
+    };
+
+    // Category listings only carry names and thumbnails.
+    let mut listed = state.cache
+        .get_or_fetch(cache_service::MEALDB_CATEGORY, category, false, || {
+            state.mealdb_service.filter_by_category(category)
+        }).await
+        .map_err(AppError::InternalError)?;
+    listed.shuffle(&mut rand::thread_rng());
+
+    let mut meals = Vec::new();
+    for partial in listed.iter().take(MAX_RECIPE_ESTIMATES) {
+        let meal: Option<Meal> = state.cache
+            .get_or_fetch(cache_service::MEALDB_MEAL, &partial.id_meal, false, || {
+                state.mealdb_service.get_meal_by_id(&partial.id_meal)
+            }).await
+            .map_err(AppError::InternalError)?;
+        meals.extend(meal);
+    }
+    Ok(meals)
+}
+
+/// Desserts and breakfasts aren't recommended for lunch or dinner.
+fn is_off_menu(meal: &Meal, meal_type: Option<&MealType>) -> bool {
+    matches!(meal_type, Some(MealType::Lunch | MealType::Dinner)) &&
+        matches!(meal.str_category.as_deref(), Some("Dessert" | "Breakfast"))
+}
+
+/// The targets for `today` minus what's been logged in `meals`, never below zero.
+fn remaining_budget<'a>(
+    profile: &HealthProfile,
+    today: NaiveDate,
+    meals: impl Iterator<Item = &'a MealLog>
+) -> RemainingBudget {
+    let (target_calories, target_protein, target_carbs, target_fat) = profile.targets_on(today);
+    let (mut calories, mut protein, mut carbs, mut fat) = (0.0, 0.0, 0.0, 0.0);
+    for meal in meals {
+        calories += meal.calories;
+        protein += meal.protein_g;
+        carbs += meal.carbs_g;
+        fat += meal.fat_g;
+    }
+
+    RemainingBudget {
+        calories: (target_calories - calories).max(0.0).round(),
+        protein_g: round1((target_protein - protein).max(0.0)),
+        carbs_g: round1((target_carbs - carbs).max(0.0)),
+        fat_g: round1((target_fat - fat).max(0.0)),
+    }
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
@@ -0,0 +1,92 @@
+use chrono::{ DateTime, FixedOffset, NaiveDate, TimeZone, Utc };
+
+use crate::{ error::{ AppError, Result }, models::User };
+
+/// Parses the `tz` query parameter: a fixed UTC offset such as `+07:00`,
+/// `-0530`, `UTC+7` or `UTC`. Missing means UTC. A `+` sent unencoded in a
+/// query string arrives as a space, which is read as `+`.
+pub fn parse(value: Option<&str>) -> Result<FixedOffset> {
+    let Some(value) = value else {
+        return Ok(utc());
+    };
+    let invalid = || AppError::BadRequest(format!("Invalid tz '{}'. Use a UTC offset such as +07:00", value));
+
+    let upper = value.to_uppercase();
+    let mut rest = upper.as_str();
+    for prefix in ["UTC", "GMT"] {
+        if let Some(stripped) = rest.strip_prefix(prefix) {
+            rest = stripped;
+        }
+    }
+    if rest.is_empty() || rest == "Z" {
+        return Ok(utc());
+    }
+
+    let (sign, digits) = match rest.chars().next() {
+        Some('+') | Some(' ') => (1, &rest[1..]),
+        Some('-') => (-1, &rest[1..]),
+        _ => (1, rest),
+    };
+    let digits = digits.trim();
+
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if digits.len() == 4 => digits.split_at(2),
+        None => (digits, "0"),
+    };
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if !(0..60).contains(&minutes) {
+        return Err(invalid());
+    }
+
+    let total = sign * (hours * 60 + minutes);
+    if !(-12 * 60..=14 * 60).contains(&total) {
+        return Err(invalid());
+    }
+    from_minutes(total).ok_or_else(invalid)
+}
+
+pub fn utc() -> FixedOffset {
+    FixedOffset::east_opt(0).unwrap()
+}
+
+pub fn from_minutes(minutes: i32) -> Option<FixedOffset> {
+    FixedOffset::east_opt(minutes * 60)
+}
+
+/// The offset from the user's notification preferences; UTC without them.
+pub fn of_user(user: &User) -> FixedOffset {
+    user.notification_preferences
+        .as_ref()
+        .and_then(|preferences| from_minutes(preferences.utc_offset_minutes))
+        .unwrap_or_else(utc)
+}
+
+pub fn minutes(offset: FixedOffset) -> i32 {
+    offset.local_minus_utc() / 60
+}
+
+pub fn is_utc(offset: FixedOffset) -> bool {
+    offset.local_minus_utc() == 0
+}
+
+/// Local midnight at the start of `day`, as a UTC instant.
+pub fn start_of_day(day: NaiveDate, offset: FixedOffset) -> DateTime<Utc> {
+    offset
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// Today's date at `offset`.
+pub fn today(offset: FixedOffset) -> NaiveDate {
+    Utc::now().with_timezone(&offset).date_naive()
+}
+
+/// The offset as MongoDB's date operators expect it, e.g. `+07:00`.
+pub fn mongo_timezone(offset: FixedOffset) -> String {
+    let minutes = minutes(offset);
+    let sign = if minutes < 0 { '-' } else { '+' };
+    format!("{}{:02}:{:02}", sign, minutes.abs() / 60, minutes.abs() % 60)
+}
@@ -0,0 +1,47 @@
+use axum::http::{ header, HeaderMap, HeaderValue };
+use mongodb::bson::{ doc, Bson };
+
+use crate::error::{ AppError, Result };
+
+/// The version the client last read, from `If-Match: "<version>"`. `None`
+/// when the header is absent or `*`, in which case the write is unconditional.
+pub fn expected_version(headers: &HeaderMap) -> Result<Option<i64>> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+
+    let value = value
+        .to_str()
+        .map_err(|_| AppError::BadRequest("Invalid If-Match header".to_string()))?
+        .trim();
+    if value == "*" {
+        return Ok(None);
+    }
+
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse::<i64>()
+        .map(Some)
+        .map_err(|_| AppError::BadRequest("If-Match must be an ETag returned by this API".to_string()))
+}
+
+/// Filter value matching `version`. Entries written before versioning have no
+/// field, which reads as version 0.
+pub fn version_filter(version: i64) -> Bson {
+    if version == 0 {
+        Bson::Document(doc! { "$in": [0_i64, Bson::Null] })
+    } else {
+        Bson::Int64(version)
+    }
+}
+
+pub fn etag(version: i64) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", version)).expect("a quoted integer is a valid header value")
+}
+
+pub fn conflict(entry: &str) -> AppError {
+    AppError::Conflict(
+        format!("This {} was changed on another device. Reload it and try again.", entry)
+    )
+}
@@ -0,0 +1,81 @@
+use chrono::{ NaiveDate, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::bson::{ doc, oid::ObjectId };
+use std::collections::BTreeMap;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ DailyProgress, User },
+    services::progress_service,
+};
+
+/// Used when the user has no health profile to derive a target from.
+pub const DEFAULT_WATER_TARGET_ML: f64 = 2000.0;
+const WATER_ML_PER_KG: f64 = 35.0;
+pub const MAX_WATER_LOG_ML: f64 = 5000.0;
+
+/// Daily hydration target: 35 ml per kg of body weight.
+pub fn water_target_ml(user: &User) -> f64 {
+    user.health_profile
+        .as_ref()
+        .map_or(DEFAULT_WATER_TARGET_ML, |p| (p.weight_kg * WATER_ML_PER_KG).round())
+}
+
+/// Adds `amount_ml` to the day's total and returns the new total.
+pub async fn log_water(state: &AppState, user_id: ObjectId, day: NaiveDate, amount_ml: f64) -> Result<f64> {
+    let now = Utc::now().to_rfc3339();
+
+    let progress = progress_service::upsert(
+        state,
+        doc! { "user_id": user_id, "date": progress_service::day_key(day) },
+        doc! {
+            "$inc": { "water_ml": amount_ml },
+            "$set": { "updated_at": &now },
+            "$setOnInsert": {
+                "total_calories": 0.0,
+                "total_protein_g": 0.0,
+                "total_carbs_g": 0.0,
+                "total_fat_g": 0.0,
+                "meal_count": 0_i64,
+                "created_at": &now,
+            },
+        }
+    ).await?;
+
+    Ok(progress.water_ml.unwrap_or(0.0))
+}
+
+pub async fn water_for_day(state: &AppState, user_id: ObjectId, day: NaiveDate) -> Result<f64> {
+    let progress = progress_service::progress_for_day(state, user_id, day).await?;
+    Ok(progress.and_then(|p| p.water_ml).unwrap_or(0.0))
+}
+
+/// Water logged on each day of `start..=end` that has any.
+pub async fn water_between(
+    state: &AppState,
+    user_id: ObjectId,
+    start: NaiveDate,
+    end: NaiveDate
+) -> Result<BTreeMap<NaiveDate, f64>> {
+    let days: Vec<DailyProgress> = state.db
+        .collection::<DailyProgress>("daily_progress")
+        .find(
+            doc! {
+                "user_id": user_id,
+                "date": { "$gte": progress_service::day_key(start), "$lte": progress_service::day_key(end) },
+                "water_ml": { "$gt": 0.0 },
+            },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(
+        days
+            .into_iter()
+            .map(|day| (day.date.date_naive(), day.water_ml.unwrap_or(0.0)))
+            .collect()
+    )
+}
@@ -0,0 +1,149 @@
+use chrono::{ DateTime, Duration, Utc };
+use futures::stream::TryStreamExt;
+use mongodb::{ bson::{ doc, oid::ObjectId }, options::{ FindOneOptions, FindOptions } };
+use serde::Serialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ HealthGoal, HealthProfile, WeightLog },
+};
+
+const MOVING_AVERAGE_DAYS: i64 = 7;
+/// Maintainers count as on target within this many kg of their target.
+const MAINTAIN_TOLERANCE_KG: f64 = 1.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WeightTrend {
+    pub starting_weight: f64,
+    pub ending_weight: f64,
+    pub weight_change: f64,
+    /// Least-squares slope over the weigh-ins, in kg per week. `None` until the
+    /// weigh-ins span at least a day.
+    pub weekly_rate_kg: Option<f64>,
+    /// Mean of the weigh-ins in the 7 days up to the latest one.
+    pub moving_average_kg: f64,
+    pub entries: usize,
+}
+
+/// Weigh-ins for the user in `[start, end]`, oldest first.
+pub async fn fetch_weight_logs_between(
+    state: &AppState,
+    user_id: ObjectId,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>
+) -> Result<Vec<WeightLog>> {
+    state.db
+        .collection::<WeightLog>("weight_logs")
+        .find(
+            doc! {
+                "user_id": user_id,
+                "date": {
+                    "$gte": mongodb::bson::DateTime::from_chrono(start),
+                    "$lte": mongodb::bson::DateTime::from_chrono(end)
+                }
+            },
+            FindOptions::builder().sort(doc! { "date": 1 }).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .try_collect().await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+/// Most recent weigh-in at or before `at`.
+pub async fn latest_weight_before(
+    state: &AppState,
+    user_id: ObjectId,
+    at: DateTime<Utc>
+) -> Result<Option<WeightLog>> {
+    state.db
+        .collection::<WeightLog>("weight_logs")
+        .find_one(
+            doc! { "user_id": user_id, "date": { "$lte": mongodb::bson::DateTime::from_chrono(at) } },
+            FindOneOptions::builder().sort(doc! { "date": -1 }).build()
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))
+}
+
+/// Expects `logs` oldest first, as returned by [`fetch_weight_logs_between`].
+pub fn trend(logs: &[WeightLog]) -> Option<WeightTrend> {
+    let first = logs.first()?;
+    let last = logs.last()?;
+
+    Some(WeightTrend {
+        starting_weight: first.weight_kg,
+        ending_weight: last.weight_kg,
+        weight_change: round2(last.weight_kg - first.weight_kg),
+        weekly_rate_kg: weekly_rate(logs).map(round2),
+        moving_average_kg: moving_average(logs, last.date),
+        entries: logs.len(),
+    })
+}
+
+/// Trailing 7-day mean of the weigh-ins at or before `at`.
+pub fn moving_average(logs: &[WeightLog], at: DateTime<Utc>) -> f64 {
+    let window_start = at - Duration::days(MOVING_AVERAGE_DAYS);
+    let window: Vec<f64> = logs
+        .iter()
+        .filter(|log| log.date > window_start && log.date <= at)
+        .map(|log| log.weight_kg)
+        .collect();
+
+    if window.is_empty() {
+        return 0.0;
+    }
+    round2(window.iter().sum::<f64>() / (window.len() as f64))
+}
+
+fn weekly_rate(logs: &[WeightLog]) -> Option<f64> {
+    let origin = logs.first()?.date;
+    let points: Vec<(f64, f64)> = logs
+        .iter()
+        .map(|log| ((log.date - origin).num_seconds() as f64 / 86_400.0, log.weight_kg))
+        .collect();
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let variance: f64 = points
+        .iter()
+        .map(|(x, _)| (x - mean_x).powi(2))
+        .sum();
+
+    if points.last()?.0 < 1.0 || variance == 0.0 {
+        return None;
+    }
+
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+
+    Some((covariance / variance) * 7.0)
+}
+
+pub fn target_weight(profile: &HealthProfile) -> f64 {
+    target_weight_from(&profile.goal, profile.weight_kg)
+}
+
+/// The target for `goal` starting from `weight_kg`.
+pub fn target_weight_from(goal: &HealthGoal, weight_kg: f64) -> f64 {
+    match goal {
+        HealthGoal::LoseWeight => weight_kg * 0.9,
+        HealthGoal::GainWeight => weight_kg * 1.1,
+        HealthGoal::BuildMuscle => weight_kg * 1.05,
+        HealthGoal::MaintainWeight => weight_kg,
+    }
+}
+
+pub fn goal_achieved(goal: &HealthGoal, current: f64, target: f64) -> bool {
+    match goal {
+        HealthGoal::LoseWeight => current <= target,
+        HealthGoal::GainWeight | HealthGoal::BuildMuscle => current >= target,
+        HealthGoal::MaintainWeight => (current - target).abs() <= MAINTAIN_TOLERANCE_KG,
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,160 @@
+use chrono::{ DateTime, Duration, NaiveDate, Utc };
+use jsonwebtoken::{ decode, encode, DecodingKey, EncodingKey, Header, Validation };
+use mongodb::bson::{ doc, oid::ObjectId };
+use serde::Serialize;
+
+use crate::{
+    db::AppState,
+    error::{ AppError, Result },
+    models::{ HealthGoal, User, WidgetClaims },
+    services::{ achievement_service, report_service, timezone },
+};
+
+/// `aud` of widget tokens. Access tokens carry none, so neither kind is
+/// accepted where the other is expected.
+const WIDGET_AUDIENCE: &str = "widget";
+pub const DEFAULT_WIDGET_TOKEN_DAYS: i64 = 365;
+pub const MAX_WIDGET_TOKEN_DAYS: i64 = 365;
+/// How far back the current streak is counted.
+const STREAK_LOOKBACK_DAYS: i64 = 366;
+
+/// The only data a widget token can read: enough for a streak and goal badge.
+#[derive(Debug, Serialize)]
+pub struct WidgetSummary {
+    /// Consecutive days with a logged meal, ending today or, before the
+    /// first meal of the day, yesterday.
+    pub current_streak_days: usize,
+    pub goal: Option<HealthGoal>,
+    pub today: WidgetDayProgress,
+    /// Rolling 7-day compliance score, 0-100.
+    pub compliance_score: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WidgetDayProgress {
+    pub date: String,
+    pub calories: f64,
+    pub calorie_target: Option<f64>,
+    /// Share of the calorie target eaten so far, rounded to a whole percent.
+    pub calorie_progress_percent: Option<f64>,
+    pub meals_logged: usize,
+}
+
+/// Issues a widget token for the user, replacing (and so revoking) any
+/// previous one.
+pub async fn issue_token(state: &AppState, user_id: ObjectId, days: i64) -> Result<(String, DateTime<Utc>)> {
+    let now = Utc::now();
+    let expires_at = now + Duration::days(days);
+    let jti = uuid::Uuid::new_v4().to_string();
+
+    let result = state.db
+        .collection::<User>("users")
+        .update_one(doc! { "_id": user_id }, doc! { "$set": { "widget_token_id": &jti } }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    let claims = WidgetClaims {
+        sub: user_id.to_hex(),
+        aud: WIDGET_AUDIENCE.to_string(),
+        jti,
+        exp: expires_at.timestamp(),
+        iat: now.timestamp(),
+        tenant: state.config.tenant_database().map(str::to_string),
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(state.config.jwt.secret.as_bytes())
+    ).map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok((token, expires_at))
+}
+
+/// Revokes the user's widget token. `false` if they had none.
+pub async fn revoke_token(state: &AppState, user_id: ObjectId) -> Result<bool> {
+    let result = state.db
+        .collection::<User>("users")
+        .update_one(
+            doc! { "_id": user_id, "widget_token_id": { "$exists": true } },
+            doc! { "$unset": { "widget_token_id": "" } },
+            None
+        ).await
+        .map_err(|e| AppError::InternalError(e.into()))?;
+
+    Ok(result.modified_count > 0)
+}
+
+/// The user a widget token belongs to, if it is signed, unexpired, for this
+/// tenant and still the user's live widget token.
+pub async fn verify_token(state: &AppState, token: &str) -> Result<User> {
+    let invalid = || AppError::BadRequest("Invalid or expired widget token".to_string());
+
+    let mut validation = Validation::default();
+    validation.set_audience(&[WIDGET_AUDIENCE]);
+
+    let claims = decode::<WidgetClaims>(
+        token,
+        &DecodingKey::from_secret(state.config.jwt.secret.as_bytes()),
+        &validation
+    )
+        .ok()
+        .map(|data| data.claims)
+        .filter(|claims| claims.tenant.as_deref() == state.config.tenant_database())
+        .ok_or_else(invalid)?;
+
+    let user_id = ObjectId::parse_str(&claims.sub).map_err(|_| invalid())?;
+
+    state.db
+        .collection::<User>("users")
+        .find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| AppError::InternalError(e.into()))?
+        .filter(|user| user.widget_token_id.as_deref() == Some(claims.jti.as_str()))
+        .ok_or_else(invalid)
+}
+
+pub async fn widget_summary(state: &AppState, user: &User) -> Result<WidgetSummary> {
+    let user_id = user.id.ok_or_else(|| AppError::InternalError(anyhow::anyhow!("User has no ID")))?;
+    let offset = user.notification_preferences
+        .as_ref()
+        .and_then(|preferences| timezone::from_minutes(preferences.utc_offset_minutes))
+        .unwrap_or_else(timezone::utc);
+    let today = timezone::today(offset);
+
+    let totals = report_service::daily_meal_totals_in(
+        state,
+        user_id,
+        timezone::start_of_day(today - Duration::days(STREAK_LOOKBACK_DAYS), offset),
+        timezone::start_of_day(today + Duration::days(1), offset),
+        offset
+    ).await?;
+
+    let logged_days: Vec<NaiveDate> = totals.iter().map(|day| day.date).collect();
+    let today_totals = totals
+        .iter()
+        .find(|day| day.date == today)
+        .map(|day| day.totals.clone())
+        .unwrap_or_default();
+
+    let calorie_target = user.health_profile.as_ref().map(|profile| profile.targets_on(today).0);
+    let calorie_progress_percent = calorie_target
+        .filter(|target| *target > 0.0)
+        .map(|target| ((today_totals.calories / target) * 100.0).round());
+
+    let score = report_service::get_compliance_score(state, user_id).await?;
+
+    Ok(WidgetSummary {
+        current_streak_days: achievement_service::current_streak(&logged_days, today),
+        goal: user.health_profile.as_ref().map(|profile| profile.goal.clone()),
+        today: WidgetDayProgress {
+            date: today.format("%Y-%m-%d").to_string(),
+            calories: today_totals.calories.round(),
+            calorie_target,
+            calorie_progress_percent,
+            meals_logged: today_totals.meal_count,
+        },
+        compliance_score: score.score,
+    })
+}
@@ -0,0 +1,94 @@
+use std::{ collections::BTreeMap, fmt::Display };
+
+use crate::error::{ AppError, Result };
+
+/// Range checks for a request body. Handlers call `payload.validate()?`
+/// before using it; every invalid field is reported in one 422 response.
+pub trait Validate {
+    fn validate(&self) -> Result<()>;
+}
+
+/// Collects per-field problems; [`Checks::finish`] turns them into
+/// `AppError::InvalidFields`. Field names are the JSON ones, with indexes for
+/// list entries, e.g. `items[1].calories`.
+#[derive(Debug, Default)]
+pub struct Checks {
+    errors: BTreeMap<String, String>,
+}
+
+impl Checks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a problem with `field`. Only the first one per field is kept.
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) -> &mut Self {
+        self.errors.entry(field.into()).or_insert_with(|| message.into());
+        self
+    }
+
+    /// `value` within `[min, max]`. NaN is out of every range.
+    pub fn range<T: PartialOrd + Display + Copy>(&mut self, field: &str, value: T, min: T, max: T) -> &mut Self {
+        if !(min..=max).contains(&value) {
+            self.add(field, format!("{} must be between {} and {}", field, min, max));
+        }
+        self
+    }
+
+    pub fn optional_range<T: PartialOrd + Display + Copy>(
+        &mut self,
+        field: &str,
+        value: Option<T>,
+        min: T,
+        max: T
+    ) -> &mut Self {
+        if let Some(value) = value {
+            self.range(field, value, min, max);
+        }
+        self
+    }
+
+    /// Not blank and at most `max` characters.
+    pub fn required_text(&mut self, field: &str, value: &str, max: usize) -> &mut Self {
+        if value.trim().is_empty() {
+            self.add(field, format!("{} must not be empty", field));
+        }
+        self.max_length(field, value, max)
+    }
+
+    pub fn max_length(&mut self, field: &str, value: &str, max: usize) -> &mut Self {
+        if value.chars().count() > max {
+            self.add(field, format!("{} must be at most {} characters", field, max));
+        }
+        self
+    }
+
+    pub fn optional_max_length(&mut self, field: &str, value: Option<&str>, max: usize) -> &mut Self {
+        if let Some(value) = value {
+            self.max_length(field, value, max);
+        }
+        self
+    }
+
+    /// At most `max_entries` entries, each not blank and at most `max_length`
+    /// characters.
+    pub fn text_list(&mut self, field: &str, values: Option<&[String]>, max_entries: usize, max_length: usize) -> &mut Self {
+        let Some(values) = values else {
+            return self;
+        };
+        if values.len() > max_entries {
+            self.add(field, format!("{} must have at most {} entries", field, max_entries));
+        }
+        for (i, value) in values.iter().enumerate() {
+            self.required_text(&format!("{}[{}]", field, i), value, max_length);
+        }
+        self
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::InvalidFields(std::mem::take(&mut self.errors)))
+    }
+}